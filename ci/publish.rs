@@ -32,6 +32,7 @@ const CRATES_TO_PUBLISH: &[&str] = &[
     "wasm-compose",
     "wit-smith",
     "wasm-wave",
+    "wasm-resource-table",
     "wasm-tools",
 ];
 
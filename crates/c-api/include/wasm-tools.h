@@ -7,6 +7,8 @@
 #ifndef WASM_TOOLS_H
 #define WASM_TOOLS_H
 
+#include <stdbool.h>
+#include <stddef.h>
 #include <stdint.h>
 
 #ifdef __cplusplus
@@ -71,6 +73,79 @@ void wasm_tools_byte_vec_delete(struct wasm_tools_byte_vec_t* bytes);
  */
 enum wasm_tools_error wasm_smith_create(const char* seed, size_t seed_len, struct wasm_tools_byte_vec_t* bytes);
 
+/**
+ * \struct wasm_tools_features_t
+ * \brief A set of WebAssembly features to validate with
+ *
+ * This is an opaque type created with #wasm_tools_features_new and deleted
+ * with #wasm_tools_features_delete.
+ */
+struct wasm_tools_features_t;
+
+/**
+ * \brief Creates a new set of features with the features that are enabled by
+ *   default in `wasm-tools validate`.
+ *
+ * The returned value must be deleted with #wasm_tools_features_delete.
+ */
+struct wasm_tools_features_t* wasm_tools_features_new(void);
+
+/**
+ * \brief Deletes a wasm_tools_features_t instance.
+ */
+void wasm_tools_features_delete(struct wasm_tools_features_t* features);
+
+/**
+ * \brief Enables or disables a WebAssembly feature.
+ *
+ * \param features the set of features to modify
+ * \param name the name of the feature, such as `simd` or `multi-memory`, in
+ *   the same format as the `--features` flag of `wasm-tools validate`, or
+ *   `all` to change all features at once
+ * \param name_len the length of `name`, in bytes
+ * \param enable whether the feature is enabled or disabled
+ *
+ * \return WASM_TOOLS_SUCCESS if the feature was changed, or WASM_TOOLS_ERROR
+ *   if `name` isn't a known feature
+ *
+ * This function does not take ownership of `name`
+ */
+enum wasm_tools_error wasm_tools_features_set(struct wasm_tools_features_t* features, const char* name, size_t name_len, bool enable);
+
+/**
+ * \brief Validates a WebAssembly module or component.
+ *
+ * \param wasm the input pointer to the binary module or component
+ * \param wasm_len the length of `wasm`, in bytes
+ * \param features the features to validate with, or NULL to use the default
+ *   features
+ * \param error wasm_tools_byte_vec_t instance where the UTF-8 error message is
+ *   written if validation fails, which is not NUL-terminated. It's left empty
+ *   on success.
+ *
+ * \return WASM_TOOLS_SUCCESS if `wasm` is valid, or WASM_TOOLS_ERROR otherwise
+ *
+ * This function does not take ownership of `wasm` or `features`
+ */
+enum wasm_tools_error wasm_validate(const uint8_t* wasm, size_t wasm_len, const struct wasm_tools_features_t* features, struct wasm_tools_byte_vec_t* error);
+
+/**
+ * \brief Prints a WebAssembly module or component in the text format.
+ *
+ * \param wasm the input pointer to the binary module or component
+ * \param wasm_len the length of `wasm`, in bytes
+ * \param text wasm_tools_byte_vec_t instance where the UTF-8 text format is
+ *   written on success, which is not NUL-terminated
+ * \param error wasm_tools_byte_vec_t instance where the UTF-8 error message is
+ *   written on failure, which is not NUL-terminated
+ *
+ * \return WASM_TOOLS_SUCCESS if printing is successful, or WASM_TOOLS_ERROR if
+ *   `wasm` couldn't be parsed
+ *
+ * This function does not take ownership of `wasm`
+ */
+enum wasm_tools_error wasm_print(const uint8_t* wasm, size_t wasm_len, struct wasm_tools_byte_vec_t* text, struct wasm_tools_byte_vec_t* error);
+
 #ifdef __cplusplus
 } // extern "C"
 #endif
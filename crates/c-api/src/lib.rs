@@ -10,7 +10,9 @@
 #![allow(unsafe_code)]
 
 use arbitrary::{Error, Unstructured};
+use bitflags::Flags;
 use wasm_smith::{Config, Module};
+use wasmparser::{Validator, WasmFeatures};
 
 #[repr(C)]
 pub struct wasm_tools_byte_vec_t {
@@ -33,6 +35,30 @@ pub extern "C" fn wasm_tools_byte_vec_delete(bytes: &mut wasm_tools_byte_vec_t)
     }
 }
 
+impl wasm_tools_byte_vec_t {
+    fn set(&mut self, bytes: Vec<u8>) {
+        let mut buffer = bytes.into_boxed_slice();
+        self.data = buffer.as_mut_ptr();
+        self.size = buffer.len();
+        std::mem::forget(buffer);
+    }
+
+    fn clear(&mut self) {
+        self.data = std::ptr::null_mut();
+        self.size = 0;
+    }
+}
+
+/// Returns the slice for the `len` bytes at `data`, which may be NULL if `len`
+/// is zero.
+unsafe fn slice<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len) }
+    }
+}
+
 /// Create a new WebAssembly module with the given seed.
 ///
 /// # Safety
@@ -45,23 +71,115 @@ pub unsafe extern "C" fn wasm_smith_create(
     bytes: &mut wasm_tools_byte_vec_t,
 ) -> wasm_tools_error {
     // seed == NULL is acceptable as long as seed_len is zero
-    let seed_bytes = if seed_len == 0 {
-        &[]
-    } else {
-        unsafe { std::slice::from_raw_parts(seed, seed_len) }
-    };
+    let seed_bytes = unsafe { slice(seed, seed_len) };
 
     bytes.data = std::ptr::null_mut();
     let mut u = Unstructured::new(seed_bytes);
     match Module::new(Config::default(), &mut u) {
         Ok(module) => {
-            let mut wasm_buffer = module.to_bytes().into_boxed_slice();
-            bytes.data = wasm_buffer.as_mut_ptr();
-            bytes.size = wasm_buffer.len();
-            std::mem::forget(wasm_buffer);
+            bytes.set(module.to_bytes());
             WASM_TOOLS_SUCCESS
         }
         Err(Error::NotEnoughData) => WASM_TOOLS_INSUFFICIENT_ENTROPY,
         Err(_e) => WASM_TOOLS_ERROR,
     }
 }
+
+/// A set of WebAssembly features to validate with.
+pub struct wasm_tools_features_t {
+    features: WasmFeatures,
+}
+
+/// Creates a new set of features with the features enabled by default.
+#[no_mangle]
+pub extern "C" fn wasm_tools_features_new() -> Box<wasm_tools_features_t> {
+    Box::new(wasm_tools_features_t {
+        features: WasmFeatures::default(),
+    })
+}
+
+/// Deletes a set of features.
+#[no_mangle]
+pub extern "C" fn wasm_tools_features_delete(_features: Box<wasm_tools_features_t>) {}
+
+/// Enables or disables the feature `name`, such as `simd` or
+/// `multi-memory`, or all features if `name` is `all`.
+///
+/// # Safety
+///
+/// `name` must be a valid pointer to `name_len` bytes of memory.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_tools_features_set(
+    features: &mut wasm_tools_features_t,
+    name: *const u8,
+    name_len: usize,
+    enable: bool,
+) -> wasm_tools_error {
+    let name = unsafe { slice(name, name_len) };
+    let flag = if name == b"all" {
+        WasmFeatures::all()
+    } else {
+        match WasmFeatures::FLAGS
+            .iter()
+            .find(|f| f.name().to_lowercase().replace('_', "-").as_bytes() == name)
+        {
+            Some(flag) => *flag.value(),
+            None => return WASM_TOOLS_ERROR,
+        }
+    };
+    features.features.set(flag, enable);
+    WASM_TOOLS_SUCCESS
+}
+
+/// Validates a WebAssembly module or component.
+///
+/// # Safety
+///
+/// `wasm` must be a valid pointer to `wasm_len` bytes of memory.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_validate(
+    wasm: *const u8,
+    wasm_len: usize,
+    features: Option<&wasm_tools_features_t>,
+    error: &mut wasm_tools_byte_vec_t,
+) -> wasm_tools_error {
+    let wasm = unsafe { slice(wasm, wasm_len) };
+    let features = features.map(|f| f.features).unwrap_or_default();
+
+    error.clear();
+    match Validator::new_with_features(features).validate_all(wasm) {
+        Ok(_) => WASM_TOOLS_SUCCESS,
+        Err(e) => {
+            error.set(e.to_string().into_bytes());
+            WASM_TOOLS_ERROR
+        }
+    }
+}
+
+/// Prints a WebAssembly module or component in the text format.
+///
+/// # Safety
+///
+/// `wasm` must be a valid pointer to `wasm_len` bytes of memory.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_print(
+    wasm: *const u8,
+    wasm_len: usize,
+    text: &mut wasm_tools_byte_vec_t,
+    error: &mut wasm_tools_byte_vec_t,
+) -> wasm_tools_error {
+    let wasm = unsafe { slice(wasm, wasm_len) };
+
+    text.clear();
+    error.clear();
+    match wasmprinter::print_bytes(wasm) {
+        Ok(s) => {
+            text.set(s.into_bytes());
+            WASM_TOOLS_SUCCESS
+        }
+        Err(e) => {
+            error.set(format!("{e:#}").into_bytes());
+            WASM_TOOLS_ERROR
+        }
+    }
+}
@@ -7,10 +7,16 @@ use crate::{
         Component, ComponentId, CompositionGraph, EncodeOptions, ExportIndex, ImportIndex,
         InstanceId,
     },
+    intercept, stub,
 };
 use anyhow::{anyhow, bail, Context, Result};
-use indexmap::IndexMap;
-use std::{collections::VecDeque, ffi::OsStr, path::Path};
+use indexmap::{IndexMap, IndexSet};
+use serde_derive::Serialize;
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
 use wasmparser::{
     types::{ComponentEntityType, ComponentInstanceTypeId, TypesRef},
     ComponentExternalKind, ComponentTypeRef,
@@ -45,6 +51,16 @@ enum DependencyKind {
         /// The export on the definition component to use as the instantiation argument.
         export: ExportIndex,
     },
+
+    /// The dependency is intercepted by a configured instance.
+    Intercept {
+        /// The name of the intercepting instance.
+        instance: String,
+        /// The name of the instance export of the intercepting instance.
+        export: Option<String>,
+        /// The kind of dependency the import would otherwise have.
+        original: Box<DependencyKind>,
+    },
 }
 
 /// An instance dependency to process in the composer.
@@ -57,6 +73,21 @@ struct Dependency {
     kind: DependencyKind,
 }
 
+/// An intercepted import whose connections are made once all dependencies
+/// have been processed.
+struct Interception {
+    /// The index into `instances` for the dependent instance.
+    dependent: usize,
+    /// The intercepted import of the dependent instance.
+    import: ImportIndex,
+    /// The index into `instances` for the instance of the glue component.
+    glue: usize,
+    /// The index into `instances` for the intercepting instance.
+    instance: usize,
+    /// The instance export of the intercepting instance.
+    export: ExportIndex,
+}
+
 /// A composition graph builder that wires up instances from components
 /// resolved from the file system.
 struct CompositionGraphBuilder<'a> {
@@ -68,6 +99,8 @@ struct CompositionGraphBuilder<'a> {
     instances: IndexMap<String, InstanceId>,
     /// The definition components in the graph.
     definitions: Vec<(ComponentId, Option<InstanceId>)>,
+    /// The intercepted imports in the graph.
+    interceptions: Vec<Interception>,
 }
 
 impl<'a> CompositionGraphBuilder<'a> {
@@ -97,6 +130,7 @@ impl<'a> CompositionGraphBuilder<'a> {
             graph,
             instances: Default::default(),
             definitions,
+            interceptions: Vec::new(),
         })
     }
 
@@ -299,7 +333,11 @@ impl<'a> CompositionGraphBuilder<'a> {
     /// Processes a dependency in the graph.
     ///
     /// Returns `Ok(Some(index))` if the dependency resulted in a new dependency instance being created.
-    fn process_dependency(&mut self, dependency: Dependency) -> Result<Option<usize>> {
+    fn process_dependency(
+        &mut self,
+        dependency: Dependency,
+        queue: &mut VecDeque<Dependency>,
+    ) -> Result<Option<usize>> {
         match dependency.kind {
             DependencyKind::Instance { instance, export } => self.process_instance_dependency(
                 dependency.dependent,
@@ -337,7 +375,60 @@ impl<'a> CompositionGraphBuilder<'a> {
                 // No new dependency instance was created
                 Ok(None)
             }
+            DependencyKind::Intercept {
+                instance,
+                export,
+                original,
+            } => self.process_intercept_dependency(
+                dependency.dependent,
+                dependency.import,
+                &instance,
+                export.as_deref(),
+                *original,
+                queue,
+            ),
+        }
+    }
+
+    /// Adds a stub component named `name` to the graph if the instance
+    /// `instance` is configured to be stubbed and no component with that
+    /// name can otherwise be found.
+    ///
+    /// The stub component is generated from the type of `import`.
+    fn add_stub(
+        &mut self,
+        instance: &str,
+        name: &str,
+        import: InstanceImportRef,
+        export: Option<&str>,
+    ) -> Result<()> {
+        let kind = match self.config.stubs.get(instance) {
+            Some(kind) => *kind,
+            None => return Ok(()),
+        };
+
+        if self.instances.contains_key(instance)
+            || self.graph.get_component_by_name(name).is_some()
+            || self.find_component(name)?.is_some()
+        {
+            return Ok(());
+        }
+
+        if let Some(export) = export {
+            bail!("an explicit export `{export}` cannot be specified for stubbed instance `{instance}`");
         }
+
+        let (dependent, import_name, ty) = self.resolve_import_ref(import);
+        log::warn!("instance `{instance}` will be stubbed because a dependency named `{name}` could not be found");
+        let bytes = stub::encode(dependent, ty, kind).with_context(|| {
+            format!(
+                "failed to create a stub for import `{import_name}` of component `{path}`",
+                path = dependent.path().unwrap().display(),
+            )
+        })?;
+        self.graph
+            .add_component(Component::from_bytes(name, bytes)?)?;
+        Ok(())
     }
 
     fn process_instance_dependency(
@@ -354,6 +445,8 @@ impl<'a> CompositionGraphBuilder<'a> {
             dependent_name = self.instances.get_index(dependent_index).unwrap().0,
         );
 
+        self.add_stub(instance, name, import, export)?;
+
         match self.instantiate(instance, name)? {
             Some((instance, existing)) => {
                 let (dependent, import_name, import_type) = self.resolve_import_ref(import);
@@ -399,6 +492,157 @@ impl<'a> CompositionGraphBuilder<'a> {
         }
     }
 
+    /// Processes a dependency which is intercepted by the instance `instance`.
+    ///
+    /// A glue component is generated to satisfy the import, which takes the
+    /// intercepted functions from the export of the intercepting instance and
+    /// everything else from an instance import resolved as the `original`
+    /// dependency.
+    fn process_intercept_dependency(
+        &mut self,
+        dependent_index: usize,
+        import: InstanceImportRef,
+        instance: &str,
+        export: Option<&str>,
+        original: DependencyKind,
+        queue: &mut VecDeque<Dependency>,
+    ) -> Result<Option<usize>> {
+        let name = self.config.dependency_name(instance);
+        let dependent_name = self.instances.get_index(dependent_index).unwrap().0.clone();
+
+        log::info!(
+            "processing interception of dependency from instance `{dependent_name}` by instance `{instance}`",
+        );
+
+        if self.add_component(name)?.is_none() {
+            bail!("a dependency named `{name}` could not be found to intercept an import of instance `{dependent_name}`");
+        }
+        let (instance, existing) = self.instantiate(instance, name)?.unwrap();
+
+        let (dependent, import_name, import_type) = self.resolve_import_ref(import);
+        let export = export.unwrap_or(import_name);
+        let (_, component) = self
+            .graph
+            .get_component_of_instance(self.instances[instance])
+            .unwrap();
+        let (export_index, intercepted) = match component.export_by_name(export) {
+            Some((export_index, ComponentExternalKind::Instance, index)) => {
+                let ty = component.types.component_instance_at(index);
+                let names = component.types[ty]
+                    .exports
+                    .keys()
+                    .map(|name| name.as_str())
+                    .collect::<IndexSet<_>>();
+                (export_index, names)
+            }
+            _ => bail!(
+                "component `{path}` does not export an instance named `{export}`",
+                path = component.path().unwrap().display(),
+            ),
+        };
+
+        if !dependent.types[import_type]
+            .exports
+            .iter()
+            .any(|(name, ty)| {
+                matches!(ty, ComponentEntityType::Func(_)) && intercepted.contains(name.as_str())
+            })
+        {
+            bail!(
+                "instance `{export}` exported by component `{path}` does not export any function \
+                 of import `{import_name}` of component `{dependent_path}`",
+                path = component.path().unwrap().display(),
+                dependent_path = dependent.path().unwrap().display(),
+            );
+        }
+
+        let bytes = intercept::encode(dependent, import_name, import_type, &intercepted)
+            .with_context(|| {
+                format!(
+                    "failed to create a glue component for import `{import_name}` of component `{path}`",
+                    path = dependent.path().unwrap().display(),
+                )
+            })?;
+
+        // Component names must be valid component names, so glue components
+        // are simply numbered
+        let glue_name = format!("glue{}", self.interceptions.len());
+        // The glue component takes the path of the dependent for errors about
+        // the import it satisfies
+        let mut glue = Component::from_bytes(glue_name.clone(), bytes)?;
+        glue.path = dependent.path.clone();
+        self.graph.add_component(glue)?;
+        let (glue, _) = self.instantiate(&glue_name, &glue_name)?.unwrap();
+
+        // The glue component's first import takes the place of the intercepted import
+        let (glue_component, _) = self
+            .graph
+            .get_component_of_instance(self.instances[glue])
+            .unwrap();
+        queue.push_back(Dependency {
+            dependent: glue,
+            import: InstanceImportRef {
+                component: glue_component,
+                import: ImportIndex(0),
+            },
+            kind: original,
+        });
+
+        self.interceptions.push(Interception {
+            dependent: dependent_index,
+            import: import.import,
+            glue,
+            instance,
+            export: export_index,
+        });
+
+        if existing {
+            return Ok(None);
+        }
+
+        Ok(Some(instance))
+    }
+
+    /// Connects an intercepted import to its glue component and the glue
+    /// component to the intercepting instance.
+    fn connect_interception(&mut self, interception: Interception) -> Result<()> {
+        let (dependent_name, dependent) = self.instances.get_index(interception.dependent).unwrap();
+        let (instance_name, instance) = self.instances.get_index(interception.instance).unwrap();
+        let glue = self.instances[interception.glue];
+
+        self.graph
+            .connect(*instance, Some(interception.export), glue, ImportIndex(1))
+            .with_context(|| {
+                format!("failed to connect instance `{instance_name}` to intercept an import of instance `{dependent_name}`")
+            })?;
+
+        self.graph
+            .connect(glue, Some(ExportIndex(0)), *dependent, interception.import)
+            .with_context(|| {
+                format!("failed to connect an intercepted import of instance `{dependent_name}`")
+            })
+    }
+
+    /// Finds a definition component export to satisfy the import `name`.
+    fn find_definition(&self, name: &str) -> Option<DependencyKind> {
+        for (index, (def_component_id, _)) in self.definitions.iter().enumerate() {
+            let def_component = self.graph.get_component(*def_component_id).unwrap();
+
+            if let Some((export, ComponentExternalKind::Instance, _)) =
+                def_component.export_by_name(name)
+            {
+                log::debug!(
+                    "found matching instance export `{name}` in definition component `{path}`",
+                    path = def_component.path().unwrap().display()
+                );
+
+                return Some(DependencyKind::Definition { index, export });
+            }
+        }
+
+        None
+    }
+
     /// Push dependencies of the given instance to the dependency queue.
     fn push_dependencies(&self, instance: usize, queue: &mut VecDeque<Dependency>) -> Result<()> {
         let (instance_name, instance_id) = self.instances.get_index(instance).unwrap();
@@ -407,48 +651,43 @@ impl<'a> CompositionGraphBuilder<'a> {
         let count = queue.len();
 
         // Push a dependency for every instance import
-        'outer: for (import, name, _) in component.imports() {
+        for (import, name, _) in component.imports() {
             log::debug!("adding dependency for argument `{name}` (import index {import}) from instance `{instance_name}` to the queue", import = import.0);
 
             // Search for a matching definition export for this import
-            for (index, (def_component_id, _)) in self.definitions.iter().enumerate() {
-                let def_component = self.graph.get_component(*def_component_id).unwrap();
-
-                match def_component.export_by_name(name) {
-                    Some((export, ComponentExternalKind::Instance, _)) => {
-                        log::debug!(
-                            "found matching instance export `{name}` in definition component `{path}`",
-                            path = def_component.path().unwrap().display()
-                        );
-
-                        queue.push_back(Dependency {
-                            dependent: instance,
-                            import: InstanceImportRef {
-                                component: component_id,
-                                import,
-                            },
-                            kind: DependencyKind::Definition { index, export },
-                        });
-
-                        continue 'outer;
+            let kind = self.find_definition(name).unwrap_or_else(|| {
+                let arg = instantiation.and_then(|c| c.arguments.get(name));
+                DependencyKind::Instance {
+                    instance: arg
+                        .map(|arg| arg.instance.clone())
+                        .unwrap_or_else(|| name.to_string()),
+                    export: arg.and_then(|arg| arg.export.clone()),
+                }
+            });
+
+            // The imports of an intercepting instance are never intercepted
+            let kind = match self.config.intercepts.get(name) {
+                Some(intercept) if intercept.instance != *instance_name => {
+                    log::debug!(
+                        "argument `{name}` from instance `{instance_name}` is intercepted by instance `{intercepting}`",
+                        intercepting = intercept.instance,
+                    );
+                    DependencyKind::Intercept {
+                        instance: intercept.instance.clone(),
+                        export: intercept.export.clone(),
+                        original: Box::new(kind),
                     }
-                    _ => continue,
                 }
-            }
+                _ => kind,
+            };
 
-            let arg = instantiation.and_then(|c| c.arguments.get(name));
             queue.push_back(Dependency {
                 dependent: instance,
                 import: InstanceImportRef {
                     component: component_id,
                     import,
                 },
-                kind: DependencyKind::Instance {
-                    instance: arg
-                        .map(|arg| arg.instance.clone())
-                        .unwrap_or_else(|| name.to_string()),
-                    export: arg.and_then(|arg| arg.export.clone()),
-                },
+                kind,
             });
         }
 
@@ -477,6 +716,93 @@ impl<'a> CompositionGraphBuilder<'a> {
 
     /// Build the instantiation graph.
     fn build(mut self) -> Result<(InstanceId, CompositionGraph<'a>)> {
+        let root_instance = self.resolve()?;
+        Ok((root_instance, self.graph))
+    }
+
+    /// Build the instantiation graph and describe it as a plan.
+    fn build_plan(mut self) -> Result<CompositionPlan> {
+        let root_instance = self.resolve()?;
+
+        let components: IndexMap<_, _> = self
+            .graph
+            .components
+            .iter()
+            .map(|(id, entry)| {
+                (
+                    *id,
+                    PlannedComponent {
+                        name: entry.component.name().to_string(),
+                        path: entry.component.path().map(Path::to_path_buf),
+                    },
+                )
+            })
+            .collect();
+
+        // Instances of definition components aren't named in the configuration,
+        // so they're named after their component instead.
+        let name_of = |id: InstanceId| {
+            self.instances
+                .iter()
+                .find(|(_, i)| **i == id)
+                .map(|(name, _)| name.clone())
+                .unwrap_or_else(|| {
+                    let (_, component) = self.graph.get_component_of_instance(id).unwrap();
+                    component.name().to_string()
+                })
+        };
+
+        let mut instances = Vec::new();
+        for (id, instance) in &self.graph.instances {
+            let component = self.graph.get_component(instance.component).unwrap();
+            let mut arguments = Vec::new();
+            for (source, _, map) in self
+                .graph
+                .graph
+                .edges_directed(*id, petgraph::EdgeDirection::Incoming)
+            {
+                let (_, source_component) = self.graph.get_component_of_instance(source).unwrap();
+                for (import, export) in map {
+                    arguments.push(PlannedArgument {
+                        import: component.import(*import).unwrap().0.to_string(),
+                        import_index: import.0,
+                        instance: self.graph.instances.get_index_of(&source).unwrap(),
+                        export: export.map(|e| {
+                            source_component
+                                .exports
+                                .get_index(e.0)
+                                .unwrap()
+                                .0
+                                .to_string()
+                        }),
+                        export_index: export.map(|e| e.0),
+                    });
+                }
+            }
+            arguments.sort_by_key(|a| a.import_index);
+
+            instances.push(PlannedInstance {
+                name: name_of(*id),
+                component: components.get_index_of(&instance.component).unwrap(),
+                arguments,
+                unsatisfied: component
+                    .imports()
+                    .filter(|(index, _, _)| !instance.connected.contains(index))
+                    .map(|(_, name, _)| name.to_string())
+                    .collect(),
+            });
+        }
+
+        Ok(CompositionPlan {
+            components: components.into_values().collect(),
+            instances,
+            root: self.graph.instances.get_index_of(&root_instance).unwrap(),
+        })
+    }
+
+    /// Resolves the dependencies of the root component, returning the id of
+    /// the root instance.
+    fn resolve(&mut self) -> Result<InstanceId> {
         let mut queue: VecDeque<Dependency> = VecDeque::new();
 
         // Instantiate the root and push its dependencies to the queue
@@ -490,17 +816,103 @@ impl<'a> CompositionGraphBuilder<'a> {
 
         // Process all remaining dependencies in the queue
         while let Some(dependency) = queue.pop_front() {
-            if let Some(instance) = self.process_dependency(dependency)? {
+            if let Some(instance) = self.process_dependency(dependency, &mut queue)? {
                 self.push_dependencies(instance, &mut queue)?;
             }
         }
 
+        // Intercepted imports are connected last so that the resources of the
+        // instances they would otherwise be connected to are known
+        for interception in std::mem::take(&mut self.interceptions) {
+            self.connect_interception(interception)?;
+        }
+
         self.graph.unify_imported_resources();
 
-        Ok((self.instances[root_instance], self.graph))
+        // If only the root component was instantiated, then there are no resolved dependencies
+        if self.graph.instances.len() == 1 {
+            bail!(
+                "no dependencies of component `{path}` were found",
+                path = self
+                    .graph
+                    .get_component_of_instance(self.instances[root_instance])
+                    .unwrap()
+                    .1
+                    .path()
+                    .unwrap()
+                    .display()
+            );
+        }
+
+        Ok(self.instances[root_instance])
     }
 }
 
+/// The plan of a composition, describing how the composer resolved the
+/// dependencies of the root component without encoding the composed
+/// component.
+///
+/// Instances and components refer to each other by their index in
+/// [`CompositionPlan::instances`] and [`CompositionPlan::components`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CompositionPlan {
+    /// The components which are part of the composition.
+    pub components: Vec<PlannedComponent>,
+    /// The instances of components in the composition.
+    pub instances: Vec<PlannedInstance>,
+    /// The index of the root component's instance, whose exports are exported
+    /// from the composed component.
+    pub root: usize,
+}
+
+/// A component which is part of a [`CompositionPlan`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlannedComponent {
+    /// The name of the component.
+    pub name: String,
+    /// The path the component was read from, or `None` if it's a generated
+    /// stub or glue component.
+    pub path: Option<PathBuf>,
+}
+
+/// An instance of a component in a [`CompositionPlan`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlannedInstance {
+    /// The name of the instance.
+    ///
+    /// This is the name of the instance in the configuration, or the name of
+    /// the component for instances of definition components.
+    pub name: String,
+    /// The index of the component being instantiated.
+    pub component: usize,
+    /// The imports of the instance which are satisfied by other instances.
+    pub arguments: Vec<PlannedArgument>,
+    /// The names of the imports of the instance which are not satisfied by
+    /// other instances and are instead imported by the composed component.
+    pub unsatisfied: Vec<String>,
+}
+
+/// An import of a [`PlannedInstance`] which is satisfied by another instance.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlannedArgument {
+    /// The name of the import.
+    pub import: String,
+    /// The index of the import in the instantiated component.
+    pub import_index: usize,
+    /// The index of the instance satisfying the import.
+    pub instance: usize,
+    /// The name of the export of the instance satisfying the import, or
+    /// `None` if the instance itself satisfies the import.
+    pub export: Option<String>,
+    /// The index of the export of the instance satisfying the import, or
+    /// `None` if the instance itself satisfies the import.
+    pub export_index: Option<usize>,
+}
+
 /// Used to compose a WebAssembly component from other components.
 ///
 /// The component composer resolves the dependencies of a root component
@@ -531,14 +943,6 @@ impl<'a> ComponentComposer<'a> {
         let (root_instance, graph) =
             CompositionGraphBuilder::new(self.component, self.config)?.build()?;
 
-        // If only the root component was instantiated, then there are no resolved dependencies
-        if graph.instances.len() == 1 {
-            bail!(
-                "no dependencies of component `{path}` were found",
-                path = self.component.display()
-            );
-        }
-
         CompositionGraphEncoder::new(
             EncodeOptions {
                 define_components: !self.config.import_components,
@@ -549,4 +953,15 @@ impl<'a> ComponentComposer<'a> {
         )
         .encode()
     }
+
+    /// Resolves the dependencies of the root component like
+    /// [`ComponentComposer::compose`] but without encoding the composed
+    /// component.
+    ///
+    /// ## Returns
+    /// Returns the plan of the composition, describing which instance
+    /// satisfies each import and which imports remain unsatisfied.
+    pub fn plan(&self) -> Result<CompositionPlan> {
+        CompositionGraphBuilder::new(self.component, self.config)?.build_plan()
+    }
 }
@@ -67,6 +67,45 @@ pub struct Instantiation {
     pub arguments: IndexMap<String, InstantiationArg>,
 }
 
+/// The behavior of the functions of a stub instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum StubKind {
+    /// Every function traps when called.
+    #[default]
+    Trap,
+    /// Every function returns the canonical default value of its result
+    /// types, such as zero, `false`, an empty string or list, or the first
+    /// case of a variant.
+    Default,
+}
+
+/// An interception of the functions of an instance import.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Intercept {
+    /// The name of the instance intercepting the import.
+    pub instance: String,
+
+    /// The name of the instance export of the intercepting instance whose
+    /// functions replace those of the import.
+    ///
+    /// If `None`, the export with the same name as the import will be used.
+    #[serde(default)]
+    pub export: Option<String>,
+}
+
+impl FromStr for Intercept {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            instance: s.to_string(),
+            export: None,
+        })
+    }
+}
+
 /// The configuration for composing a WebAssembly component.
 #[derive(Default, Debug, Clone, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
@@ -112,6 +151,26 @@ pub struct Config {
     /// The explicit instantiations of the composed component.
     #[serde(default)]
     pub instantiations: IndexMap<String, Instantiation>,
+
+    /// Instances to satisfy with generated stubs if a dependency for them
+    /// cannot be found.
+    ///
+    /// Maps the name of the instance to the behavior of the stub's functions.
+    /// A stub exports everything the importing component expects of the
+    /// instance, which allows partially-composed components to be produced
+    /// for testing.
+    #[serde(default)]
+    pub stubs: IndexMap<String, StubKind>,
+
+    /// Instance imports whose functions are intercepted by other instances.
+    ///
+    /// Maps the name of an instance import to the instance intercepting it.
+    /// Functions exported by the intercepting instance, such as the methods
+    /// of a resource, replace those of the instance the import would otherwise
+    /// be satisfied with, which still provides everything else. The imports of
+    /// the intercepting instance itself are not intercepted.
+    #[serde(default, deserialize_with = "de::index_map")]
+    pub intercepts: IndexMap<String, Intercept>,
 }
 
 impl Config {
@@ -215,6 +215,18 @@ impl<'a> TypeState<'a> {
     }
 }
 
+impl<'a> TypeState<'a> {
+    /// Returns the component being built at the top-level scope of this
+    /// state.
+    pub(crate) fn builder(&mut self) -> &mut ComponentBuilder {
+        assert!(self.scopes.is_empty());
+        match &mut self.cur.encodable {
+            Encodable::Builder(b) => b,
+            _ => unreachable!(),
+        }
+    }
+}
+
 impl Default for TypeState<'_> {
     fn default() -> Self {
         Self::new()
@@ -319,6 +331,127 @@ impl<'a> TypeEncoder<'a> {
         }
     }
 
+    /// Defines the type `referenced` in the component being built by `state`
+    /// and exports it as `name`.
+    ///
+    /// Subsequent uses of `created` will refer to the exported type. Returns
+    /// the index of the exported type.
+    pub(crate) fn export_type(
+        &self,
+        state: &mut TypeState<'a>,
+        name: &'a str,
+        created: ComponentAnyTypeId,
+        referenced: ComponentAnyTypeId,
+    ) -> u32 {
+        let ty = self.ty(state, referenced.into());
+        let index = state
+            .builder()
+            .export(name, ComponentExportKind::Type, ty, None);
+        let key = (PtrKey(self.0), created.into());
+        state.cur.type_defs.insert(key, index);
+        state.cur.add_type_export(key, name);
+        index
+    }
+
+    /// Defines the function type `id` in the scope of `state`, returning its
+    /// index.
+    pub(crate) fn func_type(&self, state: &mut TypeState<'a>, id: ComponentFuncTypeId) -> u32 {
+        self.ty(state, ComponentAnyTypeId::from(id).into())
+    }
+
+    /// Imports an instance named `name` with the exports `exports` into the
+    /// component being built by `state`, returning the index of the instance.
+    ///
+    /// Exported types which are available from a previously imported instance
+    /// are equal to that type rather than being defined anew.
+    pub(crate) fn import_instance<E>(
+        &self,
+        state: &mut TypeState<'a>,
+        name: &str,
+        exports: E,
+    ) -> u32
+    where
+        E: IntoIterator<Item = (&'a str, ComponentEntityType)>,
+    {
+        state.push(Encodable::Instance(InstanceType::new()));
+
+        for (name, ty) in exports {
+            let export = match ty {
+                ComponentEntityType::Type { created, .. }
+                    if state.scopes.iter().any(|s| {
+                        s.instance_exports
+                            .contains_key(&(PtrKey(self.0), created.into()))
+                    }) =>
+                {
+                    // Refer to the previous definition through an outer alias
+                    // and use the exported type from now on.
+                    let index = self.ty(state, created.into());
+                    let value = state.cur.encodable.type_count();
+                    state
+                        .cur
+                        .type_defs
+                        .insert((PtrKey(self.0), created.into()), value);
+                    ComponentTypeRef::Type(TypeBounds::Eq(index))
+                }
+                _ => self.export(name, ty, state),
+            };
+            let t = match &mut state.cur.encodable {
+                Encodable::Instance(t) => t,
+                _ => unreachable!(),
+            };
+            t.export(name, export);
+        }
+
+        let instance_type = match state.pop() {
+            Encodable::Instance(t) => t,
+            _ => unreachable!(),
+        };
+        let builder = state.builder();
+        let index = builder.type_instance(&instance_type);
+        builder.import(name, ComponentTypeRef::Instance(index))
+    }
+
+    /// Aliases the export `name` of type `ty` from the instance `instance` of
+    /// the component being built by `state` and exports it with the same
+    /// name.
+    ///
+    /// Exported functions are ascribed their type in terms of previously
+    /// exported types, which keeps the names of methods of resources valid.
+    pub(crate) fn reexport(
+        &self,
+        state: &mut TypeState<'a>,
+        instance: u32,
+        name: &str,
+        ty: ComponentEntityType,
+    ) -> u32 {
+        let kind = match ty {
+            ComponentEntityType::Module(_) => ComponentExportKind::Module,
+            ComponentEntityType::Func(_) => ComponentExportKind::Func,
+            ComponentEntityType::Value(_) => ComponentExportKind::Value,
+            ComponentEntityType::Type { .. } => ComponentExportKind::Type,
+            ComponentEntityType::Instance(_) => ComponentExportKind::Instance,
+            ComponentEntityType::Component(_) => ComponentExportKind::Component,
+        };
+        let index = state.builder().alias_export(instance, name, kind);
+        match ty {
+            ComponentEntityType::Type { created, .. } => {
+                let index = state.builder().export(name, kind, index, None);
+                state
+                    .cur
+                    .type_defs
+                    .insert((PtrKey(self.0), created.into()), index);
+                index
+            }
+            ComponentEntityType::Func(id) => {
+                let ty = self.func_type(state, id);
+                state
+                    .builder()
+                    .export(name, kind, index, Some(ComponentTypeRef::Func(ty)))
+            }
+            _ => state.builder().export(name, kind, index, None),
+        }
+    }
+
     pub fn module<I, E>(&self, imports: I, exports: E) -> ModuleType
     where
         I: IntoIterator<Item = (&'a str, &'a str, wasmparser::types::EntityType)>,
@@ -489,14 +489,20 @@ impl ResourceMapping {
                     if let Some((export_component, export_resource)) =
                         exports.get(&export_name).copied()
                     {
-                        let value = self
+                        // An exported resource may itself be mapped, such as
+                        // when an imported resource is re-exported.
+                        let export_value = self
                             .map
                             .get(&export_resource)
                             .copied()
-                            .or_else(|| self.map.get(&import_resource).copied())
                             .unwrap_or((export_component, export_resource));
+                        let value = self
+                            .map
+                            .get(&import_resource)
+                            .copied()
+                            .unwrap_or(export_value);
 
-                        if value.1 == export_resource {
+                        if value.1 == export_value.1 {
                             self.map.insert(export_resource, value);
                             self.map.insert(import_resource, value);
                         } else {
@@ -0,0 +1,116 @@
+//! Module for generating glue components to intercept functions of instance
+//! imports.
+
+use crate::{
+    encoding::{TypeEncoder, TypeState},
+    graph::Component,
+};
+use anyhow::{bail, Result};
+use indexmap::IndexSet;
+use wasm_encoder::ComponentExportKind;
+use wasmparser::types::{ComponentAnyTypeId, ComponentEntityType, ComponentInstanceTypeId};
+
+/// The name of the import of the intercepting instance in a glue component.
+pub(crate) const INTERCEPT_IMPORT_NAME: &str = "intercept";
+
+/// Encodes a glue component which imports an instance named `name` of the
+/// instance type `ty`, as defined by `component`, and an intercepting instance
+/// named [`INTERCEPT_IMPORT_NAME`]. The glue component exports an instance
+/// named `name` which satisfies an import of that type.
+///
+/// The functions of the exported instance which are named in `intercepted`
+/// are those of the intercepting instance, which must also export the types
+/// named in `intercepted` that those functions use. Everything else is
+/// re-exported from the original instance.
+pub(crate) fn encode<'a>(
+    component: &'a Component<'a>,
+    name: &str,
+    ty: ComponentInstanceTypeId,
+    intercepted: &IndexSet<&str>,
+) -> Result<Vec<u8>> {
+    let types = component.types();
+    let exports = &types[ty].exports;
+
+    for (export, ty) in exports.iter() {
+        if let ComponentEntityType::Type {
+            created: created @ ComponentAnyTypeId::Resource(_),
+            referenced,
+        } = *ty
+        {
+            if created != referenced {
+                bail!("cannot intercept resource `{export}` defined by another instance");
+            }
+        }
+    }
+
+    let encoder = TypeEncoder::new(component);
+
+    // The inner component re-exports the items of either instance under the
+    // names of the original instance.
+    let mut state = TypeState::default();
+    let (original, intercept) =
+        import_instances(component, &encoder, &mut state, name, ty, intercepted);
+    for (export, ty) in exports.iter() {
+        let instance = match ty {
+            ComponentEntityType::Func(_) if intercepted.contains(export.as_str()) => intercept,
+            _ => original,
+        };
+        encoder.reexport(&mut state, instance, export, *ty);
+    }
+    let inner = std::mem::take(state.builder());
+
+    // The glue component exports an instance of the inner component, as an
+    // instance of the glue component itself could not be connected to the
+    // import.
+    let mut state = TypeState::default();
+    let (original, intercept) =
+        import_instances(component, &encoder, &mut state, name, ty, intercepted);
+    let builder = state.builder();
+    let inner = builder.component(inner);
+    let instance = builder.instantiate(
+        inner,
+        [
+            (name, ComponentExportKind::Instance, original),
+            (
+                INTERCEPT_IMPORT_NAME,
+                ComponentExportKind::Instance,
+                intercept,
+            ),
+        ],
+    );
+    builder.export(name, ComponentExportKind::Instance, instance, None);
+
+    Ok(std::mem::take(builder).finish())
+}
+
+/// Imports the original and the intercepting instances into the component
+/// being built by `state`, returning their indexes.
+fn import_instances<'a>(
+    component: &'a Component<'a>,
+    encoder: &TypeEncoder<'a>,
+    state: &mut TypeState<'a>,
+    name: &str,
+    ty: ComponentInstanceTypeId,
+    intercepted: &IndexSet<&str>,
+) -> (u32, u32) {
+    let exports = &component.types[ty].exports;
+    let original = encoder.import_instance(
+        state,
+        name,
+        exports.iter().map(|(name, ty)| (name.as_str(), *ty)),
+    );
+    let intercept = encoder.import_instance(
+        state,
+        INTERCEPT_IMPORT_NAME,
+        exports
+            .iter()
+            .filter(|(name, ty)| {
+                matches!(
+                    ty,
+                    ComponentEntityType::Type { .. } | ComponentEntityType::Func(_)
+                ) && intercepted.contains(name.as_str())
+            })
+            .map(|(name, ty)| (name.as_str(), *ty)),
+    );
+    (original, intercept)
+}
@@ -6,3 +6,5 @@ pub mod composer;
 pub mod config;
 pub(crate) mod encoding;
 pub mod graph;
+pub(crate) mod intercept;
+pub(crate) mod stub;
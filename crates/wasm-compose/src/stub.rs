@@ -0,0 +1,397 @@
+//! Module for generating stub components to satisfy instance imports.
+
+use crate::{
+    config::StubKind,
+    encoding::{TypeEncoder, TypeState},
+    graph::Component,
+};
+use anyhow::{bail, Result};
+use wasm_encoder::{
+    CanonicalOption, CodeSection, ExportKind, ExportSection, Function, FunctionSection,
+    Instruction, MemorySection, MemoryType, Module, ModuleArg, TypeSection, ValType,
+};
+use wasmparser::types::{
+    ComponentDefinedType, ComponentEntityType, ComponentFuncTypeId, ComponentInstanceTypeId,
+    ComponentValType, TypesRef,
+};
+use wasmparser::PrimitiveValType;
+
+/// The maximum number of flat parameters before they're passed indirectly.
+const MAX_FLAT_PARAMS: usize = 16;
+
+/// The maximum number of flat results before they're returned indirectly.
+const MAX_FLAT_RESULTS: usize = 1;
+
+/// The size of a page of linear memory.
+const PAGE_SIZE: u32 = 65536;
+
+/// Encodes a component which exports all of the items of the instance type
+/// `ty`, as defined by `component`, such that instantiating it satisfies an
+/// import of that type.
+///
+/// Functions of the stub behave according to `kind`.
+pub(crate) fn encode(
+    component: &Component,
+    ty: ComponentInstanceTypeId,
+    kind: StubKind,
+) -> Result<Vec<u8>> {
+    let types = component.types();
+    let encoder = TypeEncoder::new(component);
+    let mut state = TypeState::default();
+
+    // First define and export all of the types of the instance so functions
+    // can refer to them, and collect the functions to implement.
+    let mut funcs = Vec::new();
+    for (name, export) in types[ty].exports.iter() {
+        match *export {
+            ComponentEntityType::Type {
+                referenced,
+                created,
+            } => {
+                if let wasmparser::types::ComponentAnyTypeId::Resource(_) = referenced {
+                    bail!("cannot stub resource `{name}`");
+                }
+                encoder.export_type(&mut state, name, created, referenced);
+            }
+            ComponentEntityType::Func(id) => {
+                let index = encoder.func_type(&mut state, id);
+                funcs.push((name.as_str(), index, Signature::new(types, id, kind)?));
+            }
+            export => bail!(
+                "cannot stub {desc} export `{name}`",
+                desc = crate::graph::type_desc(export)
+            ),
+        }
+    }
+
+    // Next create a core module which implements each function along with a
+    // memory and `realloc` function for lifting.
+    let module = core_module(&funcs, kind);
+    let builder = state.builder();
+    let module = builder.core_module(&module);
+    let instance = builder.core_instantiate(module, Vec::<(&str, ModuleArg)>::new());
+    let memory = builder.core_alias_export(instance, "memory", ExportKind::Memory);
+    let realloc = builder.core_alias_export(instance, "realloc", ExportKind::Func);
+
+    // Finally lift and export each function.
+    for (i, (name, ty, _)) in funcs.iter().enumerate() {
+        let func = builder.core_alias_export(instance, &i.to_string(), ExportKind::Func);
+        let func = builder.lift_func(
+            func,
+            *ty,
+            [
+                CanonicalOption::UTF8,
+                CanonicalOption::Memory(memory),
+                CanonicalOption::Realloc(realloc),
+            ],
+        );
+        builder.export(name, wasm_encoder::ComponentExportKind::Func, func, None);
+    }
+
+    Ok(std::mem::take(builder).finish())
+}
+
+/// Creates the core module implementing the stubbed functions `funcs`.
+fn core_module(funcs: &[(&str, u32, Signature)], kind: StubKind) -> Module {
+    let mut types = TypeSection::new();
+    let mut functions = FunctionSection::new();
+    let mut exports = ExportSection::new();
+    let mut code = CodeSection::new();
+
+    // The `realloc` function allocates by growing memory, which is always
+    // suitably aligned.
+    types.ty().function([ValType::I32; 4], [ValType::I32]);
+    functions.function(0);
+    exports.export("realloc", ExportKind::Func, 0);
+    let mut realloc = Function::new([(1, ValType::I32)]);
+    realloc.instruction(&Instruction::LocalGet(3));
+    realloc.instruction(&Instruction::I32Const((PAGE_SIZE - 1) as i32));
+    realloc.instruction(&Instruction::I32Add);
+    realloc.instruction(&Instruction::I32Const(16));
+    realloc.instruction(&Instruction::I32ShrU);
+    realloc.instruction(&Instruction::MemoryGrow(0));
+    realloc.instruction(&Instruction::LocalTee(4));
+    realloc.instruction(&Instruction::I32Const(-1));
+    realloc.instruction(&Instruction::I32Eq);
+    realloc.instruction(&Instruction::If(wasm_encoder::BlockType::Empty));
+    realloc.instruction(&Instruction::Unreachable);
+    realloc.instruction(&Instruction::End);
+    realloc.instruction(&Instruction::LocalGet(4));
+    realloc.instruction(&Instruction::I32Const(16));
+    realloc.instruction(&Instruction::I32Shl);
+    realloc.instruction(&Instruction::End);
+    code.function(&realloc);
+
+    // Indirect results of functions returning defaults are read from the
+    // start of memory, which is always zero.
+    let mut pages = 1;
+    for (i, (_, _, sig)) in funcs.iter().enumerate() {
+        let index = i as u32 + 1;
+        types
+            .ty()
+            .function(sig.params.iter().copied(), sig.results.iter().copied());
+        functions.function(index);
+        exports.export(&i.to_string(), ExportKind::Func, index);
+
+        let mut func = Function::new([]);
+        match kind {
+            StubKind::Trap => {
+                func.instruction(&Instruction::Unreachable);
+            }
+            StubKind::Default => match sig.indirect_result_size {
+                Some(size) => {
+                    pages = pages.max(size.div_ceil(PAGE_SIZE));
+                    func.instruction(&Instruction::I32Const(0));
+                }
+                None => {
+                    for ty in sig.results.iter() {
+                        func.instruction(&match ty {
+                            ValType::I64 => Instruction::I64Const(0),
+                            ValType::F32 => Instruction::F32Const(0.0),
+                            ValType::F64 => Instruction::F64Const(0.0),
+                            _ => Instruction::I32Const(0),
+                        });
+                    }
+                }
+            },
+        }
+        func.instruction(&Instruction::End);
+        code.function(&func);
+    }
+
+    let mut memories = MemorySection::new();
+    memories.memory(MemoryType {
+        minimum: pages.into(),
+        maximum: None,
+        memory64: false,
+        shared: false,
+        page_size_log2: None,
+    });
+    exports.export("memory", ExportKind::Memory, 0);
+
+    let mut module = Module::new();
+    module.section(&types);
+    module.section(&functions);
+    module.section(&memories);
+    module.section(&exports);
+    module.section(&code);
+    module
+}
+
+/// The core wasm signature of a lifted function.
+struct Signature {
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// The size of the results if they're returned indirectly through memory.
+    indirect_result_size: Option<u32>,
+}
+
+impl Signature {
+    fn new(types: TypesRef, id: ComponentFuncTypeId, kind: StubKind) -> Result<Self> {
+        let ty = &types[id];
+
+        let mut params = Vec::new();
+        for (_, ty) in ty.params.iter() {
+            push_flat(types, *ty, &mut params);
+        }
+        if params.len() > MAX_FLAT_PARAMS {
+            params = vec![ValType::I32];
+        }
+
+        let mut results = Vec::new();
+        for (_, ty) in ty.results.iter() {
+            push_flat(types, *ty, &mut results);
+            if kind == StubKind::Default && has_handle(types, *ty) {
+                bail!("cannot return a default value for a type containing a resource handle");
+            }
+        }
+        let mut indirect_result_size = None;
+        if results.len() > MAX_FLAT_RESULTS {
+            results = vec![ValType::I32];
+            let (size, _) = record_size_align(types, ty.results.iter().map(|(_, ty)| *ty));
+            indirect_result_size = Some(size);
+        }
+
+        Ok(Self {
+            params,
+            results,
+            indirect_result_size,
+        })
+    }
+}
+
+/// Pushes the flat core wasm types of `ty` onto `result`.
+fn push_flat(types: TypesRef, ty: ComponentValType, result: &mut Vec<ValType>) {
+    let id = match ty {
+        ComponentValType::Primitive(ty) => return push_flat_primitive(ty, result),
+        ComponentValType::Type(id) => id,
+    };
+    match &types[id] {
+        ComponentDefinedType::Primitive(ty) => push_flat_primitive(*ty, result),
+        ComponentDefinedType::Record(r) => {
+            for (_, ty) in r.fields.iter() {
+                push_flat(types, *ty, result);
+            }
+        }
+        ComponentDefinedType::Tuple(t) => {
+            for ty in t.types.iter() {
+                push_flat(types, *ty, result);
+            }
+        }
+        ComponentDefinedType::Flags(names) => {
+            result.extend((0..names.len().div_ceil(32)).map(|_| ValType::I32))
+        }
+        ComponentDefinedType::Enum(_)
+        | ComponentDefinedType::Own(_)
+        | ComponentDefinedType::Borrow(_) => result.push(ValType::I32),
+        ComponentDefinedType::List(_) => result.extend([ValType::I32, ValType::I32]),
+        ComponentDefinedType::Variant(v) => {
+            push_flat_variant(types, v.cases.values().map(|c| c.ty), result)
+        }
+        ComponentDefinedType::Option(ty) => push_flat_variant(types, [None, Some(*ty)], result),
+        ComponentDefinedType::Result { ok, err } => push_flat_variant(types, [*ok, *err], result),
+    }
+}
+
+fn push_flat_primitive(ty: PrimitiveValType, result: &mut Vec<ValType>) {
+    match ty {
+        PrimitiveValType::S64 | PrimitiveValType::U64 => result.push(ValType::I64),
+        PrimitiveValType::F32 => result.push(ValType::F32),
+        PrimitiveValType::F64 => result.push(ValType::F64),
+        PrimitiveValType::String => result.extend([ValType::I32, ValType::I32]),
+        _ => result.push(ValType::I32),
+    }
+}
+
+fn push_flat_variant(
+    types: TypesRef,
+    cases: impl IntoIterator<Item = Option<ComponentValType>>,
+    result: &mut Vec<ValType>,
+) {
+    result.push(ValType::I32);
+    let start = result.len();
+    let mut temp = Vec::new();
+    for ty in cases.into_iter().flatten() {
+        push_flat(types, ty, &mut temp);
+        for (i, ty) in temp.drain(..).enumerate() {
+            match result.get_mut(start + i) {
+                Some(prev) if *prev == ty => {}
+                Some(prev @ (ValType::I32 | ValType::F32))
+                    if matches!(ty, ValType::I32 | ValType::F32) =>
+                {
+                    *prev = ValType::I32
+                }
+                Some(prev) => *prev = ValType::I64,
+                None => result.push(ty),
+            }
+        }
+    }
+}
+
+/// Returns whether `ty` contains a resource handle.
+fn has_handle(types: TypesRef, ty: ComponentValType) -> bool {
+    let id = match ty {
+        ComponentValType::Primitive(_) => return false,
+        ComponentValType::Type(id) => id,
+    };
+    match &types[id] {
+        ComponentDefinedType::Own(_) | ComponentDefinedType::Borrow(_) => true,
+        ComponentDefinedType::Primitive(_)
+        | ComponentDefinedType::Flags(_)
+        | ComponentDefinedType::Enum(_) => false,
+        ComponentDefinedType::Record(r) => r.fields.values().any(|ty| has_handle(types, *ty)),
+        ComponentDefinedType::Tuple(t) => t.types.iter().any(|ty| has_handle(types, *ty)),
+        ComponentDefinedType::List(ty) | ComponentDefinedType::Option(ty) => has_handle(types, *ty),
+        ComponentDefinedType::Variant(v) => v
+            .cases
+            .values()
+            .any(|c| c.ty.map_or(false, |ty| has_handle(types, ty))),
+        ComponentDefinedType::Result { ok, err } => [ok, err]
+            .into_iter()
+            .any(|ty| ty.map_or(false, |ty| has_handle(types, ty))),
+    }
+}
+
+/// Returns the size and alignment of `ty` in linear memory.
+fn size_align(types: TypesRef, ty: ComponentValType) -> (u32, u32) {
+    let id = match ty {
+        ComponentValType::Primitive(ty) => return primitive_size_align(ty),
+        ComponentValType::Type(id) => id,
+    };
+    match &types[id] {
+        ComponentDefinedType::Primitive(ty) => primitive_size_align(*ty),
+        ComponentDefinedType::Record(r) => record_size_align(types, r.fields.values().copied()),
+        ComponentDefinedType::Tuple(t) => record_size_align(types, t.types.iter().copied()),
+        ComponentDefinedType::Flags(names) => match names.len() {
+            0 => (0, 1),
+            n if n <= 8 => (1, 1),
+            n if n <= 16 => (2, 2),
+            n => (4 * n.div_ceil(32) as u32, 4),
+        },
+        ComponentDefinedType::Enum(cases) => discriminant_size_align(cases.len()),
+        ComponentDefinedType::Own(_) | ComponentDefinedType::Borrow(_) => (4, 4),
+        ComponentDefinedType::List(_) => (8, 4),
+        ComponentDefinedType::Variant(v) => {
+            variant_size_align(types, v.cases.len(), v.cases.values().map(|c| c.ty))
+        }
+        ComponentDefinedType::Option(ty) => variant_size_align(types, 2, [None, Some(*ty)]),
+        ComponentDefinedType::Result { ok, err } => variant_size_align(types, 2, [*ok, *err]),
+    }
+}
+
+fn primitive_size_align(ty: PrimitiveValType) -> (u32, u32) {
+    match ty {
+        PrimitiveValType::Bool | PrimitiveValType::S8 | PrimitiveValType::U8 => (1, 1),
+        PrimitiveValType::S16 | PrimitiveValType::U16 => (2, 2),
+        PrimitiveValType::S32
+        | PrimitiveValType::U32
+        | PrimitiveValType::F32
+        | PrimitiveValType::Char => (4, 4),
+        PrimitiveValType::S64 | PrimitiveValType::U64 | PrimitiveValType::F64 => (8, 8),
+        PrimitiveValType::String => (8, 4),
+    }
+}
+
+fn record_size_align(
+    types: TypesRef,
+    fields: impl IntoIterator<Item = ComponentValType>,
+) -> (u32, u32) {
+    let mut size = 0;
+    let mut align = 1;
+    for ty in fields {
+        let (field_size, field_align) = size_align(types, ty);
+        size = align_to(size, field_align) + field_size;
+        align = align.max(field_align);
+    }
+    (align_to(size, align), align)
+}
+
+fn variant_size_align(
+    types: TypesRef,
+    num_cases: usize,
+    cases: impl IntoIterator<Item = Option<ComponentValType>>,
+) -> (u32, u32) {
+    let (discriminant_size, discriminant_align) = discriminant_size_align(num_cases);
+    let mut case_size = 0;
+    let mut case_align = 1;
+    for ty in cases.into_iter().flatten() {
+        let (size, align) = size_align(types, ty);
+        case_size = case_size.max(size);
+        case_align = case_align.max(align);
+    }
+    let align = discriminant_align.max(case_align);
+    let size = align_to(discriminant_size, case_align) + case_size;
+    (align_to(size, align), align)
+}
+
+fn discriminant_size_align(num_cases: usize) -> (u32, u32) {
+    match num_cases {
+        0..=0x100 => (1, 1),
+        0x101..=0x1_0000 => (2, 2),
+        _ => (4, 4),
+    }
+}
+
+fn align_to(val: u32, align: u32) -> u32 {
+    (val + align - 1) & !(align - 1)
+}
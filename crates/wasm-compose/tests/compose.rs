@@ -19,6 +19,9 @@ use wasmparser::{Validator, WasmFeatures};
 /// * `composed.wat` - the composed component if the composition is expected to succeed.
 /// * `error.txt` - the expected error message if the composition is expected to fail.
 ///
+/// Additionally, if a `plan.json` file is present, the plan of the composition
+/// is expected to match it.
+///
 /// The test composes a component based on the input files. If the encoding succeeds,
 /// it expects the output to match `composed.wat`. If the encoding fails, it expects
 /// the output to match `error.txt`.
@@ -49,6 +52,24 @@ fn component_composing() -> Result<()> {
         };
         let composer = ComponentComposer::new(&root_path, &config);
 
+        let plan_path = path.join("plan.json");
+        if plan_path.is_file() {
+            let plan = composer
+                .plan()
+                .with_context(|| format!("failed to plan test case `{}`", test_case))?;
+            let plan = serde_json::to_string_pretty(&plan)?.replace("\\\\", "/") + "\n";
+            if std::env::var_os("BLESS").is_some() {
+                fs::write(&plan_path, plan)?;
+            } else {
+                assert_eq!(
+                    fs::read_to_string(&plan_path)?.replace("\r\n", "\n"),
+                    plan,
+                    "failed plan comparison for test case `{}`",
+                    test_case,
+                );
+            }
+        }
+
         let r = composer.compose();
         let (output, baseline_path) = if error_path.is_file() {
             match r {
@@ -0,0 +1,161 @@
+(component
+  (type (;0;)
+    (instance
+      (export (;0;) "logger" (type (sub resource)))
+      (type (;1;) (borrow 0))
+      (type (;2;) (func (param "self" 1) (param "message" string)))
+      (export (;0;) "[method]logger.log" (func (type 2)))
+      (type (;3;) (own 0))
+      (type (;4;) (func (result 3)))
+      (export (;1;) "get-logger" (func (type 4)))
+    )
+  )
+  (import "example:service/logging@0.1.0" (instance (;0;) (type 0)))
+  (component (;0;)
+    (type (;0;)
+      (instance
+        (export (;0;) "logger" (type (sub resource)))
+        (type (;1;) (borrow 0))
+        (type (;2;) (func (param "self" 1) (param "message" string)))
+        (export (;0;) "[method]logger.log" (func (type 2)))
+        (type (;3;) (own 0))
+        (type (;4;) (func (result 3)))
+        (export (;1;) "get-logger" (func (type 4)))
+      )
+    )
+    (import "example:service/logging@0.1.0" (instance (;0;) (type 0)))
+  )
+  (component (;1;)
+    (type (;0;)
+      (instance
+        (export (;0;) "logger" (type (sub resource)))
+        (type (;1;) (borrow 0))
+        (type (;2;) (func (param "self" 1) (param "message" string)))
+        (export (;0;) "[method]logger.log" (func (type 2)))
+        (type (;3;) (own 0))
+        (type (;4;) (func (result 3)))
+        (export (;1;) "get-logger" (func (type 4)))
+      )
+    )
+    (import "example:service/logging@0.1.0" (instance $logging (;0;) (type 0)))
+    (alias export $logging "logger" (type $logger (;1;)))
+    (core module $module (;0;)
+      (type (;0;) (func (param i32 i32 i32 i32) (result i32)))
+      (type (;1;) (func (param i32 i32 i32)))
+      (memory $memory (;0;) 1)
+      (export "memory" (memory $memory))
+      (export "cabi_realloc" (func $cabi_realloc))
+      (export "[method]logger.log" (func $logger-log))
+      (func $cabi_realloc (;0;) (type 0) (param i32 i32 i32 i32) (result i32)
+        unreachable
+      )
+      (func $logger-log (;1;) (type 1) (param i32 i32 i32)
+        unreachable
+      )
+    )
+    (core instance $module-instance (;0;) (instantiate $module))
+    (alias core export $module-instance "memory" (core memory $memory (;0;)))
+    (alias core export $module-instance "cabi_realloc" (core func $realloc (;0;)))
+    (type (;2;) (borrow $logger))
+    (type $logger-log-type (;3;) (func (param "self" 2) (param "message" string)))
+    (alias core export $module-instance "[method]logger.log" (core func $logger-log (;1;)))
+    (func $logger-log-lifted (;0;) (type $logger-log-type) (canon lift (core func $logger-log) (memory $memory) (realloc $realloc) string-encoding=utf8))
+    (component $interceptor (;0;)
+      (import "import-type-logger" (type $import-logger (;0;) (sub resource)))
+      (type (;1;) (borrow $import-logger))
+      (type (;2;) (func (param "self" 1) (param "message" string)))
+      (import "import-method-logger-log" (func $import-logger-log (;0;) (type 2)))
+      (export $logger (;3;) "logger" (type $import-logger))
+      (type (;4;) (borrow $logger))
+      (type (;5;) (func (param "self" 4) (param "message" string)))
+      (export (;1;) "[method]logger.log" (func $import-logger-log) (func (type 5)))
+    )
+    (instance $interceptor-instance (;1;) (instantiate $interceptor
+        (with "import-method-logger-log" (func $logger-log-lifted))
+        (with "import-type-logger" (type $logger))
+      )
+    )
+    (export (;2;) "example:service/logging@0.1.0" (instance $interceptor-instance))
+  )
+  (component (;2;)
+    (type (;0;)
+      (instance
+        (export (;0;) "logger" (type (sub resource)))
+        (type (;1;) (borrow 0))
+        (type (;2;) (func (param "self" 1) (param "message" string)))
+        (export (;0;) "[method]logger.log" (func (type 2)))
+        (type (;3;) (own 0))
+        (type (;4;) (func (result 3)))
+        (export (;1;) "get-logger" (func (type 4)))
+      )
+    )
+    (import "example:service/logging@0.1.0" (instance (;0;) (type 0)))
+    (alias export 0 "logger" (type (;1;)))
+    (type (;2;)
+      (instance
+        (alias outer 1 1 (type (;0;)))
+        (export (;1;) "logger" (type (eq 0)))
+        (type (;2;) (borrow 1))
+        (type (;3;) (func (param "self" 2) (param "message" string)))
+        (export (;0;) "[method]logger.log" (func (type 3)))
+      )
+    )
+    (import "intercept" (instance (;1;) (type 2)))
+    (component (;0;)
+      (type (;0;)
+        (instance
+          (export (;0;) "logger" (type (sub resource)))
+          (type (;1;) (borrow 0))
+          (type (;2;) (func (param "self" 1) (param "message" string)))
+          (export (;0;) "[method]logger.log" (func (type 2)))
+          (type (;3;) (own 0))
+          (type (;4;) (func (result 3)))
+          (export (;1;) "get-logger" (func (type 4)))
+        )
+      )
+      (import "example:service/logging@0.1.0" (instance (;0;) (type 0)))
+      (alias export 0 "logger" (type (;1;)))
+      (type (;2;)
+        (instance
+          (alias outer 1 1 (type (;0;)))
+          (export (;1;) "logger" (type (eq 0)))
+          (type (;2;) (borrow 1))
+          (type (;3;) (func (param "self" 2) (param "message" string)))
+          (export (;0;) "[method]logger.log" (func (type 3)))
+        )
+      )
+      (import "intercept" (instance (;1;) (type 2)))
+      (alias export 0 "logger" (type (;3;)))
+      (export (;4;) "logger" (type 3))
+      (alias export 1 "[method]logger.log" (func (;0;)))
+      (type (;5;) (borrow 4))
+      (type (;6;) (func (param "self" 5) (param "message" string)))
+      (export (;1;) "[method]logger.log" (func 0) (func (type 6)))
+      (alias export 0 "get-logger" (func (;2;)))
+      (type (;7;) (own 4))
+      (type (;8;) (func (result 7)))
+      (export (;3;) "get-logger" (func 2) (func (type 8)))
+    )
+    (instance (;2;) (instantiate 0
+        (with "example:service/logging@0.1.0" (instance 0))
+        (with "intercept" (instance 1))
+      )
+    )
+    (export (;3;) "example:service/logging@0.1.0" (instance 2))
+  )
+  (instance (;1;) (instantiate 1
+      (with "example:service/logging@0.1.0" (instance 0))
+    )
+  )
+  (alias export 1 "example:service/logging@0.1.0" (instance (;2;)))
+  (instance (;3;) (instantiate 2
+      (with "intercept" (instance 2))
+      (with "example:service/logging@0.1.0" (instance 0))
+    )
+  )
+  (alias export 3 "example:service/logging@0.1.0" (instance (;4;)))
+  (instance (;5;) (instantiate 0
+      (with "example:service/logging@0.1.0" (instance 4))
+    )
+  )
+)
@@ -0,0 +1,50 @@
+(component
+  (import "example:service/logging@0.1.0"
+    (instance $logging
+      (export $logger "logger" (type (sub resource)))
+      (export "[method]logger.log" (func (param "self" (borrow $logger)) (param "message" string)))
+      (export "get-logger" (func (result (own $logger))))
+    )
+  )
+  (alias export $logging "logger" (type $logger))
+
+  (core module $module
+    (func $cabi_realloc (param i32 i32 i32 i32) (result i32)
+      unreachable
+    )
+    (func $logger-log (param i32 i32 i32)
+      unreachable
+    )
+    (memory $memory 1)
+    (export "memory" (memory $memory))
+    (export "cabi_realloc" (func $cabi_realloc))
+    (export "[method]logger.log" (func $logger-log))
+  )
+  (core instance $module-instance (instantiate $module))
+  (alias core export $module-instance "memory" (core memory $memory))
+  (alias core export $module-instance "cabi_realloc" (core func $realloc))
+
+  (type $logger-log-type (func (param "self" (borrow $logger)) (param "message" string)))
+  (alias core export $module-instance "[method]logger.log" (core func $logger-log))
+  (func $logger-log-lifted
+    (type $logger-log-type)
+    (canon lift (core func $logger-log) (memory $memory) (realloc $realloc) string-encoding=utf8)
+  )
+
+  (component $interceptor
+    (import "import-type-logger" (type $import-logger (sub resource)))
+    (import "import-method-logger-log"
+      (func $import-logger-log (param "self" (borrow $import-logger)) (param "message" string))
+    )
+    (export $logger "logger" (type $import-logger))
+    (export "[method]logger.log" (func $import-logger-log) (func (param "self" (borrow $logger)) (param "message" string)))
+  )
+
+  (instance $interceptor-instance (instantiate $interceptor
+      (with "import-method-logger-log" (func $logger-log-lifted))
+      (with "import-type-logger" (type $logger))
+    )
+  )
+
+  (export "example:service/logging@0.1.0" (instance $interceptor-instance))
+)
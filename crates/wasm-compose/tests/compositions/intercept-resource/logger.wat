@@ -0,0 +1,72 @@
+(component
+  (core module $module
+    (import "[export]example:service/logging@0.1.0" "[resource-new]logger" (func (param i32) (result i32)))
+    (func $cabi_realloc (param i32 i32 i32 i32) (result i32)
+      unreachable
+    )
+    (func $logger-log (param i32 i32 i32)
+      unreachable
+    )
+    (func $get-logger (result i32)
+      unreachable
+    )
+    (memory $memory 1)
+    (export "memory" (memory $memory))
+    (export "cabi_realloc" (func $cabi_realloc))
+    (export "example:service/logging@0.1.0#[method]logger.log" (func $logger-log))
+    (export "example:service/logging@0.1.0#get-logger" (func $get-logger))
+  )
+
+  (core module $module-indirect
+    (func $dtor-logger (param i32)
+      unreachable
+    )
+    (export "[dtor]logger" (func $dtor-logger))
+  )
+  (core instance $module-indirect-instance (instantiate $module-indirect))
+
+  (alias core export $module-indirect-instance "[dtor]logger" (core func $logger-dtor))
+  (type $logger-resource (resource (rep i32) (dtor (func $logger-dtor))))
+  (core func $logger-new (canon resource.new $logger-resource))
+  (core instance $logger-new-instance
+    (export "[resource-new]logger" (func $logger-new))
+  )
+
+  (core instance $module-instance
+    (instantiate $module
+      (with "[export]example:service/logging@0.1.0" (instance $logger-new-instance))
+    )
+  )
+  (alias core export $module-instance "memory" (core memory $memory))
+  (alias core export $module-instance "cabi_realloc" (core func $realloc))
+
+  (type $logger-log-type (func (param "self" (borrow $logger-resource)) (param "message" string)))
+  (alias core export $module-instance "example:service/logging@0.1.0#[method]logger.log" (core func $logger-log))
+  (func $logger-log-lifted
+    (type $logger-log-type)
+    (canon lift (core func $logger-log) (memory $memory) (realloc $realloc) string-encoding=utf8)
+  )
+  (alias core export $module-instance "example:service/logging@0.1.0#get-logger" (core func $get-logger))
+  (type $get-logger-type (func (result (own $logger-resource))))
+  (func $get-logger-lifted (type $get-logger-type) (canon lift (core func $get-logger)))
+
+  (component $logger
+    (import "import-type-logger" (type $import-logger (sub resource)))
+    (import "import-method-logger-log"
+      (func $import-logger-log (param "self" (borrow $import-logger)) (param "message" string))
+    )
+    (import "import-func-get-logger" (func $import-get-logger (result (own $import-logger))))
+    (export $logger "logger" (type $import-logger))
+    (export "[method]logger.log" (func $import-logger-log) (func (param "self" (borrow $logger)) (param "message" string)))
+    (export "get-logger" (func $import-get-logger) (func (result (own $logger))))
+  )
+
+  (instance $logger-instance (instantiate $logger
+      (with "import-method-logger-log" (func $logger-log-lifted))
+      (with "import-func-get-logger" (func $get-logger-lifted))
+      (with "import-type-logger" (type $logger-resource))
+    )
+  )
+
+  (export "example:service/logging@0.1.0" (instance $logger-instance))
+)
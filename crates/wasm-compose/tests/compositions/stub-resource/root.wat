@@ -0,0 +1,5 @@
+(component
+  (import "a:b/handles" (instance
+    (export "handle" (type (sub resource)))
+  ))
+)
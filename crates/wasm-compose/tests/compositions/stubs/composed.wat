@@ -0,0 +1,120 @@
+(component
+  (component (;0;)
+    (type (;0;)
+      (instance
+        (type (;0;) (record (field "x" u32) (field "label" string)))
+        (export (;1;) "point" (type (eq 0)))
+        (type (;2;) (func (result 1)))
+        (export (;0;) "origin" (func (type 2)))
+        (type (;3;) (func (param "p" 1) (result u64)))
+        (export (;1;) "count" (func (type 3)))
+      )
+    )
+    (import "a:b/types" (instance $types (;0;) (type 0)))
+    (type (;1;)
+      (instance
+        (type (;0;) (func (param "msg" string)))
+        (export (;0;) "log" (func (type 0)))
+      )
+    )
+    (import "a:b/log" (instance $log (;1;) (type 1)))
+    (alias export $log "log" (func $log (;0;)))
+    (export (;1;) "log" (func $log))
+  )
+  (component (;1;)
+    (type (;0;) (record (field "x" u32) (field "label" string)))
+    (export (;1;) "point" (type 0))
+    (type (;2;) (func (result 1)))
+    (type (;3;) (func (param "p" 1) (result u64)))
+    (core module (;0;)
+      (type (;0;) (func (param i32 i32 i32 i32) (result i32)))
+      (type (;1;) (func (result i32)))
+      (type (;2;) (func (param i32 i32 i32) (result i64)))
+      (memory (;0;) 1)
+      (export "realloc" (func 0))
+      (export "0" (func 1))
+      (export "1" (func 2))
+      (export "memory" (memory 0))
+      (func (;0;) (type 0) (param i32 i32 i32 i32) (result i32)
+        (local i32)
+        local.get 3
+        i32.const 65535
+        i32.add
+        i32.const 16
+        i32.shr_u
+        memory.grow
+        local.tee 4
+        i32.const -1
+        i32.eq
+        if ;; label = @1
+          unreachable
+        end
+        local.get 4
+        i32.const 16
+        i32.shl
+      )
+      (func (;1;) (type 1) (result i32)
+        i32.const 0
+      )
+      (func (;2;) (type 2) (param i32 i32 i32) (result i64)
+        i64.const 0
+      )
+    )
+    (core instance (;0;) (instantiate 0))
+    (alias core export 0 "memory" (core memory (;0;)))
+    (alias core export 0 "realloc" (core func (;0;)))
+    (alias core export 0 "0" (core func (;1;)))
+    (func (;0;) (type 2) (canon lift (core func 1) string-encoding=utf8 (memory 0) (realloc 0)))
+    (export (;1;) "origin" (func 0))
+    (alias core export 0 "1" (core func (;2;)))
+    (func (;2;) (type 3) (canon lift (core func 2) string-encoding=utf8 (memory 0) (realloc 0)))
+    (export (;3;) "count" (func 2))
+  )
+  (component (;2;)
+    (type (;0;) (func (param "msg" string)))
+    (core module (;0;)
+      (type (;0;) (func (param i32 i32 i32 i32) (result i32)))
+      (type (;1;) (func (param i32 i32)))
+      (memory (;0;) 1)
+      (export "realloc" (func 0))
+      (export "0" (func 1))
+      (export "memory" (memory 0))
+      (func (;0;) (type 0) (param i32 i32 i32 i32) (result i32)
+        (local i32)
+        local.get 3
+        i32.const 65535
+        i32.add
+        i32.const 16
+        i32.shr_u
+        memory.grow
+        local.tee 4
+        i32.const -1
+        i32.eq
+        if ;; label = @1
+          unreachable
+        end
+        local.get 4
+        i32.const 16
+        i32.shl
+      )
+      (func (;1;) (type 1) (param i32 i32)
+        unreachable
+      )
+    )
+    (core instance (;0;) (instantiate 0))
+    (alias core export 0 "memory" (core memory (;0;)))
+    (alias core export 0 "realloc" (core func (;0;)))
+    (alias core export 0 "0" (core func (;1;)))
+    (func (;0;) (type 0) (canon lift (core func 1) string-encoding=utf8 (memory 0) (realloc 0)))
+    (export (;1;) "log" (func 0))
+  )
+  (instance (;0;) (instantiate 2))
+  (instance (;1;) (instantiate 1))
+  (instance (;2;) (instantiate 0
+      (with "a:b/types" (instance 1))
+      (with "a:b/log" (instance 0))
+    )
+  )
+  (alias export 2 "log" (func (;0;)))
+  (export (;1;) "log" (func 0))
+)
@@ -0,0 +1,14 @@
+(component
+  (import "a:b/types" (instance $types
+    (type $point (record (field "x" u32) (field "label" string)))
+    (export "point" (type (eq $point)))
+    (export "origin" (func (result 1)))
+    (export "count" (func (param "p" 1) (result u64)))
+  ))
+  (import "a:b/log" (instance $log
+    (export "log" (func (param "msg" string)))
+  ))
+
+  (alias export $log "log" (func $log))
+  (export "log" (func $log))
+)
@@ -9,6 +9,7 @@ mod modules;
 mod names;
 mod start;
 mod types;
+mod values;
 
 pub use self::aliases::*;
 pub use self::builder::*;
@@ -21,6 +22,7 @@ pub use self::modules::*;
 pub use self::names::*;
 pub use self::start::*;
 pub use self::types::*;
+pub use self::values::*;
 
 use crate::{CustomSection, Encode, ProducersSection, RawCustomSection};
 
@@ -82,6 +84,8 @@ pub enum ComponentSectionId {
     Import = 10,
     /// The section is an export section.
     Export = 11,
+    /// The section is a value section.
+    Value = 12,
 }
 
 impl From<ComponentSectionId> for u8 {
@@ -322,6 +322,12 @@ impl ComponentBuilder {
         inc(&mut self.types)
     }
 
+    /// Defines a new value of type `ty` within this component.
+    pub fn value(&mut self, ty: impl Into<ComponentValType>, value: &ComponentValue) -> u32 {
+        self.values().value(ty, value);
+        inc(&mut self.values)
+    }
+
     /// Defines a new subcomponent of this component.
     pub fn component(&mut self, mut builder: ComponentBuilder) -> u32 {
         builder.flush();
@@ -458,6 +464,7 @@ section_accessors! {
     imports => ComponentImportSection
     types => ComponentTypeSection
     core_types => CoreTypeSection
+    values => ComponentValueSection
 }
 
 fn inc(idx: &mut u32) -> u32 {
@@ -0,0 +1,318 @@
+use crate::{encode_section, ComponentSection, ComponentSectionId, ComponentValType, Encode};
+
+/// An encoder for the value section of WebAssembly components.
+///
+/// Values are a part of the component model's value definitions, which allow
+/// a component to embed constant values, such as configuration, which may
+/// then be exported or passed as arguments when instantiating components.
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{Component, ComponentValue, ComponentValueSection, PrimitiveValType};
+///
+/// let mut values = ComponentValueSection::new();
+/// values.value(PrimitiveValType::String, &ComponentValue::string("hello"));
+/// values.value(PrimitiveValType::U32, &ComponentValue::u32(42));
+///
+/// let mut component = Component::new();
+/// component.section(&values);
+///
+/// let bytes = component.finish();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ComponentValueSection {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl ComponentValueSection {
+    /// Create a new component value section encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of values in the section.
+    pub fn len(&self) -> u32 {
+        self.num_added
+    }
+
+    /// Determines if the section is empty.
+    pub fn is_empty(&self) -> bool {
+        self.num_added == 0
+    }
+
+    /// Define a value of type `ty`.
+    ///
+    /// Note that the encoding of `value` is not checked against `ty`, so it's
+    /// up to the caller to ensure the two agree.
+    pub fn value(&mut self, ty: impl Into<ComponentValType>, value: &ComponentValue) -> &mut Self {
+        ty.into().encode(&mut self.bytes);
+        value.bytes.encode(&mut self.bytes);
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Encode for ComponentValueSection {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        encode_section(sink, self.num_added, &self.bytes);
+    }
+}
+
+impl ComponentSection for ComponentValueSection {
+    fn id(&self) -> u8 {
+        ComponentSectionId::Value.into()
+    }
+}
+
+/// The encoding of a component-level value, for use with
+/// [`ComponentValueSection`].
+///
+/// The encoding of a value depends on its type but doesn't include it, so for
+/// example a `record` is encoded as just its fields and an `enum` is encoded
+/// as just the index of its case. Values of compound types are created from
+/// the values of their fields, cases, or elements.
+///
+/// ```
+/// use wasm_encoder::ComponentValue;
+///
+/// // A `list<tuple<string, option<u8>>>`.
+/// let value = ComponentValue::list(&[
+///     ComponentValue::tuple(&[
+///         ComponentValue::string("a"),
+///         ComponentValue::option(Some(&ComponentValue::u8(1))),
+///     ]),
+///     ComponentValue::tuple(&[ComponentValue::string("b"), ComponentValue::option(None)]),
+/// ]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ComponentValue {
+    bytes: Vec<u8>,
+}
+
+impl ComponentValue {
+    /// Create a value with the specified raw encoding.
+    pub fn raw(bytes: impl IntoIterator<Item = u8>) -> Self {
+        Self {
+            bytes: bytes.into_iter().collect(),
+        }
+    }
+
+    fn encoded(value: impl Encode) -> Self {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        Self { bytes }
+    }
+
+    /// Returns the encoding of this value.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Create a `bool` value.
+    pub fn bool(value: bool) -> Self {
+        Self::raw([u8::from(value)])
+    }
+
+    /// Create a `u8` value.
+    pub fn u8(value: u8) -> Self {
+        Self::raw([value])
+    }
+
+    /// Create an `s8` value.
+    pub fn s8(value: i8) -> Self {
+        Self::raw([value as u8])
+    }
+
+    /// Create a `u16` value.
+    pub fn u16(value: u16) -> Self {
+        Self::encoded(u32::from(value))
+    }
+
+    /// Create an `s16` value.
+    pub fn s16(value: i16) -> Self {
+        Self::encoded(i32::from(value))
+    }
+
+    /// Create a `u32` value.
+    pub fn u32(value: u32) -> Self {
+        Self::encoded(value)
+    }
+
+    /// Create an `s32` value.
+    pub fn s32(value: i32) -> Self {
+        Self::encoded(value)
+    }
+
+    /// Create a `u64` value.
+    pub fn u64(value: u64) -> Self {
+        Self::encoded(value)
+    }
+
+    /// Create an `s64` value.
+    pub fn s64(value: i64) -> Self {
+        Self::encoded(value)
+    }
+
+    /// Create an `f32` value.
+    ///
+    /// Only the canonical NaN is a valid `f32` value, so any NaN is encoded
+    /// as the canonical NaN.
+    pub fn f32(value: f32) -> Self {
+        if value.is_nan() {
+            Self::encoded(f32::from_bits(0x7fc0_0000))
+        } else {
+            Self::encoded(value)
+        }
+    }
+
+    /// Create an `f64` value.
+    ///
+    /// Only the canonical NaN is a valid `f64` value, so any NaN is encoded
+    /// as the canonical NaN.
+    pub fn f64(value: f64) -> Self {
+        if value.is_nan() {
+            Self::encoded(f64::from_bits(0x7ff8_0000_0000_0000))
+        } else {
+            Self::encoded(value)
+        }
+    }
+
+    /// Create a `char` value, which is encoded as its UTF-8 bytes.
+    pub fn char(value: char) -> Self {
+        let mut buf = [0; 4];
+        Self::raw(value.encode_utf8(&mut buf).bytes())
+    }
+
+    /// Create a `string` value.
+    pub fn string(value: &str) -> Self {
+        Self::encoded(value)
+    }
+
+    /// Create a `record` value from the values of its fields, in order.
+    pub fn record<'a>(fields: impl IntoIterator<Item = &'a ComponentValue>) -> Self {
+        Self::raw(fields.into_iter().flat_map(|f| f.bytes.iter().copied()))
+    }
+
+    /// Create a `tuple` value from the values of its elements, in order.
+    pub fn tuple<'a>(elements: impl IntoIterator<Item = &'a ComponentValue>) -> Self {
+        Self::record(elements)
+    }
+
+    /// Create a `variant` value of the case at index `case`, with the
+    /// `payload` if the case has one.
+    pub fn variant(case: u32, payload: Option<&ComponentValue>) -> Self {
+        let mut ret = Self::encoded(case);
+        if let Some(payload) = payload {
+            ret.bytes.extend_from_slice(&payload.bytes);
+        }
+        ret
+    }
+
+    /// Create a `list` value from its elements.
+    pub fn list<'a, I>(elements: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ComponentValue>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let elements = elements.into_iter();
+        let mut ret = Self::encoded(elements.len());
+        for element in elements {
+            ret.bytes.extend_from_slice(&element.bytes);
+        }
+        ret
+    }
+
+    /// Create a `flags` value from whether each flag is set, in the order
+    /// the flags are declared in the type.
+    ///
+    /// This must yield exactly one item per flag, as the flags are encoded as
+    /// a bit vector whose size depends on the number of flags.
+    pub fn flags(flags: impl IntoIterator<Item = bool>) -> Self {
+        let mut bytes = Vec::new();
+        for (i, set) in flags.into_iter().enumerate() {
+            if i % 8 == 0 {
+                bytes.push(0);
+            }
+            if set {
+                *bytes.last_mut().unwrap() |= 1 << (i % 8);
+            }
+        }
+        Self { bytes }
+    }
+
+    /// Create an `enum` value of the case at index `case`.
+    pub fn enum_case(case: u32) -> Self {
+        Self::encoded(case)
+    }
+
+    /// Create an `option` value.
+    pub fn option(value: Option<&ComponentValue>) -> Self {
+        match value {
+            Some(value) => Self::variant(1, Some(value)),
+            None => Self::variant(0, None),
+        }
+    }
+
+    /// Create a `result` value, where either case has a payload only if the
+    /// type has one for that case.
+    pub fn result(value: Result<Option<&ComponentValue>, Option<&ComponentValue>>) -> Self {
+        match value {
+            Ok(value) => Self::variant(0, value),
+            Err(value) => Self::variant(1, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PrimitiveValType;
+
+    #[test]
+    fn test_value_section() {
+        let mut values = ComponentValueSection::new();
+        values.value(
+            ComponentValType::Type(3),
+            &ComponentValue::record(&[
+                ComponentValue::string("hi"),
+                ComponentValue::variant(2, Some(&ComponentValue::s16(-1))),
+                ComponentValue::list(&[ComponentValue::bool(true), ComponentValue::bool(false)]),
+                ComponentValue::flags([
+                    true, false, false, false, false, false, false, false, true,
+                ]),
+            ]),
+        );
+        values.value(PrimitiveValType::F32, &ComponentValue::f32(f32::NAN));
+
+        let mut encoded = vec![];
+        values.encode(&mut encoded);
+
+        #[rustfmt::skip]
+        assert_eq!(encoded, vec![
+            // LEB128 length of section.
+            19,
+            // Number of values.
+            2,
+            // Type index 3.
+            3,
+            // LEB128 length of the value.
+            10,
+            // The string.
+            2, b'h', b'i',
+            // The variant case and its payload.
+            2, 0x7f,
+            // The list of two bools.
+            2, 1, 0,
+            // The nine flags, as two bytes.
+            0b0000_0001, 0b0000_0001,
+            // An `f32`.
+            0x76,
+            // LEB128 length of the value.
+            4,
+            // The canonical NaN.
+            0x00, 0x00, 0xc0, 0x7f,
+        ]);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{encode_section, Encode, Section, SectionId};
+use std::fmt;
 
 /// An encoder for the memory section.
 ///
@@ -88,6 +89,117 @@ pub struct MemoryType {
     pub page_size_log2: Option<u32>,
 }
 
+impl MemoryType {
+    /// Creates the type of a 32-bit memory with `minimum` pages, and at most
+    /// `maximum` pages if given, using the default page size of 64KiB.
+    ///
+    /// Returns an error if the limits are out of range, or if the memory is
+    /// `shared` without a maximum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wasm_encoder::{MemoryType, MemoryTypeError};
+    ///
+    /// let ty = MemoryType::memory32(1, Some(2), true).unwrap();
+    /// assert!(ty.shared && !ty.memory64);
+    ///
+    /// assert_eq!(
+    ///     MemoryType::memory32(1, None, true),
+    ///     Err(MemoryTypeError::SharedWithoutMaximum),
+    /// );
+    /// ```
+    pub fn memory32(
+        minimum: u64,
+        maximum: Option<u64>,
+        shared: bool,
+    ) -> Result<MemoryType, MemoryTypeError> {
+        let ty = MemoryType {
+            minimum,
+            maximum,
+            memory64: false,
+            shared,
+            page_size_log2: None,
+        };
+        ty.validate()?;
+        Ok(ty)
+    }
+
+    /// Creates the type of a 64-bit memory with `minimum` pages, and at most
+    /// `maximum` pages if given, using the default page size of 64KiB.
+    ///
+    /// Returns an error if the limits are out of range, or if the memory is
+    /// `shared` without a maximum.
+    pub fn memory64(
+        minimum: u64,
+        maximum: Option<u64>,
+        shared: bool,
+    ) -> Result<MemoryType, MemoryTypeError> {
+        let ty = MemoryType {
+            minimum,
+            maximum,
+            memory64: true,
+            shared,
+            page_size_log2: None,
+        };
+        ty.validate()?;
+        Ok(ty)
+    }
+
+    /// Returns this type with a custom page size of 2<sup>`page_size_log2`</sup>
+    /// bytes, which is part of the custom-page-sizes proposal.
+    ///
+    /// Returns an error if the page size isn't supported or if the limits of
+    /// this type are out of range for the new page size.
+    pub fn with_page_size_log2(mut self, page_size_log2: u32) -> Result<Self, MemoryTypeError> {
+        self.page_size_log2 = Some(page_size_log2);
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Returns the largest number of pages a memory of this type can have,
+    /// given whether it's a 64-bit memory and its page size.
+    pub fn max_pages(&self) -> u64 {
+        let page_size_log2 = self.page_size_log2.unwrap_or(16);
+        let bits = if self.memory64 { 64 } else { 32 };
+        let pages = (1_u128 << bits).checked_shr(page_size_log2).unwrap_or(0);
+        u64::try_from(pages).unwrap_or(u64::MAX)
+    }
+
+    /// Checks that the flags and limits of this type form a valid memory
+    /// type.
+    ///
+    /// This performs the same checks on the type itself as validation of a
+    /// module does, without checking whether the proposals the type uses are
+    /// enabled.
+    pub fn validate(&self) -> Result<(), MemoryTypeError> {
+        if let Some(page_size_log2) = self.page_size_log2 {
+            // Currently 2**0 and 2**16 are the only valid page sizes.
+            if page_size_log2 != 0 && page_size_log2 != 16 {
+                return Err(MemoryTypeError::InvalidPageSize { page_size_log2 });
+            }
+        }
+        if let Some(maximum) = self.maximum {
+            if self.minimum > maximum {
+                return Err(MemoryTypeError::MinimumExceedsMaximum {
+                    minimum: self.minimum,
+                    maximum,
+                });
+            }
+        }
+        let max_pages = self.max_pages();
+        for pages in [Some(self.minimum), self.maximum].into_iter().flatten() {
+            if pages > max_pages {
+                return Err(MemoryTypeError::TooLarge { pages, max_pages });
+            }
+        }
+        if self.shared && self.maximum.is_none() {
+            return Err(MemoryTypeError::SharedWithoutMaximum);
+        }
+        Ok(())
+    }
+}
+
 impl Encode for MemoryType {
     fn encode(&self, sink: &mut Vec<u8>) {
         let mut flags = 0;
@@ -113,3 +225,108 @@ impl Encode for MemoryType {
         }
     }
 }
+
+/// An error from building or validating a [`MemoryType`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemoryTypeError {
+    /// The minimum size of the memory is greater than its maximum.
+    MinimumExceedsMaximum {
+        /// The minimum size, in pages.
+        minimum: u64,
+        /// The maximum size, in pages.
+        maximum: u64,
+    },
+    /// A limit of the memory is larger than a memory of its index type and
+    /// page size can be.
+    TooLarge {
+        /// The out-of-range limit, in pages.
+        pages: u64,
+        /// The largest valid limit, in pages.
+        max_pages: u64,
+    },
+    /// The memory is shared but has no maximum size.
+    SharedWithoutMaximum,
+    /// The custom page size of the memory isn't supported.
+    InvalidPageSize {
+        /// The log base 2 of the page size.
+        page_size_log2: u32,
+    },
+}
+
+impl fmt::Display for MemoryTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryTypeError::MinimumExceedsMaximum { minimum, maximum } => write!(
+                f,
+                "minimum memory size of {minimum} pages is greater than the maximum of {maximum} pages"
+            ),
+            MemoryTypeError::TooLarge { pages, max_pages } => write!(
+                f,
+                "memory size of {pages} pages is out of bounds, it must be at most {max_pages} pages"
+            ),
+            MemoryTypeError::SharedWithoutMaximum => {
+                write!(f, "shared memory must have a maximum size")
+            }
+            MemoryTypeError::InvalidPageSize { page_size_log2 } => write!(
+                f,
+                "invalid custom page size of 2**{page_size_log2} bytes, it must be 1 or 65536 bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MemoryTypeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_type_limits() {
+        assert!(MemoryType::memory32(0, Some(65536), false).is_ok());
+        assert_eq!(
+            MemoryType::memory32(0, Some(65537), false),
+            Err(MemoryTypeError::TooLarge {
+                pages: 65537,
+                max_pages: 65536
+            })
+        );
+        assert!(MemoryType::memory64(65537, None, false).is_ok());
+        assert_eq!(
+            MemoryType::memory64(2, Some(1), false),
+            Err(MemoryTypeError::MinimumExceedsMaximum {
+                minimum: 2,
+                maximum: 1
+            })
+        );
+        assert_eq!(
+            MemoryType::memory64(0, None, true),
+            Err(MemoryTypeError::SharedWithoutMaximum)
+        );
+        assert_eq!(
+            MemoryType::memory64(0, Some(u64::MAX), false)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "memory size of {} pages is out of bounds, it must be at most {} pages",
+                u64::MAX,
+                1_u64 << 48
+            )
+        );
+
+        let ty = MemoryType::memory32(0, None, false).unwrap();
+        let ty = MemoryType {
+            maximum: Some(1 << 20),
+            ..ty
+        };
+        assert!(ty.validate().is_err());
+        assert_eq!(
+            ty.with_page_size_log2(0).map(|ty| ty.max_pages()),
+            Ok(1 << 32)
+        );
+        assert_eq!(
+            ty.with_page_size_log2(12),
+            Err(MemoryTypeError::InvalidPageSize { page_size_log2: 12 })
+        );
+    }
+}
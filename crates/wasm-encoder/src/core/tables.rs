@@ -1,4 +1,5 @@
-use crate::{encode_section, ConstExpr, Encode, RefType, Section, SectionId, ValType};
+use crate::{encode_section, ConstExpr, Encode, HeapType, RefType, Section, SectionId, ValType};
+use std::fmt;
 
 /// An encoder for the table section.
 ///
@@ -95,6 +96,96 @@ pub struct TableType {
 }
 
 impl TableType {
+    /// Creates the type of a 32-bit table of `element_type` with `minimum`
+    /// elements, and at most `maximum` elements if given.
+    ///
+    /// Returns an error if the limits are out of range, or if the table is
+    /// `shared` but its element type isn't.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wasm_encoder::{RefType, TableType, TableTypeError};
+    ///
+    /// let ty = TableType::table32(RefType::FUNCREF, 1, None, false).unwrap();
+    /// assert!(!ty.table64);
+    ///
+    /// assert_eq!(
+    ///     TableType::table32(RefType::FUNCREF, 1, None, true),
+    ///     Err(TableTypeError::UnsharedElementType),
+    /// );
+    /// ```
+    pub fn table32(
+        element_type: RefType,
+        minimum: u64,
+        maximum: Option<u64>,
+        shared: bool,
+    ) -> Result<TableType, TableTypeError> {
+        let ty = TableType {
+            element_type,
+            table64: false,
+            minimum,
+            maximum,
+            shared,
+        };
+        ty.validate()?;
+        Ok(ty)
+    }
+
+    /// Creates the type of a 64-bit table of `element_type` with `minimum`
+    /// elements, and at most `maximum` elements if given.
+    ///
+    /// Returns an error if the limits are out of range, or if the table is
+    /// `shared` but its element type isn't.
+    pub fn table64(
+        element_type: RefType,
+        minimum: u64,
+        maximum: Option<u64>,
+        shared: bool,
+    ) -> Result<TableType, TableTypeError> {
+        let ty = TableType {
+            element_type,
+            table64: true,
+            minimum,
+            maximum,
+            shared,
+        };
+        ty.validate()?;
+        Ok(ty)
+    }
+
+    /// Checks that the flags and limits of this type form a valid table
+    /// type.
+    ///
+    /// This performs the same checks on the type itself as validation of a
+    /// module does, without checking whether the proposals the type uses are
+    /// enabled. Whether a concrete element type is shared depends on the
+    /// type it refers to, so shared tables of concrete element types aren't
+    /// rejected.
+    pub fn validate(&self) -> Result<(), TableTypeError> {
+        if let Some(maximum) = self.maximum {
+            if self.minimum > maximum {
+                return Err(TableTypeError::MinimumExceedsMaximum {
+                    minimum: self.minimum,
+                    maximum,
+                });
+            }
+        }
+        if !self.table64 {
+            for size in [Some(self.minimum), self.maximum].into_iter().flatten() {
+                if size > u64::from(u32::MAX) {
+                    return Err(TableTypeError::TooLarge { size });
+                }
+            }
+        }
+        if self.shared {
+            if let HeapType::Abstract { shared: false, .. } = self.element_type.heap_type {
+                return Err(TableTypeError::UnsharedElementType);
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the type used to index this table.
     pub fn index_type(&self) -> ValType {
         if self.table64 {
@@ -127,3 +218,77 @@ impl Encode for TableType {
         }
     }
 }
+
+/// An error from building or validating a [`TableType`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableTypeError {
+    /// The minimum size of the table is greater than its maximum.
+    MinimumExceedsMaximum {
+        /// The minimum size, in elements.
+        minimum: u64,
+        /// The maximum size, in elements.
+        maximum: u64,
+    },
+    /// A limit of a 32-bit table doesn't fit in 32 bits.
+    TooLarge {
+        /// The out-of-range limit, in elements.
+        size: u64,
+    },
+    /// The table is shared but its element type isn't.
+    UnsharedElementType,
+}
+
+impl fmt::Display for TableTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableTypeError::MinimumExceedsMaximum { minimum, maximum } => write!(
+                f,
+                "minimum table size of {minimum} elements is greater than the maximum of {maximum} elements"
+            ),
+            TableTypeError::TooLarge { size } => write!(
+                f,
+                "table size of {size} elements is out of bounds for a 32-bit table"
+            ),
+            TableTypeError::UnsharedElementType => {
+                write!(f, "shared tables must have a shared element type")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TableTypeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_type_limits() {
+        assert!(TableType::table32(RefType::FUNCREF, 0, Some(u32::MAX.into()), false).is_ok());
+        assert_eq!(
+            TableType::table32(RefType::FUNCREF, 1 << 32, None, false),
+            Err(TableTypeError::TooLarge { size: 1 << 32 })
+        );
+        assert!(TableType::table64(RefType::FUNCREF, 1 << 32, None, false).is_ok());
+        assert_eq!(
+            TableType::table64(RefType::FUNCREF, 2, Some(1), false),
+            Err(TableTypeError::MinimumExceedsMaximum {
+                minimum: 2,
+                maximum: 1
+            })
+        );
+
+        let shared_funcref = RefType {
+            nullable: true,
+            heap_type: HeapType::Abstract {
+                shared: true,
+                ty: crate::AbstractHeapType::Func,
+            },
+        };
+        assert!(TableType::table32(shared_funcref, 1, None, true).is_ok());
+        assert_eq!(
+            TableType::table64(RefType::EXTERNREF, 1, None, true),
+            Err(TableTypeError::UnsharedElementType)
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{encode_section, Encode, Section, SectionId};
+use crate::{
+    encode_section, Encode, ExportKind, ExportSection, FuncType, NameMap, Section, SectionId,
+};
+use std::collections::BTreeMap;
+use std::fmt;
 
 /// An encoder for the tag section.
 ///
@@ -46,6 +50,15 @@ impl TagSection {
         self.num_added += 1;
         self
     }
+
+    /// Define an exception tag whose type is `ty`, the function type at
+    /// `func_type_idx`.
+    ///
+    /// Returns an error if `ty` has results, which isn't valid for the type
+    /// of a tag.
+    pub fn exception(&mut self, func_type_idx: u32, ty: &FuncType) -> Result<&mut Self, TagError> {
+        Ok(self.tag(TagType::exception(func_type_idx, ty)?))
+    }
 }
 
 impl Encode for TagSection {
@@ -77,9 +90,247 @@ pub struct TagType {
     pub func_type_idx: u32,
 }
 
+impl TagType {
+    /// Creates the type of an exception tag whose type is `ty`, the function
+    /// type at `func_type_idx`.
+    ///
+    /// Returns an error if `ty` has results, which isn't valid for the type
+    /// of a tag.
+    pub fn exception(func_type_idx: u32, ty: &FuncType) -> Result<TagType, TagError> {
+        if !ty.results().is_empty() {
+            return Err(TagError::Results { func_type_idx });
+        }
+        Ok(TagType {
+            kind: TagKind::Exception,
+            func_type_idx,
+        })
+    }
+}
+
 impl Encode for TagType {
     fn encode(&self, sink: &mut Vec<u8>) {
         sink.push(self.kind as u8);
         self.func_type_idx.encode(sink);
     }
 }
+
+/// A builder of the exception tags defined by a module, along with their
+/// exports and names.
+///
+/// Tags are checked as they're defined, so that producers of exception
+/// handling code get an error where a tag is defined instead of from the
+/// validation of the final module.
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{
+///     ExceptionTags, ExportSection, Module, NameSection, TypeSection, ValType,
+/// };
+///
+/// let mut types = TypeSection::new();
+/// types.ty().function([ValType::I32], []);
+///
+/// let mut tags = ExceptionTags::new(0);
+/// let ty = wasm_encoder::FuncType::new([ValType::I32], []);
+/// let tag = tags.exception(0, &ty).unwrap();
+/// tags.export(tag, "error").unwrap();
+/// tags.name(tag, "error").unwrap();
+///
+/// let mut exports = ExportSection::new();
+/// tags.exports(&mut exports);
+/// let mut names = NameSection::new();
+/// names.tags(&tags.names());
+///
+/// let mut module = Module::new();
+/// module
+///     .section(&types)
+///     .section(tags.section())
+///     .section(&exports)
+///     .section(&names);
+/// let wasm_bytes = module.finish();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ExceptionTags {
+    imported: u32,
+    section: TagSection,
+    exports: Vec<(String, u32)>,
+    names: BTreeMap<u32, String>,
+}
+
+impl ExceptionTags {
+    /// Creates a new builder of the tags of a module which imports
+    /// `imported` tags, which come before the defined tags in the tag index
+    /// space.
+    pub fn new(imported: u32) -> Self {
+        ExceptionTags {
+            imported,
+            ..Self::default()
+        }
+    }
+
+    /// Defines an exception tag whose type is `ty`, the function type at
+    /// `func_type_idx`, and returns its index.
+    ///
+    /// Returns an error if `ty` has results.
+    pub fn exception(&mut self, func_type_idx: u32, ty: &FuncType) -> Result<u32, TagError> {
+        self.section.exception(func_type_idx, ty)?;
+        Ok(self.imported + self.section.len() - 1)
+    }
+
+    /// Exports the tag at `index` as `name`.
+    ///
+    /// Returns an error if `index` isn't the index of an imported or defined
+    /// tag, or if another tag is already exported as `name`.
+    pub fn export(&mut self, index: u32, name: &str) -> Result<&mut Self, TagError> {
+        self.check_index(index)?;
+        if self.exports.iter().any(|(other, _)| other == name) {
+            return Err(TagError::DuplicateExport {
+                name: name.to_string(),
+            });
+        }
+        self.exports.push((name.to_string(), index));
+        Ok(self)
+    }
+
+    /// Names the tag at `index` in the `name` section.
+    ///
+    /// Returns an error if `index` isn't the index of an imported or defined
+    /// tag. Naming a tag again replaces its previous name.
+    pub fn name(&mut self, index: u32, name: &str) -> Result<&mut Self, TagError> {
+        self.check_index(index)?;
+        self.names.insert(index, name.to_string());
+        Ok(self)
+    }
+
+    /// Returns the section defining the tags.
+    pub fn section(&self) -> &TagSection {
+        &self.section
+    }
+
+    /// Appends the exports of tags to `exports`.
+    pub fn exports(&self, exports: &mut ExportSection) {
+        for (name, index) in self.exports.iter() {
+            exports.export(name, ExportKind::Tag, *index);
+        }
+    }
+
+    /// Returns the names of tags, for [`NameSection::tags`](crate::NameSection::tags).
+    pub fn names(&self) -> NameMap {
+        let mut names = NameMap::new();
+        for (index, name) in self.names.iter() {
+            names.append(*index, name);
+        }
+        names
+    }
+
+    fn check_index(&self, index: u32) -> Result<(), TagError> {
+        if index >= self.imported + self.section.len() {
+            return Err(TagError::UnknownTag { index });
+        }
+        Ok(())
+    }
+}
+
+/// An error from defining tags with [`TagSection::exception`] or
+/// [`ExceptionTags`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TagError {
+    /// The function type of a tag has results.
+    Results {
+        /// The index of the function type.
+        func_type_idx: u32,
+    },
+    /// A tag is exported as a name which another tag is already exported as.
+    DuplicateExport {
+        /// The name of the export.
+        name: String,
+    },
+    /// A tag index is out of bounds.
+    UnknownTag {
+        /// The tag index.
+        index: u32,
+    },
+}
+
+impl fmt::Display for TagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagError::Results { func_type_idx } => write!(
+                f,
+                "type {func_type_idx} of tag has results, but tag types must have no results"
+            ),
+            TagError::DuplicateExport { name } => {
+                write!(f, "a tag is already exported as `{name}`")
+            }
+            TagError::UnknownTag { index } => write!(f, "unknown tag {index}"),
+        }
+    }
+}
+
+impl std::error::Error for TagError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, TypeSection, ValType};
+
+    #[test]
+    fn exception_tags() {
+        let mut types = TypeSection::new();
+        types.ty().function([ValType::I32], []);
+        types.ty().function([], [ValType::I32]);
+        let exception = FuncType::new([ValType::I32], []);
+        let returns = FuncType::new([], [ValType::I32]);
+
+        let mut tags = ExceptionTags::new(1);
+        assert_eq!(
+            tags.exception(1, &returns),
+            Err(TagError::Results { func_type_idx: 1 })
+        );
+        assert_eq!(tags.exception(0, &exception), Ok(1));
+        assert_eq!(tags.exception(0, &exception), Ok(2));
+        tags.export(2, "b").unwrap().export(1, "a").unwrap();
+        assert_eq!(
+            tags.export(0, "a").err(),
+            Some(TagError::DuplicateExport {
+                name: "a".to_string()
+            })
+        );
+        assert_eq!(
+            tags.name(3, "c").err(),
+            Some(TagError::UnknownTag { index: 3 })
+        );
+        tags.name(2, "b").unwrap().name(0, "imported").unwrap();
+
+        let mut imports = crate::ImportSection::new();
+        imports.import(
+            "env",
+            "tag",
+            TagType {
+                kind: TagKind::Exception,
+                func_type_idx: 0,
+            },
+        );
+        let mut exports = ExportSection::new();
+        tags.exports(&mut exports);
+        let mut names = crate::NameSection::new();
+        names.tags(&tags.names());
+        let mut module = Module::new();
+        module
+            .section(&types)
+            .section(&imports)
+            .section(tags.section())
+            .section(&exports)
+            .section(&names);
+        let wasm = module.finish();
+        wasmparser::Validator::new().validate_all(&wasm).unwrap();
+
+        let text = wasmprinter::print_bytes(&wasm).unwrap();
+        assert!(
+            text.contains(r#"(tag $b (;2;) (type 0) (param i32))"#),
+            "{text}"
+        );
+        assert!(text.contains(r#"(export "a" (tag 1))"#), "{text}");
+    }
+}
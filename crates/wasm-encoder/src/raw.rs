@@ -28,3 +28,165 @@ impl ComponentSection for RawSection<'_> {
         self.id
     }
 }
+
+#[cfg(feature = "wasmparser")]
+impl<'a> RawSection<'a> {
+    /// Creates a raw section of a core wasm module, checking that `data`
+    /// decodes as a section with the given `id`.
+    ///
+    /// This decodes every item of the section, including the instructions of
+    /// function bodies, so that bytes copied from the wrong range of a binary
+    /// are caught when the section is built rather than by consumers of the
+    /// final binary. The contents of custom sections other than their name,
+    /// and sections with an unknown id, are not checked.
+    ///
+    /// `offset` is the position of `data` within the binary it was copied
+    /// from, which is used for the offsets of errors.
+    pub fn checked(id: u8, data: &'a [u8], offset: usize) -> wasmparser::Result<RawSection<'a>> {
+        check(id, data, offset, MODULE_HEADER)?;
+        Ok(RawSection { id, data })
+    }
+
+    /// Same as [`RawSection::checked`] but for a section of a component.
+    pub fn checked_component(
+        id: u8,
+        data: &'a [u8],
+        offset: usize,
+    ) -> wasmparser::Result<RawSection<'a>> {
+        check(id, data, offset, COMPONENT_HEADER)?;
+        Ok(RawSection { id, data })
+    }
+
+    /// Copies the contents of the section with the given `id` at `range` of
+    /// the binary `wasm`, which is either a core wasm module or a component.
+    ///
+    /// The `range` is that of the contents of the section, excluding its id
+    /// and size, as returned by the `range` method of `wasmparser`'s section
+    /// readers. The copied bytes are checked to decode as the section like
+    /// with [`RawSection::checked`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of `wasm`.
+    pub fn from_range(
+        wasm: &'a [u8],
+        id: u8,
+        range: core::ops::Range<usize>,
+    ) -> wasmparser::Result<RawSection<'a>> {
+        let data = &wasm[range.clone()];
+        if wasm.get(4..8) == Some(&COMPONENT_HEADER[4..]) {
+            RawSection::checked_component(id, data, range.start)
+        } else {
+            RawSection::checked(id, data, range.start)
+        }
+    }
+}
+
+#[cfg(feature = "wasmparser")]
+const MODULE_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+#[cfg(feature = "wasmparser")]
+const COMPONENT_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+
+/// Checks that `data` decodes as the section `id` by parsing a binary with
+/// `header` containing only that section.
+#[cfg(feature = "wasmparser")]
+fn check(id: u8, data: &[u8], offset: usize, header: [u8; 8]) -> wasmparser::Result<()> {
+    use wasmparser::{FromReader, Parser, Payload::*, SectionLimited};
+
+    fn items<'a, T: FromReader<'a>>(section: SectionLimited<'a, T>) -> wasmparser::Result<()> {
+        for item in section {
+            item?;
+        }
+        Ok(())
+    }
+
+    let mut binary = header.to_vec();
+    binary.push(id);
+    data.encode(&mut binary);
+    // Offsets of errors are relative to the start of the binary, so it's
+    // placed such that `data` starts at `offset`.
+    let start = offset.saturating_sub(binary.len() - data.len());
+    for payload in Parser::new(start as u64).parse_all(&binary) {
+        match payload? {
+            TypeSection(s) => items(s)?,
+            ImportSection(s) => items(s)?,
+            FunctionSection(s) => items(s)?,
+            TableSection(s) => items(s)?,
+            MemorySection(s) => items(s)?,
+            TagSection(s) => items(s)?,
+            GlobalSection(s) => items(s)?,
+            ExportSection(s) => items(s)?,
+            ElementSection(s) => items(s)?,
+            DataSection(s) => items(s)?,
+            CodeSectionEntry(body) => {
+                for local in body.get_locals_reader()? {
+                    local?;
+                }
+                let mut reader = body.get_operators_reader()?;
+                while !reader.eof() {
+                    reader.read()?;
+                }
+            }
+            InstanceSection(s) => items(s)?,
+            CoreTypeSection(s) => items(s)?,
+            ComponentInstanceSection(s) => items(s)?,
+            ComponentAliasSection(s) => items(s)?,
+            ComponentTypeSection(s) => items(s)?,
+            ComponentCanonicalSection(s) => items(s)?,
+            ComponentImportSection(s) => items(s)?,
+            ComponentExportSection(s) => items(s)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "wasmparser"))]
+mod tests {
+    use super::*;
+    use crate::{CodeSection, Function, FunctionSection, Instruction, Module, TypeSection};
+
+    #[test]
+    fn from_range() {
+        let mut module = Module::new();
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        module.section(&types);
+        let mut funcs = FunctionSection::new();
+        funcs.function(0);
+        module.section(&funcs);
+        let mut code = CodeSection::new();
+        let mut f = Function::new([]);
+        f.instruction(&Instruction::Nop);
+        f.instruction(&Instruction::End);
+        code.function(&f);
+        module.section(&code);
+        let wasm = module.finish();
+
+        let mut ranges = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            if let Some((id, range)) = payload.unwrap().as_section() {
+                ranges.push((id, range));
+            }
+        }
+        assert_eq!(ranges.len(), 3);
+
+        let mut copy = Module::new();
+        for (id, range) in ranges.iter().cloned() {
+            copy.section(&RawSection::from_range(&wasm, id, range).unwrap());
+        }
+        assert_eq!(copy.finish(), wasm);
+
+        // The type section doesn't decode as a function section, and the code
+        // section doesn't decode when its start is cut off.
+        let (_, types) = ranges[0].clone();
+        assert!(RawSection::from_range(&wasm, 3, types).is_err());
+        let (id, code) = ranges[2].clone();
+        let err = RawSection::from_range(&wasm, id, code.start + 1..code.end).unwrap_err();
+        assert!(err.offset() >= code.start, "{err}");
+
+        assert!(RawSection::checked(0, &[0x03, b'f', b'o', b'o'], 0).is_ok());
+        assert!(RawSection::checked(0, &[0x04, b'f', b'o', b'o'], 0).is_err());
+    }
+}
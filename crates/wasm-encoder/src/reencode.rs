@@ -7,8 +7,12 @@ use crate::CoreTypeEncoder;
 use std::convert::Infallible;
 
 mod component;
+mod remap;
+mod rewrite;
 
 pub use self::component::*;
+pub use self::remap::*;
+pub use self::rewrite::*;
 
 #[allow(missing_docs)] // FIXME
 pub trait Reencode {
@@ -0,0 +1,391 @@
+use crate::reencode::{utils, Error, Reencode};
+use std::collections::HashMap;
+use std::fmt;
+
+/// An index space of a core wasm module.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum IndexSpace {
+    /// The type index space.
+    Type,
+    /// The function index space.
+    Function,
+    /// The table index space.
+    Table,
+    /// The memory index space.
+    Memory,
+    /// The global index space.
+    Global,
+    /// The tag index space.
+    Tag,
+    /// The element segment index space.
+    Element,
+    /// The data segment index space.
+    Data,
+}
+
+impl fmt::Display for IndexSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IndexSpace::Type => "type",
+            IndexSpace::Function => "function",
+            IndexSpace::Table => "table",
+            IndexSpace::Memory => "memory",
+            IndexSpace::Global => "global",
+            IndexSpace::Tag => "tag",
+            IndexSpace::Element => "element",
+            IndexSpace::Data => "data",
+        })
+    }
+}
+
+/// A [`Reencode`] implementation which renumbers the indices of a core wasm
+/// module.
+///
+/// Each index space has its own mapping from old indices to new indices, and
+/// indices which aren't mapped are left as-is. Reencoding a module with this
+/// rewrites every reference to an index, including those in the code, element,
+/// export, and start sections as well as the `name` custom section.
+///
+/// Note that this only renumbers references to items and doesn't reorder or
+/// remove the definitions of the items themselves, which is up to the caller.
+/// Indices may be marked as [removed](IndexRemap::remove), in which case their
+/// entries in the `name` section are dropped and any other reference to them
+/// is an error. The entries of the `name` section are also sorted by their new
+/// index, as required by its encoding.
+///
+/// ```
+/// use wasm_encoder::reencode::{IndexRemap, IndexSpace};
+/// use wasm_encoder::{
+///     CodeSection, ExportKind, ExportSection, Function, FunctionSection, Instruction, Module,
+///     TypeSection,
+/// };
+///
+/// let mut module = Module::new();
+/// let mut types = TypeSection::new();
+/// types.ty().function([], []);
+/// module.section(&types);
+/// let mut funcs = FunctionSection::new();
+/// funcs.function(0);
+/// funcs.function(0);
+/// module.section(&funcs);
+/// let mut exports = ExportSection::new();
+/// exports.export("f", ExportKind::Func, 0);
+/// module.section(&exports);
+/// let mut code = CodeSection::new();
+/// let mut f = Function::new([]);
+/// f.instruction(&Instruction::Call(1));
+/// f.instruction(&Instruction::End);
+/// code.function(&f);
+/// let mut f = Function::new([]);
+/// f.instruction(&Instruction::End);
+/// code.function(&f);
+/// module.section(&code);
+///
+/// // Renumber the functions so that references to function 0 refer to
+/// // function 1 instead, and vice versa.
+/// let mut remap = IndexRemap::new();
+/// remap.map(IndexSpace::Function, 0, 1);
+/// remap.map(IndexSpace::Function, 1, 0);
+/// let wasm = remap.apply(module.as_slice()).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IndexRemap {
+    map: HashMap<(IndexSpace, u32), Option<u32>>,
+    removed_reference: Option<(IndexSpace, u32)>,
+}
+
+impl IndexRemap {
+    /// Creates a new remapping which leaves all indices as-is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps index `old` of `space` to `new`.
+    pub fn map(&mut self, space: IndexSpace, old: u32, new: u32) -> &mut Self {
+        self.map.insert((space, old), Some(new));
+        self
+    }
+
+    /// Marks index `old` of `space` as removed.
+    pub fn remove(&mut self, space: IndexSpace, old: u32) -> &mut Self {
+        self.map.insert((space, old), None);
+        self
+    }
+
+    /// Returns the new index for index `old` of `space`, or `None` if it was
+    /// removed.
+    pub fn get(&self, space: IndexSpace, old: u32) -> Option<u32> {
+        match self.map.get(&(space, old)) {
+            Some(new) => *new,
+            None => Some(old),
+        }
+    }
+
+    /// Reencodes the core wasm module `wasm` with this remapping applied.
+    pub fn apply(&mut self, wasm: &[u8]) -> Result<Vec<u8>, Error<RemovedIndex>> {
+        let mut module = crate::Module::new();
+        self.parse_core_module(&mut module, wasmparser::Parser::new(0), wasm)?;
+        Ok(module.finish())
+    }
+
+    fn index(&mut self, space: IndexSpace, old: u32) -> u32 {
+        match self.get(space, old) {
+            Some(new) => new,
+            None => {
+                // The index hooks of `Reencode` can't fail, so remember the
+                // reference to report it once the module has been reencoded.
+                self.removed_reference.get_or_insert((space, old));
+                old
+            }
+        }
+    }
+
+    fn name_map(
+        &self,
+        space: IndexSpace,
+        map: wasmparser::NameMap<'_>,
+    ) -> wasmparser::Result<crate::NameMap> {
+        let mut names = Vec::new();
+        for naming in map {
+            let naming = naming?;
+            if let Some(index) = self.get(space, naming.index) {
+                names.push((index, naming.name));
+            }
+        }
+        names.sort_by_key(|(index, _)| *index);
+        let mut ret = crate::NameMap::new();
+        for (index, name) in names {
+            ret.append(index, name);
+        }
+        Ok(ret)
+    }
+
+    fn indirect_name_map(
+        &self,
+        space: IndexSpace,
+        map: wasmparser::IndirectNameMap<'_>,
+    ) -> wasmparser::Result<crate::IndirectNameMap> {
+        let mut names = Vec::new();
+        for naming in map {
+            let naming = naming?;
+            if let Some(index) = self.get(space, naming.index) {
+                names.push((index, utils::name_map(naming.names, |i| i)?));
+            }
+        }
+        names.sort_by_key(|(index, _)| *index);
+        let mut ret = crate::IndirectNameMap::new();
+        for (index, names) in names {
+            ret.append(index, &names);
+        }
+        Ok(ret)
+    }
+}
+
+impl Reencode for IndexRemap {
+    type Error = RemovedIndex;
+
+    fn data_index(&mut self, data: u32) -> u32 {
+        self.index(IndexSpace::Data, data)
+    }
+
+    fn element_index(&mut self, element: u32) -> u32 {
+        self.index(IndexSpace::Element, element)
+    }
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        self.index(IndexSpace::Function, func)
+    }
+
+    fn global_index(&mut self, global: u32) -> u32 {
+        self.index(IndexSpace::Global, global)
+    }
+
+    fn memory_index(&mut self, memory: u32) -> u32 {
+        self.index(IndexSpace::Memory, memory)
+    }
+
+    fn table_index(&mut self, table: u32) -> u32 {
+        self.index(IndexSpace::Table, table)
+    }
+
+    fn tag_index(&mut self, tag: u32) -> u32 {
+        self.index(IndexSpace::Tag, tag)
+    }
+
+    fn type_index(&mut self, ty: u32) -> u32 {
+        self.index(IndexSpace::Type, ty)
+    }
+
+    fn parse_core_module(
+        &mut self,
+        module: &mut crate::Module,
+        parser: wasmparser::Parser,
+        data: &[u8],
+    ) -> Result<(), Error<RemovedIndex>> {
+        self.removed_reference = None;
+        utils::parse_core_module(self, module, parser, data)?;
+        match self.removed_reference.take() {
+            Some((space, index)) => Err(Error::UserError(RemovedIndex { space, index })),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_custom_name_subsection(
+        &mut self,
+        names: &mut crate::NameSection,
+        section: wasmparser::Name<'_>,
+    ) -> Result<(), Error<RemovedIndex>> {
+        match section {
+            wasmparser::Name::Function(map) => {
+                names.functions(&self.name_map(IndexSpace::Function, map)?);
+            }
+            wasmparser::Name::Type(map) => {
+                names.types(&self.name_map(IndexSpace::Type, map)?);
+            }
+            wasmparser::Name::Local(map) => {
+                names.locals(&self.indirect_name_map(IndexSpace::Function, map)?);
+            }
+            wasmparser::Name::Label(map) => {
+                names.labels(&self.indirect_name_map(IndexSpace::Function, map)?);
+            }
+            wasmparser::Name::Table(map) => {
+                names.tables(&self.name_map(IndexSpace::Table, map)?);
+            }
+            wasmparser::Name::Memory(map) => {
+                names.memories(&self.name_map(IndexSpace::Memory, map)?);
+            }
+            wasmparser::Name::Global(map) => {
+                names.globals(&self.name_map(IndexSpace::Global, map)?);
+            }
+            wasmparser::Name::Element(map) => {
+                names.elements(&self.name_map(IndexSpace::Element, map)?);
+            }
+            wasmparser::Name::Data(map) => {
+                names.data(&self.name_map(IndexSpace::Data, map)?);
+            }
+            wasmparser::Name::Tag(map) => {
+                names.tags(&self.name_map(IndexSpace::Tag, map)?);
+            }
+            wasmparser::Name::Field(map) => {
+                names.fields(&self.indirect_name_map(IndexSpace::Type, map)?);
+            }
+            wasmparser::Name::Module { .. } | wasmparser::Name::Unknown { .. } => {
+                utils::parse_custom_name_subsection(self, names, section)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The error of an [`IndexRemap`] when an index which was removed is still
+/// referenced outside of the `name` section.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RemovedIndex {
+    /// The index space of the removed index.
+    pub space: IndexSpace,
+    /// The removed index.
+    pub index: u32,
+}
+
+impl fmt::Display for RemovedIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} index {} was removed but is still referenced",
+            self.space, self.index
+        )
+    }
+}
+
+impl std::error::Error for RemovedIndex {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CodeSection, ElementSection, Elements, ExportKind, ExportSection, Function,
+        FunctionSection, IndirectNameMap, Instruction, Module, NameMap, NameSection, RefType,
+        TableSection, TableType, TypeSection,
+    };
+
+    fn module() -> Vec<u8> {
+        let mut module = Module::new();
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        module.section(&types);
+        let mut funcs = FunctionSection::new();
+        funcs.function(0);
+        funcs.function(0);
+        funcs.function(0);
+        module.section(&funcs);
+        let mut tables = TableSection::new();
+        tables.table(TableType {
+            element_type: RefType::FUNCREF,
+            table64: false,
+            minimum: 1,
+            maximum: None,
+            shared: false,
+        });
+        module.section(&tables);
+        let mut exports = ExportSection::new();
+        exports.export("a", ExportKind::Func, 0);
+        module.section(&exports);
+        let mut elements = ElementSection::new();
+        elements.declared(Elements::Functions([1].as_slice().into()));
+        module.section(&elements);
+        let mut code = CodeSection::new();
+        for call in [1, 0, 1] {
+            let mut f = Function::new([]);
+            f.instruction(&Instruction::Call(call));
+            f.instruction(&Instruction::End);
+            code.function(&f);
+        }
+        module.section(&code);
+        let mut names = NameSection::new();
+        let mut functions = NameMap::new();
+        functions.append(0, "a");
+        functions.append(1, "b");
+        functions.append(2, "c");
+        names.functions(&functions);
+        let mut locals = IndirectNameMap::new();
+        let mut a = NameMap::new();
+        a.append(0, "x");
+        locals.append(0, &a);
+        names.locals(&locals);
+        module.section(&names);
+        module.finish()
+    }
+
+    #[test]
+    fn remap() {
+        let mut remap = IndexRemap::new();
+        remap
+            .map(IndexSpace::Function, 0, 1)
+            .map(IndexSpace::Function, 1, 0)
+            .remove(IndexSpace::Function, 2);
+        let wasm = remap.apply(&module()).unwrap();
+
+        let text = wasmprinter::print_bytes(&wasm).unwrap();
+        assert!(text.contains("(export \"a\" (func $a))"), "{text}");
+        assert!(text.contains("(elem (;0;) declare func $b)"), "{text}");
+        assert!(
+            text.contains("(func $b (;0;) (type 0)\n    call $b"),
+            "{text}"
+        );
+        assert!(!text.contains("$c"), "{text}");
+    }
+
+    #[test]
+    fn removed_reference() {
+        let mut remap = IndexRemap::new();
+        remap.remove(IndexSpace::Function, 1);
+        let err = remap.apply(&module()).unwrap_err();
+        match err {
+            Error::UserError(err) => assert_eq!(
+                err.to_string(),
+                "function index 1 was removed but is still referenced"
+            ),
+            err => panic!("unexpected error {err}"),
+        }
+    }
+}
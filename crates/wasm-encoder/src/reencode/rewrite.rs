@@ -0,0 +1,372 @@
+use crate::reencode::{utils, Error, IndexSpace, Reencode};
+use crate::{Encode, Instruction};
+use std::convert::Infallible;
+
+/// Callbacks to rewrite the immediates of instructions with
+/// [`rewrite_function_body`].
+///
+/// Each method receives the offset of the instruction in the original binary
+/// along with the value of an immediate of the instruction, and returns the
+/// value to replace it with. All methods default to leaving the immediate
+/// as-is.
+pub trait RewriteImmediates {
+    /// Rewrites an index into `space` of the instruction at `offset`.
+    ///
+    /// This includes indices within block types and heap types, such as the
+    /// type index of `ref.null $t`.
+    fn index(&mut self, offset: usize, space: IndexSpace, index: u32) -> u32 {
+        let _ = (offset, space);
+        index
+    }
+
+    /// Rewrites the static offset of the memory access to `memory` of the
+    /// instruction at `offset`.
+    ///
+    /// The `memory` is the index of the memory before it's passed to
+    /// [`RewriteImmediates::index`].
+    fn memarg_offset(&mut self, offset: usize, memory: u32, memarg_offset: u64) -> u64 {
+        let _ = (offset, memory);
+        memarg_offset
+    }
+
+    /// Rewrites a relative depth of a branch target of the instruction at
+    /// `offset`.
+    ///
+    /// This includes the targets of `br_table`, the labels of `try_table`
+    /// catch clauses and `resume` handlers, and the depths of the legacy
+    /// `delegate` and `rethrow` instructions.
+    fn branch_depth(&mut self, offset: usize, depth: u32) -> u32 {
+        let _ = offset;
+        depth
+    }
+}
+
+/// Rewrites the immediates of the instructions of `func` with `rewriter` and
+/// adds the resulting function to `code`.
+///
+/// Instructions whose immediates are all left as-is by `rewriter` are copied
+/// verbatim, as are the declarations of locals, so the encoding of anything
+/// which isn't rewritten is preserved exactly. This is useful for applying
+/// relocations or patching modules without disturbing the rest of their
+/// code. Instructions with a rewritten immediate are encoded again, which
+/// uses the minimal width for their LEB128 integers.
+///
+/// This can be combined with [`Reencode`] to rewrite the code of a whole
+/// module:
+///
+/// ```
+/// use std::convert::Infallible;
+/// use wasm_encoder::reencode::{
+///     rewrite_function_body, Error, IndexSpace, Reencode, RewriteImmediates,
+/// };
+/// use wasm_encoder::{CodeSection, Module};
+///
+/// /// Shifts all references to functions in code up by one.
+/// struct ShiftFunctions;
+///
+/// impl RewriteImmediates for ShiftFunctions {
+///     fn index(&mut self, _offset: usize, space: IndexSpace, index: u32) -> u32 {
+///         match space {
+///             IndexSpace::Function => index + 1,
+///             _ => index,
+///         }
+///     }
+/// }
+///
+/// impl Reencode for ShiftFunctions {
+///     type Error = Infallible;
+///
+///     fn parse_function_body(
+///         &mut self,
+///         code: &mut CodeSection,
+///         func: wasmparser::FunctionBody<'_>,
+///     ) -> Result<(), Error> {
+///         rewrite_function_body(self, code, &func)
+///     }
+/// }
+///
+/// fn shift_functions(wasm: &[u8]) -> Result<Vec<u8>, Error> {
+///     let mut module = Module::new();
+///     ShiftFunctions.parse_core_module(&mut module, wasmparser::Parser::new(0), wasm)?;
+///     Ok(module.finish())
+/// }
+/// ```
+pub fn rewrite_function_body<T: ?Sized + RewriteImmediates>(
+    rewriter: &mut T,
+    code: &mut crate::CodeSection,
+    func: &wasmparser::FunctionBody<'_>,
+) -> Result<(), Error> {
+    let bytes = func.as_bytes();
+    let start = func.range().start;
+    let mut reader = func.get_operators_reader()?;
+    let mut body = bytes[..reader.original_position() - start].to_vec();
+    let mut adapter = Adapter {
+        rewriter,
+        offset: 0,
+        changed: false,
+    };
+    while !reader.eof() {
+        let (op, offset) = reader.read_with_offset()?;
+        adapter.offset = offset;
+        adapter.changed = false;
+        let mut inst = adapter.instruction(op)?;
+        adapter.rewrite_branch_depths(&mut inst);
+        if adapter.changed {
+            inst.encode(&mut body);
+        } else {
+            body.extend_from_slice(&bytes[offset - start..reader.original_position() - start]);
+        }
+    }
+    code.raw(&body);
+    Ok(())
+}
+
+/// A [`Reencode`] implementation forwarding the immediates of a single
+/// instruction to a [`RewriteImmediates`] and recording whether any of them
+/// changed.
+struct Adapter<'a, T: ?Sized> {
+    rewriter: &'a mut T,
+    offset: usize,
+    changed: bool,
+}
+
+impl<T: ?Sized + RewriteImmediates> Adapter<'_, T> {
+    fn index(&mut self, space: IndexSpace, index: u32) -> u32 {
+        let new = self.rewriter.index(self.offset, space, index);
+        self.changed |= new != index;
+        new
+    }
+
+    fn depth(&mut self, depth: &mut u32) {
+        let new = self.rewriter.branch_depth(self.offset, *depth);
+        self.changed |= new != *depth;
+        *depth = new;
+    }
+
+    fn rewrite_branch_depths(&mut self, inst: &mut Instruction<'_>) {
+        match inst {
+            Instruction::Br(depth)
+            | Instruction::BrIf(depth)
+            | Instruction::BrOnNull(depth)
+            | Instruction::BrOnNonNull(depth)
+            | Instruction::BrOnCast {
+                relative_depth: depth,
+                ..
+            }
+            | Instruction::BrOnCastFail {
+                relative_depth: depth,
+                ..
+            }
+            | Instruction::Delegate(depth)
+            | Instruction::Rethrow(depth) => self.depth(depth),
+            Instruction::BrTable(targets, default) => {
+                for target in targets.to_mut() {
+                    self.depth(target);
+                }
+                self.depth(default);
+            }
+            Instruction::TryTable(_, catches) => {
+                for catch in catches.to_mut() {
+                    match catch {
+                        crate::Catch::One { label, .. }
+                        | crate::Catch::OneRef { label, .. }
+                        | crate::Catch::All { label }
+                        | crate::Catch::AllRef { label } => self.depth(label),
+                    }
+                }
+            }
+            Instruction::Resume {
+                resume_table: handles,
+                ..
+            }
+            | Instruction::ResumeThrow {
+                resume_table: handles,
+                ..
+            } => {
+                for handle in handles.to_mut() {
+                    match handle {
+                        crate::Handle::OnLabel { label, .. } => self.depth(label),
+                        crate::Handle::OnSwitch { .. } => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<T: ?Sized + RewriteImmediates> Reencode for Adapter<'_, T> {
+    type Error = Infallible;
+
+    fn data_index(&mut self, data: u32) -> u32 {
+        self.index(IndexSpace::Data, data)
+    }
+
+    fn element_index(&mut self, element: u32) -> u32 {
+        self.index(IndexSpace::Element, element)
+    }
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        self.index(IndexSpace::Function, func)
+    }
+
+    fn global_index(&mut self, global: u32) -> u32 {
+        self.index(IndexSpace::Global, global)
+    }
+
+    fn memory_index(&mut self, memory: u32) -> u32 {
+        self.index(IndexSpace::Memory, memory)
+    }
+
+    fn table_index(&mut self, table: u32) -> u32 {
+        self.index(IndexSpace::Table, table)
+    }
+
+    fn tag_index(&mut self, tag: u32) -> u32 {
+        self.index(IndexSpace::Tag, tag)
+    }
+
+    fn type_index(&mut self, ty: u32) -> u32 {
+        self.index(IndexSpace::Type, ty)
+    }
+
+    fn mem_arg(&mut self, arg: wasmparser::MemArg) -> crate::MemArg {
+        let offset = self
+            .rewriter
+            .memarg_offset(self.offset, arg.memory, arg.offset);
+        self.changed |= offset != arg.offset;
+        crate::MemArg {
+            offset,
+            ..utils::mem_arg(self, arg)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CodeSection, ConstExpr, DataSection, Function, FunctionSection, MemArg, MemorySection,
+        MemoryType, Module, TypeSection, ValType,
+    };
+
+    fn module(body: &Function) -> Vec<u8> {
+        let mut module = Module::new();
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        module.section(&types);
+        let mut funcs = FunctionSection::new();
+        funcs.function(0);
+        funcs.function(0);
+        module.section(&funcs);
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: 1,
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        module.section(&memories);
+        let mut code = CodeSection::new();
+        code.function(body);
+        let mut f = Function::new([]);
+        f.instruction(&Instruction::End);
+        code.function(&f);
+        module.section(&code);
+        let mut data = DataSection::new();
+        data.active(0, &ConstExpr::i32_const(0), [1]);
+        module.section(&data);
+        module.finish()
+    }
+
+    /// Rewrites the code of `wasm` with the [`RewriteImmediates`] and leaves
+    /// the rest of it as-is.
+    struct Rewrite<T>(T);
+
+    impl<T: RewriteImmediates> Reencode for Rewrite<T> {
+        type Error = Infallible;
+
+        fn parse_function_body(
+            &mut self,
+            code: &mut CodeSection,
+            func: wasmparser::FunctionBody<'_>,
+        ) -> Result<(), Error> {
+            rewrite_function_body(&mut self.0, code, &func)
+        }
+    }
+
+    fn rewrite(wasm: &[u8], rewriter: impl RewriteImmediates) -> Vec<u8> {
+        let mut module = Module::new();
+        Rewrite(rewriter)
+            .parse_core_module(&mut module, wasmparser::Parser::new(0), wasm)
+            .unwrap();
+        module.finish()
+    }
+
+    struct Identity;
+
+    impl RewriteImmediates for Identity {}
+
+    struct Patch;
+
+    impl RewriteImmediates for Patch {
+        fn index(&mut self, _offset: usize, space: IndexSpace, index: u32) -> u32 {
+            match space {
+                IndexSpace::Function => 1 - index,
+                _ => index,
+            }
+        }
+
+        fn memarg_offset(&mut self, _offset: usize, _memory: u32, offset: u64) -> u64 {
+            offset + 8
+        }
+
+        fn branch_depth(&mut self, _offset: usize, depth: u32) -> u32 {
+            depth + 1
+        }
+    }
+
+    #[test]
+    fn rewrite_immediates() {
+        let mut f = Function::new([(1, ValType::I32)]);
+        f.instruction(&Instruction::Block(crate::BlockType::Empty));
+        f.instruction(&Instruction::Block(crate::BlockType::Empty));
+        f.instruction(&Instruction::Call(0));
+        f.instruction(&Instruction::I32Const(0));
+        f.instruction(&Instruction::I32Load(MemArg {
+            offset: 4,
+            align: 2,
+            memory_index: 0,
+        }));
+        f.instruction(&Instruction::Drop);
+        f.instruction(&Instruction::I32Const(0));
+        f.instruction(&Instruction::BrTable([0].as_slice().into(), 0));
+        f.instruction(&Instruction::End);
+        f.instruction(&Instruction::End);
+        f.instruction(&Instruction::End);
+        let wasm = rewrite(&module(&f), Patch);
+
+        let text = wasmprinter::print_bytes(&wasm).unwrap();
+        assert!(text.contains("call 1"), "{text}");
+        assert!(text.contains("i32.load offset=12"), "{text}");
+        assert!(text.contains("br_table 1 (;@1;) 1 (;@1;)"), "{text}");
+    }
+
+    #[test]
+    fn unchanged_instructions_are_copied() {
+        // An `i32.const 0` with a padded LEB128 immediate, which would be
+        // encoded in a single byte if it were encoded again.
+        let mut f = Function::new([]);
+        f.raw([0x41, 0x80, 0x80, 0x80, 0x80, 0x00, 0x1a]);
+        f.instruction(&Instruction::Call(0));
+        f.instruction(&Instruction::End);
+        let wasm = module(&f);
+        assert_eq!(rewrite(&wasm, Identity), wasm);
+
+        // Rewriting the call keeps the padded constant as-is.
+        let rewritten = rewrite(&wasm, Patch);
+        let padded = [0x41, 0x80, 0x80, 0x80, 0x80, 0x00, 0x1a, 0x10, 0x01];
+        assert!(rewritten.windows(padded.len()).any(|w| w == padded));
+    }
+}
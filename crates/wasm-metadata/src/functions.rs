@@ -0,0 +1,222 @@
+use crate::ModuleNames;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use wasmparser::{
+    CompositeInnerType, ExternalKind, FuncType, FunctionBody, KnownCustom, Linking, Parser,
+    Payload::*, SymbolInfo, TypeRef,
+};
+
+/// Lookup of the functions of a WebAssembly module by their symbolic names.
+///
+/// Names are resolved using the exports of the module first, then the
+/// function names of the `name` section, and finally the symbol table of the
+/// `linking` section of relocatable object files.
+pub struct ModuleFunctions<'a> {
+    types: Vec<Option<FuncType>>,
+    functions: Vec<(u32, Option<FunctionBody<'a>>)>,
+    exports: HashMap<&'a str, u32>,
+    names: ModuleNames<'a>,
+    symbols: HashMap<&'a str, u32>,
+}
+
+/// A function found with [`ModuleFunctions::function_by_name`].
+#[derive(Debug, Clone)]
+pub struct NamedFunction<'a> {
+    /// The index of the function in the function index space.
+    pub index: u32,
+    /// The index of the type of the function.
+    pub type_index: u32,
+    /// The signature of the function.
+    pub ty: FuncType,
+    /// The body of the function, or `None` if the function is imported.
+    pub body: Option<FunctionBody<'a>>,
+}
+
+impl<'a> ModuleFunctions<'a> {
+    /// Parse the functions, and the names they're known by, of the core
+    /// WebAssembly module `wasm`.
+    pub fn new(wasm: &'a [u8]) -> Result<ModuleFunctions<'a>> {
+        let mut ret = ModuleFunctions {
+            types: Vec::new(),
+            functions: Vec::new(),
+            exports: HashMap::new(),
+            names: ModuleNames::empty(),
+            symbols: HashMap::new(),
+        };
+        let mut imported = 0;
+        let mut bodies = 0;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Version { encoding, .. } if encoding != wasmparser::Encoding::Module => {
+                    bail!("function lookup is only supported for core modules")
+                }
+                TypeSection(s) => {
+                    for ty in s.into_iter().flat_map(|g| match g {
+                        Ok(g) => g.into_types().map(Ok).collect::<Vec<_>>(),
+                        Err(e) => vec![Err(e)],
+                    }) {
+                        ret.types.push(match ty?.composite_type.inner {
+                            CompositeInnerType::Func(f) => Some(f),
+                            _ => None,
+                        });
+                    }
+                }
+                ImportSection(s) => {
+                    for import in s {
+                        if let TypeRef::Func(ty) = import?.ty {
+                            ret.functions.push((ty, None));
+                            imported += 1;
+                        }
+                    }
+                }
+                FunctionSection(s) => {
+                    for ty in s {
+                        ret.functions.push((ty?, None));
+                    }
+                }
+                ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            ret.exports.entry(export.name).or_insert(export.index);
+                        }
+                    }
+                }
+                CodeSectionEntry(body) => {
+                    match ret.functions.get_mut(imported + bodies) {
+                        Some(f) => f.1 = Some(body),
+                        None => bail!("more function bodies than declared functions"),
+                    }
+                    bodies += 1;
+                }
+                CustomSection(c) => match c.as_known() {
+                    KnownCustom::Name(_) => {
+                        ret.names = ModuleNames::from_bytes(c.data(), c.data_offset())?;
+                    }
+                    KnownCustom::Linking(reader) => {
+                        for subsection in reader.subsections() {
+                            let Linking::SymbolTable(symbols) = subsection? else {
+                                continue;
+                            };
+                            for symbol in symbols {
+                                if let SymbolInfo::Func {
+                                    index,
+                                    name: Some(name),
+                                    ..
+                                } = symbol?
+                                {
+                                    ret.symbols.entry(name).or_insert(index);
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the function known as `name`, if any.
+    pub fn function_by_name(&self, name: &str) -> Result<Option<NamedFunction<'a>>> {
+        let index = match self.exports.get(name) {
+            Some(index) => Some(*index),
+            None => match self.names.function_index(name)? {
+                Some(index) => Some(index),
+                None => self.symbols.get(name).copied(),
+            },
+        };
+        match index {
+            Some(index) => self.function(index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the function at `index` in the function index space.
+    pub fn function(&self, index: u32) -> Result<NamedFunction<'a>> {
+        let Some((type_index, body)) = self.functions.get(index as usize) else {
+            bail!("function index {index} out of bounds");
+        };
+        let Some(Some(ty)) = self.types.get(*type_index as usize) else {
+            bail!("type index {type_index} of function {index} is not a function type");
+        };
+        Ok(NamedFunction {
+            index,
+            type_index: *type_index,
+            ty: ty.clone(),
+            body: body.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_order() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "imported" (func $imported (param i32)))
+                (func $internal (result i32) i32.const 1)
+                (func $exported (export "foo") (result i32) i32.const 2)
+                (func $shadowed (result i32) i32.const 3)
+                (export "shadowed" (func $exported))
+            )
+            "#,
+        )
+        .unwrap();
+        let functions = ModuleFunctions::new(&wasm).unwrap();
+
+        let f = functions.function_by_name("foo").unwrap().unwrap();
+        assert_eq!(f.index, 2);
+        assert_eq!(f.ty.results().len(), 1);
+        assert!(f.body.is_some());
+
+        // Exports take precedence over the name section.
+        let f = functions.function_by_name("shadowed").unwrap().unwrap();
+        assert_eq!(f.index, 2);
+
+        let f = functions.function_by_name("internal").unwrap().unwrap();
+        assert_eq!(f.index, 1);
+        let mut ops = f.body.unwrap().get_operators_reader().unwrap();
+        assert!(matches!(
+            ops.read().unwrap(),
+            wasmparser::Operator::I32Const { value: 1 }
+        ));
+
+        let f = functions.function_by_name("imported").unwrap().unwrap();
+        assert_eq!(f.index, 0);
+        assert_eq!(f.ty.params().len(), 1);
+        assert!(f.body.is_none());
+
+        assert!(functions.function_by_name("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn linking_symbols() {
+        use wasm_encoder::{LinkingSection, Section, SymbolTable};
+
+        let mut wasm = wat::parse_str("(module (func) (func $named))").unwrap();
+        let mut symbols = SymbolTable::new();
+        symbols.function(0, 0, Some("first"));
+        symbols.function(0, 1, Some("second"));
+        LinkingSection::new()
+            .symbol_table(&symbols)
+            .append_to(&mut wasm);
+        let functions = ModuleFunctions::new(&wasm).unwrap();
+
+        let f = functions.function_by_name("first").unwrap().unwrap();
+        assert_eq!(f.index, 0);
+        assert_eq!(f.type_index, 0);
+        assert!(f.ty.params().is_empty());
+
+        // The name section takes precedence over symbols.
+        let f = functions.function_by_name("named").unwrap().unwrap();
+        assert_eq!(f.index, 1);
+        let f = functions.function_by_name("second").unwrap().unwrap();
+        assert_eq!(f.index, 1);
+    }
+}
@@ -9,10 +9,21 @@ use std::mem;
 use std::ops::Range;
 use wasm_encoder::{ComponentSection as _, ComponentSectionId, Encode, Section};
 use wasmparser::{
-    BinaryReader, ComponentNameSectionReader, KnownCustom, NameSectionReader, Parser, Payload::*,
-    ProducersSectionReader,
+    BinaryReader, ComponentNameSectionReader, Digest, KnownCustom, NameSectionReader, Parser,
+    Payload::*, ProducersSectionReader, Sha256,
 };
 
+mod functions;
+pub use functions::*;
+
+mod oci;
+pub use oci::*;
+
+#[cfg(feature = "signatures")]
+mod signature;
+#[cfg(feature = "signatures")]
+pub use signature::*;
+
 /// A representation of a WebAssembly producers section.
 ///
 /// Spec: <https://github.com/WebAssembly/tool-conventions/blob/main/ProducersSection.md>
@@ -145,6 +156,22 @@ impl Producers {
         section
     }
 
+    /// Serialize into the contents of a producers section, which is the
+    /// inverse of [`Producers::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::new();
+        (self.0.len() as u32).encode(&mut ret);
+        for (fieldname, fieldvalues) in self.0.iter() {
+            fieldname.encode(&mut ret);
+            (fieldvalues.len() as u32).encode(&mut ret);
+            for (name, version) in fieldvalues {
+                name.encode(&mut ret);
+                version.encode(&mut ret);
+            }
+        }
+        ret
+    }
+
     /// Serialize into the raw bytes of a wasm custom section.
     pub fn raw_custom_section(&self) -> Vec<u8> {
         let mut ret = Vec::new();
@@ -155,7 +182,7 @@ impl Producers {
     /// Merge into an existing wasm module. Rewrites the module with this producers section
     /// merged into its existing one, or adds this producers section if none is present.
     pub fn add_to_wasm(&self, input: &[u8]) -> Result<Vec<u8>> {
-        rewrite_wasm(&None, self, None, input)
+        rewrite_wasm(&None, self, &Dependencies::empty(), None, input)
     }
 
     fn display(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
@@ -220,6 +247,10 @@ pub struct AddMetadata {
     /// Add an registry metadata to the registry-metadata section
     #[cfg_attr(feature="clap", clap(long, value_parser = parse_registry_metadata_value, value_name="PATH"))]
     pub registry_metadata: Option<RegistryMetadata>,
+
+    /// Add a dependency to the dependencies section
+    #[cfg_attr(feature="clap", clap(long, value_parser = parse_dependency, value_name="NAME[@VERSION][=HASH]"))]
+    pub dependency: Vec<Dependency>,
 }
 
 #[cfg(feature = "clap")]
@@ -238,6 +269,26 @@ fn parse_registry_metadata_value(s: &str) -> Result<RegistryMetadata> {
     Ok(registry_metadata)
 }
 
+#[cfg(feature = "clap")]
+fn parse_dependency(s: &str) -> Result<Dependency> {
+    let (s, hash) = match s.split_once('=') {
+        Some((s, hash)) => (s, Some(hash.to_owned())),
+        None => (s, None),
+    };
+    let (name, version) = match s.split_once('@') {
+        Some((name, version)) => (name, Some(version.to_owned())),
+        None => (s, None),
+    };
+    if name.is_empty() {
+        anyhow::bail!("expected a dependency name");
+    }
+    Ok(Dependency {
+        name: name.to_owned(),
+        version,
+        hash,
+    })
+}
+
 impl AddMetadata {
     /// Process a WebAssembly binary. Supports both core WebAssembly modules, and WebAssembly
     /// components. The module and component will have, at very least, an empty name and producers
@@ -246,6 +297,7 @@ impl AddMetadata {
         rewrite_wasm(
             &self.name,
             &Producers::from_meta(self),
+            &Dependencies::from_meta(self),
             self.registry_metadata.as_ref(),
             input,
         )
@@ -255,10 +307,12 @@ impl AddMetadata {
 fn rewrite_wasm(
     add_name: &Option<String>,
     add_producers: &Producers,
+    add_dependencies: &Dependencies,
     add_registry_metadata: Option<&RegistryMetadata>,
     input: &[u8],
 ) -> Result<Vec<u8>> {
     let mut producers_found = false;
+    let mut dependencies_found = false;
     let mut names_found = false;
     let mut stack = Vec::new();
     let mut output = Vec::new();
@@ -322,6 +376,13 @@ fn rewrite_wasm(
                         names.section()?.as_custom().append_to(&mut output);
                         continue;
                     }
+                    KnownCustom::Unknown if c.name() == "dependencies" => {
+                        dependencies_found = true;
+                        let mut dependencies = Dependencies::from_bytes(c.data(), 0)?;
+                        dependencies.merge(add_dependencies);
+                        dependencies.section()?.append_to(&mut output);
+                        continue;
+                    }
                     KnownCustom::Unknown if c.name() == "registry-metadata" => {
                         // Pass section through if a new registry metadata isn't provided, otherwise ignore and overwrite with new
                         if add_registry_metadata.is_none() {
@@ -365,6 +426,9 @@ fn rewrite_wasm(
         // Encode into output:
         producers.section().append_to(&mut output);
     }
+    if !dependencies_found && !add_dependencies.is_empty() {
+        add_dependencies.section()?.append_to(&mut output);
+    }
     if add_registry_metadata.is_some() {
         let registry_metadata = wasm_encoder::CustomSection {
             name: Cow::Borrowed("registry-metadata"),
@@ -387,10 +451,15 @@ pub enum Metadata {
         producers: Option<Producers>,
         /// The component's registry metadata section, if any.
         registry_metadata: Option<RegistryMetadata>,
+        /// The component's dependencies section, if any.
+        dependencies: Option<Dependencies>,
         /// All child modules and components inside the component.
         children: Vec<Box<Metadata>>,
         /// Byte range of the module in the parent binary
         range: Range<usize>,
+        /// The SHA-256 digest of the bytes of the component, as
+        /// `sha256:<hex-digest>`.
+        digest: String,
     },
     /// Metadata found inside a WebAssembly module.
     Module {
@@ -400,8 +469,13 @@ pub enum Metadata {
         producers: Option<Producers>,
         /// The module's registry metadata section, if any.
         registry_metadata: Option<RegistryMetadata>,
+        /// The module's dependencies section, if any.
+        dependencies: Option<Dependencies>,
         /// Byte range of the module in the parent binary
         range: Range<usize>,
+        /// The SHA-256 digest of the bytes of the module, as
+        /// `sha256:<hex-digest>`.
+        digest: String,
     },
 }
 
@@ -417,10 +491,10 @@ impl Metadata {
                     if metadata.is_empty() {
                         match encoding {
                             wasmparser::Encoding::Module => {
-                                metadata.push(Metadata::empty_module(0..input.len()))
+                                metadata.push(Metadata::empty_module(input, 0..input.len())?)
                             }
                             wasmparser::Encoding::Component => {
-                                metadata.push(Metadata::empty_component(0..input.len()))
+                                metadata.push(Metadata::empty_component(input, 0..input.len())?)
                             }
                         }
                     }
@@ -428,11 +502,11 @@ impl Metadata {
                 ModuleSection {
                     unchecked_range: range,
                     ..
-                } => metadata.push(Metadata::empty_module(range)),
+                } => metadata.push(Metadata::empty_module(input, range)?),
                 ComponentSection {
                     unchecked_range: range,
                     ..
-                } => metadata.push(Metadata::empty_component(range)),
+                } => metadata.push(Metadata::empty_component(input, range)?),
                 End { .. } => {
                     let finished = metadata.pop().expect("non-empty metadata stack");
                     if metadata.is_empty() {
@@ -475,6 +549,13 @@ impl Metadata {
                             .expect("non-empty metadata stack")
                             .set_registry_metadata(registry);
                     }
+                    KnownCustom::Unknown if c.name() == "dependencies" => {
+                        let dependencies = Dependencies::from_bytes(c.data(), 0)?;
+                        metadata
+                            .last_mut()
+                            .expect("non-empty metadata stack")
+                            .set_dependencies(dependencies);
+                    }
                     _ => {}
                 },
                 _ => {}
@@ -485,23 +566,27 @@ impl Metadata {
         ))
     }
 
-    fn empty_component(range: Range<usize>) -> Self {
-        Metadata::Component {
+    fn empty_component(input: &[u8], range: Range<usize>) -> Result<Self> {
+        Ok(Metadata::Component {
             name: None,
             producers: None,
             registry_metadata: None,
+            dependencies: None,
             children: Vec::new(),
+            digest: digest(input, &range)?,
             range,
-        }
+        })
     }
 
-    fn empty_module(range: Range<usize>) -> Self {
-        Metadata::Module {
+    fn empty_module(input: &[u8], range: Range<usize>) -> Result<Self> {
+        Ok(Metadata::Module {
             name: None,
             producers: None,
             registry_metadata: None,
+            dependencies: None,
+            digest: digest(input, &range)?,
             range,
-        }
+        })
     }
     fn set_name(&mut self, n: &str) {
         match self {
@@ -525,6 +610,12 @@ impl Metadata {
             } => *registry_metadata = Some(r),
         }
     }
+    fn set_dependencies(&mut self, d: Dependencies) {
+        match self {
+            Metadata::Module { dependencies, .. } => *dependencies = Some(d),
+            Metadata::Component { dependencies, .. } => *dependencies = Some(d),
+        }
+    }
     fn push_child(&mut self, child: Self) {
         match self {
             Metadata::Module { .. } => panic!("module shouldnt have children"),
@@ -539,6 +630,7 @@ impl Metadata {
                 name,
                 producers,
                 registry_metadata,
+                dependencies,
                 ..
             } => {
                 if let Some(name) = name {
@@ -552,12 +644,16 @@ impl Metadata {
                 if let Some(registry_metadata) = registry_metadata {
                     registry_metadata.display(f, indent + 4)?;
                 }
+                if let Some(dependencies) = dependencies {
+                    dependencies.display(f, indent + 4)?;
+                }
                 Ok(())
             }
             Metadata::Component {
                 name,
                 producers,
                 registry_metadata,
+                dependencies,
                 children,
                 ..
             } => {
@@ -572,6 +668,9 @@ impl Metadata {
                 if let Some(registry_metadata) = registry_metadata {
                     registry_metadata.display(f, indent + 4)?;
                 }
+                if let Some(dependencies) = dependencies {
+                    dependencies.display(f, indent + 4)?;
+                }
                 for c in children {
                     c.display(f, indent + 4)?;
                 }
@@ -581,6 +680,22 @@ impl Metadata {
     }
 }
 
+/// Returns the digest of the module or component at `range` of `input`.
+fn digest(input: &[u8], range: &Range<usize>) -> Result<String> {
+    let bytes = match input.get(range.clone()) {
+        Some(bytes) => bytes,
+        None => anyhow::bail!("malformed wasm binary, nested module or component out of bounds"),
+    };
+    let mut digest = Sha256::default();
+    digest.update(bytes);
+    let hex = digest
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    Ok(format!("sha256:{hex}"))
+}
+
 impl fmt::Display for Metadata {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.display(f, 0)
@@ -639,6 +754,20 @@ impl<'a> ModuleNames<'a> {
     pub fn get_name(&self) -> Option<&String> {
         self.module_name.as_ref()
     }
+    /// Get the index of the function named `name`, if any
+    pub fn function_index(&self, name: &str) -> Result<Option<u32>> {
+        for n in self.names.iter() {
+            if let wasmparser::Name::Function(m) = n {
+                for naming in m.clone() {
+                    let naming = naming?;
+                    if naming.name == name {
+                        return Ok(Some(naming.index));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
     /// Serialize into [`wasm_encoder::NameSection`].
     fn section(&self) -> Result<wasm_encoder::NameSection> {
         let mut section = wasm_encoder::NameSection::new();
@@ -820,7 +949,13 @@ impl RegistryMetadata {
     /// Merge into an existing wasm module. Rewrites the module with this registry-metadata section
     /// overwriting its existing one, or adds this registry-metadata section if none is present.
     pub fn add_to_wasm(&self, input: &[u8]) -> Result<Vec<u8>> {
-        rewrite_wasm(&None, &Producers::empty(), Some(&self), input)
+        rewrite_wasm(
+            &None,
+            &Producers::empty(),
+            &Dependencies::empty(),
+            Some(&self),
+            input,
+        )
     }
 
     pub fn from_wasm(bytes: &[u8]) -> Result<Option<Self>> {
@@ -1118,6 +1253,178 @@ impl Display for CustomLicense {
     }
 }
 
+/// A representation of a `dependencies` custom section, which records the
+/// artifacts that a WebAssembly binary was built from.
+///
+/// Each entry has a name along with an optional version and hash of the input
+/// artifact, which is enough information for generating a software bill of
+/// materials (SBOM). The section is encoded as JSON, similar to the
+/// `registry-metadata` section.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Dependencies(Vec<Dependency>);
+
+/// A single entry of a [`Dependencies`] section.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The name of the dependency, such as a crate or package name.
+    pub name: String,
+
+    /// The version of the dependency, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// A hash of the input artifact, conventionally in the form
+    /// `algorithm:hex-digest` such as `sha256:...`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl Dependencies {
+    /// Creates an empty dependencies section.
+    pub fn empty() -> Self {
+        Dependencies(Vec::new())
+    }
+
+    /// Indicates if section is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Read the dependencies section from a Wasm binary. Supports both core
+    /// Modules and Components. In the component case, only returns the
+    /// dependencies section in the outer component, ignoring all interior
+    /// components and modules.
+    pub fn from_wasm(bytes: &[u8]) -> Result<Option<Self>> {
+        let mut depth = 0;
+        for payload in Parser::new(0).parse_all(bytes) {
+            let payload = payload?;
+            match payload {
+                ModuleSection { .. } | ComponentSection { .. } => depth += 1,
+                End { .. } => depth -= 1,
+                CustomSection(c) if c.name() == "dependencies" && depth == 0 => {
+                    return Ok(Some(Self::from_bytes(c.data(), 0)?));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read and merge the dependencies sections of a Wasm binary and all of
+    /// the modules and components nested within it.
+    ///
+    /// This is useful for producing a full list of the inputs of a component
+    /// which embeds multiple modules that each have their own dependencies.
+    pub fn from_wasm_recursive(bytes: &[u8]) -> Result<Self> {
+        let mut ret = Self::empty();
+        for payload in Parser::new(0).parse_all(bytes) {
+            if let CustomSection(c) = payload? {
+                if c.name() == "dependencies" {
+                    ret.merge(&Self::from_bytes(c.data(), 0)?);
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Read the dependencies section from a slice of bytes.
+    pub fn from_bytes(bytes: &[u8], offset: usize) -> Result<Self> {
+        let dependencies: Dependencies = serde_json::from_slice(&bytes[offset..])?;
+        Ok(dependencies)
+    }
+
+    /// Add a dependency to this section.
+    ///
+    /// If a dependency with the same name and version is already present then
+    /// its hash is replaced with the hash of `dependency`, if any.
+    pub fn add(&mut self, dependency: Dependency) {
+        let existing = self
+            .0
+            .iter_mut()
+            .find(|d| d.name == dependency.name && d.version == dependency.version);
+        match existing {
+            Some(existing) => {
+                if dependency.hash.is_some() {
+                    existing.hash = dependency.hash;
+                }
+            }
+            None => self.0.push(dependency),
+        }
+    }
+
+    /// Add all dependencies found in another `Dependencies` section. Values in
+    /// `other` take precedence.
+    pub fn merge(&mut self, other: &Self) {
+        for dependency in other.iter() {
+            self.add(dependency.clone());
+        }
+    }
+
+    /// Construct the dependencies specified by [`AddMetadata`]
+    fn from_meta(add: &AddMetadata) -> Self {
+        let mut s = Self::empty();
+        for dependency in add.dependency.iter() {
+            s.add(dependency.clone());
+        }
+        s
+    }
+
+    /// Iterate through all dependencies.
+    pub fn iter(&self) -> impl Iterator<Item = &Dependency> + '_ {
+        self.0.iter()
+    }
+
+    /// Serialize into the raw bytes of a wasm custom section.
+    pub fn raw_custom_section(&self) -> Result<Vec<u8>> {
+        let mut ret = Vec::new();
+        self.section()?.encode(&mut ret);
+        Ok(ret)
+    }
+
+    fn section(&self) -> Result<wasm_encoder::CustomSection<'static>> {
+        Ok(wasm_encoder::CustomSection {
+            name: Cow::Borrowed("dependencies"),
+            data: Cow::Owned(serde_json::to_vec(self)?),
+        })
+    }
+
+    /// Merge into an existing wasm module. Rewrites the module with this
+    /// dependencies section merged into its existing one, or adds this
+    /// dependencies section if none is present.
+    pub fn add_to_wasm(&self, input: &[u8]) -> Result<Vec<u8>> {
+        rewrite_wasm(&None, &Producers::empty(), self, None, input)
+    }
+
+    fn display(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let spaces = std::iter::repeat(" ").take(indent).collect::<String>();
+        writeln!(f, "{spaces}dependencies:")?;
+        for dependency in self.iter() {
+            writeln!(f, "{spaces}    {dependency}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Dependencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(f, 0)
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, "@{version}")?;
+        }
+        if let Some(hash) = &self.hash {
+            write!(f, " ({hash})")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::vec;
@@ -1154,6 +1461,11 @@ mod test {
                 ]),
                 categories: Some(vec!["Tools".to_owned()]),
             }),
+            dependency: vec![Dependency {
+                name: "dep".to_owned(),
+                version: Some("1.0".to_owned()),
+                hash: Some("sha256:00".to_owned()),
+            }],
         };
         let module = add.to_wasm(&module).unwrap();
 
@@ -1163,9 +1475,21 @@ mod test {
                 name,
                 producers,
                 registry_metadata,
+                dependencies,
                 range,
+                ..
             } => {
                 assert_eq!(name, Some("foo".to_owned()));
+                let dependencies = dependencies.expect("some dependencies");
+                assert_eq!(
+                    dependencies.iter().collect::<Vec<_>>(),
+                    [&Dependency {
+                        name: "dep".to_owned(),
+                        version: Some("1.0".to_owned()),
+                        hash: Some("sha256:00".to_owned()),
+                    }]
+                );
+
                 let producers = producers.expect("some producers");
                 assert_eq!(producers.get("language").unwrap().get("bar").unwrap(), "");
                 assert_eq!(
@@ -1215,7 +1539,7 @@ mod test {
                 );
 
                 assert_eq!(range.start, 0);
-                assert_eq!(range.end, 422);
+                assert_eq!(range.end, 488);
             }
             _ => panic!("metadata should be module"),
         }
@@ -1252,6 +1576,11 @@ mod test {
                 ]),
                 categories: Some(vec!["Tools".to_owned()]),
             }),
+            dependency: vec![Dependency {
+                name: "dep".to_owned(),
+                version: Some("1.0".to_owned()),
+                hash: Some("sha256:00".to_owned()),
+            }],
         };
         let component = add.to_wasm(&component).unwrap();
 
@@ -1261,10 +1590,22 @@ mod test {
                 name,
                 producers,
                 registry_metadata,
+                dependencies,
                 children,
                 range,
+                ..
             } => {
                 assert!(children.is_empty());
+                let dependencies = dependencies.expect("some dependencies");
+                assert_eq!(
+                    dependencies.iter().collect::<Vec<_>>(),
+                    [&Dependency {
+                        name: "dep".to_owned(),
+                        version: Some("1.0".to_owned()),
+                        hash: Some("sha256:00".to_owned()),
+                    }]
+                );
+
                 assert_eq!(name, Some("foo".to_owned()));
                 let producers = producers.expect("some producers");
                 assert_eq!(producers.get("language").unwrap().get("bar").unwrap(), "");
@@ -1315,7 +1656,7 @@ mod test {
                 );
 
                 assert_eq!(range.start, 0);
-                assert_eq!(range.end, 432);
+                assert_eq!(range.end, 498);
             }
             _ => panic!("metadata should be component"),
         }
@@ -1335,6 +1676,7 @@ mod test {
                 authors: Some(vec!["Foo".to_owned()]),
                 ..Default::default()
             }),
+            dependency: vec![],
         };
         let module = add.to_wasm(&module).unwrap();
 
@@ -1377,9 +1719,12 @@ mod test {
                         name,
                         producers,
                         registry_metadata,
+                        dependencies,
                         range,
+                        digest,
                     } => {
                         assert_eq!(name, &Some("foo".to_owned()));
+                        assert!(dependencies.is_none());
                         let producers = producers.as_ref().expect("some producers");
                         assert_eq!(producers.get("language").unwrap().get("bar").unwrap(), "");
                         assert_eq!(
@@ -1395,6 +1740,9 @@ mod test {
 
                         assert_eq!(range.start, 10);
                         assert_eq!(range.end, 120);
+                        // The module is embedded as-is, so its digest is the
+                        // same as on its own.
+                        assert_eq!(digest, &super::digest(&module, &(0..module.len())).unwrap());
                     }
                     _ => panic!("child is a module"),
                 }
@@ -1486,6 +1834,17 @@ mod test {
         }
     }
 
+    #[test]
+    fn producers_to_bytes_roundtrip() {
+        let mut producers = Producers::empty();
+        producers.add("language", "Rust", "");
+        producers.add("processed-by", "rustc", "1.80");
+        producers.add("processed-by", "wasm-ld", "18.0");
+
+        let roundtrip = Producers::from_bytes(&producers.to_bytes(), 0).unwrap();
+        assert_eq!(roundtrip.to_string(), producers.to_string());
+    }
+
     #[test]
     fn overwrite_registry_metadata() {
         let wat = "(module)";
@@ -1513,4 +1872,52 @@ mod test {
             _ => panic!("metadata should be module"),
         }
     }
+
+    #[test]
+    fn merge_dependencies() {
+        let dep = |name: &str, version: Option<&str>, hash: Option<&str>| Dependency {
+            name: name.to_owned(),
+            version: version.map(|s| s.to_owned()),
+            hash: hash.map(|s| s.to_owned()),
+        };
+
+        let mut a = Dependencies::empty();
+        a.add(dep("foo", Some("1.0"), None));
+        a.add(dep("bar", None, Some("sha256:01")));
+        let module = a.add_to_wasm(&wat::parse_str("(module)").unwrap()).unwrap();
+
+        let mut b = Dependencies::empty();
+        b.add(dep("foo", Some("1.0"), Some("sha256:02")));
+        b.add(dep("foo", Some("2.0"), None));
+        b.add(dep("bar", None, None));
+        let module = b.add_to_wasm(&module).unwrap();
+
+        let expected = [
+            dep("foo", Some("1.0"), Some("sha256:02")),
+            dep("bar", None, Some("sha256:01")),
+            dep("foo", Some("2.0"), None),
+        ];
+        let dependencies = Dependencies::from_wasm(&module).unwrap().unwrap();
+        assert_eq!(dependencies.iter().cloned().collect::<Vec<_>>(), expected);
+
+        // Dependencies of nested modules are merged into those of the outer
+        // component.
+        let mut c = Dependencies::empty();
+        c.add(dep("baz", None, None));
+        let mut component = wasm_encoder::Component::new();
+        component.section(&wasm_encoder::RawSection {
+            id: ComponentSectionId::CoreModule as u8,
+            data: &module,
+        });
+        let component = c.add_to_wasm(&component.finish()).unwrap();
+        let outer = Dependencies::from_wasm(&component).unwrap().unwrap();
+        assert_eq!(
+            outer.iter().cloned().collect::<Vec<_>>(),
+            [dep("baz", None, None)]
+        );
+        let all = Dependencies::from_wasm_recursive(&component).unwrap();
+        let mut expected = expected.to_vec();
+        expected.push(dep("baz", None, None));
+        assert_eq!(all.iter().cloned().collect::<Vec<_>>(), expected);
+    }
 }
@@ -0,0 +1,167 @@
+use crate::{AddMetadata, Link, LinkType, Metadata, RegistryMetadata};
+use indexmap::IndexMap;
+
+/// Annotation for the human-readable title of an OCI artifact.
+pub const OCI_TITLE: &str = "org.opencontainers.image.title";
+/// Annotation for the description of an OCI artifact.
+pub const OCI_DESCRIPTION: &str = "org.opencontainers.image.description";
+/// Annotation for the authors of an OCI artifact.
+pub const OCI_AUTHORS: &str = "org.opencontainers.image.authors";
+/// Annotation for the SPDX license expression of an OCI artifact.
+pub const OCI_LICENSES: &str = "org.opencontainers.image.licenses";
+/// Annotation for the homepage of an OCI artifact.
+pub const OCI_URL: &str = "org.opencontainers.image.url";
+/// Annotation for the source code repository of an OCI artifact.
+pub const OCI_SOURCE: &str = "org.opencontainers.image.source";
+/// Annotation for the documentation of an OCI artifact.
+pub const OCI_DOCUMENTATION: &str = "org.opencontainers.image.documentation";
+
+/// Link types which have a corresponding OCI annotation.
+const OCI_LINKS: [(LinkType, &str); 3] = [
+    (LinkType::Homepage, OCI_URL),
+    (LinkType::Repository, OCI_SOURCE),
+    (LinkType::Documentation, OCI_DOCUMENTATION),
+];
+
+impl Metadata {
+    /// Returns the [OCI annotations] corresponding to the metadata of the
+    /// outermost module or component.
+    ///
+    /// The name is mapped to `org.opencontainers.image.title` and the fields of
+    /// the registry metadata are mapped to their respective
+    /// `org.opencontainers.image.*` annotations, with multiple authors joined
+    /// by commas. Metadata without a standard annotation, such as categories,
+    /// custom links, and producers, is omitted.
+    ///
+    /// This is the inverse of [`AddMetadata::from_oci_annotations`].
+    ///
+    /// [OCI annotations]: https://github.com/opencontainers/image-spec/blob/main/annotations.md
+    pub fn oci_annotations(&self) -> IndexMap<String, String> {
+        let (name, registry_metadata) = match self {
+            Metadata::Component {
+                name,
+                registry_metadata,
+                ..
+            }
+            | Metadata::Module {
+                name,
+                registry_metadata,
+                ..
+            } => (name, registry_metadata),
+        };
+
+        let mut ret = IndexMap::new();
+        if let Some(name) = name {
+            ret.insert(OCI_TITLE.to_string(), name.clone());
+        }
+        let Some(meta) = registry_metadata else {
+            return ret;
+        };
+        if let Some(description) = &meta.description {
+            ret.insert(OCI_DESCRIPTION.to_string(), description.clone());
+        }
+        if let Some(authors) = &meta.authors {
+            ret.insert(OCI_AUTHORS.to_string(), authors.join(", "));
+        }
+        if let Some(license) = &meta.license {
+            ret.insert(OCI_LICENSES.to_string(), license.clone());
+        }
+        for (ty, annotation) in OCI_LINKS {
+            let link = meta.links.iter().flatten().find(|link| link.ty == ty);
+            if let Some(link) = link {
+                ret.insert(annotation.to_string(), link.value.clone());
+            }
+        }
+        ret
+    }
+}
+
+impl AddMetadata {
+    /// Creates the metadata to add to a module or component from
+    /// [OCI annotations].
+    ///
+    /// This is the inverse of [`Metadata::oci_annotations`]. Annotations which
+    /// don't correspond to any metadata are ignored, and the registry metadata
+    /// is only set if at least one annotation for it is present.
+    ///
+    /// [OCI annotations]: https://github.com/opencontainers/image-spec/blob/main/annotations.md
+    pub fn from_oci_annotations<'a>(
+        annotations: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> AddMetadata {
+        let annotations = annotations.into_iter().collect::<IndexMap<_, _>>();
+        let get = |key: &str| annotations.get(key).map(|s| s.to_string());
+
+        let mut meta = RegistryMetadata {
+            description: get(OCI_DESCRIPTION),
+            authors: get(OCI_AUTHORS).map(|authors| {
+                authors
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            }),
+            license: get(OCI_LICENSES),
+            ..RegistryMetadata::default()
+        };
+        let links = OCI_LINKS
+            .into_iter()
+            .filter_map(|(ty, annotation)| {
+                Some(Link {
+                    ty,
+                    value: get(annotation)?,
+                })
+            })
+            .collect::<Vec<_>>();
+        if !links.is_empty() {
+            meta.links = Some(links);
+        }
+
+        AddMetadata {
+            name: get(OCI_TITLE),
+            registry_metadata: if meta == RegistryMetadata::default() {
+                None
+            } else {
+                Some(meta)
+            },
+            ..AddMetadata::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let annotations = [
+            (OCI_TITLE, "foo"),
+            (OCI_DESCRIPTION, "a component"),
+            (OCI_AUTHORS, "Alice, Bob"),
+            (OCI_LICENSES, "Apache-2.0 WITH LLVM-exception"),
+            (OCI_URL, "https://example.com"),
+            (OCI_SOURCE, "https://example.com/foo.git"),
+            ("org.opencontainers.image.vendor", "ignored"),
+        ];
+        let add = AddMetadata::from_oci_annotations(annotations);
+        let meta = add.registry_metadata.as_ref().unwrap();
+        assert_eq!(
+            meta.get_authors().unwrap(),
+            &["Alice".to_string(), "Bob".to_string()]
+        );
+
+        let component = add
+            .to_wasm(&wat::parse_str("(component)").unwrap())
+            .unwrap();
+        let metadata = Metadata::from_binary(&component).unwrap();
+        let expected = annotations[..annotations.len() - 1]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<IndexMap<_, _>>();
+        assert_eq!(metadata.oci_annotations(), expected);
+
+        let add = AddMetadata::from_oci_annotations([]);
+        assert!(add.name.is_none());
+        assert!(add.registry_metadata.is_none());
+    }
+}
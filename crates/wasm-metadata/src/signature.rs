@@ -0,0 +1,381 @@
+//! Support for signing WebAssembly binaries and verifying their signatures.
+//!
+//! This follows the design of the [WebAssembly signatures] proposal where a
+//! SHA-256 hash of the binary is signed with an Ed25519 key. Signatures are
+//! either embedded in a `signature` custom section at the start of the binary
+//! or stored separately from the binary in a detached signature, which has the
+//! same encoding as the contents of the custom section.
+//!
+//! [WebAssembly signatures]: https://github.com/wasm-signatures/design
+
+use anyhow::{bail, Context, Result};
+use std::ops::Range;
+use wasm_encoder::{Encode, Section};
+use wasmparser::{BinaryReader, Parser, Payload};
+
+/// The name of the custom section holding embedded signatures.
+const SECTION_NAME: &str = "signature";
+
+const SPEC_VERSION: u8 = 0x01;
+const CONTENT_TYPE_WASM: u8 = 0x01;
+const HASH_SHA256: u8 = 0x01;
+const ALG_ED25519: u8 = 0x01;
+
+/// A key used to sign WebAssembly binaries.
+pub struct SigningKey(ed25519_compact::KeyPair);
+
+impl SigningKey {
+    /// Creates a signing key deterministically from a 32-byte seed.
+    pub fn from_seed(seed: [u8; 32]) -> SigningKey {
+        SigningKey(ed25519_compact::KeyPair::from_seed(
+            ed25519_compact::Seed::new(seed),
+        ))
+    }
+
+    /// Parses a PEM-encoded Ed25519 secret key, such as one created with
+    /// `openssl genpkey -algorithm ed25519`.
+    pub fn from_pem(pem: &str) -> Result<SigningKey> {
+        let sk = ed25519_compact::SecretKey::from_pem(pem)
+            .map_err(|e| anyhow::anyhow!("invalid secret key: {e}"))?;
+        Ok(SigningKey(ed25519_compact::KeyPair {
+            pk: sk.public_key(),
+            sk,
+        }))
+    }
+
+    /// Returns the key used to verify signatures created with this key.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.pk)
+    }
+}
+
+/// A public key used to verify the signatures of WebAssembly binaries.
+pub struct VerifyingKey(ed25519_compact::PublicKey);
+
+impl VerifyingKey {
+    /// Parses a PEM-encoded Ed25519 public key, such as one created with
+    /// `openssl pkey -pubout`.
+    pub fn from_pem(pem: &str) -> Result<VerifyingKey> {
+        let pk = ed25519_compact::PublicKey::from_pem(pem)
+            .map_err(|e| anyhow::anyhow!("invalid public key: {e}"))?;
+        Ok(VerifyingKey(pk))
+    }
+
+    /// Returns the PEM encoding of this key.
+    pub fn to_pem(&self) -> String {
+        self.0.to_pem()
+    }
+}
+
+/// The signatures over a WebAssembly binary, as found in an embedded
+/// `signature` custom section or in a detached signature.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Signatures {
+    hashes: Vec<SignedHash>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SignedHash {
+    hash: [u8; 32],
+    signatures: Vec<Signature>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Signature {
+    key_id: Vec<u8>,
+    alg: u8,
+    signature: Vec<u8>,
+}
+
+impl Signatures {
+    /// Reads the signatures embedded in a WebAssembly binary, if any.
+    pub fn from_wasm(bytes: &[u8]) -> Result<Option<Signatures>> {
+        match find_section(bytes)? {
+            Some(range) => Ok(Some(Signatures::from_bytes(&bytes[range], 0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads signatures from the contents of a `signature` custom section or
+    /// from a detached signature.
+    pub fn from_bytes(bytes: &[u8], offset: usize) -> Result<Signatures> {
+        let mut reader = BinaryReader::new(bytes, offset);
+        let version = reader.read_u8()?;
+        if version != SPEC_VERSION {
+            bail!("unsupported signature version {version:#x}");
+        }
+        let content_type = reader.read_u8()?;
+        if content_type != CONTENT_TYPE_WASM {
+            bail!("unsupported signature content type {content_type:#x}");
+        }
+        let hash_fn = reader.read_u8()?;
+        if hash_fn != HASH_SHA256 {
+            bail!("unsupported signature hash function {hash_fn:#x}");
+        }
+        let mut hashes = Vec::new();
+        for _ in 0..reader.read_var_u32()? {
+            let len = reader.read_var_u32()? as usize;
+            let hash = reader
+                .read_bytes(len)?
+                .try_into()
+                .context("invalid SHA-256 hash length")?;
+            let mut signatures = Vec::new();
+            for _ in 0..reader.read_var_u32()? {
+                let len = reader.read_var_u32()? as usize;
+                let key_id = reader.read_bytes(len)?;
+                let alg = reader.read_u8()?;
+                let len = reader.read_var_u32()? as usize;
+                let signature = reader.read_bytes(len)?;
+                signatures.push(Signature {
+                    key_id: key_id.to_vec(),
+                    alg,
+                    signature: signature.to_vec(),
+                });
+            }
+            hashes.push(SignedHash { hash, signatures });
+        }
+        if !reader.eof() {
+            bail!("trailing data at the end of the signature");
+        }
+        Ok(Signatures { hashes })
+    }
+
+    /// Returns the total number of signatures.
+    pub fn len(&self) -> usize {
+        self.hashes.iter().map(|h| h.signatures.len()).sum()
+    }
+
+    /// Indicates if there are no signatures.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Serialize into the contents of a `signature` custom section, which is
+    /// also the encoding of a detached signature.
+    pub fn raw_custom_section(&self) -> Vec<u8> {
+        let mut ret = vec![SPEC_VERSION, CONTENT_TYPE_WASM, HASH_SHA256];
+        self.hashes.len().encode(&mut ret);
+        for hash in self.hashes.iter() {
+            hash.hash[..].encode(&mut ret);
+            hash.signatures.len().encode(&mut ret);
+            for signature in hash.signatures.iter() {
+                signature.key_id[..].encode(&mut ret);
+                ret.push(signature.alg);
+                signature.signature[..].encode(&mut ret);
+            }
+        }
+        ret
+    }
+
+    /// Adds a signature of `hash` created with `key`.
+    fn add(&mut self, hash: [u8; 32], key: &SigningKey) {
+        let signature = Signature {
+            key_id: Vec::new(),
+            alg: ALG_ED25519,
+            signature: key.0.sk.sign(message(&hash), None).to_vec(),
+        };
+        match self.hashes.iter_mut().find(|h| h.hash == hash) {
+            Some(h) => {
+                if !h.signatures.contains(&signature) {
+                    h.signatures.push(signature);
+                }
+            }
+            None => self.hashes.push(SignedHash {
+                hash,
+                signatures: vec![signature],
+            }),
+        }
+    }
+}
+
+/// Signs a WebAssembly binary, returning a new binary with the signature
+/// embedded in a `signature` custom section.
+///
+/// If the binary is already signed then the new signature is added to the
+/// existing ones, unless the binary has changed since it was signed in which
+/// case the stale signatures are replaced.
+pub fn sign(wasm: &[u8], key: &SigningKey) -> Result<Vec<u8>> {
+    let section = find_section(wasm)?;
+    let hash = hash(wasm, section.clone());
+    let mut signatures = match &section {
+        Some(range) => {
+            let mut signatures = Signatures::from_bytes(&wasm[range.clone()], 0)?;
+            signatures.hashes.retain(|h| h.hash == hash);
+            signatures
+        }
+        None => Signatures::default(),
+    };
+    signatures.add(hash, key);
+
+    // The signature section is always placed directly after the header of the
+    // binary so that it can be found and skipped without parsing everything
+    // else.
+    let mut ret = wasm[..8].to_vec();
+    wasm_encoder::CustomSection {
+        name: SECTION_NAME.into(),
+        data: signatures.raw_custom_section().into(),
+    }
+    .append_to(&mut ret);
+    match section {
+        Some(range) => {
+            let start = section_start(wasm, range.clone())?;
+            ret.extend_from_slice(&wasm[8..start]);
+            ret.extend_from_slice(&wasm[range.end..]);
+        }
+        None => ret.extend_from_slice(&wasm[8..]),
+    }
+    Ok(ret)
+}
+
+/// Signs a WebAssembly binary, returning a detached signature.
+///
+/// Any signature embedded in `wasm` is not part of the signed contents, so the
+/// detached signature remains valid if the binary is later signed with an
+/// embedded signature.
+pub fn sign_detached(wasm: &[u8], key: &SigningKey) -> Result<Vec<u8>> {
+    let hash = hash(wasm, find_section(wasm)?);
+    let mut signatures = Signatures::default();
+    signatures.add(hash, key);
+    Ok(signatures.raw_custom_section())
+}
+
+/// Verifies that a WebAssembly binary was signed with `key`.
+///
+/// If `detached` is provided then it's the detached signature created for
+/// `wasm` with [`sign_detached`], otherwise the signatures embedded in `wasm`
+/// are checked.
+pub fn verify(wasm: &[u8], key: &VerifyingKey, detached: Option<&[u8]>) -> Result<()> {
+    let section = find_section(wasm)?;
+    let signatures = match detached {
+        Some(bytes) => Signatures::from_bytes(bytes, 0).context("invalid detached signature")?,
+        None => match &section {
+            Some(range) => Signatures::from_bytes(&wasm[range.clone()], 0)
+                .context("invalid signature section")?,
+            None => bail!("binary has no embedded signature"),
+        },
+    };
+    let hash = hash(wasm, section);
+    let signed = signatures
+        .hashes
+        .iter()
+        .filter(|h| h.hash == hash)
+        .flat_map(|h| h.signatures.iter())
+        .filter(|s| s.alg == ALG_ED25519)
+        .any(|s| {
+            let signature = match ed25519_compact::Signature::from_slice(&s.signature) {
+                Ok(signature) => signature,
+                Err(_) => return false,
+            };
+            key.0.verify(message(&hash), &signature).is_ok()
+        });
+    if !signed {
+        if signatures.hashes.iter().all(|h| h.hash != hash) {
+            bail!("binary has been modified since it was signed");
+        }
+        bail!("binary was not signed with the provided key");
+    }
+    Ok(())
+}
+
+/// Returns the range of the contents of the top-level `signature` custom
+/// section in `wasm`, if any.
+fn find_section(wasm: &[u8]) -> Result<Option<Range<usize>>> {
+    let mut depth = 0;
+    let mut ret = None;
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => depth += 1,
+            Payload::End(_) => {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            Payload::CustomSection(c) if depth == 0 && c.name() == SECTION_NAME => {
+                if ret.is_some() {
+                    bail!("binary has more than one `{SECTION_NAME}` section");
+                }
+                ret = Some(c.data_offset()..c.data_offset() + c.data().len());
+            }
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
+
+/// Returns the offset of the section id byte for the custom section whose
+/// contents are at `range`.
+fn section_start(wasm: &[u8], range: Range<usize>) -> Result<usize> {
+    let mut reader = BinaryReader::new(&wasm[8..], 8);
+    while reader.original_position() < range.start {
+        let start = reader.original_position();
+        reader.read_u8()?;
+        let size = reader.read_var_u32()? as usize;
+        if reader.original_position() + size == range.end {
+            return Ok(start);
+        }
+        reader.read_bytes(size)?;
+    }
+    bail!("failed to find the start of the signature section")
+}
+
+/// Computes the SHA-256 hash of `wasm` excluding the signature section at
+/// `section`, if any.
+fn hash(wasm: &[u8], section: Option<Range<usize>>) -> [u8; 32] {
+    let mut hasher = hmac_sha256::Hash::new();
+    match section {
+        Some(range) => {
+            let start = section_start(wasm, range.clone()).unwrap_or(range.start);
+            hasher.update(&wasm[..start]);
+            hasher.update(&wasm[range.end..]);
+        }
+        None => hasher.update(wasm),
+    }
+    hasher.finalize()
+}
+
+/// The message that's actually signed for a binary with the hash `hash`.
+fn message(hash: &[u8; 32]) -> Vec<u8> {
+    let mut ret = b"wasmsig".to_vec();
+    ret.extend_from_slice(&[SPEC_VERSION, CONTENT_TYPE_WASM, HASH_SHA256]);
+    ret.extend_from_slice(hash);
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let wasm = wat::parse_str("(module (func (export \"f\")))").unwrap();
+        let a = SigningKey::from_seed([1; 32]);
+        let b = SigningKey::from_seed([2; 32]);
+
+        let signed = sign(&wasm, &a).unwrap();
+        verify(&signed, &a.verifying_key(), None).unwrap();
+        assert!(verify(&signed, &b.verifying_key(), None).is_err());
+        assert!(verify(&wasm, &a.verifying_key(), None).is_err());
+
+        // Signing again with another key keeps the first signature.
+        let signed = sign(&signed, &b).unwrap();
+        assert_eq!(Signatures::from_wasm(&signed).unwrap().unwrap().len(), 2);
+        verify(&signed, &a.verifying_key(), None).unwrap();
+        verify(&signed, &b.verifying_key(), None).unwrap();
+        for payload in Parser::new(0).parse_all(&signed) {
+            payload.unwrap();
+        }
+
+        // Detached signatures work for both the unsigned and signed binary.
+        let detached = sign_detached(&wasm, &b).unwrap();
+        verify(&wasm, &b.verifying_key(), Some(&detached)).unwrap();
+        verify(&signed, &b.verifying_key(), Some(&detached)).unwrap();
+        assert!(verify(&wasm, &a.verifying_key(), Some(&detached)).is_err());
+
+        // Modifying the binary invalidates the signatures.
+        let mut modified = signed.clone();
+        *modified.last_mut().unwrap() ^= 1;
+        let err = verify(&modified, &a.verifying_key(), None).unwrap_err();
+        assert!(err.to_string().contains("modified"), "{err}");
+    }
+}
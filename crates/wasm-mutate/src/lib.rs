@@ -120,6 +120,32 @@ pub struct WasmMutate<'wasm> {
 
     #[cfg_attr(feature = "clap", clap(skip = None))]
     info: Option<ModuleInfo<'wasm>>,
+
+    #[cfg_attr(feature = "clap", clap(skip = None))]
+    trace: Option<MutationTrace>,
+
+    #[cfg_attr(feature = "clap", clap(skip = None))]
+    replay: Option<MutationTrace>,
+}
+
+/// A record of the configuration of a call to
+/// [`run`][crate::WasmMutate::run] and of the mutator it chose.
+///
+/// Given the same input Wasm, a trace can be passed to
+/// [`replay`][crate::WasmMutate::replay] to reproduce the same mutations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MutationTrace {
+    /// The RNG seed used to choose which transformation to apply.
+    pub seed: u64,
+    /// Whether only semantics-preserving transformations were performed.
+    pub preserve_semantics: bool,
+    /// Whether only size-reducing transformations were performed.
+    pub reduce: bool,
+    /// The fuel available for the mutation.
+    pub fuel: u64,
+    /// The name of the mutator chosen to mutate the input Wasm, if any was
+    /// applicable.
+    pub mutator: Option<String>,
 }
 
 impl Default for WasmMutate<'_> {
@@ -133,6 +159,8 @@ impl Default for WasmMutate<'_> {
             fuel: u64::MAX,
             rng: None,
             info: None,
+            trace: None,
+            replay: None,
         }
     }
 }
@@ -190,6 +218,28 @@ impl<'wasm> WasmMutate<'wasm> {
         self
     }
 
+    /// Returns the trace of the last call to [`run`][crate::WasmMutate::run],
+    /// if any.
+    pub fn trace(&self) -> Option<&MutationTrace> {
+        self.trace.as_ref()
+    }
+
+    /// Configure this `WasmMutate` to replay the mutation recorded in `trace`.
+    ///
+    /// This overrides the seed, fuel, and other configuration recorded in the
+    /// trace. When run on the same input Wasm, the same mutations are
+    /// generated again, and [`run`][crate::WasmMutate::run] returns an error
+    /// if a different mutator than the one recorded is chosen, which can
+    /// happen if the input Wasm or the version of `wasm-mutate` differ.
+    pub fn replay(&mut self, trace: &MutationTrace) -> &mut Self {
+        self.seed = trace.seed;
+        self.preserve_semantics = trace.preserve_semantics;
+        self.reduce = trace.reduce;
+        self.fuel = trace.fuel;
+        self.replay = Some(trace.clone());
+        self
+    }
+
     pub(crate) fn consume_fuel(&mut self, qt: u64) -> Result<()> {
         if qt > self.fuel {
             log::info!("Out of fuel");
@@ -248,6 +298,8 @@ impl<'wasm> WasmMutate<'wasm> {
                 continue;
             }
             log::debug!("attempting to mutate with `{}`", m.name());
+            self.check_replay(Some(&m.name()))?;
+            self.trace.as_mut().unwrap().mutator = Some(m.name().into_owned());
             match m.mutate(self) {
                 Ok(iter) => {
                     log::debug!("mutator `{}` succeeded", m.name());
@@ -260,12 +312,37 @@ impl<'wasm> WasmMutate<'wasm> {
             }
         }
 
+        self.check_replay(None)?;
         Err(Error::no_mutations_applicable())
     }
 
+    /// Checks that `mutator` is the mutator recorded in the trace being
+    /// replayed, if any.
+    fn check_replay(&self, mutator: Option<&str>) -> Result<()> {
+        let expected = match &self.replay {
+            Some(replay) => replay.mutator.as_deref(),
+            None => return Ok(()),
+        };
+        if expected == mutator {
+            return Ok(());
+        }
+        Err(Error::other(format!(
+            "replay diverged from the trace: expected mutator `{}` but chose `{}`",
+            expected.unwrap_or("<none>"),
+            mutator.unwrap_or("<none>"),
+        )))
+    }
+
     fn setup(&mut self, input_wasm: &'wasm [u8]) -> Result<()> {
         self.info = Some(ModuleInfo::new(input_wasm)?);
         self.rng = Some(SmallRng::seed_from_u64(self.seed));
+        self.trace = Some(MutationTrace {
+            seed: self.seed,
+            preserve_semantics: self.preserve_semantics,
+            reduce: self.reduce,
+            fuel: self.fuel,
+            mutator: None,
+        });
         Ok(())
     }
 
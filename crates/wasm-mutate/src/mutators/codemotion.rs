@@ -15,8 +15,12 @@
 //! ];
 //! ```
 
+pub mod block_wrapping;
+pub mod br_if_to_if;
 pub mod if_complement;
+pub mod if_to_br_if;
 pub mod ir;
+pub mod loop_rotation;
 pub mod loop_unrolling;
 
 use self::ir::parse_context::Ast;
@@ -25,7 +29,9 @@ use crate::{
     module::map_type,
     mutators::{
         codemotion::{
-            if_complement::IfComplementMutator, ir::AstBuilder, loop_unrolling::LoopUnrollMutator,
+            block_wrapping::BlockWrapMutator, br_if_to_if::BrIfToIfMutator,
+            if_complement::IfComplementMutator, if_to_br_if::IfToBrIfMutator, ir::AstBuilder,
+            loop_rotation::LoopRotationMutator, loop_unrolling::LoopUnrollMutator,
         },
         OperatorAndByteOffset,
     },
@@ -95,9 +101,11 @@ impl CodemotionMutator {
 
             match filtered.choose(config.rng()) {
                 Some(choosen_mutator) => {
+                    let function_index = config.info().num_imported_functions() + fidx;
                     let newfunc = choosen_mutator.mutate(
                         config,
                         &ast,
+                        function_index,
                         &self.copy_locals(reader)?,
                         &operators,
                         config.info().raw_sections[original_code_section].data,
@@ -114,10 +122,14 @@ impl CodemotionMutator {
 /// Trait to be implemented by all code motion mutators
 pub trait AstMutator {
     /// Transform the function AST in order to generate a new Wasm module
+    ///
+    /// The `function_index` is the index of the function being mutated in the
+    /// function index space, including imported functions.
     fn mutate<'a>(
         &self,
         config: &'a mut WasmMutate,
         ast: &Ast,
+        function_index: u32,
         locals: &[(u32, ValType)],
         operators: &Vec<OperatorAndByteOffset>,
         input_wasm: &'a [u8],
@@ -136,7 +148,11 @@ impl Mutator for CodemotionMutator {
         // Initialize mutators
         let mutators: Vec<Box<dyn AstMutator>> = vec![
             Box::new(IfComplementMutator),
-            Box::new(LoopUnrollMutator), // Add the other here
+            Box::new(LoopUnrollMutator),
+            Box::new(BlockWrapMutator),
+            Box::new(IfToBrIfMutator),
+            Box::new(BrIfToIfMutator),
+            Box::new(LoopRotationMutator), // Add the other here
         ];
 
         let (newfunc, function_to_mutate) = self.random_mutate(config, &mutators)?;
@@ -694,4 +710,164 @@ mod tests {
             1,
         );
     }
+
+    #[test]
+    fn test_block_wrapping() {
+        test_motion_mutator(
+            r#"
+        (module
+            (func (export "exported_func") (param i32) (result i32)
+                block
+                    block
+                        local.get 0
+                        br_if 1
+                    end
+                end
+                local.get 0
+            )
+        )
+        "#,
+            r#"
+        (module
+            (func (export "exported_func") (param i32) (result i32)
+                block
+                    block
+                        block
+                            local.get 0
+                            br_if 2
+                        end
+                    end
+                end
+                local.get 0
+            )
+        )
+        "#,
+            0,
+        );
+    }
+
+    #[test]
+    fn test_if_to_br_if() {
+        test_motion_mutator(
+            r#"
+        (module
+            (func (export "exported_func") (param i32) (result i32)
+                local.get 0
+                if (result i32)
+                    i32.const 50
+                else
+                    i32.const 41
+                end
+            )
+        )
+        "#,
+            r#"
+        (module
+            (func (export "exported_func") (param i32) (result i32)
+                (local i32)
+                local.get 0
+                local.set 1
+                block (result i32)
+                    block
+                        local.get 1
+                        br_if 0
+                        i32.const 41
+                        br 1
+                    end
+                    i32.const 50
+                end
+            )
+        )
+        "#,
+            0,
+        );
+    }
+
+    #[test]
+    fn test_br_if_to_if() {
+        test_motion_mutator(
+            r#"
+        (module
+            (func (export "exported_func") (param i32)
+                block
+                    local.get 0
+                    br_if 0
+                    nop
+                end
+            )
+        )
+        "#,
+            r#"
+        (module
+            (func (export "exported_func") (param i32)
+                block
+                    local.get 0
+                    if
+                        br 1
+                    end
+                    nop
+                end
+            )
+        )
+        "#,
+            0,
+        );
+    }
+
+    #[test]
+    fn test_loop_rotation() {
+        test_motion_mutator(
+            r#"
+        (module
+            (func (export "exported_func") (param i32)
+                loop
+                    block
+                        local.get 0
+                        br_if 0
+                        local.get 0
+                        br_if 2
+                    end
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.tee 0
+                    br_if 0
+                end
+            )
+        )
+        "#,
+            r#"
+        (module
+            (func (export "exported_func") (param i32)
+                block
+                    local.get 0
+                    br_if 0
+                    local.get 0
+                    br_if 1
+                end
+                block
+                    loop
+                        block
+                            local.get 0
+                            i32.const 1
+                            i32.sub
+                            local.tee 0
+                            br_if 0
+                            br 2
+                        end
+                        block
+                            local.get 0
+                            br_if 0
+                            local.get 0
+                            br_if 3
+                        end
+                        br 0
+                    end
+                end
+            )
+        )
+        "#,
+            0,
+        );
+    }
 }
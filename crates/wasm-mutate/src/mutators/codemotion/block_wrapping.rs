@@ -0,0 +1,126 @@
+//! This mutator selects a random `block` or `loop` construction in a function
+//! and wraps it in a redundant `block` of the same type.
+//! The branches inside the construction that jump out of it are adjusted to
+//! skip the new `block` label, so the semantics of the input Wasm is preserved.
+use rand::prelude::SliceRandom;
+use wasm_encoder::{Function, Instruction, ValType};
+
+use crate::{
+    module::map_block_type,
+    mutators::{
+        codemotion::{
+            ir::{
+                parse_context::{Ast, Node},
+                write_remapping_depths, AstWriter,
+            },
+            AstMutator,
+        },
+        OperatorAndByteOffset,
+    },
+    WasmMutate,
+};
+
+/// This mutator selects a random `block` or `loop` construction in a function
+/// and wraps it in a redundant `block` of the same type.
+pub struct BlockWrapMutator;
+
+struct BlockWrapWriter {
+    node_to_mutate: usize,
+}
+
+impl BlockWrapWriter {
+    /// Writes the construction at `nodeidx` wrapped in a new block
+    fn wrap<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<()> {
+        let nodes = ast.get_nodes();
+        let (ty, range) = match &nodes[nodeidx] {
+            Node::Block { ty, range, .. } | Node::Loop { ty, range, .. } => (ty, range),
+            _ => unreachable!("Invalid node passed as a block or loop to wrap"),
+        };
+        // The parameters of the construction, if any, are forwarded by the
+        // new block since it has the same type
+        newfunc.instruction(&Instruction::Block(map_block_type(*ty)?));
+        write_remapping_depths(
+            operators,
+            range.start..range.end + 1,
+            newfunc,
+            input_wasm,
+            |depth| depth + 1,
+        )?;
+        newfunc.instruction(&Instruction::End);
+        Ok(())
+    }
+}
+
+impl AstWriter for BlockWrapWriter {
+    fn write_loop<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        body: &[usize],
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+        ty: &wasmparser::BlockType,
+    ) -> crate::Result<()> {
+        if self.node_to_mutate == nodeidx {
+            self.wrap(ast, nodeidx, newfunc, operators, input_wasm)
+        } else {
+            self.write_loop_default(ast, nodeidx, body, newfunc, operators, input_wasm, ty)
+        }
+    }
+
+    fn write_block<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        body: &[usize],
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+        ty: &wasmparser::BlockType,
+    ) -> crate::Result<()> {
+        if self.node_to_mutate == nodeidx {
+            self.wrap(ast, nodeidx, newfunc, operators, input_wasm)
+        } else {
+            self.write_block_default(ast, nodeidx, body, newfunc, operators, input_wasm, ty)
+        }
+    }
+}
+
+impl AstMutator for BlockWrapMutator {
+    fn can_mutate<'a>(&self, _config: &crate::WasmMutate, ast: &Ast) -> bool {
+        !ast.get_blocks().is_empty() || !ast.get_loops().is_empty()
+    }
+
+    fn mutate<'a>(
+        &self,
+        config: &'a mut WasmMutate,
+        ast: &Ast,
+        _function_index: u32,
+        locals: &[(u32, ValType)],
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<Function> {
+        let mut newfunc = Function::new(locals.to_vec());
+        let candidates = ast
+            .get_blocks()
+            .iter()
+            .chain(ast.get_loops())
+            .collect::<Vec<_>>();
+        let node_index = candidates.choose(config.rng()).expect(
+            "This mutator should check first if the AST contains at least one block or loop",
+        );
+        let writer = BlockWrapWriter {
+            node_to_mutate: **node_index,
+        };
+        writer.write(ast, ast.get_root(), &mut newfunc, operators, input_wasm)?;
+        Ok(newfunc)
+    }
+}
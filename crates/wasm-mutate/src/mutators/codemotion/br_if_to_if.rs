@@ -0,0 +1,138 @@
+//! This mutator selects a random `br_if` instruction in a function and replaces
+//! it by an `if` construction containing an unconditional `br`.
+//!
+//! ```wat
+//! br_if $l
+//! ;; is written as
+//! if
+//!     br $l
+//! end
+//! ```
+//!
+//! Since the `if` has no result, this mutator only works on `br_if`
+//! instructions whose target label doesn't expect any value.
+use rand::prelude::SliceRandom;
+use wasm_encoder::{BlockType as EncoderBlockType, Function, Instruction, ValType};
+use wasmparser::{BlockType, Operator};
+
+use crate::{
+    mutators::{
+        codemotion::{
+            ir::{parse_context::Ast, AstWriter},
+            AstMutator,
+        },
+        OperatorAndByteOffset,
+    },
+    WasmMutate,
+};
+use std::ops::Range;
+
+/// This mutator selects a random `br_if` instruction in a function and replaces
+/// it by an `if` construction containing an unconditional `br`.
+pub struct BrIfToIfMutator;
+
+struct BrIfToIfWriter {
+    /// Index of the `br_if` in the instructions stream
+    br_if_to_mutate: usize,
+    relative_depth: u32,
+}
+
+impl AstWriter for BrIfToIfWriter {
+    fn write_code<'a>(
+        &self,
+        _ast: &Ast,
+        _nodeidx: usize,
+        range: Range<usize>,
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<()> {
+        if !range.contains(&self.br_if_to_mutate) {
+            let piece_of_code = &input_wasm[operators[range.start].1..operators[range.end].1];
+            newfunc.raw(piece_of_code.to_vec());
+            return Ok(());
+        }
+        let before = &input_wasm[operators[range.start].1..operators[self.br_if_to_mutate].1];
+        newfunc.raw(before.to_vec());
+        newfunc.instruction(&Instruction::If(EncoderBlockType::Empty));
+        // The `if` adds one more label to skip
+        newfunc.instruction(&Instruction::Br(self.relative_depth + 1));
+        newfunc.instruction(&Instruction::End);
+        let after = &input_wasm[operators[self.br_if_to_mutate + 1].1..operators[range.end].1];
+        newfunc.raw(after.to_vec());
+        Ok(())
+    }
+}
+
+impl BrIfToIfMutator {
+    /// Returns the `br_if` instructions, with their relative depths, whose
+    /// target labels don't expect any value
+    ///
+    /// Branches to the function body label are not considered.
+    fn get_candidate_br_ifs(
+        &self,
+        ast: &Ast,
+        operators: &[OperatorAndByteOffset],
+    ) -> Vec<(usize, u32)> {
+        let mut candidates = vec![];
+        let mut br_ifs = ast.get_br_ifs().iter().peekable();
+        // Whether each enclosing label expects no values when branched to
+        let mut frames = vec![];
+        for (idx, (op, _)) in operators.iter().enumerate() {
+            let next = match br_ifs.peek() {
+                Some(next) => **next,
+                None => break,
+            };
+            match op {
+                Operator::Block { blockty } | Operator::If { blockty } => {
+                    frames.push(matches!(blockty, BlockType::Empty))
+                }
+                Operator::Loop { blockty } => {
+                    frames.push(!matches!(blockty, BlockType::FuncType(_)))
+                }
+                Operator::End => {
+                    frames.pop();
+                }
+                Operator::BrIf { relative_depth } if idx == next => {
+                    br_ifs.next();
+                    let relative_depth = *relative_depth;
+                    if (relative_depth as usize) < frames.len()
+                        && frames[frames.len() - 1 - relative_depth as usize]
+                    {
+                        candidates.push((idx, relative_depth));
+                    }
+                }
+                _ => {}
+            }
+        }
+        candidates
+    }
+}
+
+impl AstMutator for BrIfToIfMutator {
+    fn can_mutate<'a>(&self, _config: &crate::WasmMutate, ast: &Ast) -> bool {
+        !ast.get_br_ifs().is_empty()
+    }
+
+    fn mutate<'a>(
+        &self,
+        config: &'a mut WasmMutate,
+        ast: &Ast,
+        _function_index: u32,
+        locals: &[(u32, ValType)],
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<Function> {
+        let mut newfunc = Function::new(locals.to_vec());
+        let candidates = self.get_candidate_br_ifs(ast, operators);
+        let (br_if_index, relative_depth) = *candidates
+            .choose(config.rng())
+            .ok_or_else(crate::Error::no_mutations_applicable)?;
+        let writer = BrIfToIfWriter {
+            br_if_to_mutate: br_if_index,
+            relative_depth,
+        };
+        writer.write(ast, ast.get_root(), &mut newfunc, operators, input_wasm)?;
+        Ok(newfunc)
+    }
+}
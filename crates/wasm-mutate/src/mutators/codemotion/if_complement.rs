@@ -113,6 +113,7 @@ impl AstMutator for IfComplementMutator {
         &self,
         config: &'a mut WasmMutate,
         ast: &Ast,
+        _function_index: u32,
         locals: &[(u32, ValType)],
         operators: &Vec<OperatorAndByteOffset>,
         input_wasm: &'a [u8],
@@ -0,0 +1,187 @@
+//! This mutator selects a random `if` construction in a function and replaces
+//! it by two nested blocks and a `br_if`.
+//!
+//! The condition of the `if` is saved in a new local and the construction is
+//! written as:
+//!
+//! ```wat
+//! local.set $c
+//! block (type)
+//!     block
+//!         local.get $c
+//!         br_if 0
+//!         ;; alternative
+//!         br 1
+//!     end
+//!     ;; consequent
+//! end
+//! ```
+//!
+//! The branches inside the alternative are adjusted to the new nesting, so the
+//! semantics of the input Wasm is preserved.
+use rand::prelude::SliceRandom;
+use wasm_encoder::{BlockType as EncoderBlockType, Function, Instruction, ValType};
+use wasmparser::{BlockType, Operator};
+
+use crate::{
+    module::{map_block_type, TypeInfo},
+    mutators::{
+        codemotion::{
+            ir::{
+                parse_context::{Ast, Node},
+                write_remapping_depths, AstWriter,
+            },
+            AstMutator,
+        },
+        OperatorAndByteOffset,
+    },
+    WasmMutate,
+};
+
+/// This mutator selects a random `if` construction in a function and replaces
+/// it by two nested blocks and a `br_if`.
+pub struct IfToBrIfMutator;
+
+struct IfToBrIfWriter {
+    if_to_mutate: usize,
+    /// Index of the new local holding the condition of the `if`
+    condition_local: u32,
+}
+
+impl IfToBrIfWriter {
+    fn write_br_if<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<()> {
+        let nodes = ast.get_nodes();
+        let (ty, range) = match &nodes[nodeidx] {
+            Node::IfElse { ty, range, .. } => (ty, range),
+            _ => unreachable!("Invalid node passed as an if"),
+        };
+        // Find the `else` of this `if`, skipping the nested constructions
+        let mut depth = 0;
+        let mut else_index = None;
+        for idx in range.start + 1..range.end {
+            match operators[idx].0 {
+                Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => depth += 1,
+                Operator::End => depth -= 1,
+                Operator::Else if depth == 0 => {
+                    else_index = Some(idx);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let then_end = else_index.unwrap_or(range.end);
+
+        newfunc.instruction(&Instruction::LocalSet(self.condition_local));
+        newfunc.instruction(&Instruction::Block(map_block_type(*ty)?));
+        newfunc.instruction(&Instruction::Block(EncoderBlockType::Empty));
+        newfunc.instruction(&Instruction::LocalGet(self.condition_local));
+        newfunc.instruction(&Instruction::BrIf(0));
+        if let Some(else_index) = else_index {
+            write_remapping_depths(
+                operators,
+                else_index + 1..range.end,
+                newfunc,
+                input_wasm,
+                |depth| depth + 1,
+            )?;
+        }
+        newfunc.instruction(&Instruction::Br(1));
+        newfunc.instruction(&Instruction::End);
+        write_remapping_depths(
+            operators,
+            range.start + 1..then_end,
+            newfunc,
+            input_wasm,
+            |depth| depth,
+        )?;
+        newfunc.instruction(&Instruction::End);
+        Ok(())
+    }
+}
+
+impl AstWriter for IfToBrIfWriter {
+    fn write_if_else<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        then: &[usize],
+        alternative: &Option<Vec<usize>>,
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+        ty: &BlockType,
+    ) -> crate::Result<()> {
+        if self.if_to_mutate == nodeidx {
+            self.write_br_if(ast, nodeidx, newfunc, operators, input_wasm)
+        } else {
+            self.write_if_else_default(
+                ast,
+                nodeidx,
+                then,
+                alternative,
+                newfunc,
+                operators,
+                input_wasm,
+                ty,
+            )
+        }
+    }
+}
+
+impl IfToBrIfMutator {
+    /// Returns the indexes of the if nodes without parameters, which can be
+    /// written as blocks without a function type
+    fn get_candidate_ifs(&self, ast: &Ast) -> Vec<usize> {
+        let nodes = ast.get_nodes();
+        ast.get_ifs()
+            .iter()
+            .copied()
+            .filter(|idx| match &nodes[*idx] {
+                Node::IfElse { ty, .. } => !matches!(ty, BlockType::FuncType(_)),
+                _ => unreachable!("Invalid if node"),
+            })
+            .collect()
+    }
+}
+
+impl AstMutator for IfToBrIfMutator {
+    fn can_mutate<'a>(&self, _config: &crate::WasmMutate, ast: &Ast) -> bool {
+        !self.get_candidate_ifs(ast).is_empty()
+    }
+
+    fn mutate<'a>(
+        &self,
+        config: &'a mut WasmMutate,
+        ast: &Ast,
+        function_index: u32,
+        locals: &[(u32, ValType)],
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<Function> {
+        let num_params = match config.info().get_functype_idx(function_index) {
+            TypeInfo::Func(ty) => ty.params.len() as u32,
+        };
+        let condition_local = num_params + locals.iter().map(|(count, _)| count).sum::<u32>();
+        let mut new_locals = locals.to_vec();
+        new_locals.push((1, ValType::I32));
+        let mut newfunc = Function::new(new_locals);
+
+        let if_index = *self
+            .get_candidate_ifs(ast)
+            .choose(config.rng())
+            .expect("This mutator should check first if the AST contains at least one if");
+        let writer = IfToBrIfWriter {
+            if_to_mutate: if_index,
+            condition_local,
+        };
+        writer.write(ast, ast.get_root(), &mut newfunc, operators, input_wasm)?;
+        Ok(newfunc)
+    }
+}
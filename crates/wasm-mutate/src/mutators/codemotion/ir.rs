@@ -204,6 +204,7 @@ pub trait AstWriter {
                 consequent,
                 alternative,
                 ty,
+                range: _,
             } => {
                 self.write_if_else(
                     ast,
@@ -222,7 +223,7 @@ pub trait AstWriter {
             Node::Loop { body, ty, range: _ } => {
                 self.write_loop(ast, nodeidx, body, newfunc, operators, input_wasm, ty)?
             }
-            Node::Block { body, ty } => {
+            Node::Block { body, ty, range: _ } => {
                 self.write_block(ast, nodeidx, body, newfunc, operators, input_wasm, ty)?
             }
             Node::Root(body) => {
@@ -241,6 +242,124 @@ impl AstWriter for Ast {
     /* It has the default implementation */
 }
 
+/// Returns the relative depths of the branch targets of `op`, or `None` if it
+/// isn't a branch.
+///
+/// Returns an error for instructions with branch targets which code motion
+/// mutators don't support rewriting.
+fn branch_depths(op: &Operator) -> crate::Result<Option<Vec<u32>>> {
+    Ok(match op {
+        Operator::Br { relative_depth } | Operator::BrIf { relative_depth } => {
+            Some(vec![*relative_depth])
+        }
+        Operator::BrTable { targets } => {
+            let mut depths = targets.targets().collect::<Result<Vec<_>, _>>()?;
+            depths.push(targets.default());
+            Some(depths)
+        }
+        Operator::BrOnCast { .. }
+        | Operator::BrOnCastFail { .. }
+        | Operator::BrOnNull { .. }
+        | Operator::BrOnNonNull { .. }
+        | Operator::Try { .. }
+        | Operator::Delegate { .. }
+        | Operator::Rethrow { .. }
+        | Operator::TryTable { .. }
+        | Operator::Resume { .. }
+        | Operator::ResumeThrow { .. } => {
+            log::info!("unsupported operator {op:?}");
+            return Err(Error::no_mutations_applicable());
+        }
+        _ => None,
+    })
+}
+
+/// Checks if any branch within `range` of the `operators` targets the label at
+/// `depth` relative to the label enclosing them.
+pub(crate) fn branches_to(
+    operators: &[OperatorAndByteOffset],
+    range: Range<usize>,
+    depth: u32,
+) -> crate::Result<bool> {
+    let mut current_depth = 0;
+    for (op, _) in &operators[range] {
+        match op {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                current_depth += 1
+            }
+            Operator::End => current_depth -= 1,
+            _ => {
+                if let Some(depths) = branch_depths(op)? {
+                    if depths.contains(&(current_depth + depth)) {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Writes the operators within `range` to `newfunc`, rewriting the relative
+/// depths of branches leaving them with `remap`.
+///
+/// `remap` receives the depth of the branch target relative to the label
+/// enclosing the operators, so 0 is the innermost label around them, and
+/// returns the new relative depth for the same position. All other operators
+/// are copied verbatim from the `input_wasm`.
+pub(crate) fn write_remapping_depths(
+    operators: &[OperatorAndByteOffset],
+    range: Range<usize>,
+    newfunc: &mut Function,
+    input_wasm: &[u8],
+    remap: impl Fn(u32) -> u32,
+) -> crate::Result<()> {
+    let mut current_depth = 0;
+    let fix = |depth: u32, current_depth: u32| {
+        if depth < current_depth {
+            depth
+        } else {
+            remap(depth - current_depth) + current_depth
+        }
+    };
+    for idx in range {
+        let (op, offset) = &operators[idx];
+        let instruction = match op {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                current_depth += 1;
+                None
+            }
+            Operator::End => {
+                current_depth -= 1;
+                None
+            }
+            Operator::Br { relative_depth } => {
+                Some(Instruction::Br(fix(*relative_depth, current_depth)))
+            }
+            Operator::BrIf { relative_depth } => {
+                Some(Instruction::BrIf(fix(*relative_depth, current_depth)))
+            }
+            Operator::BrTable { targets } => {
+                let depths = targets
+                    .targets()
+                    .map(|depth| Ok(fix(depth?, current_depth)))
+                    .collect::<crate::Result<Vec<_>>>()?;
+                let default = fix(targets.default(), current_depth);
+                Some(Instruction::BrTable(depths.into(), default))
+            }
+            _ => {
+                branch_depths(op)?;
+                None
+            }
+        };
+        match instruction {
+            Some(instruction) => newfunc.instruction(&instruction),
+            None => newfunc.raw(input_wasm[*offset..operators[idx + 1].1].to_vec()),
+        };
+    }
+    Ok(())
+}
+
 impl AstBuilder {
     /// Returns an Ast from the operators collected from the Wasm function
     pub fn build_ast<'a>(&self, operators: &'a [OperatorAndByteOffset]) -> crate::Result<Ast> {
@@ -289,6 +408,10 @@ impl AstBuilder {
                     parse_context.push_frame(State::Loop, Some(*blockty), idx);
                 }
                 Operator::TryTable { .. } => return Err(Error::no_mutations_applicable()),
+                Operator::BrIf { .. } => {
+                    parse_context.push_br_if(idx);
+                    parse_context.append_instruction_to_current_code();
+                }
                 Operator::End => {
                     if !parse_context.current_code_is_empty() {
                         parse_context.push_current_code_as_node();
@@ -305,10 +428,11 @@ impl AstBuilder {
                                 consequent: then_branch,
                                 alternative: None,
                                 ty: ty.expect("Missing if type"),
+                                range: frame_start..idx,
                             });
                         }
                         State::Else => {
-                            let (last_frame, ty, _if_start) = parse_context.pop_frame()?;
+                            let (last_frame, ty, if_start) = parse_context.pop_frame()?;
                             // Validate parent
                             match last_frame {
                                 State::If => {}
@@ -322,6 +446,7 @@ impl AstBuilder {
                                 consequent: then_branch,
                                 alternative: Some(else_branch),
                                 ty: ty.expect("Missing if type"),
+                                range: if_start..idx,
                             });
                         }
                         State::Loop => {
@@ -341,6 +466,7 @@ impl AstBuilder {
                             parse_context.push_node_to_current_parsing(Node::Block {
                                 body: children,
                                 ty: ty.expect("Missing block type for loop"),
+                                range: frame_start..idx,
                             });
                         }
                         State::Root => {
@@ -12,6 +12,10 @@ pub struct Ast {
     ifs: Vec<usize>,
     // indexeds of loop nodes
     loops: Vec<usize>,
+    // indexes of block nodes
+    blocks: Vec<usize>,
+    // indexes of `br_if` instructions in the instructions stream
+    br_ifs: Vec<usize>,
 }
 
 impl Ast {
@@ -30,6 +34,17 @@ impl Ast {
         &self.loops
     }
 
+    /// Returns the node indexes corresponding to block nodes
+    pub fn get_blocks(&self) -> &[usize] {
+        &self.blocks
+    }
+
+    /// Returns the indexes of the `br_if` instructions in the instructions
+    /// stream
+    pub fn get_br_ifs(&self) -> &[usize] {
+        &self.br_ifs
+    }
+
     /// Returns the `Root` node index of the Ast
     pub fn get_root(&self) -> usize {
         self.root
@@ -51,6 +66,8 @@ pub enum Node {
         alternative: Option<Vec<usize>>,
         /// The block type for the branches.
         ty: BlockType,
+        /// Range on the instructions stream, from the `if` to its `end`
+        range: Range<usize>,
     },
     /// Code node
     Code {
@@ -72,6 +89,8 @@ pub enum Node {
         body: Vec<usize>,
         /// Block type
         ty: BlockType,
+        /// Range on the instructions stream
+        range: Range<usize>,
     },
     /// Special node to wrap the root nodes of the Ast
     Root(Vec<usize>),
@@ -97,6 +116,7 @@ pub(crate) struct ParseContext {
     ifs: Vec<usize>,
     loops: Vec<usize>,
     blocks: Vec<usize>,
+    br_ifs: Vec<usize>,
 }
 
 impl Default for ParseContext {
@@ -110,6 +130,7 @@ impl Default for ParseContext {
             ifs: Vec::new(),
             loops: Vec::new(),
             blocks: Vec::new(),
+            br_ifs: Vec::new(),
         }
     }
 }
@@ -140,11 +161,7 @@ impl ParseContext {
         self.current_parsing.push(id);
 
         match node {
-            Node::IfElse {
-                consequent: _,
-                alternative: _,
-                ty: _,
-            } => self.ifs.push(id),
+            Node::IfElse { .. } => self.ifs.push(id),
             Node::Loop { .. } => self.loops.push(id),
             Node::Block { .. } => self.blocks.push(id),
             _ => {}
@@ -197,6 +214,11 @@ impl ParseContext {
         self.current_code_range.end += 1;
     }
 
+    /// Records the `br_if` instruction at `idx` in the Wasm code
+    pub fn push_br_if(&mut self, idx: usize) {
+        self.br_ifs.push(idx);
+    }
+
     /// Closes the parsing, creates a `Node::Root` node where the children are the current
     /// parsed nodes in the current state.
     ///
@@ -209,6 +231,8 @@ impl ParseContext {
             nodes: self.nodes,
             ifs: self.ifs,
             loops: self.loops,
+            blocks: self.blocks,
+            br_ifs: self.br_ifs,
         }
     }
 }
@@ -0,0 +1,184 @@
+//! This mutator selects a random `loop` construction starting with a nested
+//! `block` or `loop`, and rotates it so that the nested construction is
+//! executed at the end of each iteration instead of at the beginning.
+//!
+//! Given a loop whose body is the construction `A1` followed by `A2`, the loop
+//! is written as:
+//!
+//! ```wat
+//! A1
+//! block
+//!     loop
+//!         block
+//!             A2 ;; branches to the original loop jump to the end of this block
+//!             br 2
+//!         end
+//!         A1
+//!         br 0
+//!     end
+//! end
+//! ```
+//!
+//! This mutator only works on empty-returning loops and nested constructions,
+//! where `A1` does not branch to the original loop.
+use rand::prelude::SliceRandom;
+use wasm_encoder::{BlockType as EncoderBlockType, Function, Instruction, ValType};
+use wasmparser::BlockType;
+
+use crate::{
+    mutators::{
+        codemotion::{
+            ir::{
+                branches_to,
+                parse_context::{Ast, Node},
+                write_remapping_depths, AstWriter,
+            },
+            AstMutator,
+        },
+        OperatorAndByteOffset,
+    },
+    Error, WasmMutate,
+};
+use std::ops::Range;
+
+/// This mutator selects a random `loop` construction starting with a nested
+/// `block` or `loop`, and rotates it.
+pub struct LoopRotationMutator;
+
+struct LoopRotationWriter {
+    loop_to_mutate: usize,
+}
+
+impl LoopRotationWriter {
+    fn rotate_loop<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<()> {
+        let nodes = ast.get_nodes();
+        let (loop_range, first) = match &nodes[nodeidx] {
+            Node::Loop { body, range, .. } => (range, &nodes[body[0]]),
+            _ => unreachable!("Invalid node passed as a loop to rotate"),
+        };
+        let first = match first {
+            Node::Block { range, .. } | Node::Loop { range, .. } => range.start..range.end + 1,
+            _ => unreachable!("Invalid first node of a loop to rotate"),
+        };
+        let rest = first.end..loop_range.end;
+
+        // Write A1 for the first iteration, out of the loop
+        write_remapping_depths(operators, first.clone(), newfunc, input_wasm, |depth| {
+            depth - 1
+        })?;
+        newfunc.instruction(&Instruction::Block(EncoderBlockType::Empty));
+        newfunc.instruction(&Instruction::Loop(EncoderBlockType::Empty));
+        newfunc.instruction(&Instruction::Block(EncoderBlockType::Empty));
+        // Write A2, continuing the loop by falling through the inner block
+        write_remapping_depths(operators, rest, newfunc, input_wasm, |depth| {
+            if depth == 0 {
+                0
+            } else {
+                depth + 2
+            }
+        })?;
+        newfunc.instruction(&Instruction::Br(2));
+        newfunc.instruction(&Instruction::End);
+        // Write A1 for the next iteration
+        write_remapping_depths(operators, first, newfunc, input_wasm, |depth| depth + 1)?;
+        newfunc.instruction(&Instruction::Br(0));
+        newfunc.instruction(&Instruction::End);
+        newfunc.instruction(&Instruction::End);
+        Ok(())
+    }
+}
+
+impl AstWriter for LoopRotationWriter {
+    fn write_loop<'a>(
+        &self,
+        ast: &Ast,
+        nodeidx: usize,
+        body: &[usize],
+        newfunc: &mut Function,
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+        ty: &BlockType,
+    ) -> crate::Result<()> {
+        if self.loop_to_mutate == nodeidx {
+            self.rotate_loop(ast, nodeidx, newfunc, operators, input_wasm)
+        } else {
+            self.write_loop_default(ast, nodeidx, body, newfunc, operators, input_wasm, ty)
+        }
+    }
+}
+
+impl LoopRotationMutator {
+    /// Returns the indexes of the empty-returning loops starting with an
+    /// empty-returning block or loop, along with the range of the latter on
+    /// the instructions stream
+    fn get_rotatable_loops(&self, ast: &Ast) -> Vec<(usize, Range<usize>)> {
+        let nodes = ast.get_nodes();
+        let mut loops = vec![];
+        for idx in ast.get_loops() {
+            let body = match &nodes[*idx] {
+                Node::Loop {
+                    ty: BlockType::Empty,
+                    body,
+                    ..
+                } => body,
+                Node::Loop { .. } => continue,
+                _ => unreachable!("Invalid loop node"),
+            };
+            match body.first().map(|first| &nodes[*first]) {
+                Some(Node::Block {
+                    ty: BlockType::Empty,
+                    range,
+                    ..
+                })
+                | Some(Node::Loop {
+                    ty: BlockType::Empty,
+                    range,
+                    ..
+                }) => loops.push((*idx, range.start..range.end + 1)),
+                _ => {}
+            }
+        }
+        loops
+    }
+}
+
+impl AstMutator for LoopRotationMutator {
+    fn can_mutate<'a>(&self, _config: &crate::WasmMutate, ast: &Ast) -> bool {
+        !self.get_rotatable_loops(ast).is_empty()
+    }
+
+    fn mutate<'a>(
+        &self,
+        config: &'a mut WasmMutate,
+        ast: &Ast,
+        _function_index: u32,
+        locals: &[(u32, ValType)],
+        operators: &Vec<OperatorAndByteOffset>,
+        input_wasm: &'a [u8],
+    ) -> crate::Result<Function> {
+        let mut newfunc = Function::new(locals.to_vec());
+        // The first construction is moved out of the loop, so it can't branch
+        // to it
+        let mut loops = vec![];
+        for (idx, first) in self.get_rotatable_loops(ast) {
+            if !branches_to(operators, first, 0)? {
+                loops.push(idx);
+            }
+        }
+        let loop_index = *loops
+            .choose(config.rng())
+            .ok_or_else(Error::no_mutations_applicable)?;
+        let writer = LoopRotationWriter {
+            loop_to_mutate: loop_index,
+        };
+        writer.write(ast, ast.get_root(), &mut newfunc, operators, input_wasm)?;
+        Ok(newfunc)
+    }
+}
@@ -206,10 +206,7 @@ impl Mutator for ConstExpressionMutator {
                         if let Self::ElementFunc = self {
                             // Pick a specific element item to mutate. We do this through an option
                             // to skip a specific number of activations of the Translator methods.
-                            let item_count = match &element.items {
-                                wasmparser::ElementItems::Functions(r) => r.count(),
-                                wasmparser::ElementItems::Expressions(_, r) => r.count(),
-                            };
+                            let item_count = element.items.count();
                             if item_count > 0 {
                                 let skip = translator.config.rng().gen_range(0..item_count);
                                 translator.skip_inits = skip
@@ -68,3 +68,42 @@ fn integration_test() {
         elapsed.subsec_millis()
     );
 }
+
+#[test]
+fn replay_trace() {
+    let original = &wat::parse_str(
+        r#"
+        (module
+            (func (export "exported_func") (param i32) (result i32)
+                local.get 0
+                if (result i32)
+                    i32.const 1
+                else
+                    i32.const 2
+                end
+            )
+        )
+    "#,
+    )
+    .unwrap();
+
+    let mut mutator = WasmMutate::default();
+    mutator.seed(7).preserve_semantics(true);
+    let mutated = mutator.run(original).unwrap().next().unwrap().unwrap();
+    let trace = mutator.trace().unwrap().clone();
+    assert!(trace.mutator.is_some());
+
+    let mut replay = WasmMutate::default();
+    replay.replay(&trace);
+    let replayed = replay.run(original).unwrap().next().unwrap().unwrap();
+    assert_eq!(mutated, replayed);
+    assert_eq!(replay.trace(), Some(&trace));
+
+    // Replaying on another input chooses another mutator.
+    let other = &wat::parse_str("(module (global i32 (i32.const 0)))").unwrap();
+    let err = match replay.run(other) {
+        Ok(_) => panic!("replay should diverge"),
+        Err(e) => e,
+    };
+    assert!(matches!(err.kind(), ErrorKind::Other(_)), "{err}");
+}
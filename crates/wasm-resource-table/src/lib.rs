@@ -0,0 +1,494 @@
+//! A model of the resource handle tables of the WebAssembly component model.
+//!
+//! Each component instance has a table of handles to resources, which core
+//! wasm refers to by their index. This crate implements the rules of the
+//! [canonical ABI] for these tables, so that hosts and test harnesses which
+//! execute components get the same handle indices and the same traps as the
+//! spec's reference implementation:
+//!
+//! * [`ResourceTables::resource_new`], [`ResourceTables::resource_rep`], and
+//!   [`ResourceTables::resource_drop`] implement the `resource.new`,
+//!   `resource.rep`, and `resource.drop` canonical built-ins.
+//! * [`ResourceTables::lift_own`] and [`ResourceTables::lower_own`] move `own`
+//!   handles between instances.
+//! * [`ResourceTables::lift_borrow`] and [`ResourceTables::lower_borrow`] lend
+//!   handles to a [`Call`], which must drop its `borrow` handles before it
+//!   [exits](ResourceTables::exit_call).
+//!
+//! Representations of resources and handles are the `i32` values seen by
+//! core wasm, represented as `u32`.
+//!
+//! ```
+//! use wasm_resource_table::{ResourceTables, Trap};
+//!
+//! let mut tables = ResourceTables::new();
+//! let implementer = tables.add_instance();
+//! let user = tables.add_instance();
+//! let ty = tables.add_resource_type(implementer);
+//!
+//! // The implementer creates a resource and returns it to `user`.
+//! let handle = tables.resource_new(implementer, ty, 42)?;
+//! let rep = tables.lift_own(implementer, ty, handle)?;
+//! let handle = tables.lower_own(user, ty, rep)?;
+//! assert_eq!(handle, 1);
+//!
+//! // `user` then lends it back to the implementer for a call.
+//! let call = tables.enter_call(Some(user), implementer);
+//! let rep = tables.lift_borrow(call, ty, handle)?;
+//! assert_eq!(tables.lower_borrow(call, ty, rep)?, 42);
+//! assert_eq!(tables.resource_drop(user, ty, handle), Err(Trap::Lent { handle }));
+//! tables.exit_call(call)?;
+//!
+//! // Dropping the last `own` handle returns the representation to destroy.
+//! assert_eq!(tables.resource_drop(user, ty, handle)?, Some(42));
+//! # Ok::<(), Trap>(())
+//! ```
+//!
+//! [canonical ABI]: https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md
+
+#![deny(missing_docs)]
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// The maximum length of a handle table, as defined by the canonical ABI,
+/// which is also the largest index a handle can have.
+pub const MAX_TABLE_LENGTH: u32 = (1 << 28) - 1;
+
+/// A component instance, created with [`ResourceTables::add_instance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instance(u32);
+
+/// A resource type, created with [`ResourceTables::add_resource_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceType(u32);
+
+/// A call into an instance which `borrow` handles can be lent to, created
+/// with [`ResourceTables::enter_call`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Call(u64);
+
+/// A trap raised by an operation on a handle table.
+///
+/// Operations which trap leave the tables unmodified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trap {
+    /// A handle doesn't refer to anything in the instance's table.
+    UnknownHandle {
+        /// The index of the handle.
+        handle: u32,
+    },
+    /// A handle refers to a resource of a different type than expected.
+    TypeMismatch {
+        /// The index of the handle.
+        handle: u32,
+    },
+    /// A `borrow` handle was lifted as an `own` handle.
+    NotOwned {
+        /// The index of the handle.
+        handle: u32,
+    },
+    /// A handle was dropped or moved while it was lent to a call.
+    Lent {
+        /// The index of the handle.
+        handle: u32,
+    },
+    /// An instance other than the one which implements a resource type
+    /// created a resource of that type or asked for its representation.
+    ///
+    /// This is rejected when validating a component, so only a host which
+    /// calls the built-ins for the wrong instance can cause this.
+    NotImplementer,
+    /// A call exited without dropping all of the `borrow` handles lent to it.
+    BorrowsOutstanding {
+        /// The number of `borrow` handles which weren't dropped.
+        count: u32,
+    },
+    /// A table has no room for another handle, see [`MAX_TABLE_LENGTH`].
+    TableFull,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::UnknownHandle { handle } => write!(f, "unknown handle index {handle}"),
+            Trap::TypeMismatch { handle } => {
+                write!(f, "handle index {handle} used with the wrong type")
+            }
+            Trap::NotOwned { handle } => {
+                write!(f, "handle index {handle} is a borrow, not an owned handle")
+            }
+            Trap::Lent { handle } => {
+                write!(
+                    f,
+                    "handle index {handle} is lent to a call that hasn't returned"
+                )
+            }
+            Trap::NotImplementer => {
+                write!(f, "resource used by an instance which doesn't implement it")
+            }
+            Trap::BorrowsOutstanding { count } => {
+                write!(f, "call returned with {count} borrow handle(s) not dropped")
+            }
+            Trap::TableFull => write!(f, "handle table is full"),
+        }
+    }
+}
+
+impl std::error::Error for Trap {}
+
+/// The state of a handle, as returned by [`ResourceTables::handle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handle {
+    /// The type of the resource the handle refers to.
+    pub ty: ResourceType,
+    /// The representation of the resource.
+    pub rep: u32,
+    /// Whether this is an `own` handle rather than a `borrow` handle.
+    pub own: bool,
+    /// The number of calls this handle is currently lent to.
+    pub lends: u32,
+    /// The call a `borrow` handle was lent to, which it must be dropped
+    /// before the exit of.
+    pub borrow_scope: Option<Call>,
+}
+
+/// A table of handles, where the index `0` is reserved and the indices of
+/// removed handles are reused last-in, first-out.
+struct Table {
+    array: Vec<Option<Handle>>,
+    free: Vec<u32>,
+}
+
+impl Table {
+    fn new() -> Table {
+        Table {
+            array: vec![None],
+            free: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, handle: Handle) -> Result<u32, Trap> {
+        if let Some(i) = self.free.pop() {
+            self.array[i as usize] = Some(handle);
+            return Ok(i);
+        }
+        let i = self.array.len() as u32;
+        if i > MAX_TABLE_LENGTH {
+            return Err(Trap::TableFull);
+        }
+        self.array.push(Some(handle));
+        Ok(i)
+    }
+
+    fn get(&self, i: u32) -> Result<&Handle, Trap> {
+        match self.array.get(i as usize) {
+            Some(Some(handle)) => Ok(handle),
+            _ => Err(Trap::UnknownHandle { handle: i }),
+        }
+    }
+
+    fn get_mut(&mut self, i: u32) -> Result<&mut Handle, Trap> {
+        match self.array.get_mut(i as usize) {
+            Some(Some(handle)) => Ok(handle),
+            _ => Err(Trap::UnknownHandle { handle: i }),
+        }
+    }
+
+    fn remove(&mut self, i: u32) -> Result<Handle, Trap> {
+        self.get(i)?;
+        self.free.push(i);
+        Ok(self.array[i as usize].take().unwrap())
+    }
+}
+
+struct CallState {
+    caller: Option<Instance>,
+    callee: Instance,
+    /// The number of `borrow` handles lowered into the callee which haven't
+    /// been dropped yet.
+    num_borrows: u32,
+    /// The handles of the caller which were lent to the callee.
+    lenders: Vec<u32>,
+}
+
+/// The handle tables of a set of component instances, along with the
+/// resource types they implement and the calls between them.
+///
+/// Methods panic when given an [`Instance`], [`ResourceType`], or [`Call`]
+/// which wasn't created by the same `ResourceTables`, or a `Call` which has
+/// exited.
+pub struct ResourceTables {
+    tables: Vec<Table>,
+    /// The implementing instance of each resource type.
+    implementers: Vec<Instance>,
+    calls: HashMap<u64, CallState>,
+    next_call: u64,
+}
+
+impl Default for ResourceTables {
+    fn default() -> ResourceTables {
+        ResourceTables::new()
+    }
+}
+
+impl ResourceTables {
+    /// Creates an empty set of tables.
+    pub fn new() -> ResourceTables {
+        ResourceTables {
+            tables: Vec::new(),
+            implementers: Vec::new(),
+            calls: HashMap::new(),
+            next_call: 0,
+        }
+    }
+
+    /// Adds a component instance with an empty handle table.
+    pub fn add_instance(&mut self) -> Instance {
+        self.tables.push(Table::new());
+        Instance(self.tables.len() as u32 - 1)
+    }
+
+    /// Adds a resource type which is implemented by `implementer`, the
+    /// instance which defines the type and creates its resources.
+    pub fn add_resource_type(&mut self, implementer: Instance) -> ResourceType {
+        self.implementers.push(implementer);
+        ResourceType(self.implementers.len() as u32 - 1)
+    }
+
+    /// Returns the instance which implements `ty`.
+    pub fn implementer(&self, ty: ResourceType) -> Instance {
+        self.implementers[ty.0 as usize]
+    }
+
+    /// Returns the state of `handle` in the table of `instance`, or `None` if
+    /// there's no such handle.
+    pub fn handle(&self, instance: Instance, handle: u32) -> Option<Handle> {
+        self.table(instance).get(handle).ok().copied()
+    }
+
+    /// Returns the indices and states of all handles in the table of
+    /// `instance`, in order.
+    ///
+    /// This can be used to check that no handles were leaked once an instance
+    /// is done.
+    pub fn handles(&self, instance: Instance) -> impl Iterator<Item = (u32, Handle)> + '_ {
+        self.table(instance)
+            .array
+            .iter()
+            .enumerate()
+            .filter_map(|(i, h)| Some((i as u32, (*h)?)))
+    }
+
+    /// The `resource.new` built-in: creates an `own` handle to a new resource
+    /// of type `ty` with representation `rep` in the table of `instance`.
+    pub fn resource_new(
+        &mut self,
+        instance: Instance,
+        ty: ResourceType,
+        rep: u32,
+    ) -> Result<u32, Trap> {
+        if self.implementer(ty) != instance {
+            return Err(Trap::NotImplementer);
+        }
+        self.lower_own(instance, ty, rep)
+    }
+
+    /// The `resource.rep` built-in: returns the representation of the
+    /// resource of type `ty` which `handle` refers to in the table of
+    /// `instance`.
+    pub fn resource_rep(
+        &self,
+        instance: Instance,
+        ty: ResourceType,
+        handle: u32,
+    ) -> Result<u32, Trap> {
+        if self.implementer(ty) != instance {
+            return Err(Trap::NotImplementer);
+        }
+        let h = self.table(instance).get(handle)?;
+        check_type(h, ty, handle)?;
+        Ok(h.rep)
+    }
+
+    /// The `resource.drop` built-in: removes `handle`, which refers to a
+    /// resource of type `ty`, from the table of `instance`.
+    ///
+    /// Returns the representation of the resource if an `own` handle was
+    /// dropped, in which case the caller should run the destructor of `ty`
+    /// if it has one.
+    pub fn resource_drop(
+        &mut self,
+        instance: Instance,
+        ty: ResourceType,
+        handle: u32,
+    ) -> Result<Option<u32>, Trap> {
+        let h = self.table(instance).get(handle)?;
+        check_type(h, ty, handle)?;
+        check_not_lent(h, handle)?;
+        let h = self.table_mut(instance).remove(handle)?;
+        if h.own {
+            return Ok(Some(h.rep));
+        }
+        if let Some(call) = h.borrow_scope {
+            self.call_mut(call).num_borrows -= 1;
+        }
+        Ok(None)
+    }
+
+    /// Lifts the `own` handle `handle`, which refers to a resource of type
+    /// `ty`, out of the table of `instance` to pass it to another instance,
+    /// returning the resource's representation.
+    ///
+    /// The handle is removed from the table, as ownership moves to the
+    /// instance it's lowered into with [`ResourceTables::lower_own`].
+    pub fn lift_own(
+        &mut self,
+        instance: Instance,
+        ty: ResourceType,
+        handle: u32,
+    ) -> Result<u32, Trap> {
+        let h = self.table(instance).get(handle)?;
+        check_type(h, ty, handle)?;
+        check_not_lent(h, handle)?;
+        if !h.own {
+            return Err(Trap::NotOwned { handle });
+        }
+        Ok(self.table_mut(instance).remove(handle)?.rep)
+    }
+
+    /// Lowers an `own` handle to the resource of type `ty` with
+    /// representation `rep` into the table of `instance`, returning its
+    /// index.
+    pub fn lower_own(
+        &mut self,
+        instance: Instance,
+        ty: ResourceType,
+        rep: u32,
+    ) -> Result<u32, Trap> {
+        self.table_mut(instance).add(Handle {
+            ty,
+            rep,
+            own: true,
+            lends: 0,
+            borrow_scope: None,
+        })
+    }
+
+    /// Starts a call from `caller`, or from the host if `None`, into
+    /// `callee`, which arguments are lifted from and lowered into.
+    pub fn enter_call(&mut self, caller: Option<Instance>, callee: Instance) -> Call {
+        let call = Call(self.next_call);
+        self.next_call += 1;
+        self.calls.insert(
+            call.0,
+            CallState {
+                caller,
+                callee,
+                num_borrows: 0,
+                lenders: Vec::new(),
+            },
+        );
+        call
+    }
+
+    /// Lifts `handle`, which refers to a resource of type `ty` in the table
+    /// of the caller of `call`, to lend it to the callee for the duration of
+    /// the call, returning the resource's representation.
+    ///
+    /// Both `own` and `borrow` handles can be lent, and can't be dropped or
+    /// moved until the call exits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the caller of `call` is the host, which has no handle table.
+    pub fn lift_borrow(&mut self, call: Call, ty: ResourceType, handle: u32) -> Result<u32, Trap> {
+        let caller = self
+            .call(call)
+            .caller
+            .expect("the host has no handle table to lift from");
+        let h = self.table_mut(caller).get_mut(handle)?;
+        check_type(h, ty, handle)?;
+        h.lends += 1;
+        let rep = h.rep;
+        self.call_mut(call).lenders.push(handle);
+        Ok(rep)
+    }
+
+    /// Lowers a `borrow` handle to the resource of type `ty` with
+    /// representation `rep` into the table of the callee of `call`, returning
+    /// its index.
+    ///
+    /// If the callee implements `ty` this returns `rep` itself without adding
+    /// a handle. Otherwise the handle must be dropped before the call exits.
+    pub fn lower_borrow(&mut self, call: Call, ty: ResourceType, rep: u32) -> Result<u32, Trap> {
+        let callee = self.call(call).callee;
+        if self.implementer(ty) == callee {
+            return Ok(rep);
+        }
+        let handle = self.table_mut(callee).add(Handle {
+            ty,
+            rep,
+            own: false,
+            lends: 0,
+            borrow_scope: Some(call),
+        })?;
+        self.call_mut(call).num_borrows += 1;
+        Ok(handle)
+    }
+
+    /// Ends `call` when the callee returns, releasing the handles lent to it.
+    ///
+    /// Traps if the callee didn't drop all of the `borrow` handles lowered
+    /// into it, in which case the call doesn't exit.
+    pub fn exit_call(&mut self, call: Call) -> Result<(), Trap> {
+        let state = self.call(call);
+        if state.num_borrows > 0 {
+            return Err(Trap::BorrowsOutstanding {
+                count: state.num_borrows,
+            });
+        }
+        let state = self.calls.remove(&call.0).unwrap();
+        if let Some(caller) = state.caller {
+            let table = self.table_mut(caller);
+            for handle in state.lenders {
+                table.get_mut(handle).unwrap().lends -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn table(&self, instance: Instance) -> &Table {
+        &self.tables[instance.0 as usize]
+    }
+
+    fn table_mut(&mut self, instance: Instance) -> &mut Table {
+        &mut self.tables[instance.0 as usize]
+    }
+
+    fn call(&self, call: Call) -> &CallState {
+        self.calls.get(&call.0).expect("call has already exited")
+    }
+
+    fn call_mut(&mut self, call: Call) -> &mut CallState {
+        self.calls
+            .get_mut(&call.0)
+            .expect("call has already exited")
+    }
+}
+
+fn check_type(h: &Handle, ty: ResourceType, handle: u32) -> Result<(), Trap> {
+    if h.ty == ty {
+        Ok(())
+    } else {
+        Err(Trap::TypeMismatch { handle })
+    }
+}
+
+fn check_not_lent(h: &Handle, handle: u32) -> Result<(), Trap> {
+    if h.lends == 0 {
+        Ok(())
+    } else {
+        Err(Trap::Lent { handle })
+    }
+}
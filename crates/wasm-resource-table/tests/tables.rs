@@ -0,0 +1,149 @@
+use wasm_resource_table::{ResourceTables, Trap, MAX_TABLE_LENGTH};
+
+#[test]
+fn handle_indices() {
+    let mut tables = ResourceTables::new();
+    let instance = tables.add_instance();
+    let ty = tables.add_resource_type(instance);
+
+    // Index 0 is never used, and freed indices are reused most recent first.
+    let handles = (0..4)
+        .map(|rep| tables.resource_new(instance, ty, rep).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(handles, [1, 2, 3, 4]);
+    assert_eq!(tables.resource_drop(instance, ty, 2), Ok(Some(1)));
+    assert_eq!(tables.resource_drop(instance, ty, 3), Ok(Some(2)));
+    assert_eq!(tables.resource_new(instance, ty, 10), Ok(3));
+    assert_eq!(tables.resource_new(instance, ty, 11), Ok(2));
+    assert_eq!(tables.resource_new(instance, ty, 12), Ok(5));
+
+    let reps = tables
+        .handles(instance)
+        .map(|(i, h)| (i, h.rep))
+        .collect::<Vec<_>>();
+    assert_eq!(reps, [(1, 0), (2, 11), (3, 10), (4, 3), (5, 12)]);
+    assert_eq!(tables.resource_rep(instance, ty, 3), Ok(10));
+    assert_eq!(
+        tables.resource_rep(instance, ty, 0),
+        Err(Trap::UnknownHandle { handle: 0 })
+    );
+    assert_eq!(MAX_TABLE_LENGTH, (1 << 28) - 1);
+}
+
+#[test]
+fn type_checks() {
+    let mut tables = ResourceTables::new();
+    let implementer = tables.add_instance();
+    let user = tables.add_instance();
+    let a = tables.add_resource_type(implementer);
+    let b = tables.add_resource_type(implementer);
+
+    let handle = tables.resource_new(implementer, a, 1).unwrap();
+    assert_eq!(
+        tables.resource_rep(implementer, b, handle),
+        Err(Trap::TypeMismatch { handle })
+    );
+    assert_eq!(
+        tables.resource_drop(implementer, b, handle),
+        Err(Trap::TypeMismatch { handle })
+    );
+    assert_eq!(
+        tables.lift_own(implementer, b, handle),
+        Err(Trap::TypeMismatch { handle })
+    );
+    assert_eq!(tables.resource_new(user, a, 2), Err(Trap::NotImplementer));
+    assert_eq!(
+        tables.resource_rep(user, a, handle),
+        Err(Trap::NotImplementer)
+    );
+    assert!(tables.handle(implementer, handle).is_some());
+}
+
+#[test]
+fn move_own() {
+    let mut tables = ResourceTables::new();
+    let implementer = tables.add_instance();
+    let user = tables.add_instance();
+    let ty = tables.add_resource_type(implementer);
+
+    let handle = tables.resource_new(implementer, ty, 7).unwrap();
+    let rep = tables.lift_own(implementer, ty, handle).unwrap();
+    assert_eq!(tables.handle(implementer, handle), None);
+    assert_eq!(
+        tables.lift_own(implementer, ty, handle),
+        Err(Trap::UnknownHandle { handle })
+    );
+
+    let handle = tables.lower_own(user, ty, rep).unwrap();
+    let h = tables.handle(user, handle).unwrap();
+    assert!(h.own);
+    assert_eq!((h.ty, h.rep, h.lends), (ty, 7, 0));
+    assert_eq!(tables.resource_drop(user, ty, handle), Ok(Some(7)));
+    assert_eq!(tables.handles(user).count(), 0);
+}
+
+#[test]
+fn borrows() {
+    let mut tables = ResourceTables::new();
+    let implementer = tables.add_instance();
+    let middle = tables.add_instance();
+    let user = tables.add_instance();
+    let ty = tables.add_resource_type(implementer);
+
+    let rep = 3;
+    let owned = tables.lower_own(user, ty, rep).unwrap();
+
+    // `user` lends its handle to `middle`, which gets a borrow handle.
+    let outer = tables.enter_call(Some(user), middle);
+    let rep = tables.lift_borrow(outer, ty, owned).unwrap();
+    let borrowed = tables.lower_borrow(outer, ty, rep).unwrap();
+    let h = tables.handle(middle, borrowed).unwrap();
+    assert!(!h.own);
+    assert_eq!(h.borrow_scope, Some(outer));
+    assert_eq!(tables.handle(user, owned).unwrap().lends, 1);
+    assert_eq!(
+        tables.lift_own(user, ty, owned),
+        Err(Trap::Lent { handle: owned })
+    );
+    assert_eq!(
+        tables.lift_own(middle, ty, borrowed),
+        Err(Trap::NotOwned { handle: borrowed })
+    );
+
+    // `middle` lends its borrow on to the implementer, which sees the rep.
+    let inner = tables.enter_call(Some(middle), implementer);
+    let rep = tables.lift_borrow(inner, ty, borrowed).unwrap();
+    assert_eq!(tables.lower_borrow(inner, ty, rep), Ok(3));
+    assert_eq!(tables.handles(implementer).count(), 0);
+    assert_eq!(
+        tables.resource_drop(middle, ty, borrowed),
+        Err(Trap::Lent { handle: borrowed })
+    );
+    tables.exit_call(inner).unwrap();
+
+    // `middle` has to drop its borrow before returning.
+    assert_eq!(
+        tables.exit_call(outer),
+        Err(Trap::BorrowsOutstanding { count: 1 })
+    );
+    assert_eq!(tables.resource_drop(middle, ty, borrowed), Ok(None));
+    tables.exit_call(outer).unwrap();
+    assert_eq!(tables.handle(user, owned).unwrap().lends, 0);
+    assert_eq!(tables.resource_drop(user, ty, owned), Ok(Some(3)));
+}
+
+#[test]
+fn host_calls() {
+    let mut tables = ResourceTables::new();
+    let implementer = tables.add_instance();
+    let guest = tables.add_instance();
+    let ty = tables.add_resource_type(implementer);
+
+    // The host lends a resource to a guest which doesn't implement it.
+    let call = tables.enter_call(None, guest);
+    let handle = tables.lower_borrow(call, ty, 5).unwrap();
+    assert_eq!(tables.handle(guest, handle).unwrap().rep, 5);
+    assert!(tables.exit_call(call).is_err());
+    tables.resource_drop(guest, ty, handle).unwrap();
+    tables.exit_call(call).unwrap();
+}
@@ -28,7 +28,8 @@ macro_rules! define_config {
             ///
             /// The provided value must be a valid binary encoding of a
             /// WebAssembly module. `wasm-smith` will panic if the module cannot
-            /// be parsed.
+            /// be parsed. See [`Config::set_available_imports`] to provide the
+            /// imports as a list instead.
             ///
             /// # Example
             ///
@@ -94,6 +95,53 @@ macro_rules! define_config {
             /// ```
             pub exports: Option<Vec<u8>>,
 
+            /// Function bodies which must appear in the generated module.
+            ///
+            /// Defaults to `None` which means all function bodies are
+            /// arbitrarily generated.
+            ///
+            /// When provided this must be a WebAssembly module whose defined
+            /// functions are "kernels": each one is included in the generated
+            /// module with exactly the same locals and instruction sequence,
+            /// while everything around it is generated as usual. The kernels
+            /// may freely call each other and use the kernel module's own
+            /// types, imports, tables, memories, globals, and tags. Imported
+            /// functions are replaced with arbitrarily-generated functions and
+            /// all other entities are replaced with defined entities of the
+            /// same type (or an existing entity of the same type when the
+            /// module is at its limits). Indices within the kernels are
+            /// rewritten to refer to these entities.
+            ///
+            /// Kernels exported from the kernel module are exported under the
+            /// same name in the generated module, and the remaining generated
+            /// functions may call any kernel. The kernel module may not contain
+            /// data or element segments.
+            ///
+            /// Wasm features used by the kernels must also be enabled in this
+            /// configuration for the generated module to be valid, and like
+            /// [`Self::exports`] the kernels' prerequisites are added even if
+            /// that exceeds configured limits such as [`Self::max_funcs`].
+            ///
+            /// The provided value must be a valid binary encoding of a
+            /// WebAssembly module. `wasm-smith` will panic if the module cannot
+            /// be parsed or validated.
+            ///
+            /// # Example
+            ///
+            /// ```rust
+            /// Some(wat::parse_str(r#"
+            ///     (module
+            ///         (memory 1)
+            ///         (func (export "sum") (param i32 i32) (result i32)
+            ///             local.get 0
+            ///             i32.load
+            ///             local.get 1
+            ///             i32.add)
+            ///     )
+            /// "#));
+            /// ```
+            pub kernels: Option<Vec<u8>>,
+
             $(
                 $(#[$field_attr])*
                 pub $field: $field_ty,
@@ -105,6 +153,7 @@ macro_rules! define_config {
                 Config {
                     available_imports: None,
                     exports: None,
+                    kernels: None,
 
                     $(
                         $field: $default,
@@ -166,6 +215,20 @@ macro_rules! define_config {
             #[cfg_attr(feature = "clap", clap(long))]
             exports: Option<std::path::PathBuf>,
 
+            /// Function bodies which must appear in the generated module.
+            ///
+            /// When specified this is a file path of a WebAssembly module whose
+            /// defined functions are "kernels": each one is included in the
+            /// generated module with exactly the same locals and instruction
+            /// sequence, with indices rewritten to refer to compatible
+            /// generated types, functions, tables, memories, globals, and tags.
+            ///
+            /// The provided value must be a valid binary encoding of a
+            /// WebAssembly module. `wasm-smith` will panic if the module cannot
+            /// be parsed or validated.
+            #[cfg_attr(feature = "clap", clap(long))]
+            kernels: Option<std::path::PathBuf>,
+
             $(
                 $(#[$field_attr])*
                 #[cfg_attr(feature = "clap", clap(long))]
@@ -179,6 +242,7 @@ macro_rules! define_config {
                 Self {
                     available_imports: self.available_imports.or(other.available_imports),
                     exports: self.exports.or(other.exports),
+                    kernels: self.kernels.or(other.kernels),
 
                     $(
                         $field: self.$field.or(other.$field),
@@ -207,6 +271,13 @@ macro_rules! define_config {
                         } else {
                             None
                         },
+                    kernels: if let Some(file) = config
+                        .kernels
+                        .as_ref() {
+                            Some(wat::parse_file(file)?)
+                        } else {
+                            None
+                        },
 
                     $(
                         $field: config.$field.unwrap_or(default.$field),
@@ -287,6 +358,23 @@ define_config! {
         /// instructions.
         pub canonicalize_nans: bool = false,
 
+        /// Returns whether we should avoid generating code with data races.
+        ///
+        /// When enabled, shared memories are only ever accessed with atomic
+        /// instructions, along with `memory.size` and `memory.grow`. Plain
+        /// loads and stores, SIMD loads and stores, and bulk memory
+        /// instructions instead use unshared memories. This keeps the
+        /// behavior of generated modules well-defined when their shared
+        /// memories are accessed by multiple threads at once, so that engines
+        /// with different memory models can still be compared with each other.
+        ///
+        /// This only has an effect if [`Config::threads_enabled`] is set, and
+        /// doesn't apply to the bodies of [`Config::kernels`], which are used
+        /// verbatim.
+        ///
+        /// Defaults to `false`.
+        pub disallow_data_races: bool = false,
+
         /// Returns whether we should avoid generating code that will possibly
         /// trap.
         ///
@@ -365,6 +453,11 @@ define_config! {
         pub max_globals: usize = 100,
 
         /// The maximum number of imports to generate. Defaults to 100.
+        ///
+        /// Set this to zero to generate self-contained modules without any
+        /// imports, which can be instantiated and executed without providing
+        /// anything from the host. This applies to
+        /// [`Config::available_imports`] as well.
         pub max_imports: usize = 100,
 
         /// The maximum number of instances to use. Defaults to 10.
@@ -581,7 +674,10 @@ define_config! {
         /// Determines whether the threads proposal is enabled.
         ///
         /// The [threads proposal] involves shared linear memory, new atomic
-        /// instructions, and new `wait` and `notify` instructions.
+        /// instructions, and new `wait` and `notify` instructions. Atomic
+        /// instructions are not generated when [`Config::disallow_traps`] is
+        /// set as they trap on unaligned addresses, and `wait` instructions
+        /// always time out immediately.
         ///
         /// [threads proposal]: https://github.com/WebAssembly/threads/blob/master/proposals/threads/Overview.md
         ///
@@ -633,6 +729,29 @@ impl Default for MemoryOffsetChoices {
     }
 }
 
+/// The type of an import provided to [`Config::set_available_imports`].
+#[derive(Clone, Debug)]
+pub enum ImportType {
+    /// A function with the given parameters and results.
+    Func {
+        /// The types of the parameters of the function.
+        params: Vec<wasm_encoder::ValType>,
+        /// The types of the results of the function.
+        results: Vec<wasm_encoder::ValType>,
+    },
+    /// An exception tag with the given parameters.
+    Tag {
+        /// The types of the values carried by exceptions with the tag.
+        params: Vec<wasm_encoder::ValType>,
+    },
+    /// A global of the given type.
+    Global(wasm_encoder::GlobalType),
+    /// A memory of the given type.
+    Memory(wasm_encoder::MemoryType),
+    /// A table of the given type.
+    Table(wasm_encoder::TableType),
+}
+
 #[cfg(feature = "_internal_cli")]
 impl std::str::FromStr for MemoryOffsetChoices {
     type Err = String;
@@ -703,6 +822,7 @@ impl<'a> Arbitrary<'a> for Config {
             },
             table_max_size_required: u.arbitrary()?,
             max_table_elements: u.int_in_range(0..=1_000_000)?,
+            disallow_data_races: u.arbitrary()?,
             disallow_traps: u.arbitrary()?,
             allow_floats: u.arbitrary()?,
 
@@ -731,6 +851,7 @@ impl<'a> Arbitrary<'a> for Config {
             canonicalize_nans: false,
             available_imports: None,
             exports: None,
+            kernels: None,
             export_everything: false,
             generate_custom_sections: false,
             allow_invalid_funcs: false,
@@ -745,6 +866,62 @@ impl<'a> Arbitrary<'a> for Config {
 }
 
 impl Config {
+    /// Sets [`Config::available_imports`] to the `imports` given as triples of
+    /// their module name, field name, and type.
+    ///
+    /// Generated modules only import a subset of `imports`, so hosts which
+    /// provide all of them can instantiate every generated module. As with
+    /// [`Config::available_imports`], this requires the `wasmparser` feature
+    /// of this crate to be enabled when generating modules.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wasm_encoder::ValType;
+    /// use wasm_smith::{Config, ImportType};
+    ///
+    /// let mut config = Config::default();
+    /// config.set_available_imports([
+    ///     (
+    ///         "env",
+    ///         "print",
+    ///         ImportType::Func {
+    ///             params: vec![ValType::I32],
+    ///             results: vec![],
+    ///         },
+    ///     ),
+    /// ]);
+    /// ```
+    pub fn set_available_imports<'a>(
+        &mut self,
+        imports: impl IntoIterator<Item = (&'a str, &'a str, ImportType)>,
+    ) {
+        let mut types = wasm_encoder::TypeSection::new();
+        let mut section = wasm_encoder::ImportSection::new();
+        for (module, field, ty) in imports {
+            let ty = match ty {
+                ImportType::Func { params, results } => {
+                    types.ty().function(params, results);
+                    wasm_encoder::EntityType::Function(types.len() - 1)
+                }
+                ImportType::Tag { params } => {
+                    types.ty().function(params, []);
+                    wasm_encoder::EntityType::Tag(wasm_encoder::TagType {
+                        kind: wasm_encoder::TagKind::Exception,
+                        func_type_idx: types.len() - 1,
+                    })
+                }
+                ImportType::Global(ty) => ty.into(),
+                ImportType::Memory(ty) => ty.into(),
+                ImportType::Table(ty) => ty.into(),
+            };
+            section.import(module, field, ty);
+        }
+        let mut module = wasm_encoder::Module::new();
+        module.section(&types).section(&section);
+        self.available_imports = Some(module.finish());
+    }
+
     /// "Shrink" this `Config` where appropriate to ensure its configuration is
     /// valid for wasm-smith.
     ///
@@ -2,6 +2,8 @@
 
 mod code_builder;
 pub(crate) mod encode;
+mod kernels;
+mod lower;
 mod terminate;
 
 use crate::{arbitrary_loop, limited_string, unique_string, Config};
@@ -127,6 +129,10 @@ pub struct Module {
     /// entry is the type of each memory.
     memories: Vec<MemoryType>,
 
+    /// Bodies of the user-provided kernel functions, keyed by their function
+    /// index, which are used instead of generating arbitrary bodies.
+    kernel_code: HashMap<u32, Code>,
+
     exports: Vec<(String, ExportKind, u32)>,
     start: Option<u32>,
     elems: Vec<ElementSegment>,
@@ -229,6 +235,7 @@ impl Module {
             tables: Vec::new(),
             globals: Vec::new(),
             memories: Vec::new(),
+            kernel_code: HashMap::new(),
             exports: Vec::new(),
             start: None,
             elems: Vec::new(),
@@ -401,6 +408,9 @@ struct Code {
 enum Instructions {
     Generated(Vec<Instruction>),
     Arbitrary(Vec<u8>),
+    /// The verbatim body of a user-provided kernel, see [`Config::kernels`].
+    #[cfg_attr(not(feature = "wasmparser"), allow(dead_code))]
+    Kernel(Vec<u8>),
 }
 
 #[derive(Debug)]
@@ -440,6 +450,7 @@ impl Module {
 
         self.should_encode_imports = !self.imports.is_empty() || u.arbitrary()?;
 
+        self.kernels(u)?;
         self.arbitrary_tags(u)?;
         self.arbitrary_funcs(u)?;
         self.arbitrary_tables(u)?;
@@ -1318,6 +1329,9 @@ impl Module {
         };
 
         for import in available_imports {
+            if self.num_imports >= self.config.max_imports {
+                break;
+            }
             let type_size_budget = self.config.max_type_size - self.type_size;
             let entity_type = match &import.ty {
                 wasmparser::TypeRef::Func(sig_idx) => {
@@ -2102,8 +2116,15 @@ impl Module {
 
         self.code.reserve(self.num_defined_funcs);
         let mut allocs = CodeBuilderAllocations::new(self, self.config.exports.is_some());
-        for (_, ty) in self.funcs[self.funcs.len() - self.num_defined_funcs..].iter() {
-            let body = self.arbitrary_func_body(u, ty, &mut allocs)?;
+        let first_defined = self.funcs.len() - self.num_defined_funcs;
+        for i in first_defined..self.funcs.len() {
+            let body = match self.kernel_code.remove(&(i as u32)) {
+                Some(kernel) => kernel,
+                None => {
+                    let ty = self.funcs[i].1.clone();
+                    self.arbitrary_func_body(u, &ty, &mut allocs)?
+                }
+            };
             self.code.push(body);
         }
         allocs.finish(u, self)?;
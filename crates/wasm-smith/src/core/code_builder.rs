@@ -155,6 +155,74 @@ instructions! {
     (Some(data_drop_valid), data_drop, MemoryInt),
     (Some(memory_copy_valid), memory_copy, MemoryInt),
     (Some(memory_fill_valid), memory_fill, MemoryInt),
+    // Atomic memory instructions.
+    (Some(have_atomic_memory_and_offset), i32_atomic_load, MemoryInt),
+    (Some(have_atomic_memory_and_offset), i64_atomic_load, MemoryInt),
+    (Some(have_atomic_memory_and_offset), i32_atomic_load8_u, MemoryInt),
+    (Some(have_atomic_memory_and_offset), i32_atomic_load16_u, MemoryInt),
+    (Some(have_atomic_memory_and_offset), i64_atomic_load8_u, MemoryInt),
+    (Some(have_atomic_memory_and_offset), i64_atomic_load16_u, MemoryInt),
+    (Some(have_atomic_memory_and_offset), i64_atomic_load32_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_store, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_store, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_store8, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_store16, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_store8, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_store16, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_store32, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw_add, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw_add, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw8_add_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw16_add_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw8_add_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw16_add_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw32_add_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw_sub, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw_sub, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw8_sub_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw16_sub_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw8_sub_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw16_sub_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw32_sub_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw_and, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw_and, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw8_and_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw16_and_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw8_and_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw16_and_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw32_and_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw_or, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw_or, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw8_or_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw16_or_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw8_or_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw16_or_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw32_or_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw_xor, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw_xor, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw8_xor_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw16_xor_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw8_xor_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw16_xor_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw32_xor_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw_xchg, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw_xchg, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw8_xchg_u, MemoryInt),
+    (Some(i32_atomic_store_valid), i32_atomic_rmw16_xchg_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw8_xchg_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw16_xchg_u, MemoryInt),
+    (Some(i64_atomic_store_valid), i64_atomic_rmw32_xchg_u, MemoryInt),
+    (Some(i32_atomic_cmpxchg_valid), i32_atomic_rmw_cmpxchg, MemoryInt),
+    (Some(i64_atomic_cmpxchg_valid), i64_atomic_rmw_cmpxchg, MemoryInt),
+    (Some(i32_atomic_cmpxchg_valid), i32_atomic_rmw8_cmpxchg_u, MemoryInt),
+    (Some(i32_atomic_cmpxchg_valid), i32_atomic_rmw16_cmpxchg_u, MemoryInt),
+    (Some(i64_atomic_cmpxchg_valid), i64_atomic_rmw8_cmpxchg_u, MemoryInt),
+    (Some(i64_atomic_cmpxchg_valid), i64_atomic_rmw16_cmpxchg_u, MemoryInt),
+    (Some(i64_atomic_cmpxchg_valid), i64_atomic_rmw32_cmpxchg_u, MemoryInt),
+    (Some(i32_atomic_store_valid), memory_atomic_notify, MemoryInt),
+    (Some(memory_atomic_wait32_valid), memory_atomic_wait32, MemoryInt),
+    (Some(memory_atomic_wait64_valid), memory_atomic_wait64, MemoryInt),
+    (Some(atomic_fence_valid), atomic_fence, MemoryInt),
     // Numeric instructions.
     (None, i32_const, NumericInt),
     (None, i64_const, NumericInt),
@@ -647,6 +715,15 @@ pub(crate) struct CodeBuilderAllocations {
     table32: Vec<u32>,
     table64: Vec<u32>,
 
+    // Like `memory32` and `memory64` but for atomic instructions and
+    // `memory.grow`, which may use shared memories even when data races are
+    // disallowed and non-atomic instructions may not. The `shared_*` lists
+    // are the subset of those which are shared, for `memory.atomic.wait*`.
+    atomic_memory32: Vec<u32>,
+    atomic_memory64: Vec<u32>,
+    shared_memory32: Vec<u32>,
+    shared_memory64: Vec<u32>,
+
     // State used when dropping operands to avoid dropping them into the ether
     // but instead folding their final values into module state, at this time
     // chosen to be exported globals.
@@ -793,11 +870,23 @@ impl CodeBuilderAllocations {
 
         let mut memory32 = Vec::new();
         let mut memory64 = Vec::new();
+        let mut atomic_memory32 = Vec::new();
+        let mut atomic_memory64 = Vec::new();
+        let mut shared_memory32 = Vec::new();
+        let mut shared_memory64 = Vec::new();
+        let disallow_data_races = module.config.disallow_data_races;
         for (i, mem) in module.memories.iter().enumerate() {
-            if mem.memory64 {
-                memory64.push(i as u32);
+            let (all, atomic, shared) = if mem.memory64 {
+                (&mut memory64, &mut atomic_memory64, &mut shared_memory64)
             } else {
-                memory32.push(i as u32);
+                (&mut memory32, &mut atomic_memory32, &mut shared_memory32)
+            };
+            atomic.push(i as u32);
+            if mem.shared {
+                shared.push(i as u32);
+            }
+            if !(mem.shared && disallow_data_races) {
+                all.push(i as u32);
             }
         }
 
@@ -883,6 +972,10 @@ impl CodeBuilderAllocations {
             memory64,
             table32,
             table64,
+            atomic_memory32,
+            atomic_memory64,
+            shared_memory32,
+            shared_memory64,
 
             global_dropped_i32,
             global_dropped_i64,
@@ -3311,8 +3404,8 @@ fn memory_size(
 
 #[inline]
 fn memory_grow_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    (builder.allocs.memory32.len() > 0 && builder.type_on_stack(module, ValType::I32))
-        || (builder.allocs.memory64.len() > 0 && builder.type_on_stack(module, ValType::I64))
+    (builder.allocs.atomic_memory32.len() > 0 && builder.type_on_stack(module, ValType::I32))
+        || (builder.allocs.atomic_memory64.len() > 0 && builder.type_on_stack(module, ValType::I64))
 }
 
 fn memory_grow(
@@ -3326,7 +3419,11 @@ fn memory_grow(
     } else {
         ValType::I64
     };
-    let index = memory_index(u, builder, ty)?;
+    let index = if ty == ValType::I32 {
+        *u.choose(&builder.allocs.atomic_memory32)?
+    } else {
+        *u.choose(&builder.allocs.atomic_memory64)?
+    };
     builder.pop_operands(module, &[ty]);
     builder.push_operands(&[ty]);
     instructions.push(Instruction::MemoryGrow(index));
@@ -3475,6 +3572,240 @@ fn gen_copy_src_and_dst(
     }
 }
 
+#[inline]
+fn atomic_enabled(module: &Module) -> bool {
+    // Atomic accesses trap on unaligned addresses, which isn't handled by the
+    // non-trapping mode yet.
+    module.config.threads_enabled && !module.config.disallow_traps
+}
+
+#[inline]
+fn have_atomic_memory_and_offset(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_enabled(module)
+        && ((builder.allocs.atomic_memory32.len() > 0
+            && builder.type_on_stack(module, ValType::I32))
+            || (builder.allocs.atomic_memory64.len() > 0
+                && builder.type_on_stack(module, ValType::I64)))
+}
+
+#[inline]
+fn atomic_store_valid(module: &Module, builder: &mut CodeBuilder, ty: ValType) -> bool {
+    atomic_enabled(module)
+        && ((builder.allocs.atomic_memory32.len() > 0
+            && builder.types_on_stack(module, &[ValType::I32, ty]))
+            || (builder.allocs.atomic_memory64.len() > 0
+                && builder.types_on_stack(module, &[ValType::I64, ty])))
+}
+
+#[inline]
+fn i32_atomic_store_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_store_valid(module, builder, ValType::I32)
+}
+
+#[inline]
+fn i64_atomic_store_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_store_valid(module, builder, ValType::I64)
+}
+
+#[inline]
+fn atomic_cmpxchg_valid(module: &Module, builder: &mut CodeBuilder, ty: ValType) -> bool {
+    atomic_enabled(module)
+        && ((builder.allocs.atomic_memory32.len() > 0
+            && builder.types_on_stack(module, &[ValType::I32, ty, ty]))
+            || (builder.allocs.atomic_memory64.len() > 0
+                && builder.types_on_stack(module, &[ValType::I64, ty, ty])))
+}
+
+#[inline]
+fn i32_atomic_cmpxchg_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_cmpxchg_valid(module, builder, ValType::I32)
+}
+
+#[inline]
+fn i64_atomic_cmpxchg_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_cmpxchg_valid(module, builder, ValType::I64)
+}
+
+/// Pops the address of an atomic access from the operand stack and picks the
+/// memory it accesses from `memory32` or `memory64` depending on its type.
+///
+/// Atomic accesses must be naturally aligned, so the offset is a multiple of
+/// the `align` of the access to give aligned addresses a chance.
+fn atomic_mem_arg(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    memory32: fn(&CodeBuilderAllocations) -> &[u32],
+    memory64: fn(&CodeBuilderAllocations) -> &[u32],
+    align: u32,
+) -> Result<MemArg> {
+    let memory_index = if builder.type_on_stack(module, ValType::I32) {
+        builder.pop_operands(module, &[ValType::I32]);
+        *u.choose(memory32(&builder.allocs))?
+    } else {
+        builder.pop_operands(module, &[ValType::I64]);
+        *u.choose(memory64(&builder.allocs))?
+    };
+    let offset = memory_offset(u, module, memory_index)? & !((1 << align) - 1);
+    Ok(MemArg {
+        memory_index,
+        offset,
+        align,
+    })
+}
+
+macro_rules! atomic_access {
+    ($instruction:ident, $generator_fn_name:ident, $align:expr, [$($operand:ident),*] -> [$($result:ident)?]) => {
+        fn $generator_fn_name(
+            u: &mut Unstructured,
+            module: &Module,
+            builder: &mut CodeBuilder,
+            instructions: &mut Vec<Instruction>,
+        ) -> Result<()> {
+            builder.pop_operands(module, &[$(ValType::$operand),*]);
+            let memarg = atomic_mem_arg(
+                u,
+                module,
+                builder,
+                |a| &a.atomic_memory32,
+                |a| &a.atomic_memory64,
+                $align,
+            )?;
+            builder.push_operands(&[$(ValType::$result)?]);
+            instructions.push(Instruction::$instruction(memarg));
+            Ok(())
+        }
+    };
+}
+
+atomic_access!(I32AtomicLoad, i32_atomic_load, 2, [] -> [I32]);
+atomic_access!(I64AtomicLoad, i64_atomic_load, 3, [] -> [I64]);
+atomic_access!(I32AtomicLoad8U, i32_atomic_load8_u, 0, [] -> [I32]);
+atomic_access!(I32AtomicLoad16U, i32_atomic_load16_u, 1, [] -> [I32]);
+atomic_access!(I64AtomicLoad8U, i64_atomic_load8_u, 0, [] -> [I64]);
+atomic_access!(I64AtomicLoad16U, i64_atomic_load16_u, 1, [] -> [I64]);
+atomic_access!(I64AtomicLoad32U, i64_atomic_load32_u, 2, [] -> [I64]);
+atomic_access!(I32AtomicStore, i32_atomic_store, 2, [I32] -> []);
+atomic_access!(I64AtomicStore, i64_atomic_store, 3, [I64] -> []);
+atomic_access!(I32AtomicStore8, i32_atomic_store8, 0, [I32] -> []);
+atomic_access!(I32AtomicStore16, i32_atomic_store16, 1, [I32] -> []);
+atomic_access!(I64AtomicStore8, i64_atomic_store8, 0, [I64] -> []);
+atomic_access!(I64AtomicStore16, i64_atomic_store16, 1, [I64] -> []);
+atomic_access!(I64AtomicStore32, i64_atomic_store32, 2, [I64] -> []);
+atomic_access!(I32AtomicRmwAdd, i32_atomic_rmw_add, 2, [I32] -> [I32]);
+atomic_access!(I64AtomicRmwAdd, i64_atomic_rmw_add, 3, [I64] -> [I64]);
+atomic_access!(I32AtomicRmw8AddU, i32_atomic_rmw8_add_u, 0, [I32] -> [I32]);
+atomic_access!(I32AtomicRmw16AddU, i32_atomic_rmw16_add_u, 1, [I32] -> [I32]);
+atomic_access!(I64AtomicRmw8AddU, i64_atomic_rmw8_add_u, 0, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw16AddU, i64_atomic_rmw16_add_u, 1, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw32AddU, i64_atomic_rmw32_add_u, 2, [I64] -> [I64]);
+atomic_access!(I32AtomicRmwSub, i32_atomic_rmw_sub, 2, [I32] -> [I32]);
+atomic_access!(I64AtomicRmwSub, i64_atomic_rmw_sub, 3, [I64] -> [I64]);
+atomic_access!(I32AtomicRmw8SubU, i32_atomic_rmw8_sub_u, 0, [I32] -> [I32]);
+atomic_access!(I32AtomicRmw16SubU, i32_atomic_rmw16_sub_u, 1, [I32] -> [I32]);
+atomic_access!(I64AtomicRmw8SubU, i64_atomic_rmw8_sub_u, 0, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw16SubU, i64_atomic_rmw16_sub_u, 1, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw32SubU, i64_atomic_rmw32_sub_u, 2, [I64] -> [I64]);
+atomic_access!(I32AtomicRmwAnd, i32_atomic_rmw_and, 2, [I32] -> [I32]);
+atomic_access!(I64AtomicRmwAnd, i64_atomic_rmw_and, 3, [I64] -> [I64]);
+atomic_access!(I32AtomicRmw8AndU, i32_atomic_rmw8_and_u, 0, [I32] -> [I32]);
+atomic_access!(I32AtomicRmw16AndU, i32_atomic_rmw16_and_u, 1, [I32] -> [I32]);
+atomic_access!(I64AtomicRmw8AndU, i64_atomic_rmw8_and_u, 0, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw16AndU, i64_atomic_rmw16_and_u, 1, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw32AndU, i64_atomic_rmw32_and_u, 2, [I64] -> [I64]);
+atomic_access!(I32AtomicRmwOr, i32_atomic_rmw_or, 2, [I32] -> [I32]);
+atomic_access!(I64AtomicRmwOr, i64_atomic_rmw_or, 3, [I64] -> [I64]);
+atomic_access!(I32AtomicRmw8OrU, i32_atomic_rmw8_or_u, 0, [I32] -> [I32]);
+atomic_access!(I32AtomicRmw16OrU, i32_atomic_rmw16_or_u, 1, [I32] -> [I32]);
+atomic_access!(I64AtomicRmw8OrU, i64_atomic_rmw8_or_u, 0, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw16OrU, i64_atomic_rmw16_or_u, 1, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw32OrU, i64_atomic_rmw32_or_u, 2, [I64] -> [I64]);
+atomic_access!(I32AtomicRmwXor, i32_atomic_rmw_xor, 2, [I32] -> [I32]);
+atomic_access!(I64AtomicRmwXor, i64_atomic_rmw_xor, 3, [I64] -> [I64]);
+atomic_access!(I32AtomicRmw8XorU, i32_atomic_rmw8_xor_u, 0, [I32] -> [I32]);
+atomic_access!(I32AtomicRmw16XorU, i32_atomic_rmw16_xor_u, 1, [I32] -> [I32]);
+atomic_access!(I64AtomicRmw8XorU, i64_atomic_rmw8_xor_u, 0, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw16XorU, i64_atomic_rmw16_xor_u, 1, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw32XorU, i64_atomic_rmw32_xor_u, 2, [I64] -> [I64]);
+atomic_access!(I32AtomicRmwXchg, i32_atomic_rmw_xchg, 2, [I32] -> [I32]);
+atomic_access!(I64AtomicRmwXchg, i64_atomic_rmw_xchg, 3, [I64] -> [I64]);
+atomic_access!(I32AtomicRmw8XchgU, i32_atomic_rmw8_xchg_u, 0, [I32] -> [I32]);
+atomic_access!(I32AtomicRmw16XchgU, i32_atomic_rmw16_xchg_u, 1, [I32] -> [I32]);
+atomic_access!(I64AtomicRmw8XchgU, i64_atomic_rmw8_xchg_u, 0, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw16XchgU, i64_atomic_rmw16_xchg_u, 1, [I64] -> [I64]);
+atomic_access!(I64AtomicRmw32XchgU, i64_atomic_rmw32_xchg_u, 2, [I64] -> [I64]);
+atomic_access!(I32AtomicRmwCmpxchg, i32_atomic_rmw_cmpxchg, 2, [I32, I32] -> [I32]);
+atomic_access!(I64AtomicRmwCmpxchg, i64_atomic_rmw_cmpxchg, 3, [I64, I64] -> [I64]);
+atomic_access!(I32AtomicRmw8CmpxchgU, i32_atomic_rmw8_cmpxchg_u, 0, [I32, I32] -> [I32]);
+atomic_access!(I32AtomicRmw16CmpxchgU, i32_atomic_rmw16_cmpxchg_u, 1, [I32, I32] -> [I32]);
+atomic_access!(I64AtomicRmw8CmpxchgU, i64_atomic_rmw8_cmpxchg_u, 0, [I64, I64] -> [I64]);
+atomic_access!(I64AtomicRmw16CmpxchgU, i64_atomic_rmw16_cmpxchg_u, 1, [I64, I64] -> [I64]);
+atomic_access!(I64AtomicRmw32CmpxchgU, i64_atomic_rmw32_cmpxchg_u, 2, [I64, I64] -> [I64]);
+atomic_access!(MemoryAtomicNotify, memory_atomic_notify, 2, [I32] -> [I32]);
+
+// `memory.atomic.wait*` traps on unshared memories so these only use shared
+// memories. Their timeout is always pushed as zero so they never block.
+#[inline]
+fn memory_atomic_wait32_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_enabled(module)
+        && ((builder.allocs.shared_memory32.len() > 0
+            && builder.types_on_stack(module, &[ValType::I32, ValType::I32]))
+            || (builder.allocs.shared_memory64.len() > 0
+                && builder.types_on_stack(module, &[ValType::I64, ValType::I32])))
+}
+
+#[inline]
+fn memory_atomic_wait64_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    atomic_enabled(module)
+        && ((builder.allocs.shared_memory32.len() > 0
+            && builder.types_on_stack(module, &[ValType::I32, ValType::I64]))
+            || (builder.allocs.shared_memory64.len() > 0
+                && builder.types_on_stack(module, &[ValType::I64, ValType::I64])))
+}
+
+macro_rules! memory_atomic_wait {
+    ($instruction:ident, $generator_fn_name:ident, $align:expr, $expected:ident) => {
+        fn $generator_fn_name(
+            u: &mut Unstructured,
+            module: &Module,
+            builder: &mut CodeBuilder,
+            instructions: &mut Vec<Instruction>,
+        ) -> Result<()> {
+            builder.pop_operands(module, &[ValType::$expected]);
+            let memarg = atomic_mem_arg(
+                u,
+                module,
+                builder,
+                |a| &a.shared_memory32,
+                |a| &a.shared_memory64,
+                $align,
+            )?;
+            builder.push_operands(&[ValType::I32]);
+            instructions.push(Instruction::I64Const(0));
+            instructions.push(Instruction::$instruction(memarg));
+            Ok(())
+        }
+    };
+}
+
+memory_atomic_wait!(MemoryAtomicWait32, memory_atomic_wait32, 2, I32);
+memory_atomic_wait!(MemoryAtomicWait64, memory_atomic_wait64, 3, I64);
+
+#[inline]
+fn atomic_fence_valid(module: &Module, _: &mut CodeBuilder) -> bool {
+    module.config.threads_enabled
+}
+
+fn atomic_fence(
+    _: &mut Unstructured,
+    _: &Module,
+    _: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    instructions.push(Instruction::AtomicFence);
+    Ok(())
+}
+
 #[inline]
 fn data_drop_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     have_data(module, builder) && module.config.bulk_memory_enabled
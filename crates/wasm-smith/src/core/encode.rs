@@ -220,7 +220,7 @@ impl Module {
                     }
                     func.instruction(&wasm_encoder::Instruction::End);
                 }
-                Instructions::Arbitrary(body) => {
+                Instructions::Arbitrary(body) | Instructions::Kernel(body) => {
                     func.raw(body.iter().copied());
                 }
             }
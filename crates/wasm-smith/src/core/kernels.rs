@@ -0,0 +1,360 @@
+//! Support for generating modules around user-provided kernel functions, see
+//! [`Config::kernels`].
+
+use super::*;
+
+impl Module {
+    pub(super) fn kernels(&mut self, u: &mut Unstructured) -> Result<()> {
+        let kernel_module = if let Some(wasm) = self.config.kernels.clone() {
+            wasm
+        } else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "wasmparser")]
+        {
+            self._kernels(u, &kernel_module)
+        }
+        #[cfg(not(feature = "wasmparser"))]
+        {
+            let _ = (kernel_module, u);
+            panic!("support for `kernels` was disabled at compile time");
+        }
+    }
+
+    #[cfg(feature = "wasmparser")]
+    fn _kernels(&mut self, u: &mut Unstructured, kernel_module: &[u8]) -> Result<()> {
+        use wasm_encoder::reencode::Reencode;
+
+        wasmparser::Validator::new()
+            .validate_all(kernel_module)
+            .expect("Failed to validate `kernels` Wasm");
+
+        // Every entity of the kernel module is given a counterpart in this
+        // module, and `indices` records where each one ended up so that the
+        // kernels' bodies can be rewritten to refer to them.
+        let mut indices = KernelIndices::default();
+        let mut kernels = Vec::new();
+        let mut num_bodies = 0;
+        let mut referenced_funcs = Vec::new();
+        let mut exports = Vec::new();
+
+        for payload in wasmparser::Parser::new(0).parse_all(kernel_module) {
+            match payload.expect("Failed to read `kernels` Wasm") {
+                wasmparser::Payload::TypeSection(reader) => {
+                    for ty in reader.into_iter_err_on_gc_types() {
+                        let ty = ty.expect("Failed to read `kernels` type section");
+                        let mut val_types = |tys: &[wasmparser::ValType]| {
+                            tys.iter()
+                                .map(|t| reencoded(indices.val_type(*t)))
+                                .collect()
+                        };
+                        let func_type = Rc::new(FuncType {
+                            params: val_types(ty.params()),
+                            results: val_types(ty.results()),
+                        });
+                        self.rec_groups.push(self.types.len()..self.types.len() + 1);
+                        let index = self.add_type(SubType {
+                            is_final: true,
+                            supertype: None,
+                            composite_type: CompositeType::new_func(func_type, false),
+                        });
+                        indices.types.push(index);
+                    }
+                }
+
+                // Imports are satisfied by generated definitions rather than
+                // imports so that they don't disturb the index spaces of
+                // arbitrarily-generated imports.
+                wasmparser::Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.expect("Failed to read `kernels` import");
+                        match import.ty {
+                            wasmparser::TypeRef::Func(ty) => {
+                                let func = self.add_kernel_func(indices.types[ty as usize]);
+                                indices.funcs.push(func);
+                            }
+                            wasmparser::TypeRef::Table(ty) => {
+                                let ty = reencoded(indices.table_type(ty));
+                                let table = self.add_kernel_table(u, ty)?;
+                                indices.tables.push(table);
+                            }
+                            wasmparser::TypeRef::Memory(ty) => {
+                                let memory = self.add_kernel_memory(indices.memory_type(ty));
+                                indices.memories.push(memory);
+                            }
+                            wasmparser::TypeRef::Global(ty) => {
+                                let ty = reencoded(indices.global_type(ty));
+                                let global = self.add_kernel_global(u, ty)?;
+                                indices.globals.push(global);
+                            }
+                            wasmparser::TypeRef::Tag(ty) => {
+                                let ty = indices.tag_type(ty);
+                                let tag = self.add_kernel_tag(ty.func_type_idx);
+                                indices.tags.push(tag);
+                            }
+                        }
+                    }
+                }
+
+                wasmparser::Payload::FunctionSection(reader) => {
+                    for ty in reader {
+                        let ty = ty.expect("Failed to read `kernels` function section");
+                        let func = self.add_kernel_func(indices.types[ty as usize]);
+                        indices.funcs.push(func);
+                        kernels.push(func);
+                    }
+                }
+
+                wasmparser::Payload::TableSection(reader) => {
+                    for table in reader {
+                        let table = table.expect("Failed to read `kernels` table section");
+                        let ty = reencoded(indices.table_type(table.ty));
+                        let table = self.add_kernel_table(u, ty)?;
+                        indices.tables.push(table);
+                    }
+                }
+
+                wasmparser::Payload::MemorySection(reader) => {
+                    for ty in reader {
+                        let ty = ty.expect("Failed to read `kernels` memory section");
+                        let memory = self.add_kernel_memory(indices.memory_type(ty));
+                        indices.memories.push(memory);
+                    }
+                }
+
+                wasmparser::Payload::TagSection(reader) => {
+                    for ty in reader {
+                        let ty = ty.expect("Failed to read `kernels` tag section");
+                        let ty = indices.tag_type(ty);
+                        let tag = self.add_kernel_tag(ty.func_type_idx);
+                        indices.tags.push(tag);
+                    }
+                }
+
+                wasmparser::Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        let global = global.expect("Failed to read `kernels` global section");
+                        let ty = reencoded(indices.global_type(global.ty));
+                        let global = self.add_kernel_global(u, ty)?;
+                        indices.globals.push(global);
+                    }
+                }
+
+                wasmparser::Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.expect("Failed to read `kernels` export section");
+                        if export.kind == wasmparser::ExternalKind::Func {
+                            let func = indices.funcs[export.index as usize];
+                            if kernels.contains(&func) {
+                                exports.push((export.name.to_string(), func));
+                            }
+                        }
+                    }
+                }
+
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    let func = kernels[num_bodies];
+                    num_bodies += 1;
+
+                    let mut locals = Vec::new();
+                    let reader = body
+                        .get_locals_reader()
+                        .expect("Failed to read `kernels` locals");
+                    for local in reader {
+                        let (count, ty) = local.expect("Failed to read `kernels` locals");
+                        let ty = reencoded(indices.val_type(ty));
+                        locals.extend((0..count).map(|_| ty));
+                    }
+
+                    let mut instructions = Vec::new();
+                    let mut reader = body
+                        .get_operators_reader()
+                        .expect("Failed to read `kernels` function body");
+                    while !reader.eof() {
+                        let op = reader
+                            .read()
+                            .expect("Failed to read `kernels` function body");
+                        if let wasmparser::Operator::RefFunc { function_index } = op {
+                            referenced_funcs.push(indices.funcs[function_index as usize]);
+                        }
+                        wasm_encoder::Encode::encode(
+                            &reencoded(indices.instruction(op)),
+                            &mut instructions,
+                        );
+                    }
+
+                    self.kernel_code.insert(
+                        func,
+                        Code {
+                            locals,
+                            instructions: Instructions::Kernel(instructions),
+                        },
+                    );
+                }
+
+                wasmparser::Payload::ElementSection(_)
+                | wasmparser::Payload::DataSection(_)
+                | wasmparser::Payload::DataCountSection { .. } => {
+                    panic!("`kernels` Wasm may not contain data or element segments")
+                }
+
+                _ => {}
+            }
+        }
+
+        // Functions referenced with `ref.func` must be declared somewhere in
+        // the module for the kernel to validate.
+        if !referenced_funcs.is_empty() {
+            referenced_funcs.sort_unstable();
+            referenced_funcs.dedup();
+            self.elems.push(ElementSegment {
+                kind: ElementKind::Declared,
+                ty: RefType::FUNCREF,
+                items: Elements::Functions(referenced_funcs),
+            });
+        }
+
+        // Exports are left untouched when the user asked for a precise set of
+        // them.
+        if self.config.exports.is_none() {
+            for (name, func) in exports {
+                if self.export_names.insert(name.clone()) {
+                    self.exports.push((name, ExportKind::Func, func));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "wasmparser")]
+impl Module {
+    fn add_kernel_func(&mut self, ty: u32) -> u32 {
+        let index = self.funcs.len() as u32;
+        self.funcs.push((ty, self.func_type(ty).clone()));
+        self.num_defined_funcs += 1;
+        index
+    }
+
+    fn add_kernel_table(&mut self, u: &mut Unstructured, ty: TableType) -> Result<u32> {
+        if !self.can_add_local_or_import_table() {
+            let existing = self.tables.iter().position(|t| {
+                t.element_type == ty.element_type
+                    && t.table64 == ty.table64
+                    && t.shared == ty.shared
+            });
+            if let Some(index) = existing {
+                return Ok(index as u32);
+            }
+        }
+        let init = self.arbitrary_table_init(u, ty.element_type)?;
+        self.defined_tables.push(init);
+        self.tables.push(ty);
+        Ok(self.tables.len() as u32 - 1)
+    }
+
+    fn add_kernel_memory(&mut self, ty: MemoryType) -> u32 {
+        if !self.can_add_local_or_import_memory() {
+            // Prefer a memory which is shared exactly when the kernel's is,
+            // but accesses of an unshared memory are just as valid on a shared
+            // one.
+            let compatible = |m: &MemoryType, shared| {
+                m.memory64 == ty.memory64
+                    && m.shared == shared
+                    && m.page_size_log2 == ty.page_size_log2
+            };
+            let existing = self
+                .memories
+                .iter()
+                .position(|m| compatible(m, ty.shared))
+                .or_else(|| {
+                    if ty.shared {
+                        None
+                    } else {
+                        self.memories.iter().position(|m| compatible(m, true))
+                    }
+                });
+            if let Some(index) = existing {
+                return index as u32;
+            }
+        }
+        self.num_defined_memories += 1;
+        self.memories.push(ty);
+        self.memories.len() as u32 - 1
+    }
+
+    fn add_kernel_global(&mut self, u: &mut Unstructured, ty: GlobalType) -> Result<u32> {
+        if !self.can_add_local_or_import_global() {
+            if let Some(index) = self.globals.iter().position(|g| *g == ty) {
+                return Ok(index as u32);
+            }
+        }
+        self.add_arbitrary_global_of_type(ty, u)
+    }
+
+    fn add_kernel_tag(&mut self, ty: u32) -> u32 {
+        let func_type = self.func_type(ty).clone();
+        if !self.can_add_local_or_import_tag() {
+            if let Some(index) = self.tags.iter().position(|t| t.func_type == func_type) {
+                return index as u32;
+            }
+        }
+        self.tags.push(TagType {
+            func_type_idx: ty,
+            func_type,
+        });
+        self.num_defined_tags += 1;
+        self.tags.len() as u32 - 1
+    }
+}
+
+/// Maps the indices of entities in the kernel module to the indices of their
+/// counterparts in the generated module.
+#[cfg(feature = "wasmparser")]
+#[derive(Default)]
+struct KernelIndices {
+    types: Vec<u32>,
+    funcs: Vec<u32>,
+    tables: Vec<u32>,
+    memories: Vec<u32>,
+    globals: Vec<u32>,
+    tags: Vec<u32>,
+}
+
+#[cfg(feature = "wasmparser")]
+impl wasm_encoder::reencode::Reencode for KernelIndices {
+    type Error = std::convert::Infallible;
+
+    fn type_index(&mut self, ty: u32) -> u32 {
+        self.types[ty as usize]
+    }
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        self.funcs[func as usize]
+    }
+
+    fn table_index(&mut self, table: u32) -> u32 {
+        self.tables[table as usize]
+    }
+
+    fn memory_index(&mut self, memory: u32) -> u32 {
+        self.memories[memory as usize]
+    }
+
+    fn global_index(&mut self, global: u32) -> u32 {
+        self.globals[global as usize]
+    }
+
+    fn tag_index(&mut self, tag: u32) -> u32 {
+        self.tags[tag as usize]
+    }
+}
+
+#[cfg(feature = "wasmparser")]
+fn reencoded<T>(
+    result: std::result::Result<T, wasm_encoder::reencode::Error<std::convert::Infallible>>,
+) -> T {
+    result.expect("Failed to translate `kernels` Wasm")
+}
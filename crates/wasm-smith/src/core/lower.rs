@@ -0,0 +1,334 @@
+use super::*;
+use anyhow::{bail, Result};
+
+impl Module {
+    /// Creates a pair of modules from the same `Unstructured` input for
+    /// differential testing, where the second module is a projection of the
+    /// first onto the features enabled in `lowered`.
+    ///
+    /// Both modules are generated with `config`, so they're identical except
+    /// that the second module has been passed through
+    /// [`Module::lower_features`]. Running both in an engine and comparing
+    /// their results tests the engine's code generation for the lowered
+    /// features against its code generation for their replacements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a module can't be generated from `u`, or if
+    /// lowering the second module fails as described by
+    /// [`Module::lower_features`].
+    pub fn new_differential_pair(
+        config: Config,
+        lowered: &Config,
+        u: &mut Unstructured<'_>,
+    ) -> Result<(Module, Module)> {
+        // Generation is deterministic, so generating the second module from a
+        // copy of the remaining input produces the same module again.
+        let data = u.peek_bytes(u.len()).unwrap();
+        let mut projection = Module::new(config.clone(), &mut Unstructured::new(data))?;
+        let module = Module::new(config, u)?;
+        projection.lower_features(lowered)?;
+        Ok((module, projection))
+    }
+
+    /// Rewrites this module to only use the features enabled in `config`,
+    /// replacing instructions of disabled features with equivalent sequences
+    /// of instructions which don't use them.
+    ///
+    /// The following features can be lowered:
+    ///
+    /// * `sign_extension_ops_enabled`: sign-extension instructions are replaced
+    ///   by a pair of shifts.
+    ///
+    /// * `saturating_float_to_int_enabled`: saturating conversions are
+    ///   replaced by explicit checks for NaN and out-of-range inputs around the
+    ///   trapping conversions. This adds locals to functions using them.
+    ///
+    /// The lowered instructions are semantically equivalent to the originals,
+    /// so both versions of the module behave the same when executed. Features
+    /// enabled in `config` but not in this module's configuration are
+    /// ignored, and the configuration of this module is updated to disable the
+    /// lowered features.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any other feature, such as SIMD, is enabled in
+    /// this module's configuration but not in `config`, as lowering it isn't
+    /// supported. Like [`Module::ensure_termination`], this also returns an
+    /// error if any function body was generated with possibly-invalid bytes
+    /// rather than being generated by wasm-smith.
+    pub fn lower_features(&mut self, config: &Config) -> Result<()> {
+        let unsupported = [
+            (
+                "bulk memory",
+                self.config.bulk_memory_enabled,
+                config.bulk_memory_enabled,
+            ),
+            (
+                "custom page sizes",
+                self.config.custom_page_sizes_enabled,
+                config.custom_page_sizes_enabled,
+            ),
+            (
+                "exceptions",
+                self.config.exceptions_enabled,
+                config.exceptions_enabled,
+            ),
+            ("gc", self.config.gc_enabled, config.gc_enabled),
+            (
+                "memory64",
+                self.config.memory64_enabled,
+                config.memory64_enabled,
+            ),
+            (
+                "multi-value",
+                self.config.multi_value_enabled,
+                config.multi_value_enabled,
+            ),
+            (
+                "reference types",
+                self.config.reference_types_enabled,
+                config.reference_types_enabled,
+            ),
+            (
+                "relaxed SIMD",
+                self.config.relaxed_simd_enabled,
+                config.relaxed_simd_enabled,
+            ),
+            ("SIMD", self.config.simd_enabled, config.simd_enabled),
+            (
+                "tail calls",
+                self.config.tail_call_enabled,
+                config.tail_call_enabled,
+            ),
+            (
+                "threads",
+                self.config.threads_enabled,
+                config.threads_enabled,
+            ),
+        ];
+        for (name, enabled, target) in unsupported {
+            if enabled && !target {
+                bail!("lowering the {name} feature is not supported");
+            }
+        }
+
+        let sign_extension =
+            self.config.sign_extension_ops_enabled && !config.sign_extension_ops_enabled;
+        let saturating_float_to_int =
+            self.config.saturating_float_to_int_enabled && !config.saturating_float_to_int_enabled;
+        if !sign_extension && !saturating_float_to_int {
+            return Ok(());
+        }
+
+        let num_imported_funcs = self.funcs.len() - self.code.len();
+        for (code, (_, ty)) in self.code.iter_mut().zip(&self.funcs[num_imported_funcs..]) {
+            let instrs = match &mut code.instructions {
+                Instructions::Generated(list) => list,
+                Instructions::Arbitrary(_) => {
+                    bail!(
+                        "failed to lower the features of a function due to it \
+                         containing arbitrary instructions"
+                    )
+                }
+                Instructions::Kernel(_) => {
+                    bail!(
+                        "failed to lower the features of a function due to it \
+                         being a kernel"
+                    )
+                }
+            };
+            let mut scratch = ScratchLocals {
+                first: ty.params.len() as u32,
+                locals: &mut code.locals,
+                f32: None,
+                f64: None,
+            };
+            let mut new_insts = Vec::with_capacity(instrs.len());
+            for inst in mem::replace(instrs, vec![]) {
+                match inst {
+                    Instruction::I32Extend8S if sign_extension => {
+                        sign_extend_i32(24, &mut new_insts)
+                    }
+                    Instruction::I32Extend16S if sign_extension => {
+                        sign_extend_i32(16, &mut new_insts)
+                    }
+                    Instruction::I64Extend8S if sign_extension => {
+                        sign_extend_i64(56, &mut new_insts)
+                    }
+                    Instruction::I64Extend16S if sign_extension => {
+                        sign_extend_i64(48, &mut new_insts)
+                    }
+                    Instruction::I64Extend32S if sign_extension => {
+                        sign_extend_i64(32, &mut new_insts)
+                    }
+                    Instruction::I32TruncSatF32S
+                    | Instruction::I32TruncSatF32U
+                    | Instruction::I32TruncSatF64S
+                    | Instruction::I32TruncSatF64U
+                    | Instruction::I64TruncSatF32S
+                    | Instruction::I64TruncSatF32U
+                    | Instruction::I64TruncSatF64S
+                    | Instruction::I64TruncSatF64U
+                        if saturating_float_to_int =>
+                    {
+                        trunc_sat(inst, &mut scratch, &mut new_insts)
+                    }
+                    inst => new_insts.push(inst),
+                }
+            }
+            *instrs = new_insts;
+        }
+
+        if sign_extension {
+            self.config.sign_extension_ops_enabled = false;
+        }
+        if saturating_float_to_int {
+            self.config.saturating_float_to_int_enabled = false;
+        }
+        Ok(())
+    }
+}
+
+/// Locals added to a function for lowering its instructions, allocated on
+/// first use.
+struct ScratchLocals<'a> {
+    /// The index of the first local which isn't a parameter.
+    first: u32,
+    locals: &'a mut Vec<ValType>,
+    f32: Option<u32>,
+    f64: Option<u32>,
+}
+
+impl ScratchLocals<'_> {
+    fn get(&mut self, ty: ValType) -> u32 {
+        let slot = match ty {
+            ValType::F32 => &mut self.f32,
+            ValType::F64 => &mut self.f64,
+            _ => unreachable!(),
+        };
+        *slot.get_or_insert_with(|| {
+            self.locals.push(ty);
+            self.first + self.locals.len() as u32 - 1
+        })
+    }
+}
+
+fn sign_extend_i32(shift: i32, insts: &mut Vec<Instruction>) {
+    // [input:i32]
+    insts.push(Instruction::I32Const(shift));
+    insts.push(Instruction::I32Shl);
+    // [input << shift:i32]
+    insts.push(Instruction::I32Const(shift));
+    insts.push(Instruction::I32ShrS);
+    // [sign_extended:i32]
+}
+
+fn sign_extend_i64(shift: i64, insts: &mut Vec<Instruction>) {
+    // [input:i64]
+    insts.push(Instruction::I64Const(shift));
+    insts.push(Instruction::I64Shl);
+    // [input << shift:i64]
+    insts.push(Instruction::I64Const(shift));
+    insts.push(Instruction::I64ShrS);
+    // [sign_extended:i64]
+}
+
+/// Lowers the saturating conversion `inst` to its trapping counterpart,
+/// guarded by checks for the inputs on which it traps.
+fn trunc_sat(inst: Instruction, scratch: &mut ScratchLocals, insts: &mut Vec<Instruction>) {
+    use wasm_encoder::Instruction::*;
+
+    let (float, int, signed, trunc) = match inst {
+        I32TruncSatF32S => (ValType::F32, ValType::I32, true, I32TruncF32S),
+        I32TruncSatF32U => (ValType::F32, ValType::I32, false, I32TruncF32U),
+        I32TruncSatF64S => (ValType::F64, ValType::I32, true, I32TruncF64S),
+        I32TruncSatF64U => (ValType::F64, ValType::I32, false, I32TruncF64U),
+        I64TruncSatF32S => (ValType::F32, ValType::I64, true, I64TruncF32S),
+        I64TruncSatF32U => (ValType::F32, ValType::I64, false, I64TruncF32U),
+        I64TruncSatF64S => (ValType::F64, ValType::I64, true, I64TruncF64S),
+        I64TruncSatF64U => (ValType::F64, ValType::I64, false, I64TruncF64U),
+        _ => unreachable!(),
+    };
+    let bits = match int {
+        ValType::I32 => 32,
+        _ => 64,
+    };
+    let float_const = |x: f64| match float {
+        ValType::F32 => F32Const(x as f32),
+        _ => F64Const(x),
+    };
+    let int_const = |x: i64| match int {
+        ValType::I32 => I32Const(x as i32),
+        _ => I64Const(x),
+    };
+    let (lt, le, ge, ne) = match float {
+        ValType::F32 => (F32Lt, F32Le, F32Ge, F32Ne),
+        _ => (F64Lt, F64Le, F64Ge, F64Ne),
+    };
+
+    // The inputs below the range of the result are those less than its
+    // minimum, except for conversions of `f64` to `i32` where the minimum
+    // minus one is representable and is the largest such input. For unsigned
+    // conversions inputs in (-1, 0) are in range as they truncate to zero.
+    let (below_min, min, above_max, max) = if signed {
+        let min = -(2f64.powi(bits - 1));
+        let below_min = if float == ValType::F64 && bits == 32 {
+            vec![float_const(min - 1.0), le]
+        } else {
+            vec![float_const(min), lt]
+        };
+        let max = if bits == 32 {
+            i32::MAX.into()
+        } else {
+            i64::MAX
+        };
+        (below_min, min as i64, 2f64.powi(bits - 1), max)
+    } else {
+        (vec![float_const(-1.0), le], 0, 2f64.powi(bits), -1)
+    };
+
+    let temp = scratch.get(float);
+    let result = BlockType::Result(int);
+    // [input:float]
+    insts.push(LocalSet(temp));
+    // []
+    insts.push(LocalGet(temp));
+    insts.push(LocalGet(temp));
+    insts.push(ne);
+    // [is_nan:i32]
+    insts.push(If(result));
+    {
+        insts.push(int_const(0));
+    }
+    insts.push(Else);
+    {
+        insts.push(LocalGet(temp));
+        insts.extend(below_min);
+        // [is_below_min:i32]
+        insts.push(If(result));
+        {
+            insts.push(int_const(min));
+        }
+        insts.push(Else);
+        {
+            insts.push(LocalGet(temp));
+            insts.push(float_const(above_max));
+            insts.push(ge);
+            // [is_above_max:i32]
+            insts.push(If(result));
+            {
+                insts.push(int_const(max));
+            }
+            insts.push(Else);
+            {
+                insts.push(LocalGet(temp));
+                insts.push(trunc);
+            }
+            insts.push(End);
+        }
+        insts.push(End);
+    }
+    insts.push(End);
+    // [result:int]
+}
@@ -52,6 +52,12 @@ impl Module {
                          containing arbitrary instructions"
                     )
                 }
+                Instructions::Kernel(_) => {
+                    bail!(
+                        "failed to ensure that a function generated due to it \
+                         being a kernel"
+                    )
+                }
             };
             let mut new_insts = Vec::with_capacity(instrs.len() * 2);
 
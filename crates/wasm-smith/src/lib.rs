@@ -52,7 +52,7 @@
 
 #![deny(missing_docs, missing_debug_implementations)]
 // Needed for the `instructions!` macro in `src/code_builder.rs`.
-#![recursion_limit = "512"]
+#![recursion_limit = "1024"]
 
 mod component;
 mod config;
@@ -61,7 +61,7 @@ mod core;
 pub use crate::core::{InstructionKind, InstructionKinds, Module};
 use arbitrary::{Result, Unstructured};
 pub use component::Component;
-pub use config::{Config, MemoryOffsetChoices};
+pub use config::{Config, ImportType, MemoryOffsetChoices};
 use std::{collections::HashSet, fmt::Write, str};
 use wasm_encoder::MemoryType;
 
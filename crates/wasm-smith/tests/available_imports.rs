@@ -3,7 +3,7 @@
 use arbitrary::{Arbitrary, Unstructured};
 use rand::{rngs::SmallRng, RngCore, SeedableRng};
 use std::collections::HashMap;
-use wasm_smith::{Config, Module};
+use wasm_smith::{Config, ImportType, Module};
 use wasmparser::Validator;
 use wasmparser::{Parser, TypeRef, ValType};
 
@@ -162,3 +162,74 @@ fn import_config(
     );
     (config, available)
 }
+
+#[test]
+fn smoke_test_set_available_imports() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 512];
+    let mut num_imported = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let mut config = Config::arbitrary(&mut u).expect("arbitrary swarm");
+        config.set_available_imports([
+            (
+                "env",
+                "print",
+                ImportType::Func {
+                    params: vec![wasm_encoder::ValType::I32],
+                    results: vec![],
+                },
+            ),
+            (
+                "env",
+                "counter",
+                ImportType::Global(wasm_encoder::GlobalType {
+                    val_type: wasm_encoder::ValType::I64,
+                    mutable: true,
+                    shared: false,
+                }),
+            ),
+        ]);
+        let features = parser_features_from_config(&config);
+
+        if let Ok(module) = Module::new(config, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(features);
+            validate(&mut validator, &wasm_bytes);
+            for payload in Parser::new(0).parse_all(&wasm_bytes) {
+                if let wasmparser::Payload::ImportSection(rdr) = payload.unwrap() {
+                    for import in rdr {
+                        let import = import.unwrap();
+                        match (import.module, import.name, import.ty) {
+                            ("env", "print", TypeRef::Func(_))
+                            | ("env", "counter", TypeRef::Global(_)) => num_imported += 1,
+                            _ => panic!("import of an unknown entity: {:?}", import),
+                        }
+                    }
+                }
+            }
+        }
+    }
+    assert!(num_imported > 0);
+}
+
+#[test]
+fn smoke_test_no_imports() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 512];
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let (mut config, _) = import_config(&mut u);
+        config.max_imports = 0;
+        if let Ok(module) = Module::new(config, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            for payload in Parser::new(0).parse_all(&wasm_bytes) {
+                if let wasmparser::Payload::ImportSection(rdr) = payload.unwrap() {
+                    assert_eq!(rdr.count(), 0);
+                }
+            }
+        }
+    }
+}
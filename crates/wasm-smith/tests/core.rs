@@ -1,7 +1,7 @@
 use arbitrary::{Arbitrary, Unstructured};
 use rand::{rngs::SmallRng, RngCore, SeedableRng};
 use wasm_smith::{Config, Module};
-use wasmparser::{Validator, WasmFeatures};
+use wasmparser::{Parser, Payload, TypeRef, Validator, WasmFeatures};
 
 mod common;
 use common::{parser_features_from_config, validate};
@@ -202,6 +202,122 @@ fn smoke_test_wasm_custom_page_sizes() {
     }
 }
 
+#[test]
+fn smoke_test_disallow_data_races() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut num_atomic_accesses = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let config = Config {
+            disallow_data_races: true,
+            min_memories: 1,
+            max_memories: 4,
+            bulk_memory_enabled: true,
+            ..Config::default()
+        };
+        let Ok(module) = Module::new(config, &mut u) else {
+            continue;
+        };
+        let wasm_bytes = module.to_bytes();
+        let mut validator = Validator::new_with_features(wasm_features());
+        validate(&mut validator, &wasm_bytes);
+
+        let mut shared = Vec::new();
+        for payload in Parser::new(0).parse_all(&wasm_bytes) {
+            match payload.unwrap() {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let TypeRef::Memory(ty) = import.unwrap().ty {
+                            shared.push(ty.shared);
+                        }
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for ty in reader {
+                        shared.push(ty.unwrap().shared);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut reader = body.get_operators_reader().unwrap();
+                    while !reader.eof() {
+                        // Atomic instructions, `memory.size`, and `memory.grow`
+                        // may use any memory, everything else must not use
+                        // shared memories.
+                        let op = format!("{:?}", reader.read().unwrap());
+                        if op.contains("Atomic") {
+                            num_atomic_accesses += 1;
+                            continue;
+                        }
+                        if op.starts_with("MemorySize") || op.starts_with("MemoryGrow") {
+                            continue;
+                        }
+                        for field in ["memory: ", "mem: "] {
+                            for (i, _) in op.match_indices(field) {
+                                let rest = &op[i + field.len()..];
+                                let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap();
+                                let index = rest[..end].parse::<usize>().unwrap();
+                                assert!(!shared[index], "`{op}` accesses a shared memory");
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    assert!(num_atomic_accesses > 0);
+}
+
+#[test]
+fn smoke_test_differential_pair() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut num_lowered = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let config = Config::default();
+        let lowered = Config {
+            sign_extension_ops_enabled: false,
+            saturating_float_to_int_enabled: false,
+            ..config.clone()
+        };
+        if let Ok((module, projection)) =
+            Module::new_differential_pair(config.clone(), &lowered, &mut u)
+        {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(parser_features_from_config(&config));
+            validate(&mut validator, &wasm_bytes);
+
+            let lowered_bytes = projection.to_bytes();
+            let mut validator = Validator::new_with_features(parser_features_from_config(&lowered));
+            validate(&mut validator, &lowered_bytes);
+
+            if wasm_bytes != lowered_bytes {
+                num_lowered += 1;
+            }
+        }
+    }
+    assert!(num_lowered > 0);
+}
+
+#[test]
+fn lowering_unsupported_features_fails() {
+    let mut u = Unstructured::new(&[]);
+    let config = Config::default();
+    let lowered = Config {
+        simd_enabled: false,
+        ..config.clone()
+    };
+    let err = Module::new_differential_pair(config, &lowered, &mut u).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "lowering the SIMD feature is not supported"
+    );
+}
+
 fn wasm_features() -> WasmFeatures {
     WasmFeatures::all()
 }
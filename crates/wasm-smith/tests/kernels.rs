@@ -0,0 +1,126 @@
+#![cfg(feature = "wasmparser")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use wasm_smith::{Config, Module};
+use wasmparser::{Parser, Payload, Validator};
+
+mod common;
+use common::{parser_features_from_config, validate};
+
+#[test]
+fn smoke_test_pure_kernel() {
+    let kernel = wat::parse_str(
+        r#"
+        (module
+            (func (export "mix") (param i32 i32) (result i32)
+                (local i64)
+                local.get 0
+                local.get 1
+                i32.mul
+                i32.const 7
+                i32.rotl
+                local.get 0
+                i32.xor
+            )
+        )
+        "#,
+    )
+    .unwrap();
+    let expected = kernel_bodies(&kernel);
+
+    for module in generate(&kernel, 21) {
+        // Without any indices to rewrite the kernel is copied byte-for-byte.
+        let bodies = kernel_bodies(&module);
+        assert!(bodies.contains(&expected[0]));
+        assert!(exported_funcs(&module).contains(&"mix".to_string()));
+    }
+}
+
+#[test]
+fn smoke_test_stateful_kernel() {
+    let kernel = wat::parse_str(
+        r#"
+        (module
+            (import "env" "log" (func $log (param i32)))
+            (memory 1)
+            (global $counter (mut i32) (i32.const 0))
+            (func $helper (param i32) (result i32)
+                local.get 0
+                i32.const 1
+                i32.add
+            )
+            (func (export "step") (param i32)
+                global.get $counter
+                call $helper
+                global.set $counter
+                local.get 0
+                global.get $counter
+                i32.store
+                global.get $counter
+                call $log
+            )
+        )
+        "#,
+    )
+    .unwrap();
+
+    for module in generate(&kernel, 22) {
+        assert!(exported_funcs(&module).contains(&"step".to_string()));
+        assert!(kernel_bodies(&module).len() >= 3);
+    }
+}
+
+fn generate(kernel: &[u8], seed: u64) -> Vec<Vec<u8>> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut buf = vec![0; 512];
+    let mut modules = Vec::new();
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+
+        let mut config = Config::arbitrary(&mut u).expect("arbitrary config");
+        config.kernels = Some(kernel.to_vec());
+        config.memory64_enabled = false;
+        config.exports = None;
+
+        let features = parser_features_from_config(&config);
+        let module = match Module::new(config, &mut u) {
+            Ok(module) => module,
+            Err(_) => continue,
+        };
+        let wasm = module.to_bytes();
+        let mut validator = Validator::new_with_features(features);
+        validate(&mut validator, &wasm);
+        modules.push(wasm);
+    }
+    assert!(!modules.is_empty());
+    modules
+}
+
+fn kernel_bodies(wasm: &[u8]) -> Vec<Vec<u8>> {
+    let mut bodies = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+            let ops = body.get_operators_reader().unwrap();
+            let start = ops.original_position() - body.range().start;
+            bodies.push(body.as_bytes()[start..].to_vec());
+        }
+    }
+    bodies
+}
+
+fn exported_funcs(wasm: &[u8]) -> Vec<String> {
+    let mut names = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::ExportSection(reader) = payload.unwrap() {
+            for export in reader {
+                let export = export.unwrap();
+                if export.kind == wasmparser::ExternalKind::Func {
+                    names.push(export.name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
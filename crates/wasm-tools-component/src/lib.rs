@@ -0,0 +1,64 @@
+#[allow(warnings)]
+mod bindings;
+
+use bindings::exports::bytecodealliance::wasm_tools::tools::{Dependency, Guest};
+use wasm_compose::graph::{CompositionGraph, EncodeOptions, ExportIndex};
+
+struct Component;
+
+impl Guest for Component {
+    fn parse(contents: String) -> Result<Vec<u8>, String> {
+        wat::parse_str(contents).map_err(|e| e.to_string())
+    }
+
+    fn validate(bytes: Vec<u8>) -> Result<(), String> {
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn print(bytes: Vec<u8>, skeleton: bool) -> Result<String, String> {
+        let mut config = wasmprinter::Config::new();
+        config.print_skeleton(skeleton);
+
+        let mut output = String::new();
+        config
+            .print(&bytes, &mut wasmprinter::PrintFmtWrite(&mut output))
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
+    fn compose(root: Vec<u8>, dependencies: Vec<Dependency>) -> Result<Vec<u8>, String> {
+        compose(root, dependencies).map_err(|e| format!("{e:?}"))
+    }
+}
+
+fn compose(root: Vec<u8>, dependencies: Vec<Dependency>) -> anyhow::Result<Vec<u8>> {
+    let mut graph = CompositionGraph::new();
+    let root = graph.add_component(wasm_compose::graph::Component::from_bytes("root", root)?)?;
+    let root_instance = graph.instantiate(root)?;
+
+    for dependency in dependencies {
+        let (import, _) = graph
+            .get_component(root)
+            .unwrap()
+            .import_by_name(&dependency.name)
+            .ok_or_else(|| {
+                anyhow::anyhow!("root component has no import named `{}`", dependency.name)
+            })?;
+        let component =
+            wasm_compose::graph::Component::from_bytes(dependency.name, dependency.bytes)?;
+        let id = graph.add_component(component)?;
+        let instance = graph.instantiate(id)?;
+        graph.connect(instance, None::<ExportIndex>, root_instance, import)?;
+    }
+
+    graph.encode(EncodeOptions {
+        define_components: true,
+        export: Some(root_instance),
+        validate: true,
+    })
+}
+
+bindings::export!(Component with_types_in bindings);
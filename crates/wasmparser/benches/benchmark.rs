@@ -4,7 +4,10 @@ use once_cell::unsync::Lazy;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
-use wasmparser::{DataKind, ElementKind, Parser, Payload, Validator, VisitOperator, WasmFeatures};
+use wasmparser::{
+    BinaryReader, DataKind, ElementKind, Parser, Payload, SectionMap, Validator, VisitOperator,
+    WasmFeatures,
+};
 
 /// A benchmark input.
 pub struct BenchmarkInput {
@@ -274,6 +277,14 @@ fn define_benchmarks(c: &mut Criterion) {
             }
         })
     });
+    c.bench_function("scan/tests", |b| {
+        Lazy::force(&parse_inputs);
+        b.iter(|| {
+            for wasm in parse_inputs.iter() {
+                SectionMap::scan(wasm).unwrap();
+            }
+        })
+    });
 
     let validate_inputs = once_cell::unsync::Lazy::new(|| {
         let mut list = Vec::new();
@@ -320,7 +331,63 @@ fn define_benchmarks(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benchmark, define_benchmarks);
+/// Benchmarks decoding and skipping of LEB128-encoded integers, which are
+/// accelerated with the `simd-leb128` feature.
+fn define_leb128_benchmarks(c: &mut Criterion) {
+    // Mostly single-byte integers, as is typical of indices, with some
+    // larger ones mixed in.
+    let values = (0..100_000u32)
+        .map(|i| if i % 10 == 0 { i * 1000 } else { i % 100 })
+        .collect::<Vec<_>>();
+    let mut encoded = Vec::new();
+    for value in values.iter() {
+        wasm_encoder::Encode::encode(value, &mut encoded);
+    }
+
+    c.bench_function("leb128/read_var_u32", |b| {
+        b.iter(|| {
+            let mut reader = BinaryReader::new(&encoded, 0);
+            for _ in 0..values.len() {
+                reader.read_var_u32().unwrap();
+            }
+        })
+    });
+    c.bench_function("leb128/read_var_u32s", |b| {
+        let mut out = vec![0; values.len()];
+        b.iter(|| {
+            BinaryReader::new(&encoded, 0)
+                .read_var_u32s(&mut out)
+                .unwrap();
+        })
+    });
+
+    // Reading a `br_table` skips over all of its targets.
+    let mut module = wasm_encoder::Module::new();
+    let mut types = wasm_encoder::TypeSection::new();
+    types.ty().function([], []);
+    module.section(&types);
+    let mut funcs = wasm_encoder::FunctionSection::new();
+    funcs.function(0);
+    module.section(&funcs);
+    let mut code = wasm_encoder::CodeSection::new();
+    let mut func = wasm_encoder::Function::new([]);
+    for _ in 0..10 {
+        func.instruction(&wasm_encoder::Instruction::I32Const(0));
+        func.instruction(&wasm_encoder::Instruction::BrTable(
+            values.iter().map(|_| 0).collect(),
+            0,
+        ));
+    }
+    func.instruction(&wasm_encoder::Instruction::End);
+    code.function(&func);
+    module.section(&code);
+    let wasm = module.finish();
+    c.bench_function("leb128/br_table", |b| {
+        b.iter(|| read_all_wasm(&wasm).unwrap())
+    });
+}
+
+criterion_group!(benchmark, define_benchmarks, define_leb128_benchmarks);
 criterion_main!(benchmark);
 
 struct NopVisit;
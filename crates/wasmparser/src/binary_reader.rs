@@ -387,12 +387,7 @@ impl<'a> BinaryReader<'a> {
 
     fn read_br_table(&mut self) -> Result<BrTable<'a>> {
         let cnt = self.read_size(MAX_WASM_BR_TABLE_SIZE, "br_table")?;
-        let reader = self.skip(|reader| {
-            for _ in 0..cnt {
-                reader.read_var_u32()?;
-            }
-            Ok(())
-        })?;
+        let reader = self.skip(|reader| reader.skip_var_u32s(cnt))?;
         let default = self.read_var_u32()?;
         Ok(BrTable {
             reader,
@@ -571,6 +566,112 @@ impl<'a> BinaryReader<'a> {
         Ok(result)
     }
 
+    /// Reads `out.len()` variable length integers as `u32`s into `out`.
+    ///
+    /// This is equivalent to calling [`BinaryReader::read_var_u32`] for each
+    /// element of `out`, but with the `simd-leb128` feature runs of
+    /// single-byte integers are decoded many at a time.
+    ///
+    /// # Errors
+    ///
+    /// If any integer is invalid or the end of the input is reached.
+    pub fn read_var_u32s(&mut self, out: &mut [u32]) -> Result<()> {
+        self.read_leb128s(out, u32::from, Self::read_var_u32)
+    }
+
+    /// Reads `out.len()` variable length integers as `u64`s into `out`.
+    ///
+    /// This is equivalent to calling [`BinaryReader::read_var_u64`] for each
+    /// element of `out`, but with the `simd-leb128` feature runs of
+    /// single-byte integers are decoded many at a time.
+    ///
+    /// # Errors
+    ///
+    /// If any integer is invalid or the end of the input is reached.
+    pub fn read_var_u64s(&mut self, out: &mut [u64]) -> Result<()> {
+        self.read_leb128s(out, u64::from, Self::read_var_u64)
+    }
+
+    #[inline]
+    fn read_leb128s<T>(
+        &mut self,
+        out: &mut [T],
+        #[cfg_attr(not(feature = "simd-leb128"), allow(unused_variables))] from_byte: impl Fn(u8) -> T,
+        read_one: impl Fn(&mut Self) -> Result<T>,
+    ) -> Result<()> {
+        #[cfg_attr(not(feature = "simd-leb128"), allow(unused_mut))]
+        let mut i = 0;
+        #[cfg(feature = "simd-leb128")]
+        while out.len() - i >= crate::simd::CHUNK {
+            let Some(chunk) = self.remaining_chunk() else {
+                break;
+            };
+            if crate::simd::all_single_byte(chunk) {
+                for (dst, byte) in out[i..].iter_mut().zip(chunk) {
+                    *dst = from_byte(*byte);
+                }
+                self.position += chunk.len();
+                i += chunk.len();
+                continue;
+            }
+            // Decode the integers in this chunk one at a time so that it's
+            // only scanned once.
+            let end = self.position + chunk.len();
+            while self.position < end && i < out.len() {
+                out[i] = read_one(self)?;
+                i += 1;
+            }
+        }
+        for dst in out[i..].iter_mut() {
+            *dst = read_one(self)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "simd-leb128")]
+    #[inline]
+    fn remaining_chunk(&self) -> Option<&'a [u8; crate::simd::CHUNK]> {
+        self.buffer
+            .get(self.position..)?
+            .get(..crate::simd::CHUNK)?
+            .try_into()
+            .ok()
+    }
+
+    /// Advances the `BinaryReader` past `count` variable length integers
+    /// which must be valid `u32`s.
+    pub(crate) fn skip_var_u32s(&mut self, count: usize) -> Result<()> {
+        self.skip_leb128s(count, |reader| reader.read_var_u32().map(drop))
+    }
+
+    #[inline]
+    fn skip_leb128s(
+        &mut self,
+        #[cfg_attr(not(feature = "simd-leb128"), allow(unused_mut))] mut count: usize,
+        skip_one: impl Fn(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        #[cfg(feature = "simd-leb128")]
+        while count > 0 {
+            let Some(chunk) = self.remaining_chunk() else {
+                break;
+            };
+            match crate::simd::skip_leb128s(chunk, count) {
+                Some((integers, bytes)) => {
+                    self.position += bytes;
+                    count -= integers;
+                }
+                None => {
+                    skip_one(self)?;
+                    count -= 1;
+                }
+            }
+        }
+        for _ in 0..count {
+            skip_one(self)?;
+        }
+        Ok(())
+    }
+
     /// Executes `f` to skip some data in this binary reader and then returns a
     /// reader which will read the skipped data.
     pub fn skip(&mut self, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<Self> {
@@ -924,7 +1025,10 @@ impl<'a> BinaryReader<'a> {
                 }
                 visitor.visit_typed_select(self.read()?)
             }
-            0x1f => visitor.visit_try_table(self.read()?),
+            0x1f => {
+                let ty = self.read_block_type()?;
+                visitor.visit_try_table_catches(ty, TryTableCatches::new(self)?)
+            }
 
             0x20 => visitor.visit_local_get(self.read_var_u32()?),
             0x21 => visitor.visit_local_set(self.read_var_u32()?),
@@ -1112,9 +1216,15 @@ impl<'a> BinaryReader<'a> {
             0xe0 => visitor.visit_cont_new(self.read_var_u32()?),
             0xe1 => visitor.visit_cont_bind(self.read_var_u32()?, self.read_var_u32()?),
             0xe2 => visitor.visit_suspend(self.read_var_u32()?),
-            0xe3 => visitor.visit_resume(self.read_var_u32()?, self.read()?),
+            0xe3 => {
+                let cont_type_index = self.read_var_u32()?;
+                visitor.visit_resume_handlers(cont_type_index, ResumeTableHandlers::new(self)?)
+            }
             0xe4 => {
-                visitor.visit_resume_throw(self.read_var_u32()?, self.read_var_u32()?, self.read()?)
+                let cont_type_index = self.read_var_u32()?;
+                let tag_index = self.read_var_u32()?;
+                let handlers = ResumeTableHandlers::new(self)?;
+                visitor.visit_resume_throw_handlers(cont_type_index, tag_index, handlers)
             }
             0xe5 => visitor.visit_switch(self.read_var_u32()?, self.read_var_u32()?),
 
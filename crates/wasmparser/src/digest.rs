@@ -0,0 +1,330 @@
+use crate::parser::*;
+use crate::prelude::*;
+use crate::Result;
+use core::iter;
+use core::ops::Range;
+
+/// A hash function used by a [`DigestParser`] to compute digests.
+///
+/// This is implemented by [`Sha256`] when the `sha256` feature of this crate
+/// is enabled, and can be implemented for any other hash function.
+pub trait Digest: Default {
+    /// The digest produced by this hash function.
+    type Output;
+
+    /// Feeds `bytes` into this hash function.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Returns the digest of all the bytes fed into this hash function.
+    fn finalize(self) -> Self::Output;
+}
+
+/// The SHA-256 hash function.
+#[cfg(feature = "sha256")]
+#[derive(Default)]
+pub struct Sha256(hmac_sha256::Hash);
+
+#[cfg(feature = "sha256")]
+impl Digest for Sha256 {
+    type Output = [u8; 32];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.finalize()
+    }
+}
+
+/// A [`Parser`] which computes digests of each section, and of the whole
+/// module or component, while parsing.
+///
+/// This avoids a second pass over the input to compute content hashes, for
+/// example for registries storing large binaries. Like [`Parser::parse`],
+/// input can be fed incrementally with [`DigestParser::parse`]. Unlike it,
+/// modules and components nested within a component are parsed with the same
+/// `DigestParser`, in the same way as [`Parser::parse_all`].
+///
+/// ```
+/// use wasmparser::{Digest, DigestParser, Parser, Payload};
+///
+/// /// The 32-bit FNV-1a hash function.
+/// struct Fnv(u32);
+///
+/// impl Default for Fnv {
+///     fn default() -> Fnv {
+///         Fnv(0x811c9dc5)
+///     }
+/// }
+///
+/// impl Digest for Fnv {
+///     type Output = u32;
+///     fn update(&mut self, bytes: &[u8]) {
+///         for b in bytes {
+///             self.0 = (self.0 ^ u32::from(*b)).wrapping_mul(0x01000193);
+///         }
+///     }
+///     fn finalize(self) -> u32 {
+///         self.0
+///     }
+/// }
+///
+/// # fn foo() -> wasmparser::Result<()> {
+/// let wasm = [
+///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+///     0x03, 0x03, 0x02, 0x00, 0x00, // function section with two items
+/// ];
+/// let mut module = None;
+/// for item in DigestParser::<Fnv>::new(Parser::new(0)).parse_all(&wasm) {
+///     let (payload, digests) = item?;
+///     if let Payload::FunctionSection(_) = payload {
+///         assert_eq!(digests.sections[0].id, 3);
+///         assert_eq!(digests.sections[0].range, 10..13);
+///     }
+///     module = module.or(digests.module);
+/// }
+///
+/// let mut expected = Fnv::default();
+/// expected.update(&wasm);
+/// assert_eq!(module, Some(expected.0));
+/// # Ok(())
+/// # }
+/// # foo().unwrap();
+/// ```
+pub struct DigestParser<D: Digest> {
+    cur: Parser,
+    parents: Vec<Parser>,
+    offset: usize,
+    module: Option<D>,
+    sections: Vec<(u8, Range<usize>, D)>,
+}
+
+/// The digest of a section computed by a [`DigestParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionDigest<T> {
+    /// The id of the section.
+    pub id: u8,
+    /// The range of the contents of the section within the input, which
+    /// excludes the id and size of the section.
+    pub range: Range<usize>,
+    /// The digest of the contents of the section.
+    pub digest: T,
+}
+
+/// The digests completed by parsing a chunk of input with a
+/// [`DigestParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Digests<T> {
+    /// The digests of the sections which end with the chunk.
+    ///
+    /// This usually contains at most one section, but the last section of a
+    /// nested module or component ends along with the section containing it,
+    /// in which case the innermost section comes first. Function bodies are
+    /// parsed one chunk at a time, so the digest of a code section is
+    /// returned along with its last function body.
+    pub sections: Vec<SectionDigest<T>>,
+    /// The digest of the whole input, returned along with the
+    /// [`Payload::End`] of the outermost module or component.
+    pub module: Option<T>,
+}
+
+impl<D: Digest> DigestParser<D> {
+    /// Creates a new `DigestParser` which parses input with `parser`.
+    ///
+    /// The digest of the whole input starts at the current offset of
+    /// `parser`.
+    pub fn new(parser: Parser) -> DigestParser<D> {
+        DigestParser {
+            offset: parser.offset() as usize,
+            cur: parser,
+            parents: Vec::new(),
+            module: Some(D::default()),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Same as [`Parser::parse`], except that the digests completed by the
+    /// chunk parsed are returned along with it.
+    pub fn parse<'a>(
+        &mut self,
+        data: &'a [u8],
+        eof: bool,
+    ) -> Result<(Chunk<'a>, Digests<D::Output>)> {
+        let mut digests = Digests {
+            sections: Vec::new(),
+            module: None,
+        };
+        let (consumed, payload) = match self.cur.parse(data, eof)? {
+            Chunk::NeedMoreData(n) => return Ok((Chunk::NeedMoreData(n), digests)),
+            Chunk::Parsed { consumed, payload } => (consumed, payload),
+        };
+
+        let mut end = false;
+        match &payload {
+            Payload::ModuleSection { parser, .. } | Payload::ComponentSection { parser, .. } => {
+                self.parents.push(self.cur.clone());
+                self.cur = parser.clone();
+            }
+            Payload::End(_) => match self.parents.pop() {
+                Some(parent) => self.cur = parent,
+                None => end = true,
+            },
+            _ => {}
+        }
+        if let Some((id, range)) = payload.as_section() {
+            self.sections.push((id, range, D::default()));
+        }
+
+        // Feed the bytes parsed into the digests of the whole input and of
+        // each section they're a part of.
+        let start = self.offset;
+        self.offset += consumed;
+        let bytes = &data[..consumed];
+        if let Some(module) = &mut self.module {
+            module.update(bytes);
+        }
+        for (_, range, digest) in self.sections.iter_mut() {
+            let lo = range.start.max(start).min(self.offset);
+            let hi = range.end.max(start).min(self.offset);
+            digest.update(&bytes[lo - start..hi - start]);
+        }
+        while let Some((_, range, _)) = self.sections.last() {
+            if range.end > self.offset {
+                break;
+            }
+            let (id, range, digest) = self.sections.pop().unwrap();
+            digests.sections.push(SectionDigest {
+                id,
+                range,
+                digest: digest.finalize(),
+            });
+        }
+        if end {
+            digests.module = self.module.take().map(D::finalize);
+        }
+
+        Ok((Chunk::Parsed { consumed, payload }, digests))
+    }
+
+    /// Same as [`Parser::parse_all`], except that the digests completed by
+    /// each payload are returned along with it.
+    pub fn parse_all(
+        mut self,
+        mut data: &[u8],
+    ) -> impl Iterator<Item = Result<(Payload<'_>, Digests<D::Output>)>> {
+        let mut done = false;
+        iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let (payload, digests) = match self.parse(data, true) {
+                Err(e) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+                // This isn't possible because `eof` is always true.
+                Ok((Chunk::NeedMoreData(_), _)) => unreachable!(),
+                Ok((Chunk::Parsed { payload, consumed }, digests)) => {
+                    data = &data[consumed..];
+                    (payload, digests)
+                }
+            };
+            done = digests.module.is_some();
+            Some(Ok((payload, digests)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A digest which records the bytes fed into it.
+    #[derive(Default)]
+    struct Bytes(Vec<u8>);
+
+    impl Digest for Bytes {
+        type Output = Vec<u8>;
+        fn update(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+        fn finalize(self) -> Vec<u8> {
+            self.0
+        }
+    }
+
+    fn digests(wasm: &[u8]) -> (Vec<SectionDigest<Vec<u8>>>, Vec<u8>) {
+        let mut sections = Vec::new();
+        let mut module = None;
+        for item in DigestParser::<Bytes>::new(Parser::new(0)).parse_all(wasm) {
+            let (_, digests) = item.unwrap();
+            sections.extend(digests.sections);
+            assert!(module.is_none());
+            module = digests.module;
+        }
+        (sections, module.unwrap())
+    }
+
+    #[test]
+    fn code_section() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (result i32) i32.const 1)
+                (func (result i32) i32.const 2)
+            )
+            "#,
+        )
+        .unwrap();
+        let (sections, module) = digests(&wasm);
+        assert_eq!(module, wasm);
+        let ids = sections.iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(ids, [1, 3, 10]);
+        for section in sections {
+            assert_eq!(section.digest, &wasm[section.range]);
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let wasm = wat::parse_str(
+            r#"
+            (component
+                (core module (func) (@custom "a" "b"))
+                (component (core module))
+            )
+            "#,
+        )
+        .unwrap();
+        let (sections, module) = digests(&wasm);
+        assert_eq!(module, wasm);
+        for section in sections.iter() {
+            assert_eq!(section.digest, &wasm[section.range.clone()]);
+        }
+        // The custom section of the first module ends before the section
+        // containing the module.
+        let custom = sections.iter().position(|s| s.id == 0).unwrap();
+        assert_eq!(sections[custom + 1].id, 1);
+
+        // Chunks of input are fed incrementally.
+        let mut parser = DigestParser::<Bytes>::new(Parser::new(0));
+        let mut offset = 0;
+        let mut len = 0;
+        let mut count = 0;
+        loop {
+            let eof = len == wasm.len();
+            let (chunk, digests) = parser.parse(&wasm[offset..len], eof).unwrap();
+            match chunk {
+                Chunk::NeedMoreData(_) => len += 1,
+                Chunk::Parsed { consumed, .. } => offset += consumed,
+            }
+            count += digests.sections.len();
+            if let Some(module) = digests.module {
+                assert_eq!(module, wasm);
+                break;
+            }
+        }
+        assert_eq!(count, sections.len());
+    }
+}
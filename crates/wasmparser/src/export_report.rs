@@ -0,0 +1,272 @@
+use crate::prelude::*;
+use crate::{BinaryReaderError, Encoding, Export, ExternalKind, Parser, Payload, Result, TypeRef};
+use alloc::collections::BTreeMap;
+
+/// A report of the exports of a WebAssembly module.
+///
+/// This is created by [`ExportReport::new`], which lists every export of a
+/// module along with exports that policies commonly reject: exports of
+/// mutable globals, exports sharing a name, and exports of the start
+/// function. The module is only parsed, not validated, so the report can be
+/// created for modules which a [`Validator`](crate::Validator) would reject
+/// for exporting the same name twice.
+///
+/// ```
+/// use wasmparser::ExportReport;
+///
+/// # fn foo() -> anyhow::Result<()> {
+/// let wasm = wat::parse_str(r#"
+///     (module
+///         (global $counter (mut i32) (i32.const 0))
+///         (func $init)
+///         (start $init)
+///         (export "counter" (global $counter))
+///         (export "init" (func $init))
+///         (export "init" (global $counter))
+///     )
+/// "#)?;
+/// let report = ExportReport::new(&wasm)?;
+/// assert_eq!(report.exports().len(), 3);
+/// assert_eq!(report.mutable_globals().count(), 2);
+/// assert_eq!(report.duplicates()[0].name, "init");
+/// assert_eq!(report.start_exports().next().unwrap().name, "init");
+/// # Ok(())
+/// # }
+/// # foo().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExportReport<'a> {
+    exports: Vec<Export<'a>>,
+    mutable_globals: Vec<usize>,
+    duplicates: Vec<DuplicateExport<'a>>,
+    start: Option<u32>,
+}
+
+/// A name exported more than once, as found by [`ExportReport::new`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateExport<'a> {
+    /// The name which is exported more than once.
+    pub name: &'a str,
+    /// The positions within [`ExportReport::exports`] of every export with
+    /// this name, in the order they appear in the export section.
+    pub exports: Vec<usize>,
+}
+
+impl<'a> ExportReport<'a> {
+    /// Creates a report of the exports of the module `bytes`.
+    ///
+    /// # Errors
+    ///
+    /// If `bytes` is a component rather than a module, or if the import,
+    /// global, export, or start sections of the module fail to parse.
+    pub fn new(bytes: &'a [u8]) -> Result<ExportReport<'a>> {
+        let mut exports = Vec::new();
+        let mut global_mutability = Vec::new();
+        let mut start = None;
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    range,
+                    ..
+                } => {
+                    return Err(BinaryReaderError::new(
+                        "export reports can only be created for modules",
+                        range.start,
+                    ));
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        if let TypeRef::Global(ty) = import?.ty {
+                            global_mutability.push(ty.mutable);
+                        }
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        global_mutability.push(global?.ty.mutable);
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        exports.push(export?);
+                    }
+                }
+                Payload::StartSection { func, .. } => start = Some(func),
+                Payload::End(_) => break,
+                _ => {}
+            }
+        }
+
+        // Exports of out-of-bounds globals aren't reported as mutable, as
+        // validation is what rejects those.
+        let mutable_globals = exports
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                e.kind == ExternalKind::Global
+                    && global_mutability
+                        .get(e.index as usize)
+                        .copied()
+                        .unwrap_or(false)
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        // Exports are grouped by name in the order names are first exported.
+        let mut groups = BTreeMap::new();
+        let mut by_name = Vec::<DuplicateExport<'a>>::new();
+        for (i, export) in exports.iter().enumerate() {
+            let group = *groups.entry(export.name).or_insert_with(|| {
+                by_name.push(DuplicateExport {
+                    name: export.name,
+                    exports: Vec::new(),
+                });
+                by_name.len() - 1
+            });
+            by_name[group].exports.push(i);
+        }
+        let duplicates = by_name
+            .into_iter()
+            .filter(|d| d.exports.len() > 1)
+            .collect();
+
+        Ok(ExportReport {
+            exports,
+            mutable_globals,
+            duplicates,
+            start,
+        })
+    }
+
+    /// Returns every export of the module, in the order they appear in the
+    /// export section.
+    pub fn exports(&self) -> &[Export<'a>] {
+        &self.exports
+    }
+
+    /// Returns the exports of mutable globals, whether the global is
+    /// imported or defined by the module.
+    pub fn mutable_globals(&self) -> impl ExactSizeIterator<Item = &Export<'a>> + '_ {
+        self.mutable_globals.iter().map(|i| &self.exports[*i])
+    }
+
+    /// Returns the names which are exported more than once, in the order
+    /// they're first exported.
+    ///
+    /// The module is invalid if this isn't empty.
+    pub fn duplicates(&self) -> &[DuplicateExport<'a>] {
+        &self.duplicates
+    }
+
+    /// Returns the index of the start function of the module, if it has one.
+    pub fn start(&self) -> Option<u32> {
+        self.start
+    }
+
+    /// Returns the exports of the start function of the module.
+    pub fn start_exports(&self) -> impl Iterator<Item = &Export<'a>> + '_ {
+        self.exports
+            .iter()
+            .filter(move |e| e.kind == ExternalKind::Func && Some(e.index) == self.start)
+    }
+
+    /// Returns whether the module exports no mutable globals, no name more
+    /// than once, and not its start function.
+    pub fn is_clean(&self) -> bool {
+        self.mutable_globals.is_empty()
+            && self.duplicates.is_empty()
+            && self.start_exports().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imported_and_defined_globals() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "a" (global (mut i32)))
+                (import "env" "b" (global i32))
+                (import "env" "f" (func))
+                (global i64 (i64.const 0))
+                (global (mut f32) (f32.const 0))
+                (export "a" (global 0))
+                (export "b" (global 1))
+                (export "c" (global 2))
+                (export "d" (global 3))
+                (export "f" (func 0))
+            )
+        "#,
+        )
+        .unwrap();
+        let report = ExportReport::new(&wasm).unwrap();
+        assert_eq!(report.exports().len(), 5);
+        let names = report.mutable_globals().map(|e| e.name).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "d"]);
+        assert!(report.duplicates().is_empty());
+        assert_eq!(report.start(), None);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn duplicates_and_start() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func $a)
+                (func $b)
+                (start $b)
+                (export "x" (func $a))
+                (export "y" (func $b))
+                (export "x" (func $b))
+                (export "x" (func $a))
+            )
+        "#,
+        )
+        .unwrap();
+        let report = ExportReport::new(&wasm).unwrap();
+        assert_eq!(
+            report.duplicates(),
+            [DuplicateExport {
+                name: "x",
+                exports: vec![0, 2, 3],
+            }]
+        );
+        assert_eq!(report.start(), Some(1));
+        let names = report.start_exports().map(|e| e.name).collect::<Vec<_>>();
+        assert_eq!(names, ["y", "x"]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn clean_module() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func $start)
+                (func $f)
+                (start $start)
+                (global i32 (i32.const 0))
+                (memory 1)
+                (export "f" (func $f))
+                (export "g" (global 0))
+                (export "memory" (memory 0))
+            )
+        "#,
+        )
+        .unwrap();
+        let report = ExportReport::new(&wasm).unwrap();
+        assert_eq!(report.exports().len(), 3);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn rejects_components() {
+        let wasm = wat::parse_str("(component)").unwrap();
+        assert!(ExportReport::new(&wasm).is_err());
+    }
+}
@@ -826,15 +826,23 @@ macro_rules! bail {
 }
 
 pub use crate::binary_reader::{BinaryReader, BinaryReaderError, Result};
+pub use crate::digest::*;
+pub use crate::export_report::*;
 pub use crate::features::*;
 pub use crate::parser::*;
 pub use crate::readers::*;
+pub use crate::section_map::*;
 
 mod binary_reader;
+mod digest;
+mod export_report;
 mod features;
 mod limits;
 mod parser;
 mod readers;
+mod section_map;
+#[cfg(feature = "simd-leb128")]
+mod simd;
 
 #[cfg(feature = "validate")]
 mod resources;
@@ -27,8 +27,8 @@ pub(crate) const WASM_MODULE_VERSION: u16 = 0x1;
 //                      allows for `(import (interface "...") ...)` syntax.
 pub(crate) const WASM_COMPONENT_VERSION: u16 = 0xd;
 
-const KIND_MODULE: u16 = 0x00;
-const KIND_COMPONENT: u16 = 0x01;
+pub(crate) const KIND_MODULE: u16 = 0x00;
+pub(crate) const KIND_COMPONENT: u16 = 0x01;
 
 /// The supported encoding formats for the parser.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -306,32 +306,32 @@ pub enum Payload<'a> {
     End(usize),
 }
 
-const CUSTOM_SECTION: u8 = 0;
-const TYPE_SECTION: u8 = 1;
-const IMPORT_SECTION: u8 = 2;
-const FUNCTION_SECTION: u8 = 3;
-const TABLE_SECTION: u8 = 4;
-const MEMORY_SECTION: u8 = 5;
-const GLOBAL_SECTION: u8 = 6;
-const EXPORT_SECTION: u8 = 7;
-const START_SECTION: u8 = 8;
-const ELEMENT_SECTION: u8 = 9;
-const CODE_SECTION: u8 = 10;
-const DATA_SECTION: u8 = 11;
-const DATA_COUNT_SECTION: u8 = 12;
-const TAG_SECTION: u8 = 13;
-
-const COMPONENT_MODULE_SECTION: u8 = 1;
-const COMPONENT_CORE_INSTANCE_SECTION: u8 = 2;
-const COMPONENT_CORE_TYPE_SECTION: u8 = 3;
-const COMPONENT_SECTION: u8 = 4;
-const COMPONENT_INSTANCE_SECTION: u8 = 5;
-const COMPONENT_ALIAS_SECTION: u8 = 6;
-const COMPONENT_TYPE_SECTION: u8 = 7;
-const COMPONENT_CANONICAL_SECTION: u8 = 8;
-const COMPONENT_START_SECTION: u8 = 9;
-const COMPONENT_IMPORT_SECTION: u8 = 10;
-const COMPONENT_EXPORT_SECTION: u8 = 11;
+pub(crate) const CUSTOM_SECTION: u8 = 0;
+pub(crate) const TYPE_SECTION: u8 = 1;
+pub(crate) const IMPORT_SECTION: u8 = 2;
+pub(crate) const FUNCTION_SECTION: u8 = 3;
+pub(crate) const TABLE_SECTION: u8 = 4;
+pub(crate) const MEMORY_SECTION: u8 = 5;
+pub(crate) const GLOBAL_SECTION: u8 = 6;
+pub(crate) const EXPORT_SECTION: u8 = 7;
+pub(crate) const START_SECTION: u8 = 8;
+pub(crate) const ELEMENT_SECTION: u8 = 9;
+pub(crate) const CODE_SECTION: u8 = 10;
+pub(crate) const DATA_SECTION: u8 = 11;
+pub(crate) const DATA_COUNT_SECTION: u8 = 12;
+pub(crate) const TAG_SECTION: u8 = 13;
+
+pub(crate) const COMPONENT_MODULE_SECTION: u8 = 1;
+pub(crate) const COMPONENT_CORE_INSTANCE_SECTION: u8 = 2;
+pub(crate) const COMPONENT_CORE_TYPE_SECTION: u8 = 3;
+pub(crate) const COMPONENT_SECTION: u8 = 4;
+pub(crate) const COMPONENT_INSTANCE_SECTION: u8 = 5;
+pub(crate) const COMPONENT_ALIAS_SECTION: u8 = 6;
+pub(crate) const COMPONENT_TYPE_SECTION: u8 = 7;
+pub(crate) const COMPONENT_CANONICAL_SECTION: u8 = 8;
+pub(crate) const COMPONENT_START_SECTION: u8 = 9;
+pub(crate) const COMPONENT_IMPORT_SECTION: u8 = 10;
+pub(crate) const COMPONENT_EXPORT_SECTION: u8 = 11;
 
 impl Parser {
     /// Creates a new parser.
@@ -350,6 +350,49 @@ impl Parser {
         }
     }
 
+    /// Creates a new parser for a fragment of a module or component which
+    /// consists of only some of its sections, without a header.
+    ///
+    /// This is useful for tooling which stores the sections of binaries
+    /// separately, for example in content-addressed chunks, and wants to parse
+    /// them on their own without synthesizing a header. The `encoding` is
+    /// that of the module or component the sections are from, which would
+    /// otherwise be determined by the header. Parsing starts at the first
+    /// section, so no [`Payload::Version`] is produced, but otherwise payloads
+    /// are produced just as with [`Parser::new`], ending with [`Payload::End`].
+    ///
+    /// The sections of a fragment don't need to contain the sections they
+    /// refer to, as parsing doesn't resolve indices. For example a code section
+    /// can be parsed on its own and the caller is responsible for knowing the
+    /// types of its functions from elsewhere. The `offset` is the offset of the
+    /// first section of the fragment, and should be the offset of the sections
+    /// within the original binary for errors and ranges to match it.
+    ///
+    /// ```
+    /// use wasmparser::{Encoding, Parser, Payload};
+    ///
+    /// // A code section with a single function body containing only `end`.
+    /// let fragment = [0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b];
+    /// for payload in Parser::new_fragment(0, Encoding::Module).parse_all(&fragment) {
+    ///     match payload.unwrap() {
+    ///         Payload::CodeSectionStart { count, .. } => assert_eq!(count, 1),
+    ///         Payload::CodeSectionEntry(body) => {
+    ///             let ops = body.get_operators_reader().unwrap();
+    ///             assert_eq!(ops.into_iter().count(), 1);
+    ///         }
+    ///         Payload::End(offset) => assert_eq!(offset, fragment.len()),
+    ///         payload => panic!("unexpected payload {payload:?}"),
+    ///     }
+    /// }
+    /// ```
+    pub fn new_fragment(offset: u64, encoding: Encoding) -> Parser {
+        Parser {
+            state: State::SectionStart,
+            encoding,
+            ..Parser::new(offset)
+        }
+    }
+
     /// Tests whether `bytes` looks like a core WebAssembly module.
     ///
     /// This will inspect the first 8 bytes of `bytes` and return `true` if it
@@ -934,7 +977,52 @@ impl Parser {
     ///
     /// # parse(&b"\0asm\x01\0\0\0"[..]).unwrap();
     /// ```
-    pub fn parse_all(self, mut data: &[u8]) -> impl Iterator<Item = Result<Payload>> {
+    pub fn parse_all(self, data: &[u8]) -> impl Iterator<Item = Result<Payload<'_>>> {
+        self.parse_all_with_ranges(data)
+            .map(|item| item.map(|(payload, _)| payload))
+    }
+
+    /// Like [`Parser::parse_all`], but also returns the byte ranges of each
+    /// payload within the original byte stream.
+    ///
+    /// Ranges are relative to the start of the byte stream, as given by the
+    /// `offset` of this parser, for payloads of nested modules and components
+    /// as well. This is useful for consumers which build an index of a binary
+    /// or re-parse parts of it later, which would otherwise have to
+    /// reconstruct where section headers start. See [`PayloadRanges`] for
+    /// details.
+    ///
+    /// ```
+    /// use wasmparser::{Parser, Payload};
+    ///
+    /// let wasm = [
+    ///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    ///     0x03, 0x02, 0x01, 0x00, // function section
+    ///     0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // code section
+    /// ];
+    /// for item in Parser::new(0).parse_all_with_ranges(&wasm) {
+    ///     let (payload, ranges) = item.unwrap();
+    ///     match payload {
+    ///         Payload::FunctionSection(_) => {
+    ///             assert_eq!(ranges.range, 8..12);
+    ///             assert_eq!(ranges.contents, 10..12);
+    ///         }
+    ///         Payload::CodeSectionStart { .. } => {
+    ///             assert_eq!(ranges.range, 12..18);
+    ///             assert_eq!(ranges.contents, 14..18);
+    ///         }
+    ///         Payload::CodeSectionEntry(_) => {
+    ///             assert_eq!(ranges.range, 15..18);
+    ///             assert_eq!(ranges.contents, 16..18);
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn parse_all_with_ranges(
+        self,
+        mut data: &[u8],
+    ) -> impl Iterator<Item = Result<(Payload<'_>, PayloadRanges)>> {
         let mut stack = Vec::new();
         let mut cur = self;
         let mut done = false;
@@ -942,6 +1030,7 @@ impl Parser {
             if done {
                 return None;
             }
+            let start = cur.offset as usize;
             let payload = match cur.parse(data, true) {
                 // Propagate all errors
                 Err(e) => {
@@ -972,7 +1061,8 @@ impl Parser {
                 _ => {}
             }
 
-            Some(Ok(payload))
+            let ranges = payload.ranges(start);
+            Some(Ok((payload, ranges)))
         })
     }
 
@@ -1122,7 +1212,48 @@ impl Default for Parser {
     }
 }
 
+/// The byte ranges of a [`Payload`] within the original byte stream.
+///
+/// These are returned by [`Parser::parse_all_with_ranges`], or by
+/// [`Payload::ranges`] for consumers driving [`Parser::parse`] themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PayloadRanges {
+    /// The range of all bytes making up the payload.
+    ///
+    /// For sections this includes their id and size. For
+    /// [`Payload::CodeSectionStart`], [`Payload::ModuleSection`], and
+    /// [`Payload::ComponentSection`] it covers the entire section even though
+    /// its contents are returned as further payloads. For
+    /// [`Payload::CodeSectionEntry`] this includes the size of the function
+    /// body, and [`Payload::End`] has an empty range at the end of its module
+    /// or component.
+    pub range: Range<usize>,
+    /// The range of the contents of the payload, which excludes the id and
+    /// size of sections and of function bodies.
+    pub contents: Range<usize>,
+}
+
 impl Payload<'_> {
+    /// Returns the byte ranges of this payload, given the offset that the
+    /// [`Parser`] which produced it was at before it was parsed, as returned
+    /// by [`Parser::offset`].
+    ///
+    /// Note that ranges of [`Payload::ModuleSection`] and
+    /// [`Payload::ComponentSection`] aren't checked to be in bounds.
+    pub fn ranges(&self, start: usize) -> PayloadRanges {
+        let contents = match self {
+            Payload::Version { range, .. } => range.clone(),
+            Payload::CodeSectionEntry(body) => body.range(),
+            Payload::End(offset) => *offset..*offset,
+            _ => self.as_section().unwrap().1,
+        };
+        let range = match self {
+            Payload::Version { .. } | Payload::End(_) => contents.clone(),
+            _ => start..contents.end,
+        };
+        PayloadRanges { range, contents }
+    }
+
     /// If this `Payload` represents a section in the original wasm module then
     /// the section's id and range within the original wasm binary are returned.
     ///
@@ -1350,6 +1481,40 @@ mod tests {
         p
     }
 
+    #[test]
+    fn fragment() {
+        let mut p = Parser::new_fragment(100, Encoding::Module);
+        assert_matches!(
+            p.parse(b"\x05\x03\x01\x00\x01", false),
+            Ok(Chunk::Parsed {
+                consumed: 5,
+                payload: Payload::MemorySection(_),
+            }),
+        );
+        assert_matches!(
+            p.parse(&[], true),
+            Ok(Chunk::Parsed {
+                consumed: 0,
+                payload: Payload::End(105)
+            })
+        );
+
+        // Component fragments parse component sections.
+        let mut p = Parser::new_fragment(0, Encoding::Component);
+        assert_matches!(
+            p.parse(b"\x07\x01\x00", false),
+            Ok(Chunk::Parsed {
+                consumed: 3,
+                payload: Payload::ComponentTypeSection(_),
+            }),
+        );
+
+        // A header isn't accepted in a fragment.
+        assert!(Parser::new_fragment(0, Encoding::Module)
+            .parse(b"\0asm\x01\0\0\0", true)
+            .is_err());
+    }
+
     fn parser_after_component_header() -> Parser {
         let mut p = Parser::default();
         assert_matches!(
@@ -1661,6 +1826,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn nested_ranges() {
+        let wasm = [
+            b'\0', b'a', b's', b'm', 0x0d, 0x00, 0x01, 0x00, // component header
+            0x01, 0x0b, // module section
+            b'\0', b'a', b's', b'm', 0x01, 0x00, 0x00, 0x00, // module header
+            0x01, 0x01, 0x00, // empty type section
+            0x00, 0x02, 0x01, b'a', // custom section `a`
+        ];
+        for offset in [0, 100] {
+            let ranges = Parser::new(offset as u64)
+                .parse_all_with_ranges(&wasm)
+                .map(|item| {
+                    let (_, ranges) = item.unwrap();
+                    (
+                        ranges.range.start - offset..ranges.range.end - offset,
+                        ranges.contents.start - offset..ranges.contents.end - offset,
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                ranges,
+                [
+                    (0..8, 0..8),     // component header
+                    (8..21, 10..21),  // module section
+                    (10..18, 10..18), // module header
+                    (18..21, 20..21), // type section
+                    (21..21, 21..21), // end of the module
+                    (21..25, 23..25), // custom section
+                    (25..25, 25..25), // end of the component
+                ]
+            );
+        }
+    }
+
     #[test]
     fn nested_section_too_big() {
         let mut p = parser_after_component_header();
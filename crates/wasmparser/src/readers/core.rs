@@ -7,12 +7,15 @@ mod dylink0;
 mod elements;
 mod exports;
 mod functions;
+mod gc_layout;
 mod globals;
 mod imports;
 mod init;
 mod linking;
 mod memories;
 mod names;
+mod operator_arity;
+mod operator_info;
 mod operators;
 mod producers;
 mod reloc;
@@ -29,12 +32,15 @@ pub use self::dylink0::*;
 pub use self::elements::*;
 pub use self::exports::*;
 pub use self::functions::*;
+pub use self::gc_layout::*;
 pub use self::globals::*;
 pub use self::imports::*;
 pub use self::init::*;
 pub use self::linking::*;
 pub use self::memories::*;
 pub use self::names::*;
+pub use self::operator_arity::*;
+pub use self::operator_info::*;
 pub use self::operators::*;
 pub use self::producers::*;
 pub use self::reloc::*;
@@ -14,8 +14,8 @@
  */
 
 use crate::{
-    BinaryReader, BinaryReaderError, ConstExpr, ExternalKind, FromReader, RefType, Result,
-    SectionLimited,
+    BinaryReader, BinaryReaderError, ConstExpr, ExternalKind, FromReader, HeapType, Operator,
+    RefType, Result, SectionLimited, SectionLimitedIntoIter,
 };
 use core::ops::Range;
 
@@ -55,6 +55,135 @@ pub enum ElementItems<'a> {
     Expressions(RefType, SectionLimited<'a, ConstExpr<'a>>),
 }
 
+impl<'a> ElementItems<'a> {
+    /// Returns the type of the references produced by these items.
+    ///
+    /// Function indices are always `funcref`s.
+    pub fn ref_type(&self) -> RefType {
+        match self {
+            ElementItems::Functions(_) => RefType::FUNCREF,
+            ElementItems::Expressions(ty, _) => *ty,
+        }
+    }
+
+    /// Returns the number of items.
+    pub fn count(&self) -> u32 {
+        match self {
+            ElementItems::Functions(r) => r.count(),
+            ElementItems::Expressions(_, r) => r.count(),
+        }
+    }
+
+    /// Returns an iterator over these items which resolves `ref.func` and
+    /// `ref.null` constant expressions.
+    ///
+    /// This yields the same items regardless of whether they're encoded as
+    /// function indices or constant expressions, so that only constant
+    /// expressions which need to be evaluated, such as `global.get`, are
+    /// returned as [`ElementItem::Expr`].
+    ///
+    /// ```
+    /// use wasmparser::{ElementItem, HeapType, Parser, Payload};
+    ///
+    /// # fn main() -> wasmparser::Result<()> {
+    /// let wasm = wat::parse_str(
+    ///     r#"
+    ///         (module
+    ///             (global $g funcref (ref.null func))
+    ///             (func $f)
+    ///             (elem declare func $f)
+    ///             (elem funcref (ref.func $f) (ref.null func) (global.get $g))
+    ///         )
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// let mut items = Vec::new();
+    /// for payload in Parser::new(0).parse_all(&wasm) {
+    ///     if let Payload::ElementSection(s) = payload? {
+    ///         for element in s {
+    ///             for item in element?.items.items() {
+    ///                 items.push(item?);
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    /// assert!(matches!(items[0], ElementItem::Func(0)));
+    /// assert!(matches!(items[1], ElementItem::Func(0)));
+    /// assert!(matches!(items[2], ElementItem::Null(HeapType::FUNC)));
+    /// assert!(matches!(items[3], ElementItem::Expr(_)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn items(&self) -> ElementItemsIter<'a> {
+        ElementItemsIter {
+            inner: match self {
+                ElementItems::Functions(r) => {
+                    ElementItemsIterInner::Functions(r.clone().into_iter())
+                }
+                ElementItems::Expressions(_, r) => {
+                    ElementItemsIterInner::Expressions(r.clone().into_iter())
+                }
+            },
+        }
+    }
+}
+
+/// An item of an element segment, as returned by [`ElementItems::items`].
+#[derive(Debug, Clone)]
+pub enum ElementItem<'a> {
+    /// A reference to the function at this index, from either a function
+    /// index or a `ref.func` constant expression.
+    Func(u32),
+    /// A null reference of this heap type, from a `ref.null` constant
+    /// expression.
+    Null(HeapType),
+    /// Any other constant expression, which needs to be evaluated to produce
+    /// a reference.
+    Expr(ConstExpr<'a>),
+}
+
+/// An iterator over the items of an element segment, created with
+/// [`ElementItems::items`].
+pub struct ElementItemsIter<'a> {
+    inner: ElementItemsIterInner<'a>,
+}
+
+enum ElementItemsIterInner<'a> {
+    Functions(SectionLimitedIntoIter<'a, u32>),
+    Expressions(SectionLimitedIntoIter<'a, ConstExpr<'a>>),
+}
+
+impl<'a> Iterator for ElementItemsIter<'a> {
+    type Item = Result<ElementItem<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            ElementItemsIterInner::Functions(iter) => Some(iter.next()?.map(ElementItem::Func)),
+            ElementItemsIterInner::Expressions(iter) => Some(iter.next()?.and_then(|expr| {
+                let mut ops = expr.get_operators_reader();
+                let item = match ops.read()? {
+                    Operator::RefFunc { function_index } => ElementItem::Func(function_index),
+                    Operator::RefNull { hty } => ElementItem::Null(hty),
+                    _ => return Ok(ElementItem::Expr(expr)),
+                };
+                match ops.read()? {
+                    Operator::End if ops.eof() => Ok(item),
+                    _ => Ok(ElementItem::Expr(expr)),
+                }
+            })),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            ElementItemsIterInner::Functions(iter) => iter.size_hint(),
+            ElementItemsIterInner::Expressions(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for ElementItemsIter<'_> {}
+
 /// A reader for the element section of a WebAssembly module.
 pub type ElementSectionReader<'a> = SectionLimited<'a, Element<'a>>;
 
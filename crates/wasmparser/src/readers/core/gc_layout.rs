@@ -0,0 +1,252 @@
+use crate::prelude::*;
+use crate::{
+    ArrayType, FieldType, HeapType, Ieee32, Ieee64, StorageType, StructType, ValType, V128,
+};
+
+/// The default value of a defaultable type, which fields are initialized to by
+/// `struct.new_default` and `array.new_default`, and locals are initialized
+/// to on function entry.
+///
+/// Values of the packed storage types `i8` and `i16` are represented as
+/// [`DefaultValue::I32`], as they are on the operand stack.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DefaultValue {
+    /// An `i32` zero.
+    I32(i32),
+    /// An `i64` zero.
+    I64(i64),
+    /// An `f32` positive zero.
+    F32(Ieee32),
+    /// An `f64` positive zero.
+    F64(Ieee64),
+    /// A `v128` with all bits zero.
+    V128(V128),
+    /// A null reference of the given heap type.
+    RefNull(HeapType),
+}
+
+impl ValType {
+    /// Returns the default value of this type, or `None` if it's a
+    /// non-nullable reference type, which has no default value.
+    pub fn default_value(&self) -> Option<DefaultValue> {
+        Some(match *self {
+            ValType::I32 => DefaultValue::I32(0),
+            ValType::I64 => DefaultValue::I64(0),
+            ValType::F32 => DefaultValue::F32(Ieee32(0)),
+            ValType::F64 => DefaultValue::F64(Ieee64(0)),
+            ValType::V128 => DefaultValue::V128(V128([0; 16])),
+            ValType::Ref(r) if r.is_nullable() => DefaultValue::RefNull(r.heap_type()),
+            ValType::Ref(_) => return None,
+        })
+    }
+}
+
+impl StorageType {
+    /// Whether the type is defaultable, i.e. it is not a non-nullable
+    /// reference type.
+    pub fn is_defaultable(&self) -> bool {
+        self.unpack().is_defaultable()
+    }
+
+    /// Returns the default value of this type, or `None` if it's a
+    /// non-nullable reference type.
+    pub fn default_value(&self) -> Option<DefaultValue> {
+        self.unpack().default_value()
+    }
+
+    /// Returns the number of bytes needed to store a value of this type,
+    /// where references take `ref_size` bytes.
+    ///
+    /// Packed types take exactly as many bytes as their width, while other
+    /// types take the size of their representation on the operand stack.
+    pub fn byte_size(&self, ref_size: u32) -> u32 {
+        match *self {
+            StorageType::I8 => 1,
+            StorageType::I16 => 2,
+            StorageType::Val(ValType::I32 | ValType::F32) => 4,
+            StorageType::Val(ValType::I64 | ValType::F64) => 8,
+            StorageType::Val(ValType::V128) => 16,
+            StorageType::Val(ValType::Ref(_)) => ref_size,
+        }
+    }
+}
+
+impl FieldType {
+    /// Whether the type of this field is defaultable, see
+    /// [`StorageType::is_defaultable`].
+    pub fn is_defaultable(&self) -> bool {
+        self.element_type.is_defaultable()
+    }
+
+    /// Returns the default value of this field, see
+    /// [`StorageType::default_value`].
+    pub fn default_value(&self) -> Option<DefaultValue> {
+        self.element_type.default_value()
+    }
+}
+
+/// The layout of the fields of a struct in memory, as computed by
+/// [`StructType::layout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout {
+    /// The size of the struct in bytes, which is a multiple of its alignment.
+    pub size: u32,
+    /// The alignment of the struct in bytes.
+    pub align: u32,
+    /// The offset in bytes of each field, in the order the fields are
+    /// declared.
+    pub field_offsets: Vec<u32>,
+}
+
+impl StructType {
+    /// Whether `struct.new_default` can create this struct, i.e. all of its
+    /// fields are defaultable.
+    pub fn is_defaultable(&self) -> bool {
+        self.fields.iter().all(|f| f.is_defaultable())
+    }
+
+    /// Returns the default value of each field, as used by
+    /// `struct.new_default`, or `None` if any field isn't defaultable.
+    pub fn default_values(&self) -> Option<Vec<DefaultValue>> {
+        self.fields.iter().map(|f| f.default_value()).collect()
+    }
+
+    /// Computes the layout of this struct where references take `ref_size`
+    /// bytes.
+    ///
+    /// Fields are laid out in the order they're declared, each aligned to its
+    /// own size, which is a power of two as long as `ref_size` is.
+    pub fn layout(&self, ref_size: u32) -> StructLayout {
+        let mut size = 0;
+        let mut align = 1;
+        let mut field_offsets = Vec::with_capacity(self.fields.len());
+        for field in self.fields.iter() {
+            let field_size = field.element_type.byte_size(ref_size);
+            let field_align = field_size.max(1);
+            size = align_to(size, field_align);
+            field_offsets.push(size);
+            size += field_size;
+            align = align.max(field_align);
+        }
+        StructLayout {
+            size: align_to(size, align),
+            align,
+            field_offsets,
+        }
+    }
+}
+
+impl ArrayType {
+    /// Whether `array.new_default` can create this array, i.e. its elements
+    /// are defaultable.
+    pub fn is_defaultable(&self) -> bool {
+        self.0.is_defaultable()
+    }
+
+    /// Returns the default value of the elements of this array, as used by
+    /// `array.new_default`, or `None` if the elements aren't defaultable.
+    pub fn default_value(&self) -> Option<DefaultValue> {
+        self.0.default_value()
+    }
+
+    /// Returns the number of bytes needed to store the `len` elements of an
+    /// array of this type, where references take `ref_size` bytes.
+    ///
+    /// Elements are stored contiguously without padding, and `None` is
+    /// returned if the size overflows a `u64`.
+    pub fn elements_size(&self, len: u32, ref_size: u32) -> Option<u64> {
+        u64::from(self.0.element_type.byte_size(ref_size)).checked_mul(u64::from(len))
+    }
+}
+
+fn align_to(offset: u32, align: u32) -> u32 {
+    offset.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{
+        CompositeInnerType, DefaultValue, Ieee32, Parser, Payload, RefType, SubType, V128,
+    };
+
+    fn types(wat: &str) -> Vec<SubType> {
+        let wasm = wat::parse_str(wat).unwrap();
+        let mut types = Vec::new();
+        for payload in Parser::new(0).parse_all(&wasm) {
+            if let Payload::TypeSection(s) = payload.unwrap() {
+                for group in s {
+                    types.extend(group.unwrap().into_types());
+                }
+            }
+        }
+        types
+    }
+
+    #[test]
+    fn struct_layout() {
+        let types = types(
+            "(module
+                (type (struct (field i8) (field i32) (field (mut i16)) (field v128)))
+                (type (struct (field i8) (field (ref null 0)) (field i8)))
+                (type (struct))
+            )",
+        );
+        let layouts = types
+            .iter()
+            .map(|ty| match &ty.composite_type.inner {
+                CompositeInnerType::Struct(s) => s.layout(4),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(layouts[0].field_offsets, [0, 4, 8, 16]);
+        assert_eq!((layouts[0].size, layouts[0].align), (32, 16));
+        assert_eq!(layouts[1].field_offsets, [0, 4, 8]);
+        assert_eq!((layouts[1].size, layouts[1].align), (12, 4));
+        assert_eq!(layouts[2].field_offsets, []);
+        assert_eq!((layouts[2].size, layouts[2].align), (0, 1));
+    }
+
+    #[test]
+    fn default_values() {
+        let types = types(
+            "(module
+                (type (struct (field i8) (field f32) (field v128) (field anyref)))
+                (type (struct (field i64) (field (ref 0))))
+                (type (array (mut i16)))
+                (type (array (ref func)))
+            )",
+        );
+        let inner = |i: usize| &types[i].composite_type.inner;
+        let CompositeInnerType::Struct(s) = inner(0) else {
+            unreachable!()
+        };
+        assert!(s.is_defaultable());
+        assert_eq!(
+            s.default_values().unwrap(),
+            [
+                DefaultValue::I32(0),
+                DefaultValue::F32(Ieee32(0)),
+                DefaultValue::V128(V128([0; 16])),
+                DefaultValue::RefNull(RefType::ANYREF.heap_type()),
+            ]
+        );
+        let CompositeInnerType::Struct(s) = inner(1) else {
+            unreachable!()
+        };
+        assert!(!s.is_defaultable());
+        assert_eq!(s.default_values(), None);
+        assert_eq!(s.fields[0].default_value(), Some(DefaultValue::I64(0)));
+
+        let CompositeInnerType::Array(a) = inner(2) else {
+            unreachable!()
+        };
+        assert_eq!(a.default_value(), Some(DefaultValue::I32(0)));
+        assert_eq!(a.elements_size(10, 8), Some(20));
+        let CompositeInnerType::Array(a) = inner(3) else {
+            unreachable!()
+        };
+        assert!(!a.is_defaultable());
+        assert_eq!(a.elements_size(u32::MAX, 8), Some(u64::from(u32::MAX) * 8));
+    }
+}
@@ -0,0 +1,985 @@
+use crate::{BlockType, CompositeInnerType, FuncType, Operator, PackedIndex, SubType, ValType};
+
+/// The information about a module, and about the control frames enclosing an
+/// operator, that's needed to compute the arity of operators with
+/// [`Operator::operator_arity`].
+///
+/// This is implemented for [`FuncValidator`](crate::FuncValidator), which
+/// tracks the control frames of the function it validates, and can otherwise
+/// be implemented on top of the parsed sections of a module for tools which
+/// track control frames themselves.
+pub trait ModuleArity {
+    /// Returns the type at `type_index` in the module's type index space.
+    fn sub_type_at(&self, type_index: u32) -> Option<&SubType>;
+
+    /// Returns the type that `index`, which appears within another type,
+    /// refers to.
+    ///
+    /// The default implementation only supports indices into the module's
+    /// type index space.
+    fn sub_type_of_index(&self, index: PackedIndex) -> Option<&SubType> {
+        self.sub_type_at(index.as_module_index()?)
+    }
+
+    /// Returns the index of the type of the function at `function_index`.
+    fn type_index_of_function(&self, function_index: u32) -> Option<u32>;
+
+    /// Returns the function type of the tag at `tag_index`.
+    fn tag_type(&self, tag_index: u32) -> Option<&FuncType>;
+
+    /// Returns the type of the control frame at `depth`, where a `depth` of 0
+    /// is the innermost frame, along with whether that frame is a `loop`.
+    ///
+    /// The outermost frame is that of the function body, whose type is the
+    /// type of the function.
+    fn label_block(&self, depth: u32) -> Option<(BlockType, bool)>;
+
+    /// Returns the number of control frames enclosing the operator, including
+    /// the frame of the function body.
+    fn control_stack_height(&self) -> u32;
+}
+
+impl Operator<'_> {
+    /// Returns the number of operands this operator pops from the operand
+    /// stack and the number of results it pushes onto it, in that order.
+    ///
+    /// Many operators have a fixed arity, while the arity of others, such as
+    /// calls and branches, depends on the types in `module` or on the
+    /// enclosing control frames. Returns `None` if `module` doesn't have the
+    /// types that the operator refers to.
+    ///
+    /// Operators which transfer control away unconditionally, such as `br`
+    /// and `return`, push no results; whatever follows them is unreachable.
+    /// Operators which begin a block pop the block's parameters and push them
+    /// again as the operands of the new block, and `end` pops the results of
+    /// the block it ends and pushes them again onto the enclosing block.
+    ///
+    /// This is defined by an exhaustive match over every [`Operator`], so
+    /// operators added to [`for_each_operator!`](crate::for_each_operator)
+    /// can't be forgotten here.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wasmparser::{BlockType, FuncType, ModuleArity, Operator, PackedIndex, SubType};
+    ///
+    /// struct NoModule;
+    ///
+    /// impl ModuleArity for NoModule {
+    ///     fn sub_type_at(&self, _: u32) -> Option<&SubType> { None }
+    ///     fn type_index_of_function(&self, _: u32) -> Option<u32> { None }
+    ///     fn tag_type(&self, _: u32) -> Option<&FuncType> { None }
+    ///     fn label_block(&self, _: u32) -> Option<(BlockType, bool)> { None }
+    ///     fn control_stack_height(&self) -> u32 { 0 }
+    /// }
+    ///
+    /// assert_eq!(Operator::I32Add.operator_arity(&NoModule), Some((2, 1)));
+    /// assert_eq!(Operator::Drop.operator_arity(&NoModule), Some((1, 0)));
+    /// assert_eq!(Operator::Call { function_index: 0 }.operator_arity(&NoModule), None);
+    /// ```
+    pub fn operator_arity(&self, module: &impl ModuleArity) -> Option<(u32, u32)> {
+        let arity = match self {
+            Operator::Unreachable => (0, 0),
+            Operator::Block { blockty }
+            | Operator::Loop { blockty }
+            | Operator::Try { blockty } => {
+                let (params, _) = block_type_arity(module, *blockty)?;
+                (params, params)
+            }
+            Operator::TryTable { try_table } => {
+                let (params, _) = block_type_arity(module, try_table.ty)?;
+                (params, params)
+            }
+            Operator::If { blockty } => {
+                let (params, _) = block_type_arity(module, *blockty)?;
+                (params + 1, params)
+            }
+            Operator::Else => {
+                let (params, results) = block_type_arity(module, module.label_block(0)?.0)?;
+                (results, params)
+            }
+            Operator::End | Operator::Delegate { .. } => {
+                let (_, results) = block_type_arity(module, module.label_block(0)?.0)?;
+                (results, results)
+            }
+            Operator::Catch { tag_index } => {
+                let (_, results) = block_type_arity(module, module.label_block(0)?.0)?;
+                (results, func_arity(module.tag_type(*tag_index)?).0)
+            }
+            Operator::CatchAll => {
+                let (_, results) = block_type_arity(module, module.label_block(0)?.0)?;
+                (results, 0)
+            }
+            Operator::Throw { tag_index } => (func_arity(module.tag_type(*tag_index)?).0, 0),
+            Operator::ThrowRef => (1, 0),
+            Operator::Rethrow { .. } => (0, 0),
+            Operator::Br { relative_depth } => (label_arity(module, *relative_depth)?, 0),
+            Operator::BrIf { relative_depth } => {
+                let arity = label_arity(module, *relative_depth)?;
+                (arity + 1, arity)
+            }
+            Operator::BrTable { targets } => (label_arity(module, targets.default())? + 1, 0),
+            Operator::BrOnNull { relative_depth } => {
+                let arity = label_arity(module, *relative_depth)?;
+                (arity + 1, arity + 1)
+            }
+            Operator::BrOnNonNull { relative_depth } => {
+                let arity = label_arity(module, *relative_depth)?;
+                (arity, arity.checked_sub(1)?)
+            }
+            Operator::BrOnCast { relative_depth, .. }
+            | Operator::BrOnCastFail { relative_depth, .. } => {
+                let arity = label_arity(module, *relative_depth)?;
+                (arity, arity)
+            }
+            Operator::Return => {
+                let depth = module.control_stack_height().checked_sub(1)?;
+                let (_, results) = block_type_arity(module, module.label_block(depth)?.0)?;
+                (results, 0)
+            }
+            Operator::Call { function_index } => {
+                let ty = module.type_index_of_function(*function_index)?;
+                func_arity(func_type_at(module, ty)?)
+            }
+            Operator::ReturnCall { function_index } => {
+                let ty = module.type_index_of_function(*function_index)?;
+                (func_arity(func_type_at(module, ty)?).0, 0)
+            }
+            Operator::CallIndirect { type_index, .. } | Operator::CallRef { type_index } => {
+                let (params, results) = func_arity(func_type_at(module, *type_index)?);
+                (params + 1, results)
+            }
+            Operator::ReturnCallIndirect { type_index, .. }
+            | Operator::ReturnCallRef { type_index } => {
+                (func_arity(func_type_at(module, *type_index)?).0 + 1, 0)
+            }
+            Operator::StructNew { struct_type_index } => {
+                match &module.sub_type_at(*struct_type_index)?.composite_type.inner {
+                    CompositeInnerType::Struct(ty) => (u32::try_from(ty.fields.len()).ok()?, 1),
+                    _ => return None,
+                }
+            }
+            Operator::ArrayNewFixed { array_size, .. } => (*array_size, 1),
+            Operator::ContNew { .. } => (1, 1),
+            Operator::ContBind {
+                argument_index,
+                result_index,
+            } => {
+                let (argument_params, _) = func_arity(cont_func_type(module, *argument_index)?);
+                let (result_params, _) = func_arity(cont_func_type(module, *result_index)?);
+                (argument_params.checked_sub(result_params)? + 1, 1)
+            }
+            Operator::Suspend { tag_index } => func_arity(module.tag_type(*tag_index)?),
+            Operator::Resume {
+                cont_type_index, ..
+            } => {
+                let (params, results) = func_arity(cont_func_type(module, *cont_type_index)?);
+                (params + 1, results)
+            }
+            Operator::ResumeThrow {
+                cont_type_index,
+                tag_index,
+                ..
+            } => {
+                let (_, results) = func_arity(cont_func_type(module, *cont_type_index)?);
+                (func_arity(module.tag_type(*tag_index)?).0 + 1, results)
+            }
+            Operator::Switch {
+                cont_type_index, ..
+            } => {
+                // The last parameter of the continuation is a reference to the
+                // continuation which is switched to, and whose parameters are
+                // the results of the `switch`.
+                let ty = cont_func_type(module, *cont_type_index)?;
+                let other = match ty.params().last()? {
+                    ValType::Ref(rt) => rt.type_index()?,
+                    _ => return None,
+                };
+                let other = match &module.sub_type_of_index(other)?.composite_type.inner {
+                    CompositeInnerType::Cont(cont) => cont.0,
+                    _ => return None,
+                };
+                let other = match &module.sub_type_of_index(other)?.composite_type.inner {
+                    CompositeInnerType::Func(ty) => ty,
+                    _ => return None,
+                };
+                (func_arity(ty).0, func_arity(other).0)
+            }
+
+            // All other operators have a fixed arity.
+            Operator::Nop
+            | Operator::DataDrop { .. }
+            | Operator::ElemDrop { .. }
+            | Operator::AtomicFence => (0, 0),
+            Operator::LocalGet { .. }
+            | Operator::GlobalGet { .. }
+            | Operator::MemorySize { .. }
+            | Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::F32Const { .. }
+            | Operator::F64Const { .. }
+            | Operator::RefNull { .. }
+            | Operator::RefFunc { .. }
+            | Operator::StructNewDefault { .. }
+            | Operator::TableSize { .. }
+            | Operator::GlobalAtomicGet { .. }
+            | Operator::V128Const { .. } => (0, 1),
+            Operator::Drop
+            | Operator::LocalSet { .. }
+            | Operator::GlobalSet { .. }
+            | Operator::GlobalAtomicSet { .. } => (1, 0),
+            Operator::LocalTee { .. }
+            | Operator::I32Load { .. }
+            | Operator::I64Load { .. }
+            | Operator::F32Load { .. }
+            | Operator::F64Load { .. }
+            | Operator::I32Load8S { .. }
+            | Operator::I32Load8U { .. }
+            | Operator::I32Load16S { .. }
+            | Operator::I32Load16U { .. }
+            | Operator::I64Load8S { .. }
+            | Operator::I64Load8U { .. }
+            | Operator::I64Load16S { .. }
+            | Operator::I64Load16U { .. }
+            | Operator::I64Load32S { .. }
+            | Operator::I64Load32U { .. }
+            | Operator::MemoryGrow { .. }
+            | Operator::RefIsNull
+            | Operator::I32Eqz
+            | Operator::I64Eqz
+            | Operator::I32Clz
+            | Operator::I32Ctz
+            | Operator::I32Popcnt
+            | Operator::I64Clz
+            | Operator::I64Ctz
+            | Operator::I64Popcnt
+            | Operator::F32Abs
+            | Operator::F32Neg
+            | Operator::F32Ceil
+            | Operator::F32Floor
+            | Operator::F32Trunc
+            | Operator::F32Nearest
+            | Operator::F32Sqrt
+            | Operator::F64Abs
+            | Operator::F64Neg
+            | Operator::F64Ceil
+            | Operator::F64Floor
+            | Operator::F64Trunc
+            | Operator::F64Nearest
+            | Operator::F64Sqrt
+            | Operator::I32WrapI64
+            | Operator::I32TruncF32S
+            | Operator::I32TruncF32U
+            | Operator::I32TruncF64S
+            | Operator::I32TruncF64U
+            | Operator::I64ExtendI32S
+            | Operator::I64ExtendI32U
+            | Operator::I64TruncF32S
+            | Operator::I64TruncF32U
+            | Operator::I64TruncF64S
+            | Operator::I64TruncF64U
+            | Operator::F32ConvertI32S
+            | Operator::F32ConvertI32U
+            | Operator::F32ConvertI64S
+            | Operator::F32ConvertI64U
+            | Operator::F32DemoteF64
+            | Operator::F64ConvertI32S
+            | Operator::F64ConvertI32U
+            | Operator::F64ConvertI64S
+            | Operator::F64ConvertI64U
+            | Operator::F64PromoteF32
+            | Operator::I32ReinterpretF32
+            | Operator::I64ReinterpretF64
+            | Operator::F32ReinterpretI32
+            | Operator::F64ReinterpretI64
+            | Operator::I32Extend8S
+            | Operator::I32Extend16S
+            | Operator::I64Extend8S
+            | Operator::I64Extend16S
+            | Operator::I64Extend32S
+            | Operator::StructGet { .. }
+            | Operator::StructGetS { .. }
+            | Operator::StructGetU { .. }
+            | Operator::ArrayNewDefault { .. }
+            | Operator::ArrayLen
+            | Operator::RefTestNonNull { .. }
+            | Operator::RefTestNullable { .. }
+            | Operator::RefCastNonNull { .. }
+            | Operator::RefCastNullable { .. }
+            | Operator::AnyConvertExtern
+            | Operator::ExternConvertAny
+            | Operator::RefI31
+            | Operator::I31GetS
+            | Operator::I31GetU
+            | Operator::I32TruncSatF32S
+            | Operator::I32TruncSatF32U
+            | Operator::I32TruncSatF64S
+            | Operator::I32TruncSatF64U
+            | Operator::I64TruncSatF32S
+            | Operator::I64TruncSatF32U
+            | Operator::I64TruncSatF64S
+            | Operator::I64TruncSatF64U
+            | Operator::TableGet { .. }
+            | Operator::I32AtomicLoad { .. }
+            | Operator::I64AtomicLoad { .. }
+            | Operator::I32AtomicLoad8U { .. }
+            | Operator::I32AtomicLoad16U { .. }
+            | Operator::I64AtomicLoad8U { .. }
+            | Operator::I64AtomicLoad16U { .. }
+            | Operator::I64AtomicLoad32U { .. }
+            | Operator::GlobalAtomicRmwAdd { .. }
+            | Operator::GlobalAtomicRmwSub { .. }
+            | Operator::GlobalAtomicRmwAnd { .. }
+            | Operator::GlobalAtomicRmwOr { .. }
+            | Operator::GlobalAtomicRmwXor { .. }
+            | Operator::GlobalAtomicRmwXchg { .. }
+            | Operator::TableAtomicGet { .. }
+            | Operator::StructAtomicGet { .. }
+            | Operator::StructAtomicGetS { .. }
+            | Operator::StructAtomicGetU { .. }
+            | Operator::RefI31Shared
+            | Operator::V128Load { .. }
+            | Operator::V128Load8x8S { .. }
+            | Operator::V128Load8x8U { .. }
+            | Operator::V128Load16x4S { .. }
+            | Operator::V128Load16x4U { .. }
+            | Operator::V128Load32x2S { .. }
+            | Operator::V128Load32x2U { .. }
+            | Operator::V128Load8Splat { .. }
+            | Operator::V128Load16Splat { .. }
+            | Operator::V128Load32Splat { .. }
+            | Operator::V128Load64Splat { .. }
+            | Operator::V128Load32Zero { .. }
+            | Operator::V128Load64Zero { .. }
+            | Operator::I8x16ExtractLaneS { .. }
+            | Operator::I8x16ExtractLaneU { .. }
+            | Operator::I16x8ExtractLaneS { .. }
+            | Operator::I16x8ExtractLaneU { .. }
+            | Operator::I32x4ExtractLane { .. }
+            | Operator::I64x2ExtractLane { .. }
+            | Operator::F32x4ExtractLane { .. }
+            | Operator::F64x2ExtractLane { .. }
+            | Operator::I8x16Splat
+            | Operator::I16x8Splat
+            | Operator::I32x4Splat
+            | Operator::I64x2Splat
+            | Operator::F32x4Splat
+            | Operator::F64x2Splat
+            | Operator::V128Not
+            | Operator::V128AnyTrue
+            | Operator::I8x16Abs
+            | Operator::I8x16Neg
+            | Operator::I8x16Popcnt
+            | Operator::I8x16AllTrue
+            | Operator::I8x16Bitmask
+            | Operator::I16x8ExtAddPairwiseI8x16S
+            | Operator::I16x8ExtAddPairwiseI8x16U
+            | Operator::I16x8Abs
+            | Operator::I16x8Neg
+            | Operator::I16x8AllTrue
+            | Operator::I16x8Bitmask
+            | Operator::I16x8ExtendLowI8x16S
+            | Operator::I16x8ExtendHighI8x16S
+            | Operator::I16x8ExtendLowI8x16U
+            | Operator::I16x8ExtendHighI8x16U
+            | Operator::I32x4ExtAddPairwiseI16x8S
+            | Operator::I32x4ExtAddPairwiseI16x8U
+            | Operator::I32x4Abs
+            | Operator::I32x4Neg
+            | Operator::I32x4AllTrue
+            | Operator::I32x4Bitmask
+            | Operator::I32x4ExtendLowI16x8S
+            | Operator::I32x4ExtendHighI16x8S
+            | Operator::I32x4ExtendLowI16x8U
+            | Operator::I32x4ExtendHighI16x8U
+            | Operator::I64x2Abs
+            | Operator::I64x2Neg
+            | Operator::I64x2AllTrue
+            | Operator::I64x2Bitmask
+            | Operator::I64x2ExtendLowI32x4S
+            | Operator::I64x2ExtendHighI32x4S
+            | Operator::I64x2ExtendLowI32x4U
+            | Operator::I64x2ExtendHighI32x4U
+            | Operator::F32x4Ceil
+            | Operator::F32x4Floor
+            | Operator::F32x4Trunc
+            | Operator::F32x4Nearest
+            | Operator::F32x4Abs
+            | Operator::F32x4Neg
+            | Operator::F32x4Sqrt
+            | Operator::F64x2Ceil
+            | Operator::F64x2Floor
+            | Operator::F64x2Trunc
+            | Operator::F64x2Nearest
+            | Operator::F64x2Abs
+            | Operator::F64x2Neg
+            | Operator::F64x2Sqrt
+            | Operator::I32x4TruncSatF32x4S
+            | Operator::I32x4TruncSatF32x4U
+            | Operator::F32x4ConvertI32x4S
+            | Operator::F32x4ConvertI32x4U
+            | Operator::I32x4TruncSatF64x2SZero
+            | Operator::I32x4TruncSatF64x2UZero
+            | Operator::F64x2ConvertLowI32x4S
+            | Operator::F64x2ConvertLowI32x4U
+            | Operator::F32x4DemoteF64x2Zero
+            | Operator::F64x2PromoteLowF32x4
+            | Operator::I32x4RelaxedTruncF32x4S
+            | Operator::I32x4RelaxedTruncF32x4U
+            | Operator::I32x4RelaxedTruncF64x2SZero
+            | Operator::I32x4RelaxedTruncF64x2UZero
+            | Operator::RefAsNonNull => (1, 1),
+            Operator::I32Store { .. }
+            | Operator::I64Store { .. }
+            | Operator::F32Store { .. }
+            | Operator::F64Store { .. }
+            | Operator::I32Store8 { .. }
+            | Operator::I32Store16 { .. }
+            | Operator::I64Store8 { .. }
+            | Operator::I64Store16 { .. }
+            | Operator::I64Store32 { .. }
+            | Operator::StructSet { .. }
+            | Operator::TableSet { .. }
+            | Operator::MemoryDiscard { .. }
+            | Operator::I32AtomicStore { .. }
+            | Operator::I64AtomicStore { .. }
+            | Operator::I32AtomicStore8 { .. }
+            | Operator::I32AtomicStore16 { .. }
+            | Operator::I64AtomicStore8 { .. }
+            | Operator::I64AtomicStore16 { .. }
+            | Operator::I64AtomicStore32 { .. }
+            | Operator::TableAtomicSet { .. }
+            | Operator::StructAtomicSet { .. }
+            | Operator::V128Store { .. }
+            | Operator::V128Store8Lane { .. }
+            | Operator::V128Store16Lane { .. }
+            | Operator::V128Store32Lane { .. }
+            | Operator::V128Store64Lane { .. } => (2, 0),
+            Operator::RefEq
+            | Operator::I32Eq
+            | Operator::I32Ne
+            | Operator::I32LtS
+            | Operator::I32LtU
+            | Operator::I32GtS
+            | Operator::I32GtU
+            | Operator::I32LeS
+            | Operator::I32LeU
+            | Operator::I32GeS
+            | Operator::I32GeU
+            | Operator::I64Eq
+            | Operator::I64Ne
+            | Operator::I64LtS
+            | Operator::I64LtU
+            | Operator::I64GtS
+            | Operator::I64GtU
+            | Operator::I64LeS
+            | Operator::I64LeU
+            | Operator::I64GeS
+            | Operator::I64GeU
+            | Operator::F32Eq
+            | Operator::F32Ne
+            | Operator::F32Lt
+            | Operator::F32Gt
+            | Operator::F32Le
+            | Operator::F32Ge
+            | Operator::F64Eq
+            | Operator::F64Ne
+            | Operator::F64Lt
+            | Operator::F64Gt
+            | Operator::F64Le
+            | Operator::F64Ge
+            | Operator::I32Add
+            | Operator::I32Sub
+            | Operator::I32Mul
+            | Operator::I32DivS
+            | Operator::I32DivU
+            | Operator::I32RemS
+            | Operator::I32RemU
+            | Operator::I32And
+            | Operator::I32Or
+            | Operator::I32Xor
+            | Operator::I32Shl
+            | Operator::I32ShrS
+            | Operator::I32ShrU
+            | Operator::I32Rotl
+            | Operator::I32Rotr
+            | Operator::I64Add
+            | Operator::I64Sub
+            | Operator::I64Mul
+            | Operator::I64DivS
+            | Operator::I64DivU
+            | Operator::I64RemS
+            | Operator::I64RemU
+            | Operator::I64And
+            | Operator::I64Or
+            | Operator::I64Xor
+            | Operator::I64Shl
+            | Operator::I64ShrS
+            | Operator::I64ShrU
+            | Operator::I64Rotl
+            | Operator::I64Rotr
+            | Operator::F32Add
+            | Operator::F32Sub
+            | Operator::F32Mul
+            | Operator::F32Div
+            | Operator::F32Min
+            | Operator::F32Max
+            | Operator::F32Copysign
+            | Operator::F64Add
+            | Operator::F64Sub
+            | Operator::F64Mul
+            | Operator::F64Div
+            | Operator::F64Min
+            | Operator::F64Max
+            | Operator::F64Copysign
+            | Operator::ArrayNew { .. }
+            | Operator::ArrayNewData { .. }
+            | Operator::ArrayNewElem { .. }
+            | Operator::ArrayGet { .. }
+            | Operator::ArrayGetS { .. }
+            | Operator::ArrayGetU { .. }
+            | Operator::TableGrow { .. }
+            | Operator::MemoryAtomicNotify { .. }
+            | Operator::I32AtomicRmwAdd { .. }
+            | Operator::I64AtomicRmwAdd { .. }
+            | Operator::I32AtomicRmw8AddU { .. }
+            | Operator::I32AtomicRmw16AddU { .. }
+            | Operator::I64AtomicRmw8AddU { .. }
+            | Operator::I64AtomicRmw16AddU { .. }
+            | Operator::I64AtomicRmw32AddU { .. }
+            | Operator::I32AtomicRmwSub { .. }
+            | Operator::I64AtomicRmwSub { .. }
+            | Operator::I32AtomicRmw8SubU { .. }
+            | Operator::I32AtomicRmw16SubU { .. }
+            | Operator::I64AtomicRmw8SubU { .. }
+            | Operator::I64AtomicRmw16SubU { .. }
+            | Operator::I64AtomicRmw32SubU { .. }
+            | Operator::I32AtomicRmwAnd { .. }
+            | Operator::I64AtomicRmwAnd { .. }
+            | Operator::I32AtomicRmw8AndU { .. }
+            | Operator::I32AtomicRmw16AndU { .. }
+            | Operator::I64AtomicRmw8AndU { .. }
+            | Operator::I64AtomicRmw16AndU { .. }
+            | Operator::I64AtomicRmw32AndU { .. }
+            | Operator::I32AtomicRmwOr { .. }
+            | Operator::I64AtomicRmwOr { .. }
+            | Operator::I32AtomicRmw8OrU { .. }
+            | Operator::I32AtomicRmw16OrU { .. }
+            | Operator::I64AtomicRmw8OrU { .. }
+            | Operator::I64AtomicRmw16OrU { .. }
+            | Operator::I64AtomicRmw32OrU { .. }
+            | Operator::I32AtomicRmwXor { .. }
+            | Operator::I64AtomicRmwXor { .. }
+            | Operator::I32AtomicRmw8XorU { .. }
+            | Operator::I32AtomicRmw16XorU { .. }
+            | Operator::I64AtomicRmw8XorU { .. }
+            | Operator::I64AtomicRmw16XorU { .. }
+            | Operator::I64AtomicRmw32XorU { .. }
+            | Operator::I32AtomicRmwXchg { .. }
+            | Operator::I64AtomicRmwXchg { .. }
+            | Operator::I32AtomicRmw8XchgU { .. }
+            | Operator::I32AtomicRmw16XchgU { .. }
+            | Operator::I64AtomicRmw8XchgU { .. }
+            | Operator::I64AtomicRmw16XchgU { .. }
+            | Operator::I64AtomicRmw32XchgU { .. }
+            | Operator::GlobalAtomicRmwCmpxchg { .. }
+            | Operator::TableAtomicRmwXchg { .. }
+            | Operator::StructAtomicRmwAdd { .. }
+            | Operator::StructAtomicRmwSub { .. }
+            | Operator::StructAtomicRmwAnd { .. }
+            | Operator::StructAtomicRmwOr { .. }
+            | Operator::StructAtomicRmwXor { .. }
+            | Operator::StructAtomicRmwXchg { .. }
+            | Operator::ArrayAtomicGet { .. }
+            | Operator::ArrayAtomicGetS { .. }
+            | Operator::ArrayAtomicGetU { .. }
+            | Operator::V128Load8Lane { .. }
+            | Operator::V128Load16Lane { .. }
+            | Operator::V128Load32Lane { .. }
+            | Operator::V128Load64Lane { .. }
+            | Operator::I8x16Shuffle { .. }
+            | Operator::I8x16ReplaceLane { .. }
+            | Operator::I16x8ReplaceLane { .. }
+            | Operator::I32x4ReplaceLane { .. }
+            | Operator::I64x2ReplaceLane { .. }
+            | Operator::F32x4ReplaceLane { .. }
+            | Operator::F64x2ReplaceLane { .. }
+            | Operator::I8x16Swizzle
+            | Operator::I8x16Eq
+            | Operator::I8x16Ne
+            | Operator::I8x16LtS
+            | Operator::I8x16LtU
+            | Operator::I8x16GtS
+            | Operator::I8x16GtU
+            | Operator::I8x16LeS
+            | Operator::I8x16LeU
+            | Operator::I8x16GeS
+            | Operator::I8x16GeU
+            | Operator::I16x8Eq
+            | Operator::I16x8Ne
+            | Operator::I16x8LtS
+            | Operator::I16x8LtU
+            | Operator::I16x8GtS
+            | Operator::I16x8GtU
+            | Operator::I16x8LeS
+            | Operator::I16x8LeU
+            | Operator::I16x8GeS
+            | Operator::I16x8GeU
+            | Operator::I32x4Eq
+            | Operator::I32x4Ne
+            | Operator::I32x4LtS
+            | Operator::I32x4LtU
+            | Operator::I32x4GtS
+            | Operator::I32x4GtU
+            | Operator::I32x4LeS
+            | Operator::I32x4LeU
+            | Operator::I32x4GeS
+            | Operator::I32x4GeU
+            | Operator::I64x2Eq
+            | Operator::I64x2Ne
+            | Operator::I64x2LtS
+            | Operator::I64x2GtS
+            | Operator::I64x2LeS
+            | Operator::I64x2GeS
+            | Operator::F32x4Eq
+            | Operator::F32x4Ne
+            | Operator::F32x4Lt
+            | Operator::F32x4Gt
+            | Operator::F32x4Le
+            | Operator::F32x4Ge
+            | Operator::F64x2Eq
+            | Operator::F64x2Ne
+            | Operator::F64x2Lt
+            | Operator::F64x2Gt
+            | Operator::F64x2Le
+            | Operator::F64x2Ge
+            | Operator::V128And
+            | Operator::V128AndNot
+            | Operator::V128Or
+            | Operator::V128Xor
+            | Operator::I8x16NarrowI16x8S
+            | Operator::I8x16NarrowI16x8U
+            | Operator::I8x16Shl
+            | Operator::I8x16ShrS
+            | Operator::I8x16ShrU
+            | Operator::I8x16Add
+            | Operator::I8x16AddSatS
+            | Operator::I8x16AddSatU
+            | Operator::I8x16Sub
+            | Operator::I8x16SubSatS
+            | Operator::I8x16SubSatU
+            | Operator::I8x16MinS
+            | Operator::I8x16MinU
+            | Operator::I8x16MaxS
+            | Operator::I8x16MaxU
+            | Operator::I8x16AvgrU
+            | Operator::I16x8Q15MulrSatS
+            | Operator::I16x8NarrowI32x4S
+            | Operator::I16x8NarrowI32x4U
+            | Operator::I16x8Shl
+            | Operator::I16x8ShrS
+            | Operator::I16x8ShrU
+            | Operator::I16x8Add
+            | Operator::I16x8AddSatS
+            | Operator::I16x8AddSatU
+            | Operator::I16x8Sub
+            | Operator::I16x8SubSatS
+            | Operator::I16x8SubSatU
+            | Operator::I16x8Mul
+            | Operator::I16x8MinS
+            | Operator::I16x8MinU
+            | Operator::I16x8MaxS
+            | Operator::I16x8MaxU
+            | Operator::I16x8AvgrU
+            | Operator::I16x8ExtMulLowI8x16S
+            | Operator::I16x8ExtMulHighI8x16S
+            | Operator::I16x8ExtMulLowI8x16U
+            | Operator::I16x8ExtMulHighI8x16U
+            | Operator::I32x4Shl
+            | Operator::I32x4ShrS
+            | Operator::I32x4ShrU
+            | Operator::I32x4Add
+            | Operator::I32x4Sub
+            | Operator::I32x4Mul
+            | Operator::I32x4MinS
+            | Operator::I32x4MinU
+            | Operator::I32x4MaxS
+            | Operator::I32x4MaxU
+            | Operator::I32x4DotI16x8S
+            | Operator::I32x4ExtMulLowI16x8S
+            | Operator::I32x4ExtMulHighI16x8S
+            | Operator::I32x4ExtMulLowI16x8U
+            | Operator::I32x4ExtMulHighI16x8U
+            | Operator::I64x2Shl
+            | Operator::I64x2ShrS
+            | Operator::I64x2ShrU
+            | Operator::I64x2Add
+            | Operator::I64x2Sub
+            | Operator::I64x2Mul
+            | Operator::I64x2ExtMulLowI32x4S
+            | Operator::I64x2ExtMulHighI32x4S
+            | Operator::I64x2ExtMulLowI32x4U
+            | Operator::I64x2ExtMulHighI32x4U
+            | Operator::F32x4Add
+            | Operator::F32x4Sub
+            | Operator::F32x4Mul
+            | Operator::F32x4Div
+            | Operator::F32x4Min
+            | Operator::F32x4Max
+            | Operator::F32x4PMin
+            | Operator::F32x4PMax
+            | Operator::F64x2Add
+            | Operator::F64x2Sub
+            | Operator::F64x2Mul
+            | Operator::F64x2Div
+            | Operator::F64x2Min
+            | Operator::F64x2Max
+            | Operator::F64x2PMin
+            | Operator::F64x2PMax
+            | Operator::I8x16RelaxedSwizzle
+            | Operator::F32x4RelaxedMin
+            | Operator::F32x4RelaxedMax
+            | Operator::F64x2RelaxedMin
+            | Operator::F64x2RelaxedMax
+            | Operator::I16x8RelaxedQ15mulrS
+            | Operator::I16x8RelaxedDotI8x16I7x16S => (2, 1),
+            Operator::ArraySet { .. }
+            | Operator::MemoryInit { .. }
+            | Operator::MemoryCopy { .. }
+            | Operator::MemoryFill { .. }
+            | Operator::TableInit { .. }
+            | Operator::TableCopy { .. }
+            | Operator::TableFill { .. }
+            | Operator::ArrayAtomicSet { .. } => (3, 0),
+            Operator::Select
+            | Operator::TypedSelect { .. }
+            | Operator::MemoryAtomicWait32 { .. }
+            | Operator::MemoryAtomicWait64 { .. }
+            | Operator::I32AtomicRmwCmpxchg { .. }
+            | Operator::I64AtomicRmwCmpxchg { .. }
+            | Operator::I32AtomicRmw8CmpxchgU { .. }
+            | Operator::I32AtomicRmw16CmpxchgU { .. }
+            | Operator::I64AtomicRmw8CmpxchgU { .. }
+            | Operator::I64AtomicRmw16CmpxchgU { .. }
+            | Operator::I64AtomicRmw32CmpxchgU { .. }
+            | Operator::TableAtomicRmwCmpxchg { .. }
+            | Operator::StructAtomicRmwCmpxchg { .. }
+            | Operator::ArrayAtomicRmwAdd { .. }
+            | Operator::ArrayAtomicRmwSub { .. }
+            | Operator::ArrayAtomicRmwAnd { .. }
+            | Operator::ArrayAtomicRmwOr { .. }
+            | Operator::ArrayAtomicRmwXor { .. }
+            | Operator::ArrayAtomicRmwXchg { .. }
+            | Operator::V128Bitselect
+            | Operator::F32x4RelaxedMadd
+            | Operator::F32x4RelaxedNmadd
+            | Operator::F64x2RelaxedMadd
+            | Operator::F64x2RelaxedNmadd
+            | Operator::I8x16RelaxedLaneselect
+            | Operator::I16x8RelaxedLaneselect
+            | Operator::I32x4RelaxedLaneselect
+            | Operator::I64x2RelaxedLaneselect
+            | Operator::I32x4RelaxedDotI8x16I7x16AddS => (3, 1),
+            Operator::ArrayFill { .. }
+            | Operator::ArrayInitData { .. }
+            | Operator::ArrayInitElem { .. } => (4, 0),
+            Operator::ArrayAtomicRmwCmpxchg { .. } => (4, 1),
+            Operator::ArrayCopy { .. } => (5, 0),
+        };
+        Some(arity)
+    }
+}
+
+fn func_arity(ty: &FuncType) -> (u32, u32) {
+    (ty.params().len() as u32, ty.results().len() as u32)
+}
+
+fn func_type_at<M: ModuleArity + ?Sized>(module: &M, type_index: u32) -> Option<&FuncType> {
+    match &module.sub_type_at(type_index)?.composite_type.inner {
+        CompositeInnerType::Func(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Returns the function type of the continuation type at `type_index`.
+fn cont_func_type<M: ModuleArity + ?Sized>(module: &M, type_index: u32) -> Option<&FuncType> {
+    let func = match &module.sub_type_at(type_index)?.composite_type.inner {
+        CompositeInnerType::Cont(cont) => cont.0,
+        _ => return None,
+    };
+    match &module.sub_type_of_index(func)?.composite_type.inner {
+        CompositeInnerType::Func(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn block_type_arity<M: ModuleArity + ?Sized>(module: &M, ty: BlockType) -> Option<(u32, u32)> {
+    match ty {
+        BlockType::Empty => Some((0, 0)),
+        BlockType::Type(_) => Some((0, 1)),
+        BlockType::FuncType(index) => Some(func_arity(func_type_at(module, index)?)),
+    }
+}
+
+/// Returns the number of values which a branch to the label at `depth`
+/// passes to it.
+fn label_arity<M: ModuleArity + ?Sized>(module: &M, depth: u32) -> Option<u32> {
+    let (ty, is_loop) = module.label_block(depth)?;
+    let (params, results) = block_type_arity(module, ty)?;
+    Some(if is_loop { params } else { results })
+}
+
+#[cfg(feature = "validate")]
+impl<T: crate::WasmModuleResources> ModuleArity for crate::FuncValidator<T> {
+    fn sub_type_at(&self, type_index: u32) -> Option<&SubType> {
+        self.resources().sub_type_at(type_index)
+    }
+
+    fn sub_type_of_index(&self, index: PackedIndex) -> Option<&SubType> {
+        match index.unpack() {
+            crate::UnpackedIndex::Module(index) => self.resources().sub_type_at(index),
+            crate::UnpackedIndex::Id(id) => Some(self.resources().sub_type_at_id(id)),
+            crate::UnpackedIndex::RecGroup(_) => None,
+        }
+    }
+
+    fn type_index_of_function(&self, function_index: u32) -> Option<u32> {
+        self.resources().type_index_of_function(function_index)
+    }
+
+    fn tag_type(&self, tag_index: u32) -> Option<&FuncType> {
+        self.resources().tag_at(tag_index)
+    }
+
+    fn label_block(&self, depth: u32) -> Option<(BlockType, bool)> {
+        let frame = self.get_control_frame(usize::try_from(depth).ok()?)?;
+        Some((frame.block_type, frame.kind == crate::FrameKind::Loop))
+    }
+
+    fn control_stack_height(&self) -> u32 {
+        crate::FuncValidator::control_stack_height(self)
+    }
+}
+
+#[cfg(all(test, feature = "validate"))]
+mod tests {
+    use crate::{
+        FuncValidatorAllocations, Operator, Parser, ValidPayload, Validator, WasmFeatures,
+    };
+    use std::collections::BTreeSet;
+    use std::path::Path;
+
+    /// Checks that the arity of every operator in the functions of `wasm`
+    /// matches how the validator changes the height of the operand stack.
+    fn check_module(wasm: &[u8], seen: &mut BTreeSet<&'static str>) {
+        let mut validator = Validator::new_with_features(WasmFeatures::all());
+        let mut allocs = FuncValidatorAllocations::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            let (func, body) = match validator.payload(&payload.unwrap()).unwrap() {
+                ValidPayload::Func(func, body) => (func, body),
+                _ => continue,
+            };
+            let mut func = func.into_validator(allocs);
+            func.read_locals(&mut body.get_binary_reader()).unwrap();
+            let mut reader = body.get_operators_reader().unwrap();
+            while !reader.eof() {
+                let (op, offset) = reader.read_with_offset().unwrap();
+                let frame = func.get_control_frame(0).unwrap();
+                let (unreachable, frame_height) = (frame.unreachable, frame.height);
+                let before = func.operand_stack_height();
+                let arity = op.operator_arity(&func);
+                func.op(offset, &op).unwrap();
+                if unreachable {
+                    continue;
+                }
+                let (pops, pushes) =
+                    arity.unwrap_or_else(|| panic!("no arity for {op:?} at {offset:#x}"));
+                seen.insert(op.info().name);
+                assert!(
+                    before - frame_height as u32 >= pops,
+                    "{op:?} at {offset:#x} pops {pops} of {} operands",
+                    before - frame_height as u32
+                );
+                let diverges = matches!(
+                    op,
+                    Operator::Unreachable
+                        | Operator::Br { .. }
+                        | Operator::BrTable { .. }
+                        | Operator::Return
+                        | Operator::ReturnCall { .. }
+                        | Operator::ReturnCallIndirect { .. }
+                        | Operator::ReturnCallRef { .. }
+                        | Operator::Throw { .. }
+                        | Operator::ThrowRef
+                        | Operator::Rethrow { .. }
+                );
+                if !diverges && func.get_control_frame(0).is_some() {
+                    let after = func.operand_stack_height();
+                    assert_eq!(
+                        i64::from(after) - i64::from(before),
+                        i64::from(pushes) - i64::from(pops),
+                        "{op:?} at {offset:#x}"
+                    );
+                }
+            }
+            func.finish(reader.original_position()).unwrap();
+            allocs = func.into_allocations();
+        }
+    }
+
+    fn check_dir(dir: &Path, seen: &mut BTreeSet<&'static str>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                check_dir(&path, seen);
+                continue;
+            }
+            let wasms = match path.extension().and_then(|e| e.to_str()) {
+                Some("wat") => match wat::parse_file(&path) {
+                    Ok(wasm) => vec![wasm],
+                    Err(_) => continue,
+                },
+                Some("wast") => {
+                    let contents = std::fs::read_to_string(&path).unwrap();
+                    let buf = match wast::parser::ParseBuffer::new(&contents) {
+                        Ok(buf) => buf,
+                        Err(_) => continue,
+                    };
+                    let wast = match wast::parser::parse::<wast::Wast>(&buf) {
+                        Ok(wast) => wast,
+                        Err(_) => continue,
+                    };
+                    wast.directives
+                        .into_iter()
+                        .filter_map(|directive| match directive {
+                            wast::WastDirective::Module(mut module)
+                            | wast::WastDirective::ModuleDefinition(mut module) => {
+                                module.encode().ok()
+                            }
+                            _ => None,
+                        })
+                        .collect()
+                }
+                _ => continue,
+            };
+            for wasm in wasms {
+                let mut validator = Validator::new_with_features(WasmFeatures::all());
+                if validator.validate_all(&wasm).is_ok() {
+                    check_module(&wasm, seen);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arity_matches_validator() {
+        let tests = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests");
+        let mut seen = BTreeSet::new();
+        check_dir(&tests.join("local"), &mut seen);
+        check_dir(&tests.join("testsuite"), &mut seen);
+        assert!(seen.len() > 100, "only checked {} operators", seen.len());
+    }
+}
@@ -0,0 +1,546 @@
+use crate::{Operator, WasmFeatures};
+
+/// Static metadata about a kind of [`Operator`].
+///
+/// This is generated from the same [`for_each_operator!`] table that defines
+/// [`Operator`] itself so it can't drift out of sync with the parser. Use
+/// [`Operator::info`] to look up the information for an operator that was
+/// parsed, or [`OperatorInfo::all`] to iterate over every known operator.
+///
+/// [`for_each_operator!`]: crate::for_each_operator
+#[derive(Debug, Clone, Copy)]
+pub struct OperatorInfo {
+    /// The name of the [`Operator`] variant, for example `"I32Add"`.
+    pub name: &'static str,
+
+    /// The text-format mnemonic of this operator, for example `"i32.add"`.
+    pub mnemonic: &'static str,
+
+    /// The name of the proposal that introduced this operator, for example
+    /// `"simd"`, or `"mvp"` for operators present in the initial release of
+    /// WebAssembly.
+    ///
+    /// Apart from `"mvp"` this is the name of the corresponding
+    /// [`WasmFeatures`] method.
+    pub proposal: &'static str,
+
+    /// The kinds of the immediates of this operator, in the order that they
+    /// are encoded in the binary format.
+    pub immediates: &'static [ImmediateKind],
+
+    /// Whether executing this operator can trap.
+    ///
+    /// Traps from exhausting resources, such as the call stack or the GC heap,
+    /// are not taken into account here.
+    pub can_trap: bool,
+
+    /// Whether this operator reads, writes, or otherwise inspects a linear
+    /// memory.
+    pub accesses_memory: bool,
+
+    enabled: fn(&WasmFeatures) -> bool,
+}
+
+/// The kind of an immediate of an [`Operator`], as reported by
+/// [`OperatorInfo::immediates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ImmediateKind {
+    /// A [`BlockType`](crate::BlockType).
+    BlockType,
+    /// A relative depth of an enclosing label.
+    LabelIndex,
+    /// A [`BrTable`](crate::BrTable).
+    BrTable,
+    /// A [`TryTable`](crate::TryTable).
+    TryTable,
+    /// A [`ResumeTable`](crate::ResumeTable).
+    ResumeTable,
+    /// An index into the function index space.
+    FunctionIndex,
+    /// An index into the type index space.
+    TypeIndex,
+    /// An index into the table index space.
+    TableIndex,
+    /// An index into the memory index space.
+    MemoryIndex,
+    /// An index into the locals of the current function.
+    LocalIndex,
+    /// An index into the global index space.
+    GlobalIndex,
+    /// An index into the tag index space.
+    TagIndex,
+    /// An index into the data segment index space.
+    DataIndex,
+    /// An index into the element segment index space.
+    ElemIndex,
+    /// An index of a field within a struct type.
+    FieldIndex,
+    /// A [`MemArg`](crate::MemArg).
+    MemArg,
+    /// An atomic [`Ordering`](crate::Ordering).
+    Ordering,
+    /// A lane index of a `v128` value.
+    LaneIndex,
+    /// The 16 lane indices of an `i8x16.shuffle`.
+    Shuffle,
+    /// A number of operands, such as the size of `array.new_fixed`.
+    Count,
+    /// A [`ValType`](crate::ValType).
+    ValType,
+    /// A [`HeapType`](crate::HeapType).
+    HeapType,
+    /// A [`RefType`](crate::RefType).
+    RefType,
+    /// An `i32` constant.
+    I32,
+    /// An `i64` constant.
+    I64,
+    /// An `f32` constant.
+    F32,
+    /// An `f64` constant.
+    F64,
+    /// A `v128` constant.
+    V128,
+}
+
+impl OperatorInfo {
+    /// Returns the information for every operator known to this crate.
+    pub fn all() -> &'static [OperatorInfo] {
+        ALL
+    }
+
+    /// Returns whether this operator is allowed with the given `features`.
+    pub fn is_enabled(&self, features: &WasmFeatures) -> bool {
+        (self.enabled)(features)
+    }
+}
+
+impl Operator<'_> {
+    /// Returns the static [`OperatorInfo`] describing this kind of operator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wasmparser::{MemArg, Operator};
+    ///
+    /// let memarg = MemArg { align: 2, max_align: 2, offset: 0, memory: 0 };
+    /// let info = Operator::I32Load { memarg }.info();
+    /// assert_eq!(info.mnemonic, "i32.load");
+    /// assert_eq!(info.proposal, "mvp");
+    /// assert!(info.can_trap);
+    /// assert!(info.accesses_memory);
+    /// ```
+    pub fn info(&self) -> &'static OperatorInfo {
+        &ALL[self.info_index()]
+    }
+}
+
+macro_rules! define_operator_info {
+    ($(@$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        static ALL: &[OperatorInfo] = &[
+            $(
+                OperatorInfo {
+                    name: stringify!($op),
+                    mnemonic: define_operator_info!(mnemonic $op $visit),
+                    proposal: stringify!($proposal),
+                    immediates: &[$($(define_operator_info!(immediate $op $arg)),*)?],
+                    can_trap: define_operator_info!(trap $op)
+                        $($(|| define_operator_info!(memarg $arg))*)?,
+                    accesses_memory: false $($(|| define_operator_info!(memory $arg))*)?,
+                    enabled: define_operator_info!(enabled $proposal),
+                },
+            )*
+        ];
+
+        impl Operator<'_> {
+            fn info_index(&self) -> usize {
+                #[allow(non_camel_case_types)]
+                enum Index {
+                    $($op,)*
+                }
+                match self {
+                    $(Operator::$op { .. } => Index::$op as usize,)*
+                }
+            }
+        }
+    };
+
+    // A few operators share a mnemonic and are distinguished by their
+    // immediates in the text format, so their names can't be derived from the
+    // name of the visitor method.
+    (mnemonic TypedSelect $visit:ident) => ("select");
+    (mnemonic RefTestNonNull $visit:ident) => ("ref.test");
+    (mnemonic RefTestNullable $visit:ident) => ("ref.test");
+    (mnemonic RefCastNonNull $visit:ident) => ("ref.cast");
+    (mnemonic RefCastNullable $visit:ident) => ("ref.cast");
+    (mnemonic $op:ident $visit:ident) => ({
+        const MNEMONIC: &Mnemonic = &Mnemonic::new(stringify!($visit));
+        MNEMONIC.as_str()
+    });
+
+    (immediate I32Const value) => (ImmediateKind::I32);
+    (immediate I64Const value) => (ImmediateKind::I64);
+    (immediate F32Const value) => (ImmediateKind::F32);
+    (immediate F64Const value) => (ImmediateKind::F64);
+    (immediate V128Const value) => (ImmediateKind::V128);
+    (immediate $op:ident blockty) => (ImmediateKind::BlockType);
+    (immediate $op:ident relative_depth) => (ImmediateKind::LabelIndex);
+    (immediate $op:ident targets) => (ImmediateKind::BrTable);
+    (immediate $op:ident try_table) => (ImmediateKind::TryTable);
+    (immediate $op:ident resume_table) => (ImmediateKind::ResumeTable);
+    (immediate $op:ident function_index) => (ImmediateKind::FunctionIndex);
+    (immediate $op:ident type_index) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident struct_type_index) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident array_type_index) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident array_type_index_dst) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident array_type_index_src) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident cont_type_index) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident argument_index) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident result_index) => (ImmediateKind::TypeIndex);
+    (immediate $op:ident table_index) => (ImmediateKind::TableIndex);
+    (immediate $op:ident table) => (ImmediateKind::TableIndex);
+    (immediate $op:ident dst_table) => (ImmediateKind::TableIndex);
+    (immediate $op:ident src_table) => (ImmediateKind::TableIndex);
+    (immediate $op:ident mem) => (ImmediateKind::MemoryIndex);
+    (immediate $op:ident dst_mem) => (ImmediateKind::MemoryIndex);
+    (immediate $op:ident src_mem) => (ImmediateKind::MemoryIndex);
+    (immediate $op:ident local_index) => (ImmediateKind::LocalIndex);
+    (immediate $op:ident global_index) => (ImmediateKind::GlobalIndex);
+    (immediate $op:ident tag_index) => (ImmediateKind::TagIndex);
+    (immediate $op:ident data_index) => (ImmediateKind::DataIndex);
+    (immediate $op:ident array_data_index) => (ImmediateKind::DataIndex);
+    (immediate $op:ident elem_index) => (ImmediateKind::ElemIndex);
+    (immediate $op:ident array_elem_index) => (ImmediateKind::ElemIndex);
+    (immediate $op:ident field_index) => (ImmediateKind::FieldIndex);
+    (immediate $op:ident memarg) => (ImmediateKind::MemArg);
+    (immediate $op:ident ordering) => (ImmediateKind::Ordering);
+    (immediate $op:ident lane) => (ImmediateKind::LaneIndex);
+    (immediate $op:ident lanes) => (ImmediateKind::Shuffle);
+    (immediate $op:ident array_size) => (ImmediateKind::Count);
+    (immediate $op:ident ty) => (ImmediateKind::ValType);
+    (immediate $op:ident hty) => (ImmediateKind::HeapType);
+    (immediate $op:ident from_ref_type) => (ImmediateKind::RefType);
+    (immediate $op:ident to_ref_type) => (ImmediateKind::RefType);
+
+    // Operators with a memory immediate all access that memory, and those that
+    // access it through a `MemArg` may trap on an out-of-bounds or misaligned
+    // address.
+    (memarg memarg) => (true);
+    (memarg $arg:ident) => (false);
+    (memory memarg) => (true);
+    (memory mem) => (true);
+    (memory dst_mem) => (true);
+    (memory src_mem) => (true);
+    (memory $arg:ident) => (false);
+
+    // Operators without a `MemArg` which may trap, for example on a null
+    // reference, an out-of-bounds table or segment access, or a failed cast.
+    (trap Unreachable) => (true);
+    (trap CallIndirect) => (true);
+    (trap ReturnCallIndirect) => (true);
+    (trap CallRef) => (true);
+    (trap ReturnCallRef) => (true);
+    (trap ThrowRef) => (true);
+    (trap I32DivS) => (true);
+    (trap I32DivU) => (true);
+    (trap I32RemS) => (true);
+    (trap I32RemU) => (true);
+    (trap I64DivS) => (true);
+    (trap I64DivU) => (true);
+    (trap I64RemS) => (true);
+    (trap I64RemU) => (true);
+    (trap I32TruncF32S) => (true);
+    (trap I32TruncF32U) => (true);
+    (trap I32TruncF64S) => (true);
+    (trap I32TruncF64U) => (true);
+    (trap I64TruncF32S) => (true);
+    (trap I64TruncF32U) => (true);
+    (trap I64TruncF64S) => (true);
+    (trap I64TruncF64U) => (true);
+    (trap MemoryInit) => (true);
+    (trap MemoryCopy) => (true);
+    (trap MemoryFill) => (true);
+    (trap MemoryDiscard) => (true);
+    (trap TableInit) => (true);
+    (trap TableCopy) => (true);
+    (trap TableFill) => (true);
+    (trap TableGet) => (true);
+    (trap TableSet) => (true);
+    (trap TableAtomicGet) => (true);
+    (trap TableAtomicSet) => (true);
+    (trap TableAtomicRmwXchg) => (true);
+    (trap TableAtomicRmwCmpxchg) => (true);
+    (trap RefAsNonNull) => (true);
+    (trap RefCastNonNull) => (true);
+    (trap RefCastNullable) => (true);
+    (trap I31GetS) => (true);
+    (trap I31GetU) => (true);
+    (trap StructGet) => (true);
+    (trap StructGetS) => (true);
+    (trap StructGetU) => (true);
+    (trap StructSet) => (true);
+    (trap StructAtomicGet) => (true);
+    (trap StructAtomicGetS) => (true);
+    (trap StructAtomicGetU) => (true);
+    (trap StructAtomicSet) => (true);
+    (trap StructAtomicRmwAdd) => (true);
+    (trap StructAtomicRmwSub) => (true);
+    (trap StructAtomicRmwAnd) => (true);
+    (trap StructAtomicRmwOr) => (true);
+    (trap StructAtomicRmwXor) => (true);
+    (trap StructAtomicRmwXchg) => (true);
+    (trap StructAtomicRmwCmpxchg) => (true);
+    (trap ArrayNewData) => (true);
+    (trap ArrayNewElem) => (true);
+    (trap ArrayGet) => (true);
+    (trap ArrayGetS) => (true);
+    (trap ArrayGetU) => (true);
+    (trap ArraySet) => (true);
+    (trap ArrayLen) => (true);
+    (trap ArrayFill) => (true);
+    (trap ArrayCopy) => (true);
+    (trap ArrayInitData) => (true);
+    (trap ArrayInitElem) => (true);
+    (trap ArrayAtomicGet) => (true);
+    (trap ArrayAtomicGetS) => (true);
+    (trap ArrayAtomicGetU) => (true);
+    (trap ArrayAtomicSet) => (true);
+    (trap ArrayAtomicRmwAdd) => (true);
+    (trap ArrayAtomicRmwSub) => (true);
+    (trap ArrayAtomicRmwAnd) => (true);
+    (trap ArrayAtomicRmwOr) => (true);
+    (trap ArrayAtomicRmwXor) => (true);
+    (trap ArrayAtomicRmwXchg) => (true);
+    (trap ArrayAtomicRmwCmpxchg) => (true);
+    (trap ContNew) => (true);
+    (trap ContBind) => (true);
+    (trap Suspend) => (true);
+    (trap Resume) => (true);
+    (trap ResumeThrow) => (true);
+    (trap $op:ident) => (false);
+
+    (enabled mvp) => (|_| true);
+    (enabled $proposal:ident) => (|features| features.$proposal());
+}
+crate::for_each_operator!(define_operator_info);
+
+/// The longest mnemonic that [`Mnemonic::new`] can produce.
+const MAX_MNEMONIC_LEN: usize = 48;
+
+/// A text-format mnemonic computed at compile time from the name of an
+/// operator's visitor method.
+///
+/// Visitor methods are named after the mnemonic with `.` replaced by `_`, so
+/// the mnemonic is recovered by turning the separators after a known prefix
+/// (such as `i32_` or `memory_`) and within `atomic_` and `rmwN_` back into
+/// `.`.
+struct Mnemonic {
+    bytes: [u8; MAX_MNEMONIC_LEN],
+    len: usize,
+}
+
+impl Mnemonic {
+    const PREFIXES: &'static [&'static str] = &[
+        "i32", "i64", "f32", "f64", "v128", "i8x16", "i16x8", "i32x4", "i64x2", "f32x4", "f64x2",
+        "local", "global", "memory", "table", "ref", "struct", "array", "any", "extern", "i31",
+        "data", "elem", "atomic", "cont",
+    ];
+
+    const fn new(visit: &str) -> Mnemonic {
+        let visit = visit.as_bytes();
+        let mut bytes = [0; MAX_MNEMONIC_LEN];
+        let mut len = 0;
+        let mut i = "visit_".len();
+        // Index of the next `_` which should be turned into a `.`, if any.
+        let mut dot = Mnemonic::prefix_end(visit, i);
+        while i < visit.len() {
+            assert!(len < MAX_MNEMONIC_LEN);
+            if i == dot {
+                bytes[len] = b'.';
+                dot = Mnemonic::nested_prefix_end(visit, i + 1);
+            } else {
+                bytes[len] = visit[i];
+            }
+            len += 1;
+            i += 1;
+        }
+        Mnemonic { bytes, len }
+    }
+
+    /// Returns the index of the `_` following a known prefix starting at
+    /// `start`, or `usize::MAX` if there is no such prefix.
+    const fn prefix_end(visit: &[u8], start: usize) -> usize {
+        let mut p = 0;
+        while p < Mnemonic::PREFIXES.len() {
+            let end = Mnemonic::component_end(visit, start, Mnemonic::PREFIXES[p].as_bytes());
+            if end != usize::MAX {
+                return end;
+            }
+            p += 1;
+        }
+        usize::MAX
+    }
+
+    /// Like `prefix_end` but for the `atomic.` and `rmwN.` components which
+    /// may follow the initial prefix.
+    const fn nested_prefix_end(visit: &[u8], start: usize) -> usize {
+        let end = Mnemonic::component_end(visit, start, b"atomic");
+        if end != usize::MAX {
+            return end;
+        }
+        if Mnemonic::starts_with(visit, start, b"rmw") {
+            let mut i = start + "rmw".len();
+            while i < visit.len() && visit[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i < visit.len() && visit[i] == b'_' {
+                return i;
+            }
+        }
+        usize::MAX
+    }
+
+    /// Returns the index of the `_` after `component` if `visit[start..]`
+    /// begins with `component` followed by a `_`, or `usize::MAX` otherwise.
+    const fn component_end(visit: &[u8], start: usize, component: &[u8]) -> usize {
+        let end = start + component.len();
+        if end < visit.len() && visit[end] == b'_' && Mnemonic::starts_with(visit, start, component)
+        {
+            end
+        } else {
+            usize::MAX
+        }
+    }
+
+    /// Returns whether `visit[start..]` begins with `prefix`.
+    const fn starts_with(visit: &[u8], start: usize, prefix: &[u8]) -> bool {
+        if start + prefix.len() > visit.len() {
+            return false;
+        }
+        let mut i = 0;
+        while i < prefix.len() {
+            if visit[start + i] != prefix[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    const fn as_str(&'static self) -> &'static str {
+        match core::str::from_utf8(self.bytes.split_at(self.len).0) {
+            Ok(s) => s,
+            Err(_) => panic!("mnemonics are always ASCII"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(mnemonic: &str) -> &'static OperatorInfo {
+        let mut infos = OperatorInfo::all()
+            .iter()
+            .filter(|i| i.mnemonic == mnemonic);
+        let info = infos.next().unwrap();
+        assert!(infos.next().is_none(), "duplicate mnemonic {mnemonic}");
+        info
+    }
+
+    #[test]
+    fn mnemonics() {
+        assert_eq!(Operator::Nop.info().mnemonic, "nop");
+        assert_eq!(
+            Operator::BrIf { relative_depth: 0 }.info().mnemonic,
+            "br_if"
+        );
+        assert_eq!(Operator::I32Add.info().mnemonic, "i32.add");
+        assert_eq!(
+            Operator::I32TruncSatF64U.info().mnemonic,
+            "i32.trunc_sat_f64_u"
+        );
+        assert_eq!(Operator::AtomicFence.info().mnemonic, "atomic.fence");
+        assert_eq!(Operator::RefI31.info().mnemonic, "ref.i31");
+        assert_eq!(
+            Operator::AnyConvertExtern.info().mnemonic,
+            "any.convert_extern"
+        );
+        assert_eq!(
+            Operator::I32x4RelaxedDotI8x16I7x16AddS.info().mnemonic,
+            "i32x4.relaxed_dot_i8x16_i7x16_add_s"
+        );
+        assert_eq!(
+            info("i64.atomic.rmw32.cmpxchg_u").name,
+            "I64AtomicRmw32CmpxchgU"
+        );
+        assert_eq!(info("i32.atomic.rmw.add").name, "I32AtomicRmwAdd");
+        assert_eq!(info("memory.atomic.wait32").name, "MemoryAtomicWait32");
+        assert_eq!(info("global.atomic.rmw.xchg").name, "GlobalAtomicRmwXchg");
+        assert_eq!(info("struct.new_default").name, "StructNewDefault");
+
+        for shared in ["select", "ref.test", "ref.cast"] {
+            let count = OperatorInfo::all()
+                .iter()
+                .filter(|i| i.mnemonic == shared)
+                .count();
+            assert_eq!(count, 2, "{shared}");
+        }
+    }
+
+    #[test]
+    fn classification() {
+        let add = info("i32.add");
+        assert_eq!(add.proposal, "mvp");
+        assert!(add.immediates.is_empty());
+        assert!(!add.can_trap);
+        assert!(!add.accesses_memory);
+
+        let div = info("i32.div_u");
+        assert!(div.can_trap);
+        assert!(!div.accesses_memory);
+
+        let size = info("memory.size");
+        assert_eq!(size.immediates, &[ImmediateKind::MemoryIndex]);
+        assert!(!size.can_trap);
+        assert!(size.accesses_memory);
+
+        let lane = info("v128.load8_lane");
+        assert_eq!(lane.proposal, "simd");
+        assert_eq!(
+            lane.immediates,
+            &[ImmediateKind::MemArg, ImmediateKind::LaneIndex]
+        );
+        assert!(lane.can_trap);
+        assert!(lane.accesses_memory);
+
+        let cast = info("br_on_cast");
+        assert_eq!(cast.proposal, "gc");
+        assert_eq!(
+            cast.immediates,
+            &[
+                ImmediateKind::LabelIndex,
+                ImmediateKind::RefType,
+                ImmediateKind::RefType
+            ]
+        );
+        assert!(!cast.can_trap);
+
+        assert_eq!(info("i64.const").immediates, &[ImmediateKind::I64]);
+    }
+
+    #[test]
+    fn enabled() {
+        let features = WasmFeatures::default();
+        assert!(info("unreachable").is_enabled(&features));
+        assert!(info("i8x16.shuffle").is_enabled(&features));
+
+        #[cfg(feature = "features")]
+        {
+            let mvp = WasmFeatures::empty();
+            assert!(info("unreachable").is_enabled(&mvp));
+            assert!(!info("i8x16.shuffle").is_enabled(&mvp));
+        }
+    }
+}
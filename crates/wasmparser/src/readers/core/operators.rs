@@ -366,6 +366,19 @@ macro_rules! define_visit_operator {
 }
 
 /// Trait implemented by types that can visit all [`Operator`] variants.
+///
+/// Visiting an operator with [`BinaryReader::visit_operator`] doesn't
+/// allocate, unless the visitor itself does. The immediates of most operators
+/// are plain values, and the targets of `br_table` are read on demand through
+/// [`BrTable::targets`]. The exceptions are `try_table`, `resume` and
+/// `resume_throw`, whose catch clauses and handlers are by default collected
+/// into a [`TryTable`] or [`ResumeTable`]. Visitors which must not allocate
+/// should override [`VisitOperator::visit_try_table_catches`],
+/// [`VisitOperator::visit_resume_handlers`] and
+/// [`VisitOperator::visit_resume_throw_handlers`] to receive them through
+/// iterators instead.
+///
+/// [`BinaryReader::visit_operator`]: crate::BinaryReader::visit_operator
 #[allow(missing_docs)]
 pub trait VisitOperator<'a> {
     /// The result type of the visitor.
@@ -393,6 +406,62 @@ pub trait VisitOperator<'a> {
         for_each_operator!(visit_operator)
     }
 
+    /// Visits a `try_table` instruction whose catch clauses haven't been
+    /// collected into a [`TryTable`].
+    ///
+    /// This is what [`BinaryReader::visit_operator`] calls for `try_table`
+    /// instructions. By default the catch clauses are collected and passed
+    /// to [`VisitOperator::visit_try_table`], which allocates, so visitors
+    /// which must not allocate should override this method instead.
+    ///
+    /// [`BinaryReader::visit_operator`]: crate::BinaryReader::visit_operator
+    fn visit_try_table_catches(
+        &mut self,
+        ty: BlockType,
+        catches: TryTableCatches<'a>,
+    ) -> Self::Output {
+        self.visit_try_table(TryTable {
+            ty,
+            catches: catches.collect(),
+        })
+    }
+
+    /// Visits a `resume` instruction whose handlers haven't been collected
+    /// into a [`ResumeTable`].
+    ///
+    /// See [`VisitOperator::visit_try_table_catches`] for more information.
+    fn visit_resume_handlers(
+        &mut self,
+        cont_type_index: u32,
+        handlers: ResumeTableHandlers<'a>,
+    ) -> Self::Output {
+        self.visit_resume(
+            cont_type_index,
+            ResumeTable {
+                handlers: handlers.collect(),
+            },
+        )
+    }
+
+    /// Visits a `resume_throw` instruction whose handlers haven't been
+    /// collected into a [`ResumeTable`].
+    ///
+    /// See [`VisitOperator::visit_try_table_catches`] for more information.
+    fn visit_resume_throw_handlers(
+        &mut self,
+        cont_type_index: u32,
+        tag_index: u32,
+        handlers: ResumeTableHandlers<'a>,
+    ) -> Self::Output {
+        self.visit_resume_throw(
+            cont_type_index,
+            tag_index,
+            ResumeTable {
+                handlers: handlers.collect(),
+            },
+        )
+    }
+
     for_each_operator!(define_visit_operator);
 }
 
@@ -411,6 +480,28 @@ impl<'a, 'b, V: VisitOperator<'a> + ?Sized> VisitOperator<'a> for &'b mut V {
     fn visit_operator(&mut self, op: &Operator<'a>) -> Self::Output {
         V::visit_operator(*self, op)
     }
+    fn visit_try_table_catches(
+        &mut self,
+        ty: BlockType,
+        catches: TryTableCatches<'a>,
+    ) -> Self::Output {
+        V::visit_try_table_catches(*self, ty, catches)
+    }
+    fn visit_resume_handlers(
+        &mut self,
+        cont_type_index: u32,
+        handlers: ResumeTableHandlers<'a>,
+    ) -> Self::Output {
+        V::visit_resume_handlers(*self, cont_type_index, handlers)
+    }
+    fn visit_resume_throw_handlers(
+        &mut self,
+        cont_type_index: u32,
+        tag_index: u32,
+        handlers: ResumeTableHandlers<'a>,
+    ) -> Self::Output {
+        V::visit_resume_throw_handlers(*self, cont_type_index, tag_index, handlers)
+    }
     for_each_operator!(define_visit_operator_delegate);
 }
 
@@ -419,6 +510,28 @@ impl<'a, V: VisitOperator<'a> + ?Sized> VisitOperator<'a> for Box<V> {
     fn visit_operator(&mut self, op: &Operator<'a>) -> Self::Output {
         V::visit_operator(&mut *self, op)
     }
+    fn visit_try_table_catches(
+        &mut self,
+        ty: BlockType,
+        catches: TryTableCatches<'a>,
+    ) -> Self::Output {
+        V::visit_try_table_catches(&mut *self, ty, catches)
+    }
+    fn visit_resume_handlers(
+        &mut self,
+        cont_type_index: u32,
+        handlers: ResumeTableHandlers<'a>,
+    ) -> Self::Output {
+        V::visit_resume_handlers(&mut *self, cont_type_index, handlers)
+    }
+    fn visit_resume_throw_handlers(
+        &mut self,
+        cont_type_index: u32,
+        tag_index: u32,
+        handlers: ResumeTableHandlers<'a>,
+    ) -> Self::Output {
+        V::visit_resume_throw_handlers(&mut *self, cont_type_index, tag_index, handlers)
+    }
     for_each_operator!(define_visit_operator_delegate);
 }
 
@@ -478,6 +591,61 @@ impl<'a> FromReader<'a> for Catch {
     }
 }
 
+/// The catch clauses of a `try_table` instruction, which are parsed on demand
+/// to avoid allocating.
+///
+/// All clauses have already been validated to be well-formed, so this
+/// iterator yields exactly [`TryTableCatches::len`] clauses.
+#[derive(Clone, Debug)]
+pub struct TryTableCatches<'a> {
+    reader: BinaryReader<'a>,
+    remaining: u32,
+}
+
+impl<'a> TryTableCatches<'a> {
+    pub(crate) fn new(reader: &mut BinaryReader<'a>) -> Result<TryTableCatches<'a>> {
+        let cnt = reader.read_size(MAX_WASM_CATCHES, "catches")?;
+        let catches = reader.skip(|reader| {
+            for _ in 0..cnt {
+                reader.read::<Catch>()?;
+            }
+            Ok(())
+        })?;
+        Ok(TryTableCatches {
+            reader: catches,
+            remaining: cnt as u32,
+        })
+    }
+
+    /// Returns the number of remaining catch clauses.
+    pub fn len(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Returns whether there are no remaining catch clauses.
+    pub fn is_empty(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+impl Iterator for TryTableCatches<'_> {
+    type Item = Catch;
+
+    fn next(&mut self) -> Option<Catch> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // Clauses were validated when this was created.
+        Some(self.reader.read().unwrap())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining as usize;
+        (remaining, Some(remaining))
+    }
+}
+
 /// A representation of dispatch tables on `resume` and `resume_throw`
 /// instructions.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -528,3 +696,244 @@ impl<'a> FromReader<'a> for Handle {
         })
     }
 }
+
+/// The handlers of a `resume` or `resume_throw` instruction, which are parsed
+/// on demand to avoid allocating.
+///
+/// All handlers have already been validated to be well-formed, so this
+/// iterator yields exactly [`ResumeTableHandlers::len`] handlers.
+#[derive(Clone, Debug)]
+pub struct ResumeTableHandlers<'a> {
+    reader: BinaryReader<'a>,
+    remaining: u32,
+}
+
+impl<'a> ResumeTableHandlers<'a> {
+    pub(crate) fn new(reader: &mut BinaryReader<'a>) -> Result<ResumeTableHandlers<'a>> {
+        let cnt = reader.read_size(MAX_WASM_HANDLERS, "resume table")?;
+        let handlers = reader.skip(|reader| {
+            for _ in 0..cnt {
+                reader.read::<Handle>()?;
+            }
+            Ok(())
+        })?;
+        Ok(ResumeTableHandlers {
+            reader: handlers,
+            remaining: cnt as u32,
+        })
+    }
+
+    /// Returns the number of remaining handlers.
+    pub fn len(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Returns whether there are no remaining handlers.
+    pub fn is_empty(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+impl Iterator for ResumeTableHandlers<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // Handlers were validated when this was created.
+        Some(self.reader.read().unwrap())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An immediate of an operator recorded by [`Recorder`].
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Imm {
+        Index(u32),
+        Type(ValType),
+        Catch(Catch),
+        Handle(Handle),
+    }
+
+    /// A visitor recording the immediates of the operators which have a
+    /// variable number of them, or which allocate when visited through
+    /// `Operator`, into a fixed-size buffer rather than allocating.
+    #[derive(Default)]
+    struct Recorder {
+        imms: [Option<Imm>; 4],
+        len: usize,
+    }
+
+    impl Recorder {
+        fn push(&mut self, imm: Imm) {
+            self.imms[self.len] = Some(imm);
+            self.len += 1;
+        }
+    }
+
+    // Ignores every operator except those `Recorder` visits itself.
+    macro_rules! define_visit_operator {
+        (@ignore visit_br_table $($rest:tt)*) => {};
+        (@ignore visit_typed_select $($rest:tt)*) => {};
+        (@ignore visit_cont_bind $($rest:tt)*) => {};
+        (@ignore $visit:ident $($arg:ident: $argty:ty),*) => {
+            fn $visit(&mut self $(,$arg: $argty)*) {
+                $(let _ = $arg;)*
+            }
+        };
+        ($(@$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+            $(
+                define_visit_operator!(@ignore $visit $($($arg: $argty),*)?);
+            )*
+        };
+    }
+
+    impl<'a> VisitOperator<'a> for Recorder {
+        type Output = ();
+
+        fn visit_br_table(&mut self, targets: BrTable<'a>) {
+            for target in targets.targets() {
+                self.push(Imm::Index(target.unwrap()));
+            }
+            self.push(Imm::Index(targets.default()));
+        }
+
+        fn visit_typed_select(&mut self, ty: ValType) {
+            self.push(Imm::Type(ty));
+        }
+
+        fn visit_cont_bind(&mut self, argument_index: u32, result_index: u32) {
+            self.push(Imm::Index(argument_index));
+            self.push(Imm::Index(result_index));
+        }
+
+        fn visit_try_table_catches(&mut self, _ty: BlockType, catches: TryTableCatches<'a>) {
+            for catch in catches {
+                self.push(Imm::Catch(catch));
+            }
+        }
+
+        fn visit_resume_handlers(
+            &mut self,
+            cont_type_index: u32,
+            handlers: ResumeTableHandlers<'a>,
+        ) {
+            self.push(Imm::Index(cont_type_index));
+            for handle in handlers {
+                self.push(Imm::Handle(handle));
+            }
+        }
+
+        fn visit_resume_throw_handlers(
+            &mut self,
+            cont_type_index: u32,
+            tag_index: u32,
+            handlers: ResumeTableHandlers<'a>,
+        ) {
+            self.push(Imm::Index(cont_type_index));
+            self.push(Imm::Index(tag_index));
+            for handle in handlers {
+                self.push(Imm::Handle(handle));
+            }
+        }
+
+        for_each_operator!(define_visit_operator);
+    }
+
+    fn record(buf: &[u8]) -> Result<Vec<Imm>> {
+        let mut visitor = Recorder::default();
+        BinaryReader::new(buf, 0).visit_operator(&mut visitor)?;
+        Ok(visitor.imms.iter().flatten().copied().collect())
+    }
+
+    #[test]
+    fn visit_without_allocating() {
+        // br_table 1 2 0
+        let imms = record(&[0x0e, 0x02, 0x01, 0x02, 0x00]).unwrap();
+        assert_eq!(imms, [Imm::Index(1), Imm::Index(2), Imm::Index(0)]);
+
+        // select (result i32)
+        let imms = record(&[0x1c, 0x01, 0x7f]).unwrap();
+        assert_eq!(imms, [Imm::Type(ValType::I32)]);
+
+        // cont.bind 1 2
+        let imms = record(&[0xe1, 0x01, 0x02]).unwrap();
+        assert_eq!(imms, [Imm::Index(1), Imm::Index(2)]);
+
+        // try_table (catch 1 0) (catch_all_ref 2)
+        let imms = record(&[0x1f, 0x40, 0x02, 0x00, 0x01, 0x00, 0x03, 0x02]).unwrap();
+        assert_eq!(
+            imms,
+            [
+                Imm::Catch(Catch::One { tag: 1, label: 0 }),
+                Imm::Catch(Catch::AllRef { label: 2 }),
+            ]
+        );
+
+        // resume 3 (on 1 0) (on 2 switch)
+        let imms = record(&[0xe3, 0x03, 0x02, 0x00, 0x01, 0x00, 0x01, 0x02]).unwrap();
+        assert_eq!(
+            imms,
+            [
+                Imm::Index(3),
+                Imm::Handle(Handle::OnLabel { tag: 1, label: 0 }),
+                Imm::Handle(Handle::OnSwitch { tag: 2 }),
+            ]
+        );
+
+        // resume_throw 3 4 (on 1 0)
+        let imms = record(&[0xe4, 0x03, 0x04, 0x01, 0x00, 0x01, 0x00]).unwrap();
+        assert_eq!(
+            imms,
+            [
+                Imm::Index(3),
+                Imm::Index(4),
+                Imm::Handle(Handle::OnLabel { tag: 1, label: 0 }),
+            ]
+        );
+
+        // Malformed clauses and handlers are reported before the visitor is
+        // called.
+        assert!(record(&[0x1f, 0x40, 0x01, 0x04]).is_err());
+        assert!(record(&[0xe3, 0x03, 0x01, 0x02]).is_err());
+        assert!(record(&[0xe4, 0x03, 0x04, 0x01, 0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn visit_operator_collects_tables() {
+        let buf = [0x1f, 0x40, 0x02, 0x00, 0x01, 0x00, 0x03, 0x02];
+        let op = BinaryReader::new(&buf, 0).read_operator().unwrap();
+        assert_eq!(
+            op,
+            Operator::TryTable {
+                try_table: TryTable {
+                    ty: BlockType::Empty,
+                    catches: vec![Catch::One { tag: 1, label: 0 }, Catch::AllRef { label: 2 }],
+                }
+            }
+        );
+
+        let buf = [0xe4, 0x03, 0x04, 0x01, 0x00, 0x01, 0x00];
+        let op = BinaryReader::new(&buf, 0).read_operator().unwrap();
+        assert_eq!(
+            op,
+            Operator::ResumeThrow {
+                cont_type_index: 3,
+                tag_index: 4,
+                resume_table: ResumeTable {
+                    handlers: vec![Handle::OnLabel { tag: 1, label: 0 }],
+                },
+            }
+        );
+    }
+}
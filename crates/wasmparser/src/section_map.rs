@@ -0,0 +1,507 @@
+use crate::parser::*;
+use crate::prelude::*;
+use crate::{BinaryReader, BinaryReaderError, Encoding, Result};
+use alloc::borrow::Cow;
+use core::ops::Range;
+
+/// A map of the sections of a WebAssembly module or component.
+///
+/// This is created by [`SectionMap::scan`], which only checks the framing of
+/// sections and the number of items in each section, without decoding any
+/// items or instructions. This takes time linear in the size of the input,
+/// with a small constant, and makes for a cheap check to reject malformed
+/// input before it's parsed or validated in full. Note that a binary which is
+/// scanned successfully may still fail to parse or validate.
+///
+/// ```
+/// use wasmparser::SectionMap;
+///
+/// # fn foo() -> wasmparser::Result<()> {
+/// let wasm = [
+///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+///     0x03, 0x03, 0x02, 0x00, 0x00, // function section with two items
+/// ];
+/// let map = SectionMap::scan(&wasm)?;
+/// assert_eq!(map.sections().len(), 1);
+/// assert_eq!(map.sections()[0].id, 3);
+/// assert_eq!(map.sections()[0].count, Some(2));
+/// # Ok(())
+/// # }
+/// # foo().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SectionMap<'a> {
+    bytes: &'a [u8],
+    encoding: Encoding,
+    sections: Vec<SectionInfo<'a>>,
+}
+
+/// A section found by [`SectionMap::scan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionInfo<'a> {
+    /// The number of modules or components this section is nested within,
+    /// which is 0 for sections of the outermost module or component.
+    pub depth: u32,
+    /// The encoding of the module or component containing this section.
+    pub encoding: Encoding,
+    /// The id of this section.
+    pub id: u8,
+    /// The range of the contents of this section within the input, which
+    /// excludes the id and size of the section.
+    pub range: Range<usize>,
+    /// The number of items in this section, for sections which contain a
+    /// vector of items.
+    pub count: Option<u32>,
+    /// The name of this section, for custom sections.
+    pub name: Option<&'a str>,
+}
+
+/// How the contents of a section are scanned.
+enum Contents {
+    /// A name followed by arbitrary data.
+    Custom,
+    /// A vector of items which are only counted.
+    Items,
+    /// A vector of size-prefixed function bodies.
+    Code,
+    /// A single index or count.
+    Single,
+    /// Contents which aren't checked.
+    Unchecked,
+    /// A nested module or component.
+    Nested(Encoding),
+}
+
+impl<'a> SectionMap<'a> {
+    /// Scans the structure of the module or component `bytes`.
+    ///
+    /// Sections of modules and components nested within a component are
+    /// included in the returned map after the section containing them.
+    ///
+    /// # Errors
+    ///
+    /// If the header is invalid, if a section id is unknown, if a section
+    /// doesn't fit within its module or component, or if the number of
+    /// items in a section exceeds what the section could possibly contain.
+    pub fn scan(bytes: &'a [u8]) -> Result<SectionMap<'a>> {
+        let mut reader = BinaryReader::new(bytes, 0);
+        let encoding = read_header(&mut reader, None)?;
+        let mut ret = SectionMap {
+            bytes,
+            encoding,
+            sections: Vec::new(),
+        };
+
+        // The encoding and end of each module or component which contains the
+        // one being scanned.
+        let mut parents = Vec::new();
+        let mut encoding = encoding;
+        let mut end = bytes.len();
+        loop {
+            if reader.current_position() == end {
+                match parents.pop() {
+                    Some((e, parent_end)) => {
+                        encoding = e;
+                        end = parent_end;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+
+            let id_pos = reader.current_position();
+            let id = reader.read_u8()?;
+            let len_pos = reader.current_position();
+            let len = reader.read_var_u32()? as usize;
+            let start = reader.current_position();
+            if len > end - start {
+                return Err(BinaryReaderError::new("section too large", len_pos));
+            }
+            let mut section = SectionInfo {
+                depth: parents.len() as u32,
+                encoding,
+                id,
+                range: start..start + len,
+                count: None,
+                name: None,
+            };
+            let mut contents = BinaryReader::new(&bytes[section.range.clone()], start);
+
+            match section_contents(encoding, id) {
+                Some(Contents::Custom) => section.name = Some(contents.read_string()?),
+                Some(Contents::Items) => section.count = Some(read_count(&mut contents)?),
+                Some(Contents::Code) => {
+                    let count = read_count(&mut contents)?;
+                    for _ in 0..count {
+                        let size = contents.read_var_u32()?;
+                        contents.read_bytes(size as usize)?;
+                    }
+                    ensure_eof(&contents)?;
+                    section.count = Some(count);
+                }
+                Some(Contents::Single) => {
+                    contents.read_var_u32()?;
+                    ensure_eof(&contents)?;
+                }
+                Some(Contents::Unchecked) => {}
+                Some(Contents::Nested(nested)) => {
+                    ret.sections.push(section);
+                    read_header(&mut contents, Some(nested))?;
+                    reader.read_bytes(contents.current_position())?;
+                    parents.push((encoding, end));
+                    encoding = nested;
+                    end = start + len;
+                    continue;
+                }
+                None => bail!(id_pos, "malformed section id: {id}"),
+            }
+            ret.sections.push(section);
+            reader.read_bytes(len)?;
+        }
+        Ok(ret)
+    }
+
+    /// Returns the encoding of the outermost module or component.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns all sections, in the order they appear in the input.
+    pub fn sections(&self) -> &[SectionInfo<'a>] {
+        &self.sections
+    }
+
+    /// Returns the contents of all custom sections of the outermost module or
+    /// component named `name`, combined as if they were a single section.
+    ///
+    /// Some producers split the data of a custom section across multiple
+    /// sections with the same name. The returned [`CombinedCustomSection`]
+    /// presents their contents as one concatenated buffer while keeping track
+    /// of where each part came from in the input, so errors while reading it
+    /// point at the right offset.
+    ///
+    /// ```
+    /// use wasmparser::SectionMap;
+    ///
+    /// # fn foo() -> wasmparser::Result<()> {
+    /// let wasm = [
+    ///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    ///     0x00, 0x04, 0x01, b'a', 0x01, 0x02, // custom section "a"
+    ///     0x00, 0x03, 0x01, b'a', 0x03, // custom section "a"
+    /// ];
+    /// let map = SectionMap::scan(&wasm)?;
+    /// let section = map.custom_sections("a");
+    /// assert_eq!(&section.data()[..], [0x01, 0x02, 0x03]);
+    /// assert_eq!(section.original_position(2), 18);
+    /// # Ok(())
+    /// # }
+    /// # foo().unwrap();
+    /// ```
+    pub fn custom_sections(&self, name: &str) -> CombinedCustomSection<'a> {
+        let parts = self
+            .sections
+            .iter()
+            .filter(|s| s.depth == 0 && s.name == Some(name))
+            .map(|s| {
+                // Skip the name of the section, which was successfully read
+                // while scanning.
+                let mut reader = BinaryReader::new(&self.bytes[s.range.clone()], s.range.start);
+                reader.skip_string().unwrap();
+                reader.original_position()..s.range.end
+            })
+            .collect();
+        CombinedCustomSection {
+            bytes: self.bytes,
+            parts,
+        }
+    }
+}
+
+/// The contents of all custom sections with the same name, as returned by
+/// [`SectionMap::custom_sections`].
+#[derive(Debug, Clone)]
+pub struct CombinedCustomSection<'a> {
+    bytes: &'a [u8],
+    /// The range of the data of each section within the input, excluding the
+    /// name of the section.
+    parts: Vec<Range<usize>>,
+}
+
+impl<'a> CombinedCustomSection<'a> {
+    /// Returns the range of the data of each section within the input, in the
+    /// order they appear in it.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.parts
+    }
+
+    /// Returns the data of all sections, concatenated.
+    ///
+    /// This is only copied if there's more than one section.
+    pub fn data(&self) -> Cow<'a, [u8]> {
+        match &self.parts[..] {
+            [] => Cow::Borrowed(&[]),
+            [part] => Cow::Borrowed(&self.bytes[part.clone()]),
+            parts => Cow::Owned(
+                parts
+                    .iter()
+                    .flat_map(|part| &self.bytes[part.clone()])
+                    .copied()
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the offset within the input of the byte at `position` within
+    /// the concatenated [`data`](CombinedCustomSection::data).
+    ///
+    /// A `position` at or beyond the end of the data is mapped to the end of
+    /// the last section.
+    pub fn original_position(&self, mut position: usize) -> usize {
+        for part in self.parts.iter() {
+            if position < part.len() {
+                return part.start + position;
+            }
+            position -= part.len();
+        }
+        match self.parts.last() {
+            Some(part) => part.end,
+            None => 0,
+        }
+    }
+
+    /// Reads the concatenated data of the sections with `f`.
+    ///
+    /// The [`BinaryReader`] passed to `f` is positioned relative to the start
+    /// of the concatenated data. The offset of an error returned by `f` is
+    /// translated to its offset within the input with
+    /// [`original_position`](CombinedCustomSection::original_position).
+    pub fn read<T>(&self, f: impl FnOnce(&mut BinaryReader<'_>) -> Result<T>) -> Result<T> {
+        let data = self.data();
+        let mut reader = BinaryReader::new(&data, 0);
+        f(&mut reader).map_err(|mut e| {
+            e.inner.offset = self.original_position(e.inner.offset);
+            e
+        })
+    }
+}
+
+/// Reads the header of a module or component, which must have the `expected`
+/// encoding if specified.
+fn read_header(reader: &mut BinaryReader<'_>, expected: Option<Encoding>) -> Result<Encoding> {
+    let start = reader.original_position();
+    let header_version = reader.read_header_version()?;
+    let (encoding, version) = match (header_version >> 16) as u16 {
+        KIND_MODULE => (Encoding::Module, WASM_MODULE_VERSION),
+        KIND_COMPONENT => (Encoding::Component, WASM_COMPONENT_VERSION),
+        _ => bail!(start + 4, "unknown binary version: {header_version:#10x}"),
+    };
+    if header_version as u16 != version {
+        bail!(start + 4, "unknown binary version: {header_version:#10x}");
+    }
+    match expected {
+        Some(Encoding::Module) if encoding != Encoding::Module => {
+            bail!(start, "expected a core module")
+        }
+        Some(Encoding::Component) if encoding != Encoding::Component => {
+            bail!(start, "expected a component")
+        }
+        _ => Ok(encoding),
+    }
+}
+
+fn section_contents(encoding: Encoding, id: u8) -> Option<Contents> {
+    Some(match (encoding, id) {
+        (_, CUSTOM_SECTION) => Contents::Custom,
+
+        (Encoding::Module, CODE_SECTION) => Contents::Code,
+        (Encoding::Module, START_SECTION | DATA_COUNT_SECTION) => Contents::Single,
+        (
+            Encoding::Module,
+            TYPE_SECTION | IMPORT_SECTION | FUNCTION_SECTION | TABLE_SECTION | MEMORY_SECTION
+            | GLOBAL_SECTION | EXPORT_SECTION | ELEMENT_SECTION | DATA_SECTION | TAG_SECTION,
+        ) => Contents::Items,
+
+        (Encoding::Component, COMPONENT_MODULE_SECTION) => Contents::Nested(Encoding::Module),
+        (Encoding::Component, COMPONENT_SECTION) => Contents::Nested(Encoding::Component),
+        (Encoding::Component, COMPONENT_START_SECTION) => Contents::Unchecked,
+        (
+            Encoding::Component,
+            COMPONENT_CORE_INSTANCE_SECTION
+            | COMPONENT_CORE_TYPE_SECTION
+            | COMPONENT_INSTANCE_SECTION
+            | COMPONENT_ALIAS_SECTION
+            | COMPONENT_TYPE_SECTION
+            | COMPONENT_CANONICAL_SECTION
+            | COMPONENT_IMPORT_SECTION
+            | COMPONENT_EXPORT_SECTION,
+        ) => Contents::Items,
+
+        _ => return None,
+    })
+}
+
+/// Reads the number of items in a section, each of which is at least one
+/// byte in size.
+fn read_count(reader: &mut BinaryReader<'_>) -> Result<u32> {
+    let pos = reader.original_position();
+    let count = reader.read_var_u32()?;
+    if count as usize > reader.bytes_remaining() {
+        bail!(
+            pos,
+            "section count of {count} exceeds the size of the section"
+        );
+    }
+    Ok(count)
+}
+
+fn ensure_eof(reader: &BinaryReader<'_>) -> Result<()> {
+    if !reader.eof() {
+        bail!(
+            reader.original_position(),
+            "unexpected data at the end of the section"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Payload};
+
+    /// Returns the id, range, and depth of every section according to
+    /// `Parser`.
+    fn parsed_sections(wasm: &[u8]) -> Vec<(u8, Range<usize>, u32)> {
+        let mut ret = Vec::new();
+        let mut depth = 0u32;
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload.unwrap();
+            if let Some((id, range)) = payload.as_section() {
+                ret.push((id, range, depth));
+            }
+            match payload {
+                Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => depth += 1,
+                Payload::End(_) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn matches_parser() {
+        let wasm = wat::parse_str(
+            r#"
+            (component
+                (core module
+                    (type (func))
+                    (func (type 0))
+                    (func (type 0) nop)
+                    (start 0)
+                    (data "x")
+                    (@custom "a" "b")
+                )
+                (component
+                    (core module)
+                    (import "x" (func))
+                )
+                (core instance (instantiate 0))
+            )
+        "#,
+        )
+        .unwrap();
+        let map = SectionMap::scan(&wasm).unwrap();
+        assert_eq!(map.encoding(), Encoding::Component);
+        let scanned = map
+            .sections()
+            .iter()
+            .map(|s| (s.id, s.range.clone(), s.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(scanned, parsed_sections(&wasm));
+
+        let code = map
+            .sections()
+            .iter()
+            .find(|s| s.encoding == Encoding::Module && s.id == CODE_SECTION)
+            .unwrap();
+        assert_eq!(code.count, Some(2));
+        let custom = map.sections().iter().find(|s| s.id == 0).unwrap();
+        assert_eq!(custom.name, Some("a"));
+        // The custom section of the nested module isn't part of the
+        // outermost component.
+        assert!(map.custom_sections("a").ranges().is_empty());
+    }
+
+    #[test]
+    fn combined_custom_sections() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (@custom "a" "\01\82")
+                (@custom "b" "\ff")
+                (@custom "a" "\03")
+                (@custom "a" "")
+            )
+        "#,
+        )
+        .unwrap();
+        let map = SectionMap::scan(&wasm).unwrap();
+        let section = map.custom_sections("a");
+        assert_eq!(section.ranges().len(), 3);
+        assert_eq!(&section.data()[..], [0x01, 0x82, 0x03]);
+        let b = map.custom_sections("b");
+        assert_eq!(section.original_position(1), section.ranges()[0].start + 1);
+        assert_eq!(section.original_position(2), section.ranges()[1].start);
+        assert_eq!(section.original_position(3), section.ranges()[2].end);
+        assert!(matches!(b.data(), Cow::Borrowed([0xff])));
+
+        // A LEB128 integer spanning two sections is read as a whole.
+        let value = section.read(|r| {
+            r.read_u8()?;
+            r.read_var_u32()
+        });
+        assert_eq!(value.unwrap(), 0x182);
+
+        // Errors point at the section where they happened.
+        let err = section
+            .read(|r| {
+                r.read_bytes(2)?;
+                r.read_u8()?;
+                r.read_u8()
+            })
+            .unwrap_err();
+        assert_eq!(err.offset(), section.ranges()[2].end);
+        let err = section
+            .read(|r| {
+                r.read_bytes(2)?;
+                Err::<(), _>(BinaryReaderError::new("bad", r.original_position()))
+            })
+            .unwrap_err();
+        assert_eq!(err.offset(), section.ranges()[1].start);
+    }
+
+    #[test]
+    fn errors() {
+        let header = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        let scan = |sections: &[u8]| {
+            let mut wasm = header.to_vec();
+            wasm.extend_from_slice(sections);
+            SectionMap::scan(&wasm).map(|_| ()).unwrap_err().to_string()
+        };
+
+        // A section extending beyond the end of the module.
+        assert!(scan(&[0x03, 0x05, 0x01, 0x00]).contains("section too large"));
+        // More items than bytes in the section.
+        assert!(scan(&[0x03, 0x02, 0x05, 0x00]).contains("exceeds the size"));
+        // A function body extending beyond the end of the code section.
+        assert!(scan(&[0x0a, 0x03, 0x01, 0x05, 0x00]).contains("unexpected end"));
+        // Trailing data in the start section.
+        assert!(scan(&[0x08, 0x02, 0x00, 0x00]).contains("unexpected data"));
+        // An unknown section.
+        assert!(scan(&[0x20, 0x00]).contains("malformed section id"));
+        // A module nested in a module section of a component is too short.
+        let mut component = vec![0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+        component.extend_from_slice(&[0x01, 0x04, 0x00, 0x61, 0x73, 0x6d]);
+        assert!(SectionMap::scan(&component).is_err());
+    }
+}
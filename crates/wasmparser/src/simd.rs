@@ -0,0 +1,214 @@
+//! SIMD-accelerated scanning of LEB128-encoded integers.
+//!
+//! LEB128 encodes integers as a sequence of bytes where the high bit of each
+//! byte is set if more bytes follow, so the boundaries between integers in a
+//! run of bytes can be found by gathering the high bits of 16 bytes at a time
+//! into a mask, which is done with SSE2 on x86 and NEON on AArch64.
+//!
+//! Only integers of at most 4 bytes, meaning values below 2^28, are handled
+//! here as these can't be out of range for either `u32` or `u64`. Longer
+//! integers, malformed integers, and the end of the input are left to the
+//! scalar decoding in `BinaryReader`, which reports any errors.
+
+// Using the SIMD intrinsics of `core::arch` fundamentally requires `unsafe`.
+#![allow(unsafe_code)]
+
+/// The number of bytes scanned at a time.
+pub(crate) const CHUNK: usize = 16;
+
+/// Returns a mask where bit `i` is set if byte `i` of `chunk` has its
+/// continuation bit set.
+#[inline]
+fn continuation_mask(chunk: &[u8; CHUNK]) -> u32 {
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2"
+    ))]
+    {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        // SAFETY: SSE2 is statically known to be available, and the load is
+        // unaligned and reads exactly `CHUNK` bytes.
+        unsafe {
+            let bytes = _mm_loadu_si128(chunk.as_ptr().cast());
+            _mm_movemask_epi8(bytes) as u32
+        }
+    }
+
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        use core::arch::aarch64::*;
+
+        const WEIGHTS: [u8; CHUNK] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+        // SAFETY: NEON is statically known to be available, and the loads
+        // read exactly `CHUNK` bytes.
+        unsafe {
+            // Isolate the high bit of each byte, weight it by its position
+            // within each half, and then sum each half to form the mask.
+            let bits = vshrq_n_u8::<7>(vld1q_u8(chunk.as_ptr()));
+            let weighted = vmulq_u8(bits, vld1q_u8(WEIGHTS.as_ptr()));
+            let low = vaddv_u8(vget_low_u8(weighted));
+            let high = vaddv_u8(vget_high_u8(weighted));
+            u32::from(low) | u32::from(high) << 8
+        }
+    }
+
+    #[cfg(not(any(
+        all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            target_feature = "sse2"
+        ),
+        all(target_arch = "aarch64", target_feature = "neon"),
+    )))]
+    {
+        chunk
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (i, byte)| mask | u32::from(byte >> 7) << i)
+    }
+}
+
+/// Scans up to `max` integers at the start of `chunk`, returning how many
+/// integers were found and how many bytes they span.
+///
+/// Returns `None` if the first integer isn't entirely within `chunk` or is
+/// longer than 4 bytes.
+#[inline]
+pub(crate) fn skip_leb128s(chunk: &[u8; CHUNK], max: usize) -> Option<(usize, usize)> {
+    let continuation = continuation_mask(chunk);
+    if continuation == 0 && max >= CHUNK {
+        return Some((CHUNK, CHUNK));
+    }
+    let mut ends = !continuation & 0xffff;
+
+    // Bit `i` is set if bytes `i..i + 4` all have their continuation bit set,
+    // meaning that an integer is at least 5 bytes long. Only the integers
+    // before any such integer are handled.
+    let long = continuation & (continuation >> 1) & (continuation >> 2) & (continuation >> 3);
+    if long != 0 {
+        ends &= (1 << long.trailing_zeros()) - 1;
+    }
+
+    let available = ends.count_ones() as usize;
+    if available == 0 {
+        return None;
+    }
+    if available <= max {
+        return Some((available, 32 - ends.leading_zeros() as usize));
+    }
+    // Only some of the integers are wanted, so find the end of the last one.
+    for _ in 1..max {
+        ends &= ends - 1;
+    }
+    Some((max, ends.trailing_zeros() as usize + 1))
+}
+
+/// Returns whether `chunk` consists entirely of single-byte integers.
+#[inline]
+pub(crate) fn all_single_byte(chunk: &[u8; CHUNK]) -> bool {
+    continuation_mask(chunk) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BinaryReader;
+    use alloc::vec::Vec;
+
+    /// Encodes `values` as a sequence of LEB128 integers.
+    fn encode(values: &[u64]) -> Vec<u8> {
+        let mut ret = Vec::new();
+        for value in values {
+            let mut value = *value;
+            loop {
+                let byte = (value & 0x7f) as u8;
+                value >>= 7;
+                if value == 0 {
+                    ret.push(byte);
+                    break;
+                }
+                ret.push(byte | 0x80);
+            }
+        }
+        ret
+    }
+
+    fn values() -> Vec<u64> {
+        let mut state = 0x1234_5678_9abc_def0u64;
+        (0..1000)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                // Mostly small values with occasional large ones.
+                match i % 7 {
+                    0 => state >> 32,
+                    1 => state >> 50,
+                    _ => state >> 58,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skip_matches_scalar() {
+        let values = values();
+        let wasm = encode(&values);
+        for count in [0, 1, 15, 16, 17, 500, values.len()] {
+            let mut reader = BinaryReader::new(&wasm, 0);
+            reader.skip_var_u32s(count).unwrap();
+            let mut expected = BinaryReader::new(&wasm, 0);
+            for _ in 0..count {
+                expected.read_var_u32().unwrap();
+            }
+            assert_eq!(reader.current_position(), expected.current_position());
+        }
+        assert!(BinaryReader::new(&wasm, 0)
+            .skip_var_u32s(values.len() + 1)
+            .is_err());
+    }
+
+    #[test]
+    fn read_matches_scalar() {
+        let values = values();
+        let wasm = encode(&values);
+        let mut read = [0; 1000];
+        BinaryReader::new(&wasm, 0)
+            .read_var_u32s(&mut read)
+            .unwrap();
+        assert!(read.iter().zip(&values).all(|(a, b)| u64::from(*a) == *b));
+
+        let mut read = [0; 1000];
+        BinaryReader::new(&wasm, 0)
+            .read_var_u64s(&mut read)
+            .unwrap();
+        assert_eq!(&read[..], &values[..]);
+    }
+
+    #[test]
+    fn errors_match_scalar() {
+        let mut wasm = encode(&[1; 20]);
+        // A 6 byte integer is too long for a `u32`.
+        wasm.extend([0x80, 0x80, 0x80, 0x80, 0x80, 0x00]);
+        wasm.extend(encode(&[1; 20]));
+
+        let err = BinaryReader::new(&wasm, 0).skip_var_u32s(41).unwrap_err();
+        assert_eq!(err.offset(), 24);
+        assert!(err.message().contains("too long"));
+
+        let mut read = [0; 41];
+        let err = BinaryReader::new(&wasm, 0)
+            .read_var_u32s(&mut read)
+            .unwrap_err();
+        assert_eq!(err.offset(), 24);
+
+        // A 5 byte integer with bits beyond 32 set is too large for a `u32`.
+        let wasm = [0x80, 0x80, 0x80, 0x80, 0x10];
+        let err = BinaryReader::new(&wasm, 0).skip_var_u32s(1).unwrap_err();
+        assert!(err.message().contains("too large"));
+        BinaryReader::new(&wasm, 0).read_var_u64s(&mut [0]).unwrap();
+    }
+}
@@ -50,7 +50,9 @@ fn test_validate() {
 
 mod component;
 mod core;
+mod fingerprint;
 mod func;
+mod incremental;
 pub mod names;
 mod operators;
 pub mod types;
@@ -58,9 +60,10 @@ pub mod types;
 use self::component::*;
 pub use self::core::ValidatorResources;
 use self::core::*;
-use self::types::{TypeAlloc, Types, TypesRef};
+use self::types::{EntityType, TypeAlloc, Types, TypesRef};
 pub use func::{FuncToValidate, FuncValidator, FuncValidatorAllocations};
-pub use operators::{Frame, FrameKind};
+pub use incremental::IncrementalValidator;
+pub use operators::{Frame, FrameKind, StackUsage};
 
 fn check_max(cur_len: usize, amt_added: u32, max: usize, desc: &str, offset: usize) -> Result<()> {
     if max
@@ -94,6 +97,9 @@ fn combine_type_sizes(a: u32, b: u32, offset: usize) -> Result<u32> {
 /// you get identifiers out of (e.g. [`CoreTypeId`][crate::types::CoreTypeId])
 /// and then later assert that you are pairing those identifiers with the same
 /// `Validator` instance when accessing the identifier's associated data.
+///
+/// Validators which take over the same [`TypeRegistry`] in turn also share
+/// its identifier.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
 pub struct ValidatorId(usize);
 
@@ -150,8 +156,19 @@ pub struct Validator {
     /// Enabled WebAssembly feature flags, dictating what's valid and what
     /// isn't.
     features: WasmFeatures,
+
+    /// Storage reused across the modules validated by this validator.
+    module_allocs: ModuleAllocations,
+
+    /// Storage reused across the functions validated by `validate_all`.
+    func_allocs: FuncValidatorAllocations,
+
+    /// The policy set with `Validator::set_policy`, if any.
+    policy: Option<Box<Policy>>,
 }
 
+type Policy = dyn FnMut(PolicyItem<'_>) -> ::core::result::Result<(), String> + Send + Sync;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum State {
     /// A header has not yet been parsed.
@@ -342,6 +359,98 @@ pub enum ValidPayload<'a> {
     End(Types),
 }
 
+/// An import or export of a core wasm module passed to the policy set with
+/// [`Validator::set_policy`].
+#[derive(Debug, Clone, Copy)]
+pub enum PolicyItem<'a> {
+    /// An import of the module.
+    Import {
+        /// The module name of the import.
+        module: &'a str,
+        /// The name of the import.
+        name: &'a str,
+        /// The type of the import.
+        ty: EntityType,
+    },
+    /// An export of the module.
+    Export {
+        /// The name of the export.
+        name: &'a str,
+        /// The type of the export.
+        ty: EntityType,
+    },
+}
+
+/// A registry of canonicalized types which is handed from one [`Validator`]
+/// to the next.
+///
+/// Types are canonicalized as they're validated, so identical rec groups and
+/// function types defined by different modules are given the same
+/// [`CoreTypeId`][crate::types::CoreTypeId]. Normally each [`Validator`] has
+/// its own registry, but a registry can instead be handed from one validator
+/// to the next with [`Validator::take_over_registry`] and
+/// [`Validator::hand_over_registry`]. Each module then only pays for
+/// canonicalizing types which no previous module defined, and the type
+/// identifiers of all modules validated with the registry can be compared
+/// with each other.
+///
+/// A registry is owned by exactly one validator at a time, which moves it out
+/// of the registry and back again. Validators running concurrently therefore
+/// can't share a registry; each needs its own.
+///
+/// All validators which take over a registry use its [`ValidatorId`] and its
+/// set of enabled wasm features, since whether a type is valid depends on
+/// them.
+///
+/// ```
+/// fn foo() -> anyhow::Result<()> {
+/// use wasmparser::{TypeRegistry, Validator, WasmFeatures};
+///
+/// let wasm1 = wat::parse_str("(module (type (func (param i32))))")?;
+/// let wasm2 = wat::parse_str("(module (type (func)) (type (func (param i32))))")?;
+///
+/// let registry = TypeRegistry::new(WasmFeatures::default());
+/// let mut validator = Validator::take_over_registry(registry);
+/// let id1 = validator.validate_all(&wasm1)?.core_type_at(0);
+///
+/// // Hand the registry over to another validator.
+/// let registry = validator.hand_over_registry();
+/// let mut validator = Validator::take_over_registry(registry);
+/// let id2 = validator.validate_all(&wasm2)?.core_type_at(1);
+///
+/// assert_eq!(id1, id2);
+/// # Ok(())
+/// # }
+/// # foo().unwrap()
+/// ```
+#[derive(Default)]
+pub struct TypeRegistry {
+    id: ValidatorId,
+    types: TypeAlloc,
+    features: WasmFeatures,
+}
+
+impl TypeRegistry {
+    /// Creates a new, empty, registry for types valid with the specified set
+    /// of wasm `features`.
+    pub fn new(features: WasmFeatures) -> TypeRegistry {
+        TypeRegistry {
+            features,
+            ..TypeRegistry::default()
+        }
+    }
+
+    /// Returns the identifier shared by all validators using this registry.
+    pub fn id(&self) -> ValidatorId {
+        self.id
+    }
+
+    /// Returns the wasm features of validators using this registry.
+    pub fn features(&self) -> &WasmFeatures {
+        &self.features
+    }
+}
+
 impl Validator {
     /// Creates a new [`Validator`] ready to validate a WebAssembly module
     /// or component.
@@ -366,11 +475,101 @@ impl Validator {
         ret
     }
 
+    /// Creates a new [`Validator`] which takes over the specified `registry`,
+    /// canonicalizing types with it and adding types to it.
+    ///
+    /// The validator owns the registry until it's handed over again with
+    /// [`Validator::hand_over_registry`], so no other validator can use the
+    /// registry in the meantime. The validator uses the features, and has the
+    /// identifier, of the registry.
+    pub fn take_over_registry(registry: TypeRegistry) -> Validator {
+        let TypeRegistry {
+            id,
+            types,
+            features,
+        } = registry;
+        Validator {
+            id,
+            types,
+            features,
+            ..Validator::default()
+        }
+    }
+
+    /// Consumes this validator, handing over the registry of all types it has
+    /// validated so far so that another validator can take it over.
+    ///
+    /// This may be called at any time, including after validation failed.
+    /// Rec groups which failed validation are checked again, and fail again,
+    /// if a later module defines them.
+    pub fn hand_over_registry(self) -> TypeRegistry {
+        TypeRegistry {
+            id: self.id,
+            types: self.types,
+            features: self.features,
+        }
+    }
+
     /// Returns the wasm features used for this validator.
     pub fn features(&self) -> &WasmFeatures {
         &self.features
     }
 
+    /// Sets a policy which decides whether the imports and exports of a module
+    /// are allowed.
+    ///
+    /// The `policy` is called with each import and export of every core wasm
+    /// module validated, including modules nested within components, once
+    /// the import or export has otherwise been validated. If it returns an
+    /// error then validation fails with that message at the offset of the
+    /// import or export. This makes it possible to reject modules which don't
+    /// fit an embedding, for example because they import from unknown modules
+    /// or export mutable globals, as part of validating them.
+    ///
+    /// The policy is retained when the validator is [reset](Validator::reset).
+    ///
+    /// ```
+    /// fn foo() -> anyhow::Result<()> {
+    /// use wasmparser::{types::EntityType, PolicyItem, Validator};
+    ///
+    /// let mut validator = Validator::new();
+    /// validator.set_policy(|item| match item {
+    ///     PolicyItem::Import { module, .. } if module != "wasi_snapshot_preview1" => {
+    ///         Err(format!("imports from `{module}` are not allowed"))
+    ///     }
+    ///     PolicyItem::Export {
+    ///         name,
+    ///         ty: EntityType::Global(ty),
+    ///     } if ty.mutable => Err(format!("mutable global export `{name}` is not allowed")),
+    ///     _ => Ok(()),
+    /// });
+    ///
+    /// let wasm = wat::parse_str(r#"(module (import "env" "f" (func)))"#)?;
+    /// let err = validator.validate_all(&wasm).err().unwrap();
+    /// assert_eq!(err.message(), "imports from `env` are not allowed");
+    /// # Ok(())
+    /// # }
+    /// # foo().unwrap()
+    /// ```
+    pub fn set_policy(
+        &mut self,
+        policy: impl FnMut(PolicyItem<'_>) -> ::core::result::Result<(), String> + Send + Sync + 'static,
+    ) {
+        self.policy = Some(Box::new(policy));
+    }
+
+    /// Calls the policy, if any, with `item` found at `offset`.
+    fn check_policy(
+        policy: &mut Option<Box<Policy>>,
+        item: PolicyItem<'_>,
+        offset: usize,
+    ) -> Result<()> {
+        match policy {
+            Some(policy) => policy(item).map_err(|msg| BinaryReaderError::new(msg, offset)),
+            None => Ok(()),
+        }
+    }
+
     /// Reset this validator's state such that it is ready to validate a new
     /// Wasm module or component.
     ///
@@ -380,6 +579,16 @@ impl Validator {
     /// [`CoreTypeId`][crate::types::CoreTypeId]) for the same types that are
     /// defined multiple times across different modules and components.
     ///
+    /// Storage used while validating a module is also retained and reused for
+    /// the next module once the [`Types`] and function validators produced for
+    /// the previous one have been dropped, which avoids allocator churn when
+    /// validating many small modules.
+    ///
+    /// This may be called at any time, including after validation failed, so
+    /// a single validator can be used for a stream of modules of which some
+    /// are invalid. Rec groups which failed validation are checked again,
+    /// and fail again, if they are defined by a later module.
+    ///
     /// ```
     /// fn foo() -> anyhow::Result<()> {
     /// use wasmparser::Validator;
@@ -440,18 +649,24 @@ impl Validator {
             // have "invalid" types inside our current types list.
             features: _,
 
+            // Retained to be reused by the next module.
+            func_allocs: _,
+
+            // The policy applies to all modules validated.
+            policy: _,
+
             state,
             module,
             components,
+            module_allocs,
         } = self;
 
-        assert!(
-            matches!(state, State::End),
-            "cannot reset a validator that did not successfully complete validation"
-        );
-        assert!(module.is_none());
-        assert!(components.is_empty());
-
+        // Validation may have failed, or been abandoned, partway through a
+        // module or component, in which case its state is discarded here.
+        if let Some(module) = module.take() {
+            module.into_allocations(module_allocs);
+        }
+        components.clear();
         *state = State::default();
     }
 
@@ -493,11 +708,10 @@ impl Validator {
             }
         }
 
-        let mut allocs = FuncValidatorAllocations::default();
         for (func, body) in functions_to_validate {
-            let mut validator = func.into_validator(allocs);
+            let mut validator = func.into_validator(mem::take(&mut self.func_allocs));
             validator.validate(&body)?;
-            allocs = validator.into_allocations();
+            self.func_allocs = validator.into_allocations();
         }
 
         Ok(last_types.unwrap())
@@ -636,7 +850,7 @@ impl Validator {
             Encoding::Module => {
                 if num == WASM_MODULE_VERSION {
                     assert!(self.module.is_none());
-                    self.module = Some(ModuleState::default());
+                    self.module = Some(ModuleState::new(&mut self.module_allocs));
                     State::Module
                 } else {
                     bail!(range.start, "unknown binary version: {num:#x}");
@@ -697,7 +911,8 @@ impl Validator {
     ///
     /// This method should only be called when parsing a module.
     pub fn import_section(&mut self, section: &crate::ImportSectionReader<'_>) -> Result<()> {
-        self.process_module_section(
+        let mut policy = self.policy.take();
+        let result = self.process_module_section(
             Order::Import,
             section,
             "import",
@@ -713,12 +928,24 @@ impl Validator {
                 Ok(())
             },
             |state, features, types, import, offset| {
-                state
-                    .module
-                    .assert_mut()
-                    .add_import(import, features, types, offset)
+                let module = state.module.assert_mut();
+                module.add_import(import, features, types, offset)?;
+                let ty = *module.imports[&(import.module.to_string(), import.name.to_string())]
+                    .last()
+                    .unwrap();
+                Self::check_policy(
+                    &mut policy,
+                    PolicyItem::Import {
+                        module: import.module,
+                        name: import.name,
+                        ty,
+                    },
+                    offset,
+                )
             },
-        )
+        );
+        self.policy = policy;
+        result
     }
 
     /// Validates [`Payload::FunctionSection`](crate::Payload).
@@ -859,7 +1086,8 @@ impl Validator {
     ///
     /// This method should only be called when parsing a module.
     pub fn export_section(&mut self, section: &crate::ExportSectionReader<'_>) -> Result<()> {
-        self.process_module_section(
+        let mut policy = self.policy.take();
+        let result = self.process_module_section(
             Order::Export,
             section,
             "export",
@@ -880,9 +1108,12 @@ impl Validator {
                 state.add_export(
                     e.name, ty, features, offset, false, /* checked above */
                     types,
-                )
+                )?;
+                Self::check_policy(&mut policy, PolicyItem::Export { name: e.name, ty }, offset)
             },
-        )
+        );
+        self.policy = policy;
+        result
     }
 
     /// Validates [`Payload::StartSection`](crate::Payload).
@@ -901,6 +1132,7 @@ impl Validator {
                 offset,
             ));
         }
+        state.module.assert_mut().start = Some(func);
 
         Ok(())
     }
@@ -1372,11 +1604,10 @@ impl Validator {
                     self.state = State::Component;
                 }
 
-                Ok(Types::from_module(
-                    self.id,
-                    self.types.commit(),
-                    state.module.arc().clone(),
-                ))
+                let types =
+                    Types::from_module(self.id, self.types.commit(), state.module.arc().clone());
+                state.into_allocations(&mut self.module_allocs);
+                Ok(types)
             }
             State::Component => {
                 let mut component = self.components.pop().unwrap();
@@ -1507,7 +1738,9 @@ impl Validator {
 
 #[cfg(test)]
 mod tests {
-    use crate::{GlobalType, MemoryType, RefType, TableType, ValType, Validator, WasmFeatures};
+    use crate::{
+        GlobalType, MemoryType, RefType, TableType, TypeRegistry, ValType, Validator, WasmFeatures,
+    };
     use anyhow::Result;
 
     #[test]
@@ -1670,4 +1903,244 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_reset_reuses_module_storage() -> Result<()> {
+        let first = wat::parse_str(
+            r#"
+            (module
+                (import "" "f" (func))
+                (memory 1)
+                (global i32 (i32.const 0))
+                (func (export "g") (call 0))
+                (data (i32.const 0) "x")
+            )
+        "#,
+        )?;
+        let second = wat::parse_str(
+            r#"
+            (module
+                (table 1 funcref)
+                (func (param i32))
+            )
+        "#,
+        )?;
+
+        let mut validator = Validator::new();
+        for _ in 0..3 {
+            let types = validator.validate_all(&first)?;
+            assert_eq!(types.core_function_count(), 2);
+            assert_eq!(types.memory_count(), 1);
+            assert_eq!(types.global_count(), 1);
+            assert_eq!(types.table_count(), 0);
+            drop(types);
+            validator.reset();
+
+            // Storage from the first module is reused here, and none of its
+            // contents may leak into the second.
+            let types = validator.validate_all(&second)?;
+            assert_eq!(types.core_function_count(), 1);
+            assert_eq!(types.memory_count(), 0);
+            assert_eq!(types.global_count(), 0);
+            assert_eq!(types.table_count(), 1);
+            validator.reset();
+        }
+
+        // A module which only validates if the first module's imports and
+        // exports aren't left behind.
+        let third = wat::parse_str(r#"(module (func (export "g")))"#)?;
+        let types = validator.validate_all(&third)?;
+        assert_eq!(types.core_function_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_after_failure() -> Result<()> {
+        // The second type of this rec group is invalid as its supertype is
+        // final, which is only found after the rec group was interned.
+        let invalid = wat::parse_str(
+            r#"
+            (module
+                (rec
+                    (type $a (struct))
+                    (type (sub $a (struct)))
+                )
+            )
+        "#,
+        )?;
+        let valid = wat::parse_str(
+            r#"
+            (module
+                (rec
+                    (type $a (sub (struct)))
+                    (type (sub $a (struct)))
+                )
+                (func (param i32))
+            )
+        "#,
+        )?;
+
+        let mut validator = Validator::new();
+        for _ in 0..2 {
+            let err = match validator.validate_all(&invalid) {
+                Ok(_) => panic!("expected an error"),
+                Err(e) => e,
+            };
+            assert!(err.message().contains("final super type"), "{err}");
+            validator.reset();
+            validator.validate_all(&valid)?;
+            validator.reset();
+        }
+
+        // Validation may also be abandoned partway through.
+        let parser = crate::Parser::new(0);
+        for payload in parser.parse_all(&valid).take(2) {
+            validator.payload(&payload?)?;
+        }
+        validator.reset();
+        validator.validate_all(&valid)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_shares_types_with_components() -> Result<()> {
+        // Core function types created for canonical builtins of a component
+        // are shared with modules defining the same type.
+        let component = wat::parse_str(
+            r#"
+            (component
+                (core type $start (shared (func (param i32))))
+                (core func (canon thread.spawn $start))
+            )
+        "#,
+        )?;
+        let module = wat::parse_str(
+            r#"
+            (module
+                (type $f (sub (shared (func (param i32)))))
+                (type (sub $f (shared (func (param i32)))))
+            )
+        "#,
+        )?;
+
+        let mut validator = Validator::new_with_features(WasmFeatures::all());
+        validator.validate_all(&component)?;
+        validator.reset();
+        validator.validate_all(&module)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_registry() -> Result<()> {
+        let wasm1 = wat::parse_str(
+            r#"
+            (module
+                (rec
+                    (type $a (sub (struct (field (ref null $b)))))
+                    (type $b (sub (array (ref null $a))))
+                )
+                (type (func (param i32)))
+            )
+        "#,
+        )?;
+        let wasm2 = wat::parse_str(
+            r#"
+            (module
+                (type (func (param i32)))
+                (rec
+                    (type $a (sub (struct (field (ref null $b)))))
+                    (type $b (sub (array (ref null $a))))
+                )
+            )
+        "#,
+        )?;
+
+        let registry = TypeRegistry::new(WasmFeatures::default());
+        let registry_id = registry.id();
+        let mut validator = Validator::take_over_registry(registry);
+        assert_eq!(validator.id(), registry_id);
+        let types1 = validator.validate_all(&wasm1)?;
+
+        let mut validator = Validator::take_over_registry(validator.hand_over_registry());
+        assert_eq!(validator.id(), registry_id);
+        let types2 = validator.validate_all(&wasm2)?;
+
+        assert_eq!(types1.id(), types2.id());
+        assert_eq!(types1.core_type_at(0), types2.core_type_at(1));
+        assert_eq!(types1.core_type_at(1), types2.core_type_at(2));
+        assert_eq!(types1.core_type_at(2), types2.core_type_at(0));
+
+        // Types of earlier modules can be looked up in later ones.
+        let id = types1.core_type_at(2).unwrap_sub();
+        assert_eq!(types2[id].unwrap_func().params(), [ValType::I32]);
+
+        // Validators which don't share a registry don't share type ids.
+        let types3 = Validator::new().validate_all(&wasm2)?;
+        assert_ne!(types1.id(), types3.id());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy() -> Result<()> {
+        use crate::types::EntityType;
+        use crate::PolicyItem;
+
+        let mut validator = Validator::new();
+        validator.set_policy(|item| match item {
+            PolicyItem::Import { module: "env", .. } => Ok(()),
+            PolicyItem::Import { module, name, .. } => {
+                Err(format!("import `{module}::{name}` is not allowed"))
+            }
+            PolicyItem::Export {
+                name,
+                ty: EntityType::Global(ty),
+            } if ty.mutable => Err(format!("export `{name}` is a mutable global")),
+            PolicyItem::Export { .. } => Ok(()),
+        });
+
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "f" (func))
+                (global (export "g") i32 (i32.const 0))
+            )
+        "#,
+        )?;
+        validator.validate_all(&wasm)?;
+
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "f" (func))
+                (import "other" "f" (func))
+            )
+        "#,
+        )?;
+        validator.reset();
+        let err = validator.validate_all(&wasm).err().unwrap();
+        assert_eq!(err.message(), "import `other::f` is not allowed");
+        assert_eq!(
+            err.offset(),
+            wasm.windows(5).position(|w| w == b"other").unwrap() - 1
+        );
+
+        // The policy also applies to modules nested in components.
+        let wasm = wat::parse_str(
+            r#"
+            (component
+                (core module
+                    (global (export "g") (mut i32) (i32.const 0))
+                )
+            )
+        "#,
+        )?;
+        validator.reset();
+        let err = validator.validate_all(&wasm).err().unwrap();
+        assert_eq!(err.message(), "export `g` is a mutable global");
+
+        Ok(())
+    }
 }
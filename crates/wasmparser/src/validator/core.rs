@@ -43,7 +43,6 @@ pub enum Order {
     Data,
 }
 
-#[derive(Default)]
 pub(crate) struct ModuleState {
     /// Internal state that is incrementally built-up for the module being
     /// validated. This houses type information for all wasm items, like
@@ -72,6 +71,27 @@ pub(crate) struct ModuleState {
 }
 
 impl ModuleState {
+    /// Creates the state for a new module, reusing the storage in `allocs`
+    /// where possible.
+    pub fn new(allocs: &mut ModuleAllocations) -> ModuleState {
+        allocs.reclaim();
+        ModuleState {
+            module: MaybeOwned::new(allocs.module.take().unwrap_or_default()),
+            order: Order::default(),
+            data_segment_count: 0,
+            expected_code_bodies: None,
+            const_expr_allocs: mem::take(&mut allocs.const_expr_allocs),
+            code_section_index: None,
+        }
+    }
+
+    /// Returns the storage of this finished module to `allocs` so it may be
+    /// reused by the next module.
+    pub fn into_allocations(mut self, allocs: &mut ModuleAllocations) {
+        allocs.const_expr_allocs = mem::take(&mut self.const_expr_allocs);
+        allocs.finished = Some(self.module.arc().clone());
+    }
+
     pub fn update_order(&mut self, order: Order, offset: usize) -> Result<()> {
         if self.order >= order {
             return Err(BinaryReaderError::new("section out of order", offset));
@@ -527,6 +547,32 @@ impl ModuleState {
     }
 }
 
+/// Storage which a [`Validator`](crate::Validator) reuses across the modules
+/// it validates.
+///
+/// A finished module is shared with the [`Types`](crate::types::Types) and
+/// function validators produced for it, so its storage is only reclaimed once
+/// those have all been dropped, at which point the next module reuses it.
+#[derive(Default)]
+pub(crate) struct ModuleAllocations {
+    /// A cleared module whose storage may be reused.
+    module: Option<Module>,
+    /// The most recently finished module.
+    finished: Option<Arc<Module>>,
+    const_expr_allocs: OperatorValidatorAllocations,
+}
+
+impl ModuleAllocations {
+    /// Reclaims the storage of the most recently finished module if it's no
+    /// longer in use elsewhere.
+    fn reclaim(&mut self) {
+        if let Some(mut module) = self.finished.take().and_then(|m| Arc::try_unwrap(m).ok()) {
+            module.clear();
+            self.module = Some(module);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Module {
     // This is set once the code section starts.
@@ -546,12 +592,76 @@ pub(crate) struct Module {
     pub function_references: Set<u32>,
     pub imports: IndexMap<(String, String), Vec<EntityType>>,
     pub exports: IndexMap<String, EntityType>,
+    pub start: Option<u32>,
     pub type_size: u32,
     num_imported_globals: u32,
     num_imported_functions: u32,
 }
 
 impl Module {
+    /// Resets this module to its default state while retaining the capacity
+    /// of its storage.
+    fn clear(&mut self) {
+        let Module {
+            snapshot,
+            types,
+            tables,
+            memories,
+            globals,
+            element_types,
+            data_count,
+            functions,
+            tags,
+            function_references,
+            imports,
+            exports,
+            start,
+            type_size,
+            num_imported_globals,
+            num_imported_functions,
+        } = self;
+        *snapshot = None;
+        types.clear();
+        tables.clear();
+        memories.clear();
+        globals.clear();
+        element_types.clear();
+        *data_count = None;
+        functions.clear();
+        tags.clear();
+        function_references.clear();
+        imports.clear();
+        exports.clear();
+        *start = None;
+        *type_size = 1;
+        *num_imported_globals = 0;
+        *num_imported_functions = 0;
+    }
+
+    /// Returns whether function bodies are validated identically in this
+    /// module and `other`, meaning that everything in scope of function
+    /// bodies is the same in both.
+    ///
+    /// Both modules must have been validated with the same type registry so
+    /// that their type identifiers can be compared.
+    pub fn same_function_context(&self, other: &Module) -> bool {
+        self.types == other.types
+            && self.tables == other.tables
+            && self.memories == other.memories
+            && self.globals == other.globals
+            && self.element_types == other.element_types
+            && self.data_count == other.data_count
+            && self.functions == other.functions
+            && self.tags == other.tags
+            && self.function_references == other.function_references
+    }
+
+    /// Returns the number of functions which are imported, and not defined,
+    /// by this module.
+    pub fn num_imported_functions(&self) -> u32 {
+        self.num_imported_functions
+    }
+
     pub fn add_types(
         &mut self,
         rec_group: RecGroup,
@@ -1130,6 +1240,7 @@ impl Default for Module {
             function_references: Default::default(),
             imports: Default::default(),
             exports: Default::default(),
+            start: None,
             type_size: 1,
             num_imported_globals: Default::default(),
             num_imported_functions: Default::default(),
@@ -1317,6 +1428,12 @@ mod arc {
     }
 
     impl<T> MaybeOwned<T> {
+        pub fn new(x: T) -> MaybeOwned<T> {
+            MaybeOwned {
+                inner: Inner::Owned(x),
+            }
+        }
+
         #[inline]
         fn as_mut(&mut self) -> Option<&mut T> {
             match &mut self.inner {
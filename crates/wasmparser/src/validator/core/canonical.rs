@@ -112,7 +112,12 @@ pub(crate) trait InternRecGroup {
             let id = CoreTypeId::from_index(i);
             debug_assert!(types.get(id).is_some());
             self.add_type_id(id);
-            if is_new {
+            // Types of a rec group which was interned previously have already
+            // been checked, unless checking them failed at the time. Such
+            // types have no subtyping depth, and are checked again here so
+            // that a validator which is reset after a failure reports the
+            // same error rather than accepting the rec group.
+            if is_new || !types.has_subtyping_depth(id) {
                 self.check_subtype(rec_group_id, id, features, types, offset)?;
             }
         }
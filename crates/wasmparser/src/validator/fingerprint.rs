@@ -0,0 +1,683 @@
+//! Hashing of the external interfaces of modules and components, as used by
+//! [`TypesRef::interface_fingerprint`](super::types::TypesRef::interface_fingerprint).
+
+use super::names::KebabString;
+use super::types::{
+    ComponentAnyTypeId, ComponentDefinedType, ComponentEntityType, ComponentFuncTypeId,
+    ComponentValType, CoreTypeId, EntityType, ModuleType, RecGroupId, ResourceId, TypeIdentifier,
+    TypeList,
+};
+use crate::prelude::*;
+use crate::{
+    CompositeInnerType, Digest, FieldType, HeapType, PrimitiveValType, RefType, StorageType,
+    SubType, TableType, UnpackedIndex, ValType,
+};
+
+/// Feeds a canonical encoding of imports, exports, and their types into a
+/// [`Digest`].
+///
+/// Types are encoded structurally, so the encoding doesn't depend on the
+/// identifiers types were given during validation, on their indices, or on
+/// which validator validated them. Core recursion groups are encoded in full
+/// the first time they're referenced and by the order in which they were
+/// first referenced afterwards, and resources are likewise numbered in the
+/// order they're first referenced.
+pub(crate) struct Fingerprinter<'a, D> {
+    types: &'a TypeList,
+    digest: D,
+    rec_groups: Map<RecGroupId, u32>,
+    resources: Map<ResourceId, u32>,
+}
+
+impl<'a, D: Digest> Fingerprinter<'a, D> {
+    pub(crate) fn new(types: &'a TypeList) -> Self {
+        Fingerprinter {
+            types,
+            digest: D::default(),
+            rec_groups: Map::default(),
+            resources: Map::default(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> D::Output {
+        self.digest.finalize()
+    }
+
+    /// Encodes the interface of a core module.
+    ///
+    /// Imports are kept in order since some embedders provide them by
+    /// position, while exports are only ever looked up by name and are
+    /// sorted.
+    pub(crate) fn module<'b>(
+        &mut self,
+        imports: impl Iterator<Item = (&'b str, &'b str, EntityType)>,
+        exports: impl Iterator<Item = (&'b str, EntityType)>,
+        has_start: bool,
+    ) {
+        self.byte(0);
+        let imports = imports.collect::<Vec<_>>();
+        self.len(imports.len());
+        for (module, name, ty) in imports {
+            self.str(module);
+            self.str(name);
+            self.entity_type(&ty);
+        }
+        let mut exports = exports.collect::<Vec<_>>();
+        exports.sort_by_key(|(name, _)| *name);
+        self.len(exports.len());
+        for (name, ty) in exports {
+            self.str(name);
+            self.entity_type(&ty);
+        }
+        self.bool(has_start);
+    }
+
+    /// Encodes the interface of a component, or of a component type.
+    ///
+    /// Imports and exports of components are only ever looked up by name, so
+    /// both are sorted.
+    pub(crate) fn component(
+        &mut self,
+        imports: &IndexMap<String, ComponentEntityType>,
+        exports: &IndexMap<String, ComponentEntityType>,
+    ) {
+        self.byte(1);
+        self.component_externs(imports);
+        self.component_externs(exports);
+    }
+
+    fn component_externs(&mut self, externs: &IndexMap<String, ComponentEntityType>) {
+        let mut externs = externs.iter().collect::<Vec<_>>();
+        externs.sort_by_key(|(name, _)| name.as_str());
+        self.len(externs.len());
+        for (name, ty) in externs {
+            self.str(name);
+            self.component_entity_type(ty);
+        }
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.digest.update(&[byte]);
+    }
+
+    fn bool(&mut self, b: bool) {
+        self.byte(b.into());
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.digest.update(&n.to_le_bytes());
+    }
+
+    fn u64(&mut self, n: u64) {
+        self.digest.update(&n.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(u32::try_from(len).unwrap());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.digest.update(s.as_bytes());
+    }
+
+    fn option<T>(&mut self, value: Option<T>, f: impl FnOnce(&mut Self, T)) {
+        match value {
+            None => self.byte(0),
+            Some(value) => {
+                self.byte(1);
+                f(self, value);
+            }
+        }
+    }
+
+    fn entity_type(&mut self, ty: &EntityType) {
+        match ty {
+            EntityType::Func(id) => {
+                self.byte(0);
+                self.core_type(*id);
+            }
+            EntityType::Table(ty) => {
+                self.byte(1);
+                self.table_type(ty);
+            }
+            EntityType::Memory(ty) => {
+                self.byte(2);
+                self.bool(ty.memory64);
+                self.bool(ty.shared);
+                self.u64(ty.initial);
+                self.option(ty.maximum, Self::u64);
+                self.option(ty.page_size_log2, Self::u32);
+            }
+            EntityType::Global(ty) => {
+                self.byte(3);
+                self.val_type(None, ty.content_type);
+                self.bool(ty.mutable);
+                self.bool(ty.shared);
+            }
+            EntityType::Tag(id) => {
+                self.byte(4);
+                self.core_type(*id);
+            }
+        }
+    }
+
+    fn table_type(&mut self, ty: &TableType) {
+        self.ref_type(None, ty.element_type);
+        self.bool(ty.table64);
+        self.bool(ty.shared);
+        self.u64(ty.initial);
+        self.option(ty.maximum, Self::u64);
+    }
+
+    /// Encodes a type defined outside of any rec group currently being
+    /// encoded, as its rec group and its index within it.
+    fn core_type(&mut self, id: CoreTypeId) {
+        let types = self.types;
+        let group = types.rec_group_id_of(id);
+        let range = types[group].clone();
+        match self.rec_groups.get(&group) {
+            Some(n) => {
+                let n = *n;
+                self.byte(0);
+                self.u32(n);
+            }
+            None => {
+                let n = u32::try_from(self.rec_groups.len()).unwrap();
+                self.rec_groups.insert(group, n);
+                self.byte(1);
+                self.len(range.end.index() - range.start.index());
+                for index in range.start.index()..range.end.index() {
+                    let id = CoreTypeId::from_index(u32::try_from(index).unwrap());
+                    self.sub_type(group, &types[id]);
+                }
+            }
+        }
+        self.len(id.index() - range.start.index());
+    }
+
+    fn sub_type(&mut self, group: RecGroupId, ty: &SubType) {
+        self.bool(ty.is_final);
+        self.option(ty.supertype_idx, |me, idx| {
+            me.type_index(Some(group), idx.unpack())
+        });
+        self.bool(ty.composite_type.shared);
+        match &ty.composite_type.inner {
+            CompositeInnerType::Func(ty) => {
+                self.byte(0);
+                self.len(ty.params().len());
+                for param in ty.params() {
+                    self.val_type(Some(group), *param);
+                }
+                self.len(ty.results().len());
+                for result in ty.results() {
+                    self.val_type(Some(group), *result);
+                }
+            }
+            CompositeInnerType::Array(ty) => {
+                self.byte(1);
+                self.field_type(group, &ty.0);
+            }
+            CompositeInnerType::Struct(ty) => {
+                self.byte(2);
+                self.len(ty.fields.len());
+                for field in ty.fields.iter() {
+                    self.field_type(group, field);
+                }
+            }
+            CompositeInnerType::Cont(ty) => {
+                self.byte(3);
+                self.type_index(Some(group), ty.0.unpack());
+            }
+        }
+    }
+
+    fn field_type(&mut self, group: RecGroupId, ty: &FieldType) {
+        match ty.element_type {
+            StorageType::I8 => self.byte(0),
+            StorageType::I16 => self.byte(1),
+            StorageType::Val(ty) => {
+                self.byte(2);
+                self.val_type(Some(group), ty);
+            }
+        }
+        self.bool(ty.mutable);
+    }
+
+    /// Encodes a value type which appears within the rec group `group`, if
+    /// any.
+    fn val_type(&mut self, group: Option<RecGroupId>, ty: ValType) {
+        match ty {
+            ValType::I32 => self.byte(0),
+            ValType::I64 => self.byte(1),
+            ValType::F32 => self.byte(2),
+            ValType::F64 => self.byte(3),
+            ValType::V128 => self.byte(4),
+            ValType::Ref(ty) => {
+                self.byte(5);
+                self.ref_type(group, ty);
+            }
+        }
+    }
+
+    fn ref_type(&mut self, group: Option<RecGroupId>, ty: RefType) {
+        self.bool(ty.is_nullable());
+        match ty.heap_type() {
+            HeapType::Abstract { shared, ty } => {
+                self.byte(0);
+                self.bool(shared);
+                self.str(ty.as_str(true));
+            }
+            HeapType::Concrete(index) => {
+                self.byte(1);
+                self.type_index(group, index);
+            }
+        }
+    }
+
+    /// Encodes a reference to a type, which is a local index if the type is
+    /// within the rec group `group`.
+    fn type_index(&mut self, group: Option<RecGroupId>, index: UnpackedIndex) {
+        let id = match index {
+            UnpackedIndex::RecGroup(index) => {
+                self.byte(0);
+                self.u32(index);
+                return;
+            }
+            UnpackedIndex::Id(id) => id,
+            UnpackedIndex::Module(_) => unreachable!("types are canonicalized"),
+        };
+        let types = self.types;
+        match group {
+            Some(group) if types.rec_group_id_of(id) == group => {
+                self.byte(0);
+                self.len(id.index() - types[group].start.index());
+            }
+            _ => {
+                self.byte(1);
+                self.core_type(id);
+            }
+        }
+    }
+
+    fn module_type(&mut self, ty: &ModuleType) {
+        self.len(ty.imports.len());
+        for ((module, name), ty) in ty.imports.iter() {
+            self.str(module);
+            self.str(name);
+            self.entity_type(ty);
+        }
+        let mut exports = ty.exports.iter().collect::<Vec<_>>();
+        exports.sort_by_key(|(name, _)| name.as_str());
+        self.len(exports.len());
+        for (name, ty) in exports {
+            self.str(name);
+            self.entity_type(ty);
+        }
+    }
+
+    fn component_entity_type(&mut self, ty: &ComponentEntityType) {
+        let types = self.types;
+        match ty {
+            ComponentEntityType::Module(id) => {
+                self.byte(0);
+                self.module_type(&types[*id]);
+            }
+            ComponentEntityType::Func(id) => {
+                self.byte(1);
+                self.component_func_type(*id);
+            }
+            ComponentEntityType::Value(ty) => {
+                self.byte(2);
+                self.component_val_type(*ty);
+            }
+            ComponentEntityType::Type { referenced, .. } => {
+                self.byte(3);
+                self.component_any_type(*referenced);
+            }
+            ComponentEntityType::Instance(id) => {
+                self.byte(4);
+                self.component_externs(&types[*id].exports);
+            }
+            ComponentEntityType::Component(id) => {
+                self.byte(5);
+                let ty = &types[*id];
+                self.component(&ty.imports, &ty.exports);
+            }
+        }
+    }
+
+    fn component_any_type(&mut self, id: ComponentAnyTypeId) {
+        let types = self.types;
+        match id {
+            ComponentAnyTypeId::Resource(id) => {
+                self.byte(0);
+                self.resource(id.resource());
+            }
+            ComponentAnyTypeId::Defined(id) => {
+                self.byte(1);
+                self.component_defined_type(&types[id]);
+            }
+            ComponentAnyTypeId::Func(id) => {
+                self.byte(2);
+                self.component_func_type(id);
+            }
+            ComponentAnyTypeId::Instance(id) => {
+                self.byte(3);
+                self.component_externs(&types[id].exports);
+            }
+            ComponentAnyTypeId::Component(id) => {
+                self.byte(4);
+                let ty = &types[id];
+                self.component(&ty.imports, &ty.exports);
+            }
+        }
+    }
+
+    fn resource(&mut self, id: ResourceId) {
+        let next = u32::try_from(self.resources.len()).unwrap();
+        let n = *self.resources.entry(id).or_insert(next);
+        self.u32(n);
+    }
+
+    fn component_func_type(&mut self, id: ComponentFuncTypeId) {
+        let ty = &self.types[id];
+        self.len(ty.params.len());
+        for (name, ty) in ty.params.iter() {
+            self.str(name);
+            self.component_val_type(*ty);
+        }
+        self.len(ty.results.len());
+        for (name, ty) in ty.results.iter() {
+            self.option(name.as_ref().map(|n| n.as_str()), Self::str);
+            self.component_val_type(*ty);
+        }
+    }
+
+    fn component_val_type(&mut self, ty: ComponentValType) {
+        match ty {
+            ComponentValType::Primitive(ty) => {
+                self.byte(0);
+                self.primitive_val_type(ty);
+            }
+            ComponentValType::Type(id) => {
+                let types = self.types;
+                self.byte(1);
+                self.component_defined_type(&types[id]);
+            }
+        }
+    }
+
+    /// Encodes a primitive type as its encoding in the binary format.
+    fn primitive_val_type(&mut self, ty: PrimitiveValType) {
+        self.byte(match ty {
+            PrimitiveValType::Bool => 0x7f,
+            PrimitiveValType::S8 => 0x7e,
+            PrimitiveValType::U8 => 0x7d,
+            PrimitiveValType::S16 => 0x7c,
+            PrimitiveValType::U16 => 0x7b,
+            PrimitiveValType::S32 => 0x7a,
+            PrimitiveValType::U32 => 0x79,
+            PrimitiveValType::S64 => 0x78,
+            PrimitiveValType::U64 => 0x77,
+            PrimitiveValType::F32 => 0x76,
+            PrimitiveValType::F64 => 0x75,
+            PrimitiveValType::Char => 0x74,
+            PrimitiveValType::String => 0x73,
+        });
+    }
+
+    fn component_defined_type(&mut self, ty: &ComponentDefinedType) {
+        match ty {
+            ComponentDefinedType::Primitive(ty) => {
+                self.byte(0);
+                self.primitive_val_type(*ty);
+            }
+            ComponentDefinedType::Record(r) => {
+                self.byte(1);
+                self.len(r.fields.len());
+                for (name, ty) in r.fields.iter() {
+                    self.str(name);
+                    self.component_val_type(*ty);
+                }
+            }
+            ComponentDefinedType::Variant(v) => {
+                self.byte(2);
+                self.len(v.cases.len());
+                for (name, case) in v.cases.iter() {
+                    self.str(name);
+                    self.option(case.ty, Self::component_val_type);
+                    self.option(case.refines.as_ref().map(|n| n.as_str()), Self::str);
+                }
+            }
+            ComponentDefinedType::List(ty) => {
+                self.byte(3);
+                self.component_val_type(*ty);
+            }
+            ComponentDefinedType::Tuple(t) => {
+                self.byte(4);
+                self.len(t.types.len());
+                for ty in t.types.iter() {
+                    self.component_val_type(*ty);
+                }
+            }
+            ComponentDefinedType::Flags(names) => {
+                self.byte(5);
+                self.names(names);
+            }
+            ComponentDefinedType::Enum(names) => {
+                self.byte(6);
+                self.names(names);
+            }
+            ComponentDefinedType::Option(ty) => {
+                self.byte(7);
+                self.component_val_type(*ty);
+            }
+            ComponentDefinedType::Result { ok, err } => {
+                self.byte(8);
+                self.option(*ok, Self::component_val_type);
+                self.option(*err, Self::component_val_type);
+            }
+            ComponentDefinedType::Own(id) => {
+                self.byte(9);
+                self.resource(id.resource());
+            }
+            ComponentDefinedType::Borrow(id) => {
+                self.byte(10);
+                self.resource(id.resource());
+            }
+        }
+    }
+
+    fn names(&mut self, names: &IndexSet<KebabString>) {
+        self.len(names.len());
+        for name in names {
+            self.str(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{Digest, Validator, WasmFeatures};
+
+    /// A digest which records the bytes fed into it.
+    #[derive(Default)]
+    struct Bytes(Vec<u8>);
+
+    impl Digest for Bytes {
+        type Output = Vec<u8>;
+        fn update(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+        fn finalize(self) -> Vec<u8> {
+            self.0
+        }
+    }
+
+    fn fingerprint(wat: &str) -> Vec<u8> {
+        let wasm = wat::parse_str(wat).unwrap();
+        Validator::new_with_features(WasmFeatures::all())
+            .validate_all(&wasm)
+            .unwrap()
+            .interface_fingerprint::<Bytes>()
+    }
+
+    #[test]
+    fn modules() {
+        let a = fingerprint(
+            r#"(module
+                (import "env" "f" (func (param i32) (result i32)))
+                (import "env" "m" (memory 1))
+                (func (export "g") (param i32) (result i32) local.get 0)
+                (global (export "x") i32 i32.const 0)
+            )"#,
+        );
+        // Code, internal items, and the order types and exports are defined
+        // in don't matter.
+        let b = fingerprint(
+            r#"(module
+                (type (func))
+                (type (func (param i32) (result i32)))
+                (import "env" "f" (func (type 1)))
+                (import "env" "m" (memory 1))
+                (global (export "x") i32 i32.const 1)
+                (func (type 0))
+                (func (export "g") (type 1) i32.const 1)
+            )"#,
+        );
+        assert_eq!(a, b);
+
+        // The order of imports, the types of exports, and the start function
+        // all do.
+        let different = [
+            r#"(module
+                (import "env" "m" (memory 1))
+                (import "env" "f" (func (param i32) (result i32)))
+                (func (export "g") (param i32) (result i32) local.get 0)
+                (global (export "x") i32 i32.const 0)
+            )"#,
+            r#"(module
+                (import "env" "f" (func (param i32) (result i32)))
+                (import "env" "m" (memory 1))
+                (func (export "g") (param i64) (result i32) i32.const 0)
+                (global (export "x") i32 i32.const 0)
+            )"#,
+            r#"(module
+                (import "env" "f" (func (param i32) (result i32)))
+                (import "env" "m" (memory 1))
+                (func (export "g") (param i32) (result i32) local.get 0)
+                (global (export "x") i32 i32.const 0)
+                (func $start)
+                (start $start)
+            )"#,
+        ];
+        for wat in different {
+            assert_ne!(a, fingerprint(wat));
+        }
+    }
+
+    #[test]
+    fn rec_groups() {
+        let a = fingerprint(
+            r#"(module
+                (rec
+                    (type $list (struct (field i32) (field (ref null $list))))
+                    (type $make (func (result (ref $list))))
+                )
+                (type $pair (struct (field (ref $list)) (field (ref $list))))
+                (import "env" "make" (func (type $make)))
+                (import "env" "pair" (global (ref null $pair)))
+            )"#,
+        );
+        let b = fingerprint(
+            r#"(module
+                (type (struct))
+                (rec
+                    (type $list (struct (field i32) (field (ref null $list))))
+                    (type $make (func (result (ref $list))))
+                )
+                (type $pair (struct (field (ref $list)) (field (ref $list))))
+                (import "env" "make" (func (type $make)))
+                (import "env" "pair" (global (ref null $pair)))
+            )"#,
+        );
+        assert_eq!(a, b);
+
+        // The same types, but in separate rec groups.
+        let c = fingerprint(
+            r#"(module
+                (type $list (struct (field i32) (field (ref null $list))))
+                (type $make (func (result (ref $list))))
+                (type $pair (struct (field (ref $list)) (field (ref $list))))
+                (import "env" "make" (func (type $make)))
+                (import "env" "pair" (global (ref null $pair)))
+            )"#,
+        );
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn components() {
+        let a = fingerprint(
+            r#"(component
+                (import "r" (type $r (sub resource)))
+                (import "f" (func $f (param "x" (own $r)) (result string)))
+                (type $t (resource (rep i32)))
+                (core func $drop (canon resource.drop $t))
+                (type $drop (func (param "x" (own $t))))
+                (func $drop (type $drop) (canon lift (core func $drop)))
+                (export $t2 "t" (type $t))
+                (export "drop" (func $drop) (func (param "x" (own $t2))))
+            )"#,
+        );
+        let b = fingerprint(
+            r#"(component
+                (type $t (resource (rep i32)))
+                (core func $drop (canon resource.drop $t))
+                (type $drop (func (param "x" (own $t))))
+                (func $drop (type $drop) (canon lift (core func $drop)))
+                (export $t2 "t" (type $t))
+                (export "drop" (func $drop) (func (param "x" (own $t2))))
+                (import "r" (type $r (sub resource)))
+                (import "f" (func $f (param "x" (own $r)) (result string)))
+            )"#,
+        );
+        assert_eq!(a, b);
+
+        // A component type with the same imports and exports has the same
+        // fingerprint.
+        let wasm = wat::parse_str(
+            r#"(component
+                (import "c" (component
+                    (import "r" (type $r (sub resource)))
+                    (import "f" (func (param "x" (own $r)) (result string)))
+                    (export $t "t" (type (sub resource)))
+                    (export "drop" (func (param "x" (own $t))))
+                ))
+            )"#,
+        )
+        .unwrap();
+        let types = Validator::new().validate_all(&wasm).unwrap();
+        let ty = types.component_at(0);
+        assert_eq!(a, types.component_type_fingerprint::<Bytes>(ty));
+
+        // Using the imported resource in place of the exported one changes
+        // the interface.
+        let wasm = wat::parse_str(
+            r#"(component
+                (import "c" (component
+                    (import "r" (type $r (sub resource)))
+                    (import "f" (func (param "x" (own $r)) (result string)))
+                    (export $t "t" (type (sub resource)))
+                    (export "drop" (func (param "x" (own $r))))
+                ))
+            )"#,
+        )
+        .unwrap();
+        let types = Validator::new().validate_all(&wasm).unwrap();
+        let ty = types.component_at(0);
+        assert_ne!(a, types.component_type_fingerprint::<Bytes>(ty));
+    }
+}
@@ -1,4 +1,4 @@
-use super::operators::{Frame, OperatorValidator, OperatorValidatorAllocations};
+use super::operators::{Frame, OperatorValidator, OperatorValidatorAllocations, StackUsage};
 use crate::{BinaryReader, Result, ValType, VisitOperator};
 use crate::{FunctionBody, Operator, WasmFeatures, WasmModuleResources};
 
@@ -225,6 +225,17 @@ impl<T: WasmModuleResources> FuncValidator<T> {
         self.validator.get_frame(depth)
     }
 
+    /// Returns how the function uses the operand and control stacks, along
+    /// with the locations of its tail calls and stack switches.
+    ///
+    /// This describes all operators validated so far, so once [`finish`] has
+    /// succeeded it describes the whole function.
+    ///
+    /// [`finish`]: FuncValidator::finish
+    pub fn stack_usage(&self) -> &StackUsage {
+        self.validator.stack_usage()
+    }
+
     /// Consumes this validator and returns the underlying allocations that
     /// were used during the validation process.
     ///
@@ -239,6 +250,7 @@ impl<T: WasmModuleResources> FuncValidator<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prelude::*;
     use crate::types::CoreTypeId;
     use crate::{HeapType, RefType};
 
@@ -340,4 +352,68 @@ mod tests {
         assert!(v.op(2, &Operator::I32Const { value: 99 }).is_ok());
         assert_eq!(v.operand_stack_height(), 2);
     }
+
+    #[test]
+    fn stack_usage() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (type $ft (func (param i32) (result i32)))
+                (type $ct (cont $ft))
+                (tag $t)
+                (func $f (type $ft)
+                    (block
+                        (block
+                            i32.const 1
+                            i32.const 2
+                            i32.const 3
+                            drop
+                            drop
+                            drop
+                        )
+                    )
+                    local.get 0
+                    (if (then
+                        (return_call $f (i32.const 0))
+                    ))
+                    local.get 0
+                    ref.null $ct
+                    resume $ct
+                )
+                (func (result i32)
+                    i32.const 0
+                )
+            )
+            "#,
+        )
+        .unwrap();
+
+        let mut validator = crate::Validator::new_with_features(crate::WasmFeatures::all());
+        let mut usages = Vec::new();
+        for payload in crate::Parser::new(0).parse_all(&wasm) {
+            if let crate::ValidPayload::Func(func, body) =
+                validator.payload(&payload.unwrap()).unwrap()
+            {
+                let mut func = func.into_validator(Default::default());
+                func.validate(&body).unwrap();
+                usages.push(func.stack_usage().clone());
+            }
+        }
+
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].max_operand_stack_height, 3);
+        assert_eq!(usages[0].max_control_stack_height, 3);
+        assert_eq!(usages[0].tail_calls.len(), 1);
+        assert_eq!(usages[0].stack_switches.len(), 1);
+        assert!(usages[0].tail_calls[0] < usages[0].stack_switches[0]);
+        assert_eq!(
+            usages[1],
+            StackUsage {
+                max_operand_stack_height: 1,
+                max_control_stack_height: 1,
+                tail_calls: Vec::new(),
+                stack_switches: Vec::new(),
+            }
+        );
+    }
 }
@@ -0,0 +1,352 @@
+//! Incremental revalidation of modules after local edits.
+
+use super::core::Module;
+use super::{FuncToValidate, FuncValidatorAllocations, ValidPayload, Validator};
+use super::{ValidatorResources, WasmFeatures};
+use crate::prelude::*;
+use crate::types::Types;
+use crate::{FunctionBody, Parser, Result};
+use ::core::mem;
+use ::core::ops::Range;
+use alloc::sync::Arc;
+
+/// A validator for repeatedly revalidating a core wasm module as it's edited,
+/// for example by an editor or a refactoring tool.
+///
+/// Validation of function bodies typically dominates the time it takes to
+/// validate a module, so an `IncrementalValidator` remembers the module it
+/// last validated successfully and avoids revalidating function bodies which
+/// can't have been affected by an edit:
+///
+/// * [`IncrementalValidator::validate`] validates a whole new version of the
+///   module, such as one with a section replaced. All sections other than the
+///   code section are validated in full, but a function body is only
+///   validated if it changed or if anything in scope of function bodies, such
+///   as a type, global, or table, changed.
+///
+/// * [`IncrementalValidator::validate_function`] validates only a single
+///   replacement function body within the module last validated.
+///
+/// Components are supported by [`IncrementalValidator::validate`] but are
+/// always validated in full.
+///
+/// ```
+/// fn foo() -> anyhow::Result<()> {
+/// use wasmparser::{IncrementalValidator, WasmFeatures};
+///
+/// let mut validator = IncrementalValidator::new(WasmFeatures::default());
+/// validator.validate(&wat::parse_str(r#"
+///     (module
+///         (func (result i32) i32.const 0)
+///         (func (result i32) call 0)
+///     )
+/// "#)?)?;
+///
+/// // Only the second function is validated again here.
+/// validator.validate(&wat::parse_str(r#"
+///     (module
+///         (func (result i32) i32.const 0)
+///         (func (result i32) call 0 i32.const 1 i32.add)
+///     )
+/// "#)?)?;
+/// # Ok(())
+/// # }
+/// # foo().unwrap()
+/// ```
+pub struct IncrementalValidator {
+    validator: Validator,
+    allocs: FuncValidatorAllocations,
+    /// The module last validated successfully, if any.
+    last: Option<Validated>,
+}
+
+/// A module which was validated successfully.
+struct Validated {
+    module: Arc<Module>,
+    /// The contents of function bodies, where `bodies` holds the range of the
+    /// body of each defined function.
+    code: Vec<u8>,
+    bodies: Vec<Range<usize>>,
+}
+
+impl IncrementalValidator {
+    /// Creates a new incremental validator with the specified set of wasm
+    /// `features` enabled.
+    pub fn new(features: WasmFeatures) -> IncrementalValidator {
+        IncrementalValidator {
+            validator: Validator::new_with_features(features),
+            allocs: FuncValidatorAllocations::default(),
+            last: None,
+        }
+    }
+
+    /// Returns the wasm features used for this validator.
+    pub fn features(&self) -> &WasmFeatures {
+        self.validator.features()
+    }
+
+    /// Validates the module or component `bytes`, which is typically an
+    /// edited version of what was previously passed to this method.
+    ///
+    /// Function bodies which are identical to those of the module last
+    /// validated successfully are only validated again if something else
+    /// which they may refer to changed.
+    ///
+    /// Upon success, the type information for the module or component is
+    /// returned.
+    pub fn validate(&mut self, bytes: &[u8]) -> Result<Types> {
+        self.validator.reset();
+
+        let mut functions = Vec::new();
+        let mut types = None;
+        let mut parser = Parser::new(0);
+        let _ = &mut parser;
+        #[cfg(feature = "features")]
+        parser.set_features(*self.validator.features());
+        for payload in parser.parse_all(bytes) {
+            match self.validator.payload(&payload?)? {
+                ValidPayload::Func(func, body) => functions.push((func, body)),
+                ValidPayload::End(t) => types = Some(t),
+                _ => {}
+            }
+        }
+        let types = types.unwrap();
+
+        let module = match types.module() {
+            Some(module) => module.clone(),
+            None => {
+                for (func, body) in functions {
+                    self.validate_body(func, &body)?;
+                }
+                self.last = None;
+                return Ok(types);
+            }
+        };
+
+        // The module last validated successfully is kept if validation fails
+        // here, so that later edits fixing the failure are still compared
+        // against it.
+        let last = self.last.take();
+        match self.validate_functions(last.as_ref(), module, functions) {
+            Ok(validated) => {
+                self.last = Some(validated);
+                Ok(types)
+            }
+            Err(e) => {
+                self.last = last;
+                Err(e)
+            }
+        }
+    }
+
+    fn validate_functions(
+        &mut self,
+        last: Option<&Validated>,
+        module: Arc<Module>,
+        functions: Vec<(FuncToValidate<ValidatorResources>, FunctionBody<'_>)>,
+    ) -> Result<Validated> {
+        let last = last.filter(|last| last.module.same_function_context(&module));
+        let mut validated = Validated {
+            module,
+            code: Vec::new(),
+            bodies: Vec::new(),
+        };
+        for (i, (func, body)) in functions.into_iter().enumerate() {
+            let bytes = body.as_bytes();
+            if last.and_then(|last| last.body(i)) != Some(bytes) {
+                self.validate_body(func, &body)?;
+            }
+            validated.push_body(bytes);
+        }
+        Ok(validated)
+    }
+
+    /// Validates `body` as the replacement of the body of the function
+    /// `index` in the module last validated successfully.
+    ///
+    /// The `index` is in the function index space of the module, which
+    /// includes imported functions. Upon success the replacement is
+    /// remembered, so the next call to [`IncrementalValidator::validate`]
+    /// won't validate it again.
+    ///
+    /// # Errors
+    ///
+    /// If no module has been validated successfully, if `index` isn't a
+    /// function defined by the module, or if `body` is invalid.
+    pub fn validate_function(&mut self, index: u32, body: &FunctionBody<'_>) -> Result<()> {
+        let offset = body.range().start;
+        let last = match &self.last {
+            Some(last) => last,
+            None => bail!(offset, "no module has been validated"),
+        };
+        let defined = index
+            .checked_sub(last.module.num_imported_functions())
+            .map(|i| i as usize)
+            .filter(|i| *i < last.bodies.len());
+        let defined = match defined {
+            Some(i) => i,
+            None => bail!(offset, "function index {index} is not a defined function"),
+        };
+        let func = FuncToValidate {
+            resources: ValidatorResources(last.module.clone()),
+            index,
+            ty: last.module.functions[index as usize],
+            features: *self.validator.features(),
+        };
+        self.validate_body(func, body)?;
+
+        let last = self.last.as_mut().unwrap();
+        let start = last.code.len();
+        last.code.extend_from_slice(body.as_bytes());
+        last.bodies[defined] = start..last.code.len();
+        Ok(())
+    }
+
+    fn validate_body(
+        &mut self,
+        func: FuncToValidate<ValidatorResources>,
+        body: &FunctionBody<'_>,
+    ) -> Result<()> {
+        let mut validator = func.into_validator(mem::take(&mut self.allocs));
+        validator.validate(body)?;
+        self.allocs = validator.into_allocations();
+        Ok(())
+    }
+}
+
+impl Default for IncrementalValidator {
+    fn default() -> IncrementalValidator {
+        IncrementalValidator::new(WasmFeatures::default())
+    }
+}
+
+impl Validated {
+    fn body(&self, i: usize) -> Option<&[u8]> {
+        Some(&self.code[self.bodies.get(i)?.clone()])
+    }
+
+    fn push_body(&mut self, bytes: &[u8]) {
+        let start = self.code.len();
+        self.code.extend_from_slice(bytes);
+        self.bodies.push(start..self.code.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Payload;
+
+    fn bodies(wasm: &[u8]) -> Vec<FunctionBody<'_>> {
+        Parser::new(0)
+            .parse_all(wasm)
+            .filter_map(|payload| match payload.unwrap() {
+                Payload::CodeSectionEntry(body) => Some(body),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn edits_are_revalidated() -> anyhow::Result<()> {
+        let mut validator = IncrementalValidator::default();
+        validator.validate(&wat::parse_str(
+            r#"
+            (module
+                (global i32 (i32.const 0))
+                (func (result i32) global.get 0)
+                (func (result i32) i32.const 0)
+            )
+        "#,
+        )?)?;
+
+        // An invalid edit of a function body.
+        let err = validator
+            .validate(&wat::parse_str(
+                r#"
+                (module
+                    (global i32 (i32.const 0))
+                    (func (result i32) global.get 0)
+                    (func (result i32) i64.const 0)
+                )
+            "#,
+            )?)
+            .err()
+            .unwrap();
+        assert!(err.message().contains("type mismatch"), "{err}");
+
+        // Removing the global invalidates the first function even though its
+        // body didn't change.
+        let err = validator
+            .validate(&wat::parse_str(
+                r#"
+                (module
+                    (func (result i32) global.get 0)
+                    (func (result i32) i32.const 0)
+                )
+            "#,
+            )?)
+            .err()
+            .unwrap();
+        assert!(err.message().contains("unknown global"), "{err}");
+
+        // As does changing its type.
+        let err = validator
+            .validate(&wat::parse_str(
+                r#"
+                (module
+                    (global i64 (i64.const 0))
+                    (func (result i32) global.get 0)
+                    (func (result i32) i32.const 0)
+                )
+            "#,
+            )?)
+            .err()
+            .unwrap();
+        assert!(err.message().contains("type mismatch"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_function() -> anyhow::Result<()> {
+        let mut validator = IncrementalValidator::default();
+        let replacements = wat::parse_str(
+            r#"
+            (module
+                (func (result i32) i32.const 1)
+                (func (result i32) i64.const 1)
+            )
+        "#,
+        )?;
+        let replacements = bodies(&replacements);
+
+        let err = validator
+            .validate_function(0, &replacements[0])
+            .unwrap_err();
+        assert!(err.message().contains("no module"), "{err}");
+
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "" "" (func))
+                (func (result i32) i32.const 0)
+            )
+        "#,
+        )?;
+        validator.validate(&wasm)?;
+
+        validator.validate_function(1, &replacements[0])?;
+        let err = validator
+            .validate_function(1, &replacements[1])
+            .unwrap_err();
+        assert!(err.message().contains("type mismatch"), "{err}");
+        for index in [0, 2] {
+            let err = validator
+                .validate_function(index, &replacements[0])
+                .unwrap_err();
+            assert!(err.message().contains("not a defined function"), "{err}");
+        }
+        Ok(())
+    }
+}
@@ -57,6 +57,9 @@ pub(crate) struct OperatorValidator {
 
     /// Whether validation is happening in a shared context.
     shared: bool,
+
+    /// The maximum heights of the stacks and the special calls found so far.
+    stack_usage: StackUsage,
 }
 
 // No science was performed in the creation of this number, feel free to change
@@ -107,6 +110,42 @@ pub struct Frame {
     pub init_height: usize,
 }
 
+/// How a function uses the operand and control stacks, as found while
+/// validating it.
+///
+/// This is available from [`FuncValidator::stack_usage`] and is intended for
+/// compilers and interpreters which want to allocate the frame of a function
+/// up front. The heights are upper bounds as they include instructions in
+/// unreachable code, which are validated but never executed.
+///
+/// [`FuncValidator::stack_usage`]: crate::FuncValidator::stack_usage
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackUsage {
+    /// The maximum number of values on the operand stack at any point in the
+    /// function.
+    ///
+    /// This doesn't include the function's parameters or locals.
+    pub max_operand_stack_height: u32,
+    /// The maximum number of frames on the control stack at any point in the
+    /// function, including the frame of the function body itself.
+    ///
+    /// This is one more than the deepest label index which can be used to
+    /// branch out of the function body.
+    pub max_control_stack_height: u32,
+    /// The offsets of the `return_call`, `return_call_indirect`, and
+    /// `return_call_ref` instructions in the function.
+    ///
+    /// Each of these replaces the function's frame with the frame of the
+    /// callee rather than pushing a new one.
+    pub tail_calls: Vec<usize>,
+    /// The offsets of the `resume`, `resume_throw`, `suspend`, and `switch`
+    /// instructions in the function.
+    ///
+    /// Each of these switches to the stack of another continuation, which
+    /// needs its own frames to be allocated.
+    pub stack_switches: Vec<usize>,
+}
+
 /// The kind of a control flow [`Frame`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FrameKind {
@@ -286,6 +325,10 @@ impl OperatorValidator {
             control,
             end_which_emptied_control: None,
             shared: false,
+            stack_usage: StackUsage {
+                max_control_stack_height: 1,
+                ..StackUsage::default()
+            },
         }
     }
 
@@ -412,6 +455,11 @@ impl OperatorValidator {
         self.control.iter().rev().nth(depth)
     }
 
+    /// Returns how the function uses its stacks so far.
+    pub fn stack_usage(&self) -> &StackUsage {
+        &self.stack_usage
+    }
+
     /// Create a temporary [`OperatorValidatorTemp`] for validation.
     pub fn with_resources<'a, 'validator, 'resources, T>(
         &'validator mut self,
@@ -513,6 +561,10 @@ where
         }
 
         self.operands.push(maybe_ty);
+        let height = self.operands.len() as u32;
+        if height > self.stack_usage.max_operand_stack_height {
+            self.stack_usage.max_operand_stack_height = height;
+        }
         Ok(())
     }
 
@@ -800,6 +852,10 @@ where
             unreachable: false,
             init_height,
         });
+        let depth = self.control.len() as u32;
+        if depth > self.stack_usage.max_control_stack_height {
+            self.stack_usage.max_control_stack_height = depth;
+        }
         // All of the parameters are now also available in this control frame,
         // so we push them here in order.
         for ty in self.params(ty)? {
@@ -968,7 +1024,10 @@ where
     fn check_return_call_ty(&mut self, ty: &FuncType) -> Result<()> {
         self.check_func_type_same_results(ty)?;
         self.check_call_ty(ty)?;
-        self.check_return()
+        self.check_return()?;
+        let offset = self.offset;
+        self.stack_usage.tail_calls.push(offset);
+        Ok(())
     }
 
     /// Checks the immediate `type_index` of a `call_ref`-style instruction
@@ -4903,6 +4962,8 @@ where
         for &ty in ft.results() {
             self.push_operand(ty)?;
         }
+        let offset = self.offset;
+        self.stack_usage.stack_switches.push(offset);
         Ok(())
     }
     fn visit_resume(&mut self, type_index: u32, table: ResumeTable) -> Self::Output {
@@ -4918,6 +4979,8 @@ where
         for &ty in ft.results() {
             self.push_operand(ty)?;
         }
+        let offset = self.offset;
+        self.stack_usage.stack_switches.push(offset);
         Ok(())
     }
     fn visit_resume_throw(
@@ -4943,6 +5006,8 @@ where
         for &ty in ft.results() {
             self.push_operand(ty)?;
         }
+        let offset = self.offset;
+        self.stack_usage.stack_switches.push(offset);
         Ok(())
     }
     fn visit_switch(&mut self, type_index: u32, tag_index: u32) -> Self::Output {
@@ -5003,6 +5068,8 @@ where
                 "type mismatch: instruction requires a continuation reference"
             ),
         }
+        let offset = self.offset;
+        self.stack_usage.stack_switches.push(offset);
         Ok(())
     }
 }
@@ -3,12 +3,13 @@
 use super::{
     component::{ComponentState, ExternKind},
     core::Module,
+    fingerprint::Fingerprinter,
 };
 use crate::{collections::map::Entry, AbstractHeapType};
 use crate::{prelude::*, CompositeInnerType};
 use crate::{validator::names::KebabString, HeapType, ValidatorId};
 use crate::{
-    BinaryReaderError, Export, ExternalKind, FuncType, GlobalType, Import, Matches, MemoryType,
+    BinaryReaderError, Digest, Export, ExternalKind, FuncType, GlobalType, Import, Matches, MemoryType,
     PackedIndex, PrimitiveValType, RecGroup, RefType, Result, SubType, TableType, TypeRef,
     UnpackedIndex, ValType, WithRecGroup,
 };
@@ -17,6 +18,7 @@ use core::ops::{Deref, DerefMut, Index, Range};
 use core::sync::atomic::{AtomicUsize, Ordering};
 use core::{
     borrow::Borrow,
+    fmt,
     hash::{Hash, Hasher},
     mem,
 };
@@ -848,6 +850,39 @@ impl EntityType {
     }
 }
 
+/// The result of matching an import of a module against the items provided
+/// by a host, as returned by [`TypesRef::check_imports`].
+#[derive(Debug, Clone)]
+pub struct ImportMatch<'a> {
+    /// The module name of the import.
+    pub module: &'a str,
+    /// The field name of the import.
+    pub name: &'a str,
+    /// The type of the import.
+    pub ty: EntityType,
+    /// Whether the host provides a compatible item for this import.
+    pub result: core::result::Result<(), ImportMismatch>,
+}
+
+/// The reason an import isn't satisfied by the items provided by a host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportMismatch {
+    /// The host doesn't provide an item with the name of the import.
+    Missing,
+    /// The host provides an item with the name of the import, but its type
+    /// doesn't match the type of the import.
+    Incompatible(String),
+}
+
+impl fmt::Display for ImportMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportMismatch::Missing => f.write_str("no item provided"),
+            ImportMismatch::Incompatible(msg) => f.write_str(msg),
+        }
+    }
+}
+
 trait ModuleImportKey {
     fn module(&self) -> &str;
     fn name(&self) -> &str;
@@ -1976,6 +2011,153 @@ impl<'a> TypesRef<'a> {
             TypesRefKind::Component(_) => None,
         }
     }
+
+    /// Checks whether each import of this module is satisfied by the items
+    /// provided by a host.
+    ///
+    /// The host is described by the imports of another module, `host`, which
+    /// imports each item the host provides with its type. An item provided by
+    /// the host matches an import if its type is a subtype of the type of the
+    /// import, following the import matching rules of the core specification,
+    /// including those of the GC proposal for concrete reference types.
+    ///
+    /// Types are compared by their [`CoreTypeId`], so this module and `host`
+    /// must have been validated by validators which took over the same
+    /// [`TypeRegistry`] in turn, or by the same [`Validator`] reset between
+    /// them.
+    ///
+    /// Returns `None` if either this type information or `host` is for a
+    /// component.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `host` wasn't validated with the same types as this module.
+    ///
+    /// ```
+    /// fn foo() -> anyhow::Result<()> {
+    /// use wasmparser::types::ImportMismatch;
+    /// use wasmparser::Validator;
+    ///
+    /// let host = wat::parse_str(r#"
+    ///     (module
+    ///         (type $base (sub (struct)))
+    ///         (type $derived (sub $base (struct (field i32))))
+    ///         (type $make (sub (func (result (ref null $base)))))
+    ///         (type $make_derived (sub $make (func (result (ref $derived)))))
+    ///         (import "env" "make" (func (type $make_derived)))
+    ///         (import "env" "memory" (memory 2))
+    ///     )
+    /// "#)?;
+    /// let module = wat::parse_str(r#"
+    ///     (module
+    ///         (type $base (sub (struct)))
+    ///         (type $make (sub (func (result (ref null $base)))))
+    ///         (import "env" "make" (func (type $make)))
+    ///         (import "env" "memory" (memory 1 1))
+    ///         (import "env" "log" (func (param i32)))
+    ///     )
+    /// "#)?;
+    ///
+    /// let mut validator = Validator::new();
+    /// let host = validator.validate_all(&host)?;
+    /// validator.reset();
+    /// let module = validator.validate_all(&module)?;
+    ///
+    /// let report = module.check_imports(&host).unwrap();
+    /// assert_eq!(report[0].result, Ok(()));
+    /// assert!(matches!(report[1].result, Err(ImportMismatch::Incompatible(_))));
+    /// assert_eq!(report[2].result, Err(ImportMismatch::Missing));
+    /// # Ok(())
+    /// # }
+    /// # foo().unwrap()
+    /// ```
+    ///
+    /// [`TypeRegistry`]: crate::TypeRegistry
+    /// [`Validator`]: crate::Validator
+    pub fn check_imports(&self, host: &TypesRef<'_>) -> Option<Vec<ImportMatch<'a>>> {
+        assert_eq!(
+            self.id, host.id,
+            "host types must be validated with the same types as the module"
+        );
+        let provided = host
+            .core_imports()?
+            .map(|(module, name, ty)| ((module, name), ty))
+            .collect::<Map<_, _>>();
+        let imports = self.core_imports()?;
+
+        // Both lists of types are snapshots of the same list, so the longer
+        // of the two knows about the types of both modules.
+        let list = if self.list.core_types.len() >= host.list.core_types.len() {
+            self.list
+        } else {
+            host.list
+        };
+        Some(
+            imports
+                .map(|(module, name, ty)| {
+                    let result = match provided.get(&(module, name)) {
+                        Some(actual) => list
+                            .entity_type_matches(actual, &ty)
+                            .map_err(ImportMismatch::Incompatible),
+                        None => Err(ImportMismatch::Missing),
+                    };
+                    ImportMatch {
+                        module,
+                        name,
+                        ty,
+                        result,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Computes a fingerprint of the external interface of this module or
+    /// component with the hash function `D`.
+    ///
+    /// The fingerprint covers the names and types of imports and exports, and
+    /// for modules whether there's a start function, but nothing else. Modules
+    /// and components with different code, or with their types defined in a
+    /// different order, have the same fingerprint as long as one can be
+    /// swapped for the other, which makes it a cheap key for caches of
+    /// compiled or linked code.
+    ///
+    /// Types are hashed structurally, so the fingerprint doesn't depend on the
+    /// [`Validator`](crate::Validator) used and is stable across runs and
+    /// platforms. The imports of a module are hashed in order since some
+    /// embedders provide them by position, while its exports, and the
+    /// imports and exports of a component, are hashed in order of their
+    /// names.
+    ///
+    /// A component has the same fingerprint as its type, see
+    /// [`TypesRef::component_type_fingerprint`].
+    pub fn interface_fingerprint<D: Digest>(&self) -> D::Output {
+        let mut fingerprinter = Fingerprinter::<D>::new(self.list);
+        match &self.kind {
+            TypesRefKind::Module(module) => fingerprinter.module(
+                self.core_imports().unwrap(),
+                self.core_exports().unwrap(),
+                module.start.is_some(),
+            ),
+            TypesRefKind::Component(component) => {
+                fingerprinter.component(&component.imports, &component.exports)
+            }
+        }
+        fingerprinter.finish()
+    }
+
+    /// Computes a fingerprint of the interface described by the component
+    /// type `id` with the hash function `D`.
+    ///
+    /// This is the same as the [`TypesRef::interface_fingerprint`] of a
+    /// component with exactly this type, so, for example, a component can be
+    /// checked against the fingerprint of the type it's expected to have.
+    pub fn component_type_fingerprint<D: Digest>(&self, id: ComponentTypeId) -> D::Output {
+        let ty = &self.list[id];
+        let mut fingerprinter = Fingerprinter::<D>::new(self.list);
+        fingerprinter.component(&ty.imports, &ty.exports);
+        fingerprinter.finish()
+    }
 }
 
 impl<T> Index<T> for TypesRef<'_>
@@ -2016,6 +2198,14 @@ impl Types {
         self.id
     }
 
+    /// Returns the validated module if these are the types of a module.
+    pub(crate) fn module(&self) -> Option<&Arc<Module>> {
+        match &self.kind {
+            TypesKind::Module(module) => Some(module),
+            TypesKind::Component(_) => None,
+        }
+    }
+
     /// Gets a reference to this validation type information.
     pub fn as_ref(&self) -> TypesRef {
         TypesRef {
@@ -2323,6 +2513,21 @@ impl Types {
     pub fn core_exports(&self) -> Option<impl Iterator<Item = (&str, EntityType)> + '_> {
         self.as_ref().core_exports()
     }
+
+    /// Same as [`TypesRef::check_imports`]
+    pub fn check_imports(&self, host: &Types) -> Option<Vec<ImportMatch<'_>>> {
+        self.as_ref().check_imports(&host.as_ref())
+    }
+
+    /// Same as [`TypesRef::interface_fingerprint`]
+    pub fn interface_fingerprint<D: Digest>(&self) -> D::Output {
+        self.as_ref().interface_fingerprint::<D>()
+    }
+
+    /// Same as [`TypesRef::component_type_fingerprint`]
+    pub fn component_type_fingerprint<D: Digest>(&self, id: ComponentTypeId) -> D::Output {
+        self.as_ref().component_type_fingerprint::<D>(id)
+    }
 }
 
 impl<T> Index<T> for Types
@@ -2633,9 +2838,17 @@ impl TypeList {
     /// Helper for interning a sub type as a rec group; see
     /// [`Self::intern_canonical_rec_group`].
     pub fn intern_sub_type(&mut self, sub_ty: SubType, offset: usize) -> CoreTypeId {
-        let (_is_new, group_id) =
+        debug_assert!(sub_ty.supertype_idx.is_none());
+        let (is_new, group_id) =
             self.intern_canonical_rec_group(RecGroup::implicit(offset, sub_ty));
-        self[group_id].start
+        let id = self[group_id].start;
+        // Without a supertype there's nothing to check, but the depth is
+        // still recorded for types within modules which declare this type as
+        // their supertype.
+        if is_new {
+            self.set_subtyping_depth(id, 0);
+        }
+        id
     }
 
     /// Get the `CoreTypeId` for a local index into a rec group.
@@ -2676,11 +2889,19 @@ impl TypeList {
         let depth = self
             .core_type_to_depth
             .as_ref()
-            .expect("cannot get subtype depth from a committed list")[id.index()];
+            .expect("cannot get subtype depth from a committed list")[&id];
         debug_assert!(usize::from(depth) <= crate::limits::MAX_WASM_SUBTYPING_DEPTH);
         depth
     }
 
+    /// Returns whether the subtyping depth of the given type has been set.
+    pub fn has_subtyping_depth(&self, id: CoreTypeId) -> bool {
+        self.core_type_to_depth
+            .as_ref()
+            .expect("cannot get subtype depth from a committed list")
+            .contains_key(&id)
+    }
+
     /// Set the subtyping depth of the given type. This may only be done once
     /// per type.
     pub fn set_subtyping_depth(&mut self, id: CoreTypeId, depth: u8) {
@@ -2865,6 +3086,98 @@ impl TypeList {
         }
     }
 
+    /// Does the `actual` item provided for an import match the `expected`
+    /// type of the import?
+    ///
+    /// Returns a description of the mismatch if it doesn't.
+    pub(crate) fn entity_type_matches(
+        &self,
+        actual: &EntityType,
+        expected: &EntityType,
+    ) -> core::result::Result<(), String> {
+        fn limits_match(actual: (u64, Option<u64>), expected: (u64, Option<u64>)) -> bool {
+            actual.0 >= expected.0
+                && match expected.1 {
+                    Some(expected) => matches!(actual.1, Some(actual) if actual <= expected),
+                    None => true,
+                }
+        }
+
+        match (actual, expected) {
+            (EntityType::Func(a), EntityType::Func(b)) => {
+                if self.id_is_subtype(*a, *b) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected func of type {}, found {}",
+                        self[*b].unwrap_func().desc(),
+                        self[*a].unwrap_func().desc(),
+                    ))
+                }
+            }
+            (EntityType::Table(a), EntityType::Table(b)) => {
+                if a.element_type != b.element_type {
+                    Err(format!(
+                        "expected table element type {}, found {}",
+                        b.element_type, a.element_type,
+                    ))
+                } else if a.table64 != b.table64 {
+                    Err("mismatch in index type used for tables".to_string())
+                } else if a.shared != b.shared {
+                    Err("mismatch in the shared flag for tables".to_string())
+                } else if !limits_match((a.initial, a.maximum), (b.initial, b.maximum)) {
+                    Err("mismatch in table limits".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            (EntityType::Memory(a), EntityType::Memory(b)) => {
+                if a.memory64 != b.memory64 {
+                    Err("mismatch in index type used for memories".to_string())
+                } else if a.shared != b.shared {
+                    Err("mismatch in the shared flag for memories".to_string())
+                } else if a.page_size_log2.unwrap_or(16) != b.page_size_log2.unwrap_or(16) {
+                    Err("mismatch in page size used for memories".to_string())
+                } else if !limits_match((a.initial, a.maximum), (b.initial, b.maximum)) {
+                    Err("mismatch in memory limits".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            (EntityType::Global(a), EntityType::Global(b)) => {
+                let matches = if a.mutable {
+                    a.content_type == b.content_type
+                } else {
+                    self.valtype_is_subtype(a.content_type, b.content_type)
+                };
+                if a.mutable != b.mutable {
+                    Err("global types differ in mutability".to_string())
+                } else if a.shared != b.shared {
+                    Err("mismatch in the shared flag for globals".to_string())
+                } else if !matches {
+                    Err(format!(
+                        "expected global type {}, found {}",
+                        b.content_type, a.content_type,
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            (EntityType::Tag(a), EntityType::Tag(b)) => {
+                if a == b {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected tag of type {}, found {}",
+                        self[*b].unwrap_func().desc(),
+                        self[*a].unwrap_func().desc(),
+                    ))
+                }
+            }
+            (a, b) => Err(format!("expected {}, found {}", b.desc(), a.desc())),
+        }
+    }
+
     /// Is `ty` shared?
     pub fn valtype_is_shared(&self, ty: ValType) -> bool {
         match ty {
@@ -9,7 +9,7 @@
 #![deny(missing_docs)]
 
 use anyhow::{anyhow, bail, Context, Result};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::io;
 use std::marker;
@@ -23,7 +23,9 @@ const MAX_WASM_FUNCTIONS: u32 = 1_000_000;
 const MAX_WASM_FUNCTION_SIZE: u32 = 128 * 1024;
 
 mod operator;
+mod parallel;
 mod print;
+mod reloc;
 
 pub use self::print::*;
 
@@ -37,6 +39,9 @@ pub fn print_file(file: impl AsRef<Path>) -> Result<String> {
 
 /// Prints an in-memory `wasm` binary blob into an in-memory `String` which is
 /// its textual representation.
+///
+/// For large binaries consider [`Config::print_to`] instead which writes the
+/// text format out incrementally rather than holding it all in memory.
 pub fn print_bytes(wasm: impl AsRef<[u8]>) -> Result<String> {
     let mut dst = String::new();
     Config::new().print(wasm.as_ref(), &mut PrintFmtWrite(&mut dst))?;
@@ -50,8 +55,15 @@ pub fn print_bytes(wasm: impl AsRef<[u8]>) -> Result<String> {
 #[derive(Default)]
 pub struct Config {
     print_offsets: bool,
+    print_relocations: bool,
     print_skeleton: bool,
     name_unnamed: bool,
+    name_labels: bool,
+    print_decimal_floats: bool,
+    print_float_bits: bool,
+    print_hex_ints: bool,
+    threads: usize,
+    max_func_size: Option<usize>,
 }
 
 /// This structure is the actual structure that prints WebAssembly binaries.
@@ -87,6 +99,9 @@ struct CoreState {
     data_names: NamingMap<u32, NameData>,
     module_names: NamingMap<u32, NameModule>,
     instance_names: NamingMap<u32, NameInstance>,
+    /// Descriptions of the relocations found in `reloc.*` sections, keyed by
+    /// the offset of the bytes they apply to.
+    relocations: BTreeMap<usize, String>,
 }
 
 /// A map of index-to-name for tracking what are the contents of the name
@@ -170,12 +185,34 @@ impl Config {
         self.print_offsets = print;
     }
 
+    /// Whether or not to print the relocations of instructions as comments
+    /// after them.
+    ///
+    /// Relocations are read from the `reloc.*` custom sections of object files
+    /// and are described by their type and the name of their symbol from the
+    /// `linking` custom section, similar to `objdump -dr`.
+    pub fn print_relocations(&mut self, print: bool) {
+        self.print_relocations = print;
+    }
+
     /// Whether or not to print only a "skeleton" which skips function bodies,
     /// data segment contents, element segment contents, etc.
+    ///
+    /// Function bodies are replaced with a comment summarizing their number of
+    /// instructions and size in bytes.
     pub fn print_skeleton(&mut self, print: bool) {
         self.print_skeleton = print;
     }
 
+    /// Skips printing function bodies larger than `size` bytes.
+    ///
+    /// Skipped bodies are summarized as with [`Config::print_skeleton`], while
+    /// all other bodies are printed in full. Defaults to `None`, meaning that
+    /// bodies of all sizes are printed.
+    pub fn max_func_size(&mut self, size: Option<usize>) {
+        self.max_func_size = size;
+    }
+
     /// Assign names to all unnamed items.
     ///
     /// If enabled then any previously unnamed item will have a name synthesized
@@ -190,6 +227,58 @@ impl Config {
         self.name_unnamed = enable;
     }
 
+    /// Assign names to unnamed labels based on the instruction introducing
+    /// them.
+    ///
+    /// If enabled then unnamed labels are given names such as `$#block0` or
+    /// `$#loop1`, where the number is the index of the label within its
+    /// function, and branches refer to their target by that name instead of
+    /// by a relative depth. Labels named in the `name` section keep their
+    /// names. This takes precedence over the names of labels synthesized by
+    /// [`Config::name_unnamed`].
+    pub fn name_labels(&mut self, enable: bool) {
+        self.name_labels = enable;
+    }
+
+    /// Whether or not to print the immediates of `f32.const` and `f64.const`
+    /// as decimal numbers instead of hexadecimal floats.
+    ///
+    /// By default floats are printed as exact hexadecimal floats followed by
+    /// a comment with their decimal value. Decimal floats are printed with
+    /// the shortest representation which parses back to the same value, so
+    /// they're exact as well, and have no comment.
+    pub fn print_decimal_floats(&mut self, print: bool) {
+        self.print_decimal_floats = print;
+    }
+
+    /// Whether or not to print the bit pattern of the immediates of
+    /// `f32.const` and `f64.const` as a comment after them.
+    pub fn print_float_bits(&mut self, print: bool) {
+        self.print_float_bits = print;
+    }
+
+    /// Whether or not to print the immediates of `i32.const` and `i64.const`
+    /// in hexadecimal instead of as signed decimal numbers.
+    ///
+    /// Negative numbers are printed as their two's complement bit pattern,
+    /// for example `i32.const 0xffffffff` for -1.
+    pub fn print_hex_ints(&mut self, print: bool) {
+        self.print_hex_ints = print;
+    }
+
+    /// Configures the number of threads used to print function bodies.
+    ///
+    /// Function bodies are independent of one another, so with more than one
+    /// thread the bodies of a code section are printed concurrently in
+    /// batches and then written to the output in their original order. The
+    /// output is the same regardless of the number of threads.
+    ///
+    /// Defaults to 1, meaning that everything is printed on the current
+    /// thread.
+    pub fn threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
     /// Prints a WebAssembly binary into a `String`
     ///
     /// This function takes an entire `wasm` binary blob and will print it to
@@ -206,6 +295,19 @@ impl Config {
         .print_contents(wasm)
     }
 
+    /// Prints a WebAssembly binary to the writer `dst`.
+    ///
+    /// The text format is written out incrementally as `wasm` is printed, so
+    /// unlike [`print_bytes`] the output is never held in memory in its
+    /// entirety. Writes to `dst` are buffered, and `dst` is flushed once
+    /// printing has finished.
+    pub fn print_to(&self, wasm: &[u8], dst: impl io::Write) -> Result<()> {
+        let mut dst = PrintIoWrite(io::BufWriter::new(dst));
+        self.print(wasm, &mut dst)?;
+        io::Write::flush(&mut dst.0)?;
+        Ok(())
+    }
+
     /// Get the line-by-line WAT disassembly for the given Wasm, along with the
     /// binary offsets for each line.
     pub fn offsets_and_lines<'a>(
@@ -264,6 +366,8 @@ impl Printer<'_, '_> {
         mut parser: Parser,
         state: &mut State,
     ) -> Result<()> {
+        let mut relocations = reloc::Relocations::default();
+        let mut linking = None;
         loop {
             let payload = match parser.parse(bytes, true)? {
                 Chunk::NeedMoreData(_) => unreachable!(),
@@ -273,6 +377,12 @@ impl Printer<'_, '_> {
                 }
             };
 
+            // Like the name section, ignore any errors in the sections
+            // describing relocations.
+            if self.config.print_relocations {
+                drop(relocations.visit(&payload));
+            }
+
             match payload {
                 Payload::CodeSectionStart { size, .. } => {
                     if size as usize > bytes.len() {
@@ -308,6 +418,7 @@ impl Printer<'_, '_> {
                         KnownCustom::BranchHints(reader) => {
                             drop(self.register_branch_hint_section(reader));
                         }
+                        KnownCustom::Linking(reader) => linking = Some(reader),
                         _ => {}
                     }
                 }
@@ -317,6 +428,16 @@ impl Printer<'_, '_> {
             }
         }
 
+        // Names from the `linking` section only fill in what the `name`
+        // section, which may come after it, leaves unnamed.
+        if let Some(reader) = linking {
+            drop(self.register_linking_names(state, reader));
+        }
+
+        if self.config.print_relocations {
+            drop(relocations.finish(state));
+        }
+
         Ok(())
     }
 
@@ -506,9 +627,24 @@ impl Printer<'_, '_> {
                     Self::ensure_module(&states)?;
                     self.print_elems(states.last_mut().unwrap(), s)?;
                 }
-                Payload::CodeSectionStart { .. } => {
+                Payload::CodeSectionStart { count, range, size } => {
                     self.update_custom_section_place(&mut states, "after code");
                     Self::ensure_module(&states)?;
+                    if self.config.threads > 1 && !self.config.print_skeleton {
+                        let size = size as usize;
+                        if size > bytes.len() {
+                            bail!("invalid code section size");
+                        }
+                        let reader = BinaryReader::new(&bytes[..size], range.end - size);
+                        bytes = &bytes[size..];
+                        parser.skip_section();
+                        self.print_code_section_parallel(
+                            states.last_mut().unwrap(),
+                            func_reader.as_mut(),
+                            count,
+                            reader,
+                        )?;
+                    }
                 }
                 Payload::CodeSectionEntry(body) => {
                     if let Some(ref mut reader) = func_reader {
@@ -688,6 +824,65 @@ impl Printer<'_, '_> {
         Ok(())
     }
 
+    /// Names the data segments and symbols of an object file which the
+    /// `name` section doesn't name after their entries in the `linking`
+    /// section, so they're referred to by name in data and element segment
+    /// offsets and instructions alike.
+    fn register_linking_names(
+        &mut self,
+        state: &mut State,
+        linking: LinkingSectionReader<'_>,
+    ) -> Result<()> {
+        let mut segments = Vec::new();
+        let mut funcs = Vec::new();
+        let mut globals = Vec::new();
+        let mut tables = Vec::new();
+        let mut tags = Vec::new();
+        for subsection in linking.subsections() {
+            match subsection? {
+                Linking::SegmentInfo(map) => {
+                    for (index, segment) in map.into_iter().enumerate() {
+                        segments.push((index as u32, segment?.name));
+                    }
+                }
+                Linking::SymbolTable(map) => {
+                    for symbol in map {
+                        match symbol? {
+                            SymbolInfo::Func {
+                                name: Some(name),
+                                index,
+                                ..
+                            } => funcs.push((index, name)),
+                            SymbolInfo::Global {
+                                name: Some(name),
+                                index,
+                                ..
+                            } => globals.push((index, name)),
+                            SymbolInfo::Table {
+                                name: Some(name),
+                                index,
+                                ..
+                            } => tables.push((index, name)),
+                            SymbolInfo::Event {
+                                name: Some(name),
+                                index,
+                                ..
+                            } => tags.push((index, name)),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        fallback_names(&mut state.core.data_names, &segments, "data");
+        fallback_names(&mut state.core.func_names, &funcs, "func");
+        fallback_names(&mut state.core.global_names, &globals, "global");
+        fallback_names(&mut state.core.table_names, &tables, "table");
+        fallback_names(&mut state.core.tag_names, &tags, "tag");
+        Ok(())
+    }
+
     fn register_component_names(
         &mut self,
         state: &mut State,
@@ -1269,41 +1464,91 @@ impl Printer<'_, '_> {
         body: &FunctionBody<'_>,
         ty: u32,
     ) -> Result<()> {
-        let mut body = body.get_binary_reader();
-        let offset = body.original_position();
-        self.newline(offset)?;
-        self.start_group("func ")?;
         let func_idx = state.core.funcs;
-        self.print_name(&state.core.func_names, func_idx)?;
-        self.result.write_str(" ")?;
-        let params = self
-            .print_core_functype_idx(state, ty, Some(func_idx))?
-            .unwrap_or(0);
+        let hints = self.take_branch_hints(func_idx);
+        self.print_func(state, func_idx, body, ty, &hints)?;
+        state.core.funcs += 1;
+        Ok(())
+    }
 
-        // Hints are stored on `self` in reverse order of function index so
-        // check the last one and see if it matches this function.
-        let hints = match self.code_section_hints.last() {
+    /// Returns the branch hints for the function `func_idx`.
+    ///
+    /// Hints are stored on `self` in reverse order of function index, so this
+    /// must be called for each function in order.
+    fn take_branch_hints(&mut self, func_idx: u32) -> Vec<(usize, BranchHint)> {
+        match self.code_section_hints.last() {
             Some((f, _)) if *f == func_idx => {
                 let (_, hints) = self.code_section_hints.pop().unwrap();
                 hints
             }
             _ => Vec::new(),
-        };
+        }
+    }
 
-        if self.config.print_skeleton {
-            self.result.write_str(" ...")?;
+    fn print_func(
+        &mut self,
+        state: &State,
+        func_idx: u32,
+        body: &FunctionBody<'_>,
+        ty: u32,
+        hints: &[(usize, BranchHint)],
+    ) -> Result<()> {
+        let offset = body.range().start;
+        self.newline(offset)?;
+        self.start_group("func ")?;
+        self.print_name(&state.core.func_names, func_idx)?;
+        self.result.write_str(" ")?;
+        let params = self
+            .print_core_functype_idx(state, ty, Some(func_idx))?
+            .unwrap_or(0);
+
+        let size = body.range().len();
+        let skip =
+            self.config.print_skeleton || self.config.max_func_size.is_some_and(|max| size > max);
+        if skip {
+            self.print_func_summary(body)?;
         } else {
-            self.print_func_body(state, func_idx, params, &mut body, &hints)?;
+            let mut body = body.get_binary_reader();
+            self.print_func_body(state, func_idx, params, &mut body, hints)?;
         }
 
-        self.end_group()?;
-        state.core.funcs += 1;
+        self.end_group()
+    }
+
+    /// Prints a comment in place of the body of a function with its number
+    /// of instructions and size in bytes.
+    ///
+    /// Bodies whose instructions can't be decoded are summarized with their
+    /// size alone.
+    fn print_func_summary(&mut self, body: &FunctionBody<'_>) -> Result<()> {
+        let instructions = body.get_operators_reader().and_then(|mut ops| {
+            let mut instructions = 0;
+            while !ops.is_end_then_eof() {
+                ops.read()?;
+                instructions += 1;
+            }
+            Ok(instructions)
+        });
+        self.result.write_str(" ")?;
+        self.result.start_comment()?;
+        let size = body.range().len();
+        match instructions {
+            Ok(instructions) => {
+                let plural = if instructions == 1 { "" } else { "s" };
+                write!(
+                    self.result,
+                    "(; {instructions} instruction{plural}, {size} bytes ;)"
+                )?;
+            }
+            Err(_) => write!(self.result, "(; {size} bytes ;)")?,
+        }
+        self.result.reset_color()?;
         Ok(())
     }
 
     fn print_func_body(
         &mut self,
-        state: &mut State,
+        state: &State,
         func_idx: u32,
         params: u32,
         body: &mut BinaryReader<'_>,
@@ -1339,8 +1584,12 @@ impl Printer<'_, '_> {
 
         let nesting_start = self.nesting;
 
-        let mut op_printer =
-            operator::PrintOperator::new(self, state, operator::OperatorSeparator::Newline);
+        let mut op_printer = operator::PrintOperator::new(
+            self,
+            state,
+            func_idx,
+            operator::OperatorSeparator::Newline,
+        );
         while !body.is_end_then_eof() {
             // Branch hints are stored in increasing order of their body offset
             // so print them whenever their instruction comes up.
@@ -1358,8 +1607,14 @@ impl Printer<'_, '_> {
                 }
             }
 
-            op_printer.op_offset = body.original_position();
+            let op_start = body.original_position();
+            op_printer.op_offset = op_start;
             body.visit_operator(&mut op_printer)??;
+            if !state.core.relocations.is_empty() {
+                op_printer
+                    .printer
+                    .print_relocations(state, op_start..body.original_position())?;
+            }
         }
 
         // If this was an invalid function body then the nesting may not
@@ -1642,8 +1897,9 @@ impl Printer<'_, '_> {
         let mut reader = expr.get_operators_reader();
         let mut first = true;
 
+        let func = state.core.funcs;
         let mut op_printer =
-            operator::PrintOperator::new(self, state, operator::OperatorSeparator::None);
+            operator::PrintOperator::new(self, state, func, operator::OperatorSeparator::None);
         while !reader.is_end_then_eof() {
             if first {
                 first = false;
@@ -3054,6 +3310,13 @@ macro_rules! print_float {
             // Overall this should do basic arithmetic for `$exp_bits` bit
             // numbers and get the result back as a signed integer with `$sint`
             // bits in `exponent` representing the same decimal value.
+            if self.config.print_decimal_floats {
+                self.result.start_literal()?;
+                write!(self.result, "{:?}", $float::from_bits(bits))?;
+                self.result.reset_color()?;
+                return Ok(());
+            }
+
             let mut exponent = (((bits << 1) as $sint) >> (mantissa_width + 1)).wrapping_sub(bias);
             exponent = (exponent << (int_width - exp_width)) >> (int_width - exp_width);
             let mut fraction = bits & ((1 << mantissa_width) - 1);
@@ -3096,8 +3359,27 @@ macro_rules! print_float {
 }
 
 impl Printer<'_, '_> {
-    print_float!(print_f32 f32 u32 i32 8);
-    print_float!(print_f64 f64 u64 i64 11);
+    print_float!(print_f32_literal f32 u32 i32 8);
+    print_float!(print_f64_literal f64 u64 i64 11);
+
+    fn print_f32(&mut self, bits: u32) -> Result<()> {
+        self.print_f32_literal(bits)?;
+        self.print_float_bits(u64::from(bits), 8)
+    }
+
+    fn print_f64(&mut self, bits: u64) -> Result<()> {
+        self.print_f64_literal(bits)?;
+        self.print_float_bits(bits, 16)
+    }
+
+    fn print_float_bits(&mut self, bits: u64, digits: usize) -> Result<()> {
+        if self.config.print_float_bits {
+            self.result.start_comment()?;
+            write!(self.result, " (;bits=0x{bits:0digits$x};)")?;
+            self.result.reset_color()?;
+        }
+        Ok(())
+    }
 }
 
 impl Naming {
@@ -3247,6 +3529,23 @@ naming_namespaces! {
     struct NameTag => "tag"
 }
 
+/// Adds each of `names` to `into` for the indices it doesn't name yet, making
+/// up a name for those whose name is already taken.
+fn fallback_names<K>(into: &mut NamingMap<u32, K>, names: &[(u32, &str)], group: &str) {
+    let existing = into
+        .index_to_name
+        .values()
+        .map(|naming| naming.name.clone())
+        .collect::<Vec<_>>();
+    let mut used = existing.iter().map(|s| s.as_str()).collect::<HashSet<_>>();
+    for (index, name) in names {
+        if !into.index_to_name.contains_key(index) {
+            let naming = Naming::new(name, *index, group, Some(&mut used));
+            into.index_to_name.insert(*index, naming);
+        }
+    }
+}
+
 fn name_map<K>(into: &mut NamingMap<u32, K>, names: NameMap<'_>, name: &str) -> Result<()> {
     let mut used = HashSet::new();
     for naming in names {
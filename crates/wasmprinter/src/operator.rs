@@ -9,9 +9,15 @@ pub struct PrintOperator<'printer, 'state, 'a, 'b> {
     pub(super) printer: &'printer mut Printer<'a, 'b>,
     pub(super) op_offset: usize,
     nesting_start: u32,
-    state: &'state mut State,
+    state: &'state State,
+    /// The index of the function being printed, which is used to look up the
+    /// names of labels and locals.
+    func: u32,
     label: u32,
     label_indices: Vec<u32>,
+    /// The kind of instruction, such as `block` or `loop`, which introduced
+    /// each label of the function so far, indexed by label.
+    label_kinds: Vec<&'static str>,
     sep: OperatorSeparator,
 }
 
@@ -23,7 +29,8 @@ pub enum OperatorSeparator {
 impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
     pub(super) fn new(
         printer: &'printer mut Printer<'a, 'b>,
-        state: &'state mut State,
+        state: &'state State,
+        func: u32,
         sep: OperatorSeparator,
     ) -> Self {
         PrintOperator {
@@ -31,8 +38,10 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
             op_offset: 0,
             printer,
             state,
+            func,
             label: 0,
             label_indices: Vec::new(),
+            label_kinds: Vec::new(),
             sep,
         }
     }
@@ -55,10 +64,11 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
 
     /// Called just before an instruction that introduces a block such as
     /// `block`, `if`, `loop`, etc.
-    fn block_start(&mut self) -> Result<()> {
+    fn block_start(&mut self, kind: &'static str) -> Result<()> {
         self.separator()?;
         self.printer.nesting += 1;
         self.label_indices.push(self.label);
+        self.label_kinds.push(kind);
         Ok(())
     }
 
@@ -85,13 +95,19 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
     }
 
     fn blockty_without_label_comment(&mut self, ty: BlockType) -> Result<bool> {
-        let key = (self.state.core.funcs, self.label);
+        let key = (self.func, self.label);
         let has_name = match self.state.core.label_names.index_to_name.get(&key) {
             Some(name) => {
                 write!(self.printer.result, " ")?;
                 name.write(self.printer)?;
                 true
             }
+            None if self.printer.config.name_labels => {
+                let label = self.label;
+                let kind = self.label_kinds[label as usize];
+                write!(self.result(), " $#{kind}{label}")?;
+                true
+            }
             None if self.printer.config.name_unnamed => {
                 // Subtract one from the depth here because the label was
                 // already pushed onto our stack when the instruction was
@@ -151,13 +167,13 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
             // names don't account for the function name so offset by one more
             // here.
             Some(i) => {
-                let name = i
+                let label = i
                     .checked_sub(1)
-                    .and_then(|idx| self.label_indices.get(idx as usize).copied())
-                    .and_then(|label_idx| {
-                        let key = (self.state.core.funcs, label_idx);
-                        self.state.core.label_names.index_to_name.get(&key)
-                    });
+                    .and_then(|idx| self.label_indices.get(idx as usize).copied());
+                let name = label.and_then(|label_idx| {
+                    let key = (self.func, label_idx);
+                    self.state.core.label_names.index_to_name.get(&key)
+                });
 
                 // This is a bit tricky, but if there's a shallower label than
                 // this target which shares the same name then we can't print
@@ -165,22 +181,29 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
                 // in the case of shadowing, which would be the wrong behavior
                 // here. All that can be done is to print the index down below
                 // instead.
-                let name_conflict = name.is_some()
-                    && self.label_indices[i as usize..].iter().any(|other_label| {
-                        let key = (self.state.core.funcs, *other_label);
-                        if let Some(other) = self.state.core.label_names.index_to_name.get(&key) {
-                            if name.unwrap().name == other.name {
-                                return true;
-                            }
-                        }
-                        false
-                    });
+                let name_conflict = name.is_some_and(|name| self.shadowed(i, &name.name));
 
                 match name {
                     // Only print the name if one is found and there's also no
                     // name conflict.
                     Some(name) if !name_conflict => name.write(self.printer)?,
 
+                    // Labels named after the instruction introducing them are
+                    // unique within a function, so they can only be shadowed
+                    // by a label of the same name from the `name` section.
+                    None if self.printer.config.name_labels
+                        && label.is_some_and(|label| {
+                            let name = format!("#{}{label}", self.label_kinds[label as usize]);
+                            !self.shadowed(i, &name)
+                        }) =>
+                    {
+                        let label = label.unwrap();
+                        let kind = self.label_kinds[label as usize];
+                        self.result().start_name()?;
+                        write!(self.result(), "$#{kind}{label}")?;
+                        self.result().reset_color()?;
+                    }
+
                     // If there's no name conflict, and we're synthesizing
                     // names, and this isn't targetting the function itself then
                     // print a synthesized names.
@@ -222,6 +245,18 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
         Ok(())
     }
 
+    /// Returns whether a label shallower than the one at depth `i` of the
+    /// label stack is named `name` in the `name` section.
+    fn shadowed(&self, i: u32, name: &str) -> bool {
+        self.label_indices[i as usize..].iter().any(|other_label| {
+            let key = (self.func, *other_label);
+            match self.state.core.label_names.index_to_name.get(&key) {
+                Some(other) => other.name == name,
+                None => false,
+            }
+        })
+    }
+
     fn targets(&mut self, targets: BrTable<'_>) -> Result<()> {
         for item in targets.targets().chain([Ok(targets.default())]) {
             self.relative_depth(item?)?;
@@ -236,8 +271,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
 
     fn local_index(&mut self, idx: u32) -> Result<()> {
         self.push_str(" ")?;
-        self.printer
-            .print_local_idx(self.state, self.state.core.funcs, idx)
+        self.printer.print_local_idx(self.state, self.func, idx)
     }
 
     fn global_index(&mut self, idx: u32) -> Result<()> {
@@ -464,11 +498,11 @@ macro_rules! define_visit {
     // depth as well as the stack of labels.
     //
     // The catch-all for "before an op" is "print an newline"
-    (before_op $self:ident Loop) => ($self.block_start()?;);
-    (before_op $self:ident Block) => ($self.block_start()?;);
-    (before_op $self:ident If) => ($self.block_start()?;);
-    (before_op $self:ident Try) => ($self.block_start()?;);
-    (before_op $self:ident TryTable) => ($self.block_start()?;);
+    (before_op $self:ident Loop) => ($self.block_start("loop")?;);
+    (before_op $self:ident Block) => ($self.block_start("block")?;);
+    (before_op $self:ident If) => ($self.block_start("if")?;);
+    (before_op $self:ident Try) => ($self.block_start("try")?;);
+    (before_op $self:ident TryTable) => ($self.block_start("try_table")?;);
     (before_op $self:ident Catch) => ($self.block_mid()?;);
     (before_op $self:ident CatchAll) => ($self.block_mid()?;);
     (before_op $self:ident Delegate) => ($self.block_end()?;);
@@ -562,12 +596,20 @@ macro_rules! define_visit {
     );
     (payload $self:ident I32Const $val:ident) => (
         $self.result().start_literal()?;
-        write!($self.result(), " {}", $val)?;
+        if $self.printer.config.print_hex_ints {
+            write!($self.result(), " {:#x}", $val as u32)?;
+        } else {
+            write!($self.result(), " {}", $val)?;
+        }
         $self.result().reset_color()?;
     );
     (payload $self:ident I64Const $val:ident) => (
         $self.result().start_literal()?;
-        write!($self.result(), " {}", $val)?;
+        if $self.printer.config.print_hex_ints {
+            write!($self.result(), " {:#x}", $val as u64)?;
+        } else {
+            write!($self.result(), " {}", $val)?;
+        }
         $self.result().reset_color()?;
     );
     (payload $self:ident F32Const $val:ident) => (
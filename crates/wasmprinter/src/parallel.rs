@@ -0,0 +1,252 @@
+//! Printing of function bodies on multiple threads.
+//!
+//! Function bodies only read the state of the module they're in, so with
+//! [`Config::threads`](crate::Config::threads) configured a code section is
+//! printed in batches of functions, where the functions of a batch are split
+//! among threads which each record their output. The recorded output of each
+//! function is then replayed to the real output in order.
+
+use super::{Print, Printer, State};
+use anyhow::{anyhow, Result};
+use std::io;
+use std::ops::Range;
+use std::panic;
+use std::thread;
+use wasmparser::{BinaryReader, BinaryReaderError, BranchHint, FunctionBody};
+
+/// The number of functions given to each thread in a batch, which bounds how
+/// much output is buffered before it's written out.
+const FUNCS_PER_THREAD: usize = 64;
+
+/// A function whose body is to be printed.
+struct Func<'a> {
+    index: u32,
+    ty: u32,
+    body: FunctionBody<'a>,
+    hints: Vec<(usize, BranchHint)>,
+}
+
+/// The output of printing a function.
+struct Printed {
+    output: Recording,
+    lines: usize,
+    result: Result<()>,
+}
+
+impl Printer<'_, '_> {
+    /// Prints the `count` functions of the code section whose contents after
+    /// the count are `code`, where `types` yields the type of each function.
+    pub(crate) fn print_code_section_parallel(
+        &mut self,
+        state: &mut State,
+        mut types: Option<&mut impl Iterator<Item = Result<u32, BinaryReaderError>>>,
+        count: u32,
+        mut code: BinaryReader<'_>,
+    ) -> Result<()> {
+        let batch_size = self.config.threads * FUNCS_PER_THREAD;
+        let mut remaining = count;
+        let mut bodies = std::iter::from_fn(|| {
+            if remaining == 0 {
+                if code.eof() {
+                    return None;
+                }
+                let offset = code.original_position();
+                return Some(Err(anyhow!(
+                    "trailing bytes at end of section (at offset {offset:#x})"
+                )));
+            }
+            remaining -= 1;
+            Some(
+                code.read_reader()
+                    .map(FunctionBody::new)
+                    .map_err(Into::into),
+            )
+        });
+        loop {
+            // Functions which were read successfully before any error are
+            // still printed, as they would be when printing on one thread.
+            let mut batch = Vec::new();
+            let mut error = None;
+            while batch.len() < batch_size {
+                let body = match bodies.next() {
+                    Some(Ok(body)) => body,
+                    Some(Err(e)) => {
+                        error = Some(e);
+                        break;
+                    }
+                    None => break,
+                };
+                let ty = match types.as_mut().and_then(|types| types.next()) {
+                    Some(Ok(ty)) => ty,
+                    _ => continue,
+                };
+                let index = state.core.funcs;
+                state.core.funcs += 1;
+                batch.push(Func {
+                    index,
+                    ty,
+                    body,
+                    hints: self.take_branch_hints(index),
+                });
+            }
+
+            let done = batch.len() < batch_size;
+            for printed in self.print_funcs(state, &batch) {
+                printed.output.replay(self.result)?;
+                self.line += printed.lines;
+                printed.result?;
+            }
+            if let Some(e) = error {
+                return Err(e);
+            }
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Prints `funcs` on multiple threads, returning the output of each
+    /// function in order.
+    fn print_funcs(&self, state: &State, funcs: &[Func<'_>]) -> Vec<Printed> {
+        if funcs.is_empty() {
+            return Vec::new();
+        }
+        let config = self.config;
+        let nesting = self.nesting;
+        let chunk_size = funcs.len().div_ceil(config.threads);
+        thread::scope(|scope| {
+            let threads = funcs
+                .chunks(chunk_size)
+                .map(|funcs| {
+                    scope.spawn(move || {
+                        funcs
+                            .iter()
+                            .map(|func| {
+                                let mut output = Recording::default();
+                                let mut printer = Printer {
+                                    config,
+                                    result: &mut output,
+                                    nesting,
+                                    line: 0,
+                                    group_lines: Vec::new(),
+                                    code_section_hints: Vec::new(),
+                                };
+                                let result = printer.print_func(
+                                    state,
+                                    func.index,
+                                    &func.body,
+                                    func.ty,
+                                    &func.hints,
+                                );
+                                let lines = printer.line;
+                                Printed {
+                                    output,
+                                    lines,
+                                    result,
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .flat_map(|thread| {
+                    thread
+                        .join()
+                        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                })
+                .collect()
+        })
+    }
+}
+
+/// A [`Print`] which records everything printed to it so that it can be
+/// replayed to another [`Print`] later.
+#[derive(Default)]
+struct Recording {
+    text: String,
+    events: Vec<Event>,
+}
+
+enum Event {
+    Str(Range<usize>),
+    Newline,
+    StartLine(Option<usize>),
+    StartLiteral,
+    StartName,
+    StartKeyword,
+    StartType,
+    StartComment,
+    ResetColor,
+}
+
+impl Recording {
+    fn replay(&self, dst: &mut dyn Print) -> io::Result<()> {
+        for event in self.events.iter() {
+            match event {
+                Event::Str(range) => dst.write_str(&self.text[range.clone()])?,
+                Event::Newline => dst.newline()?,
+                Event::StartLine(offset) => dst.start_line(*offset),
+                Event::StartLiteral => dst.start_literal()?,
+                Event::StartName => dst.start_name()?,
+                Event::StartKeyword => dst.start_keyword()?,
+                Event::StartType => dst.start_type()?,
+                Event::StartComment => dst.start_comment()?,
+                Event::ResetColor => dst.reset_color()?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Print for Recording {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        let start = self.text.len();
+        self.text.push_str(s);
+        match self.events.last_mut() {
+            Some(Event::Str(range)) if range.end == start => range.end = self.text.len(),
+            _ => self.events.push(Event::Str(start..self.text.len())),
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self) -> io::Result<()> {
+        self.events.push(Event::Newline);
+        Ok(())
+    }
+
+    fn start_line(&mut self, binary_offset: Option<usize>) {
+        self.events.push(Event::StartLine(binary_offset));
+    }
+
+    fn start_literal(&mut self) -> io::Result<()> {
+        self.events.push(Event::StartLiteral);
+        Ok(())
+    }
+
+    fn start_name(&mut self) -> io::Result<()> {
+        self.events.push(Event::StartName);
+        Ok(())
+    }
+
+    fn start_keyword(&mut self) -> io::Result<()> {
+        self.events.push(Event::StartKeyword);
+        Ok(())
+    }
+
+    fn start_type(&mut self) -> io::Result<()> {
+        self.events.push(Event::StartType);
+        Ok(())
+    }
+
+    fn start_comment(&mut self) -> io::Result<()> {
+        self.events.push(Event::StartComment);
+        Ok(())
+    }
+
+    fn reset_color(&mut self) -> io::Result<()> {
+        self.events.push(Event::ResetColor);
+        Ok(())
+    }
+}
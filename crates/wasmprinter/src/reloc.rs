@@ -0,0 +1,175 @@
+//! Printing of the relocations of instructions in object files.
+//!
+//! Object files, as produced by compilers for linkers such as `wasm-ld`,
+//! describe the locations in their code which the linker needs to patch with
+//! `reloc.*` custom sections, and the symbols those locations refer to with the
+//! symbol table of the `linking` custom section. With
+//! [`Config::print_relocations`](crate::Config::print_relocations) each
+//! instruction is followed by a comment for each relocation of its immediates,
+//! similar to `objdump -dr`.
+
+use super::{Printer, State};
+use anyhow::Result;
+use std::fmt::Write;
+use std::ops::Range;
+use wasmparser::{KnownCustom, Linking, Payload, RelocSectionReader, RelocationType, SymbolInfo};
+
+/// The symbol table and relocation sections of a module, gathered while
+/// reading ahead of printing it.
+#[derive(Default)]
+pub(crate) struct Relocations<'a> {
+    /// The offset of the contents of each section of the module, by index.
+    section_starts: Vec<usize>,
+    symbols: Vec<SymbolInfo<'a>>,
+    sections: Vec<RelocSectionReader<'a>>,
+}
+
+impl<'a> Relocations<'a> {
+    /// Records `payload` if it's relevant to relocations.
+    pub(crate) fn visit(&mut self, payload: &Payload<'a>) -> Result<()> {
+        if let Some((_, range)) = payload.as_section() {
+            self.section_starts.push(range.start);
+        }
+        let section = match payload {
+            Payload::CustomSection(section) => section,
+            _ => return Ok(()),
+        };
+        match section.as_known() {
+            KnownCustom::Linking(reader) => {
+                for subsection in reader.subsections() {
+                    if let Linking::SymbolTable(symbols) = subsection? {
+                        for symbol in symbols {
+                            self.symbols.push(symbol?);
+                        }
+                    }
+                }
+            }
+            KnownCustom::Reloc(reader) => self.sections.push(reader),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Records a description of each relocation in `state`, keyed by the
+    /// offset of the bytes it applies to.
+    pub(crate) fn finish(self, state: &mut State) -> Result<()> {
+        for section in self.sections.iter() {
+            let start = match self.section_starts.get(section.section_index() as usize) {
+                Some(start) => *start,
+                None => continue,
+            };
+            for entry in section.entries() {
+                let entry = entry?;
+                let mut desc = relocation_type_name(entry.ty).to_string();
+                desc.push(' ');
+                match entry.ty {
+                    RelocationType::TypeIndexLeb => write!(desc, "type {}", entry.index)?,
+                    _ => match self.symbols.get(entry.index as usize) {
+                        Some(symbol) => symbol_name(state, symbol, &mut desc)?,
+                        None => write!(desc, "symbol {}", entry.index)?,
+                    },
+                }
+                if entry.addend > 0 {
+                    write!(desc, "+{}", entry.addend)?;
+                } else if entry.addend < 0 {
+                    write!(desc, "{}", entry.addend)?;
+                }
+                state
+                    .core
+                    .relocations
+                    .insert(start + entry.offset as usize, desc);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the name of `symbol` to `dst`, falling back to the `name` section
+/// and then to the index of the item it refers to if the symbol table doesn't
+/// name it.
+fn symbol_name(state: &State, symbol: &SymbolInfo<'_>, dst: &mut String) -> Result<()> {
+    let (name, kind, index, names) = match *symbol {
+        SymbolInfo::Func { name, index, .. } => (
+            name,
+            "func",
+            index,
+            Some(&state.core.func_names.index_to_name),
+        ),
+        SymbolInfo::Global { name, index, .. } => (
+            name,
+            "global",
+            index,
+            Some(&state.core.global_names.index_to_name),
+        ),
+        SymbolInfo::Table { name, index, .. } => (
+            name,
+            "table",
+            index,
+            Some(&state.core.table_names.index_to_name),
+        ),
+        SymbolInfo::Event { name, index, .. } => (
+            name,
+            "tag",
+            index,
+            Some(&state.core.tag_names.index_to_name),
+        ),
+        SymbolInfo::Data { name, .. } => (Some(name), "data", 0, None),
+        SymbolInfo::Section { section, .. } => (None, "section", section, None),
+    };
+    match name {
+        Some(name) => dst.push_str(name),
+        None => match names.and_then(|names| names.get(&index)) {
+            Some(naming) => dst.push_str(&naming.name),
+            None => write!(dst, "{kind} {index}")?,
+        },
+    }
+    Ok(())
+}
+
+impl Printer<'_, '_> {
+    /// Prints a comment for each relocation applying to the bytes within
+    /// `range`.
+    pub(crate) fn print_relocations(&mut self, state: &State, range: Range<usize>) -> Result<()> {
+        for desc in state.core.relocations.range(range).map(|(_, desc)| desc) {
+            self.result.write_str(" ")?;
+            self.result.start_comment()?;
+            write!(self.result, "(; {desc} ;)")?;
+            self.result.reset_color()?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of `ty` as used by LLVM.
+fn relocation_type_name(ty: RelocationType) -> &'static str {
+    use RelocationType::*;
+    match ty {
+        FunctionIndexLeb => "R_WASM_FUNCTION_INDEX_LEB",
+        TableIndexSleb => "R_WASM_TABLE_INDEX_SLEB",
+        TableIndexI32 => "R_WASM_TABLE_INDEX_I32",
+        MemoryAddrLeb => "R_WASM_MEMORY_ADDR_LEB",
+        MemoryAddrSleb => "R_WASM_MEMORY_ADDR_SLEB",
+        MemoryAddrI32 => "R_WASM_MEMORY_ADDR_I32",
+        TypeIndexLeb => "R_WASM_TYPE_INDEX_LEB",
+        GlobalIndexLeb => "R_WASM_GLOBAL_INDEX_LEB",
+        FunctionOffsetI32 => "R_WASM_FUNCTION_OFFSET_I32",
+        SectionOffsetI32 => "R_WASM_SECTION_OFFSET_I32",
+        EventIndexLeb => "R_WASM_TAG_INDEX_LEB",
+        MemoryAddrRelSleb => "R_WASM_MEMORY_ADDR_REL_SLEB",
+        TableIndexRelSleb => "R_WASM_TABLE_INDEX_REL_SLEB",
+        GlobalIndexI32 => "R_WASM_GLOBAL_INDEX_I32",
+        MemoryAddrLeb64 => "R_WASM_MEMORY_ADDR_LEB64",
+        MemoryAddrSleb64 => "R_WASM_MEMORY_ADDR_SLEB64",
+        MemoryAddrI64 => "R_WASM_MEMORY_ADDR_I64",
+        MemoryAddrRelSleb64 => "R_WASM_MEMORY_ADDR_REL_SLEB64",
+        TableIndexSleb64 => "R_WASM_TABLE_INDEX_SLEB64",
+        TableIndexI64 => "R_WASM_TABLE_INDEX_I64",
+        TableNumberLeb => "R_WASM_TABLE_NUMBER_LEB",
+        MemoryAddrTlsSleb => "R_WASM_MEMORY_ADDR_TLS_SLEB",
+        FunctionOffsetI64 => "R_WASM_FUNCTION_OFFSET_I64",
+        MemoryAddrLocrelI32 => "R_WASM_MEMORY_ADDR_LOCREL_I32",
+        TableIndexRelSleb64 => "R_WASM_TABLE_INDEX_REL_SLEB64",
+        MemoryAddrTlsSleb64 => "R_WASM_MEMORY_ADDR_TLS_SLEB64",
+        FunctionIndexI32 => "R_WASM_FUNCTION_INDEX_I32",
+    }
+}
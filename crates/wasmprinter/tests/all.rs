@@ -46,3 +46,110 @@ fn offsets_and_lines_smoke_test() {
 
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn threads_match_single_threaded() {
+    let mut s = String::new();
+    s.push_str("(component (core module $m (type $t (func (param i32) (result i32)))\n");
+    for i in 0..1000 {
+        s.push_str(&format!(
+            "(func $f{i} (type $t) (local $l i64) block $b local.get 0 (@metadata.code.branch_hint \"\\01\") br_if $b end i32.const {i})\n"
+        ));
+    }
+    s.push_str(") (core module (func nop)))");
+    let bytes = wat::parse_str(&s).unwrap();
+
+    let print = |threads: usize, offsets: bool| {
+        let mut config = wasmprinter::Config::new();
+        config.threads(threads);
+        config.print_offsets(offsets);
+        let mut storage = String::new();
+        let lines = config
+            .offsets_and_lines(&bytes, &mut storage)
+            .unwrap()
+            .map(|(offset, line)| (offset, line.to_string()))
+            .collect::<Vec<_>>();
+        (storage, lines)
+    };
+    for offsets in [false, true] {
+        let expected = print(1, offsets);
+        assert!(expected.0.contains("$f999"));
+        assert!(expected.0.contains("@metadata.code.branch_hint"));
+        for threads in [2, 3, 16] {
+            assert!(print(threads, offsets) == expected);
+        }
+    }
+}
+
+#[test]
+fn threads_print_up_to_error() {
+    let mut bytes = wat::parse_str("(module (func) (func nop) (func i32.const 1 drop))").unwrap();
+    // Truncate the last instruction of the last function.
+    let len = bytes.len();
+    bytes[len - 4] = 0x41;
+    bytes[len - 3] = 0x80;
+    bytes[len - 2] = 0x80;
+
+    let print = |threads: usize| {
+        let mut config = wasmprinter::Config::new();
+        config.threads(threads);
+        let mut dst = String::new();
+        let result = config.print(&bytes, &mut wasmprinter::PrintFmtWrite(&mut dst));
+        (dst, result.is_err())
+    };
+    let expected = print(1);
+    assert!(expected.1);
+    assert!(expected.0.contains("nop"));
+    assert_eq!(print(4), expected);
+}
+
+#[test]
+fn print_to() {
+    let bytes = wat::parse_str("(module (func nop))").unwrap();
+    let mut dst = Vec::new();
+    wasmprinter::Config::new()
+        .print_to(&bytes, &mut dst)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(dst).unwrap(),
+        wasmprinter::print_bytes(&bytes).unwrap()
+    );
+}
+
+#[test]
+fn segment_names_and_symbolic_offsets_round_trip() {
+    let bytes = wat::parse_str(
+        r#"
+        (module
+            (import "env" "base" (global $base i32))
+            (memory $a 1)
+            (memory $b 1)
+            (table $t 4 funcref)
+            (func $f
+                (memory.init $b $passive (i32.const 0) (i32.const 0) (i32.const 1))
+                data.drop $passive
+                (table.init $t $funcs (i32.const 0) (i32.const 0) (i32.const 1))
+                elem.drop $funcs)
+            (data $active (memory $b) (offset (i32.add (global.get $base) (i32.const 16))) "abc")
+            (data $passive "xyz")
+            (elem $at (table $t) (offset (global.get $base)) func $f)
+            (elem $funcs func $f)
+        )
+        "#,
+    )
+    .unwrap();
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    for expected in [
+        "(data $active (;0;) (memory $b) (offset global.get $base i32.const 16 i32.add) \"abc\")",
+        "(data $passive (;1;) \"xyz\")",
+        "(elem $at (;0;) (table $t) (global.get $base) func $f)",
+        "(elem $funcs (;1;) func $f)",
+        "memory.init $b $passive",
+        "data.drop $passive",
+        "table.init $funcs",
+        "elem.drop $funcs",
+    ] {
+        assert!(text.contains(expected), "missing `{expected}` in:\n{text}");
+    }
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes);
+}
@@ -12,12 +12,16 @@ use std::path::Path;
 /// customize what the final binary looks like.
 ///
 /// Methods such as [`Module::encode`], [`Wat::encode`], and
-/// [`Component::encode`] will use the default options.
+/// [`Component::encode`] will use the default options, which encode all
+/// integers with the minimal number of bytes.
 #[derive(Default)]
 pub struct EncodeOptions<'a> {
     #[cfg(feature = "dwarf")]
     dwarf_info: Option<(&'a Path, &'a str, GenerateDwarf)>,
 
+    /// Bitmask of the `LebSite`s configured with `pad_lebs`.
+    padded_lebs: u8,
+
     _marker: marker::PhantomData<&'a str>,
 }
 
@@ -42,6 +46,51 @@ pub enum GenerateDwarf {
     Full,
 }
 
+/// Sites in function bodies whose LEB128-encoded immediates can be padded to
+/// a fixed width with [`EncodeOptions::pad_lebs`].
+///
+/// Padded immediates take the same number of bytes whatever their value, so
+/// tools which patch the binary later on, such as linkers, JITs, and
+/// hot-reloading runtimes, can rewrite them in place. Unsigned 32-bit and
+/// signed 32-bit immediates take 5 bytes and signed 64-bit immediates take 10
+/// bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LebSite {
+    /// The function index of `call`, `return_call`, and `ref.func`.
+    FuncIndex,
+    /// The type index of `call_indirect` and `return_call_indirect`.
+    TypeIndex,
+    /// The table index of `call_indirect` and `return_call_indirect`.
+    ///
+    /// Note that padding a table index of 0 requires the reference types
+    /// proposal, before which it was encoded as a single zero byte.
+    TableIndex,
+    /// The global index of `global.get` and `global.set`.
+    GlobalIndex,
+    /// The offset of memory loads and stores, which takes 10 bytes if it
+    /// doesn't fit in 32 bits.
+    MemoryOffset,
+    /// The value of `i32.const` and `i64.const`.
+    Const,
+}
+
+impl LebSite {
+    /// All sites which can be padded.
+    pub const ALL: [LebSite; 6] = [
+        LebSite::FuncIndex,
+        LebSite::TypeIndex,
+        LebSite::TableIndex,
+        LebSite::GlobalIndex,
+        LebSite::MemoryOffset,
+        LebSite::Const,
+    ];
+
+    fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
 impl<'a> EncodeOptions<'a> {
     /// Creates a new set of default encoding options.
     pub fn new() -> EncodeOptions<'a> {
@@ -60,6 +109,21 @@ impl<'a> EncodeOptions<'a> {
         self
     }
 
+    /// Pads the immediates at `site` in function bodies to a fixed width
+    /// rather than encoding them minimally.
+    ///
+    /// This may be called multiple times to pad the immediates at multiple
+    /// sites. Note that modules written with the `(module binary ...)` form are
+    /// emitted as-is.
+    pub fn pad_lebs(&mut self, site: LebSite) -> &mut Self {
+        self.padded_lebs |= site.mask();
+        self
+    }
+
+    fn pads(&self, site: LebSite) -> bool {
+        self.padded_lebs & site.mask() != 0
+    }
+
     /// Encodes the given [`Module`] with these options.
     ///
     /// For more information see [`Module::encode`].
@@ -138,6 +202,7 @@ pub(crate) fn encode(
     let mut e = Encoder {
         wasm: wasm_encoder::Module::new(),
         customs: &customs,
+        opts,
     };
 
     e.custom_sections(BeforeFirst);
@@ -207,6 +272,7 @@ pub(crate) fn encode(
 struct Encoder<'a> {
     wasm: wasm_encoder::Module,
     customs: &'a [&'a Custom<'a>],
+    opts: &'a EncodeOptions<'a>,
 }
 
 impl Encoder<'_> {
@@ -259,7 +325,7 @@ impl Encoder<'_> {
             let mut code_section = wasm_encoder::CodeSection::new();
 
             for func in list.iter() {
-                let hints = func.encode(&mut code_section, dwarf.as_deref_mut());
+                let hints = func.encode(&mut code_section, self.opts, dwarf.as_deref_mut());
                 if !hints.is_empty() {
                     branch_hints.function_hints(func_index, hints.into_iter());
                 }
@@ -699,6 +765,7 @@ impl Func<'_> {
     fn encode(
         &self,
         section: &mut wasm_encoder::CodeSection,
+        opts: &EncodeOptions,
         mut dwarf: Option<&mut dwarf::Dwarf>,
     ) -> Vec<wasm_encoder::BranchHint> {
         assert!(self.exports.names.is_empty());
@@ -720,7 +787,7 @@ impl Func<'_> {
         // encodes its length first then the body.
         let mut func =
             wasm_encoder::Function::new_with_locals_types(locals.iter().map(|t| t.ty.into()));
-        let branch_hints = expr.encode(&mut func, dwarf.as_deref_mut());
+        let branch_hints = expr.encode(&mut func, opts, dwarf.as_deref_mut());
         let func_size = func.byte_len();
         section.function(&func);
 
@@ -740,6 +807,7 @@ impl Expression<'_> {
     fn encode(
         &self,
         func: &mut wasm_encoder::Function,
+        opts: &EncodeOptions,
         mut dwarf: Option<&mut dwarf::Dwarf>,
     ) -> Vec<wasm_encoder::BranchHint> {
         let mut hints = Vec::with_capacity(self.branch_hints.len());
@@ -766,7 +834,11 @@ impl Expression<'_> {
             }
 
             // Finally emit the instruction and move to the next.
-            instr.encode(&mut tmp);
+            if opts.padded_lebs == 0 {
+                instr.encode(&mut tmp);
+            } else {
+                instr.encode_padded(opts, &mut tmp);
+            }
         }
         func.raw(tmp.iter().copied());
         func.instruction(&wasm_encoder::Instruction::End);
@@ -783,6 +855,91 @@ impl Expression<'_> {
     }
 }
 
+impl Instruction<'_> {
+    /// Encodes this instruction like [`Encode::encode`] except that the
+    /// immediates at sites which `opts` pads take a fixed width.
+    fn encode_padded(&self, opts: &EncodeOptions, e: &mut Vec<u8>) {
+        use Instruction::*;
+
+        match self {
+            Call(f) | ReturnCall(f) | RefFunc(f) if opts.pads(LebSite::FuncIndex) => {
+                self.encode_opcode(e);
+                encode_padded_u32(f.unwrap_u32(), e);
+            }
+            CallIndirect(c) | ReturnCallIndirect(c)
+                if opts.pads(LebSite::TypeIndex) || opts.pads(LebSite::TableIndex) =>
+            {
+                self.encode_opcode(e);
+                let ty = c.ty.unwrap_u32();
+                if opts.pads(LebSite::TypeIndex) {
+                    encode_padded_u32(ty, e);
+                } else {
+                    ty.encode(e);
+                }
+                if opts.pads(LebSite::TableIndex) {
+                    encode_padded_u32(c.table.unwrap_u32(), e);
+                } else {
+                    c.table.encode(e);
+                }
+            }
+            GlobalGet(g) | GlobalSet(g) if opts.pads(LebSite::GlobalIndex) => {
+                self.encode_opcode(e);
+                encode_padded_u32(g.unwrap_u32(), e);
+            }
+            I32Const(n) if opts.pads(LebSite::Const) => {
+                self.encode_opcode(e);
+                encode_padded_signed((*n).into(), 5, e);
+            }
+            I64Const(n) if opts.pads(LebSite::Const) => {
+                self.encode_opcode(e);
+                encode_padded_signed(*n, 10, e);
+            }
+            _ => match self.memarg_and_lane() {
+                Some((memarg, lane)) if opts.pads(LebSite::MemoryOffset) => {
+                    self.encode_opcode(e);
+                    match &memarg.memory {
+                        Index::Num(0, _) => memarg.align.trailing_zeros().encode(e),
+                        memory => {
+                            (memarg.align.trailing_zeros() | (1 << 6)).encode(e);
+                            memory.encode(e);
+                        }
+                    }
+                    let width = if memarg.offset > u64::from(u32::MAX) {
+                        10
+                    } else {
+                        5
+                    };
+                    encode_padded_unsigned(memarg.offset, width, e);
+                    e.extend(lane);
+                }
+                _ => self.encode(e),
+            },
+        }
+    }
+}
+
+fn encode_padded_u32(n: u32, e: &mut Vec<u8>) {
+    encode_padded_unsigned(n.into(), 5, e)
+}
+
+/// Encodes `n` as an unsigned LEB128 of exactly `width` bytes.
+fn encode_padded_unsigned(mut n: u64, width: usize, e: &mut Vec<u8>) {
+    for i in 0..width {
+        let more = if i + 1 < width { 0x80 } else { 0 };
+        e.push((n & 0x7f) as u8 | more);
+        n >>= 7;
+    }
+}
+
+/// Encodes `n` as a signed LEB128 of exactly `width` bytes.
+fn encode_padded_signed(mut n: i64, width: usize, e: &mut Vec<u8>) {
+    for i in 0..width {
+        let more = if i + 1 < width { 0x80 } else { 0 };
+        e.push((n & 0x7f) as u8 | more);
+        n >>= 7;
+    }
+}
+
 impl Encode for BlockType<'_> {
     fn encode(&self, e: &mut Vec<u8>) {
         // block types using an index are encoded as an sleb, not a uleb
@@ -470,6 +470,31 @@ macro_rules! instructions {
                     )*
                 }
             }
+
+            /// Encodes just the opcode of this instruction, without any of
+            /// its immediates.
+            pub(crate) fn encode_opcode(&self, v: &mut Vec<u8>) {
+                match self {
+                    $(
+                        Instruction::$name $((instructions!(@first _x $($arg)*)))? => {
+                            instructions!(@encode v $($binary)*);
+                        }
+                    )*
+                }
+            }
+
+            /// Returns the associated [`MemArg`], and lane of lane
+            /// instructions, if this instruction has one.
+            #[allow(unused_variables, non_snake_case)]
+            pub(crate) fn memarg_and_lane(&self) -> Option<(&MemArg<'a>, Option<u8>)> {
+                match self {
+                    $(
+                        Instruction::$name $((instructions!(@memarg_binding a $($arg)*)))? => {
+                            instructions!(@get_memarg_and_lane a $($($arg)*)?)
+                        }
+                    )*
+                }
+            }
         }
     );
 
@@ -496,6 +521,10 @@ macro_rules! instructions {
     (@get_memarg $name:ident LoadOrStoreLane<$amt:tt>) => (Some(&mut $name.memarg));
     (@get_memarg $($other:tt)*) => (None);
 
+    (@get_memarg_and_lane $name:ident MemArg<$amt:tt>) => (Some(($name, None)));
+    (@get_memarg_and_lane $name:ident LoadOrStoreLane<$amt:tt>) => (Some((&$name.memarg, Some($name.lane.lane))));
+    (@get_memarg_and_lane $($other:tt)*) => (None);
+
     (@memarg_binding $name:ident MemArg<$amt:tt>) => ($name);
     (@memarg_binding $name:ident LoadOrStoreLane<$amt:tt>) => ($name);
     (@memarg_binding $name:ident $other:ty) => (_);
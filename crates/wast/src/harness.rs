@@ -0,0 +1,1419 @@
+//! A driver for running `*.wast` scripts against a WebAssembly engine.
+//!
+//! Engines implement the [`Engine`] trait to instantiate modules and invoke
+//! their exports, and a [`Runner`] then executes each directive of a script
+//! against the engine. Results of invocations are compared with the expected
+//! values of `assert_return`, including the NaN patterns `nan:canonical` and
+//! `nan:arithmetic` as defined by the spec, and each directive which doesn't
+//! behave as expected is reported as a [`Failure`] rather than stopping the
+//! script.
+//!
+//! ```
+//! use wast::harness::{Engine, Runner, Val};
+//!
+//! /// An "engine" which only supports instantiating modules.
+//! struct Validator;
+//!
+//! impl Engine for Validator {
+//!     type Instance = ();
+//!     type Error = String;
+//!
+//!     fn instantiate(&mut self, wasm: &[u8]) -> Result<(), String> {
+//!         wasmparser::Validator::new()
+//!             .validate_all(wasm)
+//!             .map(drop)
+//!             .map_err(|e| e.to_string())
+//!     }
+//!
+//!     fn register(&mut self, _name: &str, _instance: &()) -> Result<(), String> {
+//!         Ok(())
+//!     }
+//!
+//!     fn invoke(&mut self, _: &(), _name: &str, _args: &[Val]) -> Result<Vec<Val>, String> {
+//!         Err("invoking functions is not supported".to_string())
+//!     }
+//!
+//!     fn get_global(&mut self, _: &(), _name: &str) -> Result<Val, String> {
+//!         Err("getting globals is not supported".to_string())
+//!     }
+//! }
+//!
+//! let report = Runner::new(Validator).run_str(r#"
+//!     (module (func (export "f")))
+//!     (assert_invalid (module (func (result i32))) "type mismatch")
+//!     (invoke "f")
+//! "#)?;
+//! assert_eq!(report.passed, 2);
+//! assert_eq!(report.failures.len(), 1);
+//! assert_eq!(report.failures[0].line, 4);
+//! # Ok::<(), wast::Error>(())
+//! ```
+//!
+//! Large suites can be split up with a [`Filter`], which selects a subset of
+//! the directives of a script, and a [`Shard`], which deterministically
+//! selects a subset of the scripts in a directory for one of several parallel
+//! jobs.
+//!
+//! A runner can also log every directive it runs, with its outcome and the
+//! values it expected and got, as a [`LogEntry`] which can be written as a
+//! line of JSON to compare the behavior of engines.
+
+use crate::core::{AbstractHeapType, HeapType, NanPattern, V128Pattern, WastArgCore, WastRetCore};
+use crate::parser::{self, ParseBuffer};
+use crate::token::{Index, F32, F64};
+use crate::{Error, QuoteWat, Wast, WastArg, WastDirective, WastExecute, WastInvoke, WastRet, Wat};
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::io;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// The text of the `spectest` module which spec tests import from.
+///
+/// This is a core module with the same exports as the `spectest` host module
+/// of the spec's reference interpreter, so engines can instantiate it to
+/// provide the imports of spec tests. A module can't print anything itself, so
+/// the `print` functions do nothing.
+pub const SPECTEST: &str = r#"(module
+  (global (export "global_i32") i32 (i32.const 666))
+  (global (export "global_i64") i64 (i64.const 666))
+  (global (export "global_f32") f32 (f32.const 666.6))
+  (global (export "global_f64") f64 (f64.const 666.6))
+  (table (export "table") 10 20 funcref)
+  (memory (export "memory") 1 2)
+  (func (export "print"))
+  (func (export "print_i32") (param i32))
+  (func (export "print_i64") (param i64))
+  (func (export "print_f32") (param f32))
+  (func (export "print_f64") (param f64))
+  (func (export "print_i32_f32") (param i32 f32))
+  (func (export "print_f64_f64") (param f64 f64))
+)
+"#;
+
+/// Returns the binary encoding of the [`SPECTEST`] module.
+pub fn spectest() -> Vec<u8> {
+    crate::encode_module(SPECTEST).expect("the spectest module should encode")
+}
+
+/// The interface to a WebAssembly engine used by a [`Runner`].
+pub trait Engine {
+    /// An instance of a module.
+    type Instance;
+
+    /// The error returned when any operation fails, such as a module failing
+    /// to validate or a function trapping.
+    type Error: fmt::Display;
+
+    /// Compiles and instantiates the module `wasm`, resolving its imports
+    /// from the instances previously registered with
+    /// [`Engine::register`].
+    ///
+    /// This fails if `wasm` is malformed or invalid, if it can't be linked, or
+    /// if its start function traps.
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Self::Instance, Self::Error>;
+
+    /// Makes the exports of `instance` available for importing under the
+    /// module name `name` in later instantiations.
+    fn register(&mut self, name: &str, instance: &Self::Instance) -> Result<(), Self::Error>;
+
+    /// Invokes the exported function `name` of `instance` with `args`,
+    /// returning its results.
+    ///
+    /// This fails if the function traps, throws an exception, or exhausts
+    /// resources such as the stack.
+    fn invoke(
+        &mut self,
+        instance: &Self::Instance,
+        name: &str,
+        args: &[Val],
+    ) -> Result<Vec<Val>, Self::Error>;
+
+    /// Returns the value of the exported global `name` of `instance`.
+    fn get_global(&mut self, instance: &Self::Instance, name: &str) -> Result<Val, Self::Error>;
+
+    /// Compiles the module `wasm` without instantiating it, which is used for
+    /// `assert_malformed` and `assert_invalid`.
+    ///
+    /// The default implementation instantiates the module and discards the
+    /// instance, which engines should override if instantiation has side
+    /// effects or may fail for other reasons.
+    fn compile(&mut self, wasm: &[u8]) -> Result<(), Self::Error> {
+        self.instantiate(wasm).map(drop)
+    }
+
+    /// Returns whether `error` is the error expected by an assertion with the
+    /// message `expected`.
+    ///
+    /// Error messages differ between engines, so the default implementation
+    /// only checks whether the message of `error` contains `expected`, and
+    /// engines may override this to map their messages to those of the spec.
+    fn error_matches(&self, error: &Self::Error, expected: &str) -> bool {
+        error.to_string().contains(expected)
+    }
+}
+
+/// A value passed to or returned from an [`Engine`].
+///
+/// Floats are represented by their bits so that NaN payloads are preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Val {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    V128(u128),
+    /// A null reference, along with its heap type when known.
+    ///
+    /// Arguments of scripts specify the heap type of null references if it's
+    /// abstract, but engines may return `None` for results.
+    Null(Option<AbstractHeapType>),
+    /// A non-null reference.
+    Ref(Ref),
+}
+
+/// A non-null reference held by a [`Val`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ref {
+    /// A function reference.
+    Func,
+    /// An external reference, which holds the host value `n` if it was
+    /// created with `ref.extern n`.
+    Extern(Option<u32>),
+    /// An internal reference to the host value `n`, created with
+    /// `ref.host n`.
+    Host(u32),
+    /// An `i31` reference, which may be `shared`.
+    I31 {
+        /// Whether this reference is `shared`.
+        shared: bool,
+    },
+    /// A reference to a struct.
+    Struct,
+    /// A reference to an array.
+    Array,
+    /// Any other kind of reference, such as an exception reference.
+    Other,
+}
+
+/// The outcome of running a script with a [`Runner`].
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The number of directives which ran as expected.
+    pub passed: usize,
+    /// The directives which didn't run as expected, in order.
+    pub failures: Vec<Failure>,
+    /// The number of directives which weren't selected by the [`Filter`] of
+    /// the runner, which only ran if they set up state for later directives.
+    pub skipped: usize,
+    /// How long each directive which ran took, in order, if enabled with
+    /// [`Runner::record_timings`].
+    pub timings: Vec<Timing>,
+    /// Every directive of the script, in order, if enabled with
+    /// [`Runner::record_log`].
+    pub log: Vec<LogEntry>,
+}
+
+/// A directive of a script which didn't run as expected.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// The 1-based line of the directive within the script.
+    pub line: usize,
+    /// The 1-based column of the directive within the script.
+    pub col: usize,
+    /// The kind of directive, such as `assert_return`.
+    pub directive: &'static str,
+    /// A description of what went wrong.
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.col, self.directive, self.message
+        )
+    }
+}
+
+/// The time taken to run a directive of a script.
+#[derive(Debug, Clone)]
+pub struct Timing {
+    /// The 1-based line of the directive within the script.
+    pub line: usize,
+    /// The kind of directive, such as `assert_return`.
+    pub directive: &'static str,
+    /// How long the directive took to run.
+    pub duration: Duration,
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {:?}", self.line, self.directive, self.duration)
+    }
+}
+
+/// A directive of a script, as logged by a [`Runner`] with
+/// [`Runner::record_log`].
+///
+/// Values are rendered in the syntax of the text format, such as
+/// `(i32.const 1)`, and expected values use the same syntax as scripts for
+/// patterns such as `(f32.const nan:canonical)`. Floats which aren't patterns
+/// are rendered such that equal bits always render the same, so expected and
+/// actual values can be compared as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// The 0-based index of the directive within the script.
+    pub index: usize,
+    /// The 1-based line of the directive within the script.
+    pub line: usize,
+    /// The 1-based column of the directive within the script.
+    pub col: usize,
+    /// The kind of directive, such as `assert_return`.
+    pub directive: &'static str,
+    /// Whether the directive ran as expected.
+    pub outcome: Outcome,
+    /// What the directive expected, for assertions.
+    pub expected: Option<Observation>,
+    /// What the engine produced, for directives which invoke functions, get
+    /// globals, or check modules, unless the directive failed before the
+    /// engine was used.
+    pub actual: Option<Observation>,
+}
+
+/// Whether a directive ran as expected, as recorded in a [`LogEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The directive ran as expected.
+    Passed,
+    /// The directive didn't run as expected, with a description of what went
+    /// wrong.
+    Failed(String),
+    /// The directive wasn't selected by the [`Filter`] of the runner, so
+    /// nothing it expects was checked.
+    Skipped,
+}
+
+/// Values or an error, as expected by a directive or produced by an engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Observation {
+    /// The values of results or of a global, where modules which are
+    /// compiled or instantiated successfully have no values.
+    Values(Vec<String>),
+    /// The message of an error.
+    Error(String),
+}
+
+impl LogEntry {
+    /// Renders this entry as a single line of JSON.
+    ///
+    /// The object has the fields `index`, `line`, `col`, `directive`, and
+    /// `outcome`, which is one of `"passed"`, `"failed"`, or `"skipped"`.
+    /// Failed entries also have a `message`. The `expected` and `actual` fields are present
+    /// for entries which have them, as an object with either a `values` array
+    /// of strings or an `error` string.
+    ///
+    /// ```
+    /// use wast::harness::{LogEntry, Observation, Outcome};
+    ///
+    /// let entry = LogEntry {
+    ///     index: 3,
+    ///     line: 7,
+    ///     col: 1,
+    ///     directive: "assert_return",
+    ///     outcome: Outcome::Passed,
+    ///     expected: Some(Observation::Values(vec!["(i32.const 1)".to_string()])),
+    ///     actual: Some(Observation::Values(vec!["(i32.const 1)".to_string()])),
+    /// };
+    /// assert_eq!(
+    ///     entry.to_json(),
+    ///     concat!(
+    ///         r#"{"index":3,"line":7,"col":1,"directive":"assert_return","outcome":"passed","#,
+    ///         r#""expected":{"values":["(i32.const 1)"]},"actual":{"values":["(i32.const 1)"]}}"#,
+    ///     ),
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            "{{\"index\":{},\"line\":{},\"col\":{},\"directive\":",
+            self.index, self.line, self.col
+        );
+        json_string(&mut json, self.directive);
+        match &self.outcome {
+            Outcome::Passed => json.push_str(",\"outcome\":\"passed\""),
+            Outcome::Failed(message) => {
+                json.push_str(",\"outcome\":\"failed\",\"message\":");
+                json_string(&mut json, message);
+            }
+            Outcome::Skipped => json.push_str(",\"outcome\":\"skipped\""),
+        }
+        for (name, observation) in [("expected", &self.expected), ("actual", &self.actual)] {
+            let observation = match observation {
+                Some(observation) => observation,
+                None => continue,
+            };
+            write!(json, ",\"{name}\":").unwrap();
+            match observation {
+                Observation::Values(values) => {
+                    json.push_str("{\"values\":[");
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            json.push(',');
+                        }
+                        json_string(&mut json, value);
+                    }
+                    json.push_str("]}");
+                }
+                Observation::Error(message) => {
+                    json.push_str("{\"error\":");
+                    json_string(&mut json, message);
+                    json.push('}');
+                }
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// Appends `s` to `json` as a JSON string.
+fn json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// Selects which directives of a script to run.
+///
+/// A directive is selected if it matches every criterion which has been set,
+/// and the default filter selects everything. Directives which set up state
+/// for later directives, as determined by [`sets_up_state`], still run when
+/// they aren't selected so that the selected directives see the same modules
+/// as they would in a full run, but their outcome isn't reported.
+///
+/// ```
+/// use wast::harness::Filter;
+///
+/// let mut filter = Filter::new();
+/// filter.lines(10..=20).name("i32.*");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    lines: Option<RangeInclusive<usize>>,
+    directives: Option<Range<usize>>,
+    names: Vec<String>,
+    kinds: Vec<String>,
+}
+
+impl Filter {
+    /// Creates a filter which selects every directive.
+    pub fn new() -> Filter {
+        Filter::default()
+    }
+
+    /// Only selects directives which start within the 1-based range of
+    /// `lines`.
+    pub fn lines(&mut self, lines: RangeInclusive<usize>) -> &mut Self {
+        self.lines = Some(lines);
+        self
+    }
+
+    /// Only selects directives whose 0-based index within the script is in
+    /// `directives`.
+    pub fn directives(&mut self, directives: Range<usize>) -> &mut Self {
+        self.directives = Some(directives);
+        self
+    }
+
+    /// Only selects directives with a name matching `pattern`, or any of the
+    /// patterns if this is called more than once.
+    ///
+    /// The names of a directive are the names of the exports it invokes or
+    /// gets, the names it registers instances under, and the identifiers of
+    /// the modules, instances, and threads it refers to, without their `$`.
+    /// In `pattern`, `*` matches any sequence of characters and `?` matches
+    /// any single character.
+    pub fn name(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.names.push(pattern.into());
+        self
+    }
+
+    /// Only selects directives of the kind `kind`, such as `assert_invalid`,
+    /// or of any of the kinds if this is called more than once.
+    ///
+    /// Kinds are named as in [`Failure::directive`].
+    pub fn kind(&mut self, kind: impl Into<String>) -> &mut Self {
+        self.kinds.push(kind.into());
+        self
+    }
+
+    /// Returns whether the directive at `index` within its script, starting
+    /// on the 1-based `line`, is selected by this filter.
+    pub fn selects(&self, index: usize, line: usize, directive: &WastDirective<'_>) -> bool {
+        if let Some(lines) = &self.lines {
+            if !lines.contains(&line) {
+                return false;
+            }
+        }
+        if let Some(directives) = &self.directives {
+            if !directives.contains(&index) {
+                return false;
+            }
+        }
+        if !self.kinds.is_empty() {
+            let kind = directive_kind(directive);
+            if !self.kinds.iter().any(|k| k == kind) {
+                return false;
+            }
+        }
+        if !self.names.is_empty() {
+            let names = directive_names(directive);
+            let matches = self
+                .names
+                .iter()
+                .any(|pattern| names.iter().any(|name| glob_matches(pattern, name)));
+            if !matches {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Returns whether `directive` sets up state which later directives may
+/// depend on, which is the case for modules, module definitions and
+/// instances, and registrations.
+pub fn sets_up_state(directive: &WastDirective<'_>) -> bool {
+    use WastDirective::*;
+
+    matches!(
+        directive,
+        Module(_) | ModuleDefinition(_) | ModuleInstance { .. } | Register { .. }
+    )
+}
+
+/// Returns the kind of `directive`, such as `assert_return`.
+fn directive_kind(directive: &WastDirective<'_>) -> &'static str {
+    use WastDirective::*;
+
+    match directive {
+        Module(_) => "module",
+        ModuleDefinition(_) => "module definition",
+        ModuleInstance { .. } => "module instance",
+        Register { .. } => "register",
+        Invoke(_) => "invoke",
+        AssertMalformed { .. } => "assert_malformed",
+        AssertInvalid { .. } => "assert_invalid",
+        AssertUnlinkable { .. } => "assert_unlinkable",
+        AssertTrap { .. } => "assert_trap",
+        AssertExhaustion { .. } => "assert_exhaustion",
+        AssertException { .. } => "assert_exception",
+        AssertSuspension { .. } => "assert_suspension",
+        AssertReturn { .. } => "assert_return",
+        Thread(_) => "thread",
+        Wait { .. } => "wait",
+    }
+}
+
+fn directive_names<'a>(directive: &WastDirective<'a>) -> Vec<&'a str> {
+    use WastDirective::*;
+
+    fn invoke<'a>(names: &mut Vec<&'a str>, invoke: &WastInvoke<'a>) {
+        names.push(invoke.name);
+        names.extend(invoke.module.map(|id| id.name()));
+    }
+
+    fn execute<'a>(names: &mut Vec<&'a str>, exec: &WastExecute<'a>) {
+        match exec {
+            WastExecute::Invoke(i) => invoke(names, i),
+            WastExecute::Get { module, global, .. } => {
+                names.push(global);
+                names.extend(module.map(|id| id.name()));
+            }
+            WastExecute::Wat(wat) => names.extend(wat_name(wat)),
+        }
+    }
+
+    fn wat_name<'a>(wat: &Wat<'a>) -> Option<&'a str> {
+        match wat {
+            Wat::Module(m) => m.id.map(|id| id.name()),
+            Wat::Component(c) => c.id.map(|id| id.name()),
+        }
+    }
+
+    let mut names = Vec::new();
+    match directive {
+        Module(module)
+        | ModuleDefinition(module)
+        | AssertMalformed { module, .. }
+        | AssertInvalid { module, .. } => names.extend(module.name().map(|id| id.name())),
+        ModuleInstance {
+            instance, module, ..
+        } => names.extend(instance.iter().chain(module).map(|id| id.name())),
+        Register { name, module, .. } => {
+            names.push(name);
+            names.extend(module.map(|id| id.name()));
+        }
+        AssertUnlinkable { module, .. } => names.extend(wat_name(module)),
+        Invoke(i) | AssertExhaustion { call: i, .. } => invoke(&mut names, i),
+        AssertTrap { exec, .. }
+        | AssertReturn { exec, .. }
+        | AssertException { exec, .. }
+        | AssertSuspension { exec, .. } => execute(&mut names, exec),
+        Thread(thread) => names.push(thread.name.name()),
+        Wait { thread, .. } => names.push(thread.name()),
+    }
+    names
+}
+
+/// Returns whether `name` matches the glob `pattern`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` in the pattern and the position in the
+    // name it has matched up to, to backtrack to when a match fails.
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// One of several parallel jobs which a suite of scripts is split between.
+///
+/// Scripts are sorted by path and dealt out to the shards in turn, so every
+/// job sees the same split regardless of the order in which the file system
+/// lists them.
+///
+/// ```
+/// use wast::harness::Shard;
+///
+/// let shard = "2/3".parse::<Shard>().unwrap();
+/// let selected = shard.select(["d.wast", "a.wast", "c.wast", "b.wast", "e.wast"]);
+/// assert_eq!(selected, ["b.wast", "e.wast"]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    index: usize,
+    count: usize,
+}
+
+impl Shard {
+    /// Creates the shard at the 0-based `index` of `count` shards.
+    ///
+    /// Returns `None` if `index` isn't less than `count`.
+    pub fn new(index: usize, count: usize) -> Option<Shard> {
+        if index < count {
+            Some(Shard { index, count })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the paths of `paths` which belong to this shard, in sorted
+    /// order.
+    pub fn select<P: AsRef<Path>>(&self, paths: impl IntoIterator<Item = P>) -> Vec<P> {
+        let mut paths = paths.into_iter().collect::<Vec<_>>();
+        paths.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        paths
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % self.count == self.index)
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    /// Returns the `*.wast` files within `dir`, recursively, which belong to
+    /// this shard.
+    pub fn wast_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fn find(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+            for entry in dir.read_dir()? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    find(&path, files)?;
+                } else if path.extension().and_then(|s| s.to_str()) == Some("wast") {
+                    files.push(path);
+                }
+            }
+            Ok(())
+        }
+
+        let mut files = Vec::new();
+        find(dir, &mut files)?;
+        Ok(self.select(files))
+    }
+}
+
+/// Parses a shard written as `I/N`, the 1-based shard `I` of `N` shards.
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shard, String> {
+        let err = || format!("invalid shard `{s}`, expected `I/N` with 1 <= I <= N");
+        let (index, count) = s.split_once('/').ok_or_else(err)?;
+        let index = index.trim().parse::<usize>().map_err(|_| err())?;
+        let count = count.trim().parse::<usize>().map_err(|_| err())?;
+        index
+            .checked_sub(1)
+            .and_then(|index| Shard::new(index, count))
+            .ok_or_else(err)
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index + 1, self.count)
+    }
+}
+
+/// Runs the directives of `*.wast` scripts against an [`Engine`].
+///
+/// Instances and registrations persist across scripts run with the same
+/// runner.
+pub struct Runner<E: Engine> {
+    engine: E,
+    instances: Vec<E::Instance>,
+    current: Option<usize>,
+    named: HashMap<String, usize>,
+    /// Modules defined with `(module definition ...)`, by name.
+    definitions: HashMap<String, Vec<u8>>,
+    filter: Filter,
+    record_timings: bool,
+    record_log: bool,
+}
+
+impl<E: Engine> Runner<E> {
+    /// Creates a new runner for `engine`.
+    pub fn new(engine: E) -> Runner<E> {
+        Runner {
+            engine,
+            instances: Vec::new(),
+            current: None,
+            named: HashMap::new(),
+            definitions: HashMap::new(),
+            filter: Filter::default(),
+            record_timings: false,
+            record_log: false,
+        }
+    }
+
+    /// Only runs the directives of scripts which `filter` selects, along
+    /// with those which set up state for later directives.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// Records how long each directive takes to run in [`Report::timings`].
+    pub fn record_timings(&mut self, record: bool) {
+        self.record_timings = record;
+    }
+
+    /// Records every directive in [`Report::log`], with what it expected and
+    /// what the engine produced for those which the filter selects.
+    pub fn record_log(&mut self, record: bool) {
+        self.record_log = record;
+    }
+
+    /// Returns the engine of this runner.
+    pub fn engine(&self) -> &E {
+        &self.engine
+    }
+
+    /// Returns the engine of this runner.
+    pub fn engine_mut(&mut self) -> &mut E {
+        &mut self.engine
+    }
+
+    /// Instantiates the [`SPECTEST`] module and registers it under the name
+    /// `spectest`, which spec tests import from.
+    pub fn register_spectest(&mut self) -> Result<(), E::Error> {
+        let instance = self.engine.instantiate(&spectest())?;
+        self.engine.register("spectest", &instance)?;
+        self.instances.push(instance);
+        Ok(())
+    }
+
+    /// Parses and runs the script `source`.
+    ///
+    /// Only fails if `source` can't be parsed, while directives which don't
+    /// run as expected are reported in the returned [`Report`].
+    pub fn run_str(&mut self, source: &str) -> Result<Report, Error> {
+        let with_text = |mut e: Error| {
+            e.set_text(source);
+            e
+        };
+        let buf = ParseBuffer::new(source).map_err(with_text)?;
+        let wast = parser::parse::<Wast<'_>>(&buf).map_err(with_text)?;
+        Ok(self.run(source, wast))
+    }
+
+    /// Runs the directives of `wast`, which was parsed from `source`.
+    pub fn run(&mut self, source: &str, wast: Wast<'_>) -> Report {
+        let mut report = Report::default();
+        let lines = LineOffsets::new(source);
+        for (index, directive) in wast.directives.into_iter().enumerate() {
+            let span = directive.span();
+            let line = lines.line(span.offset());
+            let name = directive_kind(&directive);
+            let col = lines.col(span.offset(), line);
+            if !self.filter.selects(index, line, &directive) {
+                if sets_up_state(&directive) {
+                    // Failures surface in the selected directives which
+                    // depend on this one, if any.
+                    let _ = self.directive(directive, &mut Observations::default());
+                }
+                report.skipped += 1;
+                if self.record_log {
+                    report.log.push(LogEntry {
+                        index,
+                        line,
+                        col,
+                        directive: name,
+                        outcome: Outcome::Skipped,
+                        expected: None,
+                        actual: None,
+                    });
+                }
+                continue;
+            }
+            let start = self.record_timings.then(Instant::now);
+            let mut observations = Observations {
+                enabled: self.record_log,
+                ..Observations::default()
+            };
+            let result = self.directive(directive, &mut observations);
+            if let Some(start) = start {
+                report.timings.push(Timing {
+                    line,
+                    directive: name,
+                    duration: start.elapsed(),
+                });
+            }
+            if self.record_log {
+                report.log.push(LogEntry {
+                    index,
+                    line,
+                    col,
+                    directive: name,
+                    outcome: match &result {
+                        Ok(()) => Outcome::Passed,
+                        Err(message) => Outcome::Failed(message.clone()),
+                    },
+                    expected: observations.expected,
+                    actual: observations.actual,
+                });
+            }
+            match result {
+                Ok(()) => report.passed += 1,
+                Err(message) => report.failures.push(Failure {
+                    line,
+                    col,
+                    directive: name,
+                    message,
+                }),
+            }
+        }
+        report
+    }
+
+    fn directive(
+        &mut self,
+        directive: WastDirective<'_>,
+        log: &mut Observations,
+    ) -> Result<(), String> {
+        use WastDirective::*;
+
+        match directive {
+            Module(mut module) => self.module(&mut module),
+            ModuleDefinition(mut module) => self.definition(&mut module),
+            ModuleInstance {
+                instance, module, ..
+            } => self.module_instance(instance.map(|id| id.name()), module.map(|id| id.name())),
+            Register { name, module, .. } => self
+                .instance(module.map(|id| id.name()))
+                .and_then(|i| check(self.engine.register(name, &self.instances[i]))),
+            Invoke(invoke) => self.invoke_raw(&invoke).and_then(|result| {
+                log.actual(|| observe(&result, render_vals));
+                check(result).map(drop)
+            }),
+            AssertMalformed {
+                mut module,
+                message,
+                ..
+            } => {
+                log.expected(|| Observation::Error(message.to_string()));
+                // Modules which can't be encoded, such as `module quote` with
+                // malformed text, are rejected as expected.
+                match module.encode() {
+                    Ok(wasm) => {
+                        let result = self.engine.compile(&wasm);
+                        log.actual(|| observe(&result, |_| Vec::new()));
+                        self.expect_error(result, "module was not malformed", message)
+                    }
+                    Err(e) => {
+                        log.actual(|| Observation::Error(e.message()));
+                        Ok(())
+                    }
+                }
+            }
+            AssertInvalid {
+                mut module,
+                message,
+                ..
+            } => {
+                log.expected(|| Observation::Error(message.to_string()));
+                encode(&mut module).and_then(|wasm| {
+                    let result = self.engine.compile(&wasm);
+                    log.actual(|| observe(&result, |_| Vec::new()));
+                    self.expect_error(result, "module was not invalid", message)
+                })
+            }
+            AssertUnlinkable {
+                mut module,
+                message,
+                ..
+            } => {
+                log.expected(|| Observation::Error(message.to_string()));
+                match module.encode() {
+                    Ok(wasm) => {
+                        let result = self.engine.instantiate(&wasm).map(drop);
+                        log.actual(|| observe(&result, |_| Vec::new()));
+                        self.expect_error(result, "module was linked successfully", message)
+                    }
+                    Err(e) => Err(format!("failed to encode module: {}", e.message())),
+                }
+            }
+            AssertTrap { exec, message, .. } => {
+                log.expected(|| Observation::Error(message.to_string()));
+                self.execute(exec).and_then(|result| {
+                    log.actual(|| observe(&result, render_vals));
+                    self.expect_error(result, "no trap occurred", message)
+                })
+            }
+            AssertExhaustion { call, message, .. } => {
+                log.expected(|| Observation::Error(message.to_string()));
+                self.invoke_raw(&call).and_then(|result| {
+                    log.actual(|| observe(&result, render_vals));
+                    self.expect_error(result, "no resources were exhausted", message)
+                })
+            }
+            AssertException { exec, .. } => match self.execute(exec) {
+                Ok(result) => {
+                    log.actual(|| observe(&result, render_vals));
+                    match result {
+                        Ok(_) => Err("expected an exception to be thrown".to_string()),
+                        Err(_) => Ok(()),
+                    }
+                }
+                Err(e) => Err(e),
+            },
+            AssertSuspension { exec, message, .. } => {
+                log.expected(|| Observation::Error(message.to_string()));
+                self.execute(exec).and_then(|result| {
+                    log.actual(|| observe(&result, render_vals));
+                    self.expect_error(result, "no suspension occurred", message)
+                })
+            }
+            AssertReturn { exec, results, .. } => {
+                log.expected(|| Observation::Values(results.iter().map(render_ret).collect()));
+                self.execute(exec).and_then(|result| {
+                    log.actual(|| observe(&result, render_vals));
+                    match_results(&check(result)?, &results)
+                })
+            }
+            Thread(_) | Wait { .. } => Err("threads are not supported".to_string()),
+        }
+    }
+
+    fn module(&mut self, module: &mut QuoteWat<'_>) -> Result<(), String> {
+        let name = module.name().map(|id| id.name());
+        let wasm = encode(module)?;
+        let instance = check(self.engine.instantiate(&wasm))?;
+        self.push_instance(name, instance);
+        Ok(())
+    }
+
+    fn definition(&mut self, module: &mut QuoteWat<'_>) -> Result<(), String> {
+        let name = module.name().map(|id| id.name());
+        let wasm = encode(module)?;
+        check(self.engine.compile(&wasm))?;
+        if let Some(name) = name {
+            self.definitions.insert(name.to_string(), wasm);
+        }
+        Ok(())
+    }
+
+    fn module_instance(&mut self, name: Option<&str>, module: Option<&str>) -> Result<(), String> {
+        let wasm = match module.and_then(|module| self.definitions.get(module)) {
+            Some(wasm) => wasm,
+            None => return Err("unknown module definition".to_string()),
+        };
+        let instance = check(self.engine.instantiate(wasm))?;
+        self.push_instance(name, instance);
+        Ok(())
+    }
+
+    fn push_instance(&mut self, name: Option<&str>, instance: E::Instance) {
+        let index = self.instances.len();
+        self.instances.push(instance);
+        self.current = Some(index);
+        if let Some(name) = name {
+            self.named.insert(name.to_string(), index);
+        }
+    }
+
+    /// Returns the index of the instance named `name`, or the most recent
+    /// instance if `None`.
+    fn instance(&self, name: Option<&str>) -> Result<usize, String> {
+        match name {
+            Some(name) => self
+                .named
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("unknown module `${name}`")),
+            None => self
+                .current
+                .ok_or_else(|| "no module has been instantiated".to_string()),
+        }
+    }
+
+    /// Runs `exec`, where the outer error is a failure of the script itself,
+    /// such as referring to an unknown module, and the inner error is the
+    /// result of the engine.
+    fn execute(&mut self, exec: WastExecute<'_>) -> Result<Result<Vec<Val>, E::Error>, String> {
+        match exec {
+            WastExecute::Invoke(invoke) => self.invoke_raw(&invoke),
+            WastExecute::Wat(mut module) => {
+                let wasm = module
+                    .encode()
+                    .map_err(|e| format!("failed to encode module: {}", e.message()))?;
+                Ok(self.engine.instantiate(&wasm).map(|_| Vec::new()))
+            }
+            WastExecute::Get { module, global, .. } => {
+                let i = self.instance(module.map(|id| id.name()))?;
+                Ok(self
+                    .engine
+                    .get_global(&self.instances[i], global)
+                    .map(|val| vec![val]))
+            }
+        }
+    }
+
+    fn invoke_raw(
+        &mut self,
+        invoke: &WastInvoke<'_>,
+    ) -> Result<Result<Vec<Val>, E::Error>, String> {
+        let i = self.instance(invoke.module.map(|id| id.name()))?;
+        let args = invoke.args.iter().map(arg).collect::<Result<Vec<_>, _>>()?;
+        Ok(self.engine.invoke(&self.instances[i], invoke.name, &args))
+    }
+
+    /// Checks that `result` is an error matching `expected`, where
+    /// `unexpected` describes a success.
+    fn expect_error<T>(
+        &self,
+        result: Result<T, E::Error>,
+        unexpected: &str,
+        expected: &str,
+    ) -> Result<(), String> {
+        match result {
+            Ok(_) => Err(format!("{unexpected}, expected `{expected}`")),
+            Err(e) if self.engine.error_matches(&e, expected) => Ok(()),
+            Err(e) => Err(format!("expected error `{expected}`, got `{e}`")),
+        }
+    }
+}
+
+/// The offsets at which each line of a script starts, to find the line of a
+/// directive without re-counting the lines before it every time.
+struct LineOffsets(Vec<usize>);
+
+impl LineOffsets {
+    fn new(source: &str) -> LineOffsets {
+        let starts = source.match_indices('\n').map(|(i, _)| i + 1);
+        LineOffsets(std::iter::once(0).chain(starts).collect())
+    }
+
+    /// Returns the 1-based line containing the byte at `offset`.
+    fn line(&self, offset: usize) -> usize {
+        match self.0.binary_search(&offset) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        }
+    }
+
+    /// Returns the 1-based column of the byte at `offset` on the 1-based
+    /// `line` containing it.
+    fn col(&self, offset: usize, line: usize) -> usize {
+        offset - self.0[line - 1] + 1
+    }
+}
+
+/// The expected and actual values of a directive, which are only rendered if
+/// logging is enabled.
+#[derive(Default)]
+struct Observations {
+    enabled: bool,
+    expected: Option<Observation>,
+    actual: Option<Observation>,
+}
+
+impl Observations {
+    fn expected(&mut self, observation: impl FnOnce() -> Observation) {
+        if self.enabled {
+            self.expected = Some(observation());
+        }
+    }
+
+    fn actual(&mut self, observation: impl FnOnce() -> Observation) {
+        if self.enabled {
+            self.actual = Some(observation());
+        }
+    }
+}
+
+/// Returns what an engine produced with `result`, where `values` renders the
+/// values of a success.
+fn observe<T>(
+    result: &Result<T, impl fmt::Display>,
+    values: impl FnOnce(&T) -> Vec<String>,
+) -> Observation {
+    match result {
+        Ok(t) => Observation::Values(values(t)),
+        Err(e) => Observation::Error(e.to_string()),
+    }
+}
+
+fn check<T>(result: Result<T, impl fmt::Display>) -> Result<T, String> {
+    result.map_err(|e| e.to_string())
+}
+
+fn encode(module: &mut QuoteWat<'_>) -> Result<Vec<u8>, String> {
+    module
+        .encode()
+        .map_err(|e| format!("failed to encode module: {}", e.message()))
+}
+
+fn arg(arg: &WastArg<'_>) -> Result<Val, String> {
+    let arg = match arg {
+        WastArg::Core(arg) => arg,
+        WastArg::Component(_) => return Err(component_values_unsupported()),
+    };
+    Ok(match arg {
+        WastArgCore::I32(i) => Val::I32(*i),
+        WastArgCore::I64(i) => Val::I64(*i),
+        WastArgCore::F32(f) => Val::F32(f.bits),
+        WastArgCore::F64(f) => Val::F64(f.bits),
+        WastArgCore::V128(v) => Val::V128(u128::from_le_bytes(v.to_le_bytes())),
+        WastArgCore::RefNull(HeapType::Abstract { ty, .. }) => Val::Null(Some(*ty)),
+        WastArgCore::RefNull(HeapType::Concrete(_)) => Val::Null(None),
+        WastArgCore::RefExtern(n) => Val::Ref(Ref::Extern(Some(*n))),
+        WastArgCore::RefHost(n) => Val::Ref(Ref::Host(*n)),
+    })
+}
+
+fn component_values_unsupported() -> String {
+    "component model values are not supported".to_string()
+}
+
+fn match_results(actual: &[Val], expected: &[WastRet<'_>]) -> Result<(), String> {
+    if actual.len() != expected.len() {
+        return Err(format!(
+            "expected {} results, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    for (i, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+        let expected = match expected {
+            WastRet::Core(ret) => ret,
+            WastRet::Component(_) => return Err(component_values_unsupported()),
+        };
+        if !match_val(actual, expected) {
+            return Err(format!(
+                "result {i} mismatch: expected {expected:?}, got {actual:?}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn render_vals(vals: &Vec<Val>) -> Vec<String> {
+    vals.iter().map(render_val).collect()
+}
+
+/// Renders `val` in the syntax of the text format.
+fn render_val(val: &Val) -> String {
+    match val {
+        Val::I32(i) => format!("(i32.const {i})"),
+        Val::I64(i) => format!("(i64.const {i})"),
+        Val::F32(bits) => format!("(f32.const {})", render_f32(*bits)),
+        Val::F64(bits) => format!("(f64.const {})", render_f64(*bits)),
+        Val::V128(v) => {
+            let lanes = v
+                .to_le_bytes()
+                .chunks(4)
+                .map(|b| i32::from_le_bytes(b.try_into().unwrap()).to_string())
+                .collect::<Vec<_>>();
+            format!("(v128.const i32x4 {})", lanes.join(" "))
+        }
+        Val::Null(Some(ty)) => format!("(ref.null {})", heap_type_name(*ty)),
+        Val::Null(None) => "(ref.null)".to_string(),
+        Val::Ref(Ref::Extern(Some(n))) => format!("(ref.extern {n})"),
+        Val::Ref(Ref::Host(n)) => format!("(ref.host {n})"),
+        Val::Ref(r) => match r {
+            Ref::Func => "(ref.func)",
+            Ref::Extern(_) => "(ref.extern)",
+            Ref::I31 { shared: false } => "(ref.i31)",
+            Ref::I31 { shared: true } => "(ref.i31_shared)",
+            Ref::Struct => "(ref.struct)",
+            Ref::Array => "(ref.array)",
+            Ref::Host(_) | Ref::Other => "(ref)",
+        }
+        .to_string(),
+    }
+}
+
+/// Renders the expected result `ret` in the syntax of scripts.
+fn render_ret(ret: &WastRet<'_>) -> String {
+    match ret {
+        WastRet::Core(ret) => render_ret_core(ret),
+        WastRet::Component(_) => "(component value)".to_string(),
+    }
+}
+
+fn render_ret_core(ret: &WastRetCore<'_>) -> String {
+    fn index(index: &Index<'_>) -> String {
+        match index {
+            Index::Num(n, _) => n.to_string(),
+            Index::Id(id) => format!("${}", id.name()),
+        }
+    }
+
+    fn lanes<T>(lanes: &[T], render: impl Fn(&T) -> String) -> String {
+        lanes.iter().map(render).collect::<Vec<_>>().join(" ")
+    }
+
+    match ret {
+        WastRetCore::I32(i) => format!("(i32.const {i})"),
+        WastRetCore::I64(i) => format!("(i64.const {i})"),
+        WastRetCore::F32(f) => format!(
+            "(f32.const {})",
+            render_nan_pattern(f, |f| render_f32(f.bits))
+        ),
+        WastRetCore::F64(f) => format!(
+            "(f64.const {})",
+            render_nan_pattern(f, |f| render_f64(f.bits))
+        ),
+        WastRetCore::V128(pattern) => {
+            let (shape, lanes) = match pattern {
+                V128Pattern::I8x16(l) => ("i8x16", lanes(l, |i| i.to_string())),
+                V128Pattern::I16x8(l) => ("i16x8", lanes(l, |i| i.to_string())),
+                V128Pattern::I32x4(l) => ("i32x4", lanes(l, |i| i.to_string())),
+                V128Pattern::I64x2(l) => ("i64x2", lanes(l, |i| i.to_string())),
+                V128Pattern::F32x4(l) => (
+                    "f32x4",
+                    lanes(l, |f| render_nan_pattern(f, |f| render_f32(f.bits))),
+                ),
+                V128Pattern::F64x2(l) => (
+                    "f64x2",
+                    lanes(l, |f| render_nan_pattern(f, |f| render_f64(f.bits))),
+                ),
+            };
+            format!("(v128.const {shape} {lanes})")
+        }
+        WastRetCore::RefNull(None) => "(ref.null)".to_string(),
+        WastRetCore::RefNull(Some(HeapType::Abstract { shared, ty })) => {
+            if *shared {
+                format!("(ref.null (shared {}))", heap_type_name(*ty))
+            } else {
+                format!("(ref.null {})", heap_type_name(*ty))
+            }
+        }
+        WastRetCore::RefNull(Some(HeapType::Concrete(i))) => format!("(ref.null {})", index(i)),
+        WastRetCore::RefExtern(None) => "(ref.extern)".to_string(),
+        WastRetCore::RefExtern(Some(n)) => format!("(ref.extern {n})"),
+        WastRetCore::RefHost(n) => format!("(ref.host {n})"),
+        WastRetCore::RefFunc(None) => "(ref.func)".to_string(),
+        WastRetCore::RefFunc(Some(i)) => format!("(ref.func {})", index(i)),
+        WastRetCore::RefAny => "(ref.any)".to_string(),
+        WastRetCore::RefEq => "(ref.eq)".to_string(),
+        WastRetCore::RefArray => "(ref.array)".to_string(),
+        WastRetCore::RefStruct => "(ref.struct)".to_string(),
+        WastRetCore::RefI31 => "(ref.i31)".to_string(),
+        WastRetCore::RefI31Shared => "(ref.i31_shared)".to_string(),
+        WastRetCore::Either(cases) => format!("(either {})", lanes(cases, render_ret_core)),
+    }
+}
+
+fn render_nan_pattern<T>(pattern: &NanPattern<T>, render: impl Fn(&T) -> String) -> String {
+    match pattern {
+        NanPattern::CanonicalNan => "nan:canonical".to_string(),
+        NanPattern::ArithmeticNan => "nan:arithmetic".to_string(),
+        NanPattern::Value(value) => render(value),
+    }
+}
+
+/// Renders the `f32` with bits `bits`, with the payload of NaNs.
+fn render_f32(bits: u32) -> String {
+    let f = f32::from_bits(bits);
+    if f.is_nan() {
+        let sign = if bits >> 31 == 0 { "" } else { "-" };
+        format!("{sign}nan:0x{:x}", bits & 0x7f_ffff)
+    } else {
+        f.to_string()
+    }
+}
+
+/// Renders the `f64` with bits `bits`, with the payload of NaNs.
+fn render_f64(bits: u64) -> String {
+    let f = f64::from_bits(bits);
+    if f.is_nan() {
+        let sign = if bits >> 63 == 0 { "" } else { "-" };
+        format!("{sign}nan:0x{:x}", bits & 0xf_ffff_ffff_ffff)
+    } else {
+        f.to_string()
+    }
+}
+
+fn heap_type_name(ty: AbstractHeapType) -> &'static str {
+    match ty {
+        AbstractHeapType::Func => "func",
+        AbstractHeapType::Extern => "extern",
+        AbstractHeapType::Exn => "exn",
+        AbstractHeapType::Cont => "cont",
+        AbstractHeapType::Any => "any",
+        AbstractHeapType::Eq => "eq",
+        AbstractHeapType::Struct => "struct",
+        AbstractHeapType::Array => "array",
+        AbstractHeapType::I31 => "i31",
+        AbstractHeapType::NoFunc => "nofunc",
+        AbstractHeapType::NoExtern => "noextern",
+        AbstractHeapType::None => "none",
+        AbstractHeapType::NoExn => "noexn",
+        AbstractHeapType::NoCont => "nocont",
+    }
+}
+
+/// Returns whether `actual` matches the `expected` pattern.
+pub fn match_val(actual: &Val, expected: &WastRetCore<'_>) -> bool {
+    match (actual, expected) {
+        (Val::I32(a), WastRetCore::I32(b)) => a == b,
+        (Val::I64(a), WastRetCore::I64(b)) => a == b,
+        (Val::F32(a), WastRetCore::F32(b)) => match_f32(*a, b),
+        (Val::F64(a), WastRetCore::F64(b)) => match_f64(*a, b),
+        (Val::V128(a), WastRetCore::V128(b)) => match_v128(*a, b),
+        (Val::Null(_), WastRetCore::RefNull(_)) => true,
+        (Val::Ref(r), expected) => match_ref(r, expected),
+        (_, WastRetCore::Either(cases)) => cases.iter().any(|case| match_val(actual, case)),
+        _ => false,
+    }
+}
+
+fn match_ref(actual: &Ref, expected: &WastRetCore<'_>) -> bool {
+    match (actual, expected) {
+        (Ref::Extern(_), WastRetCore::RefExtern(None)) => true,
+        (Ref::Extern(a), WastRetCore::RefExtern(Some(b))) => *a == Some(*b),
+        (Ref::Host(a), WastRetCore::RefHost(b)) => a == b,
+        (Ref::Func, WastRetCore::RefFunc(_)) => true,
+        (
+            Ref::Host(_) | Ref::I31 { .. } | Ref::Struct | Ref::Array | Ref::Other,
+            WastRetCore::RefAny,
+        ) => true,
+        (Ref::I31 { .. } | Ref::Struct | Ref::Array, WastRetCore::RefEq) => true,
+        (Ref::Array, WastRetCore::RefArray) => true,
+        (Ref::Struct, WastRetCore::RefStruct) => true,
+        (Ref::I31 { shared: false }, WastRetCore::RefI31) => true,
+        (Ref::I31 { shared: true }, WastRetCore::RefI31Shared) => true,
+        (_, WastRetCore::Either(cases)) => cases.iter().any(|case| match_ref(actual, case)),
+        _ => false,
+    }
+}
+
+/// Returns whether the `f32` with bits `actual` matches `expected`.
+///
+/// A canonical NaN has only the most significant bit of its payload set,
+/// while an arithmetic NaN has at least that bit set, and both may have
+/// either sign.
+pub fn match_f32(actual: u32, expected: &NanPattern<F32>) -> bool {
+    const CANONICAL: u32 = 0x7fc0_0000;
+    match expected {
+        NanPattern::CanonicalNan => actual & 0x7fff_ffff == CANONICAL,
+        NanPattern::ArithmeticNan => actual & CANONICAL == CANONICAL,
+        NanPattern::Value(expected) => actual == expected.bits,
+    }
+}
+
+/// Returns whether the `f64` with bits `actual` matches `expected`.
+///
+/// See [`match_f32`] for how NaN patterns are matched.
+pub fn match_f64(actual: u64, expected: &NanPattern<F64>) -> bool {
+    const CANONICAL: u64 = 0x7ff8_0000_0000_0000;
+    match expected {
+        NanPattern::CanonicalNan => actual & 0x7fff_ffff_ffff_ffff == CANONICAL,
+        NanPattern::ArithmeticNan => actual & CANONICAL == CANONICAL,
+        NanPattern::Value(expected) => actual == expected.bits,
+    }
+}
+
+fn match_v128(actual: u128, expected: &V128Pattern) -> bool {
+    let bytes = actual.to_le_bytes();
+    match expected {
+        V128Pattern::I8x16(lanes) => lanes
+            .iter()
+            .zip(bytes)
+            .all(|(lane, byte)| *lane as u8 == byte),
+        V128Pattern::I16x8(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(2))
+            .all(|(lane, b)| lane.to_le_bytes() == b),
+        V128Pattern::I32x4(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(4))
+            .all(|(lane, b)| lane.to_le_bytes() == b),
+        V128Pattern::I64x2(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(8))
+            .all(|(lane, b)| lane.to_le_bytes() == b),
+        V128Pattern::F32x4(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(4))
+            .all(|(lane, b)| match_f32(u32::from_le_bytes(b.try_into().unwrap()), lane)),
+        V128Pattern::F64x2(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(8))
+            .all(|(lane, b)| match_f64(u64::from_le_bytes(b.try_into().unwrap()), lane)),
+    }
+}
@@ -360,6 +360,7 @@ mod gensym;
 #[cfg(feature = "wasm-module")]
 mod names;
 pub use self::error::*;
+pub use self::token::{parse_f32_literal, parse_f64_literal};
 
 #[cfg(feature = "wasm-module")]
 macro_rules! id {
@@ -368,8 +369,10 @@ macro_rules! id {
 
 #[cfg(feature = "wasm-module")]
 id! {
+    mod splice;
     mod wast;
     mod wat;
+    pub use self::splice::*;
     pub use self::wast::*;
     pub use self::wat::*;
 
@@ -378,6 +381,9 @@ id! {
 
     // Support for component model parsing
     pub mod component;
+
+    // Support for running `*.wast` scripts against an engine
+    pub mod harness;
 }
 
 /// Common keyword used to parse WebAssembly text files.
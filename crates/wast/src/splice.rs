@@ -0,0 +1,270 @@
+use crate::parser::{self, Parse, ParseBuffer, Parser, Result};
+use crate::QuoteWat;
+
+/// Encodes a single module or component in any of the forms allowed in a
+/// `*.wast` script to its binary representation.
+///
+/// This accepts `(module ...)` and `(component ...)` in the text format as
+/// well as the `(module binary ...)` and `(module quote ...)` forms, and
+/// their component equivalents, which otherwise are only evaluated as part of
+/// a [`Wast`](crate::Wast) script. Note that the bytes of a `binary` form are
+/// returned as-is, so they may not be a valid module or component.
+///
+/// ```
+/// # fn foo() -> Result<(), wast::Error> {
+/// let wasm = wast::encode_module(r#"(module binary "\00asm" "\01\00\00\00")"#)?;
+/// assert_eq!(wasm, b"\0asm\x01\0\0\0");
+///
+/// let wasm = wast::encode_module(r#"(module quote "(func" ")")"#)?;
+/// assert_eq!(wasm, wast::encode_module("(module (func))")?);
+/// # Ok(())
+/// # }
+/// # foo().unwrap();
+/// ```
+pub fn encode_module(source: &str) -> Result<Vec<u8>> {
+    let encode = || {
+        let buf = ParseBuffer::new(source)?;
+        parser::parse::<TopLevel<'_>>(&buf)?.0.encode()
+    };
+    encode().map_err(|mut e| {
+        e.set_text(source);
+        e
+    })
+}
+
+/// A module or component parsed as a top-level item rather than as a
+/// directive of a `*.wast` script.
+struct TopLevel<'a>(QuoteWat<'a>);
+
+impl<'a> Parse<'a> for TopLevel<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        parser.with_standard_annotations_registered(|parser| {
+            let wat = parser.parens(|parser| parser.parse::<QuoteWat<'a>>())?;
+            if let QuoteWat::Wat(wat) = &wat {
+                wat.validate(parser)?;
+            }
+            Ok(TopLevel(wat))
+        })
+    }
+}
+
+/// A helper to splice raw bytes into the binary encoding of a module or
+/// component written in the text format.
+///
+/// This is intended for programmatically writing tests of malformed modules,
+/// where most of a module is easiest to write in the text format but some part
+/// of it needs to be malformed in a way the text format can't express. The
+/// encoded module is split into its header and then a list of pieces which
+/// are each a section, and raw bytes may be inserted in between them or
+/// replace them.
+///
+/// Only the sections of the outermost module or component are split apart,
+/// and any bytes which can't be decoded as a section are kept together as a
+/// single piece.
+///
+/// ```
+/// use wast::Splicer;
+///
+/// # fn foo() -> Result<(), wast::Error> {
+/// let mut splicer = Splicer::new("(module (func) (func))")?;
+///
+/// // Replace the function section with one that has a bogus count.
+/// let index = splicer.find_section(3).unwrap();
+/// splicer.replace_section(index, &[0x05, 0x00, 0x00]);
+///
+/// // And append trailing garbage at the end.
+/// splicer.push_raw(&[0xff]);
+/// let wasm = splicer.finish();
+/// # Ok(())
+/// # }
+/// # foo().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Splicer {
+    header: Vec<u8>,
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone)]
+struct Piece {
+    /// The id of this section, or `None` if it's raw bytes.
+    id: Option<u8>,
+    bytes: Vec<u8>,
+}
+
+impl Splicer {
+    /// Encodes `source` with [`encode_module`] and splits it into its
+    /// sections.
+    pub fn new(source: &str) -> Result<Splicer> {
+        Ok(Splicer::from_binary(&encode_module(source)?))
+    }
+
+    /// Splits the already-encoded module or component `wasm` into its
+    /// sections.
+    pub fn from_binary(wasm: &[u8]) -> Splicer {
+        let (header, mut rest) = wasm.split_at(wasm.len().min(8));
+        let mut pieces = Vec::new();
+        while !rest.is_empty() {
+            match split_section(rest) {
+                Some((id, len)) => {
+                    pieces.push(Piece {
+                        id: Some(id),
+                        bytes: rest[..len].to_vec(),
+                    });
+                    rest = &rest[len..];
+                }
+                None => {
+                    pieces.push(Piece {
+                        id: None,
+                        bytes: rest.to_vec(),
+                    });
+                    break;
+                }
+            }
+        }
+        Splicer {
+            header: header.to_vec(),
+            pieces,
+        }
+    }
+
+    /// Returns the number of pieces, sections or raw bytes, after the header.
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Returns whether there are no pieces after the header.
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    /// Returns the index of the first section with the `id` specified.
+    pub fn find_section(&self, id: u8) -> Option<usize> {
+        self.pieces.iter().position(|p| p.id == Some(id))
+    }
+
+    /// Returns the section id of the piece at `index`, or `None` if it's raw
+    /// bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn section_id(&self, index: usize) -> Option<u8> {
+        self.pieces[index].id
+    }
+
+    /// Replaces the header, which is normally the magic number and version.
+    pub fn set_header(&mut self, header: &[u8]) -> &mut Self {
+        self.header = header.to_vec();
+        self
+    }
+
+    /// Inserts a section with the `id` and `contents` specified before the
+    /// piece at `index`, where the size of the section is encoded
+    /// automatically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than [`Splicer::len`].
+    pub fn insert_section(&mut self, index: usize, id: u8, contents: &[u8]) -> &mut Self {
+        self.pieces.insert(
+            index,
+            Piece {
+                id: Some(id),
+                bytes: section(id, contents),
+            },
+        );
+        self
+    }
+
+    /// Appends a section with the `id` and `contents` specified.
+    pub fn push_section(&mut self, id: u8, contents: &[u8]) -> &mut Self {
+        self.insert_section(self.pieces.len(), id, contents)
+    }
+
+    /// Inserts `bytes` verbatim before the piece at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than [`Splicer::len`].
+    pub fn insert_raw(&mut self, index: usize, bytes: &[u8]) -> &mut Self {
+        self.pieces.insert(
+            index,
+            Piece {
+                id: None,
+                bytes: bytes.to_vec(),
+            },
+        );
+        self
+    }
+
+    /// Appends `bytes` verbatim.
+    pub fn push_raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.insert_raw(self.pieces.len(), bytes)
+    }
+
+    /// Replaces the contents of the section at `index` with `contents`,
+    /// keeping its id and encoding the new size of the section.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds or refers to raw bytes.
+    pub fn replace_section(&mut self, index: usize, contents: &[u8]) -> &mut Self {
+        let piece = &mut self.pieces[index];
+        let id = piece.id.expect("cannot replace the contents of raw bytes");
+        piece.bytes = section(id, contents);
+        self
+    }
+
+    /// Replaces the piece at `index` with `bytes` verbatim.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn replace_raw(&mut self, index: usize, bytes: &[u8]) -> &mut Self {
+        self.pieces[index] = Piece {
+            id: None,
+            bytes: bytes.to_vec(),
+        };
+        self
+    }
+
+    /// Removes the piece at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> &mut Self {
+        self.pieces.remove(index);
+        self
+    }
+
+    /// Returns the spliced binary.
+    pub fn finish(&self) -> Vec<u8> {
+        let mut ret = self.header.clone();
+        for piece in self.pieces.iter() {
+            ret.extend_from_slice(&piece.bytes);
+        }
+        ret
+    }
+}
+
+/// Returns the id and total size of the section at the start of `bytes`, if
+/// it's entirely contained within `bytes`.
+fn split_section(bytes: &[u8]) -> Option<(u8, usize)> {
+    let (id, mut rest) = bytes.split_first()?;
+    let before = rest.len();
+    let len = leb128::read::unsigned(&mut rest).ok()?;
+    let len = usize::try_from(len).ok()?;
+    if len > rest.len() {
+        return None;
+    }
+    Some((*id, 1 + before - rest.len() + len))
+}
+
+fn section(id: u8, contents: &[u8]) -> Vec<u8> {
+    let mut ret = vec![id];
+    leb128::write::unsigned(&mut ret, contents.len() as u64).unwrap();
+    ret.extend_from_slice(contents);
+    ret
+}
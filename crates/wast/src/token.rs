@@ -4,7 +4,7 @@
 
 use crate::annotation;
 use crate::lexer::Float;
-use crate::parser::{Cursor, Parse, Parser, Peek, Result};
+use crate::parser::{self, Cursor, Parse, ParseBuffer, Parser, Peek, Result};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::str;
@@ -426,6 +426,13 @@ macro_rules! float {
             }
         }
 
+        impl $name {
+            /// Returns the floating-point number represented by these bits.
+            pub fn value(&self) -> $float {
+                $float::from_bits(self.bits)
+            }
+        }
+
         fn $parse(val: &Float<'_>) -> Option<$int> {
             // Compute a few well-known constants about the float representation
             // given the parameters to the macro here.
@@ -560,10 +567,20 @@ macro_rules! float {
                 }
             }
 
-            exponent = exponent.checked_add(match exponent_str {
-                Some(s) => s.parse::<i32>().ok()?,
-                None => 0,
-            })?;
+            let explicit_exponent = match exponent_str {
+                Some(s) => s.parse::<i32>().ok(),
+                None => Some(0),
+            };
+            exponent = match explicit_exponent.and_then(|e| exponent.checked_add(e)) {
+                Some(exponent) => exponent,
+                // An exponent too large to represent underflows to zero if
+                // it's negative, and otherwise overflows to infinity which is
+                // invalid.
+                None if exponent_str.as_ref().is_some_and(|s| s.starts_with('-')) => {
+                    return Some((negative as $int) << (width - 1));
+                }
+                None => return None,
+            };
             debug_assert!(significand != 0);
 
             let (encoded_exponent, encoded_significand, discarded_significand) =
@@ -647,6 +664,52 @@ float! {
     }
 }
 
+impl Float<'_> {
+    /// Returns the bits of the `f32` closest to this float, or `None` if it
+    /// overflows to infinity or is an invalid `NaN` payload.
+    ///
+    /// Values are rounded to the nearest `f32`, with ties to even, exactly
+    /// as required by the text format, including for hexadecimal floats with
+    /// more digits than fit in an `f32`.
+    pub fn f32_bits(&self) -> Option<u32> {
+        strtof(self)
+    }
+
+    /// Same as [`Float::f32_bits`], but for an `f64`.
+    pub fn f64_bits(&self) -> Option<u64> {
+        strtod(self)
+    }
+}
+
+/// Parses `s` as an `f32` literal of the text format, such as `1.5`,
+/// `-0x1.8p+3`, `1_000.0`, `inf`, or `nan:0x200000`.
+///
+/// Integer literals are accepted as well, as they are for `f32.const`, and
+/// surrounding whitespace and comments are ignored.
+///
+/// # Examples
+///
+/// ```
+/// let f = wast::parse_f32_literal("0x1.8p1").unwrap();
+/// assert_eq!(f.value(), 3.0);
+/// assert_eq!(f.bits, 0x40400000);
+///
+/// let nan = wast::parse_f32_literal("-nan:0x1").unwrap();
+/// assert_eq!(nan.bits, 0xff800001);
+///
+/// assert!(wast::parse_f32_literal("0x1p128").is_err());
+/// ```
+pub fn parse_f32_literal(s: &str) -> Result<F32> {
+    let buf = ParseBuffer::new(s)?;
+    parser::parse(&buf)
+}
+
+/// Same as [`parse_f32_literal`], but for an `f64` literal.
+pub fn parse_f64_literal(s: &str) -> Result<F64> {
+    let buf = ParseBuffer::new(s)?;
+    parser::parse(&buf)
+}
+
 fn to_hex(c: char) -> u8 {
     match c {
         'a'..='f' => c as u8 - b'a' + 10,
@@ -724,5 +787,34 @@ mod tests {
             super::strtof(&f!("1" . "00000100000000000" p "-50")),
             Some(0x26800000)
         );
+        assert_eq!(super::strtof(&f!("1" p "-99999999999")), Some(0));
+        assert_eq!(super::strtof(&f!("-1" p "-99999999999")), Some(1 << 31));
+        assert_eq!(super::strtod(&f!("1" p "-99999999999")), Some(0));
+        assert_eq!(super::strtof(&f!("1" p "99999999999")), None);
+    }
+
+    #[test]
+    fn parse_literals() {
+        let f32_bits = |s| super::parse_f32_literal(s).map(|f| f.bits).ok();
+        let f64_bits = |s| super::parse_f64_literal(s).map(|f| f.bits).ok();
+        assert_eq!(f32_bits("1_000.5"), Some(1000.5f32.to_bits()));
+        assert_eq!(f32_bits(" 0x1_0.8p-1_0 "), Some(0x3c840000));
+        assert_eq!(f32_bits("-0"), Some(1 << 31));
+        assert_eq!(f32_bits("0x1p-149"), Some(1));
+        assert_eq!(f32_bits("0x1p-150"), Some(0));
+        assert_eq!(f32_bits("0x1.8p-150"), Some(1));
+        assert_eq!(f32_bits("0x1.fffffe8p127"), Some(0x7f7fffff));
+        assert_eq!(f32_bits("0x1.ffffffp127"), None);
+        assert_eq!(f32_bits("inf"), Some(0x7f800000));
+        assert_eq!(f32_bits("nan:0x0"), None);
+        assert_eq!(f32_bits("1 2"), None);
+        assert_eq!(f32_bits("0x"), None);
+        assert_eq!(f64_bits("0x1p-1074"), Some(1));
+        assert_eq!(
+            f64_bits("0x1.fffffffffffff7ffp1023"),
+            Some(0x7fefffffffffffff)
+        );
+        assert_eq!(f64_bits("1e-400"), Some(0));
+        assert_eq!(f64_bits("1e400"), None);
     }
 }
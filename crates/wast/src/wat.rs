@@ -17,7 +17,7 @@ pub enum Wat<'a> {
 }
 
 impl Wat<'_> {
-    fn validate(&self, parser: Parser<'_>) -> Result<()> {
+    pub(crate) fn validate(&self, parser: Parser<'_>) -> Result<()> {
         match self {
             Wat::Module(m) => m.validate(parser),
             Wat::Component(c) => c.validate(parser),
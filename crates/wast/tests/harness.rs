@@ -0,0 +1,426 @@
+use wasmparser::{Operator, Parser, Payload};
+use wast::harness::{
+    Engine, Failure, Filter, LogEntry, Observation, Outcome, Ref, Runner, Shard, Val,
+};
+
+/// An engine which can only run functions made of constants, `local.get`,
+/// and `unreachable`.
+#[derive(Default)]
+struct ConstEngine {
+    registered: Vec<String>,
+}
+
+#[derive(Default)]
+struct Instance {
+    /// The operators of each exported function, by name.
+    funcs: Vec<(String, Vec<Operator<'static>>)>,
+    /// The value of each exported global, by name.
+    globals: Vec<(String, Val)>,
+}
+
+fn eval(ops: &[Operator<'_>], args: &[Val]) -> Result<Vec<Val>, String> {
+    let mut stack = Vec::new();
+    for op in ops {
+        stack.push(match op {
+            Operator::I32Const { value } => Val::I32(*value),
+            Operator::I64Const { value } => Val::I64(*value),
+            Operator::F32Const { value } => Val::F32(value.bits()),
+            Operator::F64Const { value } => Val::F64(value.bits()),
+            Operator::V128Const { value } => Val::V128(value.i128() as u128),
+            Operator::RefNull { .. } => Val::Null(None),
+            Operator::RefFunc { .. } => Val::Ref(Ref::Func),
+            Operator::LocalGet { local_index } => args[*local_index as usize],
+            Operator::Unreachable => return Err("unreachable executed".to_string()),
+            Operator::End => break,
+            op => return Err(format!("unsupported operator {op:?}")),
+        });
+    }
+    Ok(stack)
+}
+
+impl Engine for ConstEngine {
+    type Instance = Instance;
+    type Error = String;
+
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<Instance, String> {
+        wasmparser::Validator::new()
+            .validate_all(wasm)
+            .map_err(|e| e.to_string())?;
+        // Leak the module so that operators can be kept around.
+        let wasm: &'static [u8] = Vec::leak(wasm.to_vec());
+        let mut instance = Instance::default();
+        let mut exports = Vec::new();
+        let mut bodies = Vec::new();
+        let mut globals = Vec::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.unwrap() {
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        let import = import.unwrap();
+                        if !self.registered.iter().any(|name| name == import.module) {
+                            return Err(format!("unknown import `{}`", import.module));
+                        }
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        exports.push(export.unwrap());
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        let ops = global
+                            .unwrap()
+                            .init_expr
+                            .get_operators_reader()
+                            .into_iter()
+                            .collect::<Result<Vec<_>, _>>()
+                            .unwrap();
+                        globals.push(eval(&ops, &[])?[0]);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let ops = body
+                        .get_operators_reader()
+                        .unwrap()
+                        .into_iter()
+                        .collect::<Result<Vec<_>, _>>()
+                        .unwrap();
+                    bodies.push(ops);
+                }
+                _ => {}
+            }
+        }
+        for export in exports {
+            let index = export.index as usize;
+            match export.kind {
+                wasmparser::ExternalKind::Func => instance
+                    .funcs
+                    .push((export.name.to_string(), bodies[index].clone())),
+                wasmparser::ExternalKind::Global => instance
+                    .globals
+                    .push((export.name.to_string(), globals[index])),
+                _ => {}
+            }
+        }
+        Ok(instance)
+    }
+
+    fn register(&mut self, name: &str, _instance: &Instance) -> Result<(), String> {
+        self.registered.push(name.to_string());
+        Ok(())
+    }
+
+    fn invoke(
+        &mut self,
+        instance: &Instance,
+        name: &str,
+        args: &[Val],
+    ) -> Result<Vec<Val>, String> {
+        match instance.funcs.iter().find(|(n, _)| n == name) {
+            Some((_, ops)) => eval(ops, args),
+            None => Err(format!("unknown export `{name}`")),
+        }
+    }
+
+    fn get_global(&mut self, instance: &Instance, name: &str) -> Result<Val, String> {
+        match instance.globals.iter().find(|(n, _)| n == name) {
+            Some((_, val)) => Ok(*val),
+            None => Err(format!("unknown global `{name}`")),
+        }
+    }
+}
+
+fn run(script: &str) -> Vec<Failure> {
+    let mut runner = Runner::new(ConstEngine::default());
+    runner.run_str(script).unwrap().failures
+}
+
+#[test]
+fn passing_script() {
+    let failures = run(r#"
+        (module $m
+            (global (export "g") i64 (i64.const 7))
+            (func (export "id") (param i32) (result i32) local.get 0)
+            (func (export "nan") (result f32 f64) f32.const nan f64.const -nan:0xfffffffffffff)
+            (func (export "v") (result v128) v128.const f32x4 1 nan 2 -nan)
+            (func (export "null") (result funcref) ref.null func)
+            (func (export "trap") unreachable)
+        )
+        (assert_return (invoke "id" (i32.const 3)) (i32.const 3))
+        (assert_return (invoke $m "nan") (f32.const nan:canonical) (f64.const nan:arithmetic))
+        (assert_return (invoke "v") (v128.const f32x4 1 nan:canonical 2 nan:canonical))
+        (assert_return (invoke "null") (ref.null func))
+        (assert_return (get "g") (i64.const 7))
+        (assert_return (invoke "id" (i32.const 1)) (either (i32.const 0) (i32.const 1)))
+        (assert_trap (invoke "trap") "unreachable")
+        (assert_invalid (module (func (result i32))) "type mismatch")
+        (assert_malformed (module quote "(func") "unexpected end")
+
+        (register "m" $m)
+        (module (import "m" "g" (global i64)))
+        (assert_unlinkable (module (import "unknown" "g" (global i64))) "unknown import")
+    "#);
+    assert!(failures.is_empty(), "{failures:#?}");
+}
+
+#[test]
+fn spectest() {
+    let mut runner = Runner::new(ConstEngine::default());
+    runner.register_spectest().unwrap();
+    let report = runner
+        .run_str(
+            r#"
+            (module
+                (import "spectest" "print_i32" (func (param i32)))
+                (import "spectest" "global_i32" (global i32))
+                (import "spectest" "table" (table 10 funcref))
+                (import "spectest" "memory" (memory 1))
+            )
+            (module $s (import "spectest" "global_f64" (global f64)))
+            "#,
+        )
+        .unwrap();
+    assert!(report.failures.is_empty(), "{:#?}", report.failures);
+
+    let mut runner = Runner::new(ConstEngine::default());
+    let instance = runner
+        .engine_mut()
+        .instantiate(&wast::harness::spectest())
+        .unwrap();
+    assert_eq!(
+        instance.globals[0],
+        ("global_i32".to_string(), Val::I32(666))
+    );
+    assert_eq!(
+        instance.globals[3],
+        ("global_f64".to_string(), Val::F64(666.6f64.to_bits()))
+    );
+}
+
+#[test]
+fn failing_script() {
+    let failures = run(r#"
+        (module
+            (func (export "id") (param i32) (result i32) local.get 0)
+            (func (export "nan") (result f32) f32.const -nan:0x600000)
+        )
+        (assert_return (invoke "id" (i32.const 3)) (i32.const 4))
+        (assert_return (invoke "nan") (f32.const nan:canonical))
+        (assert_return (invoke "nan") (f32.const nan:arithmetic))
+        (assert_trap (invoke "id" (i32.const 0)) "unreachable")
+        (invoke $missing "id" (i32.const 0))
+        (assert_invalid (module) "type mismatch")
+    "#);
+    let lines = failures
+        .iter()
+        .map(|f| (f.line, f.directive))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            (6, "assert_return"),
+            (7, "assert_return"),
+            (9, "assert_trap"),
+            (10, "invoke"),
+            (11, "assert_invalid"),
+        ]
+    );
+    assert!(failures[0].message.contains("mismatch"), "{}", failures[0]);
+    assert!(
+        failures[3].message.contains("unknown module"),
+        "{}",
+        failures[3]
+    );
+}
+
+#[test]
+fn filtered_script() {
+    let script = r#"
+        (module $m
+            (func (export "one") (result i32) i32.const 1)
+            (func (export "two") (result i32) i32.const 2)
+        )
+        (assert_return (invoke "one") (i32.const 1))
+        (assert_return (invoke "two") (i32.const 0))
+        (assert_return (invoke $m "one") (i32.const 0))
+        (invoke "two")
+    "#;
+    let run = |filter: &Filter| {
+        let mut runner = Runner::new(ConstEngine::default());
+        runner.set_filter(filter.clone());
+        let report = runner.run_str(script).unwrap();
+        let lines = report.failures.iter().map(|f| f.line).collect::<Vec<_>>();
+        (report.passed, report.skipped, lines)
+    };
+
+    // Modules still run outside of the selected lines, but aren't reported.
+    assert_eq!(run(Filter::new().lines(7..=8)), (0, 3, vec![7, 8]));
+    assert_eq!(run(Filter::new().directives(3..5)), (1, 3, vec![8]));
+    assert_eq!(run(Filter::new().name("tw?")), (1, 3, vec![7]));
+    assert_eq!(run(Filter::new().name("m").name("o*")), (2, 2, vec![8]));
+    assert_eq!(
+        run(Filter::new().name("*").directives(1..3)),
+        (1, 3, vec![7])
+    );
+    assert_eq!(run(Filter::new().kind("invoke")), (1, 4, vec![]));
+    assert_eq!(run(Filter::new().kind("module")), (1, 4, vec![]));
+    assert_eq!(
+        run(Filter::new().kind("module").kind("assert_return")),
+        (2, 1, vec![7, 8])
+    );
+}
+
+#[test]
+fn timings() {
+    let mut runner = Runner::new(ConstEngine::default());
+    runner.record_timings(true);
+    let mut filter = Filter::new();
+    filter.name("f");
+    runner.set_filter(filter);
+    let report = runner
+        .run_str(
+            r#"
+            (module $f (func (export "f")) (func (export "g")))
+            (invoke "f")
+            (invoke "g")
+            "#,
+        )
+        .unwrap();
+    let timings = report
+        .timings
+        .iter()
+        .map(|t| (t.line, t.directive))
+        .collect::<Vec<_>>();
+    assert_eq!(timings, [(2, "module"), (3, "invoke")]);
+}
+
+#[test]
+fn log() {
+    let mut runner = Runner::new(ConstEngine::default());
+    runner.record_log(true);
+    let report = runner
+        .run_str(
+            r#"
+            (module
+                (func (export "id") (param i32) (result i32) local.get 0)
+                (func (export "nan") (result f32 f64) f32.const -nan:0x200000 f64.const 1.5)
+                (func (export "trap") unreachable)
+            )
+            (assert_return (invoke "id" (i32.const -3)) (i32.const -3))
+            (assert_return (invoke "nan") (f32.const nan:canonical) (f64.const 1.5))
+            (assert_trap (invoke "trap") "unreachable")
+            (assert_invalid (module (func (result i32))) "type mismatch")
+            (invoke "missing")
+            "#,
+        )
+        .unwrap();
+    assert_eq!(report.log.len(), 6);
+    assert_eq!(report.failures.len(), 2);
+
+    let values = |values: &[&str]| {
+        Some(Observation::Values(
+            values.iter().map(|v| v.to_string()).collect(),
+        ))
+    };
+    assert_eq!(
+        report.log[1],
+        LogEntry {
+            index: 1,
+            line: 7,
+            col: 14,
+            directive: "assert_return",
+            outcome: Outcome::Passed,
+            expected: values(&["(i32.const -3)"]),
+            actual: values(&["(i32.const -3)"]),
+        }
+    );
+    let entry = &report.log[2];
+    assert!(matches!(entry.outcome, Outcome::Failed(_)));
+    assert_eq!(
+        entry.expected,
+        values(&["(f32.const nan:canonical)", "(f64.const 1.5)"])
+    );
+    assert_eq!(
+        entry.actual,
+        values(&["(f32.const -nan:0x200000)", "(f64.const 1.5)"])
+    );
+    assert_eq!(
+        report.log[3].actual,
+        Some(Observation::Error("unreachable executed".to_string()))
+    );
+    assert!(matches!(report.log[4].actual, Some(Observation::Error(_))));
+    assert_eq!(
+        report.log[5].actual,
+        Some(Observation::Error("unknown export `missing`".to_string()))
+    );
+
+    assert_eq!(
+        report.log[0].to_json(),
+        r#"{"index":0,"line":2,"col":14,"directive":"module","outcome":"passed"}"#
+    );
+    assert_eq!(
+        report.log[3].to_json(),
+        concat!(
+            r#"{"index":3,"line":9,"col":14,"directive":"assert_trap","outcome":"passed","#,
+            r#""expected":{"error":"unreachable"},"actual":{"error":"unreachable executed"}}"#,
+        )
+    );
+    let json = report.log[5].to_json();
+    assert!(
+        json.contains(r#""outcome":"failed","message":"unknown export `missing`""#),
+        "{json}"
+    );
+}
+
+#[test]
+fn log_skipped() {
+    let mut runner = Runner::new(ConstEngine::default());
+    runner.record_log(true);
+    let mut filter = Filter::new();
+    filter.kind("invoke");
+    runner.set_filter(filter);
+    let report = runner
+        .run_str(
+            r#"
+            (module (func (export "f")))
+            (assert_return (invoke "f"))
+            (invoke "f")
+            "#,
+        )
+        .unwrap();
+    let outcomes = report
+        .log
+        .iter()
+        .map(|e| (e.directive, e.outcome.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        [
+            ("module", Outcome::Skipped),
+            ("assert_return", Outcome::Skipped),
+            ("invoke", Outcome::Passed),
+        ]
+    );
+    assert_eq!(
+        report.log[1].to_json(),
+        r#"{"index":1,"line":3,"col":14,"directive":"assert_return","outcome":"skipped"}"#
+    );
+}
+
+#[test]
+fn shards() {
+    let paths = ["c", "a", "e", "b", "d", "f", "g"];
+    let mut all = Vec::new();
+    for i in 1..=3 {
+        let shard = format!("{i}/3").parse::<Shard>().unwrap();
+        assert_eq!(shard.to_string(), format!("{i}/3"));
+        all.extend(shard.select(paths));
+    }
+    assert_eq!(all, ["a", "d", "g", "b", "e", "c", "f"]);
+
+    assert!("0/3".parse::<Shard>().is_err());
+    assert!("4/3".parse::<Shard>().is_err());
+    assert!("1".parse::<Shard>().is_err());
+    assert_eq!(Shard::new(0, 0), None);
+}
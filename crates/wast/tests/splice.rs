@@ -0,0 +1,79 @@
+use wasmparser::{Parser, Payload, Validator};
+
+#[test]
+fn encode_module_forms() -> anyhow::Result<()> {
+    let expected = wat::parse_str("(module (func (result i32) i32.const 1))")?;
+    assert_eq!(
+        wast::encode_module("(module (func (result i32) i32.const 1))")?,
+        expected
+    );
+    assert_eq!(
+        wast::encode_module(r#"(module quote "(func (result i32)" "i32.const 1)")"#)?,
+        expected
+    );
+
+    let mut binary = String::from("(module binary");
+    for byte in expected.iter() {
+        binary.push_str(&format!(r#" "\{byte:02x}""#));
+    }
+    binary.push(')');
+    assert_eq!(wast::encode_module(&binary)?, expected);
+
+    let component = wast::encode_module(r#"(component quote "(core module)")"#)?;
+    assert_eq!(component, wat::parse_str("(component (core module))")?);
+
+    // Errors in quoted text are reported.
+    let err = wast::encode_module(r#"(module quote "(func" "i32.bad)")"#).unwrap_err();
+    assert!(err.to_string().contains("unknown operator"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn splice_sections() -> anyhow::Result<()> {
+    let mut splicer = wast::Splicer::new("(module (func) (func))")?;
+    let ids = (0..splicer.len())
+        .map(|i| splicer.section_id(i))
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [Some(1), Some(3), Some(10)]);
+    Validator::new().validate_all(&splicer.finish())?;
+
+    // A custom section in between the type and function sections.
+    let index = splicer.find_section(3).unwrap();
+    splicer.insert_section(index, 0, b"\x01xdata");
+    let wasm = splicer.finish();
+    Validator::new().validate_all(&wasm)?;
+    let custom = Parser::new(0)
+        .parse_all(&wasm)
+        .find_map(|payload| match payload {
+            Ok(Payload::CustomSection(c)) => Some((c.name().to_string(), c.data().to_vec())),
+            _ => None,
+        });
+    assert_eq!(custom, Some(("x".to_string(), b"data".to_vec())));
+
+    // A function section declaring more functions than the code section.
+    let index = splicer.find_section(3).unwrap();
+    splicer.replace_section(index, &[0x03, 0x00, 0x00, 0x00]);
+    let err = match Validator::new().validate_all(&splicer.finish()) {
+        Ok(_) => panic!("expected a validation error"),
+        Err(e) => e,
+    };
+    assert!(err.message().contains("function and code section"), "{err}");
+
+    // Trailing bytes which aren't a section.
+    let mut splicer = wast::Splicer::new("(module)")?;
+    splicer.push_raw(&[0x01, 0x05]);
+    assert!(Validator::new().validate_all(&splicer.finish()).is_err());
+    Ok(())
+}
+
+#[test]
+fn splice_malformed_binary() -> anyhow::Result<()> {
+    let mut splicer = wast::Splicer::new(r#"(module binary "\00asm" "\01\00\00\00" "\01\ff")"#)?;
+    assert_eq!(splicer.len(), 1);
+    assert_eq!(splicer.section_id(0), None);
+    assert_eq!(splicer.finish(), b"\0asm\x01\0\0\0\x01\xff");
+
+    splicer.remove(0).set_header(b"\0asm\x0d\0\x01\0");
+    assert_eq!(splicer.finish(), b"\0asm\x0d\0\x01\0");
+    Ok(())
+}
@@ -78,6 +78,8 @@ use wast::parser::{self, ParseBuffer};
 
 #[doc(inline)]
 pub use wast::core::GenerateDwarf;
+#[doc(inline)]
+pub use wast::core::LebSite;
 
 /// Parses a file on disk as a [WebAssembly Text format][wat] file, or a binary
 /// WebAssembly file
@@ -199,6 +201,7 @@ pub fn parse_str(wat: impl AsRef<str>) -> Result<Vec<u8>> {
 pub struct Parser {
     #[cfg(feature = "dwarf")]
     generate_dwarf: Option<GenerateDwarf>,
+    padded_lebs: Vec<LebSite>,
     _private: (),
 }
 
@@ -222,6 +225,17 @@ impl Parser {
         self
     }
 
+    /// Indicates that the immediates at `site` in function bodies should be
+    /// padded to a fixed width instead of encoded with the minimal number of
+    /// bytes, so they can be patched in place later.
+    ///
+    /// Like DWARF, this only applies to textual-based modules which don't use
+    /// the `(module binary ...)` form. See [`LebSite`] for more information.
+    pub fn pad_lebs(&mut self, site: LebSite) -> &mut Self {
+        self.padded_lebs.push(site);
+        self
+    }
+
     /// Equivalent of [`parse_file`] but uses this parser's settings.
     pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
         self._parse_file(path.as_ref())
@@ -281,6 +295,9 @@ impl Parser {
         if let Some(style) = self.generate_dwarf {
             _opts.dwarf(path.unwrap_or("<input>.wat".as_ref()), wat, style);
         }
+        for site in self.padded_lebs.iter() {
+            _opts.pad_lebs(*site);
+        }
         _opts
             .encode_wat(&mut ast)
             .map_err(|e| Error::cvt(e, wat, path))
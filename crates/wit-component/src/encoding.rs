@@ -85,7 +85,7 @@ use wasmparser::Validator;
 use wit_parser::{
     abi::{AbiVariant, WasmSignature, WasmType},
     Function, FunctionKind, InterfaceId, LiveTypes, Resolve, Type, TypeDefKind, TypeId, TypeOwner,
-    WorldItem, WorldKey,
+    WorldId, WorldItem, WorldKey,
 };
 
 const INDIRECT_TABLE_NAME: &str = "$imports";
@@ -93,6 +93,9 @@ const INDIRECT_TABLE_NAME: &str = "$imports";
 mod wit;
 pub use wit::{encode, encode_world};
 
+mod cache;
+pub use cache::{ComponentCache, DirectoryCache};
+use cache::CacheKey;
 mod types;
 use types::{InstanceTypeEncoder, RootTypeEncoder, ValtypeEncoder};
 mod world;
@@ -128,7 +131,7 @@ bitflags::bitflags! {
 }
 
 impl RequiredOptions {
-    fn for_import(resolve: &Resolve, func: &Function) -> RequiredOptions {
+    pub(crate) fn for_import(resolve: &Resolve, func: &Function) -> RequiredOptions {
         let sig = resolve.wasm_signature(AbiVariant::GuestImport, func);
         let mut ret = RequiredOptions::empty();
         // Lift the params and lower the results for imports
@@ -146,7 +149,7 @@ impl RequiredOptions {
         ret
     }
 
-    fn for_export(resolve: &Resolve, func: &Function) -> RequiredOptions {
+    pub(crate) fn for_export(resolve: &Resolve, func: &Function) -> RequiredOptions {
         let sig = resolve.wasm_signature(AbiVariant::GuestExport, func);
         let mut ret = RequiredOptions::empty();
         // Lower the params and lift the results for exports
@@ -1854,6 +1857,95 @@ struct Adapter {
     library_info: Option<LibraryInfo>,
 }
 
+/// Configuration of the canonical ABI options used to lift and lower the
+/// functions of the main module of a component.
+///
+/// By default the string encoding of each function is the one recorded in the
+/// `component-type` custom sections of the module, and the module's exports
+/// are found by their conventional names: `cabi_realloc`,
+/// `cabi_import_realloc`, and `cabi_export_realloc` for realloc functions,
+/// `cabi_post_*` for post-return functions, and the module's only exported
+/// memory. Each of these can be overridden here, for example for toolchains
+/// which can't emit exports with these names.
+///
+/// Configured realloc functions and memories must be exported by the module,
+/// otherwise encoding fails.
+///
+/// Options don't apply to adapters, which always use the default conventions.
+#[derive(Debug, Clone, Default)]
+pub struct CanonicalOptions {
+    pub(crate) import_encoding: Option<StringEncoding>,
+    pub(crate) export_encoding: Option<StringEncoding>,
+    pub(crate) realloc: Option<String>,
+    pub(crate) import_realloc: Option<String>,
+    pub(crate) export_realloc: Option<String>,
+    pub(crate) post_return_prefix: Option<String>,
+    pub(crate) memory: Option<String>,
+}
+
+impl CanonicalOptions {
+    /// Use `encoding` for the strings of all imported and exported
+    /// functions.
+    ///
+    /// For example [`StringEncoding::CompactUTF16`] represents strings as
+    /// either latin1 or UTF-16, as JavaScript engines do.
+    pub fn string_encoding(self, encoding: StringEncoding) -> Self {
+        self.import_string_encoding(encoding)
+            .export_string_encoding(encoding)
+    }
+
+    /// Use `encoding` for the strings of all imported functions, which are
+    /// lowered into the module.
+    pub fn import_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.import_encoding = Some(encoding);
+        self
+    }
+
+    /// Use `encoding` for the strings of all exported functions, which are
+    /// lifted from the module.
+    pub fn export_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.export_encoding = Some(encoding);
+        self
+    }
+
+    /// Use the export `name` as the realloc function instead of
+    /// `cabi_realloc`.
+    pub fn realloc(mut self, name: &str) -> Self {
+        self.realloc = Some(name.to_string());
+        self
+    }
+
+    /// Use the export `name` as the realloc function for lowering imported
+    /// functions instead of `cabi_import_realloc`.
+    pub fn import_realloc(mut self, name: &str) -> Self {
+        self.import_realloc = Some(name.to_string());
+        self
+    }
+
+    /// Use the export `name` as the realloc function for lifting exported
+    /// functions instead of `cabi_export_realloc`.
+    pub fn export_realloc(mut self, name: &str) -> Self {
+        self.export_realloc = Some(name.to_string());
+        self
+    }
+
+    /// Find the post-return function of exported functions by prefixing
+    /// their names with `prefix` instead of `cabi_post_`.
+    pub fn post_return_prefix(mut self, prefix: &str) -> Self {
+        self.post_return_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Use the exported memory `name`.
+    ///
+    /// Other memories exported by the module are ignored, so this allows
+    /// modules exporting multiple memories to be encoded.
+    pub fn memory(mut self, name: &str) -> Self {
+        self.memory = Some(name.to_string());
+        self
+    }
+}
+
 /// An encoder of components based on `wit` interface definitions.
 #[derive(Default)]
 pub struct ComponentEncoder {
@@ -1865,6 +1957,9 @@ pub struct ComponentEncoder {
     import_name_map: HashMap<String, String>,
     realloc_via_memory_grow: bool,
     merge_imports_based_on_semver: Option<bool>,
+    canonical_options: CanonicalOptions,
+    cache: Option<Box<dyn ComponentCache>>,
+    cache_key: CacheKey,
 }
 
 impl ComponentEncoder {
@@ -1874,6 +1969,7 @@ impl ComponentEncoder {
     /// It will also add any producers information inside the component type information to the
     /// core module.
     pub fn module(mut self, module: &[u8]) -> Result<Self> {
+        self.cache_key.add_module(module)?;
         let (wasm, metadata) = self.decode(module)?;
         let exports = self
             .merge_metadata(metadata)
@@ -1938,6 +2034,32 @@ impl ComponentEncoder {
         self.library_or_adapter(name, bytes, None)
     }
 
+    /// Same as [`ComponentEncoder::adapter`], except that the WIT interface of
+    /// the adapter is described by `world` instead of by `component-type`
+    /// custom sections within `bytes`.
+    ///
+    /// This can be used to supply arbitrary adapter modules, such as custom
+    /// host-API shims, which weren't produced by bindings generators. The
+    /// `name` provided is the core wasm import module of the main module which
+    /// the adapter satisfies, and the imports of `world` are the new
+    /// component-level imports the adapter introduces. Exports of `world` are
+    /// exported from the final component as implemented by the adapter.
+    ///
+    /// Any `component-type` sections already present in `bytes` are merged
+    /// with `world`.
+    pub fn adapter_with_world(
+        self,
+        name: &str,
+        bytes: &[u8],
+        resolve: &Resolve,
+        world: WorldId,
+    ) -> Result<Self> {
+        let mut bytes = bytes.to_vec();
+        crate::embed_component_metadata(&mut bytes, resolve, world, StringEncoding::UTF8)
+            .with_context(|| format!("failed to embed WIT metadata for adapter `{name}`"))?;
+        self.library_or_adapter(name, &bytes, None)
+    }
+
     /// Specifies a shared-everything library to link into the component.
     ///
     /// Unlike adapters, libraries _may_ have data and/or element segments, but
@@ -1960,6 +2082,11 @@ impl ComponentEncoder {
         bytes: &[u8],
         library_info: Option<LibraryInfo>,
     ) -> Result<Self> {
+        self.cache_key.add(name.as_bytes());
+        self.cache_key.add(bytes);
+        if library_info.is_some() {
+            self.cache_key.set_uncachable();
+        }
         let (wasm, mut metadata) = self.decode(bytes)?;
         // Merge the adapter's document into our own document to have one large
         // document, and then afterwards merge worlds as well.
@@ -2017,6 +2144,15 @@ impl ComponentEncoder {
         self
     }
 
+    /// Sets the canonical ABI options used to lift and lower the functions of
+    /// the main module.
+    ///
+    /// See [`CanonicalOptions`] for the defaults.
+    pub fn canonical_options(mut self, options: CanonicalOptions) -> Self {
+        self.canonical_options = options;
+        self
+    }
+
     /// The instance import name map to use.
     ///
     /// This is used to rename instance imports in the final component.
@@ -2032,18 +2168,78 @@ impl ComponentEncoder {
         self
     }
 
+    /// Reuses components encoded previously which differ only in the code or
+    /// data of their main module, storing new encodings in `cache`.
+    ///
+    /// The encoding of a component doesn't depend on the code or data of the
+    /// main module, which is instead embedded verbatim, so when all other
+    /// inputs to this encoder are unchanged the new main module is spliced into
+    /// the cached component instead of encoding it from scratch. Cache keys are
+    /// digests of the remaining sections of the main module, the adapters, and
+    /// the options of this encoder.
+    ///
+    /// Components which link libraries added with [`ComponentEncoder::library`]
+    /// are never cached.
+    pub fn cache(mut self, cache: impl ComponentCache + 'static) -> Self {
+        self.cache = Some(Box::new(cache));
+        self
+    }
+
     /// Encode the component and return the bytes.
     pub fn encode(&mut self) -> Result<Vec<u8>> {
         if self.module.is_empty() {
             bail!("a module is required when encoding a component");
         }
 
+        let cache_key = match &self.cache {
+            Some(_) => {
+                let mut import_names = self.import_name_map.iter().collect::<Vec<_>>();
+                import_names.sort();
+                let options = format!(
+                    "{} {:?} {} {:?} {import_names:?}",
+                    env!("CARGO_PKG_VERSION"),
+                    self.merge_imports_based_on_semver,
+                    self.realloc_via_memory_grow,
+                    self.canonical_options,
+                );
+                self.cache_key.finish(options.as_bytes())
+            }
+            None => None,
+        };
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            let spliced = cache
+                .get(key)?
+                .and_then(|cached| cache::splice_main_module(&cached, &self.module));
+            if let Some(bytes) = spliced {
+                if self.validate {
+                    Validator::new()
+                        .validate_all(&bytes)
+                        .context("failed to validate component output")?;
+                }
+                return Ok(bytes);
+            }
+        }
+
         if self.merge_imports_based_on_semver.unwrap_or(true) {
             self.metadata
                 .resolve
                 .merge_world_imports_based_on_semver(self.metadata.world)?;
         }
 
+        let metadata = &mut self.metadata.metadata;
+        if let Some(encoding) = self.canonical_options.import_encoding {
+            metadata
+                .import_encodings
+                .values_mut()
+                .for_each(|e| *e = encoding);
+        }
+        if let Some(encoding) = self.canonical_options.export_encoding {
+            metadata
+                .export_encodings
+                .values_mut()
+                .for_each(|e| *e = encoding);
+        }
+
         let world = ComponentWorld::new(self).context("failed to decode world from module")?;
         let mut state = EncodingState {
             component: ComponentBuilder::default(),
@@ -2082,6 +2278,10 @@ impl ComponentEncoder {
                 .context("failed to validate component output")?;
         }
 
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            cache.put(key, &bytes)?;
+        }
+
         Ok(bytes)
     }
 }
@@ -2166,4 +2366,59 @@ world test {
         assert!(wat.contains("unlocked-dep=<foo:bar/foo@{>=1.0.0 <1.1.0}>"));
         assert!(wat.contains("locked-dep=<foo:bar/i@1.2.3>"));
     }
+    #[test]
+    fn it_reuses_cached_components() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        #[derive(Clone, Default)]
+        struct MemoryCache(Rc<RefCell<HashMap<String, Vec<u8>>>>);
+
+        impl ComponentCache for MemoryCache {
+            fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+                Ok(self.0.borrow().get(key).cloned())
+            }
+
+            fn put(&self, key: &str, component: &[u8]) -> Result<()> {
+                self.0.borrow_mut().insert(key.to_string(), component.to_vec());
+                Ok(())
+            }
+        }
+
+        let module = |wit: &str, result: i32| {
+            let mut resolve = Resolve::new();
+            let pkg = resolve.push_str("test.wit", wit).unwrap();
+            let world = resolve.select_world(pkg, None).unwrap();
+            let mut module = wat::parse_str(format!(
+                r#"(module (func (export "f") (result i32) i32.const {result}))"#
+            ))
+            .unwrap();
+            embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)
+                .unwrap();
+            module
+        };
+        let encode = |module: &[u8], cache: Option<&MemoryCache>| {
+            let mut encoder = ComponentEncoder::default().validate(true);
+            if let Some(cache) = cache {
+                encoder = encoder.cache(cache.clone());
+            }
+            encoder.module(module).unwrap().encode().unwrap()
+        };
+
+        let wit = "package test:wit; world test { export f: func() -> u32; }";
+        let cache = MemoryCache::default();
+        encode(&module(wit, 1), Some(&cache));
+        assert_eq!(cache.0.borrow().len(), 1);
+
+        // Only the code changed, so the cached component is reused and is the
+        // same as encoding from scratch.
+        let cached = encode(&module(wit, 2), Some(&cache));
+        assert_eq!(cache.0.borrow().len(), 1);
+        assert_eq!(cached, encode(&module(wit, 2), None));
+
+        // Changing the WIT is a cache miss.
+        let wit = "package test:wit; world test { export f: func() -> s32; }";
+        encode(&module(wit, 2), Some(&cache));
+        assert_eq!(cache.0.borrow().len(), 2);
+    }
 }
@@ -0,0 +1,176 @@
+//! Caching of encoded components across builds which only change code.
+//!
+//! The component encoding of a core module only depends on the module's
+//! imports, exports, and other declarations along with its WIT metadata,
+//! adapters, and encoder options. The module's code and data are embedded
+//! verbatim as the first core module of the component. When only the code
+//! or data of a module changes a previously encoded component can therefore be
+//! reused by splicing the new module into it, skipping the comparatively
+//! expensive work of encoding types, shims, and canonical functions.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use wasm_encoder::Encode;
+use wasmparser::{Digest, DigestParser, Parser, Payload, Sha256};
+
+/// A store for components previously produced by a
+/// [`ComponentEncoder`](crate::ComponentEncoder), see
+/// [`ComponentEncoder::cache`](crate::ComponentEncoder::cache).
+///
+/// Keys are digests of everything which influences an encoding, except for
+/// the code and data of the main module.
+pub trait ComponentCache {
+    /// Returns the component previously stored under `key`, if any.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Stores `component` under `key`, replacing any previous component.
+    fn put(&self, key: &str, component: &[u8]) -> Result<()>;
+}
+
+/// A [`ComponentCache`] storing each component as a file in a directory.
+#[derive(Debug, Clone)]
+pub struct DirectoryCache {
+    dir: PathBuf,
+}
+
+impl DirectoryCache {
+    /// Creates a cache which stores components in `dir`, which is created if
+    /// it doesn't already exist.
+    pub fn new(dir: impl Into<PathBuf>) -> DirectoryCache {
+        DirectoryCache { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.wasm"))
+    }
+}
+
+impl ComponentCache for DirectoryCache {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.path(key);
+        match fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("failed to read `{}`", path.display())),
+        }
+    }
+
+    fn put(&self, key: &str, component: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create `{}`", self.dir.display()))?;
+        // Write to a temporary file first so concurrent builds never observe a
+        // partially written component.
+        let path = self.path(key);
+        let tmp = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        fs::write(&tmp, component)
+            .with_context(|| format!("failed to write `{}`", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("failed to write `{}`", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Accumulates the inputs of a `ComponentEncoder` which determine the cache
+/// key of its output.
+#[derive(Default)]
+pub(super) struct CacheKey {
+    inputs: Vec<u8>,
+    uncachable: bool,
+}
+
+impl CacheKey {
+    /// Adds the digest of `bytes` to this key.
+    pub(super) fn add(&mut self, bytes: &[u8]) {
+        let mut digest = Sha256::default();
+        digest.update(bytes);
+        self.inputs.extend_from_slice(&digest.finalize());
+    }
+
+    /// Adds the section digests of `module` to this key, except for its code,
+    /// data, and the custom sections which the encoder doesn't inspect.
+    pub(super) fn add_module(&mut self, module: &[u8]) -> Result<()> {
+        const CUSTOM_SECTION: u8 = 0;
+        const CODE_SECTION: u8 = 10;
+        const DATA_SECTION: u8 = 11;
+        const DATA_COUNT_SECTION: u8 = 12;
+
+        for item in DigestParser::<Sha256>::new(Parser::new(0)).parse_all(module) {
+            let (payload, digests) = item?;
+            let inspected = match &payload {
+                Payload::CustomSection(s) => {
+                    s.name() == "producers" || s.name().starts_with("component-type")
+                }
+                _ => true,
+            };
+            for section in digests.sections {
+                match section.id {
+                    CODE_SECTION | DATA_SECTION | DATA_COUNT_SECTION => continue,
+                    CUSTOM_SECTION if !inspected => continue,
+                    _ => {}
+                }
+                self.inputs.push(section.id);
+                self.inputs.extend_from_slice(&section.digest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the encoding as one which can't be cached.
+    pub(super) fn set_uncachable(&mut self) {
+        self.uncachable = true;
+    }
+
+    /// Returns the key for the inputs added so far, including `extra`, or
+    /// `None` if the output can't be cached.
+    pub(super) fn finish(&self, extra: &[u8]) -> Option<String> {
+        if self.uncachable {
+            return None;
+        }
+        let mut digest = Sha256::default();
+        digest.update(&self.inputs);
+        digest.update(extra);
+        let key = digest
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Some(key)
+    }
+}
+
+/// Replaces the main module of the previously encoded `component` with
+/// `module`.
+///
+/// Returns `None` if `component` doesn't look like the output of a
+/// `ComponentEncoder`.
+pub(super) fn splice_main_module(component: &[u8], module: &[u8]) -> Option<Vec<u8>> {
+    const COMPONENT_MODULE_SECTION: u8 = 1;
+
+    for payload in Parser::new(0).parse_all(component) {
+        let range = match payload.ok()? {
+            Payload::ModuleSection {
+                unchecked_range, ..
+            } => unchecked_range,
+            _ => continue,
+        };
+
+        // Find the start of the section header which precedes the module, and
+        // double-check that it's actually there.
+        let mut size = Vec::new();
+        range.len().encode(&mut size);
+        let start = range.start.checked_sub(size.len() + 1)?;
+        if component[start] != COMPONENT_MODULE_SECTION
+            || component[start + 1..range.start] != size[..]
+        {
+            return None;
+        }
+
+        let mut spliced = component[..start].to_vec();
+        spliced.push(COMPONENT_MODULE_SECTION);
+        module.len().encode(&mut spliced);
+        spliced.extend_from_slice(module);
+        spliced.extend_from_slice(&component[range.end..]);
+        return Some(spliced);
+    }
+    None
+}
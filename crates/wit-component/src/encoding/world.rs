@@ -74,6 +74,7 @@ impl<'a> ComponentWorld<'a> {
             &encoder.metadata,
             &encoder.main_module_exports,
             &adapters,
+            &encoder.canonical_options,
         )
         .context("module was not valid")?;
 
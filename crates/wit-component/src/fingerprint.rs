@@ -0,0 +1,30 @@
+use crate::encoding::encode_world;
+use anyhow::{Context, Result};
+use wasm_encoder::{ComponentBuilder, ComponentTypeRef};
+use wasmparser::{Digest, Validator};
+use wit_parser::{Resolve, WorldId};
+
+/// Computes a fingerprint of the interface of `world` with the hash function
+/// `D`.
+///
+/// The fingerprint is the same as the one computed by
+/// [`Types::interface_fingerprint`] for a component which imports and exports
+/// exactly what `world` does, so components can be checked against a world
+/// without decoding their WIT. Like that fingerprint, this only covers the
+/// names and types of imports and exports, so documentation, the package the
+/// world is defined in, and the order items are defined in don't matter.
+///
+/// [`Types::interface_fingerprint`]: wasmparser::types::Types::interface_fingerprint
+pub fn world_fingerprint<D: Digest>(resolve: &Resolve, world: WorldId) -> Result<D::Output> {
+    // Encode the world as the type of an imported component and let
+    // wasmparser resolve the types within it.
+    let ty = encode_world(resolve, world)?;
+    let mut component = ComponentBuilder::default();
+    let ty = component.type_component(&ty);
+    component.import(&resolve.worlds[world].name, ComponentTypeRef::Component(ty));
+    let bytes = component.finish();
+    let types = Validator::new()
+        .validate_all(&bytes)
+        .context("failed to validate encoded world")?;
+    Ok(types.component_type_fingerprint::<D>(types.component_at(0)))
+}
@@ -0,0 +1,461 @@
+//! Flattening of components into a single core wasm module.
+//!
+//! Engines without support for the component model can still run code which
+//! targets it if a host shim implements the canonical ABI for them. The
+//! [`flatten`] function here extracts the core wasm module implementing a
+//! component along with a [`Glue`] description of how each of the module's
+//! imports and exports corresponds to the component's WIT world, which is what
+//! such a shim needs to know.
+//!
+//! Only simple components are supported, namely those created by
+//! `wit-component` from a single core wasm module without any adapters.
+
+use crate::encoding::RequiredOptions;
+use crate::metadata::{Bindgen, ModuleMetadata};
+use crate::validation::{validate_module, Export, Import, ValidatedModule};
+use crate::StringEncoding;
+use anyhow::{bail, Context, Result};
+use indexmap::IndexSet;
+use serde_derive::Serialize;
+use std::fmt::Display;
+use wasmparser::{CanonicalFunction, CanonicalOption, Parser, Payload};
+use wit_parser::decoding::{decode, DecodedWasm};
+use wit_parser::{Function, InterfaceId, Resolve, TypeId, TypeOwner, WorldId, WorldItem, WorldKey};
+
+/// A component flattened into a core wasm module by [`flatten`].
+#[derive(Debug, Clone)]
+pub struct Flattened {
+    /// The core wasm module which implements the component.
+    pub module: Vec<u8>,
+    /// How the imports and exports of `module` correspond to the component.
+    pub glue: Glue,
+}
+
+/// The canonical ABI glue which a host needs to provide to run a flattened
+/// module in place of the component it came from.
+///
+/// Functions are referred to by the name of the interface in the
+/// component's world, if any, and their name within that interface or
+/// world. The WIT of the component describes their types.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Glue {
+    /// The string encoding the module uses.
+    #[serde(serialize_with = "serialize_display")]
+    pub string_encoding: StringEncoding,
+    /// The name of the memory the module exports, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// The name of a function the module exports which must be called after
+    /// instantiation and before any other exports, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initialize: Option<String>,
+    /// What each import of the module is, in the order they're imported.
+    pub imports: Vec<GlueImport>,
+    /// What each export of the module implements, excluding the memory,
+    /// `realloc` and post-return functions which are listed in the options of
+    /// the functions using them.
+    pub exports: Vec<GlueExport>,
+}
+
+/// An import of a flattened module.
+#[derive(Debug, Clone, Serialize)]
+pub struct GlueImport {
+    /// The module name of the import.
+    pub module: String,
+    /// The field name of the import.
+    pub name: String,
+    /// What the host must provide for this import.
+    #[serde(flatten)]
+    pub item: GlueItem,
+    /// The options to use to lower the function, if this is a function.
+    #[serde(flatten)]
+    pub options: GlueOptions,
+}
+
+/// An export of a flattened module.
+#[derive(Debug, Clone, Serialize)]
+pub struct GlueExport {
+    /// The name of the core wasm export.
+    pub name: String,
+    /// What this export implements.
+    #[serde(flatten)]
+    pub item: GlueItem,
+    /// The options to use to lift the function, if this is a function.
+    #[serde(flatten)]
+    pub options: GlueOptions,
+}
+
+/// An item of the component which an import or export of a flattened module
+/// corresponds to.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum GlueItem {
+    /// A function imported or exported by the component.
+    Func {
+        /// The interface containing the function, or `None` for functions
+        /// of the world itself.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interface: Option<String>,
+        /// The name of the function.
+        function: String,
+    },
+    /// The destructor of a resource imported by the component.
+    ResourceDrop {
+        /// The interface containing the resource, or `None` for resources
+        /// of the world itself.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interface: Option<String>,
+        /// The name of the resource.
+        resource: String,
+    },
+    /// The `resource.drop` intrinsic of a resource exported by the
+    /// component.
+    ExportedResourceDrop {
+        /// The interface containing the resource.
+        interface: String,
+        /// The name of the resource.
+        resource: String,
+    },
+    /// The `resource.new` intrinsic of a resource exported by the component.
+    ExportedResourceNew {
+        /// The interface containing the resource.
+        interface: String,
+        /// The name of the resource.
+        resource: String,
+    },
+    /// The `resource.rep` intrinsic of a resource exported by the component.
+    ExportedResourceRep {
+        /// The interface containing the resource.
+        interface: String,
+        /// The name of the resource.
+        resource: String,
+    },
+    /// The destructor of a resource exported by the component, which is
+    /// called with the representation of a resource when it's dropped.
+    ResourceDtor {
+        /// The interface containing the resource.
+        interface: String,
+        /// The name of the resource.
+        resource: String,
+    },
+}
+
+/// The canonical ABI options of a function, where only the options the
+/// function requires are present.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GlueOptions {
+    /// Whether the function accesses the memory of the module.
+    #[serde(skip_serializing_if = "is_false")]
+    pub memory: bool,
+    /// The name of the exported function to allocate memory in the module
+    /// with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realloc: Option<String>,
+    /// The name of the exported function to call after the results of an
+    /// exported function have been lifted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_return: Option<String>,
+}
+
+fn serialize_display<S: serde::Serializer>(
+    value: &impl Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Flattens the `component` into the core wasm module implementing it.
+///
+/// The component must contain a core wasm module which implements its world
+/// on its own, which is the case for components created by `wit-component`
+/// without any adapters. The module is returned as-is along with the glue
+/// describing its imports and exports.
+pub fn flatten(component: &[u8]) -> Result<Flattened> {
+    if Parser::is_core_wasm(component) {
+        bail!("input is a core wasm module, not a component");
+    }
+    let (resolve, world) = match decode(component).context("failed to decode component")? {
+        DecodedWasm::Component(resolve, world) => (resolve, world),
+        DecodedWasm::WitPackage(..) => bail!("input is a WIT package, not a component"),
+    };
+
+    // Find the core wasm modules defined at the top level of the component,
+    // and the string encoding used by its canonical functions.
+    let mut modules = Vec::new();
+    let mut string_encoding = None;
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(component) {
+        match payload? {
+            Payload::ModuleSection {
+                unchecked_range, ..
+            } => {
+                if depth == 0 {
+                    modules.push(&component[unchecked_range]);
+                }
+                depth += 1;
+            }
+            Payload::ComponentSection { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            Payload::ComponentCanonicalSection(s) if depth == 0 => {
+                for func in s {
+                    let options = match func? {
+                        CanonicalFunction::Lift { options, .. }
+                        | CanonicalFunction::Lower { options, .. } => options,
+                        _ => continue,
+                    };
+                    for option in options.iter() {
+                        let encoding = match option {
+                            CanonicalOption::UTF8 => StringEncoding::UTF8,
+                            CanonicalOption::UTF16 => StringEncoding::UTF16,
+                            CanonicalOption::CompactUTF16 => StringEncoding::CompactUTF16,
+                            _ => continue,
+                        };
+                        match string_encoding {
+                            Some(prev) if prev != encoding => {
+                                bail!("component uses more than one string encoding")
+                            }
+                            _ => string_encoding = Some(encoding),
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let string_encoding = string_encoding.unwrap_or_default();
+
+    // The module implementing the component is the one whose imports and
+    // exports match the world of the component. Modules generated by
+    // `wit-component` for indirect calls and adapters won't match.
+    let metadata = Bindgen {
+        metadata: ModuleMetadata::new(&resolve, world, string_encoding),
+        resolve,
+        world,
+        producers: None,
+    };
+    let exports = metadata.resolve.worlds[world]
+        .exports
+        .keys()
+        .cloned()
+        .collect::<IndexSet<_>>();
+    let mut first_error = None;
+    for module in modules {
+        match validate_module(
+            module,
+            &metadata,
+            &exports,
+            &IndexSet::new(),
+            &Default::default(),
+        ) {
+            Ok(info) => {
+                let glue = Glue::new(&metadata.resolve, world, string_encoding, &info)?;
+                return Ok(Flattened {
+                    module: module.to_vec(),
+                    glue,
+                });
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) => Err(e.context(
+            "no core wasm module in the component implements its world on its own, \
+             only components without adapters can be flattened",
+        )),
+        None => bail!("component does not contain any core wasm modules"),
+    }
+}
+
+impl Glue {
+    fn new(
+        resolve: &Resolve,
+        world: WorldId,
+        string_encoding: StringEncoding,
+        info: &ValidatedModule,
+    ) -> Result<Glue> {
+        let mut glue = Glue {
+            string_encoding,
+            memory: info.exports.memory().map(|s| s.to_string()),
+            initialize: info.exports.initialize().map(|s| s.to_string()),
+            imports: Vec::new(),
+            exports: Vec::new(),
+        };
+        let resource = |ty: TypeId| resolve.types[ty].name.clone().unwrap_or_default();
+        let interface_name = |key: &WorldKey| resolve.name_world_key(key);
+
+        for (module, name, import) in info.imports.imports() {
+            let mut options = GlueOptions::default();
+            let item = match import {
+                Import::WorldFunc(func) => {
+                    let f = world_func(resolve, world, func, true)?;
+                    options = import_options(resolve, info, None, f);
+                    GlueItem::Func {
+                        interface: None,
+                        function: func.clone(),
+                    }
+                }
+                Import::InterfaceFunc(key, id, func) => {
+                    let f = &resolve.interfaces[*id].functions[func.as_str()];
+                    options = import_options(resolve, info, Some(*id), f);
+                    GlueItem::Func {
+                        interface: Some(interface_name(key)),
+                        function: func.clone(),
+                    }
+                }
+                Import::ImportedResourceDrop(key, ty) => GlueItem::ResourceDrop {
+                    interface: key.as_ref().map(interface_name),
+                    resource: resource(*ty),
+                },
+                Import::ExportedResourceDrop(key, ty) => GlueItem::ExportedResourceDrop {
+                    interface: interface_name(key),
+                    resource: resource(*ty),
+                },
+                Import::ExportedResourceNew(key, ty) => GlueItem::ExportedResourceNew {
+                    interface: interface_name(key),
+                    resource: resource(*ty),
+                },
+                Import::ExportedResourceRep(key, ty) => GlueItem::ExportedResourceRep {
+                    interface: interface_name(key),
+                    resource: resource(*ty),
+                },
+                Import::AdapterExport(_)
+                | Import::MainModuleMemory
+                | Import::MainModuleExport { .. }
+                | Import::Item(_) => {
+                    bail!("import `{module}::{name}` is not supported when flattening")
+                }
+            };
+            glue.imports.push(GlueImport {
+                module: module.to_string(),
+                name: name.to_string(),
+                item,
+                options,
+            });
+        }
+
+        for (name, export) in info.exports.iter() {
+            let (item, options) = match export {
+                Export::WorldFunc(func) => {
+                    let f = world_func(resolve, world, func, false)?;
+                    let item = GlueItem::Func {
+                        interface: None,
+                        function: func.clone(),
+                    };
+                    (item, export_options(resolve, info, None, f))
+                }
+                Export::InterfaceFunc(id, func) => {
+                    let f = &resolve.interfaces[*id].functions[func.as_str()];
+                    let item = GlueItem::Func {
+                        interface: Some(exported_interface_name(resolve, world, *id)),
+                        function: func.clone(),
+                    };
+                    (item, export_options(resolve, info, Some(*id), f))
+                }
+                Export::ResourceDtor(ty) => {
+                    let interface = match resolve.types[*ty].owner {
+                        TypeOwner::Interface(id) => exported_interface_name(resolve, world, id),
+                        _ => bail!("resource destructor `{name}` isn't for an interface"),
+                    };
+                    let item = GlueItem::ResourceDtor {
+                        interface,
+                        resource: resource(*ty),
+                    };
+                    (item, GlueOptions::default())
+                }
+                Export::WorldFuncPostReturn(_)
+                | Export::InterfaceFuncPostReturn(..)
+                | Export::Memory
+                | Export::GeneralPurposeRealloc
+                | Export::GeneralPurposeExportRealloc
+                | Export::GeneralPurposeImportRealloc
+                | Export::Initialize
+                | Export::ReallocForAdapter => continue,
+            };
+            glue.exports.push(GlueExport {
+                name: name.to_string(),
+                item,
+                options,
+            });
+        }
+
+        Ok(glue)
+    }
+}
+
+fn world_func<'a>(
+    resolve: &'a Resolve,
+    world: WorldId,
+    name: &str,
+    import: bool,
+) -> Result<&'a Function> {
+    let world = &resolve.worlds[world];
+    let items = if import {
+        &world.imports
+    } else {
+        &world.exports
+    };
+    match items.get(&WorldKey::Name(name.to_string())) {
+        Some(WorldItem::Function(f)) => Ok(f),
+        _ => bail!("world has no function named `{name}`"),
+    }
+}
+
+fn exported_interface_name(resolve: &Resolve, world: WorldId, id: InterfaceId) -> String {
+    resolve.worlds[world]
+        .exports
+        .iter()
+        .find(|(_, item)| matches!(item, WorldItem::Interface { id: i, .. } if *i == id))
+        .map(|(key, _)| resolve.name_world_key(key))
+        .unwrap_or_else(|| resolve.id_of(id).unwrap_or_default())
+}
+
+fn import_options(
+    resolve: &Resolve,
+    info: &ValidatedModule,
+    interface: Option<InterfaceId>,
+    func: &Function,
+) -> GlueOptions {
+    let required = RequiredOptions::for_import(resolve, func);
+    GlueOptions {
+        memory: required.contains(RequiredOptions::MEMORY),
+        realloc: if required.contains(RequiredOptions::REALLOC) {
+            info.exports
+                .import_realloc_for(interface, &func.name)
+                .map(|s| s.to_string())
+        } else {
+            None
+        },
+        post_return: None,
+    }
+}
+
+fn export_options(
+    resolve: &Resolve,
+    info: &ValidatedModule,
+    interface: Option<InterfaceId>,
+    func: &Function,
+) -> GlueOptions {
+    let required = RequiredOptions::for_export(resolve, func);
+    GlueOptions {
+        memory: required.contains(RequiredOptions::MEMORY),
+        realloc: if required.contains(RequiredOptions::REALLOC) {
+            info.exports
+                .export_realloc_for(interface, func)
+                .map(|s| s.to_string())
+        } else {
+            None
+        },
+        post_return: info
+            .exports
+            .post_return(interface, func)
+            .map(|s| s.to_string()),
+    }
+}
@@ -11,15 +11,22 @@ use wasm_encoder::{CanonicalOption, Encode, Section};
 use wit_parser::{Resolve, WorldId};
 
 mod encoding;
+mod fingerprint;
+pub mod flatten;
 mod gc;
 mod linking;
 mod printing;
+mod static_linking;
 mod targets;
 mod validation;
 
-pub use encoding::{encode, ComponentEncoder};
+pub use encoding::{
+    encode, CanonicalOptions, ComponentCache, ComponentEncoder, DirectoryCache,
+};
+pub use fingerprint::world_fingerprint;
 pub use linking::Linker;
 pub use printing::*;
+pub use static_linking::StaticLinker;
 pub use targets::*;
 pub use wit_parser::decoding::{decode, decode_reader, DecodedWasm};
 
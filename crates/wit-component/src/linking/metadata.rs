@@ -426,6 +426,25 @@ impl<'a> Metadata<'a> {
                     }
                 }
 
+                // Shared-everything linking requires that all modules share
+                // a single memory and table which are defined by the
+                // synthesized `env` module, so modules which define their own
+                // can't be linked. This is typically the result of linking a
+                // module as an executable rather than a shared library.
+                Payload::MemorySection(reader) if reader.count() > 0 => {
+                    bail!(
+                        "module defines its own memory, but libraries must import `env.memory` \
+                         to be linked (was this module built with `-shared`?)"
+                    )
+                }
+                Payload::TableSection(reader) if reader.count() > 0 => {
+                    bail!(
+                        "module defines its own table, but libraries must import \
+                         `env.__indirect_function_table` to be linked (was this module \
+                         built with `-shared`?)"
+                    )
+                }
+
                 Payload::FunctionSection(reader) => {
                     for function in reader {
                         function_types.push(usize::try_from(function?).unwrap());
@@ -3,9 +3,16 @@ use crate::{
     StringEncoding,
 };
 use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use semver::Version;
+use std::fmt;
+use std::mem;
 use wasm_encoder::{ComponentBuilder, ComponentExportKind, ComponentTypeRef};
 use wasmparser::Validator;
-use wit_parser::{Resolve, WorldId};
+use wit_parser::{
+    Function, FunctionKind, Handle, InterfaceId, PackageId, Resolve, Results, Type, TypeDefKind,
+    TypeId, TypeOwner, WorldId, WorldItem, WorldKey,
+};
 
 /// Tests whether `new` is a semver-compatible upgrade from the world `prev`.
 ///
@@ -108,3 +115,609 @@ pub fn semver_check(mut resolve: Resolve, prev: WorldId, new: WorldId) -> Result
 
     Ok(())
 }
+
+/// The kind of version bump that a change to a WIT package requires.
+///
+/// Variants are ordered such that a "larger" bump compares greater than a
+/// smaller one, meaning the required bump for a set of changes is the maximum
+/// of each individual change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SemverBump {
+    /// Only documentation or otherwise non-semantic changes were made.
+    Patch,
+    /// Backwards-compatible additions were made.
+    Minor,
+    /// Backwards-incompatible changes were made.
+    Major,
+}
+
+impl SemverBump {
+    /// Returns the smallest version greater than `prev` which accounts for
+    /// this bump.
+    ///
+    /// This follows the conventions of Cargo and the component model where the
+    /// first nonzero component of a version is considered the "major" version,
+    /// so for example a major bump of `0.2.1` is `0.3.0`.
+    pub fn next_version(&self, prev: &Version) -> Version {
+        let bump = if prev.major > 0 {
+            *self
+        } else if prev.minor > 0 {
+            match self {
+                SemverBump::Major => SemverBump::Minor,
+                SemverBump::Minor | SemverBump::Patch => SemverBump::Patch,
+            }
+        } else {
+            SemverBump::Patch
+        };
+        let (major, minor, patch) = (prev.major, prev.minor, prev.patch);
+        match (bump, prev.major > 0) {
+            (SemverBump::Major, _) => Version::new(major + 1, 0, 0),
+            (SemverBump::Minor, true) => Version::new(major, minor + 1, 0),
+            (SemverBump::Minor, false) => Version::new(0, minor + 1, 0),
+            (SemverBump::Patch, _) => Version::new(major, minor, patch + 1),
+        }
+    }
+}
+
+impl fmt::Display for SemverBump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SemverBump::Patch => f.write_str("patch"),
+            SemverBump::Minor => f.write_str("minor"),
+            SemverBump::Major => f.write_str("major"),
+        }
+    }
+}
+
+/// A single difference found by [`semver_diff`] or [`semver_diff_packages`].
+#[derive(Debug, Clone)]
+pub struct SemverChange {
+    /// The version bump that this change requires.
+    pub bump: SemverBump,
+    /// A path to the item that changed, such as `a:b/foo#bar` or
+    /// `world w/import foo`.
+    pub path: String,
+    /// A human readable description of what changed.
+    pub description: String,
+}
+
+impl fmt::Display for SemverChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.bump, self.path, self.description)
+    }
+}
+
+/// The result of comparing two versions of a WIT package or world.
+#[derive(Debug, Clone, Default)]
+pub struct SemverReport {
+    /// All changes that were detected, in the order they were found.
+    pub changes: Vec<SemverChange>,
+}
+
+impl SemverReport {
+    /// Returns the minimum version bump required to account for all changes
+    /// in this report.
+    pub fn required_bump(&self) -> SemverBump {
+        self.changes
+            .iter()
+            .map(|c| c.bump)
+            .max()
+            .unwrap_or(SemverBump::Patch)
+    }
+
+    /// Returns whether the new version is backwards-compatible with the old
+    /// version, meaning that no major changes were detected.
+    pub fn is_compatible(&self) -> bool {
+        self.required_bump() < SemverBump::Major
+    }
+}
+
+/// Compares two worlds, possibly from different [`Resolve`]s, and reports all
+/// differences between them along with the version bump each one requires.
+///
+/// Unlike [`semver_check`] this does not require both worlds to live in the
+/// same package and instead works on a structural diff of the two worlds. The
+/// rules applied are the same as the ones `semver_check` tests, namely from
+/// the perspective of a component targeting `prev`:
+///
+/// * Adding imports is a minor change while removing them is a major change
+///   since a component may be using them.
+/// * Removing exports is a minor change while adding them is a major change
+///   since existing components won't provide them.
+/// * Adding a function (including resource methods) to an imported interface
+///   is minor, and similarly removing a function from an exported interface
+///   is minor. The reverse is major.
+/// * Any change to the structure of a type or function signature is major.
+///   This includes adding cases to an `enum`, `variant`, or `flags` since the
+///   component model requires exact type equality for these.
+/// * Changes to documentation only require a patch bump.
+pub fn semver_diff(
+    prev_resolve: &Resolve,
+    prev: WorldId,
+    new_resolve: &Resolve,
+    new: WorldId,
+) -> SemverReport {
+    let mut diff = Diff {
+        prev: prev_resolve,
+        new: new_resolve,
+        report: SemverReport::default(),
+    };
+    let path = format!("world {}", prev_resolve.worlds[prev].name);
+    diff.world(&path, prev, new);
+    diff.report
+}
+
+/// Compares two versions of a WIT package, possibly from different
+/// [`Resolve`]s, and reports all differences between them.
+///
+/// Interfaces and worlds are matched up by name. Adding an interface or world
+/// is a minor change and removing one is a major change. Worlds are compared
+/// with [`semver_diff`]. Interfaces at the package level may be used as both
+/// imports and exports, so adding functions or types to them is considered
+/// minor, in line with `@since`-based evolution, while removing or changing
+/// anything is major.
+pub fn semver_diff_packages(
+    prev_resolve: &Resolve,
+    prev: PackageId,
+    new_resolve: &Resolve,
+    new: PackageId,
+) -> SemverReport {
+    let mut diff = Diff {
+        prev: prev_resolve,
+        new: new_resolve,
+        report: SemverReport::default(),
+    };
+    let prev_pkg = &prev_resolve.packages[prev];
+    let new_pkg = &new_resolve.packages[new];
+    let pkg_name = format!("{}:{}", prev_pkg.name.namespace, prev_pkg.name.name);
+    if prev_pkg.docs != new_pkg.docs {
+        diff.change(SemverBump::Patch, &pkg_name, "documentation changed");
+    }
+
+    for (name, prev_id) in prev_pkg.interfaces.iter() {
+        let path = format!("{pkg_name}/{name}");
+        match new_pkg.interfaces.get(name) {
+            Some(new_id) => diff.interface(&path, *prev_id, *new_id, Position::Package),
+            None => diff.change(SemverBump::Major, &path, "interface removed"),
+        }
+    }
+    for name in new_pkg.interfaces.keys() {
+        if !prev_pkg.interfaces.contains_key(name) {
+            let path = format!("{pkg_name}/{name}");
+            diff.change(SemverBump::Minor, &path, "interface added");
+        }
+    }
+
+    for (name, prev_id) in prev_pkg.worlds.iter() {
+        let path = format!("world {name}");
+        match new_pkg.worlds.get(name) {
+            Some(new_id) => diff.world(&path, *prev_id, *new_id),
+            None => diff.change(SemverBump::Major, &path, "world removed"),
+        }
+    }
+    for name in new_pkg.worlds.keys() {
+        if !prev_pkg.worlds.contains_key(name) {
+            let path = format!("world {name}");
+            diff.change(SemverBump::Minor, &path, "world added");
+        }
+    }
+
+    diff.report
+}
+
+/// Where an interface or item is located, which determines whether additions
+/// or removals are backwards-compatible.
+#[derive(Copy, Clone)]
+enum Position {
+    Import,
+    Export,
+    Package,
+}
+
+impl Position {
+    fn added(&self) -> SemverBump {
+        match self {
+            Position::Import | Position::Package => SemverBump::Minor,
+            Position::Export => SemverBump::Major,
+        }
+    }
+
+    fn removed(&self) -> SemverBump {
+        match self {
+            Position::Export => SemverBump::Minor,
+            Position::Import | Position::Package => SemverBump::Major,
+        }
+    }
+}
+
+struct Diff<'a> {
+    prev: &'a Resolve,
+    new: &'a Resolve,
+    report: SemverReport,
+}
+
+impl Diff<'_> {
+    fn change(&mut self, bump: SemverBump, path: &str, description: impl Into<String>) {
+        self.report.changes.push(SemverChange {
+            bump,
+            path: path.to_string(),
+            description: description.into(),
+        });
+    }
+
+    fn world(&mut self, path: &str, prev: WorldId, new: WorldId) {
+        let prev_world = &self.prev.worlds[prev];
+        let new_world = &self.new.worlds[new];
+        if prev_world.docs != new_world.docs {
+            self.change(SemverBump::Patch, path, "documentation changed");
+        }
+        self.world_items(
+            path,
+            "import",
+            &prev_world.imports,
+            &new_world.imports,
+            Position::Import,
+        );
+        self.world_items(
+            path,
+            "export",
+            &prev_world.exports,
+            &new_world.exports,
+            Position::Export,
+        );
+    }
+
+    fn world_items(
+        &mut self,
+        path: &str,
+        kind: &str,
+        prev: &IndexMap<WorldKey, WorldItem>,
+        new: &IndexMap<WorldKey, WorldItem>,
+        pos: Position,
+    ) {
+        let prev_items = prev
+            .iter()
+            .map(|(k, v)| (unversioned_key(self.prev, k), v))
+            .collect::<IndexMap<_, _>>();
+        let new_items = new
+            .iter()
+            .map(|(k, v)| (unversioned_key(self.new, k), v))
+            .collect::<IndexMap<_, _>>();
+
+        for (name, prev_item) in prev_items.iter() {
+            let path = format!("{path}/{kind} {name}");
+            let new_item = match new_items.get(name) {
+                Some(item) => item,
+                None => {
+                    self.change(pos.removed(), &path, format!("{kind} removed"));
+                    continue;
+                }
+            };
+            match (prev_item, new_item) {
+                (WorldItem::Interface { id: a, .. }, WorldItem::Interface { id: b, .. }) => {
+                    self.interface(&path, *a, *b, pos)
+                }
+                (WorldItem::Function(a), WorldItem::Function(b)) => self.function(&path, a, b),
+                (WorldItem::Type(a), WorldItem::Type(b)) => self.typedef(&path, *a, *b),
+                _ => self.change(
+                    SemverBump::Major,
+                    &path,
+                    format!(
+                        "{kind} changed from {} to {}",
+                        desc(prev_item),
+                        desc(new_item)
+                    ),
+                ),
+            }
+        }
+        for name in new_items.keys() {
+            if !prev_items.contains_key(name) {
+                let path = format!("{path}/{kind} {name}");
+                self.change(pos.added(), &path, format!("{kind} added"));
+            }
+        }
+
+        fn desc(item: &WorldItem) -> &'static str {
+            match item {
+                WorldItem::Interface { .. } => "an interface",
+                WorldItem::Function(_) => "a function",
+                WorldItem::Type(_) => "a type",
+            }
+        }
+    }
+
+    fn interface(&mut self, path: &str, prev: InterfaceId, new: InterfaceId, pos: Position) {
+        let prev_iface = &self.prev.interfaces[prev];
+        let new_iface = &self.new.interfaces[new];
+        if prev_iface.docs != new_iface.docs {
+            self.change(SemverBump::Patch, path, "documentation changed");
+        }
+
+        for (name, prev_ty) in prev_iface.types.iter() {
+            let path = format!("{path}#{name}");
+            match new_iface.types.get(name) {
+                Some(new_ty) => self.typedef(&path, *prev_ty, *new_ty),
+                None => self.change(pos.removed(), &path, "type removed"),
+            }
+        }
+        for name in new_iface.types.keys() {
+            if !prev_iface.types.contains_key(name) {
+                let path = format!("{path}#{name}");
+                self.change(pos.added(), &path, "type added");
+            }
+        }
+
+        for (name, prev_func) in prev_iface.functions.iter() {
+            let path = format!("{path}#{name}");
+            match new_iface.functions.get(name) {
+                Some(new_func) => self.function(&path, prev_func, new_func),
+                None => self.change(
+                    pos.removed(),
+                    &path,
+                    format!("{} removed", func_kind(prev_func)),
+                ),
+            }
+        }
+        for (name, new_func) in new_iface.functions.iter() {
+            if !prev_iface.functions.contains_key(name) {
+                let path = format!("{path}#{name}");
+                self.change(pos.added(), &path, format!("{} added", func_kind(new_func)));
+            }
+        }
+
+        fn func_kind(func: &Function) -> &'static str {
+            match func.kind {
+                FunctionKind::Freestanding => "function",
+                FunctionKind::Method(_) => "resource method",
+                FunctionKind::Static(_) => "resource static method",
+                FunctionKind::Constructor(_) => "resource constructor",
+            }
+        }
+    }
+
+    fn function(&mut self, path: &str, prev: &Function, new: &Function) {
+        if prev.docs != new.docs {
+            self.change(SemverBump::Patch, path, "documentation changed");
+        }
+        if mem::discriminant(&prev.kind) != mem::discriminant(&new.kind) {
+            self.change(SemverBump::Major, path, "function kind changed");
+            return;
+        }
+        if prev.params.len() != new.params.len() {
+            self.change(
+                SemverBump::Major,
+                path,
+                format!(
+                    "number of parameters changed from {} to {}",
+                    prev.params.len(),
+                    new.params.len()
+                ),
+            );
+        } else {
+            for ((a_name, a), (b_name, b)) in prev.params.iter().zip(new.params.iter()) {
+                if a_name != b_name {
+                    self.change(
+                        SemverBump::Major,
+                        path,
+                        format!("parameter `{a_name}` renamed to `{b_name}`"),
+                    );
+                } else if !self.type_eq(a, b) {
+                    self.change(
+                        SemverBump::Major,
+                        path,
+                        format!("type of parameter `{a_name}` changed"),
+                    );
+                }
+            }
+        }
+        let results_eq = match (&prev.results, &new.results) {
+            (Results::Anon(a), Results::Anon(b)) => self.type_eq(a, b),
+            (Results::Named(a), Results::Named(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|((an, a), (bn, b))| an == bn && self.type_eq(a, b))
+            }
+            _ => false,
+        };
+        if !results_eq {
+            self.change(SemverBump::Major, path, "results changed");
+        }
+    }
+
+    fn typedef(&mut self, path: &str, prev: TypeId, new: TypeId) {
+        let prev_ty = &self.prev.types[prev];
+        let new_ty = &self.new.types[new];
+        if prev_ty.docs != new_ty.docs {
+            self.change(SemverBump::Patch, path, "documentation changed");
+        }
+        match (&prev_ty.kind, &new_ty.kind) {
+            (TypeDefKind::Record(a), TypeDefKind::Record(b)) => {
+                let a = a.fields.iter().map(|f| (f.name.as_str(), Some(&f.ty)));
+                let b = b.fields.iter().map(|f| (f.name.as_str(), Some(&f.ty)));
+                self.cases(path, "field", a, b);
+            }
+            (TypeDefKind::Variant(a), TypeDefKind::Variant(b)) => {
+                let a = a.cases.iter().map(|c| (c.name.as_str(), c.ty.as_ref()));
+                let b = b.cases.iter().map(|c| (c.name.as_str(), c.ty.as_ref()));
+                self.cases(path, "case", a, b);
+            }
+            (TypeDefKind::Enum(a), TypeDefKind::Enum(b)) => {
+                let a = a.cases.iter().map(|c| (c.name.as_str(), None));
+                let b = b.cases.iter().map(|c| (c.name.as_str(), None));
+                self.cases(path, "enum case", a, b);
+            }
+            (TypeDefKind::Flags(a), TypeDefKind::Flags(b)) => {
+                let a = a.flags.iter().map(|c| (c.name.as_str(), None));
+                let b = b.flags.iter().map(|c| (c.name.as_str(), None));
+                self.cases(path, "flag", a, b);
+            }
+            (a, b) => {
+                if !self.kind_eq(a, b) {
+                    let description = if a.as_str() == b.as_str() {
+                        format!("{} definition changed", a.as_str())
+                    } else {
+                        format!("changed from {} to {}", a.as_str(), b.as_str())
+                    };
+                    self.change(SemverBump::Major, path, description);
+                }
+            }
+        }
+    }
+
+    /// Compares the named, ordered, and optionally-typed members of a record,
+    /// variant, enum, or flags type.
+    fn cases<'b>(
+        &mut self,
+        path: &str,
+        kind: &str,
+        prev: impl Iterator<Item = (&'b str, Option<&'b Type>)>,
+        new: impl Iterator<Item = (&'b str, Option<&'b Type>)>,
+    ) {
+        let prev = prev.collect::<IndexMap<_, _>>();
+        let new = new.collect::<IndexMap<_, _>>();
+        for (i, (name, ty)) in prev.iter().enumerate() {
+            match new.get_full(name) {
+                Some((j, _, new_ty)) => {
+                    if i != j {
+                        self.change(
+                            SemverBump::Major,
+                            path,
+                            format!("{kind} `{name}` reordered"),
+                        );
+                    }
+                    let ty_eq = match (ty, new_ty) {
+                        (Some(a), Some(b)) => self.type_eq(a, b),
+                        (None, None) => true,
+                        _ => false,
+                    };
+                    if !ty_eq {
+                        self.change(
+                            SemverBump::Major,
+                            path,
+                            format!("type of {kind} `{name}` changed"),
+                        );
+                    }
+                }
+                None => self.change(SemverBump::Major, path, format!("{kind} `{name}` removed")),
+            }
+        }
+        for name in new.keys() {
+            if !prev.contains_key(name) {
+                self.change(SemverBump::Major, path, format!("{kind} `{name}` added"));
+            }
+        }
+    }
+
+    /// Tests whether two type references are the same.
+    ///
+    /// Named types are compared by name and location since their definitions
+    /// are compared separately by `typedef` above. Anonymous types are
+    /// compared structurally.
+    fn type_eq(&self, prev: &Type, new: &Type) -> bool {
+        let (prev, new) = match (prev, new) {
+            (Type::Id(a), Type::Id(b)) => (*a, *b),
+            (Type::Id(_), _) | (_, Type::Id(_)) => return false,
+            (a, b) => return a == b,
+        };
+        let prev_ty = &self.prev.types[prev];
+        let new_ty = &self.new.types[new];
+        match (&prev_ty.name, &new_ty.name) {
+            (Some(a), Some(b)) => {
+                a == b && owner_name(self.prev, prev_ty.owner) == owner_name(self.new, new_ty.owner)
+            }
+            (None, None) => self.kind_eq(&prev_ty.kind, &new_ty.kind),
+            _ => false,
+        }
+    }
+
+    fn optional_type_eq(&self, prev: Option<&Type>, new: Option<&Type>) -> bool {
+        match (prev, new) {
+            (Some(a), Some(b)) => self.type_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn kind_eq(&self, prev: &TypeDefKind, new: &TypeDefKind) -> bool {
+        match (prev, new) {
+            (TypeDefKind::Record(a), TypeDefKind::Record(b)) => {
+                a.fields.len() == b.fields.len()
+                    && a.fields
+                        .iter()
+                        .zip(&b.fields)
+                        .all(|(a, b)| a.name == b.name && self.type_eq(&a.ty, &b.ty))
+            }
+            (TypeDefKind::Resource, TypeDefKind::Resource) => true,
+            (TypeDefKind::Handle(Handle::Own(a)), TypeDefKind::Handle(Handle::Own(b)))
+            | (TypeDefKind::Handle(Handle::Borrow(a)), TypeDefKind::Handle(Handle::Borrow(b))) => {
+                self.type_eq(&Type::Id(*a), &Type::Id(*b))
+            }
+            (TypeDefKind::Flags(a), TypeDefKind::Flags(b)) => {
+                a.flags.len() == b.flags.len()
+                    && a.flags.iter().zip(&b.flags).all(|(a, b)| a.name == b.name)
+            }
+            (TypeDefKind::Tuple(a), TypeDefKind::Tuple(b)) => {
+                a.types.len() == b.types.len()
+                    && a.types
+                        .iter()
+                        .zip(&b.types)
+                        .all(|(a, b)| self.type_eq(a, b))
+            }
+            (TypeDefKind::Variant(a), TypeDefKind::Variant(b)) => {
+                a.cases.len() == b.cases.len()
+                    && a.cases.iter().zip(&b.cases).all(|(a, b)| {
+                        a.name == b.name && self.optional_type_eq(a.ty.as_ref(), b.ty.as_ref())
+                    })
+            }
+            (TypeDefKind::Enum(a), TypeDefKind::Enum(b)) => {
+                a.cases.len() == b.cases.len()
+                    && a.cases.iter().zip(&b.cases).all(|(a, b)| a.name == b.name)
+            }
+            (TypeDefKind::Option(a), TypeDefKind::Option(b))
+            | (TypeDefKind::List(a), TypeDefKind::List(b))
+            | (TypeDefKind::Type(a), TypeDefKind::Type(b)) => self.type_eq(a, b),
+            (TypeDefKind::Result(a), TypeDefKind::Result(b)) => {
+                self.optional_type_eq(a.ok.as_ref(), b.ok.as_ref())
+                    && self.optional_type_eq(a.err.as_ref(), b.err.as_ref())
+            }
+            (TypeDefKind::Future(a), TypeDefKind::Future(b)) => {
+                self.optional_type_eq(a.as_ref(), b.as_ref())
+            }
+            (TypeDefKind::Stream(a), TypeDefKind::Stream(b)) => {
+                self.optional_type_eq(a.element.as_ref(), b.element.as_ref())
+                    && self.optional_type_eq(a.end.as_ref(), b.end.as_ref())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the name of `key` within a world with any package version
+/// stripped, so keys can be matched up across versions of a package.
+fn unversioned_key(resolve: &Resolve, key: &WorldKey) -> String {
+    match key {
+        WorldKey::Name(name) => name.clone(),
+        WorldKey::Interface(id) => unversioned_interface_name(resolve, *id),
+    }
+}
+
+fn unversioned_interface_name(resolve: &Resolve, id: InterfaceId) -> String {
+    let iface = &resolve.interfaces[id];
+    let name = iface.name.as_deref().unwrap_or("<anonymous>");
+    match iface.package {
+        Some(pkg) => {
+            let pkg = &resolve.packages[pkg].name;
+            format!("{}:{}/{name}", pkg.namespace, pkg.name)
+        }
+        None => name.to_string(),
+    }
+}
+
+fn owner_name(resolve: &Resolve, owner: TypeOwner) -> Option<String> {
+    match owner {
+        TypeOwner::Interface(id) => Some(unversioned_interface_name(resolve, id)),
+        TypeOwner::World(id) => Some(format!("world {}", resolve.worlds[id].name)),
+        TypeOwner::None => None,
+    }
+}
@@ -0,0 +1,1431 @@
+//! Support for statically linking relocatable object files into a component.
+//!
+//! This implements a subset of what `wasm-ld` does when linking object files following the [linking
+//! convention](https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md), so that a component can be
+//! created from a main object file and static libraries without invoking `wasm-ld` before
+//! [`ComponentEncoder`]. The entry point into this process is `StaticLinker::encode`, which:
+//!
+//! - resolves the symbols of the main object file and of the library object files by name, only including the
+//! parts of the libraries which are (transitively) needed by the main object file,
+//!
+//! - lays out the data segments of the objects after the stack in a single memory, and assigns slots in a single
+//! function table to the functions whose address is taken,
+//!
+//! - applies the relocations of the objects to their code and data, synthesizing `__wasm_call_ctors`,
+//! `__stack_pointer`, `__heap_base`, and `__data_end` as needed, and
+//!
+//! - encodes the resulting module into a component whose type is the union of any `component-type*` custom
+//! sections found in the linked objects.
+//!
+//! Position-independent code, thread-local storage, COMDATs, exception tags, and 64-bit memories aren't supported.
+
+use {
+    crate::{encoding::ComponentEncoder, linking::DEFAULT_STACK_SIZE_BYTES},
+    anyhow::{bail, Context, Result},
+    indexmap::{map::Entry, IndexMap, IndexSet},
+    std::{
+        borrow::Cow,
+        collections::{hash_map, HashMap},
+    },
+    wasm_encoder::{
+        reencode::{Reencode, RoundtripReencoder},
+        CodeSection, ConstExpr, CustomSection, DataSection, ElementSection, Elements, EntityType,
+        ExportKind, ExportSection, Function, FunctionSection, GlobalSection, ImportSection,
+        Instruction as Ins, MemorySection, MemoryType, Module, NameMap, NameSection, RefType,
+        TableSection, TableType, TypeSection,
+    },
+    wasmparser::{
+        DataKind, Encoding, ExternalKind, FuncType, GlobalType, KnownCustom, Linking, Operator,
+        Parser, Payload, RelocationEntry, RelocationType, SegmentFlags, SymbolFlags, SymbolInfo,
+        TypeRef,
+    },
+};
+
+const PAGE_SIZE_BYTES: u64 = 65536;
+const STACK_ALIGNMENT_BYTES: u64 = 16;
+
+/// The contents of a function body or of a data segment, along with the relocations which apply to it.
+///
+/// The offsets of the relocations are relative to the start of `bytes`, and sorted.
+struct Chunk<'a> {
+    bytes: &'a [u8],
+    relocs: Vec<RelocationEntry>,
+}
+
+/// A data segment of an object file.
+struct Segment<'a> {
+    name: &'a str,
+    /// The alignment of the segment, as a power of two
+    alignment: u32,
+    chunk: Chunk<'a>,
+}
+
+/// A parsed relocatable object file.
+struct Object<'a> {
+    name: &'a str,
+    /// Whether this object is part of a library, and should only be linked if any of its symbols are needed
+    library: bool,
+    types: Vec<FuncType>,
+    /// The `(module, name, type)` triple of each imported function
+    func_imports: Vec<(&'a str, &'a str, u32)>,
+    /// The `(module, name, type)` triple of each imported global
+    global_imports: Vec<(&'a str, &'a str, GlobalType)>,
+    table_imports: Vec<&'a str>,
+    /// The type index of each defined function
+    functions: Vec<u32>,
+    /// The type and the initial value of each defined global
+    globals: Vec<(GlobalType, Operator<'a>)>,
+    exports: Vec<(&'a str, ExternalKind, u32)>,
+    bodies: Vec<Chunk<'a>>,
+    segments: Vec<Segment<'a>>,
+    symbols: Vec<SymbolInfo<'a>>,
+    /// The `(priority, symbol index)` pair of each constructor
+    init_funcs: Vec<(u32, u32)>,
+    /// Any `component-type*` custom sections, to be carried over to the linked module
+    component_types: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> Object<'a> {
+    fn parse(name: &'a str, wasm: &'a [u8], library: bool) -> Result<Self> {
+        let mut object = Object {
+            name,
+            library,
+            types: Vec::new(),
+            func_imports: Vec::new(),
+            global_imports: Vec::new(),
+            table_imports: Vec::new(),
+            functions: Vec::new(),
+            globals: Vec::new(),
+            exports: Vec::new(),
+            bodies: Vec::new(),
+            segments: Vec::new(),
+            symbols: Vec::new(),
+            init_funcs: Vec::new(),
+            component_types: Vec::new(),
+        };
+
+        // The section index and contents offset of the code and data sections, and the offset of each function
+        // body and data segment within their section, used to find out what relocations apply to.
+        let mut section_index = 0;
+        let mut code = None;
+        let mut data = None;
+        let mut bodies = Vec::new();
+        let mut segments = Vec::new();
+        let mut segment_info = Vec::new();
+        let mut relocs = Vec::new();
+        let mut linking = false;
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload?;
+            let section = payload.as_section().map(|(id, _)| id);
+            match payload {
+                Payload::Version { encoding, .. } if encoding != Encoding::Module => {
+                    bail!("components are not relocatable object files")
+                }
+                Payload::TypeSection(s) => {
+                    for ty in s.into_iter_err_on_gc_types() {
+                        object.types.push(ty?);
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        let import = import?;
+                        if import.module.starts_with("GOT.") {
+                            bail!("position-independent code is not supported");
+                        }
+                        match import.ty {
+                            TypeRef::Func(ty) => {
+                                object.func_imports.push((import.module, import.name, ty))
+                            }
+                            TypeRef::Global(ty) => {
+                                object.global_imports.push((import.module, import.name, ty))
+                            }
+                            TypeRef::Table(_) => object.table_imports.push(import.name),
+                            TypeRef::Memory(ty) => {
+                                if ty.memory64 {
+                                    bail!("64-bit memories are not supported");
+                                }
+                            }
+                            TypeRef::Tag(_) => bail!("exception tags are not supported"),
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        object.functions.push(ty?);
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        let global = global?;
+                        let mut ops = global.init_expr.get_operators_reader();
+                        let init = ops.read()?;
+                        match (&init, ops.read()?) {
+                            (
+                                Operator::I32Const { .. }
+                                | Operator::I64Const { .. }
+                                | Operator::F32Const { .. }
+                                | Operator::F64Const { .. },
+                                Operator::End,
+                            ) => object.globals.push((global.ty, init)),
+                            _ => bail!("only constant global initializers are supported"),
+                        }
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        object
+                            .exports
+                            .push((export.name, export.kind, export.index));
+                    }
+                }
+                Payload::CodeSectionStart { range, .. } => {
+                    code = Some((section_index, range.start))
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let range = body.range();
+                    bodies.push((range.start - code.unwrap().1, &wasm[range]));
+                }
+                Payload::DataSection(s) => {
+                    let start = s.range().start;
+                    data = Some(section_index);
+                    for segment in s {
+                        let segment = segment?;
+                        if let DataKind::Passive = segment.kind {
+                            bail!("passive data segments are not supported");
+                        }
+                        let offset = segment.range.end - segment.data.len() - start;
+                        segments.push((offset, segment.data));
+                    }
+                }
+                Payload::TableSection(_)
+                | Payload::MemorySection(_)
+                | Payload::TagSection(_)
+                | Payload::ElementSection(_)
+                | Payload::StartSection { .. } => {
+                    bail!(
+                        "section with id {} is not supported in relocatable object files",
+                        section.unwrap()
+                    )
+                }
+                Payload::CustomSection(c) => match c.as_known() {
+                    KnownCustom::Linking(reader) => {
+                        linking = true;
+                        for subsection in reader.subsections() {
+                            match subsection? {
+                                Linking::SymbolTable(map) => {
+                                    for symbol in map {
+                                        object.symbols.push(symbol?);
+                                    }
+                                }
+                                Linking::SegmentInfo(map) => {
+                                    for segment in map {
+                                        segment_info.push(segment?);
+                                    }
+                                }
+                                Linking::InitFuncs(map) => {
+                                    for func in map {
+                                        let func = func?;
+                                        object.init_funcs.push((func.priority, func.symbol_index));
+                                    }
+                                }
+                                Linking::ComdatInfo(map) => {
+                                    if map.count() > 0 {
+                                        bail!("COMDATs are not supported");
+                                    }
+                                }
+                                Linking::Unknown { .. } => {}
+                            }
+                        }
+                    }
+                    KnownCustom::Reloc(reader) => {
+                        let entries = reader
+                            .entries()
+                            .into_iter()
+                            .collect::<Result<Vec<_>, _>>()?;
+                        relocs.push((reader.section_index(), entries));
+                    }
+                    _ if c.name().starts_with("component-type") => {
+                        object.component_types.push((c.name(), c.data()));
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+            if section.is_some() {
+                section_index += 1;
+            }
+        }
+
+        if !linking {
+            bail!("not a relocatable object file: missing `linking` custom section");
+        }
+
+        if object.symbols.iter().any(|symbol| {
+            let flags = symbol_flags(symbol);
+            flags.contains(SymbolFlags::TLS)
+        }) || segment_info
+            .iter()
+            .any(|segment| segment.flags.contains(SegmentFlags::TLS))
+        {
+            bail!("thread-local storage is not supported");
+        }
+
+        let mut code_relocs = Vec::new();
+        let mut data_relocs = Vec::new();
+        for (section, entries) in relocs {
+            if Some(section) == code.map(|(index, _)| index) {
+                code_relocs = entries;
+            } else if Some(section) == data {
+                data_relocs = entries;
+            }
+            // Relocations of other sections only apply to debugging information, which isn't linked.
+        }
+
+        object.bodies = chunks(&bodies, code_relocs).context("invalid code relocations")?;
+        object.segments = chunks(&segments, data_relocs)
+            .context("invalid data relocations")?
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| Segment {
+                name: segment_info.get(i).map(|info| info.name).unwrap_or(""),
+                alignment: segment_info.get(i).map(|info| info.alignment).unwrap_or(0),
+                chunk,
+            })
+            .collect();
+
+        Ok(object)
+    }
+
+    fn symbol(&self, index: u32) -> Result<&SymbolInfo<'a>> {
+        match self.symbols.get(index as usize) {
+            Some(symbol) => Ok(symbol),
+            None => bail!("symbol index {index} out of bounds in `{}`", self.name),
+        }
+    }
+
+    /// Returns the name of `symbol`, which defaults to the name of the import for undefined symbols.
+    fn symbol_name(&self, symbol: &SymbolInfo<'a>) -> Option<&'a str> {
+        match *symbol {
+            SymbolInfo::Func { name, index, .. } => name.or_else(|| {
+                let (_, name, _) = self.func_imports.get(index as usize)?;
+                Some(*name)
+            }),
+            SymbolInfo::Global { name, index, .. } => name.or_else(|| {
+                let (_, name, _) = self.global_imports.get(index as usize)?;
+                Some(*name)
+            }),
+            SymbolInfo::Table { name, index, .. } => {
+                name.or_else(|| self.table_imports.get(index as usize).copied())
+            }
+            SymbolInfo::Data { name, .. } => Some(name),
+            SymbolInfo::Event { name, .. } => name,
+            SymbolInfo::Section { .. } => None,
+        }
+    }
+}
+
+fn symbol_flags(symbol: &SymbolInfo<'_>) -> SymbolFlags {
+    match *symbol {
+        SymbolInfo::Func { flags, .. }
+        | SymbolInfo::Data { flags, .. }
+        | SymbolInfo::Global { flags, .. }
+        | SymbolInfo::Section { flags, .. }
+        | SymbolInfo::Event { flags, .. }
+        | SymbolInfo::Table { flags, .. } => flags,
+    }
+}
+
+/// Assigns each relocation to the item, among `items` sorted by their offset within a section, containing it.
+fn chunks<'a>(items: &[(usize, &'a [u8])], relocs: Vec<RelocationEntry>) -> Result<Vec<Chunk<'a>>> {
+    let mut chunks = items
+        .iter()
+        .map(|(_, bytes)| Chunk {
+            bytes,
+            relocs: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    for mut reloc in relocs {
+        let offset = reloc.offset as usize;
+        let i = items.partition_point(|(start, _)| *start <= offset);
+        match i.checked_sub(1) {
+            Some(i) if offset < items[i].0 + items[i].1.len() => {
+                reloc.offset = (offset - items[i].0) as u32;
+                chunks[i].relocs.push(reloc);
+            }
+            _ => bail!("relocation at offset {offset} is not within a function or segment"),
+        }
+    }
+    for chunk in chunks.iter_mut() {
+        chunk.relocs.sort_by_key(|reloc| reloc.offset);
+    }
+    Ok(chunks)
+}
+
+/// Refers to a symbol by the index of its object and its index in the symbol table of that object.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct SymbolRef {
+    object: usize,
+    index: u32,
+}
+
+/// Something defined by the linker rather than by an object.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Synthetic {
+    StackPointer,
+    CallCtors,
+    HeapBase,
+    DataEnd,
+    FunctionTable,
+}
+
+/// What a symbol resolves to.
+///
+/// Defined functions, globals and segments are referred to by the index of their object and their index among
+/// the definitions of that object.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Target {
+    Func(usize, u32),
+    Global(usize, u32),
+    Data {
+        object: usize,
+        segment: u32,
+        offset: u32,
+    },
+    ImportedFunc(usize, u32),
+    ImportedGlobal(usize, u32),
+    Synthetic(Synthetic),
+    /// The address of an undefined weak data symbol
+    Null,
+}
+
+/// Something which is included in the linked module if it's live.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Node {
+    Func(usize, u32),
+    Global(usize, u32),
+    Segment(usize, u32),
+    ImportedFunc(usize, u32),
+    ImportedGlobal(usize, u32),
+    Synthetic(Synthetic),
+}
+
+impl Node {
+    fn new(target: Target) -> Option<Node> {
+        Some(match target {
+            Target::Func(object, index) => Node::Func(object, index),
+            Target::Global(object, index) => Node::Global(object, index),
+            Target::Data {
+                object, segment, ..
+            } => Node::Segment(object, segment),
+            Target::ImportedFunc(object, index) => Node::ImportedFunc(object, index),
+            Target::ImportedGlobal(object, index) => Node::ImportedGlobal(object, index),
+            Target::Synthetic(synthetic) => Node::Synthetic(synthetic),
+            Target::Null => return None,
+        })
+    }
+
+    fn object(&self) -> Option<usize> {
+        match *self {
+            Node::Func(object, _)
+            | Node::Global(object, _)
+            | Node::Segment(object, _)
+            | Node::ImportedFunc(object, _)
+            | Node::ImportedGlobal(object, _) => Some(object),
+            Node::Synthetic(_) => None,
+        }
+    }
+}
+
+/// The state of linking a set of objects into a module.
+struct Link<'a> {
+    objects: Vec<Object<'a>>,
+    /// The definition of each non-local symbol which is defined by some object
+    symbols: HashMap<&'a str, SymbolRef>,
+    live: IndexSet<Node>,
+    worklist: Vec<Node>,
+    /// Whether each object is linked, which is always the case for objects which aren't part of a library
+    linked: Vec<bool>,
+
+    types: IndexSet<FuncType>,
+    func_imports: IndexMap<(&'a str, &'a str), u32>,
+    global_imports: IndexMap<(&'a str, &'a str), GlobalType>,
+    funcs: HashMap<Node, u32>,
+    globals: HashMap<Node, u32>,
+    segments: HashMap<(usize, u32), u32>,
+    stack_end: u32,
+    data_end: u32,
+    heap_base: u32,
+    /// The functions in the function table, whose slots start at 1
+    table: IndexSet<u32>,
+}
+
+impl<'a> Link<'a> {
+    fn new(objects: Vec<Object<'a>>) -> Result<Self> {
+        let mut symbols = HashMap::<&str, SymbolRef>::new();
+        for (object_index, object) in objects.iter().enumerate() {
+            for (index, symbol) in object.symbols.iter().enumerate() {
+                let flags = symbol_flags(symbol);
+                if let SymbolInfo::Event { .. } = symbol {
+                    bail!("exception tags are not supported");
+                }
+                if flags.intersects(SymbolFlags::UNDEFINED | SymbolFlags::BINDING_LOCAL) {
+                    continue;
+                }
+                let Some(name) = object.symbol_name(symbol) else {
+                    continue;
+                };
+                let new = SymbolRef {
+                    object: object_index,
+                    index: index as u32,
+                };
+                match symbols.entry(name) {
+                    hash_map::Entry::Vacant(e) => {
+                        e.insert(new);
+                    }
+                    hash_map::Entry::Occupied(mut e) => {
+                        let old = *e.get();
+                        let old_object = &objects[old.object];
+                        let old_symbol = &old_object.symbols[old.index as usize];
+                        if std::mem::discriminant(old_symbol) != std::mem::discriminant(symbol) {
+                            bail!(
+                                "symbol `{name}` has different kinds in `{}` and `{}`",
+                                old_object.name,
+                                object.name
+                            );
+                        }
+                        let old_weak = symbol_flags(old_symbol).contains(SymbolFlags::BINDING_WEAK);
+                        let new_weak = flags.contains(SymbolFlags::BINDING_WEAK);
+                        // Like members of archives, definitions of libraries don't override definitions of the
+                        // main object, and the first strong definition of a library wins.
+                        if !object.library && !old_weak && !new_weak {
+                            bail!(
+                                "duplicate definitions of symbol `{name}` in `{}` and `{}`",
+                                old_object.name,
+                                object.name
+                            );
+                        }
+                        if old_weak && !new_weak && (object.library == old_object.library) {
+                            e.insert(new);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Link {
+            linked: objects.iter().map(|object| !object.library).collect(),
+            objects,
+            symbols,
+            live: IndexSet::new(),
+            worklist: Vec::new(),
+            types: IndexSet::new(),
+            func_imports: IndexMap::new(),
+            global_imports: IndexMap::new(),
+            funcs: HashMap::new(),
+            globals: HashMap::new(),
+            segments: HashMap::new(),
+            stack_end: 0,
+            data_end: 0,
+            heap_base: 0,
+            table: IndexSet::new(),
+        })
+    }
+
+    /// Resolves the symbol `symbol` to its definition.
+    fn resolve(&self, symbol: SymbolRef) -> Result<Target> {
+        let object = &self.objects[symbol.object];
+        let info = object.symbol(symbol.index)?;
+        let name = object.symbol_name(info);
+        let definition = if symbol_flags(info).contains(SymbolFlags::BINDING_LOCAL) {
+            symbol
+        } else {
+            name.and_then(|name| self.symbols.get(name).copied())
+                .unwrap_or(symbol)
+        };
+
+        let object = &self.objects[definition.object];
+        let info = object.symbol(definition.index)?;
+        let flags = symbol_flags(info);
+        if !flags.contains(SymbolFlags::UNDEFINED) {
+            return Ok(match *info {
+                SymbolInfo::Func { index, .. } => {
+                    Target::Func(definition.object, index - object.func_imports.len() as u32)
+                }
+                SymbolInfo::Global { index, .. } => Target::Global(
+                    definition.object,
+                    index - object.global_imports.len() as u32,
+                ),
+                SymbolInfo::Data {
+                    symbol: Some(data), ..
+                } => Target::Data {
+                    object: definition.object,
+                    segment: data.index,
+                    offset: data.offset,
+                },
+                _ => bail!(
+                    "unsupported definition of symbol `{}` in `{}`",
+                    name.unwrap_or("<unnamed>"),
+                    object.name
+                ),
+            });
+        }
+
+        let name = name.unwrap_or("<unnamed>");
+        Ok(match (info, name) {
+            (SymbolInfo::Global { .. }, "__stack_pointer") => {
+                Target::Synthetic(Synthetic::StackPointer)
+            }
+            (SymbolInfo::Func { .. }, "__wasm_call_ctors") => {
+                Target::Synthetic(Synthetic::CallCtors)
+            }
+            (SymbolInfo::Data { .. }, "__heap_base") => Target::Synthetic(Synthetic::HeapBase),
+            (SymbolInfo::Data { .. }, "__data_end") => Target::Synthetic(Synthetic::DataEnd),
+            (SymbolInfo::Table { .. }, "__indirect_function_table") => {
+                Target::Synthetic(Synthetic::FunctionTable)
+            }
+            (SymbolInfo::Func { index, .. }, _) => Target::ImportedFunc(definition.object, *index),
+            (SymbolInfo::Global { index, .. }, _) => {
+                Target::ImportedGlobal(definition.object, *index)
+            }
+            (SymbolInfo::Data { .. }, _) if flags.contains(SymbolFlags::BINDING_WEAK) => {
+                Target::Null
+            }
+            _ => bail!("undefined symbol `{name}` in `{}`", object.name),
+        })
+    }
+
+    fn mark(&mut self, target: Target) {
+        if let Some(node) = Node::new(target) {
+            if self.live.insert(node) {
+                self.worklist.push(node);
+            }
+        }
+    }
+
+    fn mark_symbol(&mut self, object: usize, index: u32) -> Result<()> {
+        let target = self.resolve(SymbolRef { object, index })?;
+        self.mark(target);
+        Ok(())
+    }
+
+    /// Marks everything referenced by `relocs` of `object` as live.
+    fn mark_relocs(&mut self, object: usize, relocs: &[RelocationEntry]) -> Result<()> {
+        for reloc in relocs {
+            if reloc.ty != RelocationType::TypeIndexLeb {
+                self.mark_symbol(object, reloc.index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the roots of `object` as live: its exports, and the symbols it requires to be retained.
+    fn mark_roots(&mut self, object: usize) -> Result<()> {
+        let o = &self.objects[object];
+        let mut roots = Vec::new();
+        for (index, symbol) in o.symbols.iter().enumerate() {
+            let flags = symbol_flags(symbol);
+            if !flags.contains(SymbolFlags::UNDEFINED)
+                && flags.intersects(SymbolFlags::EXPORTED | SymbolFlags::NO_STRIP)
+            {
+                roots.push(index as u32);
+            }
+        }
+        let exports = o
+            .exports
+            .iter()
+            .filter_map(|(_, kind, index)| match kind {
+                ExternalKind::Func => Some(Target::Func(
+                    object,
+                    index.checked_sub(o.func_imports.len() as u32)?,
+                )),
+                ExternalKind::Global => Some(Target::Global(
+                    object,
+                    index.checked_sub(o.global_imports.len() as u32)?,
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for index in roots {
+            self.mark_symbol(object, index)?;
+        }
+        for target in exports {
+            self.mark(target);
+        }
+        Ok(())
+    }
+
+    /// Finds out which objects, functions, globals and segments are live, i.e. transitively needed by the roots
+    /// of the objects which aren't part of a library.
+    fn gc(&mut self) -> Result<()> {
+        let mut roots_marked = vec![false; self.objects.len()];
+        let mut ctors_marked = vec![false; self.objects.len()];
+        for entry in ["_start", "_initialize"] {
+            if let Some(symbol) = self.symbols.get(entry).copied() {
+                self.mark_symbol(symbol.object, symbol.index)?;
+            }
+        }
+
+        loop {
+            while let Some(node) = self.worklist.pop() {
+                if let Some(object) = node.object() {
+                    self.linked[object] = true;
+                }
+                let relocs = match node {
+                    Node::Func(object, index) => {
+                        match self.objects[object].bodies.get(index as usize) {
+                            Some(body) => body.relocs.clone(),
+                            None => bail!(
+                                "function {index} of `{}` has no body",
+                                self.objects[object].name
+                            ),
+                        }
+                    }
+                    Node::Segment(object, index) => {
+                        match self.objects[object].segments.get(index as usize) {
+                            Some(segment) => segment.chunk.relocs.clone(),
+                            None => bail!(
+                                "data segment index {index} out of bounds in `{}`",
+                                self.objects[object].name
+                            ),
+                        }
+                    }
+                    _ => continue,
+                };
+                self.mark_relocs(node.object().unwrap(), &relocs)?;
+            }
+
+            let call_ctors = self.live.contains(&Node::Synthetic(Synthetic::CallCtors));
+            let mut changed = false;
+            for object in 0..self.objects.len() {
+                if !self.linked[object] {
+                    continue;
+                }
+                if !roots_marked[object] {
+                    roots_marked[object] = true;
+                    self.mark_roots(object)?;
+                    changed = true;
+                }
+                if call_ctors && !ctors_marked[object] {
+                    ctors_marked[object] = true;
+                    for (_, symbol) in self.objects[object].init_funcs.clone() {
+                        self.mark_symbol(object, symbol)?;
+                    }
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Assigns an index to each live function and global, and an address to each live data segment.
+    fn layout(&mut self, stack_size: u32) -> Result<()> {
+        // Imports of the same function or global by different objects are merged.
+        for (object_index, object) in self.objects.iter().enumerate() {
+            for (index, (module, name, ty)) in object.func_imports.iter().enumerate() {
+                if !self
+                    .live
+                    .contains(&Node::ImportedFunc(object_index, index as u32))
+                {
+                    continue;
+                }
+                let ty = match object.types.get(*ty as usize) {
+                    Some(ty) => self.types.insert_full(ty.clone()).0 as u32,
+                    None => bail!("type index {ty} out of bounds in `{}`", object.name),
+                };
+                match self.func_imports.entry((module, name)) {
+                    Entry::Vacant(e) => {
+                        e.insert(ty);
+                    }
+                    Entry::Occupied(e) => {
+                        if *e.get() != ty {
+                            bail!("function `{module}::{name}` is imported with different types");
+                        }
+                    }
+                }
+            }
+            for (index, (module, name, ty)) in object.global_imports.iter().enumerate() {
+                if !self
+                    .live
+                    .contains(&Node::ImportedGlobal(object_index, index as u32))
+                {
+                    continue;
+                }
+                match self.global_imports.entry((module, name)) {
+                    Entry::Vacant(e) => {
+                        e.insert(*ty);
+                    }
+                    Entry::Occupied(e) => {
+                        if e.get() != ty {
+                            bail!("global `{module}::{name}` is imported with different types");
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut funcs = self.func_imports.len() as u32;
+        let mut globals = self.global_imports.len() as u32;
+        if self
+            .live
+            .contains(&Node::Synthetic(Synthetic::StackPointer))
+        {
+            self.globals
+                .insert(Node::Synthetic(Synthetic::StackPointer), globals);
+            globals += 1;
+        }
+        let mut address = u64::from(stack_size).next_multiple_of(STACK_ALIGNMENT_BYTES);
+        self.stack_end = address as u32;
+        for (object_index, object) in self.objects.iter().enumerate() {
+            for (index, (module, name, _)) in object.func_imports.iter().enumerate() {
+                let import = self.func_imports.get_index_of(&(*module, *name));
+                if let Some(import) = import {
+                    self.funcs.insert(
+                        Node::ImportedFunc(object_index, index as u32),
+                        import as u32,
+                    );
+                }
+            }
+            for (index, (module, name, _)) in object.global_imports.iter().enumerate() {
+                let import = self.global_imports.get_index_of(&(*module, *name));
+                if let Some(import) = import {
+                    self.globals.insert(
+                        Node::ImportedGlobal(object_index, index as u32),
+                        import as u32,
+                    );
+                }
+            }
+            for index in 0..object.functions.len() as u32 {
+                let node = Node::Func(object_index, index);
+                if self.live.contains(&node) {
+                    self.funcs.insert(node, funcs);
+                    funcs += 1;
+                }
+            }
+            for index in 0..object.globals.len() as u32 {
+                let node = Node::Global(object_index, index);
+                if self.live.contains(&node) {
+                    self.globals.insert(node, globals);
+                    globals += 1;
+                }
+            }
+            for (index, segment) in object.segments.iter().enumerate() {
+                if !self
+                    .live
+                    .contains(&Node::Segment(object_index, index as u32))
+                {
+                    continue;
+                }
+                address = address.next_multiple_of(1 << segment.alignment.min(31));
+                self.segments
+                    .insert((object_index, index as u32), address as u32);
+                address += segment.chunk.bytes.len() as u64;
+            }
+        }
+        if self.live.contains(&Node::Synthetic(Synthetic::CallCtors)) {
+            self.funcs
+                .insert(Node::Synthetic(Synthetic::CallCtors), funcs);
+        }
+
+        let heap_base = address.next_multiple_of(STACK_ALIGNMENT_BYTES);
+        self.data_end = u32::try_from(address).context("data does not fit in memory")?;
+        self.heap_base = u32::try_from(heap_base).context("data does not fit in memory")?;
+        Ok(())
+    }
+
+    fn func_index(&self, target: Target) -> Result<u32> {
+        match Node::new(target).and_then(|node| self.funcs.get(&node)) {
+            Some(index) => Ok(*index),
+            None => bail!("relocation of a function index refers to something else"),
+        }
+    }
+
+    fn global_index(&self, target: Target) -> Result<u32> {
+        match Node::new(target).and_then(|node| self.globals.get(&node)) {
+            Some(index) => Ok(*index),
+            None => bail!("relocation of a global index refers to something else"),
+        }
+    }
+
+    fn address(&self, target: Target) -> Result<u32> {
+        Ok(match target {
+            Target::Data {
+                object,
+                segment,
+                offset,
+            } => self.segments[&(object, segment)] + offset,
+            Target::Synthetic(Synthetic::HeapBase) => self.heap_base,
+            Target::Synthetic(Synthetic::DataEnd) => self.data_end,
+            Target::Null => 0,
+            _ => bail!("relocation of a memory address refers to something else"),
+        })
+    }
+
+    /// Returns a copy of `chunk` of `object` with its relocations applied.
+    fn relocate(&mut self, object: usize, chunk: &Chunk<'_>) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(chunk.bytes.len());
+        let mut pos = 0;
+        for reloc in chunk.relocs.iter() {
+            let offset = reloc.offset as usize;
+            if offset < pos {
+                bail!("overlapping relocations at offset {offset}");
+            }
+            bytes.extend_from_slice(&chunk.bytes[pos..offset]);
+
+            let target = || {
+                self.resolve(SymbolRef {
+                    object,
+                    index: reloc.index,
+                })
+            };
+            let (value, encoding): (i64, RelocEncoding) = match reloc.ty {
+                RelocationType::FunctionIndexLeb => {
+                    (self.func_index(target()?)?.into(), RelocEncoding::Unsigned)
+                }
+                RelocationType::FunctionIndexI32 => {
+                    (self.func_index(target()?)?.into(), RelocEncoding::Fixed)
+                }
+                RelocationType::GlobalIndexLeb => (
+                    self.global_index(target()?)?.into(),
+                    RelocEncoding::Unsigned,
+                ),
+                RelocationType::GlobalIndexI32 => {
+                    (self.global_index(target()?)?.into(), RelocEncoding::Fixed)
+                }
+                RelocationType::TableIndexSleb | RelocationType::TableIndexI32 => {
+                    let func = self.func_index(target()?)?;
+                    let slot = self.table.insert_full(func).0 as i64 + 1;
+                    let encoding = match reloc.ty {
+                        RelocationType::TableIndexSleb => RelocEncoding::Signed,
+                        _ => RelocEncoding::Fixed,
+                    };
+                    (slot, encoding)
+                }
+                RelocationType::MemoryAddrLeb
+                | RelocationType::MemoryAddrSleb
+                | RelocationType::MemoryAddrI32 => {
+                    let address = self.address(target()?)?.wrapping_add(reloc.addend as u32);
+                    match reloc.ty {
+                        RelocationType::MemoryAddrLeb => (address.into(), RelocEncoding::Unsigned),
+                        RelocationType::MemoryAddrSleb => {
+                            ((address as i32).into(), RelocEncoding::Signed)
+                        }
+                        _ => (address.into(), RelocEncoding::Fixed),
+                    }
+                }
+                RelocationType::TypeIndexLeb => {
+                    let o = &self.objects[object];
+                    let Some(ty) = o.types.get(reloc.index as usize) else {
+                        bail!("type index {} out of bounds in `{}`", reloc.index, o.name);
+                    };
+                    let ty = self.types.insert_full(ty.clone()).0;
+                    (ty as i64, RelocEncoding::Unsigned)
+                }
+                RelocationType::TableNumberLeb => match target()? {
+                    Target::Synthetic(Synthetic::FunctionTable) => (0, RelocEncoding::Unsigned),
+                    _ => bail!("relocation of a table number refers to something else"),
+                },
+                ty => bail!("unsupported relocation type {ty:?}"),
+            };
+
+            pos = offset + encoding.write(value, &chunk.bytes[offset..], &mut bytes)?;
+        }
+        bytes.extend_from_slice(&chunk.bytes[pos..]);
+        Ok(bytes)
+    }
+
+    /// Encodes the linked module.
+    fn encode(mut self, stack_size: u32) -> Result<Vec<u8>> {
+        self.gc()?;
+        self.layout(stack_size)?;
+
+        let mut code = CodeSection::new();
+        let mut data = DataSection::new();
+        let mut functions = Vec::new();
+        let mut function_names = Vec::new();
+        let mut globals = GlobalSection::new();
+        let mut reencoder = RoundtripReencoder;
+        if self
+            .live
+            .contains(&Node::Synthetic(Synthetic::StackPointer))
+        {
+            // The stack grows down from its end towards address 0.
+            globals.global(
+                wasm_encoder::GlobalType {
+                    val_type: wasm_encoder::ValType::I32,
+                    mutable: true,
+                    shared: false,
+                },
+                &ConstExpr::i32_const(self.stack_end as i32),
+            );
+        }
+        for object in 0..self.objects.len() {
+            for index in 0..self.objects[object].functions.len() {
+                if !self.live.contains(&Node::Func(object, index as u32)) {
+                    continue;
+                }
+                let o = &self.objects[object];
+                let ty = o.functions[index];
+                let Some(ty) = o.types.get(ty as usize).cloned() else {
+                    bail!("type index {ty} out of bounds in `{}`", o.name);
+                };
+                let body = &o.bodies[index];
+                let body = Chunk {
+                    bytes: body.bytes,
+                    relocs: body.relocs.clone(),
+                };
+                code.raw(&self.relocate(object, &body)?);
+                functions.push(self.types.insert_full(ty).0 as u32);
+            }
+            let o = &self.objects[object];
+            for (index, (ty, init)) in o.globals.iter().enumerate() {
+                if !self.live.contains(&Node::Global(object, index as u32)) {
+                    continue;
+                }
+                let init = match *init {
+                    Operator::I32Const { value } => ConstExpr::i32_const(value),
+                    Operator::I64Const { value } => ConstExpr::i64_const(value),
+                    Operator::F32Const { value } => {
+                        ConstExpr::f32_const(f32::from_bits(value.bits()))
+                    }
+                    Operator::F64Const { value } => {
+                        ConstExpr::f64_const(f64::from_bits(value.bits()))
+                    }
+                    _ => unreachable!(),
+                };
+                globals.global(reencoder.global_type(*ty)?, &init);
+            }
+            for index in 0..o.segments.len() {
+                if !self.live.contains(&Node::Segment(object, index as u32)) {
+                    continue;
+                }
+                let o = &self.objects[object];
+                let segment = &o.segments[index];
+                // Zero-initialized segments don't need to be initialized, since memory starts zeroed.
+                if segment.name.starts_with(".bss") && segment.chunk.bytes.iter().all(|b| *b == 0) {
+                    continue;
+                }
+                let address = self.segments[&(object, index as u32)];
+                let chunk = Chunk {
+                    bytes: segment.chunk.bytes,
+                    relocs: segment.chunk.relocs.clone(),
+                };
+                let bytes = self.relocate(object, &chunk)?;
+                data.active(0, &ConstExpr::i32_const(address as i32), bytes);
+            }
+        }
+
+        // Names of functions defined by objects, taken from the symbols which define them.
+        for (object_index, object) in self.objects.iter().enumerate() {
+            for symbol in object.symbols.iter() {
+                if let SymbolInfo::Func {
+                    index,
+                    name: Some(name),
+                    flags,
+                } = *symbol
+                {
+                    if flags.contains(SymbolFlags::UNDEFINED) {
+                        continue;
+                    }
+                    let node = Node::Func(object_index, index - object.func_imports.len() as u32);
+                    if let Some(index) = self.funcs.get(&node) {
+                        function_names.push((*index, name));
+                    }
+                }
+            }
+        }
+
+        if let Some(index) = self
+            .funcs
+            .get(&Node::Synthetic(Synthetic::CallCtors))
+            .copied()
+        {
+            let mut ctors = Vec::new();
+            for (object, o) in self.objects.iter().enumerate() {
+                if self.linked[object] {
+                    ctors.extend(
+                        o.init_funcs
+                            .iter()
+                            .map(|(priority, symbol)| (*priority, object, *symbol)),
+                    );
+                }
+            }
+            ctors.sort_by_key(|(priority, ..)| *priority);
+            let mut function = Function::new([]);
+            for (_, object, symbol) in ctors {
+                let func = self.resolve(SymbolRef {
+                    object,
+                    index: symbol,
+                })?;
+                function.instruction(&Ins::Call(self.func_index(func)?));
+            }
+            function.instruction(&Ins::End);
+            code.function(&function);
+            functions.push(self.types.insert_full(FuncType::new([], [])).0 as u32);
+            function_names.push((index, "__wasm_call_ctors"));
+        }
+
+        let mut exports = IndexMap::new();
+        exports.insert("memory", (ExportKind::Memory, 0));
+        let mut export = |name: &'a str, kind: ExportKind, index: u32| match exports.entry(name) {
+            Entry::Vacant(e) => {
+                e.insert((kind, index));
+                Ok(())
+            }
+            Entry::Occupied(e) if *e.get() == (kind, index) => Ok(()),
+            Entry::Occupied(_) => bail!("duplicate export `{name}`"),
+        };
+        let mut exported = Vec::new();
+        for (object_index, object) in self.objects.iter().enumerate() {
+            if !self.linked[object_index] {
+                continue;
+            }
+            for (name, kind, index) in object.exports.iter().copied() {
+                let (kind, node, map) = match kind {
+                    ExternalKind::Func => (
+                        ExportKind::Func,
+                        Node::Func(
+                            object_index,
+                            index.wrapping_sub(object.func_imports.len() as u32),
+                        ),
+                        &self.funcs,
+                    ),
+                    ExternalKind::Global => (
+                        ExportKind::Global,
+                        Node::Global(
+                            object_index,
+                            index.wrapping_sub(object.global_imports.len() as u32),
+                        ),
+                        &self.globals,
+                    ),
+                    _ => continue,
+                };
+                if let Some(index) = map.get(&node) {
+                    export(name, kind, *index)?;
+                    exported.push(node);
+                }
+            }
+        }
+        for (object_index, object) in self.objects.iter().enumerate() {
+            if !self.linked[object_index] {
+                continue;
+            }
+            for (index, symbol) in object.symbols.iter().enumerate() {
+                let flags = symbol_flags(symbol);
+                if !flags.contains(SymbolFlags::EXPORTED) || flags.contains(SymbolFlags::UNDEFINED)
+                {
+                    continue;
+                }
+                let Some(name) = object.symbol_name(symbol) else {
+                    continue;
+                };
+                let target = self.resolve(SymbolRef {
+                    object: object_index,
+                    index: index as u32,
+                })?;
+                let (kind, map) = match target {
+                    Target::Func(..) => (ExportKind::Func, &self.funcs),
+                    Target::Global(..) => (ExportKind::Global, &self.globals),
+                    _ => continue,
+                };
+                let node = Node::new(target).unwrap();
+                if exported.contains(&node) {
+                    continue;
+                }
+                export(name, kind, map[&node])?;
+            }
+        }
+        for entry in ["_start", "_initialize"] {
+            if let Some(symbol) = self.symbols.get(entry).copied() {
+                if let Target::Func(object, index) = self.resolve(symbol)? {
+                    export(
+                        entry,
+                        ExportKind::Func,
+                        self.funcs[&Node::Func(object, index)],
+                    )?;
+                }
+            }
+        }
+
+        let mut module = Module::new();
+
+        let mut types = TypeSection::new();
+        for ty in self.types.iter() {
+            types.ty().func_type(&reencoder.func_type(ty.clone())?);
+        }
+        module.section(&types);
+
+        let mut imports = ImportSection::new();
+        for ((module, name), ty) in self.func_imports.iter() {
+            imports.import(module, name, EntityType::Function(*ty));
+        }
+        for ((module, name), ty) in self.global_imports.iter() {
+            imports.import(
+                module,
+                name,
+                EntityType::Global(reencoder.global_type(*ty)?),
+            );
+        }
+        if !imports.is_empty() {
+            module.section(&imports);
+        }
+
+        let mut function_section = FunctionSection::new();
+        for ty in functions {
+            function_section.function(ty);
+        }
+        module.section(&function_section);
+
+        let table = !self.table.is_empty()
+            || self
+                .live
+                .contains(&Node::Synthetic(Synthetic::FunctionTable));
+        if table {
+            let size = self.table.len() as u64 + 1;
+            let mut tables = TableSection::new();
+            tables.table(TableType {
+                element_type: RefType::FUNCREF,
+                table64: false,
+                minimum: size,
+                maximum: Some(size),
+                shared: false,
+            });
+            module.section(&tables);
+        }
+
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: u64::from(self.heap_base).div_ceil(PAGE_SIZE_BYTES),
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        module.section(&memories);
+
+        if !globals.is_empty() {
+            module.section(&globals);
+        }
+
+        let mut export_section = ExportSection::new();
+        for (name, (kind, index)) in exports {
+            export_section.export(name, kind, index);
+        }
+        module.section(&export_section);
+
+        if !self.table.is_empty() {
+            let mut elements = ElementSection::new();
+            let functions = self.table.iter().copied().collect::<Vec<_>>();
+            elements.active(
+                None,
+                &ConstExpr::i32_const(1),
+                Elements::Functions(Cow::Owned(functions)),
+            );
+            module.section(&elements);
+        }
+
+        module.section(&code);
+        module.section(&data);
+
+        for (object_index, object) in self.objects.iter().enumerate() {
+            if !self.linked[object_index] {
+                continue;
+            }
+            for (name, data) in object.component_types.iter() {
+                module.section(&CustomSection {
+                    name: Cow::Borrowed(*name),
+                    data: Cow::Borrowed(*data),
+                });
+            }
+        }
+
+        let mut names = NameMap::new();
+        for (index, (_, name)) in self.func_imports.keys().enumerate() {
+            names.append(index as u32, name);
+        }
+        function_names.sort_by_key(|(index, _)| *index);
+        function_names.dedup_by_key(|(index, _)| *index);
+        for (index, name) in function_names {
+            names.append(index, name);
+        }
+        let mut name_section = NameSection::new();
+        name_section.functions(&names);
+        module.section(&name_section);
+
+        Ok(module.finish())
+    }
+}
+
+/// How the value of a relocation is encoded.
+enum RelocEncoding {
+    Unsigned,
+    Signed,
+    /// A little-endian 32-bit integer
+    Fixed,
+}
+
+impl RelocEncoding {
+    /// Writes `value` in place of the encoded value at the start of `original`, returning the length of the
+    /// latter.
+    ///
+    /// LEB128 values are padded to the length of the original value if they fit in it, which is the case for
+    /// object files produced by LLVM where they're padded to 5 bytes.
+    fn write(&self, value: i64, original: &[u8], bytes: &mut Vec<u8>) -> Result<usize> {
+        let signed = match self {
+            RelocEncoding::Fixed => {
+                if original.len() < 4 {
+                    bail!("relocation out of bounds");
+                }
+                bytes.extend_from_slice(&(value as u32).to_le_bytes());
+                return Ok(4);
+            }
+            RelocEncoding::Unsigned => false,
+            RelocEncoding::Signed => true,
+        };
+        let Some(len) = original.iter().position(|b| b & 0x80 == 0) else {
+            bail!("relocation out of bounds");
+        };
+        let len = len + 1;
+        let fits = |n: usize| {
+            let bits = 7 * n as u32;
+            if bits >= 64 {
+                true
+            } else if signed {
+                matches!(value >> (bits - 1), 0 | -1)
+            } else {
+                value >> bits == 0
+            }
+        };
+        let padded = if fits(len) {
+            len
+        } else {
+            (1..).find(|n| fits(*n)).unwrap()
+        };
+        let mut value = value;
+        for i in 0..padded {
+            let mut byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if i + 1 < padded {
+                byte |= 0x80;
+            }
+            bytes.push(byte);
+        }
+        Ok(len)
+    }
+}
+
+/// Builder type for statically linking relocatable object files into a component
+///
+/// This takes a main object file and any number of static library object files following the [linking
+/// convention](https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md), as produced by `clang -c`
+/// or `rustc --emit obj` for example. Like members of archives, library objects are only linked if they define
+/// symbols needed by the main object or by other linked library objects.
+#[derive(Default)]
+pub struct StaticLinker {
+    /// The `(name, module)` pair of the main object file
+    module: Option<(String, Vec<u8>)>,
+
+    /// The `(name, module)` pairs of the library object files
+    libraries: Vec<(String, Vec<u8>)>,
+
+    /// The set of adapters to use when generating the component
+    adapters: Vec<(String, Vec<u8>)>,
+
+    /// Whether to validate the resulting component prior to returning it
+    validate: bool,
+
+    /// Size of stack (in bytes) to allocate in the linked module
+    ///
+    /// If `None`, use `DEFAULT_STACK_SIZE_BYTES`.
+    stack_size: Option<u32>,
+
+    /// Whether imports into the final component are merged based on semver ranges
+    merge_imports_based_on_semver: Option<bool>,
+}
+
+impl StaticLinker {
+    /// Set the main object file of this linker.
+    pub fn module(mut self, name: &str, module: &[u8]) -> Result<Self> {
+        if self.module.is_some() {
+            bail!("main module specified twice");
+        }
+        self.module = Some((name.to_owned(), module.to_vec()));
+
+        Ok(self)
+    }
+
+    /// Add a static library object file to this linker.
+    ///
+    /// The order of libraries determines priority in cases where more than one library defines the same symbol.
+    pub fn library(mut self, name: &str, module: &[u8]) -> Result<Self> {
+        self.libraries.push((name.to_owned(), module.to_vec()));
+
+        Ok(self)
+    }
+
+    /// Add an adapter to this linker.
+    ///
+    /// See [crate::encoding::ComponentEncoder::adapter] for details.
+    pub fn adapter(mut self, name: &str, module: &[u8]) -> Result<Self> {
+        self.adapters.push((name.to_owned(), module.to_vec()));
+
+        Ok(self)
+    }
+
+    /// Specify whether to validate the resulting component prior to returning it
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Specify size of stack to allocate in the linked module
+    pub fn stack_size(mut self, stack_size: u32) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Specify whether imports into the final component are merged based on semver ranges.
+    ///
+    /// This is enabled by default.
+    pub fn merge_imports_based_on_semver(mut self, merge: bool) -> Self {
+        self.merge_imports_based_on_semver = Some(merge);
+        self
+    }
+
+    /// Link the object files into a core module, without encoding it into a component
+    pub fn link_module(&self) -> Result<Vec<u8>> {
+        let Some((name, module)) = &self.module else {
+            bail!("no main module specified");
+        };
+        let mut objects = vec![Object::parse(name, module, false)
+            .with_context(|| format!("failed to parse object file `{name}`"))?];
+        for (name, module) in &self.libraries {
+            objects.push(
+                Object::parse(name, module, true)
+                    .with_context(|| format!("failed to parse object file `{name}`"))?,
+            );
+        }
+
+        Link::new(objects)?.encode(self.stack_size.unwrap_or(DEFAULT_STACK_SIZE_BYTES))
+    }
+
+    /// Encode the component and return the bytes
+    pub fn encode(self) -> Result<Vec<u8>> {
+        let module = self.link_module()?;
+
+        let mut encoder = ComponentEncoder::default().validate(self.validate);
+        if let Some(merge) = self.merge_imports_based_on_semver {
+            encoder = encoder.merge_imports_based_on_semver(merge);
+        }
+        encoder = encoder.module(&module)?;
+
+        for (name, module) in &self.adapters {
+            encoder = encoder.adapter(name, module)?;
+        }
+
+        encoder.encode()
+    }
+}
@@ -1,12 +1,23 @@
 use crate::encoding::encode_world;
 use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use std::fmt;
 use wasm_encoder::{ComponentBuilder, ComponentExportKind, ComponentTypeRef};
 use wasmparser::Validator;
-use wit_parser::{Resolve, WorldId};
+use wit_parser::decoding::{decode, DecodedWasm};
+use wit_parser::{
+    Function, Handle, InterfaceId, Resolve, Results, Type, TypeDefKind, TypeId, WorldId, WorldItem,
+    WorldKey,
+};
 
 /// This function checks whether `component_to_test` correctly conforms to the world specified.
 /// It does so by instantiating a generated component that imports a component instance with
 /// the component type as described by the "target" world.
+///
+/// If the component does not conform to the world then an attempt is made to
+/// find the exact location of the mismatch by comparing the WIT of the
+/// component with the world. If that succeeds the returned error can be
+/// downcast to a [`TargetMismatch`].
 pub fn targets(resolve: &Resolve, world: WorldId, component_to_test: &[u8]) -> Result<()> {
     let mut root_component = ComponentBuilder::default();
 
@@ -36,9 +47,608 @@ pub fn targets(resolve: &Resolve, world: WorldId, component_to_test: &[u8]) -> R
 
     let bytes = root_component.finish();
 
-    Validator::new()
-        .validate_all(&bytes)
-        .context("failed to validate encoded bytes")?;
+    let err = match Validator::new().validate_all(&bytes) {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    // (4) If validation failed then try to produce a more precise error by
+    // comparing the WIT of the component with the world. If the component
+    // can't be decoded or no difference is found, which shouldn't happen,
+    // then fall back to the validation error.
+    if let Ok(DecodedWasm::Component(actual_resolve, actual_world)) = decode(component_to_test) {
+        let mut compare = Compare {
+            expected: resolve,
+            actual: &actual_resolve,
+            path: Vec::new(),
+        };
+        if let Err(mismatch) = compare.world(world, actual_world) {
+            return Err(anyhow::Error::new(mismatch).context(format!(
+                "component does not conform to world `{}`",
+                resolve.worlds[world].name
+            )));
+        }
+    }
+
+    Err(err).context("failed to validate encoded bytes")
+}
+
+/// The error returned by [`targets`] describing where a component differs from
+/// the world that it's expected to target.
+#[derive(Debug, Clone)]
+pub struct TargetMismatch {
+    /// The location of the mismatch, starting from the import or export of
+    /// the world.
+    pub path: Vec<TargetPathSegment>,
+    /// The item expected by the world, in WIT syntax, or `None` if the world
+    /// has no such item.
+    pub expected: Option<String>,
+    /// The item found in the component, in WIT syntax, or `None` if the
+    /// component has no such item.
+    pub found: Option<String>,
+}
+
+/// A single step in the path of a [`TargetMismatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetPathSegment {
+    /// An import of the world.
+    Import(String),
+    /// An export of the world.
+    Export(String),
+    /// A function within a world or interface.
+    Function(String),
+    /// A named type within a world or interface.
+    Type(String),
+    /// A parameter of a function.
+    Param(String),
+    /// The result of a function, optionally with a name.
+    Result(Option<String>),
+    /// A field of a record.
+    Field(String),
+    /// A case of a variant.
+    Case(String),
+    /// An element of a tuple.
+    TupleElement(usize),
+    /// The element type of a list.
+    ListElement,
+    /// The payload of an option.
+    OptionPayload,
+    /// The `ok` type of a result.
+    Ok,
+    /// The `err` type of a result.
+    Err,
+    /// The payload of a future.
+    FuturePayload,
+    /// The element type of a stream.
+    StreamElement,
+    /// The end type of a stream.
+    StreamEnd,
+}
+
+impl fmt::Display for TargetPathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetPathSegment::Import(name) => write!(f, "import `{name}`"),
+            TargetPathSegment::Export(name) => write!(f, "export `{name}`"),
+            TargetPathSegment::Function(name) => write!(f, "function `{name}`"),
+            TargetPathSegment::Type(name) => write!(f, "type `{name}`"),
+            TargetPathSegment::Param(name) => write!(f, "parameter `{name}`"),
+            TargetPathSegment::Result(Some(name)) => write!(f, "result `{name}`"),
+            TargetPathSegment::Result(None) => f.write_str("result"),
+            TargetPathSegment::Field(name) => write!(f, "field `{name}`"),
+            TargetPathSegment::Case(name) => write!(f, "case `{name}`"),
+            TargetPathSegment::TupleElement(i) => write!(f, "tuple element {i}"),
+            TargetPathSegment::ListElement => f.write_str("list element"),
+            TargetPathSegment::OptionPayload => f.write_str("option payload"),
+            TargetPathSegment::Ok => f.write_str("ok type"),
+            TargetPathSegment::Err => f.write_str("err type"),
+            TargetPathSegment::FuturePayload => f.write_str("future payload"),
+            TargetPathSegment::StreamElement => f.write_str("stream element"),
+            TargetPathSegment::StreamEnd => f.write_str("stream end"),
+        }
+    }
+}
+
+impl fmt::Display for TargetMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self
+            .path
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        match (&self.expected, &self.found) {
+            (Some(expected), Some(found)) => {
+                write!(
+                    f,
+                    "mismatch at {path}: expected `{expected}`, found `{found}`"
+                )
+            }
+            (Some(expected), None) => {
+                write!(f, "missing {path}: expected `{expected}`")
+            }
+            (None, Some(found)) => write!(
+                f,
+                "unexpected {path}: found `{found}`, which is not in the world"
+            ),
+            (None, None) => write!(f, "mismatch at {path}"),
+        }
+    }
+}
+
+impl std::error::Error for TargetMismatch {}
+
+/// Structural comparison of the world a component is expected to target with
+/// the world that it actually has.
+///
+/// The component is allowed to import fewer items than the world provides and
+/// to export more items than the world requires, but otherwise types must be
+/// structurally equal.
+struct Compare<'a> {
+    expected: &'a Resolve,
+    actual: &'a Resolve,
+    path: Vec<TargetPathSegment>,
+}
+
+type CompareResult = std::result::Result<(), TargetMismatch>;
+
+impl Compare<'_> {
+    fn mismatch(&self, expected: Option<String>, found: Option<String>) -> TargetMismatch {
+        TargetMismatch {
+            path: self.path.clone(),
+            expected,
+            found,
+        }
+    }
+
+    fn nested(
+        &mut self,
+        segment: TargetPathSegment,
+        f: impl FnOnce(&mut Self) -> CompareResult,
+    ) -> CompareResult {
+        self.path.push(segment);
+        f(self)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn world(&mut self, expected: WorldId, actual: WorldId) -> CompareResult {
+        let expected_world = &self.expected.worlds[expected];
+        let actual_world = &self.actual.worlds[actual];
+
+        // Every import of the component must be provided by the world.
+        let provided = keyed(self.expected, &expected_world.imports);
+        for (name, actual_item) in keyed(self.actual, &actual_world.imports) {
+            self.nested(
+                TargetPathSegment::Import(name.clone()),
+                |me| match provided.get(&name) {
+                    Some(expected_item) => me.item(expected_item, actual_item, true),
+                    None => Err(me.mismatch(None, Some(item_wit(me.actual, actual_item)))),
+                },
+            )?;
+        }
+
+        // Every export of the world must be provided by the component.
+        let provided = keyed(self.actual, &actual_world.exports);
+        for (name, expected_item) in keyed(self.expected, &expected_world.exports) {
+            self.nested(
+                TargetPathSegment::Export(name.clone()),
+                |me| match provided.get(&name) {
+                    Some(actual_item) => me.item(expected_item, actual_item, false),
+                    None => Err(me.mismatch(Some(item_wit(me.expected, expected_item)), None)),
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Compares two items of a world, where `import` indicates whether the
+    /// component is allowed to use a subset of the world's item.
+    fn item(&mut self, expected: &WorldItem, actual: &WorldItem, import: bool) -> CompareResult {
+        match (expected, actual) {
+            (WorldItem::Interface { id: a, .. }, WorldItem::Interface { id: b, .. }) => {
+                self.interface(*a, *b, import)
+            }
+            (WorldItem::Function(a), WorldItem::Function(b)) => self.function(a, b),
+            (WorldItem::Type(a), WorldItem::Type(b)) => self.ty(&Type::Id(*a), &Type::Id(*b)),
+            _ => Err(self.mismatch(
+                Some(item_wit(self.expected, expected)),
+                Some(item_wit(self.actual, actual)),
+            )),
+        }
+    }
+
+    fn interface(
+        &mut self,
+        expected: InterfaceId,
+        actual: InterfaceId,
+        import: bool,
+    ) -> CompareResult {
+        let expected_iface = &self.expected.interfaces[expected];
+        let actual_iface = &self.actual.interfaces[actual];
 
-    Ok(())
+        // For imports the component may use fewer items than the world
+        // provides, and for exports the component may provide more items than
+        // the world requires, so only the respective smaller set is iterated.
+        if import {
+            for (name, actual_func) in actual_iface.functions.iter() {
+                self.nested(
+                    TargetPathSegment::Function(name.clone()),
+                    |me| match expected_iface.functions.get(name) {
+                        Some(expected_func) => me.function(expected_func, actual_func),
+                        None => Err(me.mismatch(None, Some(func_wit(me.actual, actual_func)))),
+                    },
+                )?;
+            }
+            for (name, actual_ty) in actual_iface.types.iter() {
+                self.nested(
+                    TargetPathSegment::Type(name.clone()),
+                    |me| match expected_iface.types.get(name) {
+                        Some(expected_ty) => me.ty(&Type::Id(*expected_ty), &Type::Id(*actual_ty)),
+                        None => Err(me.mismatch(None, Some(typedef_wit(me.actual, *actual_ty)))),
+                    },
+                )?;
+            }
+        } else {
+            for (name, expected_func) in expected_iface.functions.iter() {
+                self.nested(
+                    TargetPathSegment::Function(name.clone()),
+                    |me| match actual_iface.functions.get(name) {
+                        Some(actual_func) => me.function(expected_func, actual_func),
+                        None => Err(me.mismatch(Some(func_wit(me.expected, expected_func)), None)),
+                    },
+                )?;
+            }
+            for (name, expected_ty) in expected_iface.types.iter() {
+                self.nested(
+                    TargetPathSegment::Type(name.clone()),
+                    |me| match actual_iface.types.get(name) {
+                        Some(actual_ty) => me.ty(&Type::Id(*expected_ty), &Type::Id(*actual_ty)),
+                        None => {
+                            Err(me.mismatch(Some(typedef_wit(me.expected, *expected_ty)), None))
+                        }
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, expected: &Function, actual: &Function) -> CompareResult {
+        let signature_mismatch = |me: &Self| {
+            me.mismatch(
+                Some(func_wit(me.expected, expected)),
+                Some(func_wit(me.actual, actual)),
+            )
+        };
+        if expected.params.len() != actual.params.len()
+            || expected
+                .params
+                .iter()
+                .zip(&actual.params)
+                .any(|((a, _), (b, _))| a != b)
+        {
+            return Err(signature_mismatch(self));
+        }
+        for ((name, a), (_, b)) in expected.params.iter().zip(&actual.params) {
+            self.nested(TargetPathSegment::Param(name.clone()), |me| me.ty(a, b))?;
+        }
+        match (&expected.results, &actual.results) {
+            (Results::Anon(a), Results::Anon(b)) => {
+                self.nested(TargetPathSegment::Result(None), |me| me.ty(a, b))
+            }
+            (Results::Named(a), Results::Named(b))
+                if a.len() == b.len() && a.iter().zip(b).all(|((a, _), (b, _))| a == b) =>
+            {
+                for ((name, a), (_, b)) in a.iter().zip(b) {
+                    self.nested(TargetPathSegment::Result(Some(name.clone())), |me| {
+                        me.ty(a, b)
+                    })?;
+                }
+                Ok(())
+            }
+            _ => Err(signature_mismatch(self)),
+        }
+    }
+
+    fn optional_ty(
+        &mut self,
+        segment: TargetPathSegment,
+        expected: Option<&Type>,
+        actual: Option<&Type>,
+    ) -> CompareResult {
+        match (expected, actual) {
+            (Some(a), Some(b)) => self.nested(segment, |me| me.ty(a, b)),
+            (None, None) => Ok(()),
+            _ => Err(self.mismatch(None, None)),
+        }
+    }
+
+    fn ty(&mut self, expected: &Type, actual: &Type) -> CompareResult {
+        let expected = dealias(self.expected, *expected);
+        let actual = dealias(self.actual, *actual);
+        let (a, b) = match (expected, actual) {
+            (Type::Id(a), Type::Id(b)) => (a, b),
+            (Type::Id(_), _) | (_, Type::Id(_)) => return Err(self.ty_mismatch(expected, actual)),
+            (a, b) if a == b => return Ok(()),
+            _ => return Err(self.ty_mismatch(expected, actual)),
+        };
+
+        let result = match (&self.expected.types[a].kind, &self.actual.types[b].kind) {
+            (TypeDefKind::Record(a), TypeDefKind::Record(b))
+                if a.fields.len() == b.fields.len()
+                    && a.fields
+                        .iter()
+                        .zip(&b.fields)
+                        .all(|(a, b)| a.name == b.name) =>
+            {
+                for (a, b) in a.fields.iter().zip(&b.fields) {
+                    self.nested(TargetPathSegment::Field(a.name.clone()), |me| {
+                        me.ty(&a.ty, &b.ty)
+                    })?;
+                }
+                Ok(())
+            }
+            (TypeDefKind::Variant(a), TypeDefKind::Variant(b))
+                if a.cases.len() == b.cases.len()
+                    && a.cases.iter().zip(&b.cases).all(|(a, b)| a.name == b.name) =>
+            {
+                for (a, b) in a.cases.iter().zip(&b.cases) {
+                    self.optional_ty(
+                        TargetPathSegment::Case(a.name.clone()),
+                        a.ty.as_ref(),
+                        b.ty.as_ref(),
+                    )?;
+                }
+                Ok(())
+            }
+            (TypeDefKind::Tuple(a), TypeDefKind::Tuple(b)) if a.types.len() == b.types.len() => {
+                for (i, (a, b)) in a.types.iter().zip(&b.types).enumerate() {
+                    self.nested(TargetPathSegment::TupleElement(i), |me| me.ty(a, b))?;
+                }
+                Ok(())
+            }
+            (TypeDefKind::Enum(a), TypeDefKind::Enum(b))
+                if a.cases.len() == b.cases.len()
+                    && a.cases.iter().zip(&b.cases).all(|(a, b)| a.name == b.name) =>
+            {
+                Ok(())
+            }
+            (TypeDefKind::Flags(a), TypeDefKind::Flags(b))
+                if a.flags.len() == b.flags.len()
+                    && a.flags.iter().zip(&b.flags).all(|(a, b)| a.name == b.name) =>
+            {
+                Ok(())
+            }
+            (TypeDefKind::List(a), TypeDefKind::List(b)) => {
+                self.nested(TargetPathSegment::ListElement, |me| me.ty(a, b))
+            }
+            (TypeDefKind::Option(a), TypeDefKind::Option(b)) => {
+                self.nested(TargetPathSegment::OptionPayload, |me| me.ty(a, b))
+            }
+            (TypeDefKind::Result(a), TypeDefKind::Result(b)) => self
+                .optional_ty(TargetPathSegment::Ok, a.ok.as_ref(), b.ok.as_ref())
+                .and_then(|()| {
+                    self.optional_ty(TargetPathSegment::Err, a.err.as_ref(), b.err.as_ref())
+                }),
+            (TypeDefKind::Future(a), TypeDefKind::Future(b)) => {
+                self.optional_ty(TargetPathSegment::FuturePayload, a.as_ref(), b.as_ref())
+            }
+            (TypeDefKind::Stream(a), TypeDefKind::Stream(b)) => self
+                .optional_ty(
+                    TargetPathSegment::StreamElement,
+                    a.element.as_ref(),
+                    b.element.as_ref(),
+                )
+                .and_then(|()| {
+                    self.optional_ty(TargetPathSegment::StreamEnd, a.end.as_ref(), b.end.as_ref())
+                }),
+            // Resources are nominal so they're only compared by name here.
+            (TypeDefKind::Resource, TypeDefKind::Resource)
+                if self.expected.types[a].name == self.actual.types[b].name =>
+            {
+                Ok(())
+            }
+            (TypeDefKind::Handle(Handle::Own(a)), TypeDefKind::Handle(Handle::Own(b)))
+            | (TypeDefKind::Handle(Handle::Borrow(a)), TypeDefKind::Handle(Handle::Borrow(b))) => {
+                self.ty(&Type::Id(*a), &Type::Id(*b))
+            }
+            _ => Err(self.mismatch(None, None)),
+        };
+
+        // Mismatches which couldn't be attributed to a nested location, such as
+        // differing record fields, are reported with the full definition of
+        // both types.
+        result.map_err(|mut mismatch| {
+            if mismatch.expected.is_none() && mismatch.found.is_none() {
+                mismatch.expected = Some(typedef_wit(self.expected, a));
+                mismatch.found = Some(typedef_wit(self.actual, b));
+            }
+            mismatch
+        })
+    }
+
+    fn ty_mismatch(&self, expected: Type, actual: Type) -> TargetMismatch {
+        self.mismatch(
+            Some(type_wit(self.expected, &expected)),
+            Some(type_wit(self.actual, &actual)),
+        )
+    }
+}
+
+/// Returns the items of a world keyed by their name, or the ID of their
+/// interface.
+fn keyed<'a>(
+    resolve: &Resolve,
+    items: &'a IndexMap<WorldKey, WorldItem>,
+) -> IndexMap<String, &'a WorldItem> {
+    items
+        .iter()
+        .map(|(key, item)| (resolve.name_world_key(key), item))
+        .collect()
+}
+
+/// Follows type aliases, such as those created by `use`, to the type that they
+/// refer to.
+fn dealias(resolve: &Resolve, mut ty: Type) -> Type {
+    while let Type::Id(id) = ty {
+        match resolve.types[id].kind {
+            TypeDefKind::Type(t) => ty = t,
+            _ => break,
+        }
+    }
+    ty
+}
+
+fn item_wit(resolve: &Resolve, item: &WorldItem) -> String {
+    match item {
+        WorldItem::Interface { id, .. } => match resolve.id_of(*id) {
+            Some(name) => format!("interface {name}"),
+            None => "interface { ... }".to_string(),
+        },
+        WorldItem::Function(func) => func_wit(resolve, func),
+        WorldItem::Type(id) => typedef_wit(resolve, *id),
+    }
+}
+
+fn func_wit(resolve: &Resolve, func: &Function) -> String {
+    let params = func
+        .params
+        .iter()
+        .map(|(name, ty)| format!("{name}: {}", type_wit(resolve, ty)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut ret = format!("func({params})");
+    match &func.results {
+        Results::Anon(ty) => {
+            ret.push_str(" -> ");
+            ret.push_str(&type_wit(resolve, ty));
+        }
+        Results::Named(results) if results.is_empty() => {}
+        Results::Named(results) => {
+            let results = results
+                .iter()
+                .map(|(name, ty)| format!("{name}: {}", type_wit(resolve, ty)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ret.push_str(&format!(" -> ({results})"));
+        }
+    }
+    ret
+}
+
+/// Renders the definition of the type `id`, such as `record { a: u32 }`.
+fn typedef_wit(resolve: &Resolve, id: TypeId) -> String {
+    let optional = |ty: Option<&Type>| match ty {
+        Some(ty) => format!("({})", type_wit(resolve, ty)),
+        None => String::new(),
+    };
+    match &resolve.types[id].kind {
+        TypeDefKind::Record(r) => {
+            let fields = r
+                .fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, type_wit(resolve, &f.ty)))
+                .collect::<Vec<_>>();
+            format!("record {{ {} }}", fields.join(", "))
+        }
+        TypeDefKind::Variant(v) => {
+            let cases = v
+                .cases
+                .iter()
+                .map(|c| format!("{}{}", c.name, optional(c.ty.as_ref())))
+                .collect::<Vec<_>>();
+            format!("variant {{ {} }}", cases.join(", "))
+        }
+        TypeDefKind::Enum(e) => {
+            let cases = e.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+            format!("enum {{ {} }}", cases.join(", "))
+        }
+        TypeDefKind::Flags(f) => {
+            let flags = f.flags.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+            format!("flags {{ {} }}", flags.join(", "))
+        }
+        TypeDefKind::Resource => match &resolve.types[id].name {
+            Some(name) => format!("resource {name}"),
+            None => "resource".to_string(),
+        },
+        TypeDefKind::Type(ty) => type_wit(resolve, ty),
+        _ => anonymous_wit(resolve, id),
+    }
+}
+
+/// Renders a reference to the type `ty`, using the name of named types.
+fn type_wit(resolve: &Resolve, ty: &Type) -> String {
+    let id = match ty {
+        Type::Bool => return "bool".to_string(),
+        Type::U8 => return "u8".to_string(),
+        Type::U16 => return "u16".to_string(),
+        Type::U32 => return "u32".to_string(),
+        Type::U64 => return "u64".to_string(),
+        Type::S8 => return "s8".to_string(),
+        Type::S16 => return "s16".to_string(),
+        Type::S32 => return "s32".to_string(),
+        Type::S64 => return "s64".to_string(),
+        Type::F32 => return "f32".to_string(),
+        Type::F64 => return "f64".to_string(),
+        Type::Char => return "char".to_string(),
+        Type::String => return "string".to_string(),
+        Type::Id(id) => *id,
+    };
+    match &resolve.types[id].name {
+        Some(name) => name.clone(),
+        None => anonymous_wit(resolve, id),
+    }
+}
+
+fn anonymous_wit(resolve: &Resolve, id: TypeId) -> String {
+    let optional = |ty: Option<&Type>| match ty {
+        Some(ty) => type_wit(resolve, ty),
+        None => "_".to_string(),
+    };
+    match &resolve.types[id].kind {
+        TypeDefKind::List(ty) => format!("list<{}>", type_wit(resolve, ty)),
+        TypeDefKind::Option(ty) => format!("option<{}>", type_wit(resolve, ty)),
+        TypeDefKind::Result(r) => match (&r.ok, &r.err) {
+            (None, None) => "result".to_string(),
+            (Some(ok), None) => format!("result<{}>", type_wit(resolve, ok)),
+            (ok, Some(err)) => format!(
+                "result<{}, {}>",
+                optional(ok.as_ref()),
+                type_wit(resolve, err)
+            ),
+        },
+        TypeDefKind::Tuple(t) => {
+            let types = t
+                .types
+                .iter()
+                .map(|ty| type_wit(resolve, ty))
+                .collect::<Vec<_>>();
+            format!("tuple<{}>", types.join(", "))
+        }
+        TypeDefKind::Handle(Handle::Own(ty)) => type_wit(resolve, &Type::Id(*ty)),
+        TypeDefKind::Handle(Handle::Borrow(ty)) => {
+            format!("borrow<{}>", type_wit(resolve, &Type::Id(*ty)))
+        }
+        TypeDefKind::Future(ty) => match ty {
+            Some(ty) => format!("future<{}>", type_wit(resolve, ty)),
+            None => "future".to_string(),
+        },
+        TypeDefKind::Stream(s) => match (&s.element, &s.end) {
+            (None, None) => "stream".to_string(),
+            (Some(element), None) => format!("stream<{}>", type_wit(resolve, element)),
+            (element, Some(end)) => format!(
+                "stream<{}, {}>",
+                optional(element.as_ref()),
+                type_wit(resolve, end)
+            ),
+        },
+        TypeDefKind::Type(ty) => type_wit(resolve, ty),
+        TypeDefKind::Record(_)
+        | TypeDefKind::Variant(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Flags(_)
+        | TypeDefKind::Resource => typedef_wit(resolve, id),
+        TypeDefKind::Unknown => "unknown".to_string(),
+    }
 }
@@ -1,4 +1,4 @@
-use crate::encoding::{Instance, Item, LibraryInfo, MainOrAdapter};
+use crate::encoding::{CanonicalOptions, Instance, Item, LibraryInfo, MainOrAdapter};
 use crate::metadata::Bindgen;
 use anyhow::{bail, Context, Result};
 use indexmap::{map::Entry, IndexMap, IndexSet};
@@ -71,6 +71,7 @@ impl ValidatedModule {
         exports: &IndexSet<WorldKey>,
         adapters: &IndexSet<&str>,
         info: Option<&LibraryInfo>,
+        options: &CanonicalOptions,
     ) -> Result<ValidatedModule> {
         let mut validator = Validator::new();
         let mut ret = ValidatedModule::default();
@@ -97,14 +98,15 @@ impl ValidatedModule {
                 Payload::ExportSection(s) => {
                     for export in s {
                         let export = export?;
-                        ret.exports.add(export, resolve, world, &exports, types)?;
+                        ret.exports
+                            .add(export, resolve, world, &exports, options, types)?;
                     }
                 }
                 _ => continue,
             }
         }
 
-        ret.exports.validate(resolve, world, exports)?;
+        ret.exports.validate(resolve, world, exports, options)?;
 
         Ok(ret)
     }
@@ -613,9 +615,10 @@ impl ExportMap {
         resolve: &Resolve,
         world: WorldId,
         exports: &IndexSet<WorldKey>,
+        options: &CanonicalOptions,
         types: TypesRef<'_>,
     ) -> Result<()> {
-        if let Some(item) = self.classify(export, resolve, world, exports, types)? {
+        if let Some(item) = self.classify(export, resolve, world, exports, options, types)? {
             let prev = self.names.insert(export.name.to_string(), item);
             assert!(prev.is_none());
         }
@@ -628,11 +631,15 @@ impl ExportMap {
         resolve: &Resolve,
         world: WorldId,
         exports: &IndexSet<WorldKey>,
+        options: &CanonicalOptions,
         types: TypesRef<'_>,
     ) -> Result<Option<Export>> {
         match export.kind {
             ExternalKind::Func => {}
-            ExternalKind::Memory => return Ok(Some(Export::Memory)),
+            ExternalKind::Memory => match &options.memory {
+                Some(name) if name != export.name => return Ok(None),
+                _ => return Ok(Some(Export::Memory)),
+            },
             _ => return Ok(None),
         }
 
@@ -640,11 +647,20 @@ impl ExportMap {
         self.raw_exports.insert(export.name.to_string(), ty.clone());
 
         // Handle a few special-cased names first.
-        if export.name == "cabi_realloc" || export.name == "canonical_abi_realloc" {
+        let is_realloc = match &options.realloc {
+            Some(name) => export.name == name,
+            None => export.name == "cabi_realloc" || export.name == "canonical_abi_realloc",
+        };
+        let import_realloc = options.import_realloc.as_deref();
+        let export_realloc = options.export_realloc.as_deref();
+        if is_realloc {
+            validate_realloc(export.name, ty)?;
             return Ok(Some(Export::GeneralPurposeRealloc));
-        } else if export.name == "cabi_import_realloc" {
+        } else if export.name == import_realloc.unwrap_or("cabi_import_realloc") {
+            validate_realloc(export.name, ty)?;
             return Ok(Some(Export::GeneralPurposeImportRealloc));
-        } else if export.name == "cabi_export_realloc" {
+        } else if export.name == export_realloc.unwrap_or("cabi_export_realloc") {
+            validate_realloc(export.name, ty)?;
             return Ok(Some(Export::GeneralPurposeExportRealloc));
         } else if export.name == "cabi_realloc_adapter" {
             return Ok(Some(Export::ReallocForAdapter));
@@ -669,7 +685,11 @@ impl ExportMap {
         }
 
         // See if this is a post-return for any known WIT export.
-        if let Some(suffix) = export.name.strip_prefix(POST_RETURN_PREFIX) {
+        let post_return_prefix = options
+            .post_return_prefix
+            .as_deref()
+            .unwrap_or(POST_RETURN_PREFIX);
+        if let Some(suffix) = export.name.strip_prefix(post_return_prefix) {
             if let Some((key, id, f)) = self.match_wit_export(suffix, resolve, world, exports) {
                 validate_post_return(resolve, ty, f).with_context(|| {
                     let key = resolve.name_world_key(key);
@@ -852,7 +872,31 @@ impl ExportMap {
         resolve: &Resolve,
         world: WorldId,
         exports: &IndexSet<WorldKey>,
+        options: &CanonicalOptions,
     ) -> Result<()> {
+        // Items explicitly configured must be exported by the module.
+        let configured = [
+            (&options.memory, "memory", self.memory()),
+            (&options.realloc, "realloc", self.general_purpose_realloc()),
+            (
+                &options.import_realloc,
+                "import realloc",
+                self.find(|m| matches!(m, Export::GeneralPurposeImportRealloc)),
+            ),
+            (
+                &options.export_realloc,
+                "export realloc",
+                self.find(|m| matches!(m, Export::GeneralPurposeExportRealloc)),
+            ),
+        ];
+        for (name, desc, found) in configured {
+            if let (Some(name), None) = (name, found) {
+                bail!(
+                    "module does not export the {desc} `{name}` configured for canonical options"
+                );
+            }
+        }
+
         // Multi-memory isn't supported because otherwise we don't know what
         // memory to put things in.
         if self
@@ -914,6 +958,8 @@ impl ExportMap {
 ///   or the `adapters` set.
 /// * The given default and exported interfaces are satisfied by the module's
 ///   exports.
+/// * The realloc functions and memory configured by `options`, if any, are
+///   exported by the module.
 ///
 /// The `ValidatedModule` return value contains the metadata which describes the
 /// input module on success. This is then further used to generate a component
@@ -923,6 +969,7 @@ pub fn validate_module(
     metadata: &Bindgen,
     exports: &IndexSet<WorldKey>,
     adapters: &IndexSet<&str>,
+    options: &CanonicalOptions,
 ) -> Result<ValidatedModule> {
     ValidatedModule::new(
         bytes,
@@ -931,6 +978,7 @@ pub fn validate_module(
         exports,
         adapters,
         None,
+        options,
     )
 }
 
@@ -960,7 +1008,15 @@ pub fn validate_adapter_module(
     library_info: Option<&LibraryInfo>,
     adapters: &IndexSet<&str>,
 ) -> Result<ValidatedModule> {
-    let ret = ValidatedModule::new(bytes, resolve, world, exports, adapters, library_info)?;
+    let ret = ValidatedModule::new(
+        bytes,
+        resolve,
+        world,
+        exports,
+        adapters,
+        library_info,
+        &CanonicalOptions::default(),
+    )?;
 
     for (name, required_ty) in required_by_import {
         let actual = match ret.exports.raw_exports.get(name) {
@@ -1055,6 +1111,11 @@ fn validate_post_return(
     )
 }
 
+fn validate_realloc(name: &str, ty: &wasmparser::FuncType) -> Result<()> {
+    let expected = FuncType::new([ValType::I32; 4], [ValType::I32]);
+    validate_func_sig(name, &expected, ty)
+}
+
 fn validate_func_sig(name: &str, expected: &FuncType, ty: &wasmparser::FuncType) -> Result<()> {
     if ty != expected {
         bail!(
@@ -0,0 +1,12 @@
+(module
+  (@dylink.0
+    (mem-info (memory 0 4))
+  )
+  (type (func (param i32) (result i32)))
+  (import "env" "memory" (memory 1))
+  (table 1 funcref)
+  (func $foo (type 0) (param i32) (result i32)
+    unreachable
+  )
+  (export "test:test/test#foo" (func $foo))
+)
@@ -0,0 +1,62 @@
+#![cfg(feature = "dummy-module")]
+
+use anyhow::Result;
+use wasmparser::{Sha256, Validator};
+use wit_component::{dummy_module, embed_component_metadata, ComponentEncoder, StringEncoding};
+use wit_parser::Resolve;
+
+const WIT: &str = r#"
+package foo:bar;
+
+interface types {
+    resource thing {
+        constructor(name: string);
+        name: func() -> string;
+    }
+    record point { x: u32, y: u32 }
+}
+
+world w1 {
+    use types.{thing, point};
+    import log: func(msg: string);
+    import types;
+    export run: func(t: borrow<thing>, p: point) -> list<point>;
+}
+
+/// The same as `w1`, but with items in a different order.
+world w2 {
+    import types;
+    use types.{point, thing};
+    export run: func(t: borrow<thing>, p: point) -> list<point>;
+    import log: func(msg: string);
+}
+
+world w3 {
+    include w1;
+    export stop: func();
+}
+"#;
+
+/// The fingerprint of a world matches the fingerprint of a component
+/// targeting it.
+#[test]
+fn world_and_component() -> Result<()> {
+    let mut resolve = Resolve::default();
+    let pkg = resolve.push_str("test.wit", WIT)?;
+    let world = |name: &str| resolve.select_world(pkg, Some(name)).unwrap();
+    let w1 = wit_component::world_fingerprint::<Sha256>(&resolve, world("w1"))?;
+    let w2 = wit_component::world_fingerprint::<Sha256>(&resolve, world("w2"))?;
+    let w3 = wit_component::world_fingerprint::<Sha256>(&resolve, world("w3"))?;
+    assert_eq!(w1, w2);
+    assert_ne!(w1, w3);
+
+    let mut module = dummy_module(&resolve, world("w1"));
+    embed_component_metadata(&mut module, &resolve, world("w1"), StringEncoding::UTF8)?;
+    let component = ComponentEncoder::default()
+        .module(&module)?
+        .validate(true)
+        .encode()?;
+    let types = Validator::new().validate_all(&component)?;
+    assert_eq!(types.interface_fingerprint::<Sha256>(), w1);
+    Ok(())
+}
@@ -0,0 +1,377 @@
+use {
+    anyhow::Result,
+    wasm_encoder::{DataSymbolDefinition, Encode, Section, SymbolTable},
+    wasmparser::{Parser, Payload},
+    wit_component::StringEncoding,
+    wit_parser::Resolve,
+};
+
+const LOCAL: u32 = SymbolTable::WASM_SYM_BINDING_LOCAL;
+const UNDEFINED: u32 = SymbolTable::WASM_SYM_UNDEFINED;
+const EXPORTED: u32 = SymbolTable::WASM_SYM_EXPORTED;
+
+// Relocation types, see `wasmparser::RelocationType`.
+const FUNCTION_INDEX_LEB: u8 = 0;
+const TABLE_INDEX_I32: u8 = 2;
+const MEMORY_ADDR_SLEB: u8 = 4;
+const TYPE_INDEX_LEB: u8 = 6;
+const GLOBAL_INDEX_LEB: u8 = 7;
+
+/// A relocation of the immediate of an operator, given by the index of its defined function and its index among
+/// the operators of that function, which refers to a symbol or, for `TYPE_INDEX_LEB`, to a type.
+struct CodeReloc(u32, usize, u8, u32);
+
+/// A relocation at an offset within a data segment, given by its index, which refers to a symbol.
+struct DataReloc(u32, u32, u8, u32);
+
+/// Builds a relocatable object file out of `wat`, which has no `linking` or `reloc.*` custom sections.
+///
+/// Each segment is given a `(name, alignment)` pair in `segments`, and init funcs are `(priority, symbol)` pairs.
+fn object(
+    wat: &str,
+    symbols: &SymbolTable,
+    segments: &[(&str, u32)],
+    init_funcs: &[(u32, u32)],
+    code_relocs: &[CodeReloc],
+    data_relocs: &[DataReloc],
+) -> Result<Vec<u8>> {
+    let mut wasm = wat::parse_str(wat)?;
+
+    let mut section_index = 0u32;
+    let mut code = None;
+    let mut data = None;
+    let mut operators = Vec::new();
+    let mut data_offsets = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        let payload = payload?;
+        match &payload {
+            Payload::CodeSectionStart { range, .. } => code = Some((section_index, range.start)),
+            Payload::CodeSectionEntry(body) => {
+                let mut reader = body.get_operators_reader()?;
+                let mut offsets = Vec::new();
+                while !reader.eof() {
+                    offsets.push(reader.read_with_offset()?.1);
+                }
+                operators.push(offsets);
+            }
+            Payload::DataSection(s) => {
+                data = Some((section_index, s.range().start));
+                for segment in s.clone() {
+                    let segment = segment?;
+                    data_offsets.push(segment.range.end - segment.data.len());
+                }
+            }
+            _ => {}
+        }
+        if payload.as_section().is_some() {
+            section_index += 1;
+        }
+    }
+
+    let mut linking = Vec::new();
+    2u32.encode(&mut linking);
+    symbols.encode(&mut linking);
+    let mut subsection = Vec::new();
+    segments.len().encode(&mut subsection);
+    for (name, alignment) in segments {
+        name.encode(&mut subsection);
+        alignment.encode(&mut subsection);
+        0u32.encode(&mut subsection);
+    }
+    linking.push(5);
+    subsection.encode(&mut linking);
+    let mut subsection = Vec::new();
+    init_funcs.len().encode(&mut subsection);
+    for (priority, symbol) in init_funcs {
+        priority.encode(&mut subsection);
+        symbol.encode(&mut subsection);
+    }
+    linking.push(6);
+    subsection.encode(&mut linking);
+    custom_section(&mut wasm, "linking", &linking);
+
+    let reloc_section = |section: u32, relocs: Vec<(u8, usize, u32)>| {
+        let mut bytes = Vec::new();
+        section.encode(&mut bytes);
+        relocs.len().encode(&mut bytes);
+        for (ty, offset, index) in relocs {
+            bytes.push(ty);
+            u32::try_from(offset).unwrap().encode(&mut bytes);
+            index.encode(&mut bytes);
+            if ty == MEMORY_ADDR_SLEB {
+                0i32.encode(&mut bytes);
+            }
+        }
+        bytes
+    };
+    if let Some((section, start)) = code {
+        let relocs = code_relocs
+            .iter()
+            .map(|CodeReloc(func, op, ty, index)| {
+                // The immediates of the operators relocated here follow a 1-byte opcode.
+                (*ty, operators[*func as usize][*op] + 1 - start, *index)
+            })
+            .collect();
+        custom_section(&mut wasm, "reloc.CODE", &reloc_section(section, relocs));
+    }
+    if let Some((section, start)) = data {
+        let relocs = data_relocs
+            .iter()
+            .map(|DataReloc(segment, offset, ty, index)| {
+                let offset = data_offsets[*segment as usize] + *offset as usize - start;
+                (*ty, offset, *index)
+            })
+            .collect();
+        custom_section(&mut wasm, "reloc.DATA", &reloc_section(section, relocs));
+    }
+
+    Ok(wasm)
+}
+
+fn custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+    wasm_encoder::CustomSection {
+        name: name.into(),
+        data: data.into(),
+    }
+    .append_to(wasm);
+}
+
+const MAIN: &str = r#"
+(module
+  (type $v (func))
+  (type $i (func (result i32)))
+  (type $ii (func (param i32) (result i32)))
+  (import "env" "__linear_memory" (memory 0))
+  (import "env" "__indirect_function_table" (table 0 funcref))
+  (import "env" "__stack_pointer" (global $sp (mut i32)))
+  (import "env" "double" (func $double (type $ii)))
+  (import "env" "__wasm_call_ctors" (func $ctors (type $v)))
+  (func $init (type $v)
+    i32.const 0
+    i32.const 5
+    i32.store)
+  (func $triple (type $ii)
+    local.get 0
+    i32.const 3
+    i32.mul)
+  (func $run (type $i)
+    call $ctors
+    global.get $sp
+    i32.const 16
+    i32.sub
+    global.set $sp
+
+    i32.const 0
+    i32.load
+    call $double
+    i32.const 7
+    i32.const 0
+    i32.load
+    call_indirect (type $ii)
+    i32.add
+
+    global.get $sp
+    i32.const 16
+    i32.add
+    global.set $sp)
+  (data (i32.const 0) "\00\00\00\00")
+  (data (i32.const 0) "\00\00\00\00")
+)
+"#;
+
+fn main_object() -> Result<Vec<u8>> {
+    let mut symbols = SymbolTable::new();
+    symbols
+        .function(LOCAL, 2, Some("init"))
+        .function(LOCAL, 3, Some("triple"))
+        .function(EXPORTED, 4, Some("run"))
+        .function(UNDEFINED, 0, None)
+        .function(UNDEFINED, 1, None)
+        .global(UNDEFINED, 0, None)
+        .data(
+            LOCAL,
+            "ptr",
+            Some(DataSymbolDefinition {
+                index: 0,
+                offset: 0,
+                size: 4,
+            }),
+        )
+        .data(
+            LOCAL,
+            "counter",
+            Some(DataSymbolDefinition {
+                index: 1,
+                offset: 0,
+                size: 4,
+            }),
+        );
+    let mut wasm = object(
+        MAIN,
+        &symbols,
+        &[(".data.ptr", 2), (".bss.counter", 2)],
+        &[(65535, 0)],
+        &[
+            CodeReloc(0, 0, MEMORY_ADDR_SLEB, 7),
+            CodeReloc(2, 0, FUNCTION_INDEX_LEB, 4),
+            CodeReloc(2, 1, GLOBAL_INDEX_LEB, 5),
+            CodeReloc(2, 4, GLOBAL_INDEX_LEB, 5),
+            CodeReloc(2, 5, MEMORY_ADDR_SLEB, 7),
+            CodeReloc(2, 7, FUNCTION_INDEX_LEB, 3),
+            CodeReloc(2, 9, MEMORY_ADDR_SLEB, 6),
+            CodeReloc(2, 11, TYPE_INDEX_LEB, 2),
+            CodeReloc(2, 13, GLOBAL_INDEX_LEB, 5),
+            CodeReloc(2, 16, GLOBAL_INDEX_LEB, 5),
+        ],
+        &[DataReloc(0, 0, TABLE_INDEX_I32, 1)],
+    )?;
+
+    let mut resolve = Resolve::default();
+    let pkg = resolve.push_str(
+        "test.wit",
+        r#"
+            package test:test;
+
+            world main {
+                export run: func() -> u32;
+            }
+        "#,
+    )?;
+    let world = resolve.select_world(pkg, None)?;
+    wit_component::embed_component_metadata(&mut wasm, &resolve, world, StringEncoding::UTF8)?;
+
+    Ok(wasm)
+}
+
+/// A library defining `double`, along with an unused function which needs an import which isn't available.
+const LIBRARY: &str = r#"
+(module
+  (type $ii (func (param i32) (result i32)))
+  (import "env" "missing" (func $missing (type $ii)))
+  (func $double (type $ii)
+    local.get 0
+    i32.const 2
+    i32.mul)
+  (func $unused (type $ii)
+    local.get 0
+    call $missing)
+)
+"#;
+
+fn library_object() -> Result<Vec<u8>> {
+    let mut symbols = SymbolTable::new();
+    symbols
+        .function(0, 1, Some("double"))
+        .function(0, 2, Some("unused"))
+        .function(UNDEFINED, 0, None);
+    object(
+        LIBRARY,
+        &symbols,
+        &[],
+        &[],
+        &[CodeReloc(1, 1, FUNCTION_INDEX_LEB, 2)],
+        &[],
+    )
+}
+
+/// A library which isn't needed at all.
+fn unused_library_object() -> Result<Vec<u8>> {
+    let mut symbols = SymbolTable::new();
+    symbols
+        .function(EXPORTED, 1, Some("never"))
+        .function(UNDEFINED, 0, None);
+    object(
+        r#"
+        (module
+          (import "env" "missing" (func $missing))
+          (func $never call $missing)
+        )
+        "#,
+        &symbols,
+        &[],
+        &[(1, 0)],
+        &[CodeReloc(0, 0, FUNCTION_INDEX_LEB, 1)],
+        &[],
+    )
+}
+
+#[test]
+fn static_linking() -> Result<()> {
+    let linker = wit_component::StaticLinker::default()
+        .validate(true)
+        .module("main.o", &main_object()?)?
+        .library("unused.o", &unused_library_object()?)?
+        .library("double.o", &library_object()?)?;
+
+    let module = linker.link_module()?;
+    wasmparser::validate(&module)?;
+    let text = wasmprinter::print_bytes(&module)?;
+    assert!(!text.contains("missing"), "{text}");
+    assert!(!text.contains("never"), "{text}");
+    assert!(text.contains("(export \"run\""), "{text}");
+
+    let component = linker.encode()?;
+
+    #[cfg(target_family = "wasm")]
+    {
+        _ = component;
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    {
+        use wasmtime::{
+            component::{Component, Linker},
+            Config, Engine, Store,
+        };
+
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+
+        let engine = Engine::new(&config)?;
+        let linker = Linker::new(&engine);
+        let mut store = Store::new(&engine, ());
+        let instance = linker.instantiate(&mut store, &Component::new(&engine, &component)?)?;
+        let run = instance.get_typed_func::<(), (u32,)>(&mut store, "run")?;
+
+        // `double(5) + triple(7)`, where `5` is stored by a constructor and `triple` is called through the
+        // function table.
+        assert_eq!(31, run.call(&mut store, ())?.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn undefined_symbol() -> Result<()> {
+    let mut symbols = SymbolTable::new();
+    symbols
+        .function(EXPORTED, 0, Some("run"))
+        .data(UNDEFINED, "missing", None);
+    let wasm = object(
+        r#"
+        (module
+          (import "env" "__linear_memory" (memory 0))
+          (func $run (result i32)
+            i32.const 0
+            i32.load)
+        )
+        "#,
+        &symbols,
+        &[],
+        &[],
+        &[CodeReloc(0, 0, MEMORY_ADDR_SLEB, 1)],
+        &[],
+    )?;
+
+    let err = wit_component::StaticLinker::default()
+        .module("main.o", &wasm)?
+        .link_module()
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("undefined symbol `missing` in `main.o`"),
+        "{err:?}"
+    );
+
+    Ok(())
+}
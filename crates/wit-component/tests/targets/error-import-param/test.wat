@@ -0,0 +1,73 @@
+(component
+  (type (;0;)
+    (instance
+      (type (;0;) (list string))
+      (type (;1;) (func (param "msgs" 0)))
+      (export (;0;) "log" (func (type 1)))
+    )
+  )
+  (import "test:foo/host" (instance (;0;) (type 0)))
+  (core module (;0;)
+    (type (;0;) (func (param i32 i32)))
+    (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
+    (import "test:foo/host" "log" (func (;0;) (type 0)))
+    (memory (;0;) 0)
+    (export "memory" (memory 0))
+    (export "cabi_realloc" (func 1))
+    (func (;1;) (type 1) (param i32 i32 i32 i32) (result i32)
+      unreachable
+    )
+    (@producers
+      (processed-by "wit-component" "0.217.0")
+    )
+  )
+  (core module (;1;)
+    (type (;0;) (func (param i32 i32)))
+    (table (;0;) 1 1 funcref)
+    (export "0" (func $indirect-test:foo/host-log))
+    (export "$imports" (table 0))
+    (func $indirect-test:foo/host-log (;0;) (type 0) (param i32 i32)
+      local.get 0
+      local.get 1
+      i32.const 0
+      call_indirect (type 0)
+    )
+    (@producers
+      (processed-by "wit-component" "0.217.0")
+    )
+  )
+  (core module (;2;)
+    (type (;0;) (func (param i32 i32)))
+    (import "" "0" (func (;0;) (type 0)))
+    (import "" "$imports" (table (;0;) 1 1 funcref))
+    (elem (;0;) (i32.const 0) func 0)
+    (@producers
+      (processed-by "wit-component" "0.217.0")
+    )
+  )
+  (core instance (;0;) (instantiate 1))
+  (alias core export 0 "0" (core func (;0;)))
+  (core instance (;1;)
+    (export "log" (func 0))
+  )
+  (core instance (;2;) (instantiate 0
+      (with "test:foo/host" (instance 1))
+    )
+  )
+  (alias core export 2 "memory" (core memory (;0;)))
+  (alias core export 0 "$imports" (core table (;0;)))
+  (alias export 0 "log" (func (;0;)))
+  (alias core export 2 "cabi_realloc" (core func (;1;)))
+  (core func (;2;) (canon lower (func 0) (memory 0) string-encoding=utf8))
+  (core instance (;3;)
+    (export "$imports" (table 0))
+    (export "0" (func 2))
+  )
+  (core instance (;4;) (instantiate 2
+      (with "" (instance 3))
+    )
+  )
+  (@producers
+    (processed-by "wit-component" "0.217.0")
+  )
+)
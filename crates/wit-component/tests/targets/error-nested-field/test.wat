@@ -0,0 +1,48 @@
+(component
+  (core module (;0;)
+    (type (;0;) (func (param i32 i32 i32) (result i32)))
+    (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
+    (memory (;0;) 0)
+    (export "test:foo/types#draw" (func 0))
+    (export "memory" (memory 0))
+    (export "cabi_realloc" (func 1))
+    (func (;0;) (type 0) (param i32 i32 i32) (result i32)
+      unreachable
+    )
+    (func (;1;) (type 1) (param i32 i32 i32 i32) (result i32)
+      unreachable
+    )
+    (@producers
+      (processed-by "wit-component" "0.217.0")
+    )
+  )
+  (core instance (;0;) (instantiate 0))
+  (alias core export 0 "memory" (core memory (;0;)))
+  (type (;0;) (record (field "x" u32) (field "y" string)))
+  (type (;1;) (list 0))
+  (type (;2;) (func (param "p" 0) (result 1)))
+  (alias core export 0 "test:foo/types#draw" (core func (;0;)))
+  (alias core export 0 "cabi_realloc" (core func (;1;)))
+  (func (;0;) (type 2) (canon lift (core func 0) (memory 0) (realloc 1) string-encoding=utf8))
+  (component (;0;)
+    (type (;0;) (record (field "x" u32) (field "y" string)))
+    (import "import-type-point" (type (;1;) (eq 0)))
+    (type (;2;) (list 1))
+    (type (;3;) (func (param "p" 1) (result 2)))
+    (import "import-func-draw" (func (;0;) (type 3)))
+    (type (;4;) (record (field "x" u32) (field "y" string)))
+    (export (;5;) "point" (type 4))
+    (type (;6;) (list 5))
+    (type (;7;) (func (param "p" 5) (result 6)))
+    (export (;1;) "draw" (func 0) (func (type 7)))
+  )
+  (instance (;0;) (instantiate 0
+      (with "import-func-draw" (func 0))
+      (with "import-type-point" (type 0))
+    )
+  )
+  (export (;1;) "test:foo/types" (instance 0))
+  (@producers
+    (processed-by "wit-component" "0.217.0")
+  )
+)
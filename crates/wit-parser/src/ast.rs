@@ -1741,6 +1741,28 @@ impl SourceMap {
         })
     }
 
+    /// Parses the syntax of each file added to this source map without
+    /// resolving any names, returning the first syntax error of each file.
+    pub(crate) fn syntax_errors(&self) -> Vec<anyhow::Error> {
+        let mut ret = Vec::new();
+        for src in self.sources.iter() {
+            let result = self.rewrite_error(|| {
+                let mut tokens = Tokenizer::new(
+                    &src.contents[..src.contents.len() - 1],
+                    src.offset,
+                    self.require_f32_f64,
+                )
+                .with_context(|| format!("failed to tokenize path: {}", src.path.display()))?;
+                PackageFile::parse(&mut tokens)?;
+                Ok(())
+            });
+            if let Err(e) = result {
+                ret.push(e);
+            }
+        }
+        ret
+    }
+
     pub(crate) fn rewrite_error<F, T>(&self, f: F) -> Result<T>
     where
         F: FnOnce() -> Result<T>,
@@ -0,0 +1,1189 @@
+//! A reference implementation of lifting and lowering values with the
+//! canonical ABI.
+//!
+//! This module translates between concrete [`Value`]s and their canonical ABI
+//! representation, either in linear memory or as a sequence of flat core wasm
+//! values, given the [`Type`] of the value within a [`Resolve`]. It follows
+//! the definitions of `load`, `store`, `lift_flat` and `lower_flat` in the
+//! component model's `CanonicalABI.md` for 32-bit memories.
+//!
+//! Resources are represented by their handle index, as an `i32` in the
+//! canonical ABI, and it's up to the user of this module to manage the
+//! handle tables themselves.
+
+use crate::abi::WasmType;
+use crate::sizealign::align_to;
+use crate::{Handle, Int, Resolve, SizeAlign, Type, TypeDefKind};
+use anyhow::{bail, Context, Result};
+
+/// The tag in the length of a string that indicates it's encoded in UTF-16
+/// when using [`StringEncoding::CompactUtf16`].
+const UTF16_TAG: u32 = 1 << 31;
+
+/// The maximum byte length of a string in linear memory.
+const MAX_STRING_BYTE_LENGTH: usize = (1 << 31) - 1;
+
+/// A component model value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    S8(i8),
+    S16(i16),
+    S32(i32),
+    S64(i64),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    String(String),
+    List(Vec<Value>),
+    /// The values of each field of a record, in order.
+    Record(Vec<Value>),
+    Tuple(Vec<Value>),
+    /// The index of the case of a variant and its payload, if any.
+    Variant(u32, Option<Box<Value>>),
+    /// The index of the case of an enum.
+    Enum(u32),
+    Option(Option<Box<Value>>),
+    Result(Result<Option<Box<Value>>, Option<Box<Value>>>),
+    /// The indices of the flags which are set, in ascending order.
+    Flags(Vec<u32>),
+    /// An owned resource handle.
+    Own(u32),
+    /// A borrowed resource handle.
+    Borrow(u32),
+}
+
+/// A core wasm value used in the flat representation of a [`Value`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlatValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl FlatValue {
+    fn ty(&self) -> WasmType {
+        match self {
+            FlatValue::I32(_) => WasmType::I32,
+            FlatValue::I64(_) => WasmType::I64,
+            FlatValue::F32(_) => WasmType::F32,
+            FlatValue::F64(_) => WasmType::F64,
+        }
+    }
+
+    fn zero(ty: WasmType) -> FlatValue {
+        match ty {
+            WasmType::I64 => FlatValue::I64(0),
+            WasmType::F32 => FlatValue::F32(0.0),
+            WasmType::F64 => FlatValue::F64(0.0),
+            _ => FlatValue::I32(0),
+        }
+    }
+}
+
+/// The encoding of strings in linear memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringEncoding {
+    /// Strings are encoded as UTF-8.
+    #[default]
+    Utf8,
+    /// Strings are encoded as UTF-16, little-endian.
+    Utf16,
+    /// Strings are encoded as either latin1 or UTF-16, with the high bit of
+    /// the length indicating UTF-16.
+    CompactUtf16,
+}
+
+/// A linear memory that values are loaded from and stored to.
+pub trait Memory {
+    /// Returns the current contents of this memory.
+    fn as_slice(&self) -> &[u8];
+
+    /// Returns the current contents of this memory, mutably.
+    fn as_mut_slice(&mut self) -> &mut [u8];
+
+    /// Equivalent of the `realloc` canonical option, used to allocate space
+    /// for strings and lists when lowering values.
+    fn realloc(&mut self, old_ptr: u32, old_size: u32, align: u32, new_size: u32) -> Result<u32>;
+}
+
+/// A simple memory where `realloc` always appends a new allocation to the
+/// end, suitable for tests.
+impl Memory for Vec<u8> {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn realloc(&mut self, old_ptr: u32, old_size: u32, align: u32, new_size: u32) -> Result<u32> {
+        let ptr = align_to(self.len(), align as usize);
+        let old = old_ptr as usize..(old_ptr as usize + old_size.min(new_size) as usize);
+        self.resize(ptr + new_size as usize, 0);
+        self.copy_within(old, ptr);
+        u32::try_from(ptr).context("memory exhausted")
+    }
+}
+
+/// Lifts and lowers [`Value`]s of types defined in a [`Resolve`].
+pub struct CanonicalAbi<'a> {
+    resolve: &'a Resolve,
+    sizes: SizeAlign,
+    string_encoding: StringEncoding,
+}
+
+impl<'a> CanonicalAbi<'a> {
+    /// Creates a new instance for types within `resolve` which uses UTF-8
+    /// strings.
+    pub fn new(resolve: &'a Resolve) -> CanonicalAbi<'a> {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(resolve);
+        CanonicalAbi {
+            resolve,
+            sizes,
+            string_encoding: StringEncoding::default(),
+        }
+    }
+
+    /// Configures the encoding of strings in linear memory.
+    pub fn string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.string_encoding = encoding;
+        self
+    }
+
+    /// Returns the size, in bytes, of `ty` in linear memory.
+    pub fn size(&self, ty: &Type) -> usize {
+        self.sizes.size(ty).size_wasm32()
+    }
+
+    /// Returns the alignment, in bytes, of `ty` in linear memory.
+    pub fn align(&self, ty: &Type) -> usize {
+        self.sizes.align(ty).align_wasm32()
+    }
+
+    /// Returns the core wasm types of the flat representation of `ty`.
+    ///
+    /// Note that this isn't limited to `MAX_FLAT_PARAMS` or
+    /// `MAX_FLAT_RESULTS`, and it's up to the caller to pass values
+    /// indirectly through memory when there are too many.
+    pub fn flat_types(&self, ty: &Type) -> Vec<WasmType> {
+        let mut result = Vec::new();
+        self.push_flat(ty, &mut result);
+        result
+    }
+
+    /// Loads a value of type `ty` from `memory` at `ptr`.
+    pub fn load(&self, memory: &dyn Memory, ptr: u32, ty: &Type) -> Result<Value> {
+        self.check_range(memory, ptr, self.size(ty), self.align(ty))?;
+        self.load_at(memory, ptr, ty)
+    }
+
+    /// Stores `value`, of type `ty`, into `memory` at `ptr`.
+    ///
+    /// Strings and lists within `value` are allocated with
+    /// [`Memory::realloc`].
+    pub fn store(&self, memory: &mut dyn Memory, ptr: u32, ty: &Type, value: &Value) -> Result<()> {
+        self.check_range(memory, ptr, self.size(ty), self.align(ty))?;
+        self.store_at(memory, ptr, ty, value)
+    }
+
+    /// Lifts a value of type `ty` from its flat representation `flat`,
+    /// reading strings and lists from `memory`.
+    pub fn lift_flat(&self, memory: &dyn Memory, ty: &Type, flat: &[FlatValue]) -> Result<Value> {
+        let mut flat = flat.iter().copied();
+        let value = self.lift(memory, ty, &mut flat)?;
+        if flat.next().is_some() {
+            bail!("too many flat values provided for type");
+        }
+        Ok(value)
+    }
+
+    /// Lowers `value`, of type `ty`, to its flat representation, allocating
+    /// strings and lists in `memory`.
+    pub fn lower_flat(
+        &self,
+        memory: &mut dyn Memory,
+        ty: &Type,
+        value: &Value,
+    ) -> Result<Vec<FlatValue>> {
+        let mut result = Vec::new();
+        self.lower(memory, ty, value, &mut result)?;
+        Ok(result)
+    }
+
+    fn push_flat(&self, ty: &Type, result: &mut Vec<WasmType>) {
+        match self.shape(ty) {
+            Shape::Prim(Type::U64 | Type::S64) => result.push(WasmType::I64),
+            Shape::Prim(Type::F32) => result.push(WasmType::F32),
+            Shape::Prim(Type::F64) => result.push(WasmType::F64),
+            Shape::Prim(Type::String) | Shape::List(_) => {
+                result.push(WasmType::I32);
+                result.push(WasmType::I32);
+            }
+            Shape::Prim(_) | Shape::Handle(_) | Shape::Enum(_) => result.push(WasmType::I32),
+            Shape::Record(types) | Shape::Tuple(types) => {
+                for ty in types {
+                    self.push_flat(&ty, result);
+                }
+            }
+            Shape::Flags(n) => result.extend((0..flags_words(n)).map(|_| WasmType::I32)),
+            Shape::Variant(_, cases) => {
+                result.push(WasmType::I32);
+                result.extend(self.variant_payload_flat(&cases));
+            }
+            Shape::Unsupported(kind) => panic!("cannot flatten type: {kind}"),
+        }
+    }
+
+    /// Returns the joined flat types of the payloads of `cases`.
+    fn variant_payload_flat(&self, cases: &[Option<Type>]) -> Vec<WasmType> {
+        let mut result = Vec::new();
+        for ty in cases.iter().flatten() {
+            for (i, ty) in self.flat_types(ty).into_iter().enumerate() {
+                match result.get_mut(i) {
+                    Some(prev) => *prev = join(*prev, ty),
+                    None => result.push(ty),
+                }
+            }
+        }
+        result
+    }
+
+    fn load_at(&self, mem: &dyn Memory, ptr: u32, ty: &Type) -> Result<Value> {
+        Ok(match self.shape(ty) {
+            Shape::Prim(ty) => match ty {
+                Type::Bool => Value::Bool(read::<1>(mem, ptr)?[0] != 0),
+                Type::U8 => Value::U8(u8::from_le_bytes(read(mem, ptr)?)),
+                Type::U16 => Value::U16(u16::from_le_bytes(read(mem, ptr)?)),
+                Type::U32 => Value::U32(u32::from_le_bytes(read(mem, ptr)?)),
+                Type::U64 => Value::U64(u64::from_le_bytes(read(mem, ptr)?)),
+                Type::S8 => Value::S8(i8::from_le_bytes(read(mem, ptr)?)),
+                Type::S16 => Value::S16(i16::from_le_bytes(read(mem, ptr)?)),
+                Type::S32 => Value::S32(i32::from_le_bytes(read(mem, ptr)?)),
+                Type::S64 => Value::S64(i64::from_le_bytes(read(mem, ptr)?)),
+                Type::F32 => Value::F32(canonicalize_f32(f32::from_le_bytes(read(mem, ptr)?))),
+                Type::F64 => Value::F64(canonicalize_f64(f64::from_le_bytes(read(mem, ptr)?))),
+                Type::Char => Value::Char(to_char(u32::from_le_bytes(read(mem, ptr)?))?),
+                Type::String => {
+                    let (begin, len) = read_pair(mem, ptr)?;
+                    Value::String(self.load_string(mem, begin, len)?)
+                }
+                Type::Id(_) => unreachable!(),
+            },
+            Shape::List(elem) => {
+                let (begin, len) = read_pair(mem, ptr)?;
+                Value::List(self.load_list(mem, begin, len, &elem)?)
+            }
+            Shape::Record(types) => Value::Record(self.load_fields(mem, ptr, &types)?),
+            Shape::Tuple(types) => Value::Tuple(self.load_fields(mem, ptr, &types)?),
+            Shape::Flags(n) => {
+                let mut words = Vec::new();
+                match flags_size(n) {
+                    0 => {}
+                    1 => words.push(u32::from(read::<1>(mem, ptr)?[0])),
+                    2 => words.push(u32::from(u16::from_le_bytes(read(mem, ptr)?))),
+                    _ => {
+                        for i in 0..flags_words(n) {
+                            words.push(u32::from_le_bytes(read(mem, ptr + 4 * i as u32)?));
+                        }
+                    }
+                }
+                Value::Flags(unpack_flags(&words, n))
+            }
+            Shape::Variant(kind, cases) => {
+                let tag = kind.tag(cases.len());
+                let case = match tag {
+                    Int::U8 => u32::from(read::<1>(mem, ptr)?[0]),
+                    Int::U16 => u32::from(u16::from_le_bytes(read(mem, ptr)?)),
+                    Int::U32 | Int::U64 => u32::from_le_bytes(read(mem, ptr)?),
+                };
+                let payload_ty = match cases.get(case as usize) {
+                    Some(ty) => ty,
+                    None => bail!(
+                        "invalid discriminant {case} for a type with {} cases",
+                        cases.len()
+                    ),
+                };
+                let offset = self.payload_offset(tag, &cases);
+                let payload = match payload_ty {
+                    Some(ty) => Some(self.load_at(mem, ptr + offset, ty)?),
+                    None => None,
+                };
+                kind.value(case, payload)
+            }
+            Shape::Enum(n) => {
+                let case = match kind_tag(n) {
+                    Int::U8 => u32::from(read::<1>(mem, ptr)?[0]),
+                    Int::U16 => u32::from(u16::from_le_bytes(read(mem, ptr)?)),
+                    Int::U32 | Int::U64 => u32::from_le_bytes(read(mem, ptr)?),
+                };
+                if case as usize >= n {
+                    bail!("invalid discriminant {case} for an enum with {n} cases");
+                }
+                Value::Enum(case)
+            }
+            Shape::Handle(h) => h.value(u32::from_le_bytes(read(mem, ptr)?)),
+            Shape::Unsupported(kind) => bail!("values of type `{kind}` are not supported"),
+        })
+    }
+
+    fn load_fields(&self, mem: &dyn Memory, ptr: u32, types: &[Type]) -> Result<Vec<Value>> {
+        self.sizes
+            .field_offsets(types)
+            .into_iter()
+            .map(|(offset, ty)| self.load_at(mem, ptr + offset.size_wasm32() as u32, ty))
+            .collect()
+    }
+
+    fn load_list(&self, mem: &dyn Memory, begin: u32, len: u32, elem: &Type) -> Result<Vec<Value>> {
+        let size = self.size(elem);
+        let byte_len = (len as usize)
+            .checked_mul(size)
+            .context("list length overflows")?;
+        self.check_range(mem, begin, byte_len, self.align(elem))?;
+        (0..len)
+            .map(|i| self.load_at(mem, begin + i * size as u32, elem))
+            .collect()
+    }
+
+    fn load_string(&self, mem: &dyn Memory, begin: u32, tagged_len: u32) -> Result<String> {
+        let (encoding, align, byte_len) = match self.string_encoding {
+            StringEncoding::Utf8 => (StringEncoding::Utf8, 1, tagged_len as usize),
+            StringEncoding::Utf16 => (StringEncoding::Utf16, 2, 2 * tagged_len as usize),
+            StringEncoding::CompactUtf16 if tagged_len & UTF16_TAG != 0 => (
+                StringEncoding::Utf16,
+                2,
+                2 * (tagged_len ^ UTF16_TAG) as usize,
+            ),
+            // Note that a `CompactUtf16` encoding here indicates latin1.
+            StringEncoding::CompactUtf16 => (StringEncoding::CompactUtf16, 2, tagged_len as usize),
+        };
+        self.check_range(mem, begin, byte_len, align)?;
+        let bytes = slice(mem, begin, byte_len)?;
+        match encoding {
+            StringEncoding::Utf8 => Ok(std::str::from_utf8(bytes)
+                .context("invalid utf-8 string")?
+                .to_string()),
+            StringEncoding::Utf16 => {
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]));
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .context("invalid utf-16 string")
+            }
+            StringEncoding::CompactUtf16 => Ok(bytes.iter().map(|b| char::from(*b)).collect()),
+        }
+    }
+
+    fn store_at(&self, mem: &mut dyn Memory, ptr: u32, ty: &Type, value: &Value) -> Result<()> {
+        match (self.shape(ty), value) {
+            (Shape::Prim(Type::Bool), Value::Bool(v)) => write(mem, ptr, [u8::from(*v)]),
+            (Shape::Prim(Type::U8), Value::U8(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::U16), Value::U16(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::U32), Value::U32(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::U64), Value::U64(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::S8), Value::S8(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::S16), Value::S16(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::S32), Value::S32(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::S64), Value::S64(v)) => write(mem, ptr, v.to_le_bytes()),
+            (Shape::Prim(Type::F32), Value::F32(v)) => {
+                write(mem, ptr, canonicalize_f32(*v).to_le_bytes())
+            }
+            (Shape::Prim(Type::F64), Value::F64(v)) => {
+                write(mem, ptr, canonicalize_f64(*v).to_le_bytes())
+            }
+            (Shape::Prim(Type::Char), Value::Char(v)) => write(mem, ptr, (*v as u32).to_le_bytes()),
+            (Shape::Prim(Type::String), Value::String(s)) => {
+                let (begin, len) = self.store_string(mem, s)?;
+                write_pair(mem, ptr, begin, len)
+            }
+            (Shape::List(elem), Value::List(values)) => {
+                let (begin, len) = self.store_list(mem, &elem, values)?;
+                write_pair(mem, ptr, begin, len)
+            }
+            (Shape::Record(types), Value::Record(values))
+            | (Shape::Tuple(types), Value::Tuple(values)) => {
+                if types.len() != values.len() {
+                    bail!("expected {} fields, found {}", types.len(), values.len());
+                }
+                for ((offset, ty), value) in
+                    self.sizes.field_offsets(&types).into_iter().zip(values)
+                {
+                    self.store_at(mem, ptr + offset.size_wasm32() as u32, ty, value)?;
+                }
+                Ok(())
+            }
+            (Shape::Flags(n), Value::Flags(set)) => {
+                let words = pack_flags(set, n)?;
+                match flags_size(n) {
+                    0 => Ok(()),
+                    1 => write(mem, ptr, [words[0] as u8]),
+                    2 => write(mem, ptr, (words[0] as u16).to_le_bytes()),
+                    _ => {
+                        for (i, word) in words.iter().enumerate() {
+                            write(mem, ptr + 4 * i as u32, word.to_le_bytes())?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+            (Shape::Enum(n), Value::Enum(case)) => {
+                if *case as usize >= n {
+                    bail!("invalid case {case} for an enum with {n} cases");
+                }
+                write_tag(mem, ptr, kind_tag(n), *case)
+            }
+            (Shape::Variant(kind, cases), value) => {
+                let (case, payload) = kind.case(value)?;
+                let tag = kind.tag(cases.len());
+                let payload_ty = self.case_payload(&cases, case, payload)?;
+                write_tag(mem, ptr, tag, case)?;
+                let offset = self.payload_offset(tag, &cases);
+                match (payload_ty, payload) {
+                    (Some(ty), Some(payload)) => self.store_at(mem, ptr + offset, &ty, payload),
+                    _ => Ok(()),
+                }
+            }
+            (Shape::Handle(h), value) => write(mem, ptr, h.index(value)?.to_le_bytes()),
+            (Shape::Unsupported(kind), _) => bail!("values of type `{kind}` are not supported"),
+            (shape, _) => bail!("value does not match the expected type `{}`", shape.name()),
+        }
+    }
+
+    fn store_list(
+        &self,
+        mem: &mut dyn Memory,
+        elem: &Type,
+        values: &[Value],
+    ) -> Result<(u32, u32)> {
+        let size = self.size(elem);
+        let byte_len = values
+            .len()
+            .checked_mul(size)
+            .context("list length overflows")?;
+        let begin = self.alloc(mem, self.align(elem), byte_len)?;
+        for (i, value) in values.iter().enumerate() {
+            self.store_at(mem, begin + (i * size) as u32, elem, value)?;
+        }
+        Ok((begin, values.len() as u32))
+    }
+
+    fn store_string(&self, mem: &mut dyn Memory, s: &str) -> Result<(u32, u32)> {
+        let (bytes, align, len) = match self.string_encoding {
+            StringEncoding::Utf8 => (s.as_bytes().to_vec(), 1, s.len()),
+            StringEncoding::CompactUtf16 if s.chars().all(|c| (c as u32) < 0x100) => {
+                let bytes = s.chars().map(|c| c as u8).collect::<Vec<_>>();
+                let len = bytes.len();
+                (bytes, 2, len)
+            }
+            StringEncoding::Utf16 | StringEncoding::CompactUtf16 => {
+                let bytes = s
+                    .encode_utf16()
+                    .flat_map(|u| u.to_le_bytes())
+                    .collect::<Vec<_>>();
+                let len = bytes.len() / 2;
+                (bytes, 2, len)
+            }
+        };
+        if bytes.len() > MAX_STRING_BYTE_LENGTH {
+            bail!("string of {} bytes is too large", bytes.len());
+        }
+        let begin = self.alloc(mem, align, bytes.len())?;
+        slice_mut(mem, begin, bytes.len())?.copy_from_slice(&bytes);
+        let tagged_len = match self.string_encoding {
+            StringEncoding::CompactUtf16 if bytes.len() != len => len as u32 | UTF16_TAG,
+            _ => len as u32,
+        };
+        Ok((begin, tagged_len))
+    }
+
+    fn alloc(&self, mem: &mut dyn Memory, align: usize, size: usize) -> Result<u32> {
+        let size = u32::try_from(size).context("allocation too large")?;
+        let ptr = mem.realloc(0, 0, align as u32, size)?;
+        self.check_range(mem, ptr, size as usize, align)
+            .context("invalid pointer returned from `realloc`")?;
+        Ok(ptr)
+    }
+
+    fn lift(
+        &self,
+        mem: &dyn Memory,
+        ty: &Type,
+        flat: &mut dyn Iterator<Item = FlatValue>,
+    ) -> Result<Value> {
+        Ok(match self.shape(ty) {
+            Shape::Prim(ty) => match ty {
+                Type::Bool => Value::Bool(next_i32(flat)? != 0),
+                Type::U8 => Value::U8(next_i32(flat)? as u8),
+                Type::U16 => Value::U16(next_i32(flat)? as u16),
+                Type::U32 => Value::U32(next_i32(flat)? as u32),
+                Type::U64 => Value::U64(next_i64(flat)? as u64),
+                Type::S8 => Value::S8(next_i32(flat)? as i8),
+                Type::S16 => Value::S16(next_i32(flat)? as i16),
+                Type::S32 => Value::S32(next_i32(flat)?),
+                Type::S64 => Value::S64(next_i64(flat)?),
+                Type::F32 => match next(flat, WasmType::F32)? {
+                    FlatValue::F32(f) => Value::F32(canonicalize_f32(f)),
+                    _ => unreachable!(),
+                },
+                Type::F64 => match next(flat, WasmType::F64)? {
+                    FlatValue::F64(f) => Value::F64(canonicalize_f64(f)),
+                    _ => unreachable!(),
+                },
+                Type::Char => Value::Char(to_char(next_i32(flat)? as u32)?),
+                Type::String => {
+                    let begin = next_i32(flat)? as u32;
+                    let len = next_i32(flat)? as u32;
+                    Value::String(self.load_string(mem, begin, len)?)
+                }
+                Type::Id(_) => unreachable!(),
+            },
+            Shape::List(elem) => {
+                let begin = next_i32(flat)? as u32;
+                let len = next_i32(flat)? as u32;
+                Value::List(self.load_list(mem, begin, len, &elem)?)
+            }
+            Shape::Record(types) => Value::Record(
+                types
+                    .iter()
+                    .map(|ty| self.lift(mem, ty, flat))
+                    .collect::<Result<_>>()?,
+            ),
+            Shape::Tuple(types) => Value::Tuple(
+                types
+                    .iter()
+                    .map(|ty| self.lift(mem, ty, flat))
+                    .collect::<Result<_>>()?,
+            ),
+            Shape::Flags(n) => {
+                let words = (0..flags_words(n))
+                    .map(|_| Ok(next_i32(flat)? as u32))
+                    .collect::<Result<Vec<_>>>()?;
+                Value::Flags(unpack_flags(&words, n))
+            }
+            Shape::Enum(n) => {
+                let case = next_i32(flat)? as u32;
+                if case as usize >= n {
+                    bail!("invalid discriminant {case} for an enum with {n} cases");
+                }
+                Value::Enum(case)
+            }
+            Shape::Variant(kind, cases) => {
+                let case = next_i32(flat)? as u32;
+                let joined = self.variant_payload_flat(&cases);
+                let slots = joined
+                    .iter()
+                    .map(|ty| next(flat, *ty))
+                    .collect::<Result<Vec<_>>>()?;
+                let payload_ty = match cases.get(case as usize) {
+                    Some(ty) => ty,
+                    None => bail!(
+                        "invalid discriminant {case} for a type with {} cases",
+                        cases.len()
+                    ),
+                };
+                let payload = match payload_ty {
+                    Some(ty) => {
+                        let payload = self
+                            .flat_types(ty)
+                            .into_iter()
+                            .zip(slots)
+                            .map(|(want, have)| coerce_from_joined(have, want))
+                            .collect::<Result<Vec<_>>>()?;
+                        Some(self.lift(mem, ty, &mut payload.into_iter())?)
+                    }
+                    None => None,
+                };
+                kind.value(case, payload)
+            }
+            Shape::Handle(h) => h.value(next_i32(flat)? as u32),
+            Shape::Unsupported(kind) => bail!("values of type `{kind}` are not supported"),
+        })
+    }
+
+    fn lower(
+        &self,
+        mem: &mut dyn Memory,
+        ty: &Type,
+        value: &Value,
+        result: &mut Vec<FlatValue>,
+    ) -> Result<()> {
+        match (self.shape(ty), value) {
+            (Shape::Prim(Type::Bool), Value::Bool(v)) => result.push(FlatValue::I32((*v).into())),
+            (Shape::Prim(Type::U8), Value::U8(v)) => result.push(FlatValue::I32((*v).into())),
+            (Shape::Prim(Type::U16), Value::U16(v)) => result.push(FlatValue::I32((*v).into())),
+            (Shape::Prim(Type::U32), Value::U32(v)) => result.push(FlatValue::I32(*v as i32)),
+            (Shape::Prim(Type::U64), Value::U64(v)) => result.push(FlatValue::I64(*v as i64)),
+            (Shape::Prim(Type::S8), Value::S8(v)) => result.push(FlatValue::I32((*v).into())),
+            (Shape::Prim(Type::S16), Value::S16(v)) => result.push(FlatValue::I32((*v).into())),
+            (Shape::Prim(Type::S32), Value::S32(v)) => result.push(FlatValue::I32(*v)),
+            (Shape::Prim(Type::S64), Value::S64(v)) => result.push(FlatValue::I64(*v)),
+            (Shape::Prim(Type::F32), Value::F32(v)) => {
+                result.push(FlatValue::F32(canonicalize_f32(*v)))
+            }
+            (Shape::Prim(Type::F64), Value::F64(v)) => {
+                result.push(FlatValue::F64(canonicalize_f64(*v)))
+            }
+            (Shape::Prim(Type::Char), Value::Char(v)) => result.push(FlatValue::I32(*v as i32)),
+            (Shape::Prim(Type::String), Value::String(s)) => {
+                let (begin, len) = self.store_string(mem, s)?;
+                result.push(FlatValue::I32(begin as i32));
+                result.push(FlatValue::I32(len as i32));
+            }
+            (Shape::List(elem), Value::List(values)) => {
+                let (begin, len) = self.store_list(mem, &elem, values)?;
+                result.push(FlatValue::I32(begin as i32));
+                result.push(FlatValue::I32(len as i32));
+            }
+            (Shape::Record(types), Value::Record(values))
+            | (Shape::Tuple(types), Value::Tuple(values)) => {
+                if types.len() != values.len() {
+                    bail!("expected {} fields, found {}", types.len(), values.len());
+                }
+                for (ty, value) in types.iter().zip(values) {
+                    self.lower(mem, ty, value, result)?;
+                }
+            }
+            (Shape::Flags(n), Value::Flags(set)) => {
+                let words = pack_flags(set, n)?;
+                result.extend(words.into_iter().map(|w| FlatValue::I32(w as i32)));
+            }
+            (Shape::Enum(n), Value::Enum(case)) => {
+                if *case as usize >= n {
+                    bail!("invalid case {case} for an enum with {n} cases");
+                }
+                result.push(FlatValue::I32(*case as i32));
+            }
+            (Shape::Variant(kind, cases), value) => {
+                let (case, payload) = kind.case(value)?;
+                let payload_ty = self.case_payload(&cases, case, payload)?;
+                result.push(FlatValue::I32(case as i32));
+                let mut lowered = Vec::new();
+                if let (Some(ty), Some(payload)) = (payload_ty, payload) {
+                    self.lower(mem, &ty, payload, &mut lowered)?;
+                }
+                let joined = self.variant_payload_flat(&cases);
+                for (i, want) in joined.into_iter().enumerate() {
+                    result.push(match lowered.get(i) {
+                        Some(have) => coerce_to_joined(*have, want),
+                        None => FlatValue::zero(want),
+                    });
+                }
+            }
+            (Shape::Handle(h), value) => result.push(FlatValue::I32(h.index(value)? as i32)),
+            (Shape::Unsupported(kind), _) => bail!("values of type `{kind}` are not supported"),
+            (shape, _) => bail!("value does not match the expected type `{}`", shape.name()),
+        }
+        Ok(())
+    }
+
+    fn case_payload(
+        &self,
+        cases: &[Option<Type>],
+        case: u32,
+        payload: Option<&Value>,
+    ) -> Result<Option<Type>> {
+        match (cases.get(case as usize), payload) {
+            (None, _) => bail!("invalid case {case} for a type with {} cases", cases.len()),
+            (Some(Some(ty)), Some(_)) => Ok(Some(*ty)),
+            (Some(None), None) => Ok(None),
+            (Some(Some(_)), None) => bail!("missing payload for case {case}"),
+            (Some(None), Some(_)) => bail!("unexpected payload for case {case}"),
+        }
+    }
+
+    fn payload_offset(&self, tag: Int, cases: &[Option<Type>]) -> u32 {
+        self.sizes
+            .payload_offset(tag, cases.iter().map(|c| c.as_ref()))
+            .size_wasm32() as u32
+    }
+
+    fn check_range(&self, mem: &dyn Memory, ptr: u32, len: usize, align: usize) -> Result<()> {
+        if ptr as usize % align != 0 {
+            bail!("pointer {ptr:#x} is not aligned to {align} bytes");
+        }
+        slice(mem, ptr, len)?;
+        Ok(())
+    }
+
+    /// Classifies `ty`, following any type aliases.
+    fn shape(&self, ty: &Type) -> Shape {
+        let mut ty = *ty;
+        loop {
+            let id = match ty {
+                Type::Id(id) => id,
+                _ => return Shape::Prim(ty),
+            };
+            return match &self.resolve.types[id].kind {
+                TypeDefKind::Type(t) => {
+                    ty = *t;
+                    continue;
+                }
+                TypeDefKind::Record(r) => Shape::Record(r.fields.iter().map(|f| f.ty).collect()),
+                TypeDefKind::Tuple(t) => Shape::Tuple(t.types.clone()),
+                TypeDefKind::Flags(f) => Shape::Flags(f.flags.len()),
+                TypeDefKind::Enum(e) => Shape::Enum(e.cases.len()),
+                TypeDefKind::Variant(v) => {
+                    Shape::Variant(VariantKind::Variant, v.cases.iter().map(|c| c.ty).collect())
+                }
+                TypeDefKind::Option(t) => Shape::Variant(VariantKind::Option, vec![None, Some(*t)]),
+                TypeDefKind::Result(r) => Shape::Variant(VariantKind::Result, vec![r.ok, r.err]),
+                TypeDefKind::List(t) => Shape::List(*t),
+                TypeDefKind::Handle(h) => Shape::Handle(*h),
+                kind => Shape::Unsupported(kind.as_str()),
+            };
+        }
+    }
+}
+
+/// The structure of a type as far as the canonical ABI is concerned.
+enum Shape {
+    Prim(Type),
+    List(Type),
+    Record(Vec<Type>),
+    Tuple(Vec<Type>),
+    Flags(usize),
+    Enum(usize),
+    Variant(VariantKind, Vec<Option<Type>>),
+    Handle(Handle),
+    Unsupported(&'static str),
+}
+
+impl Shape {
+    fn name(&self) -> &'static str {
+        match self {
+            Shape::Prim(Type::Bool) => "bool",
+            Shape::Prim(Type::U8) => "u8",
+            Shape::Prim(Type::U16) => "u16",
+            Shape::Prim(Type::U32) => "u32",
+            Shape::Prim(Type::U64) => "u64",
+            Shape::Prim(Type::S8) => "s8",
+            Shape::Prim(Type::S16) => "s16",
+            Shape::Prim(Type::S32) => "s32",
+            Shape::Prim(Type::S64) => "s64",
+            Shape::Prim(Type::F32) => "f32",
+            Shape::Prim(Type::F64) => "f64",
+            Shape::Prim(Type::Char) => "char",
+            Shape::Prim(Type::String) => "string",
+            Shape::Prim(Type::Id(_)) => unreachable!(),
+            Shape::List(_) => "list",
+            Shape::Record(_) => "record",
+            Shape::Tuple(_) => "tuple",
+            Shape::Flags(_) => "flags",
+            Shape::Enum(_) => "enum",
+            Shape::Variant(VariantKind::Variant, _) => "variant",
+            Shape::Variant(VariantKind::Option, _) => "option",
+            Shape::Variant(VariantKind::Result, _) => "result",
+            Shape::Handle(Handle::Own(_)) => "own",
+            Shape::Handle(Handle::Borrow(_)) => "borrow",
+            Shape::Unsupported(kind) => kind,
+        }
+    }
+}
+
+/// Which kind of [`Value`] a variant-like type corresponds to.
+#[derive(Clone, Copy)]
+enum VariantKind {
+    Variant,
+    Option,
+    Result,
+}
+
+impl VariantKind {
+    fn tag(&self, cases: usize) -> Int {
+        match self {
+            VariantKind::Variant => kind_tag(cases),
+            VariantKind::Option | VariantKind::Result => Int::U8,
+        }
+    }
+
+    fn value(&self, case: u32, payload: Option<Value>) -> Value {
+        let payload = payload.map(Box::new);
+        match (self, case) {
+            (VariantKind::Variant, _) => Value::Variant(case, payload),
+            (VariantKind::Option, 0) => Value::Option(None),
+            (VariantKind::Option, _) => Value::Option(payload),
+            (VariantKind::Result, 0) => Value::Result(Ok(payload)),
+            (VariantKind::Result, _) => Value::Result(Err(payload)),
+        }
+    }
+
+    fn case<'v>(&self, value: &'v Value) -> Result<(u32, Option<&'v Value>)> {
+        Ok(match (self, value) {
+            (VariantKind::Variant, Value::Variant(case, payload)) => (*case, payload.as_deref()),
+            (VariantKind::Option, Value::Option(None)) => (0, None),
+            (VariantKind::Option, Value::Option(Some(v))) => (1, Some(v)),
+            (VariantKind::Result, Value::Result(Ok(v))) => (0, v.as_deref()),
+            (VariantKind::Result, Value::Result(Err(v))) => (1, v.as_deref()),
+            (VariantKind::Variant, _) => bail!("value does not match the expected type `variant`"),
+            (VariantKind::Option, _) => bail!("value does not match the expected type `option`"),
+            (VariantKind::Result, _) => bail!("value does not match the expected type `result`"),
+        })
+    }
+}
+
+trait HandleExt {
+    fn value(&self, index: u32) -> Value;
+    fn index(&self, value: &Value) -> Result<u32>;
+}
+
+impl HandleExt for Handle {
+    fn value(&self, index: u32) -> Value {
+        match self {
+            Handle::Own(_) => Value::Own(index),
+            Handle::Borrow(_) => Value::Borrow(index),
+        }
+    }
+
+    fn index(&self, value: &Value) -> Result<u32> {
+        match (self, value) {
+            (Handle::Own(_), Value::Own(i)) | (Handle::Borrow(_), Value::Borrow(i)) => Ok(*i),
+            (Handle::Own(_), _) => bail!("value does not match the expected type `own`"),
+            (Handle::Borrow(_), _) => bail!("value does not match the expected type `borrow`"),
+        }
+    }
+}
+
+/// This corresponds to `discriminant_type` in the canonical ABI.
+fn kind_tag(cases: usize) -> Int {
+    match cases {
+        0..=0x100 => Int::U8,
+        0x101..=0x1_0000 => Int::U16,
+        _ => Int::U32,
+    }
+}
+
+/// This corresponds to `join` in the canonical ABI.
+fn join(a: WasmType, b: WasmType) -> WasmType {
+    match (a, b) {
+        _ if a == b => a,
+        (WasmType::I32, WasmType::F32) | (WasmType::F32, WasmType::I32) => WasmType::I32,
+        _ => WasmType::I64,
+    }
+}
+
+/// Converts `have`, a value of a joined variant payload type, to the type
+/// `want` of the payload of a particular case.
+fn coerce_from_joined(have: FlatValue, want: WasmType) -> Result<FlatValue> {
+    let wrap = |i: i64| match u32::try_from(i) {
+        Ok(i) => Ok(i),
+        Err(_) => bail!("value {i:#x} out of range for an `i32` payload"),
+    };
+    Ok(match (have, want) {
+        (FlatValue::I32(i), WasmType::F32) => FlatValue::F32(f32::from_bits(i as u32)),
+        (FlatValue::I64(i), WasmType::I32) => FlatValue::I32(wrap(i)? as i32),
+        (FlatValue::I64(i), WasmType::F32) => FlatValue::F32(f32::from_bits(wrap(i)?)),
+        (FlatValue::I64(i), WasmType::F64) => FlatValue::F64(f64::from_bits(i as u64)),
+        (have, _) => have,
+    })
+}
+
+/// The inverse of [`coerce_from_joined`].
+fn coerce_to_joined(have: FlatValue, want: WasmType) -> FlatValue {
+    match (have, want) {
+        (FlatValue::F32(f), WasmType::I32) => FlatValue::I32(f.to_bits() as i32),
+        (FlatValue::I32(i), WasmType::I64) => FlatValue::I64(i64::from(i as u32)),
+        (FlatValue::F32(f), WasmType::I64) => FlatValue::I64(i64::from(f.to_bits())),
+        (FlatValue::F64(f), WasmType::I64) => FlatValue::I64(f.to_bits() as i64),
+        (have, _) => have,
+    }
+}
+
+fn next(flat: &mut dyn Iterator<Item = FlatValue>, ty: WasmType) -> Result<FlatValue> {
+    match flat.next() {
+        Some(v) if v.ty() == ty => Ok(v),
+        Some(v) => bail!(
+            "expected a flat value of type `{ty:?}`, found `{:?}`",
+            v.ty()
+        ),
+        None => bail!("not enough flat values provided for type"),
+    }
+}
+
+fn next_i32(flat: &mut dyn Iterator<Item = FlatValue>) -> Result<i32> {
+    match next(flat, WasmType::I32)? {
+        FlatValue::I32(i) => Ok(i),
+        _ => unreachable!(),
+    }
+}
+
+fn next_i64(flat: &mut dyn Iterator<Item = FlatValue>) -> Result<i64> {
+    match next(flat, WasmType::I64)? {
+        FlatValue::I64(i) => Ok(i),
+        _ => unreachable!(),
+    }
+}
+
+fn flags_size(n: usize) -> usize {
+    match n {
+        0 => 0,
+        n if n <= 8 => 1,
+        n if n <= 16 => 2,
+        n => 4 * flags_words(n),
+    }
+}
+
+fn flags_words(n: usize) -> usize {
+    align_to(n, 32) / 32
+}
+
+fn unpack_flags(words: &[u32], n: usize) -> Vec<u32> {
+    (0..n as u32)
+        .filter(|i| words[*i as usize / 32] & (1 << (i % 32)) != 0)
+        .collect()
+}
+
+fn pack_flags(set: &[u32], n: usize) -> Result<Vec<u32>> {
+    let mut words = vec![0; flags_words(n)];
+    for i in set {
+        if *i as usize >= n {
+            bail!("invalid flag {i} for a flags type with {n} flags");
+        }
+        words[*i as usize / 32] |= 1 << (i % 32);
+    }
+    Ok(words)
+}
+
+fn to_char(i: u32) -> Result<char> {
+    char::from_u32(i).with_context(|| format!("invalid char {i:#x}"))
+}
+
+fn canonicalize_f32(f: f32) -> f32 {
+    if f.is_nan() {
+        f32::from_bits(0x7fc00000)
+    } else {
+        f
+    }
+}
+
+fn canonicalize_f64(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::from_bits(0x7ff8000000000000)
+    } else {
+        f
+    }
+}
+
+fn slice(mem: &dyn Memory, ptr: u32, len: usize) -> Result<&[u8]> {
+    let start = ptr as usize;
+    match start
+        .checked_add(len)
+        .and_then(|end| mem.as_slice().get(start..end))
+    {
+        Some(bytes) => Ok(bytes),
+        None => bail!("out of bounds access of {len} bytes at {ptr:#x}"),
+    }
+}
+
+fn slice_mut(mem: &mut dyn Memory, ptr: u32, len: usize) -> Result<&mut [u8]> {
+    let start = ptr as usize;
+    match start
+        .checked_add(len)
+        .and_then(|end| mem.as_mut_slice().get_mut(start..end))
+    {
+        Some(bytes) => Ok(bytes),
+        None => bail!("out of bounds access of {len} bytes at {ptr:#x}"),
+    }
+}
+
+fn read<const N: usize>(mem: &dyn Memory, ptr: u32) -> Result<[u8; N]> {
+    Ok(slice(mem, ptr, N)?.try_into().unwrap())
+}
+
+fn read_pair(mem: &dyn Memory, ptr: u32) -> Result<(u32, u32)> {
+    let a = u32::from_le_bytes(read(mem, ptr)?);
+    let b = u32::from_le_bytes(read(mem, ptr + 4)?);
+    Ok((a, b))
+}
+
+fn write<const N: usize>(mem: &mut dyn Memory, ptr: u32, bytes: [u8; N]) -> Result<()> {
+    slice_mut(mem, ptr, N)?.copy_from_slice(&bytes);
+    Ok(())
+}
+
+fn write_pair(mem: &mut dyn Memory, ptr: u32, a: u32, b: u32) -> Result<()> {
+    write(mem, ptr, a.to_le_bytes())?;
+    write(mem, ptr + 4, b.to_le_bytes())
+}
+
+fn write_tag(mem: &mut dyn Memory, ptr: u32, tag: Int, case: u32) -> Result<()> {
+    match tag {
+        Int::U8 => write(mem, ptr, [case as u8]),
+        Int::U16 => write(mem, ptr, (case as u16).to_le_bytes()),
+        Int::U32 | Int::U64 => write(mem, ptr, case.to_le_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UnresolvedPackageGroup;
+
+    fn resolve() -> Resolve {
+        let mut resolve = Resolve::default();
+        let group = UnresolvedPackageGroup::parse(
+            "test.wit",
+            r#"
+                package a:b;
+
+                interface types {
+                    record point { x: u8, y: f64, name: string }
+                    variant shape { none, int(s32), float(f32), wide(u64), text(string) }
+                    flags perms { r, w, x }
+                    enum color { red, green, blue }
+                    type maybe = option<list<point>>;
+                    type res = result<char, string>;
+                    resource thing;
+                    type handle = own<thing>;
+                }
+            "#,
+        )
+        .unwrap();
+        resolve.push_group(group).unwrap();
+        resolve
+    }
+
+    fn ty(resolve: &Resolve, name: &str) -> Type {
+        let (_, iface) = resolve.interfaces.iter().next().unwrap();
+        Type::Id(iface.types[name])
+    }
+
+    fn point(x: u8, y: f64, name: &str) -> Value {
+        Value::Record(vec![
+            Value::U8(x),
+            Value::F64(y),
+            Value::String(name.to_string()),
+        ])
+    }
+
+    fn roundtrip(abi: &CanonicalAbi<'_>, ty: &Type, value: Value) {
+        let mut mem = vec![0; abi.size(ty)];
+        abi.store(&mut mem, 0, ty, &value).unwrap();
+        assert_eq!(abi.load(&mem, 0, ty).unwrap(), value);
+
+        let flat = abi.lower_flat(&mut mem, ty, &value).unwrap();
+        let types = flat.iter().map(|f| f.ty()).collect::<Vec<_>>();
+        assert_eq!(types, abi.flat_types(ty));
+        assert_eq!(abi.lift_flat(&mem, ty, &flat).unwrap(), value);
+    }
+
+    #[test]
+    fn roundtrip_values() {
+        let resolve = resolve();
+        for encoding in [
+            StringEncoding::Utf8,
+            StringEncoding::Utf16,
+            StringEncoding::CompactUtf16,
+        ] {
+            let abi = CanonicalAbi::new(&resolve).string_encoding(encoding);
+            roundtrip(&abi, &Type::S16, Value::S16(-2));
+            roundtrip(&abi, &Type::Char, Value::Char('☃'));
+            roundtrip(&abi, &Type::String, Value::String("héllo ☃".to_string()));
+            roundtrip(&abi, &Type::String, Value::String("héllo".to_string()));
+            roundtrip(&abi, &ty(&resolve, "point"), point(1, 2.5, "p"));
+            roundtrip(&abi, &ty(&resolve, "perms"), Value::Flags(vec![0, 2]));
+            roundtrip(&abi, &ty(&resolve, "color"), Value::Enum(2));
+            roundtrip(&abi, &ty(&resolve, "handle"), Value::Own(7));
+            roundtrip(
+                &abi,
+                &ty(&resolve, "maybe"),
+                Value::Option(Some(Box::new(Value::List(vec![
+                    point(1, 1.0, "a"),
+                    point(2, -1.0, "☃"),
+                ])))),
+            );
+            roundtrip(
+                &abi,
+                &ty(&resolve, "res"),
+                Value::Result(Err(Some(Box::new(Value::String("e".to_string()))))),
+            );
+            let shape = ty(&resolve, "shape");
+            roundtrip(&abi, &shape, Value::Variant(0, None));
+            roundtrip(
+                &abi,
+                &shape,
+                Value::Variant(1, Some(Box::new(Value::S32(-1)))),
+            );
+            roundtrip(
+                &abi,
+                &shape,
+                Value::Variant(2, Some(Box::new(Value::F32(1.5)))),
+            );
+            roundtrip(
+                &abi,
+                &shape,
+                Value::Variant(3, Some(Box::new(Value::U64(1 << 40)))),
+            );
+            roundtrip(
+                &abi,
+                &shape,
+                Value::Variant(4, Some(Box::new(Value::String("s".to_string())))),
+            );
+        }
+    }
+
+    #[test]
+    fn variant_flat_coercion() {
+        let resolve = resolve();
+        let abi = CanonicalAbi::new(&resolve);
+        let shape = ty(&resolve, "shape");
+        assert_eq!(
+            abi.flat_types(&shape),
+            [WasmType::I32, WasmType::I64, WasmType::I32]
+        );
+        let value = Value::Variant(2, Some(Box::new(Value::F32(1.5))));
+        let flat = abi.lower_flat(&mut Vec::new(), &shape, &value).unwrap();
+        assert_eq!(
+            flat,
+            [
+                FlatValue::I32(2),
+                FlatValue::I64(i64::from(1.5f32.to_bits())),
+                FlatValue::I32(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn compact_utf16_tagging() {
+        let resolve = Resolve::default();
+        let abi = CanonicalAbi::new(&resolve).string_encoding(StringEncoding::CompactUtf16);
+        let mut mem = Vec::new();
+        let flat = abi
+            .lower_flat(&mut mem, &Type::String, &Value::String("hé".to_string()))
+            .unwrap();
+        assert_eq!(flat[1], FlatValue::I32(2));
+        let flat = abi
+            .lower_flat(&mut mem, &Type::String, &Value::String("h☃".to_string()))
+            .unwrap();
+        assert_eq!(flat[1], FlatValue::I32((2 | UTF16_TAG) as i32));
+    }
+
+    #[test]
+    fn traps() {
+        let resolve = resolve();
+        let abi = CanonicalAbi::new(&resolve);
+        let mem = vec![0xff; 16];
+        assert!(abi.load(&mem, 1, &Type::U32).is_err());
+        assert!(abi.load(&mem, 16, &Type::U8).is_err());
+        assert!(abi.load(&mem, 0, &Type::Char).is_err());
+        assert!(abi.load(&mem, 0, &ty(&resolve, "color")).is_err());
+        assert!(abi.load(&mem, 0, &Type::String).is_err());
+        assert!(abi
+            .lift_flat(&mem, &Type::Char, &[FlatValue::I32(0xd800)])
+            .is_err());
+        assert!(abi
+            .lift_flat(&mem, &Type::U8, &[FlatValue::I64(0)])
+            .is_err());
+        assert!(abi
+            .store(&mut vec![0; 4], 0, &Type::U32, &Value::Bool(true))
+            .is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Structured access to documentation comments in WIT.
+//!
+//! Doc comments in WIT are Markdown and are stored verbatim in [`Docs`]. This
+//! module provides a light-weight model on top of that Markdown which splits
+//! out the summary line, the remaining body, and conventional sections such as
+//! `# Parameters` and `# Returns` so documentation generators don't have to
+//! re-lex `*.wit` sources or re-implement these conventions themselves.
+
+use crate::{
+    Docs, Function, Interface, PackageId, Resolve, TypeDef, TypeDefKind, World, WorldItem, WorldKey,
+};
+use indexmap::IndexMap;
+
+#[cfg(feature = "serde")]
+use serde_derive::Serialize;
+
+/// A parsed representation of the Markdown within a [`Docs`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct StructuredDocs {
+    /// The first paragraph of the documentation, with lines joined by spaces.
+    pub summary: String,
+
+    /// The remaining Markdown after the summary, with any recognized sections
+    /// removed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub body: Option<String>,
+
+    /// Documentation for individual parameters, keyed by parameter name.
+    ///
+    /// This is extracted from list items of the form ``- `name`: text`` or
+    /// `- name - text` found under a `# Parameters`, `# Params`, or
+    /// `# Arguments` heading of any level.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub params: IndexMap<String, String>,
+
+    /// Documentation of the return value found under a `# Returns` or
+    /// `# Results` heading of any level.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub returns: Option<String>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Section {
+    Body,
+    Params,
+    Returns,
+}
+
+impl Docs {
+    /// Parses the Markdown contents of these docs into a [`StructuredDocs`].
+    ///
+    /// Returns `None` if there are no docs.
+    pub fn structured(&self) -> Option<StructuredDocs> {
+        let contents = self.contents.as_deref()?;
+        Some(StructuredDocs::parse(contents))
+    }
+}
+
+impl StructuredDocs {
+    /// Parses the Markdown `contents` of a doc comment.
+    pub fn parse(contents: &str) -> StructuredDocs {
+        let mut ret = StructuredDocs::default();
+        let mut lines = contents.trim().lines().peekable();
+
+        // The summary is the first paragraph, unless the docs start with a
+        // heading in which case there is no summary.
+        let mut summary = Vec::new();
+        while let Some(line) = lines.peek() {
+            let line = line.trim();
+            if line.is_empty() || (summary.is_empty() && line.starts_with('#')) {
+                break;
+            }
+            summary.push(line);
+            lines.next();
+        }
+        ret.summary = summary.join(" ");
+
+        let mut section = Section::Body;
+        let mut in_code_block = false;
+        let mut body = Vec::new();
+        let mut returns = Vec::new();
+        let mut current_param: Option<String> = None;
+        for line in lines {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+            if !in_code_block && trimmed.starts_with('#') {
+                let title = trimmed.trim_start_matches('#').trim().to_lowercase();
+                section = match title.as_str() {
+                    "parameters" | "params" | "arguments" => Section::Params,
+                    "returns" | "results" | "return value" => Section::Returns,
+                    _ => Section::Body,
+                };
+                current_param = None;
+                if section != Section::Body {
+                    continue;
+                }
+            }
+            match section {
+                Section::Body => body.push(line),
+                Section::Returns => returns.push(trimmed),
+                Section::Params => {
+                    if let Some((name, doc)) = parse_param_item(trimmed) {
+                        ret.params.insert(name.to_string(), doc.to_string());
+                        current_param = Some(name.to_string());
+                    } else if let Some(name) = &current_param {
+                        if trimmed.is_empty() {
+                            current_param = None;
+                        } else {
+                            let doc = &mut ret.params[name];
+                            if !doc.is_empty() {
+                                doc.push(' ');
+                            }
+                            doc.push_str(trimmed);
+                        }
+                    } else if !trimmed.is_empty() {
+                        body.push(line);
+                    }
+                }
+            }
+        }
+
+        let body = body.join("\n");
+        let body = body.trim();
+        if !body.is_empty() {
+            ret.body = Some(body.to_string());
+        }
+        let returns = returns.join("\n");
+        let returns = returns.trim();
+        if !returns.is_empty() {
+            ret.returns = Some(returns.to_string());
+        }
+        ret
+    }
+}
+
+/// Parses a Markdown list item documenting a parameter, returning the name of
+/// the parameter and its documentation.
+fn parse_param_item(line: &str) -> Option<(&str, &str)> {
+    let item = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))?
+        .trim_start();
+    let (name, rest) = match item.strip_prefix('`') {
+        Some(item) => {
+            let end = item.find('`')?;
+            (&item[..end], &item[end + 1..])
+        }
+        None => {
+            let end = item
+                .find(|c: char| c != '-' && !c.is_alphanumeric())
+                .unwrap_or(item.len());
+            (&item[..end], &item[end..])
+        }
+    };
+    if name.is_empty() {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let rest = rest
+        .strip_prefix(':')
+        .or_else(|| rest.strip_prefix("- "))
+        .or_else(|| rest.strip_prefix("--"))
+        .unwrap_or(rest);
+    Some((name, rest.trim()))
+}
+
+/// Documentation of all items within a package, as returned by
+/// [`Resolve::package_docs`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageDocs {
+    /// The name of the package, such as `wasi:http@0.2.0`.
+    pub name: String,
+    /// Documentation for the package itself.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub docs: Option<StructuredDocs>,
+    /// Documentation for each interface in the package, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub interfaces: IndexMap<String, InterfaceDocs>,
+    /// Documentation for each world in the package, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub worlds: IndexMap<String, WorldDocs>,
+}
+
+/// Documentation for an interface and its contents.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct InterfaceDocs {
+    /// Documentation for the interface itself.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub docs: Option<StructuredDocs>,
+    /// Documentation for each type in the interface, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub types: IndexMap<String, TypeDocs>,
+    /// Documentation for each function in the interface, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub functions: IndexMap<String, StructuredDocs>,
+}
+
+/// Documentation for a type definition and its members.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TypeDocs {
+    /// Documentation for the type itself.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub docs: Option<StructuredDocs>,
+    /// Documentation for the fields of a record, cases of a variant or enum,
+    /// or flags of a flags type, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub members: IndexMap<String, StructuredDocs>,
+}
+
+/// Documentation for a world and its imports and exports.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WorldDocs {
+    /// Documentation for the world itself.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub docs: Option<StructuredDocs>,
+    /// Documentation for the imports of this world.
+    pub imports: WorldItemsDocs,
+    /// Documentation for the exports of this world.
+    pub exports: WorldItemsDocs,
+}
+
+/// Documentation for either the imports or the exports of a world.
+///
+/// Interfaces imported or exported by name are not included here and are
+/// instead documented in the package that defines them.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WorldItemsDocs {
+    /// Documentation for inline interfaces, keyed by their kebab-name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub interfaces: IndexMap<String, InterfaceDocs>,
+    /// Documentation for types, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub types: IndexMap<String, TypeDocs>,
+    /// Documentation for functions, keyed by name.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "IndexMap::is_empty"))]
+    pub functions: IndexMap<String, StructuredDocs>,
+}
+
+impl Resolve {
+    /// Collects the documentation of all items within the package `pkg`.
+    ///
+    /// Items without any documentation are still present in the returned
+    /// structure, with their `docs` field set to `None` or, for functions, an
+    /// empty summary, so the shape of the package is preserved.
+    pub fn package_docs(&self, pkg: PackageId) -> PackageDocs {
+        let package = &self.packages[pkg];
+        PackageDocs {
+            name: package.name.to_string(),
+            docs: package.docs.structured(),
+            interfaces: package
+                .interfaces
+                .iter()
+                .map(|(name, id)| (name.clone(), self.interface_docs(&self.interfaces[*id])))
+                .collect(),
+            worlds: package
+                .worlds
+                .iter()
+                .map(|(name, id)| (name.clone(), self.world_docs(&self.worlds[*id])))
+                .collect(),
+        }
+    }
+
+    fn interface_docs(&self, interface: &Interface) -> InterfaceDocs {
+        InterfaceDocs {
+            docs: interface.docs.structured(),
+            types: interface
+                .types
+                .iter()
+                .map(|(name, id)| (name.clone(), type_docs(&self.types[*id])))
+                .collect(),
+            functions: interface
+                .functions
+                .iter()
+                .map(|(name, func)| (name.clone(), function_docs(func)))
+                .collect(),
+        }
+    }
+
+    fn world_docs(&self, world: &World) -> WorldDocs {
+        WorldDocs {
+            docs: world.docs.structured(),
+            imports: self.world_items_docs(&world.imports),
+            exports: self.world_items_docs(&world.exports),
+        }
+    }
+
+    fn world_items_docs(&self, items: &IndexMap<WorldKey, WorldItem>) -> WorldItemsDocs {
+        let mut ret = WorldItemsDocs::default();
+        for (key, item) in items {
+            let name = match key {
+                WorldKey::Name(name) => name,
+                WorldKey::Interface(_) => continue,
+            };
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    let docs = self.interface_docs(&self.interfaces[*id]);
+                    ret.interfaces.insert(name.clone(), docs);
+                }
+                WorldItem::Function(func) => {
+                    ret.functions.insert(name.clone(), function_docs(func));
+                }
+                WorldItem::Type(id) => {
+                    ret.types.insert(name.clone(), type_docs(&self.types[*id]));
+                }
+            }
+        }
+        ret
+    }
+}
+
+fn function_docs(func: &Function) -> StructuredDocs {
+    func.docs.structured().unwrap_or_default()
+}
+
+fn type_docs(ty: &TypeDef) -> TypeDocs {
+    let members: Vec<(&String, &Docs)> = match &ty.kind {
+        TypeDefKind::Record(r) => r.fields.iter().map(|f| (&f.name, &f.docs)).collect(),
+        TypeDefKind::Variant(v) => v.cases.iter().map(|c| (&c.name, &c.docs)).collect(),
+        TypeDefKind::Enum(e) => e.cases.iter().map(|c| (&c.name, &c.docs)).collect(),
+        TypeDefKind::Flags(f) => f.flags.iter().map(|f| (&f.name, &f.docs)).collect(),
+        _ => Vec::new(),
+    };
+    TypeDocs {
+        docs: ty.docs.structured(),
+        members: members
+            .into_iter()
+            .filter_map(|(name, docs)| Some((name.clone(), docs.structured()?)))
+            .collect(),
+    }
+}
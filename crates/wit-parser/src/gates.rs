@@ -0,0 +1,391 @@
+//! Projection of a [`Resolve`] down to a set of supported feature gates.
+//!
+//! Items in WIT may be annotated with `@since(version = ...)` or
+//! `@unstable(feature = ...)`, which is recorded as the [`Stability`] of the
+//! resolved item. A host which only supports some features, or older versions
+//! of a package, can use [`Resolve::project`] to remove everything it doesn't
+//! support and then generate bindings for exactly what remains.
+
+use crate::{
+    Function, InterfaceId, LiveTypes, PackageId, PackageName, Resolve, Stability, TypeId,
+    TypeOwner, WorldId, WorldItem,
+};
+use anyhow::{bail, Result};
+use indexmap::{IndexMap, IndexSet};
+use semver::Version;
+use std::collections::HashSet;
+
+/// The feature gates supported by a host, used with [`Resolve::project`].
+#[derive(Debug, Clone, Default)]
+pub struct FeatureGates {
+    /// Features whose `@unstable(feature = ...)` items are supported.
+    pub features: IndexSet<String>,
+
+    /// Whether all `@unstable` items are supported, regardless of `features`.
+    pub all_features: bool,
+
+    /// The newest supported version of packages, keyed by package name without
+    /// a version such as `wasi:cli`.
+    ///
+    /// Items marked `@since` a newer version of their package are not
+    /// supported. All `@since` items of packages not listed here are
+    /// supported.
+    pub versions: IndexMap<String, Version>,
+}
+
+impl FeatureGates {
+    /// Returns whether an item with `stability` in the package named `package`
+    /// is supported by these gates.
+    pub fn includes(&self, package: Option<&PackageName>, stability: &Stability) -> bool {
+        match stability {
+            Stability::Unknown => true,
+            Stability::Unstable { feature, .. } => {
+                self.all_features || self.features.contains(feature)
+            }
+            Stability::Stable { since, .. } => {
+                let Some(package) = package else {
+                    return true;
+                };
+                let key = format!("{}:{}", package.namespace, package.name);
+                match self.versions.get(&key) {
+                    Some(max) => since <= max,
+                    None => true,
+                }
+            }
+        }
+    }
+}
+
+impl Resolve {
+    /// Removes all items from this [`Resolve`] which `gates` doesn't include.
+    ///
+    /// Interfaces and worlds are removed from their packages, and functions,
+    /// types, and world items are removed from their interfaces and worlds.
+    /// Types within a removed interface or world are removed as well. Note that
+    /// the arenas of this `Resolve` are not compacted, so the ids of removed
+    /// items remain valid but are no longer reachable from any package.
+    ///
+    /// Unstable items are dropped while parsing unless their feature is
+    /// enabled, so to project a package down to several different sets of
+    /// gates it should be parsed with [`Resolve::all_features`] set.
+    ///
+    /// Returns an error, without modifying this `Resolve`, if an item which is
+    /// kept refers to a type which isn't.
+    pub fn project(&mut self, gates: &FeatureGates) -> Result<()> {
+        let package_name = |pkg: Option<PackageId>| pkg.map(|p| &self.packages[p].name);
+
+        let dropped_interfaces = self
+            .interfaces
+            .iter()
+            .filter(|(_, i)| !gates.includes(package_name(i.package), &i.stability))
+            .map(|(id, _)| id)
+            .collect::<HashSet<_>>();
+        let dropped_worlds = self
+            .worlds
+            .iter()
+            .filter(|(_, w)| !gates.includes(package_name(w.package), &w.stability))
+            .map(|(id, _)| id)
+            .collect::<HashSet<_>>();
+        let dropped_types = self
+            .types
+            .iter()
+            .filter(|(_, ty)| {
+                let (package, owner_dropped) = match ty.owner {
+                    TypeOwner::Interface(i) => {
+                        (self.interfaces[i].package, dropped_interfaces.contains(&i))
+                    }
+                    TypeOwner::World(w) => (self.worlds[w].package, dropped_worlds.contains(&w)),
+                    TypeOwner::None => (None, false),
+                };
+                owner_dropped || !gates.includes(package_name(package), &ty.stability)
+            })
+            .map(|(id, _)| id)
+            .collect::<HashSet<_>>();
+        let dropped = Dropped {
+            interfaces: dropped_interfaces,
+            types: dropped_types,
+        };
+
+        // Validate everything that's kept before modifying anything.
+        for (id, iface) in self.interfaces.iter() {
+            if dropped.interfaces.contains(&id) {
+                continue;
+            }
+            let package = package_name(iface.package);
+            let iface_name = || match self.id_of(id) {
+                Some(name) => format!("interface `{name}`"),
+                None => "an inline interface".to_string(),
+            };
+            for func in iface.functions.values() {
+                if gates.includes(package, &func.stability) {
+                    self.check_func(func, &dropped.types, || {
+                        format!("function `{}` in {}", func.name, iface_name())
+                    })?;
+                }
+            }
+            for (name, ty) in iface.types.iter() {
+                if !dropped.types.contains(ty) {
+                    self.check_type(*ty, &dropped.types, || {
+                        format!("type `{name}` in {}", iface_name())
+                    })?;
+                }
+            }
+        }
+        for (id, world) in self.worlds.iter() {
+            if dropped_worlds.contains(&id) {
+                continue;
+            }
+            let package = package_name(world.package);
+            for (key, item) in world.imports.iter().chain(world.exports.iter()) {
+                if !dropped.includes_world_item(gates, package, item) {
+                    continue;
+                }
+                let mut live = LiveTypes::default();
+                live.add_world_item(self, item);
+                self.check_live(live, &dropped.types, || {
+                    format!("`{}` in world `{}`", self.name_world_key(key), world.name)
+                })?;
+            }
+        }
+
+        // Everything checks out, so actually remove all the items.
+        for (_, package) in self.packages.iter_mut() {
+            package
+                .interfaces
+                .retain(|_, id| !dropped.interfaces.contains(id));
+            package.worlds.retain(|_, id| !dropped_worlds.contains(id));
+        }
+        let interfaces = self.interfaces.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in interfaces {
+            if dropped.interfaces.contains(&id) {
+                continue;
+            }
+            let package = self.interfaces[id]
+                .package
+                .map(|p| self.packages[p].name.clone());
+            let iface = &mut self.interfaces[id];
+            iface
+                .functions
+                .retain(|_, f| gates.includes(package.as_ref(), &f.stability));
+            iface.types.retain(|_, ty| !dropped.types.contains(ty));
+        }
+        let worlds = self
+            .worlds
+            .iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<WorldId>>();
+        for id in worlds {
+            if dropped_worlds.contains(&id) {
+                continue;
+            }
+            let package = self.worlds[id]
+                .package
+                .map(|p| self.packages[p].name.clone());
+            let world = &mut self.worlds[id];
+            world
+                .imports
+                .retain(|_, item| dropped.includes_world_item(gates, package.as_ref(), item));
+            world
+                .exports
+                .retain(|_, item| dropped.includes_world_item(gates, package.as_ref(), item));
+        }
+
+        Ok(())
+    }
+
+    fn check_func(
+        &self,
+        func: &Function,
+        dropped: &HashSet<TypeId>,
+        what: impl FnOnce() -> String,
+    ) -> Result<()> {
+        let mut live = LiveTypes::default();
+        live.add_func(self, func);
+        self.check_live(live, dropped, what)
+    }
+
+    fn check_type(
+        &self,
+        ty: TypeId,
+        dropped: &HashSet<TypeId>,
+        what: impl FnOnce() -> String,
+    ) -> Result<()> {
+        let mut live = LiveTypes::default();
+        live.add_type_id(self, ty);
+        self.check_live(live, dropped, what)
+    }
+
+    fn check_live(
+        &self,
+        live: LiveTypes,
+        dropped: &HashSet<TypeId>,
+        what: impl FnOnce() -> String,
+    ) -> Result<()> {
+        let Some(ty) = live.iter().find(|ty| dropped.contains(ty)) else {
+            return Ok(());
+        };
+        let name = self.types[ty].name.as_deref().unwrap_or("<anonymous>");
+        bail!(
+            "{} refers to type `{name}` which is not included by the feature gates",
+            what()
+        )
+    }
+}
+
+/// Items of a [`Resolve`] which are removed by [`Resolve::project`].
+struct Dropped {
+    interfaces: HashSet<InterfaceId>,
+    types: HashSet<TypeId>,
+}
+
+impl Dropped {
+    fn includes_world_item(
+        &self,
+        gates: &FeatureGates,
+        package: Option<&PackageName>,
+        item: &WorldItem,
+    ) -> bool {
+        match item {
+            WorldItem::Interface { id, stability } => {
+                !self.interfaces.contains(id) && gates.includes(package, stability)
+            }
+            WorldItem::Function(f) => gates.includes(package, &f.stability),
+            WorldItem::Type(id) => !self.types.contains(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIT: &str = "
+        package foo:bar@1.2.0;
+
+        @since(version = 1.0.0)
+        interface stable {
+            @since(version = 1.0.0)
+            f: func();
+            @since(version = 1.2.0)
+            g: func();
+            @unstable(feature = fancy)
+            h: func();
+        }
+
+        @unstable(feature = fancy)
+        interface fancy {
+            @unstable(feature = fancy)
+            type t = u32;
+        }
+
+        @since(version = 1.0.0)
+        world w {
+            @since(version = 1.0.0)
+            import stable;
+            @unstable(feature = fancy)
+            import fancy;
+            @since(version = 1.1.0)
+            export run: func();
+        }
+    ";
+
+    fn resolve() -> (Resolve, PackageId) {
+        let mut resolve = Resolve {
+            all_features: true,
+            ..Resolve::default()
+        };
+        let pkg = resolve.push_str("test.wit", WIT).unwrap();
+        (resolve, pkg)
+    }
+
+    fn function_names(resolve: &Resolve, pkg: PackageId) -> Vec<&str> {
+        let iface = resolve.packages[pkg].interfaces["stable"];
+        resolve.interfaces[iface]
+            .functions
+            .keys()
+            .map(|s| s.as_str())
+            .collect()
+    }
+
+    fn world_items(resolve: &Resolve, pkg: PackageId) -> Vec<String> {
+        let world = &resolve.worlds[resolve.packages[pkg].worlds["w"]];
+        world
+            .imports
+            .keys()
+            .chain(world.exports.keys())
+            .map(|k| resolve.name_world_key(k))
+            .collect()
+    }
+
+    #[test]
+    fn everything() {
+        let (mut resolve, pkg) = resolve();
+        resolve
+            .project(&FeatureGates {
+                all_features: true,
+                ..FeatureGates::default()
+            })
+            .unwrap();
+        assert_eq!(function_names(&resolve, pkg), ["f", "g", "h"]);
+        assert_eq!(resolve.packages[pkg].interfaces.len(), 2);
+        assert_eq!(
+            world_items(&resolve, pkg),
+            ["foo:bar/stable@1.2.0", "foo:bar/fancy@1.2.0", "run"]
+        );
+    }
+
+    #[test]
+    fn no_features() {
+        let (mut resolve, pkg) = resolve();
+        resolve.project(&FeatureGates::default()).unwrap();
+        assert_eq!(function_names(&resolve, pkg), ["f", "g"]);
+        assert_eq!(resolve.packages[pkg].interfaces.len(), 1);
+        assert_eq!(world_items(&resolve, pkg), ["foo:bar/stable@1.2.0", "run"]);
+    }
+
+    #[test]
+    fn older_version() {
+        let (mut resolve, pkg) = resolve();
+        let mut gates = FeatureGates::default();
+        gates.features.insert("fancy".to_string());
+        gates
+            .versions
+            .insert("foo:bar".to_string(), Version::new(1, 0, 0));
+        resolve.project(&gates).unwrap();
+        assert_eq!(function_names(&resolve, pkg), ["f", "h"]);
+        assert_eq!(
+            world_items(&resolve, pkg),
+            ["foo:bar/stable@1.2.0", "foo:bar/fancy@1.2.0"]
+        );
+    }
+
+    #[test]
+    fn dangling_reference() {
+        let mut resolve = Resolve {
+            all_features: true,
+            ..Resolve::default()
+        };
+        resolve
+            .push_str(
+                "test.wit",
+                "
+                    package foo:bar;
+
+                    interface i {
+                        @unstable(feature = fancy)
+                        type t = u32;
+                        f: func(x: t);
+                    }
+                ",
+            )
+            .unwrap();
+        let before = format!("{:?}", resolve.interfaces);
+        let err = resolve.project(&FeatureGates::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "function `f` in interface `foo:bar/i` refers to type `t` which is not \
+             included by the feature gates"
+        );
+        assert_eq!(format!("{:?}", resolve.interfaces), before);
+    }
+}
@@ -0,0 +1,473 @@
+//! Conversion of the types of interfaces and worlds to [JSON Schema]
+//! documents, see [`Resolve::interface_json_schema`].
+//!
+//! [JSON Schema]: https://json-schema.org
+
+use crate::{
+    Docs, Function, FunctionKind, InterfaceId, Params, Resolve, Results, Type, TypeDefKind, TypeId,
+    TypeOwner, WorldId, WorldItem,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// The dialect of JSON Schema which documents are written in.
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl Resolve {
+    /// Returns a [JSON Schema] document for the JSON representation of the
+    /// types and functions of the interface `id`.
+    ///
+    /// Each named type of the interface, and each named type of another
+    /// interface which it refers to, is a definition in `$defs`. Types of the
+    /// interface are named as in WIT and types of other interfaces are
+    /// qualified with the ID of their interface, such as `wasi:io/poll/pollable`.
+    /// Each function has a definition `NAME.params` for an object of its
+    /// parameters and, if it returns anything, `NAME.result` for its result.
+    ///
+    /// Values are represented in JSON as follows:
+    ///
+    /// * `bool`, integers, and floats are JSON booleans and numbers, with the
+    ///   range of integers in `minimum` and `maximum`.
+    /// * `char` and `string` are strings.
+    /// * `list<T>` and tuples are arrays.
+    /// * Records are objects with a property for each field.
+    /// * Flags are arrays of the names of the flags which are set.
+    /// * Enum cases are strings of their name.
+    /// * Variant cases, including the `ok` and `err` cases of results, are
+    ///   strings of their name if they have no payload, and otherwise objects
+    ///   with their name as the only property and their payload as its value.
+    /// * `option<T>` is `null` for `none` and `T` for `some`.
+    ///
+    /// Resources, handles, futures, and streams have no JSON representation,
+    /// so types containing them are left out of the document, as are the
+    /// functions which use them and the functions of resources.
+    ///
+    /// [JSON Schema]: https://json-schema.org
+    pub fn interface_json_schema(&self, id: InterfaceId) -> Value {
+        let interface = &self.interfaces[id];
+        let mut schema = SchemaBuilder::new(self, TypeOwner::Interface(id));
+        for ty in interface.types.values() {
+            schema.named_type(*ty);
+        }
+        for func in interface.functions.values() {
+            schema.function(func);
+        }
+        let title = self
+            .id_of(id)
+            .or_else(|| interface.name.clone())
+            .unwrap_or_default();
+        schema.finish(title, &interface.docs)
+    }
+
+    /// Returns a [JSON Schema] document for the JSON representation of the
+    /// types and functions imported and exported by the world `id`.
+    ///
+    /// This has the same structure as the documents of
+    /// [`Resolve::interface_json_schema`], but doesn't include the interfaces
+    /// which the world imports or exports.
+    ///
+    /// [JSON Schema]: https://json-schema.org
+    pub fn world_json_schema(&self, id: WorldId) -> Value {
+        let world = &self.worlds[id];
+        let mut schema = SchemaBuilder::new(self, TypeOwner::World(id));
+        for item in world.imports.values().chain(world.exports.values()) {
+            match item {
+                WorldItem::Type(ty) => schema.named_type(*ty),
+                WorldItem::Function(func) => schema.function(func),
+                WorldItem::Interface { .. } => {}
+            }
+        }
+        let title = match world.package {
+            Some(pkg) => self.id_of_name(pkg, &world.name),
+            None => world.name.clone(),
+        };
+        schema.finish(title, &world.docs)
+    }
+}
+
+struct SchemaBuilder<'a> {
+    resolve: &'a Resolve,
+    owner: TypeOwner,
+    defs: Map<String, Value>,
+    /// Whether each type which has been looked at has a JSON representation.
+    representable: HashMap<TypeId, bool>,
+}
+
+impl<'a> SchemaBuilder<'a> {
+    fn new(resolve: &'a Resolve, owner: TypeOwner) -> SchemaBuilder<'a> {
+        SchemaBuilder {
+            resolve,
+            owner,
+            defs: Map::new(),
+            representable: HashMap::new(),
+        }
+    }
+
+    fn finish(self, title: String, docs: &Docs) -> Value {
+        let mut schema = Map::new();
+        schema.insert("$schema".to_string(), json!(DIALECT));
+        schema.insert("title".to_string(), json!(title));
+        if let Some(docs) = &docs.contents {
+            schema.insert("description".to_string(), json!(docs));
+        }
+        schema.insert("$defs".to_string(), Value::Object(self.defs));
+        Value::Object(schema)
+    }
+
+    /// Adds the definition of the named type `id`, and of all named types it
+    /// refers to, if it has a JSON representation.
+    fn named_type(&mut self, id: TypeId) {
+        if self.is_representable(&Type::Id(id)) {
+            self.define(id);
+        }
+    }
+
+    fn function(&mut self, func: &Function) {
+        if func.kind != FunctionKind::Freestanding {
+            return;
+        }
+        let mut types = func
+            .params
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(func.results.iter_types());
+        if !types.all(|ty| self.is_representable(ty)) {
+            return;
+        }
+        let mut params = self.params(&func.params);
+        if let Some(docs) = &func.docs.contents {
+            params["description"] = json!(docs);
+        }
+        self.defs.insert(format!("{}.params", func.name), params);
+        let result = match &func.results {
+            Results::Named(results) if results.is_empty() => return,
+            Results::Named(results) => self.params(results),
+            Results::Anon(ty) => self.schema(ty),
+        };
+        self.defs.insert(format!("{}.result", func.name), result);
+    }
+
+    fn params(&mut self, params: &Params) -> Value {
+        let properties = params
+            .iter()
+            .map(|(name, ty)| (name.clone(), self.schema(ty)))
+            .collect::<Map<_, _>>();
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": params.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            "additionalProperties": false,
+        })
+    }
+
+    /// Returns the key of the definition of the named type `id` in `$defs`.
+    fn key(&self, id: TypeId) -> String {
+        let ty = &self.resolve.types[id];
+        let name = ty.name.as_deref().unwrap();
+        if ty.owner == self.owner {
+            return name.to_string();
+        }
+        let owner = match ty.owner {
+            TypeOwner::Interface(i) => self
+                .resolve
+                .id_of(i)
+                .or_else(|| self.resolve.interfaces[i].name.clone()),
+            TypeOwner::World(w) => Some(self.resolve.worlds[w].name.clone()),
+            TypeOwner::None => None,
+        };
+        match owner {
+            Some(owner) => format!("{owner}/{name}"),
+            None => name.to_string(),
+        }
+    }
+
+    /// Adds the definition of the named type `id` to `$defs`, returning its
+    /// key.
+    fn define(&mut self, id: TypeId) -> String {
+        let key = self.key(id);
+        if !self.defs.contains_key(&key) {
+            // Insert a placeholder first so that, if `serde_json` preserves the
+            // order of maps, a type is defined before the types it refers to.
+            self.defs.insert(key.clone(), Value::Null);
+            let ty = &self.resolve.types[id];
+            let mut schema = self.kind(&ty.kind);
+            if let Some(docs) = &ty.docs.contents {
+                schema["description"] = json!(docs);
+            }
+            self.defs.insert(key.clone(), schema);
+        }
+        key
+    }
+
+    fn schema(&mut self, ty: &Type) -> Value {
+        match ty {
+            Type::Bool => json!({ "type": "boolean" }),
+            Type::U8 => integer(u8::MIN, u8::MAX),
+            Type::U16 => integer(u16::MIN, u16::MAX),
+            Type::U32 => integer(u32::MIN, u32::MAX),
+            Type::U64 => integer(u64::MIN, u64::MAX),
+            Type::S8 => integer(i8::MIN, i8::MAX),
+            Type::S16 => integer(i16::MIN, i16::MAX),
+            Type::S32 => integer(i32::MIN, i32::MAX),
+            Type::S64 => integer(i64::MIN, i64::MAX),
+            Type::F32 | Type::F64 => json!({ "type": "number" }),
+            Type::Char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+            Type::String => json!({ "type": "string" }),
+            Type::Id(id) => {
+                if self.resolve.types[*id].name.is_none() {
+                    return self.kind(&self.resolve.types[*id].kind);
+                }
+                let key = self.define(*id);
+                let pointer = key.replace('~', "~0").replace('/', "~1");
+                json!({ "$ref": format!("#/$defs/{pointer}") })
+            }
+        }
+    }
+
+    fn kind(&mut self, kind: &TypeDefKind) -> Value {
+        match kind {
+            TypeDefKind::Type(ty) => self.schema(ty),
+            TypeDefKind::List(ty) => json!({ "type": "array", "items": self.schema(ty) }),
+            TypeDefKind::Option(ty) => json!({
+                "anyOf": [{ "type": "null" }, self.schema(ty)],
+            }),
+            TypeDefKind::Tuple(t) => json!({
+                "type": "array",
+                "prefixItems": t.types.iter().map(|ty| self.schema(ty)).collect::<Vec<_>>(),
+                "minItems": t.types.len(),
+                "maxItems": t.types.len(),
+            }),
+            TypeDefKind::Record(r) => {
+                let properties = r
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let mut schema = self.schema(&field.ty);
+                        describe(&mut schema, &field.docs);
+                        (field.name.clone(), schema)
+                    })
+                    .collect::<Map<_, _>>();
+                json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": r.fields.iter().map(|f| &f.name).collect::<Vec<_>>(),
+                    "additionalProperties": false,
+                })
+            }
+            TypeDefKind::Flags(f) => json!({
+                "type": "array",
+                "items": {
+                    "type": "string",
+                    "enum": f.flags.iter().map(|f| &f.name).collect::<Vec<_>>(),
+                },
+                "uniqueItems": true,
+            }),
+            TypeDefKind::Enum(e) => json!({
+                "type": "string",
+                "enum": e.cases.iter().map(|c| &c.name).collect::<Vec<_>>(),
+            }),
+            TypeDefKind::Variant(v) => {
+                let cases = v
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let mut schema = self.case(&case.name, case.ty.as_ref());
+                        describe(&mut schema, &case.docs);
+                        schema
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "oneOf": cases })
+            }
+            TypeDefKind::Result(r) => json!({
+                "oneOf": [self.case("ok", r.ok.as_ref()), self.case("err", r.err.as_ref())],
+            }),
+            TypeDefKind::Resource
+            | TypeDefKind::Handle(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Unknown => unreachable!(),
+        }
+    }
+
+    fn case(&mut self, name: &str, payload: Option<&Type>) -> Value {
+        match payload {
+            Some(ty) => json!({
+                "type": "object",
+                "properties": { name: self.schema(ty) },
+                "required": [name],
+                "additionalProperties": false,
+            }),
+            None => json!({ "const": name }),
+        }
+    }
+
+    fn is_representable(&mut self, ty: &Type) -> bool {
+        let id = match ty {
+            Type::Id(id) => *id,
+            _ => return true,
+        };
+        if let Some(representable) = self.representable.get(&id) {
+            return *representable;
+        }
+        let representable = match &self.resolve.types[id].kind {
+            TypeDefKind::Type(ty) | TypeDefKind::List(ty) | TypeDefKind::Option(ty) => {
+                self.is_representable(ty)
+            }
+            TypeDefKind::Tuple(t) => t.types.iter().all(|ty| self.is_representable(ty)),
+            TypeDefKind::Record(r) => r.fields.iter().all(|f| self.is_representable(&f.ty)),
+            TypeDefKind::Variant(v) => v
+                .cases
+                .iter()
+                .filter_map(|c| c.ty.as_ref())
+                .all(|ty| self.is_representable(ty)),
+            TypeDefKind::Result(r) => {
+                r.ok.iter()
+                    .chain(&r.err)
+                    .all(|ty| self.is_representable(ty))
+            }
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => true,
+            TypeDefKind::Resource
+            | TypeDefKind::Handle(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Unknown => false,
+        };
+        self.representable.insert(id, representable);
+        representable
+    }
+}
+
+fn integer(min: impl Into<Value>, max: impl Into<Value>) -> Value {
+    json!({ "type": "integer", "minimum": min.into(), "maximum": max.into() })
+}
+
+fn describe(schema: &mut Value, docs: &Docs) {
+    if let Some(docs) = &docs.contents {
+        schema["description"] = json!(docs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str(
+                "test.wit",
+                "
+                    package a:b;
+
+                    interface shared {
+                        /// A point.
+                        record point { x: s32, y: s32 }
+                    }
+
+                    interface i {
+                        use shared.{point};
+                        resource r;
+
+                        flags perms { read, write }
+                        enum color { red, green }
+                        variant shape {
+                            /// Nothing at all.
+                            empty,
+                            dot(point),
+                        }
+                        type pair = tuple<u8, char>;
+                        record uses-r { r: r }
+
+                        draw: func(shapes: list<shape>, color: option<color>) -> result<_, string>;
+                        take: func(r: r);
+                    }
+                ",
+            )
+            .unwrap();
+        let i = resolve.packages[pkg].interfaces["i"];
+        let schema = resolve.interface_json_schema(i);
+        assert_eq!(schema["title"], "a:b/i");
+
+        let defs = schema["$defs"].as_object().unwrap();
+        let mut keys = defs.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "a:b/shared/point",
+                "color",
+                "draw.params",
+                "draw.result",
+                "pair",
+                "perms",
+                "point",
+                "shape",
+            ]
+        );
+        assert_eq!(
+            defs["point"],
+            json!({ "$ref": "#/$defs/a:b~1shared~1point" })
+        );
+        assert_eq!(defs["a:b/shared/point"]["description"], "A point.");
+        assert_eq!(
+            defs["a:b/shared/point"]["properties"]["x"],
+            json!({ "type": "integer", "minimum": i32::MIN, "maximum": i32::MAX })
+        );
+        assert_eq!(
+            defs["perms"],
+            json!({
+                "type": "array",
+                "items": { "type": "string", "enum": ["read", "write"] },
+                "uniqueItems": true,
+            })
+        );
+        assert_eq!(
+            defs["shape"],
+            json!({
+                "oneOf": [
+                    { "const": "empty", "description": "Nothing at all." },
+                    {
+                        "type": "object",
+                        "properties": { "dot": { "$ref": "#/$defs/point" } },
+                        "required": ["dot"],
+                        "additionalProperties": false,
+                    },
+                ],
+            })
+        );
+        assert_eq!(defs["pair"]["prefixItems"][1]["maxLength"], 1);
+        assert_eq!(
+            defs["draw.params"]["properties"]["color"],
+            json!({ "anyOf": [{ "type": "null" }, { "$ref": "#/$defs/color" }] })
+        );
+        assert_eq!(defs["draw.result"]["oneOf"][0], json!({ "const": "ok" }));
+    }
+
+    #[test]
+    fn world() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str(
+                "test.wit",
+                "
+                    package a:b;
+
+                    world w {
+                        record config { name: string }
+                        import log: func(msg: string);
+                        export run: func(config: config) -> u64;
+                    }
+                ",
+            )
+            .unwrap();
+        let w = resolve.packages[pkg].worlds["w"];
+        let schema = resolve.world_json_schema(w);
+        assert_eq!(schema["title"], "a:b/w");
+        let defs = schema["$defs"].as_object().unwrap();
+        let mut keys = defs.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["config", "log.params", "run.params", "run.result"]);
+        assert_eq!(defs["run.result"]["maximum"], u64::MAX);
+    }
+}
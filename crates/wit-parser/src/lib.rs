@@ -15,19 +15,35 @@ pub use metadata::PackageMetadata;
 
 pub mod abi;
 mod ast;
+pub mod canonical;
 use ast::lex::Span;
 pub use ast::SourceMap;
 pub use ast::{parse_use_path, ParsedUsePath};
 mod sizealign;
 pub use sizealign::*;
 mod resolve;
-pub use resolve::{InvalidTransitiveDependency, Package, PackageId, Remap, Resolve};
+pub use resolve::{
+    DirectoryPackageResolver, InvalidTransitiveDependency, Package, PackageId, PackageResolver,
+    Remap, Resolve,
+};
 mod live;
 pub use live::{LiveTypes, TypeIdVisitor};
+mod docs;
+mod gates;
+mod resources;
+mod usage;
+pub use docs::{InterfaceDocs, PackageDocs, StructuredDocs, TypeDocs, WorldDocs, WorldItemsDocs};
+pub use gates::FeatureGates;
+pub use resources::{HandleFlow, HandlePosition, WorldResource};
+pub use usage::{Lint, TypeUse};
+mod workspace;
+pub use workspace::WorkspaceCheck;
 
 #[cfg(feature = "serde")]
 use serde_derive::Serialize;
 #[cfg(feature = "serde")]
+mod json_schema;
+#[cfg(feature = "serde")]
 mod serde_;
 #[cfg(feature = "serde")]
 use serde_::*;
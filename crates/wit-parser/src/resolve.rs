@@ -24,6 +24,8 @@ use crate::{
 };
 
 mod clone;
+mod package_resolver;
+pub use package_resolver::{DirectoryPackageResolver, PackageResolver};
 
 /// Representation of a fully resolved set of WIT packages.
 ///
@@ -342,6 +344,107 @@ package {name} is defined in two different locations:\n\
         self.sort_unresolved_packages(top_pkg, deps)
     }
 
+    /// Same as [`Resolve::push_path`], except that any dependencies which
+    /// aren't found in `self` or in a `deps` directory are fetched through
+    /// `resolver`.
+    ///
+    /// Dependencies are requested from `resolver` by package name, and
+    /// packages returned may themselves have dependencies which are then also
+    /// requested from `resolver`. Packages found in a `deps` directory or
+    /// previously inserted into `self` take precedence over `resolver`.
+    pub fn push_path_with_resolver(
+        &mut self,
+        path: impl AsRef<Path>,
+        resolver: &mut dyn PackageResolver,
+    ) -> Result<(PackageId, Vec<PathBuf>)> {
+        let path = path.as_ref();
+        let (main, deps) = if path.is_dir() {
+            let main = UnresolvedPackageGroup::parse_dir(path)
+                .with_context(|| format!("failed to parse package: {}", path.display()))?;
+            let deps_dir = path.join("deps");
+            let deps = self.parse_deps_dir(&deps_dir).with_context(|| {
+                format!(
+                    "failed to parse dependency directory: {}",
+                    deps_dir.display()
+                )
+            })?;
+            (main, deps)
+        } else {
+            match self._push_file(path)? {
+                #[cfg(feature = "decoding")]
+                ParsedFile::Package(id) => return Ok((id, vec![path.to_path_buf()])),
+                ParsedFile::Unresolved(pkg) => (pkg, Vec::new()),
+            }
+        };
+        self.push_groups_with_resolver(main, deps, resolver)
+    }
+
+    /// Same as [`Resolve::push_group`], except that any dependencies which
+    /// aren't found in `self` are fetched through `resolver`.
+    ///
+    /// See [`Resolve::push_path_with_resolver`] for more information.
+    pub fn push_group_with_resolver(
+        &mut self,
+        unresolved_group: UnresolvedPackageGroup,
+        resolver: &mut dyn PackageResolver,
+    ) -> Result<PackageId> {
+        let (pkg_id, _) = self.push_groups_with_resolver(unresolved_group, Vec::new(), resolver)?;
+        Ok(pkg_id)
+    }
+
+    fn push_groups_with_resolver(
+        &mut self,
+        main: UnresolvedPackageGroup,
+        mut deps: Vec<UnresolvedPackageGroup>,
+        resolver: &mut dyn PackageResolver,
+    ) -> Result<(PackageId, Vec<PathBuf>)> {
+        fn packages(group: &UnresolvedPackageGroup) -> impl Iterator<Item = &UnresolvedPackage> {
+            group.nested.iter().chain([&group.main])
+        }
+
+        let mut known = HashSet::new();
+        for group in [&main].into_iter().chain(&deps) {
+            known.extend(packages(group).map(|p| p.name.clone()));
+        }
+
+        // Iteratively request all dependencies which aren't known from the
+        // resolver, including dependencies of packages that the resolver
+        // itself returned.
+        let mut i = 0;
+        loop {
+            let group = if i == 0 { &main } else { &deps[i - 1] };
+            let missing = packages(group)
+                .flat_map(|p| p.foreign_deps.keys())
+                .filter(|dep| !known.contains(*dep) && !self.package_names.contains_key(*dep))
+                .cloned()
+                .collect::<Vec<_>>();
+            for dep in missing {
+                if known.contains(&dep) {
+                    continue;
+                }
+                let group = match resolver
+                    .resolve_package(&dep)
+                    .with_context(|| format!("failed to resolve package `{dep}`"))?
+                {
+                    Some(group) => group,
+                    None => continue,
+                };
+                let names = packages(&group).map(|p| &p.name).collect::<Vec<_>>();
+                if !names.contains(&&dep) {
+                    bail!("package resolver returned packages {names:?} when asked for `{dep}`");
+                }
+                known.extend(names.into_iter().cloned());
+                deps.push(group);
+            }
+            i += 1;
+            if i > deps.len() {
+                break;
+            }
+        }
+
+        self.sort_unresolved_packages(main, deps)
+    }
+
     fn parse_deps_dir(&mut self, path: &Path) -> Result<Vec<UnresolvedPackageGroup>> {
         let mut ret = Vec::new();
         if !path.exists() {
@@ -860,6 +963,51 @@ package {name} is defined in two different locations:\n\
         Ok(())
     }
 
+    /// Creates a new world named `name` within `package` which is the union
+    /// of all of `worlds`.
+    ///
+    /// Each of `worlds` is merged into the new world with
+    /// [`Resolve::merge_worlds`], so an interface imported or exported by more
+    /// than one world is only imported or exported once, and functions and
+    /// types of the same name must have the same definition in each world.
+    /// This is useful when a single module needs to satisfy several variants
+    /// of a host API.
+    ///
+    /// An error is returned if `package` already has a world named `name` or
+    /// if the worlds conflict with each other, in which case the new world may
+    /// have been partially created.
+    pub fn union_worlds(
+        &mut self,
+        package: PackageId,
+        name: &str,
+        worlds: &[WorldId],
+    ) -> Result<WorldId> {
+        if self.packages[package].worlds.contains_key(name) {
+            bail!(
+                "package `{}` already contains a world named `{name}`",
+                self.packages[package].name
+            );
+        }
+        let union = self.worlds.alloc(World {
+            name: name.to_string(),
+            imports: Default::default(),
+            exports: Default::default(),
+            package: Some(package),
+            docs: Default::default(),
+            stability: Default::default(),
+            includes: Default::default(),
+            include_names: Default::default(),
+        });
+        self.packages[package]
+            .worlds
+            .insert(name.to_string(), union);
+        for world in worlds {
+            self.merge_worlds(*world, union)
+                .with_context(|| format!("failed to merge world `{}`", self.worlds[*world].name))?;
+        }
+        Ok(union)
+    }
+
     fn merge_world_item(&self, from: &WorldItem, into: &WorldItem) -> Result<()> {
         let mut map = MergeMap::new(self, self);
         match (from, into) {
@@ -3469,7 +3617,7 @@ impl std::error::Error for InvalidTransitiveDependency {}
 
 #[cfg(test)]
 mod tests {
-    use crate::Resolve;
+    use crate::{PackageName, Resolve, UnresolvedPackageGroup};
     use anyhow::Result;
 
     #[test]
@@ -3525,4 +3673,122 @@ mod tests {
             .is_ok());
         Ok(())
     }
+
+    #[test]
+    fn push_group_with_resolver() -> Result<()> {
+        let mut resolve = Resolve::default();
+        let mut requested = Vec::new();
+        let main = UnresolvedPackageGroup::parse(
+            "main.wit",
+            r#"
+                package foo:main;
+
+                world w {
+                    import foo:a/i@1.0.0;
+                }
+            "#,
+        )?;
+        let mut resolver = |name: &PackageName| {
+            requested.push(name.to_string());
+            let contents = match name.to_string().as_str() {
+                "foo:a@1.0.0" => {
+                    r#"
+                        package foo:a@1.0.0;
+
+                        interface i {
+                            use foo:b/types.{t};
+                        }
+                    "#
+                }
+                "foo:b" => {
+                    r#"
+                        package foo:b;
+
+                        interface types {
+                            type t = u32;
+                        }
+                    "#
+                }
+                _ => return Ok(None),
+            };
+            Ok(Some(UnresolvedPackageGroup::parse("dep.wit", contents)?))
+        };
+        let id = resolve.push_group_with_resolver(main, &mut resolver)?;
+        assert_eq!(requested, ["foo:a@1.0.0", "foo:b"]);
+        assert!(resolve.select_world(id, Some("w")).is_ok());
+        assert_eq!(resolve.packages.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn push_group_with_resolver_missing() -> Result<()> {
+        let mut resolve = Resolve::default();
+        let main = UnresolvedPackageGroup::parse(
+            "main.wit",
+            r#"
+                package foo:main;
+
+                world w {
+                    import foo:a/i;
+                }
+            "#,
+        )?;
+        let mut resolver = |_: &PackageName| Ok(None);
+        assert!(resolve
+            .push_group_with_resolver(main, &mut resolver)
+            .is_err());
+        Ok(())
+    }
+    #[test]
+    fn union_worlds() -> Result<()> {
+        let mut resolve = Resolve::default();
+        let pkg = resolve.push_str(
+            "test.wit",
+            r#"
+                package foo:bar;
+
+                interface logging {
+                    log: func(msg: string);
+                }
+
+                interface clock {
+                    now: func() -> u64;
+                }
+
+                world a {
+                    import logging;
+                    export run: func();
+                }
+
+                world b {
+                    import logging;
+                    import clock;
+                    export run: func();
+                }
+
+                world c {
+                    export run: func(x: u32);
+                }
+            "#,
+        )?;
+        let a = resolve.select_world(pkg, Some("a"))?;
+        let b = resolve.select_world(pkg, Some("b"))?;
+        let c = resolve.select_world(pkg, Some("c"))?;
+
+        let union = resolve.union_worlds(pkg, "union", &[a, b])?;
+        let world = &resolve.worlds[union];
+        let imports = world
+            .imports
+            .keys()
+            .map(|key| resolve.name_world_key(key))
+            .collect::<Vec<_>>();
+        assert_eq!(imports, ["foo:bar/logging", "foo:bar/clock"]);
+        assert_eq!(world.exports.len(), 1);
+        assert_eq!(resolve.select_world(pkg, Some("union"))?, union);
+
+        assert!(resolve.union_worlds(pkg, "union", &[a]).is_err());
+        let err = resolve.union_worlds(pkg, "conflict", &[a, c]).unwrap_err();
+        assert!(format!("{err:?}").contains("failed to merge world `c`"));
+        Ok(())
+    }
 }
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{PackageName, UnresolvedPackageGroup};
+
+/// A source of WIT packages used to satisfy dependencies which aren't
+/// otherwise available when parsing a package.
+///
+/// This is used with [`Resolve::push_path_with_resolver`] and related methods
+/// to fetch dependencies such as `foo:bar@1.2.0` on demand instead of requiring
+/// that everything is pre-vendored in a `deps` directory. Implementations of
+/// this trait are provided for [`DirectoryPackageResolver`], which reads
+/// packages from a configurable directory layout, and for closures, which can
+/// be used to fetch packages from elsewhere such as an OCI registry.
+///
+/// [`Resolve::push_path_with_resolver`]: crate::Resolve::push_path_with_resolver
+pub trait PackageResolver {
+    /// Attempts to locate the package `name`.
+    ///
+    /// Returns `Ok(None)` if this resolver doesn't know about `name`, in which
+    /// case resolution will later fail with a "package not found" error.
+    /// Returns an error if `name` was found but couldn't be loaded.
+    ///
+    /// The returned group must define `name` as its main package or as one of
+    /// its nested packages. Any dependencies of the returned packages which
+    /// are still missing will be resolved through this resolver as well.
+    fn resolve_package(&mut self, name: &PackageName) -> Result<Option<UnresolvedPackageGroup>>;
+}
+
+impl<F> PackageResolver for F
+where
+    F: FnMut(&PackageName) -> Result<Option<UnresolvedPackageGroup>>,
+{
+    fn resolve_package(&mut self, name: &PackageName) -> Result<Option<UnresolvedPackageGroup>> {
+        self(name)
+    }
+}
+
+/// A [`PackageResolver`] which looks up packages in a directory on the
+/// filesystem.
+///
+/// Packages are searched for in the root directory using the layout:
+///
+/// * `$root/$namespace/$name@$version/*.wit` - a directory of WIT files.
+/// * `$root/$namespace/$name@$version.wit` - a single WIT file.
+///
+/// For packages without a version the `@$version` suffix is omitted. The
+/// directory form takes precedence over the single-file form if both exist.
+#[derive(Debug, Clone)]
+pub struct DirectoryPackageResolver {
+    root: PathBuf,
+}
+
+impl DirectoryPackageResolver {
+    /// Creates a new resolver which looks for packages in `root`.
+    pub fn new(root: impl Into<PathBuf>) -> DirectoryPackageResolver {
+        DirectoryPackageResolver { root: root.into() }
+    }
+
+    /// Returns the directory that packages are searched for in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the candidate paths for `name`, in the order they're probed.
+    pub fn candidate_paths(&self, name: &PackageName) -> [PathBuf; 2] {
+        let mut base = name.name.clone();
+        if let Some(version) = &name.version {
+            base.push_str(&format!("@{version}"));
+        }
+        let dir = self.root.join(&name.namespace);
+        [dir.join(&base), dir.join(format!("{base}.wit"))]
+    }
+}
+
+impl PackageResolver for DirectoryPackageResolver {
+    fn resolve_package(&mut self, name: &PackageName) -> Result<Option<UnresolvedPackageGroup>> {
+        let [dir, file] = self.candidate_paths(name);
+        let group = if dir.is_dir() {
+            UnresolvedPackageGroup::parse_dir(&dir)
+                .with_context(|| format!("failed to parse package: {}", dir.display()))?
+        } else if file.is_file() {
+            UnresolvedPackageGroup::parse_file(&file)
+                .with_context(|| format!("failed to parse package: {}", file.display()))?
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(group))
+    }
+}
@@ -0,0 +1,308 @@
+//! Analysis of the resources of a world and how handles to them flow.
+//!
+//! Bindings generators need to know, for each resource, which functions are
+//! attached to it and where `own` and `borrow` handles to it are passed
+//! between a component and its host. [`Resolve::world_resources`] gathers all
+//! of that in one place rather than having each generator reconstruct it
+//! from the raw list of functions of a world.
+
+use crate::{
+    Function, FunctionKind, Handle, Resolve, Type, TypeDefKind, TypeId, TypeIdVisitor, WorldId,
+    WorldItem, WorldKey,
+};
+use indexmap::{IndexMap, IndexSet};
+
+/// A resource type of a world, as found by [`Resolve::world_resources`].
+#[derive(Debug, Clone)]
+pub struct WorldResource<'a> {
+    /// The definition of the resource type.
+    pub id: TypeId,
+    /// The constructor of the resource, if it has one.
+    pub constructor: Option<&'a Function>,
+    /// The methods of the resource, which take a `borrow` of it as their
+    /// first parameter.
+    pub methods: Vec<&'a Function>,
+    /// The static functions of the resource.
+    pub statics: Vec<&'a Function>,
+    /// Every place in the world's functions where a handle to the resource is
+    /// passed, including the `self` parameter of methods and the result of
+    /// the constructor.
+    pub flows: Vec<HandleFlow<'a>>,
+}
+
+/// A parameter or result of a function which contains a handle to a
+/// resource, as found by [`Resolve::world_resources`].
+#[derive(Debug, Clone)]
+pub struct HandleFlow<'a> {
+    /// The import or export of the world containing the function.
+    pub key: &'a WorldKey,
+    /// Whether the function is exported by the world rather than imported.
+    ///
+    /// Handles in the parameters of an imported function are passed from
+    /// the component to the host, and handles in its results are passed the
+    /// other way around. The reverse is true for exported functions.
+    pub exported: bool,
+    /// The function whose parameter or result contains the handle.
+    pub function: &'a Function,
+    /// The parameter or result containing the handle.
+    pub position: HandlePosition,
+    /// Whether the handle is `own` or `borrow`.
+    ///
+    /// The handle refers to the definition of the resource rather than to any
+    /// alias of it created by a `use`.
+    pub handle: Handle,
+}
+
+/// Where a handle occurs in the signature of a function.
+///
+/// Note that the handle may be nested within the parameter or result, for
+/// example as an element of a `list` or a field of a `record`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HandlePosition {
+    /// The parameter at this index.
+    Param(usize),
+    /// The result at this index.
+    Result(usize),
+}
+
+impl Resolve {
+    /// Returns the resource types of `world` along with their functions and
+    /// the places where handles to them are passed.
+    ///
+    /// This includes resources defined in interfaces imported or exported by
+    /// the world, including interfaces which are implicitly imported because
+    /// of a `use`, and resources defined in the world itself. Resources are
+    /// returned in the order the interfaces or world items defining them
+    /// appear in the world's imports and then exports.
+    pub fn world_resources(&self, world: WorldId) -> Vec<WorldResource<'_>> {
+        let world = &self.worlds[world];
+        let items = world
+            .imports
+            .iter()
+            .map(|(key, item)| (key, item, false))
+            .chain(world.exports.iter().map(|(key, item)| (key, item, true)));
+
+        // Gather all functions of the world alongside the item they belong to
+        // and all resources defined by the world's items.
+        let mut functions = Vec::new();
+        let mut resources = IndexMap::new();
+        for (key, item, exported) in items {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    let interface = &self.interfaces[*id];
+                    for id in interface.types.values() {
+                        self.push_resource(&mut resources, *id);
+                    }
+                    functions.extend(interface.functions.values().map(|f| (key, exported, f)));
+                }
+                WorldItem::Function(f) => functions.push((key, exported, f)),
+                WorldItem::Type(id) => self.push_resource(&mut resources, *id),
+            }
+        }
+
+        for (key, exported, function) in functions {
+            if let Some(resource) = function
+                .kind
+                .resource()
+                .and_then(|id| resources.get_mut(&self.dealias_resource(id)))
+            {
+                match function.kind {
+                    FunctionKind::Constructor(_) => resource.constructor = Some(function),
+                    FunctionKind::Method(_) => resource.methods.push(function),
+                    FunctionKind::Static(_) => resource.statics.push(function),
+                    FunctionKind::Freestanding => unreachable!(),
+                }
+            }
+
+            let params = function
+                .params
+                .iter()
+                .enumerate()
+                .map(|(i, (_, ty))| (HandlePosition::Param(i), ty));
+            let results = function
+                .results
+                .iter_types()
+                .enumerate()
+                .map(|(i, ty)| (HandlePosition::Result(i), ty));
+            for (position, ty) in params.chain(results) {
+                let mut handles = Handles {
+                    resolve: self,
+                    handles: IndexSet::new(),
+                };
+                handles.visit_type(self, ty);
+                for handle in handles.handles {
+                    let resource = match handle {
+                        Handle::Own(id) | Handle::Borrow(id) => id,
+                    };
+                    if let Some(resource) = resources.get_mut(&resource) {
+                        resource.flows.push(HandleFlow {
+                            key,
+                            exported,
+                            function,
+                            position,
+                            handle,
+                        });
+                    }
+                }
+            }
+        }
+
+        resources.into_values().collect()
+    }
+
+    fn push_resource(&self, resources: &mut IndexMap<TypeId, WorldResource<'_>>, id: TypeId) {
+        if let TypeDefKind::Resource = self.types[id].kind {
+            resources.insert(
+                id,
+                WorldResource {
+                    id,
+                    constructor: None,
+                    methods: Vec::new(),
+                    statics: Vec::new(),
+                    flows: Vec::new(),
+                },
+            );
+        }
+    }
+
+    /// Follows aliases of `id`, such as those created by `use`, to the
+    /// definition of the resource.
+    fn dealias_resource(&self, mut id: TypeId) -> TypeId {
+        while let TypeDefKind::Type(Type::Id(aliased)) = self.types[id].kind {
+            id = aliased;
+        }
+        id
+    }
+}
+
+/// Collects the handles within a type, referring to the definitions of their
+/// resources.
+struct Handles<'a> {
+    resolve: &'a Resolve,
+    handles: IndexSet<Handle>,
+}
+
+impl TypeIdVisitor for Handles<'_> {
+    fn before_visit_type_id(&mut self, id: TypeId) -> bool {
+        match self.resolve.types[id].kind {
+            TypeDefKind::Handle(Handle::Own(resource)) => {
+                let resource = self.resolve.dealias_resource(resource);
+                self.handles.insert(Handle::Own(resource));
+                false
+            }
+            TypeDefKind::Handle(Handle::Borrow(resource)) => {
+                let resource = self.resolve.dealias_resource(resource);
+                self.handles.insert(Handle::Borrow(resource));
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_resources() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str(
+                "test.wit",
+                "
+                    package foo:bar;
+
+                    interface types {
+                        resource file {
+                            constructor(path: string);
+                            read: func(len: u32) -> list<u8>;
+                            open: static func(path: string) -> file;
+                        }
+                    }
+
+                    interface fs {
+                        use types.{file};
+                        record entry { name: string, file: file }
+                        list-dir: func(dir: borrow<file>) -> list<entry>;
+                        close: func(f: file);
+                    }
+
+                    world w {
+                        import fs;
+                        resource local;
+                        export take: func(l: local, f: borrow<local>) -> option<local>;
+                    }
+                ",
+            )
+            .unwrap();
+        let world = resolve.select_world(pkg, Some("w")).unwrap();
+        let resources = resolve.world_resources(world);
+
+        let describe = |r: &WorldResource<'_>| {
+            let flows = r
+                .flows
+                .iter()
+                .map(|f| {
+                    let handle = match f.handle {
+                        Handle::Own(id) => {
+                            assert_eq!(id, r.id);
+                            "own"
+                        }
+                        Handle::Borrow(id) => {
+                            assert_eq!(id, r.id);
+                            "borrow"
+                        }
+                    };
+                    format!(
+                        "{} {} {} {:?} {handle}",
+                        if f.exported { "export" } else { "import" },
+                        resolve.name_world_key(f.key),
+                        f.function.name,
+                        f.position,
+                    )
+                })
+                .collect::<Vec<_>>();
+            (
+                resolve.types[r.id].name.clone().unwrap(),
+                r.constructor.map(|f| f.name.clone()),
+                r.methods.iter().map(|f| f.name.clone()).collect::<Vec<_>>(),
+                r.statics.iter().map(|f| f.name.clone()).collect::<Vec<_>>(),
+                flows,
+            )
+        };
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(
+            describe(&resources[0]),
+            (
+                "local".to_string(),
+                None,
+                vec![],
+                vec![],
+                vec![
+                    "export take take Param(0) own".to_string(),
+                    "export take take Param(1) borrow".to_string(),
+                    "export take take Result(0) own".to_string(),
+                ],
+            )
+        );
+        assert_eq!(
+            describe(&resources[1]),
+            (
+                "file".to_string(),
+                Some("[constructor]file".to_string()),
+                vec!["[method]file.read".to_string()],
+                vec!["[static]file.open".to_string()],
+                vec![
+                    "import foo:bar/types [constructor]file Result(0) own".to_string(),
+                    "import foo:bar/types [method]file.read Param(0) borrow".to_string(),
+                    "import foo:bar/types [static]file.open Result(0) own".to_string(),
+                    "import foo:bar/fs list-dir Param(0) borrow".to_string(),
+                    "import foo:bar/fs list-dir Result(0) own".to_string(),
+                    "import foo:bar/fs close Param(0) own".to_string(),
+                ],
+            )
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Introspection of which items of a WIT package are actually used.
+//!
+//! Large packages tend to accumulate `use` statements and types which nothing
+//! refers to anymore. The methods here find the `use`s of a package, which of
+//! them and which interfaces a world refers to, and [`Resolve::lint_package`]
+//! reports `use`s and types which are dead.
+
+use crate::{
+    Function, InterfaceId, LiveTypes, PackageId, Resolve, Type, TypeDef, TypeDefKind, TypeId,
+    TypeIdVisitor, TypeOwner, WorldId, WorldItem,
+};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A `use` of a type from another interface, as found by
+/// [`Resolve::package_uses`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TypeUse {
+    /// The type defined by the `use` in the interface or world containing it.
+    pub id: TypeId,
+    /// The type in another interface which is being used.
+    pub used: TypeId,
+}
+
+/// A potential problem in a WIT package found by [`Resolve::lint_package`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A `use` of a type which nothing refers to.
+    UnusedUse(TypeUse),
+    /// A type which isn't reachable from any function.
+    UnreachableType(TypeId),
+}
+
+impl Lint {
+    /// Returns a displayable description of this lint, using `resolve` to
+    /// name the items involved.
+    pub fn display<'a>(&'a self, resolve: &'a Resolve) -> impl fmt::Display + 'a {
+        LintDisplay {
+            lint: self,
+            resolve,
+        }
+    }
+}
+
+struct LintDisplay<'a> {
+    lint: &'a Lint,
+    resolve: &'a Resolve,
+}
+
+impl fmt::Display for LintDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resolve = self.resolve;
+        match self.lint {
+            Lint::UnusedUse(u) => {
+                let ty = &resolve.types[u.id];
+                let used = &resolve.types[u.used];
+                write!(f, "unused `use` of type `{}`", type_name(used))?;
+                if ty.name != used.name {
+                    write!(f, " as `{}`", type_name(ty))?;
+                }
+                write!(
+                    f,
+                    " from {} in {}",
+                    owner_name(resolve, used.owner),
+                    owner_name(resolve, ty.owner),
+                )
+            }
+            Lint::UnreachableType(id) => {
+                let ty = &resolve.types[*id];
+                write!(
+                    f,
+                    "type `{}` in {} is not reachable from any function",
+                    type_name(ty),
+                    owner_name(resolve, ty.owner),
+                )
+            }
+        }
+    }
+}
+
+fn type_name(ty: &TypeDef) -> &str {
+    ty.name.as_deref().unwrap_or("<anonymous>")
+}
+
+fn owner_name(resolve: &Resolve, owner: TypeOwner) -> String {
+    match owner {
+        TypeOwner::Interface(id) => match resolve.id_of(id) {
+            Some(name) => format!("interface `{name}`"),
+            None => "an anonymous interface".to_string(),
+        },
+        TypeOwner::World(id) => {
+            let world = &resolve.worlds[id];
+            match world.package {
+                Some(pkg) => format!("world `{}`", resolve.id_of_name(pkg, &world.name)),
+                None => format!("world `{}`", world.name),
+            }
+        }
+        TypeOwner::None => "no interface or world".to_string(),
+    }
+}
+
+impl Resolve {
+    /// Returns all `use`s within the interfaces and worlds of `package`.
+    ///
+    /// Each `use` of a type, such as `use a.{t}` or `use a.{t as u}`, defines
+    /// a new type in the interface or world containing it which is an alias of
+    /// the type in the other interface, and that pair of types is returned
+    /// here.
+    pub fn package_uses(&self, package: PackageId) -> Vec<TypeUse> {
+        self.package_types(package)
+            .filter_map(|id| self.type_use(id))
+            .collect()
+    }
+
+    /// Returns the `use`s within `package` which are referenced by `world`.
+    ///
+    /// A `use` is referenced by a world if the world imports or exports the
+    /// interface containing it, or if it's within the world itself and the
+    /// world refers to the type it defines.
+    pub fn world_referenced_uses(&self, world: WorldId, package: PackageId) -> Vec<TypeUse> {
+        let mut live = LiveTypes::default();
+        live.add_world(self, world);
+        let live = live.iter().collect::<HashSet<_>>();
+        self.package_uses(package)
+            .into_iter()
+            .filter(|u| live.contains(&u.id))
+            .collect()
+    }
+
+    /// Returns the interfaces within `package` which are imported or exported
+    /// by `world`.
+    ///
+    /// This includes interfaces which are part of the world because of an
+    /// `include` of another world or because a `use` within the world
+    /// implicitly imports them.
+    pub fn world_referenced_interfaces(
+        &self,
+        world: WorldId,
+        package: PackageId,
+    ) -> Vec<InterfaceId> {
+        let world = &self.worlds[world];
+        world
+            .imports
+            .values()
+            .chain(world.exports.values())
+            .filter_map(|item| match item {
+                WorldItem::Interface { id, .. } => Some(*id),
+                _ => None,
+            })
+            .filter(|id| self.interfaces[*id].package == Some(package))
+            .collect()
+    }
+
+    /// Finds `use`s and types in `package` which are dead.
+    ///
+    /// A `use` is reported as unused if no type or function of this `Resolve`
+    /// refers to the type it defines, including `use`s of it in other
+    /// interfaces. Any other type in `package` is reported as unreachable if
+    /// it can't be reached from the parameters or results of any function of
+    /// this `Resolve`.
+    pub fn lint_package(&self, package: PackageId) -> Vec<Lint> {
+        let mut referenced = DirectReferences::default();
+        let mut reachable = LiveTypes::default();
+        for (_, ty) in self.types.iter() {
+            referenced.visit_type_def(self, ty);
+        }
+        for func in self.all_functions() {
+            referenced.visit_func(self, func);
+            reachable.add_func(self, func);
+        }
+        let reachable = reachable.iter().collect::<HashSet<_>>();
+
+        let mut ret = Vec::new();
+        for id in self.package_types(package) {
+            match self.type_use(id) {
+                Some(u) => {
+                    if !referenced.0.contains(&id) {
+                        ret.push(Lint::UnusedUse(u));
+                    }
+                }
+                None => {
+                    if !reachable.contains(&id) {
+                        ret.push(Lint::UnreachableType(id));
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// Returns the types defined within the interfaces and worlds of
+    /// `package`, excluding types which worlds include from other worlds.
+    fn package_types(&self, package: PackageId) -> impl Iterator<Item = TypeId> + '_ {
+        let interfaces = self
+            .interfaces
+            .iter()
+            .filter(move |(_, i)| i.package == Some(package))
+            .flat_map(|(_, i)| i.types.values().copied());
+        let worlds = self
+            .worlds
+            .iter()
+            .filter(move |(_, w)| w.package == Some(package))
+            .flat_map(move |(world, w)| {
+                w.imports
+                    .values()
+                    .chain(w.exports.values())
+                    .filter_map(move |item| match item {
+                        WorldItem::Type(id) if self.types[*id].owner == TypeOwner::World(world) => {
+                            Some(*id)
+                        }
+                        _ => None,
+                    })
+            });
+        interfaces.chain(worlds)
+    }
+
+    fn type_use(&self, id: TypeId) -> Option<TypeUse> {
+        let ty = &self.types[id];
+        match ty.kind {
+            TypeDefKind::Type(Type::Id(used)) if self.types[used].owner != ty.owner => {
+                Some(TypeUse { id, used })
+            }
+            _ => None,
+        }
+    }
+
+    fn all_functions(&self) -> impl Iterator<Item = &Function> + '_ {
+        let interfaces = self
+            .interfaces
+            .iter()
+            .flat_map(|(_, i)| i.functions.values());
+        let worlds = self.worlds.iter().flat_map(|(_, w)| {
+            w.imports
+                .values()
+                .chain(w.exports.values())
+                .filter_map(|item| match item {
+                    WorldItem::Function(f) => Some(f),
+                    _ => None,
+                })
+        });
+        interfaces.chain(worlds)
+    }
+}
+
+/// Collects the types which are referred to directly, without recursing into
+/// them.
+#[derive(Default)]
+struct DirectReferences(HashSet<TypeId>);
+
+impl TypeIdVisitor for DirectReferences {
+    fn before_visit_type_id(&mut self, id: TypeId) -> bool {
+        self.0.insert(id);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(resolve: &Resolve, ids: impl IntoIterator<Item = TypeId>) -> Vec<String> {
+        ids.into_iter()
+            .map(|id| resolve.types[id].name.clone().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn uses() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str(
+                "test.wit",
+                "
+                    package foo:bar;
+
+                    interface a {
+                        type t = u32;
+                        type u = u64;
+                    }
+
+                    interface b {
+                        use a.{t, u as v};
+                        f: func(x: t);
+                    }
+
+                    interface c {
+                        use a.{u};
+                    }
+
+                    world w {
+                        import b;
+                    }
+                ",
+            )
+            .unwrap();
+        let world = resolve.select_world(pkg, Some("w")).unwrap();
+
+        let uses = resolve.package_uses(pkg);
+        assert_eq!(names(&resolve, uses.iter().map(|u| u.id)), ["t", "v", "u"]);
+        assert_eq!(
+            names(&resolve, uses.iter().map(|u| u.used)),
+            ["t", "u", "u"]
+        );
+
+        let uses = resolve.world_referenced_uses(world, pkg);
+        assert_eq!(names(&resolve, uses.iter().map(|u| u.id)), ["t", "v"]);
+
+        let interfaces = resolve
+            .world_referenced_interfaces(world, pkg)
+            .into_iter()
+            .map(|id| resolve.interfaces[id].name.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(interfaces, ["a", "b"]);
+    }
+
+    #[test]
+    fn lints() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str(
+                "test.wit",
+                "
+                    package foo:bar;
+
+                    interface a {
+                        record r { x: u32 }
+                        type t = u32;
+                        type unreachable = u8;
+                    }
+
+                    interface b {
+                        use a.{r, t};
+                        use a.{t as unused};
+                        f: func(x: r);
+                    }
+
+                    interface c {
+                        use b.{t};
+                        g: func() -> t;
+                    }
+
+                    world w {
+                        use a.{r as world-unused};
+                        import f: func();
+                    }
+                ",
+            )
+            .unwrap();
+
+        let lints = resolve
+            .lint_package(pkg)
+            .iter()
+            .map(|lint| lint.display(&resolve).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            [
+                "type `unreachable` in interface `foo:bar/a` is not reachable from any function",
+                "unused `use` of type `t` as `unused` from interface `foo:bar/a` in interface `foo:bar/b`",
+                "unused `use` of type `r` as `world-unused` from interface `foo:bar/a` in world `foo:bar/w`",
+            ]
+        );
+    }
+}
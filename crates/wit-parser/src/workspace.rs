@@ -0,0 +1,176 @@
+//! Checking of whole workspaces of WIT packages at once.
+//!
+//! Repositories with many WIT packages want a single check, for example in CI
+//! or as a pre-commit hook, which reports every problem in every package
+//! rather than stopping at the first error like [`Resolve::push_dir`] does.
+
+use crate::{Resolve, SourceMap};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The result of [`Resolve::check_workspace`].
+#[derive(Debug, Default)]
+pub struct WorkspaceCheck {
+    /// The directories of the packages in the workspace which were resolved
+    /// successfully.
+    pub packages: Vec<PathBuf>,
+    /// The errors found in the workspace.
+    ///
+    /// Each error describes the location of the problem in its source file
+    /// where possible.
+    pub errors: Vec<anyhow::Error>,
+}
+
+impl WorkspaceCheck {
+    /// Returns whether no errors were found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Resolve {
+    /// Resolves every WIT package in the workspace at `path`, reporting all
+    /// errors found rather than stopping at the first one.
+    ///
+    /// A workspace is a directory tree in which every directory directly
+    /// containing `*.wit` files is a package directory like those accepted by
+    /// [`Resolve::push_dir`]. The `deps` directories of packages contain their
+    /// dependencies and aren't packages of the workspace themselves, and
+    /// hidden directories are skipped.
+    ///
+    /// The syntax of every file in the workspace, including the files of
+    /// dependencies, is checked first and the first syntax error of each file
+    /// is reported. Packages without syntax errors in their files or the files
+    /// of their dependencies are then each resolved within a clone of `self`,
+    /// so any configuration of `self` such as enabled features applies, and
+    /// the first resolution error of each package is reported.
+    ///
+    /// Errors are only returned for failures to read the workspace.
+    pub fn check_workspace(&self, path: impl AsRef<Path>) -> Result<WorkspaceCheck> {
+        let path = path.as_ref();
+        let mut packages = Vec::new();
+        let mut files = Vec::new();
+        find_packages(path, false, &mut packages, &mut files)?;
+
+        let mut ret = WorkspaceCheck::default();
+        let mut invalid = HashSet::new();
+        for file in files {
+            let mut map = SourceMap::new();
+            map.push_file(&file)?;
+            let errors = map.syntax_errors();
+            if !errors.is_empty() {
+                ret.errors.extend(errors);
+                invalid.insert(file);
+            }
+        }
+
+        for package in packages {
+            let deps = package.join("deps");
+            let has_invalid_file = invalid
+                .iter()
+                .any(|file| file.parent() == Some(&package) || file.starts_with(&deps));
+            if has_invalid_file {
+                continue;
+            }
+            match self.clone().push_dir(&package) {
+                Ok(_) => ret.packages.push(package),
+                Err(e) => ret.errors.push(e),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Finds the package directories within `dir` and all `*.wit` files within
+/// them and their dependencies.
+fn find_packages(
+    dir: &Path,
+    in_deps: bool,
+    packages: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let cx = || format!("failed to read directory {dir:?}");
+    let mut entries = dir
+        .read_dir()
+        .with_context(cx)?
+        .collect::<std::io::Result<Vec<_>>>()
+        .with_context(cx)?;
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut is_package = false;
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = match name.to_str() {
+            Some(name) => name,
+            None => continue,
+        };
+        if path.is_dir() {
+            if name.starts_with('.') {
+                continue;
+            }
+            find_packages(&path, in_deps || name == "deps", packages, files)?;
+        } else if name.ends_with(".wit") {
+            is_package = true;
+            files.push(path);
+        }
+    }
+    if is_package && !in_deps {
+        packages.push(dir.to_path_buf());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_workspace() {
+        let dir = std::env::temp_dir().join(format!("wit-check-workspace-{}", std::process::id()));
+        let write = |path: &str, contents: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write("ok/a.wit", "package a:ok; interface i { use b.{t}; }");
+        write("ok/b.wit", "interface b { type t = u32; }");
+        write("ok/deps/dep.wit", "package a:dep; interface d {}");
+        write("syntax/x.wit", "package a:syntax; interface i { x: }");
+        write("syntax/y.wit", "interface j { y: func() -> ; }");
+        write(
+            "resolve/a.wit",
+            "package a:resolve; interface i { use missing.{t}; }",
+        );
+        write(
+            "bad-dep/a.wit",
+            "package a:bad-dep; interface i { use a:dep/d.{}; }",
+        );
+        write("bad-dep/deps/dep.wit", "package a:dep; interface d { z }");
+
+        let check = Resolve::default().check_workspace(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!check.is_ok());
+        assert_eq!(check.packages, [dir.join("ok")]);
+        let errors = check
+            .errors
+            .iter()
+            .map(|e| format!("{e:?}"))
+            .collect::<Vec<_>>();
+        assert_eq!(errors.len(), 4, "{errors:#?}");
+        assert!(
+            errors[0].contains("dep.wit:1:"),
+            "{}",
+            errors[0]
+        );
+        assert!(errors[1].contains("x.wit:1:"), "{}", errors[1]);
+        assert!(errors[2].contains("y.wit:1:"), "{}", errors[2]);
+        assert!(
+            errors[3].contains("interface or world `missing`"),
+            "{}",
+            errors[3]
+        );
+    }
+}
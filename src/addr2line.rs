@@ -14,6 +14,8 @@ pub struct Addr2lineModules<'a> {
 struct Module<'a> {
     range: Range<u64>,
     code_start: Option<u64>,
+    imported_funcs: u32,
+    func_bodies: Vec<Range<u64>>,
     custom_sections: HashMap<&'a str, &'a [u8]>,
     context: Option<Context<EndianSlice<'a, gimli::LittleEndian>>>,
 }
@@ -33,6 +35,8 @@ impl<'a> Addr2lineModules<'a> {
                     cur_module = Some(Module {
                         range: range.start as u64..0,
                         code_start: None,
+                        imported_funcs: 0,
+                        func_bodies: Vec::new(),
                         custom_sections: HashMap::new(),
                         context: None,
                     });
@@ -43,10 +47,25 @@ impl<'a> Addr2lineModules<'a> {
                         cur.custom_sections.insert(s.name(), s.data());
                     }
                 }
+                Payload::ImportSection(s) => {
+                    if let Some(cur) = &mut cur_module {
+                        for import in s {
+                            if let wasmparser::TypeRef::Func(_) = import?.ty {
+                                cur.imported_funcs += 1;
+                            }
+                        }
+                    }
+                }
                 Payload::CodeSectionStart { range, .. } => {
                     assert!(cur_module.is_some());
                     cur_module.as_mut().unwrap().code_start = Some(range.start as u64);
                 }
+                Payload::CodeSectionEntry(body) => {
+                    if let Some(cur) = &mut cur_module {
+                        let range = body.range();
+                        cur.func_bodies.push(range.start as u64..range.end as u64);
+                    }
+                }
 
                 Payload::End(offset) => {
                     if let Some(mut module) = cur_module.take() {
@@ -60,26 +79,75 @@ impl<'a> Addr2lineModules<'a> {
         Ok(Addr2lineModules { modules })
     }
 
-    pub fn context(
-        &mut self,
-        addr: u64,
-        code_section_relative: bool,
-    ) -> Result<Option<(&mut Context<EndianSlice<'a, gimli::LittleEndian>>, u64)>> {
-        let module = if code_section_relative {
+    fn module(&self, addr: u64, code_section_relative: bool) -> Result<Option<usize>> {
+        if code_section_relative {
             if self.modules.len() == 1 {
-                &mut self.modules[0]
+                Ok(Some(0))
             } else {
                 bail!("cannot use `--code-section-relative` with more than one module")
             }
         } else {
-            match self
+            Ok(self
                 .modules
-                .iter_mut()
-                .find(|module| module.range.start <= addr && addr <= module.range.end)
-            {
-                Some(module) => module,
-                None => return Ok(None),
+                .iter()
+                .position(|module| module.range.start <= addr && addr <= module.range.end))
+        }
+    }
+
+    /// Finds the function containing `addr` without using DWARF, returning its
+    /// index and its name from the `name` section, if any.
+    ///
+    /// This is a fallback for when there's no DWARF debugging information for
+    /// the address.
+    pub fn function(
+        &self,
+        addr: u64,
+        code_section_relative: bool,
+    ) -> Result<Option<(u32, Option<&'a str>)>> {
+        let module = match self.module(addr, code_section_relative)? {
+            Some(i) => &self.modules[i],
+            None => return Ok(None),
+        };
+        let addr = match (code_section_relative, module.code_start) {
+            (true, Some(start)) => addr + start,
+            (true, None) => return Ok(None),
+            (false, _) => addr,
+        };
+        let i = match module
+            .func_bodies
+            .iter()
+            .position(|body| body.start <= addr && addr < body.end)
+        {
+            Some(i) => i as u32,
+            None => return Ok(None),
+        };
+        let index = module.imported_funcs + i;
+
+        let mut name = None;
+        if let Some(data) = module.custom_sections.get("name") {
+            let reader = wasmparser::BinaryReader::new(data, 0);
+            for subsection in wasmparser::NameSectionReader::new(reader) {
+                if let wasmparser::Name::Function(names) = subsection? {
+                    for naming in names {
+                        let naming = naming?;
+                        if naming.index == index {
+                            name = Some(naming.name);
+                        }
+                    }
+                }
             }
+        }
+        Ok(Some((index, name)))
+    }
+
+    pub fn context(
+        &mut self,
+        addr: u64,
+        code_section_relative: bool,
+    ) -> Result<Option<(&mut Context<EndianSlice<'a, gimli::LittleEndian>>, u64)>> {
+        let module = match self.module(addr, code_section_relative)? {
+            Some(i) => &mut self.modules[i],
+            None => return Ok(None),
         };
 
         let dwarf = gimli::Dwarf::load(|id| -> Result<_> {
@@ -0,0 +1,470 @@
+//! Analysis summarizing the effects of functions on the state of their
+//! instance.
+//!
+//! For each function defined by a module this reports which globals it reads
+//! and writes, which memories it reads and writes, and whether it can trap.
+//! This is useful to audit modules and to find out which functions can run in
+//! parallel with each other.
+//!
+//! The summary of a function includes the effects of all the functions it may
+//! call. Indirect calls, through `call_indirect` or `call_ref`, are assumed to
+//! possibly call any function whose reference escapes, that is which is
+//! exported or referenced by an element segment or a `ref.func` instruction,
+//! as well as functions of the host.
+//!
+//! Functions of the host, whether imported or called indirectly, have unknown
+//! effects. They are assumed to possibly trap, and the functions calling them
+//! are flagged with [`FunctionSummary::calls_host`] since they may also access
+//! globals and memories which are imported or exported. Exhaustion of the call
+//! stack isn't considered to be a trap.
+
+use anyhow::{bail, Result};
+use std::collections::{BTreeSet, HashMap};
+use wasmparser::{
+    ElementItems, Encoding, ExternalKind, FunctionBody, Name, Operator, Parser, Payload, TypeRef,
+};
+
+/// A summary of the effects of a function defined by a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionSummary {
+    /// The index of the function.
+    pub index: u32,
+    /// The name of the function in the `name` section, if any.
+    pub name: Option<String>,
+    /// The globals which the function may read.
+    pub globals_read: BTreeSet<u32>,
+    /// The globals which the function may write.
+    pub globals_written: BTreeSet<u32>,
+    /// The memories which the function may read, including through
+    /// `memory.size`.
+    pub memories_read: BTreeSet<u32>,
+    /// The memories which the function may write, including through
+    /// `memory.grow`.
+    pub memories_written: BTreeSet<u32>,
+    /// Whether the function may trap.
+    pub can_trap: bool,
+    /// Whether the function may call functions of the host, whose effects are
+    /// unknown.
+    pub calls_host: bool,
+}
+
+impl FunctionSummary {
+    /// Merges the effects of `other` into this summary, returning whether
+    /// anything changed.
+    fn merge(&mut self, other: &FunctionSummary) -> bool {
+        let before = (
+            self.globals_read.len(),
+            self.globals_written.len(),
+            self.memories_read.len(),
+            self.memories_written.len(),
+            self.can_trap,
+            self.calls_host,
+        );
+        self.globals_read.extend(&other.globals_read);
+        self.globals_written.extend(&other.globals_written);
+        self.memories_read.extend(&other.memories_read);
+        self.memories_written.extend(&other.memories_written);
+        self.can_trap |= other.can_trap;
+        self.calls_host |= other.calls_host;
+        before
+            != (
+                self.globals_read.len(),
+                self.globals_written.len(),
+                self.memories_read.len(),
+                self.memories_written.len(),
+                self.can_trap,
+                self.calls_host,
+            )
+    }
+}
+
+/// Summarizes the effects of each function defined by the core wasm module
+/// `wasm`, in order of their index.
+pub fn summarize_functions(wasm: &[u8]) -> Result<Vec<FunctionSummary>> {
+    let mut num_imported_funcs = 0;
+    let mut escaping = BTreeSet::new();
+    let mut names = HashMap::new();
+    // The summary of each defined function's own instructions, along with
+    // the functions it calls directly and whether it makes indirect calls.
+    let mut funcs: Vec<(FunctionSummary, BTreeSet<u32>, bool)> = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::Version {
+                encoding: Encoding::Component,
+                ..
+            } => bail!("summarizing functions of components is not supported"),
+            Payload::ImportSection(s) => {
+                for import in s {
+                    if let TypeRef::Func(_) = import?.ty {
+                        num_imported_funcs += 1;
+                    }
+                }
+            }
+            Payload::GlobalSection(s) => {
+                for global in s {
+                    let mut reader = global?.init_expr.get_operators_reader();
+                    while !reader.eof() {
+                        if let Operator::RefFunc { function_index } = reader.read()? {
+                            escaping.insert(function_index);
+                        }
+                    }
+                }
+            }
+            Payload::ExportSection(s) => {
+                for export in s {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        escaping.insert(export.index);
+                    }
+                }
+            }
+            Payload::ElementSection(s) => {
+                for element in s {
+                    match element?.items {
+                        ElementItems::Functions(r) => {
+                            for func in r {
+                                escaping.insert(func?);
+                            }
+                        }
+                        ElementItems::Expressions(_, r) => {
+                            for expr in r {
+                                let mut reader = expr?.get_operators_reader();
+                                while !reader.eof() {
+                                    if let Operator::RefFunc { function_index } = reader.read()? {
+                                        escaping.insert(function_index);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let index = num_imported_funcs + funcs.len() as u32;
+                funcs.push(summarize_body(index, &body, &mut escaping)?);
+            }
+            Payload::CustomSection(c) => {
+                if let wasmparser::KnownCustom::Name(reader) = c.as_known() {
+                    for subsection in reader {
+                        // Malformed `name` sections are ignored as they are
+                        // only informational.
+                        let Ok(Name::Function(map)) = subsection else {
+                            continue;
+                        };
+                        for naming in map.into_iter().flatten() {
+                            names.insert(naming.index, naming.name.to_string());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Propagates the effects of callees to their callers until nothing
+    // changes anymore.
+    let mut summaries = funcs.iter().map(|f| f.0.clone()).collect::<Vec<_>>();
+    let defined = |index: u32| index.checked_sub(num_imported_funcs).map(|i| i as usize);
+    let none = BTreeSet::new();
+    loop {
+        let mut changed = false;
+        for (i, (_, calls, indirect)) in funcs.iter().enumerate() {
+            let mut summary = summaries[i].clone();
+            let callees = calls
+                .iter()
+                .chain(if *indirect { &escaping } else { &none });
+            for callee in callees {
+                match defined(*callee) {
+                    Some(callee) => changed |= summary.merge(&summaries[callee]),
+                    None => {
+                        changed |= !summary.calls_host || !summary.can_trap;
+                        summary.calls_host = true;
+                        summary.can_trap = true;
+                    }
+                }
+            }
+            summaries[i] = summary;
+        }
+        if !changed {
+            break;
+        }
+    }
+    for summary in summaries.iter_mut() {
+        summary.name = names.remove(&summary.index);
+    }
+    Ok(summaries)
+}
+
+/// Summarizes the effects of the instructions of the function `index` with
+/// `body`, returning the summary, the functions it calls directly, and
+/// whether it makes indirect calls.
+fn summarize_body(
+    index: u32,
+    body: &FunctionBody<'_>,
+    escaping: &mut BTreeSet<u32>,
+) -> Result<(FunctionSummary, BTreeSet<u32>, bool)> {
+    let mut summary = FunctionSummary {
+        index,
+        ..FunctionSummary::default()
+    };
+    let mut calls = BTreeSet::new();
+    let mut indirect = false;
+    let mut reader = body.get_operators_reader()?;
+    while !reader.eof() {
+        let op = reader.read()?;
+        summary.can_trap |= may_trap(&op);
+        if let Some((memory, access)) = memory_access(&op) {
+            if access != Access::Write {
+                summary.memories_read.insert(memory);
+            }
+            if access != Access::Read {
+                summary.memories_written.insert(memory);
+            }
+        }
+        match op {
+            Operator::GlobalGet { global_index }
+            | Operator::GlobalAtomicGet { global_index, .. } => {
+                summary.globals_read.insert(global_index);
+            }
+            Operator::GlobalSet { global_index }
+            | Operator::GlobalAtomicSet { global_index, .. } => {
+                summary.globals_written.insert(global_index);
+            }
+            Operator::GlobalAtomicRmwAdd { global_index, .. }
+            | Operator::GlobalAtomicRmwSub { global_index, .. }
+            | Operator::GlobalAtomicRmwAnd { global_index, .. }
+            | Operator::GlobalAtomicRmwOr { global_index, .. }
+            | Operator::GlobalAtomicRmwXor { global_index, .. }
+            | Operator::GlobalAtomicRmwXchg { global_index, .. }
+            | Operator::GlobalAtomicRmwCmpxchg { global_index, .. } => {
+                summary.globals_read.insert(global_index);
+                summary.globals_written.insert(global_index);
+            }
+            Operator::MemoryCopy { dst_mem, src_mem } => {
+                summary.memories_read.insert(src_mem);
+                summary.memories_written.insert(dst_mem);
+            }
+            Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
+                calls.insert(function_index);
+            }
+            Operator::CallIndirect { .. }
+            | Operator::ReturnCallIndirect { .. }
+            | Operator::CallRef { .. }
+            | Operator::ReturnCallRef { .. } => indirect = true,
+            Operator::RefFunc { function_index } => {
+                escaping.insert(function_index);
+            }
+            _ => {}
+        }
+    }
+    // The host may be called indirectly through references it passed in.
+    if indirect {
+        summary.calls_host = true;
+        summary.can_trap = true;
+    }
+    Ok((summary, calls, indirect))
+}
+
+/// How an instruction accesses a memory.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// Returns the memory accessed by `op`, if any, and how it's accessed.
+///
+/// `memory.copy`, which accesses two memories, is handled separately.
+fn memory_access(op: &Operator<'_>) -> Option<(u32, Access)> {
+    use Operator::*;
+
+    Some(match *op {
+        I32Load { memarg }
+        | I64Load { memarg }
+        | F32Load { memarg }
+        | F64Load { memarg }
+        | I32Load8S { memarg }
+        | I32Load8U { memarg }
+        | I32Load16S { memarg }
+        | I32Load16U { memarg }
+        | I64Load8S { memarg }
+        | I64Load8U { memarg }
+        | I64Load16S { memarg }
+        | I64Load16U { memarg }
+        | I64Load32S { memarg }
+        | I64Load32U { memarg }
+        | I32AtomicLoad { memarg }
+        | I64AtomicLoad { memarg }
+        | I32AtomicLoad8U { memarg }
+        | I32AtomicLoad16U { memarg }
+        | I64AtomicLoad8U { memarg }
+        | I64AtomicLoad16U { memarg }
+        | I64AtomicLoad32U { memarg }
+        | MemoryAtomicNotify { memarg }
+        | MemoryAtomicWait32 { memarg }
+        | MemoryAtomicWait64 { memarg }
+        | V128Load { memarg }
+        | V128Load8x8S { memarg }
+        | V128Load8x8U { memarg }
+        | V128Load16x4S { memarg }
+        | V128Load16x4U { memarg }
+        | V128Load32x2S { memarg }
+        | V128Load32x2U { memarg }
+        | V128Load8Splat { memarg }
+        | V128Load16Splat { memarg }
+        | V128Load32Splat { memarg }
+        | V128Load64Splat { memarg }
+        | V128Load32Zero { memarg }
+        | V128Load64Zero { memarg }
+        | V128Load8Lane { memarg, .. }
+        | V128Load16Lane { memarg, .. }
+        | V128Load32Lane { memarg, .. }
+        | V128Load64Lane { memarg, .. } => (memarg.memory, Access::Read),
+
+        I32Store { memarg }
+        | I64Store { memarg }
+        | F32Store { memarg }
+        | F64Store { memarg }
+        | I32Store8 { memarg }
+        | I32Store16 { memarg }
+        | I64Store8 { memarg }
+        | I64Store16 { memarg }
+        | I64Store32 { memarg }
+        | I32AtomicStore { memarg }
+        | I64AtomicStore { memarg }
+        | I32AtomicStore8 { memarg }
+        | I32AtomicStore16 { memarg }
+        | I64AtomicStore8 { memarg }
+        | I64AtomicStore16 { memarg }
+        | I64AtomicStore32 { memarg }
+        | V128Store { memarg }
+        | V128Store8Lane { memarg, .. }
+        | V128Store16Lane { memarg, .. }
+        | V128Store32Lane { memarg, .. }
+        | V128Store64Lane { memarg, .. } => (memarg.memory, Access::Write),
+
+        I32AtomicRmwAdd { memarg }
+        | I64AtomicRmwAdd { memarg }
+        | I32AtomicRmw8AddU { memarg }
+        | I32AtomicRmw16AddU { memarg }
+        | I64AtomicRmw8AddU { memarg }
+        | I64AtomicRmw16AddU { memarg }
+        | I64AtomicRmw32AddU { memarg }
+        | I32AtomicRmwSub { memarg }
+        | I64AtomicRmwSub { memarg }
+        | I32AtomicRmw8SubU { memarg }
+        | I32AtomicRmw16SubU { memarg }
+        | I64AtomicRmw8SubU { memarg }
+        | I64AtomicRmw16SubU { memarg }
+        | I64AtomicRmw32SubU { memarg }
+        | I32AtomicRmwAnd { memarg }
+        | I64AtomicRmwAnd { memarg }
+        | I32AtomicRmw8AndU { memarg }
+        | I32AtomicRmw16AndU { memarg }
+        | I64AtomicRmw8AndU { memarg }
+        | I64AtomicRmw16AndU { memarg }
+        | I64AtomicRmw32AndU { memarg }
+        | I32AtomicRmwOr { memarg }
+        | I64AtomicRmwOr { memarg }
+        | I32AtomicRmw8OrU { memarg }
+        | I32AtomicRmw16OrU { memarg }
+        | I64AtomicRmw8OrU { memarg }
+        | I64AtomicRmw16OrU { memarg }
+        | I64AtomicRmw32OrU { memarg }
+        | I32AtomicRmwXor { memarg }
+        | I64AtomicRmwXor { memarg }
+        | I32AtomicRmw8XorU { memarg }
+        | I32AtomicRmw16XorU { memarg }
+        | I64AtomicRmw8XorU { memarg }
+        | I64AtomicRmw16XorU { memarg }
+        | I64AtomicRmw32XorU { memarg }
+        | I32AtomicRmwXchg { memarg }
+        | I64AtomicRmwXchg { memarg }
+        | I32AtomicRmw8XchgU { memarg }
+        | I32AtomicRmw16XchgU { memarg }
+        | I64AtomicRmw8XchgU { memarg }
+        | I64AtomicRmw16XchgU { memarg }
+        | I64AtomicRmw32XchgU { memarg }
+        | I32AtomicRmwCmpxchg { memarg }
+        | I64AtomicRmwCmpxchg { memarg }
+        | I32AtomicRmw8CmpxchgU { memarg }
+        | I32AtomicRmw16CmpxchgU { memarg }
+        | I64AtomicRmw8CmpxchgU { memarg }
+        | I64AtomicRmw16CmpxchgU { memarg }
+        | I64AtomicRmw32CmpxchgU { memarg } => (memarg.memory, Access::ReadWrite),
+
+        MemorySize { mem } => (mem, Access::Read),
+        MemoryGrow { mem }
+        | MemoryFill { mem }
+        | MemoryInit { mem, .. }
+        | MemoryDiscard { mem } => (mem, Access::Write),
+        _ => return None,
+    })
+}
+
+/// Returns whether executing `op` may trap, not considering the functions it
+/// calls.
+fn may_trap(op: &Operator<'_>) -> bool {
+    use Operator::*;
+
+    // Memory accesses trap when out of bounds, except for `memory.size` and
+    // `memory.grow` which don't access the contents of the memory.
+    if memory_access(op).is_some() {
+        return !matches!(op, MemorySize { .. } | MemoryGrow { .. });
+    }
+    matches!(
+        op,
+        Unreachable
+            | MemoryCopy { .. }
+            | I32DivS
+            | I32DivU
+            | I32RemS
+            | I32RemU
+            | I64DivS
+            | I64DivU
+            | I64RemS
+            | I64RemU
+            | I32TruncF32S
+            | I32TruncF32U
+            | I32TruncF64S
+            | I32TruncF64U
+            | I64TruncF32S
+            | I64TruncF32U
+            | I64TruncF64S
+            | I64TruncF64U
+            | CallIndirect { .. }
+            | ReturnCallIndirect { .. }
+            | CallRef { .. }
+            | ReturnCallRef { .. }
+            | TableGet { .. }
+            | TableSet { .. }
+            | TableFill { .. }
+            | TableCopy { .. }
+            | TableInit { .. }
+            | RefAsNonNull
+            | RefCastNonNull { .. }
+            | RefCastNullable { .. }
+            | StructGet { .. }
+            | StructGetS { .. }
+            | StructGetU { .. }
+            | StructSet { .. }
+            | ArrayNewData { .. }
+            | ArrayNewElem { .. }
+            | ArrayGet { .. }
+            | ArrayGetS { .. }
+            | ArrayGetU { .. }
+            | ArraySet { .. }
+            | ArrayLen
+            | ArrayFill { .. }
+            | ArrayCopy { .. }
+            | ArrayInitData { .. }
+            | ArrayInitElem { .. }
+            | I31GetS
+            | I31GetU
+            | ContBind { .. }
+            | Resume { .. }
+            | ResumeThrow { .. }
+            | Switch { .. }
+    )
+}
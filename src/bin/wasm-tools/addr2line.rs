@@ -18,6 +18,11 @@ use wasm_tools::addr2line::Addr2lineModules;
 /// Each address may have multiple lines printed for it indicating that the
 /// address is an inlined function into another function. Frames are printed
 /// innermost or youngest first.
+///
+/// If there's no DWARF information for an address then the name of the
+/// function containing it is printed instead, as found in the `name` section.
+/// The `name` section is also used for the innermost frame if DWARF has no
+/// function for it, such as with DWARF that only has line tables.
 #[derive(clap::Parser)]
 pub struct Opts {
     #[clap(flatten)]
@@ -72,6 +77,7 @@ impl Opts {
             addr.parse()?
         };
 
+        let function = modules.function(addr, self.code_section_relative)?;
         let (cx, text_relative_addr) = match modules.context(addr, self.code_section_relative)? {
             Some(pair) => pair,
             None => bail!("no module found which contains this address"),
@@ -91,12 +97,14 @@ impl Opts {
             } else {
                 write!(out, "\t")?;
             }
-            first = false;
-            if let Some(func) = &frame.function {
-                write!(out, "{}", func.demangle()?)?;
-            } else {
-                write!(out, "<unnamed>")?;
+            match (&frame.function, function) {
+                (Some(func), _) => write!(out, "{}", func.demangle()?)?,
+                (None, Some((_, Some(name)))) if first => {
+                    write!(out, "{}", addr2line::demangle_auto(name.into(), None))?
+                }
+                (None, _) => write!(out, "<unnamed>")?,
             }
+            first = false;
 
             if let Some(loc) = &frame.location {
                 write!(out, " ")?;
@@ -112,8 +120,18 @@ impl Opts {
             }
             writeln!(out, "")?;
         }
+        drop(frames);
         if first {
-            writeln!(out, "{addr:#x}: no dwarf frames found for this address")?;
+            // Without DWARF fall back to the name of the function containing
+            // this address, if it can be found.
+            match function {
+                Some((_, Some(name))) => {
+                    let name = addr2line::demangle_auto(name.into(), None);
+                    writeln!(out, "{addr:#x}: {name}")?;
+                }
+                Some((index, None)) => writeln!(out, "{addr:#x}: <function {index}>")?,
+                None => writeln!(out, "{addr:#x}: no dwarf frames found for this address")?,
+            }
         }
         Ok(())
     }
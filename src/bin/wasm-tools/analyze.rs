@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::io::Write;
+use wasm_tools::analysis::summarize_functions;
+
+/// Summarize the effects of the functions of a WebAssembly module.
+///
+/// This prints a JSON array with an entry for each function defined by the
+/// module, reporting which globals and memories it may read and write and
+/// whether it may trap. The effects of a function include those of all the
+/// functions it may call.
+///
+/// Functions of the host have unknown effects, so functions which may call
+/// them, directly or indirectly, are flagged with `calls_host` and are
+/// assumed to possibly trap. Exhaustion of the call stack isn't considered to
+/// be a trap.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let functions = summarize_functions(&input)?
+            .into_iter()
+            .map(|f| {
+                serde_json::json!({
+                    "index": f.index,
+                    "name": f.name,
+                    "globals": {
+                        "read": f.globals_read,
+                        "written": f.globals_written,
+                    },
+                    "memories": {
+                        "read": f.memories_read,
+                        "written": f.memories_written,
+                    },
+                    "can_trap": f.can_trap,
+                    "calls_host": f.calls_host,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut output = self.io.output_writer()?;
+        writeln!(output, "{}", serde_json::to_string_pretty(&functions)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use wasm_tools::branch_hints::{verify_branch_hints, BranchProfile, InjectBranchHints};
+use wasm_tools::transform::Transform;
+
+/// Add and check branch hints of a module.
+#[derive(clap::Parser)]
+pub enum Opts {
+    Inject(InjectOpts),
+    Verify(VerifyOpts),
+}
+
+impl Opts {
+    pub fn run(&self) -> Result<()> {
+        match self {
+            Opts::Inject(opts) => opts.run(),
+            Opts::Verify(opts) => opts.run(),
+        }
+    }
+
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        match self {
+            Opts::Inject(opts) => opts.general_opts(),
+            Opts::Verify(opts) => opts.general_opts(),
+        }
+    }
+}
+
+/// Add branch hints derived from a profile to a module.
+///
+/// The profile is a text file where each line describes a branch with four
+/// integers: the function index, the offset of the `if` or `br_if`
+/// instruction from the start of the function body, and the number of times
+/// the branch was taken and not taken. Integers may be written in hexadecimal
+/// with a `0x` prefix, and `#` starts a comment. Counts of a branch on
+/// multiple lines are added together.
+///
+/// The hints are written to the `metadata.code.branch_hint` custom section,
+/// which is placed before the code section. Existing hints of branches which
+/// don't have enough data in the profile are kept. The number of hinted
+/// branches is reported on stderr.
+#[derive(clap::Parser)]
+pub struct InjectOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Path to the profile to derive hints from.
+    #[clap(short, long, value_name = "PATH")]
+    profile: PathBuf,
+
+    /// The minimum number of times a branch must have been executed to be
+    /// hinted.
+    #[clap(long, value_name = "N")]
+    min_count: Option<u64>,
+
+    /// The minimum fraction, between 0 and 1, of executions which a branch
+    /// must have gone the same way to be hinted.
+    #[clap(long, value_name = "FRACTION")]
+    min_bias: Option<f64>,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl InjectOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let profile = std::fs::read_to_string(&self.profile)
+            .with_context(|| format!("failed to read {}", self.profile.display()))?;
+        let profile = BranchProfile::parse(&profile)
+            .with_context(|| format!("failed to parse {}", self.profile.display()))?;
+        let mut inject = InjectBranchHints::new(profile);
+        if let Some(min_count) = self.min_count {
+            inject.min_count(min_count);
+        }
+        if let Some(min_bias) = self.min_bias {
+            inject.min_bias(min_bias);
+        }
+        let output = inject.apply(&input)?;
+        for diagnostic in output.diagnostics.iter() {
+            eprintln!("{diagnostic}");
+        }
+        self.io.output_wasm(&output.wasm, self.wat)?;
+        Ok(())
+    }
+}
+
+/// Check that the branch hints of a module refer to branch instructions.
+///
+/// This fails if the `metadata.code.branch_hint` custom section is after the
+/// code section, if its hints aren't sorted by function and offset, or if a
+/// hint refers to something other than an `if` or `br_if` instruction of a
+/// function defined by the module. Otherwise the number of hints is printed.
+#[derive(clap::Parser)]
+pub struct VerifyOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+}
+
+impl VerifyOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let hints = verify_branch_hints(&input)?;
+        let mut output = self.io.output_writer()?;
+        writeln!(output, "{hints} branch hints")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+
+/// Canonicalize the encoding of a module.
+///
+/// This rewrites a module into a canonical encoding which is byte-identical
+/// for modules that only differ in semantically irrelevant encoding choices.
+/// Integers are encoded with their minimal width, adjacent declarations of
+/// locals with the same type are merged, duplicate rec groups are removed,
+/// entries of the `name` section are sorted by index, and custom sections are
+/// moved to the end of the module and sorted by name. This is useful for
+/// content-addressed stores of modules.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let output = wasm_tools::canonicalize::canonicalize(&input)?;
+        self.io.output_wasm(&output, self.wat)?;
+        Ok(())
+    }
+}
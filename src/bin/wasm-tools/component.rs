@@ -2,7 +2,7 @@
 
 use anyhow::{bail, Context, Result};
 use clap::Parser;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Read;
 use std::mem;
 use std::path::{Path, PathBuf};
@@ -13,9 +13,10 @@ use wasmparser::types::{CoreTypeId, EntityType, Types};
 use wasmparser::{Payload, ValidPayload};
 use wat::Detect;
 use wit_component::{
-    embed_component_metadata, ComponentEncoder, DecodedWasm, Linker, StringEncoding, WitPrinter,
+    embed_component_metadata, CanonicalOptions, ComponentEncoder, DecodedWasm, DirectoryCache,
+    Linker, StaticLinker, StringEncoding, WitPrinter,
 };
-use wit_parser::{PackageId, Resolve};
+use wit_parser::{DirectoryPackageResolver, PackageId, Resolve};
 
 /// WebAssembly wit-based component tooling.
 #[derive(Parser)]
@@ -23,10 +24,13 @@ pub enum Opts {
     New(NewOpts),
     Wit(WitOpts),
     Embed(EmbedOpts),
+    Stub(StubOpts),
     Targets(TargetsOpts),
     Link(LinkOpts),
     SemverCheck(SemverCheckOpts),
     Unbundle(UnbundleOpts),
+    Flatten(FlattenOpts),
+    Graph(GraphOpts),
 }
 
 impl Opts {
@@ -35,10 +39,13 @@ impl Opts {
             Opts::New(new) => new.run(),
             Opts::Wit(wit) => wit.run(),
             Opts::Embed(embed) => embed.run(),
+            Opts::Stub(stub) => stub.run(),
             Opts::Targets(targets) => targets.run(),
             Opts::Link(link) => link.run(),
             Opts::SemverCheck(s) => s.run(),
             Opts::Unbundle(s) => s.run(),
+            Opts::Flatten(s) => s.run(),
+            Opts::Graph(s) => s.run(),
         }
     }
 
@@ -47,10 +54,13 @@ impl Opts {
             Opts::New(new) => new.general_opts(),
             Opts::Wit(wit) => wit.general_opts(),
             Opts::Embed(embed) => embed.general_opts(),
+            Opts::Stub(stub) => stub.general_opts(),
             Opts::Targets(targets) => targets.general_opts(),
             Opts::Link(link) => link.general_opts(),
             Opts::SemverCheck(s) => s.general_opts(),
             Opts::Unbundle(s) => s.general_opts(),
+            Opts::Flatten(s) => s.general_opts(),
+            Opts::Graph(s) => s.general_opts(),
         }
     }
 }
@@ -81,6 +91,17 @@ fn parse_adapter(s: &str) -> Result<(String, Vec<u8>)> {
     Ok((name.to_string(), wasm))
 }
 
+fn parse_adapter_path(s: &str) -> Result<(String, PathBuf)> {
+    let (name, path) = parse_optionally_name_file(s);
+    Ok((name.to_string(), PathBuf::from(path)))
+}
+
+fn parse_adapter_wit(s: &str) -> Result<(String, PathBuf)> {
+    s.split_once('=')
+        .map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+        .context("expected `--adapt-wit` option to be of the form `NAME=WIT`")
+}
+
 fn parse_import_name(s: &str) -> Result<(String, String)> {
     s.split_once('=')
         .map(|(old, new)| (old.to_string(), new.to_string()))
@@ -112,8 +133,19 @@ pub struct NewOpts {
     /// The second part of this argument, optionally specified, is the interface
     /// that this adapter module imports. If not specified then the interface
     /// imported is inferred from the adapter module itself.
-    #[clap(long = "adapt", value_name = "[NAME=]MODULE", value_parser = parse_adapter)]
-    adapters: Vec<(String, Vec<u8>)>,
+    #[clap(long = "adapt", value_name = "[NAME=]MODULE", value_parser = parse_adapter_path)]
+    adapters: Vec<(String, PathBuf)>,
+
+    /// The WIT describing an adapter specified with `--adapt`.
+    ///
+    /// This can be used for adapter modules which don't have WIT metadata
+    /// embedded within them. The `NAME` is the name of the adapter and `WIT`
+    /// is a path to a WIT package which must contain a single world. The
+    /// imports of the world are the imports the adapter introduces into the
+    /// final component and the exports of the world are implemented by the
+    /// adapter.
+    #[clap(long = "adapt-wit", value_name = "NAME=WIT", value_parser = parse_adapter_wit)]
+    adapter_wits: Vec<(String, PathBuf)>,
 
     /// Rename an instance import in the output component.
     ///
@@ -148,6 +180,41 @@ pub struct NewOpts {
     /// This is enabled by default.
     #[clap(long, value_name = "MERGE")]
     merge_imports_based_on_semver: Option<bool>,
+
+    /// The string encoding used by all functions of the module, overriding
+    /// the encoding recorded in its WIT metadata.
+    ///
+    /// Supported values are: `utf8`, `utf16`, and `compact-utf16`, which
+    /// represents strings as either latin1 or UTF-16.
+    #[clap(long, value_name = "ENCODING")]
+    string_encoding: Option<StringEncoding>,
+
+    /// The name of the module's realloc export, instead of `cabi_realloc`.
+    #[clap(long, value_name = "NAME")]
+    realloc: Option<String>,
+
+    /// The prefix of the names of the module's post-return exports, instead
+    /// of `cabi_post_`.
+    #[clap(long, value_name = "PREFIX")]
+    post_return_prefix: Option<String>,
+
+    /// The name of the memory export used for canonical ABI values, which
+    /// allows modules exporting several memories to be encoded.
+    #[clap(long, value_name = "NAME")]
+    memory: Option<String>,
+
+    /// Watch the input module, adapters, and adapter WIT, and encode the
+    /// component again whenever they change.
+    #[clap(long)]
+    watch: bool,
+
+    /// A directory in which to cache encoded components.
+    ///
+    /// When only the code or data of the input module changed since a
+    /// component was cached, the cached component is reused with the new
+    /// module spliced into it rather than encoding the component again.
+    #[clap(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
 }
 
 impl NewOpts {
@@ -157,6 +224,16 @@ impl NewOpts {
 
     /// Executes the application.
     fn run(self) -> Result<()> {
+        if self.watch {
+            let mut paths = vec![self.io.watched_input_path()?];
+            paths.extend(self.adapters.iter().map(|(_, path)| path.clone()));
+            paths.extend(self.adapter_wits.iter().map(|(_, path)| path.clone()));
+            return wasm_tools::watch::watch(&paths, self.io.output_path(), || self.encode());
+        }
+        self.encode()
+    }
+
+    fn encode(&self) -> Result<()> {
         let wasm = self.io.parse_input_wasm()?;
         let mut encoder = ComponentEncoder::default().validate(!self.skip_validation);
 
@@ -165,14 +242,46 @@ impl NewOpts {
         }
         encoder = encoder.module(&wasm)?;
 
-        for (name, wasm) in self.adapters.iter() {
-            encoder = encoder.adapter(name, wasm)?;
+        let mut adapter_wits = self.adapter_wits.iter().cloned().collect::<HashMap<_, _>>();
+        for (name, path) in self.adapters.iter() {
+            let wasm = wat::parse_file(path)?;
+            encoder = match adapter_wits.remove(name) {
+                Some(path) => {
+                    let mut resolve = Resolve::default();
+                    let (pkg, _) = resolve.push_path(&path)?;
+                    let world = resolve.select_world(pkg, None)?;
+                    encoder.adapter_with_world(name, &wasm, &resolve, world)?
+                }
+                None => encoder.adapter(name, &wasm)?,
+            };
+        }
+        if let Some(name) = adapter_wits.keys().next() {
+            bail!("`--adapt-wit` specified for `{name}` but no such adapter was specified with `--adapt`");
         }
 
         encoder = encoder.realloc_via_memory_grow(self.realloc_via_memory_grow);
 
+        let mut options = CanonicalOptions::default();
+        if let Some(encoding) = self.string_encoding {
+            options = options.string_encoding(encoding);
+        }
+        if let Some(name) = &self.realloc {
+            options = options.realloc(name);
+        }
+        if let Some(prefix) = &self.post_return_prefix {
+            options = options.post_return_prefix(prefix);
+        }
+        if let Some(name) = &self.memory {
+            options = options.memory(name);
+        }
+        encoder = encoder.canonical_options(options);
+
+        if let Some(dir) = &self.cache_dir {
+            encoder = encoder.cache(DirectoryCache::new(dir));
+        }
+
         let bytes = encoder
-            .import_name_map(self.import_names.into_iter().collect())
+            .import_name_map(self.import_names.iter().cloned().collect())
             .encode()
             .context("failed to encode a component from module")?;
 
@@ -203,6 +312,15 @@ struct WitResolve {
     /// items are otherwise hidden by default.
     #[clap(long)]
     all_features: bool,
+
+    /// A directory to search for WIT dependencies which are not otherwise
+    /// found in a `deps` directory.
+    ///
+    /// Packages are looked up in this directory as either
+    /// `$namespace/$name@$version/*.wit` or `$namespace/$name@$version.wit`,
+    /// where the `@$version` suffix is omitted for unversioned packages.
+    #[clap(long, value_name = "DIR")]
+    package_dir: Option<PathBuf>,
 }
 
 impl WitResolve {
@@ -220,8 +338,27 @@ impl WitResolve {
     }
 
     fn load(&self) -> Result<(Resolve, PackageId)> {
+        self.load_path(&self.wit)
+    }
+
+    /// The paths which WIT is loaded from, for use with `--watch`.
+    ///
+    /// A directory of WIT includes its `deps` directory.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.wit.clone()];
+        paths.extend(self.package_dir.clone());
+        paths
+    }
+
+    fn load_path(&self, path: &Path) -> Result<(Resolve, PackageId)> {
         let mut resolve = Self::resolve_with_features(&self.features, self.all_features);
-        let (pkg_id, _) = resolve.push_path(&self.wit)?;
+        let (pkg_id, _) = match &self.package_dir {
+            Some(dir) => {
+                let mut resolver = DirectoryPackageResolver::new(dir);
+                resolve.push_path_with_resolver(path, &mut resolver)?
+            }
+            None => resolve.push_path(path)?,
+        };
         Ok((resolve, pkg_id))
     }
 }
@@ -267,8 +404,13 @@ pub struct EmbedOpts {
     /// package of WIT. Finally this can be a fully qualified name too such as
     /// `wasi:http/proxy` which can select a world from a WIT dependency as
     /// well.
+    ///
+    /// This option can be specified multiple times to embed the union of
+    /// several worlds, for a module which satisfies all of them. Interfaces
+    /// imported or exported by more than one world are deduplicated, and it's
+    /// an error for the worlds to otherwise conflict.
     #[clap(short, long)]
-    world: Option<String>,
+    world: Vec<String>,
 
     /// Don't read a core wasm module as input, instead generating a "dummy"
     /// module as a placeholder.
@@ -284,6 +426,11 @@ pub struct EmbedOpts {
     /// Print the output in the WebAssembly text format instead of binary.
     #[clap(long, short = 't')]
     wat: bool,
+
+    /// Watch the WIT, including its dependencies, and the input module, and
+    /// embed the metadata again whenever they change.
+    #[clap(long)]
+    watch: bool,
 }
 
 impl EmbedOpts {
@@ -293,13 +440,44 @@ impl EmbedOpts {
 
     /// Executes the application.
     fn run(self) -> Result<()> {
+        if self.watch {
+            let mut paths = self.resolve.watched_paths();
+            if !self.dummy {
+                paths.push(self.io.watched_input_path()?);
+            }
+            return wasm_tools::watch::watch(&paths, self.io.output_path(), || self.embed());
+        }
+        self.embed()
+    }
+
+    fn embed(&self) -> Result<()> {
         let wasm = if self.dummy {
             None
         } else {
             Some(self.io.parse_input_wasm()?)
         };
-        let (resolve, pkg_id) = self.resolve.load()?;
-        let world = resolve.select_world(pkg_id, self.world.as_deref())?;
+        let (mut resolve, pkg_id) = self.resolve.load()?;
+        let world = match &self.world[..] {
+            [] => resolve.select_world(pkg_id, None)?,
+            [world] => resolve.select_world(pkg_id, Some(world))?,
+            names => {
+                let worlds = names
+                    .iter()
+                    .map(|name| resolve.select_world(pkg_id, Some(name)))
+                    .collect::<Result<Vec<_>>>()?;
+                let package = &resolve.packages[pkg_id];
+                let name = (0..)
+                    .map(|i| match i {
+                        0 => "union".to_string(),
+                        i => format!("union{i}"),
+                    })
+                    .find(|name| !package.worlds.contains_key(name))
+                    .unwrap();
+                resolve
+                    .union_worlds(pkg_id, &name, &worlds)
+                    .context("failed to create the union of the worlds specified")?
+            }
+        };
         let mut wasm = wasm.unwrap_or_else(|| wit_component::dummy_module(&resolve, world));
 
         embed_component_metadata(
@@ -315,6 +493,79 @@ impl EmbedOpts {
     }
 }
 
+/// Generates a core wasm module with the imports and exports of a WIT world.
+///
+/// The generated module imports every function the world imports and exports
+/// every function the world exports, with the core wasm signatures of the
+/// canonical ABI. Exported functions simply trap when called. This can be used
+/// as a placeholder in compositions or to test hosts before a real
+/// implementation of a world exists.
+///
+/// With `--embed` the module also contains the metadata for the world, so it
+/// can be passed directly to `wasm-tools component new`.
+#[derive(Parser)]
+pub struct StubOpts {
+    #[clap(flatten)]
+    general: wasm_tools::GeneralOpts,
+
+    #[clap(flatten)]
+    resolve: WitResolve,
+
+    #[clap(flatten)]
+    output: wasm_tools::OutputArg,
+
+    /// The world to generate a module for.
+    ///
+    /// This is the path, within the `WIT` source provided as a positional
+    /// argument, to the `world` to generate a module for. If this option is
+    /// omitted then the "main package" pointed to by `WIT` must have a single
+    /// world. Otherwise this could be a bare string `foo` to point to the
+    /// `world foo` within the main package of WIT, or a fully qualified name
+    /// such as `wasi:http/proxy`.
+    #[clap(short, long)]
+    world: Option<String>,
+
+    /// Embed the metadata for the world within the generated module.
+    #[clap(long)]
+    embed: bool,
+
+    /// The string encoding to record in the embedded metadata.
+    ///
+    /// Supported values are: `utf8` (default), `utf16`, and `compact-utf16`.
+    #[clap(long, value_name = "ENCODING", requires = "embed")]
+    encoding: Option<StringEncoding>,
+
+    /// Print the output in the WebAssembly text format instead of binary.
+    #[clap(long, short = 't')]
+    wat: bool,
+}
+
+impl StubOpts {
+    fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        &self.general
+    }
+
+    /// Executes the application.
+    fn run(self) -> Result<()> {
+        let (resolve, pkg_id) = self.resolve.load()?;
+        let world = resolve.select_world(pkg_id, self.world.as_deref())?;
+        let mut wasm = wit_component::dummy_module(&resolve, world);
+
+        if self.embed {
+            embed_component_metadata(
+                &mut wasm,
+                &resolve,
+                world,
+                self.encoding.unwrap_or(StringEncoding::UTF8),
+            )?;
+        }
+
+        self.output.output_wasm(&self.general, &wasm, self.wat)?;
+
+        Ok(())
+    }
+}
+
 fn parse_optionally_name_library(s: &str) -> (&str, &str) {
     let mut parts = s.splitn(2, '=');
     let name_or_path = parts.next().unwrap();
@@ -352,6 +603,12 @@ fn parse_library(s: &str) -> Result<(String, Vec<u8>)> {
 /// See
 /// https://github.com/WebAssembly/component-model/blob/main/design/mvp/examples/SharedEverythingDynamicLinking.md
 /// for further details.
+///
+/// With `--static`, the inputs are instead relocatable object files following the [linking
+/// convention](https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md), such as those produced by
+/// `clang -c`. The first input is the main object file and the others are static libraries, which are only linked
+/// if they define symbols needed by the main object file. The objects are linked into a single core module, much
+/// like `wasm-ld` would, which is then turned into a component.
 #[derive(Parser)]
 pub struct LinkOpts {
     /// Input libraries to link
@@ -362,6 +619,15 @@ pub struct LinkOpts {
     #[clap(long, value_name = "[NAME=]MODULE", value_parser = parse_library)]
     dl_openable: Vec<(String, Vec<u8>)>,
 
+    /// Statically link relocatable object files, the first of which is the main object file.
+    #[clap(
+        long = "static",
+        conflicts_with = "dl_openable",
+        conflicts_with = "stub_missing_functions",
+        conflicts_with = "use_built_in_libdl"
+    )]
+    static_: bool,
+
     /// The path to an adapter module to satisfy imports not otherwise bound to
     /// WIT interfaces.
     ///
@@ -418,6 +684,10 @@ impl LinkOpts {
 
     /// Executes the application.
     fn run(self) -> Result<()> {
+        if self.static_ {
+            return self.run_static();
+        }
+
         let mut linker = Linker::default()
             .validate(!self.skip_validation)
             .stub_missing_functions(self.stub_missing_functions)
@@ -451,6 +721,39 @@ impl LinkOpts {
 
         Ok(())
     }
+
+    fn run_static(self) -> Result<()> {
+        let Some(((name, wasm), libraries)) = self.inputs.split_first() else {
+            bail!("no main object file specified");
+        };
+        let mut linker = StaticLinker::default()
+            .validate(!self.skip_validation)
+            .module(name, wasm)?;
+
+        if let Some(stack_size) = self.stack_size {
+            linker = linker.stack_size(stack_size);
+        }
+
+        if let Some(merge) = self.merge_imports_based_on_semver {
+            linker = linker.merge_imports_based_on_semver(merge);
+        }
+
+        for (name, wasm) in libraries {
+            linker = linker.library(name, wasm)?;
+        }
+
+        for (name, wasm) in &self.adapters {
+            linker = linker.adapter(name, wasm)?;
+        }
+
+        let bytes = linker
+            .encode()
+            .context("failed to encode a component from object files")?;
+
+        self.output.output_wasm(&self.general, &bytes, self.wat)?;
+
+        Ok(())
+    }
 }
 
 /// Tool for working with the WIT text format for components.
@@ -497,7 +800,13 @@ pub struct WitOpts {
     /// package to the output directory specified.
     ///
     /// The output directory will contain textual WIT files which represent all
-    /// packages known from the input.
+    /// packages known from the input, with one file per package. The top level
+    /// package is written to the directory itself and all other packages to
+    /// its `deps` subdirectory, which is the layout of a WIT package directory
+    /// that this command and other toolchains can read back. Files are named
+    /// after their package, with the namespace and version only included if
+    /// needed to distinguish packages, for example `deps/io@0.2.0.wit` or
+    /// `deps/wasi-io.wit`.
     #[clap(
         long,
         conflicts_with = "wasm",
@@ -521,6 +830,60 @@ pub struct WitOpts {
     )]
     json: bool,
 
+    /// Emit the documentation of the WIT package as JSON.
+    ///
+    /// Doc comments on the package, its interfaces, worlds, types, and
+    /// functions are emitted in a structured form. Each doc comment is split
+    /// into a summary, a body, and per-parameter and return value
+    /// documentation taken from `# Parameters` and `# Returns` Markdown
+    /// sections.
+    #[clap(
+        long,
+        conflicts_with = "wasm",
+        conflicts_with = "out_dir",
+        conflicts_with = "wat",
+        conflicts_with = "json"
+    )]
+    docs_json: bool,
+
+    /// Check the WIT package for dead declarations instead of emitting it.
+    ///
+    /// This reports `use`s of types which nothing refers to, and types which
+    /// can't be reached from the parameters or results of any function. Each
+    /// problem found is printed as a warning and the command fails if there
+    /// are any.
+    #[clap(
+        long,
+        conflicts_with = "wasm",
+        conflicts_with = "out_dir",
+        conflicts_with = "wat",
+        conflicts_with = "json",
+        conflicts_with = "docs_json"
+    )]
+    lint: bool,
+
+    /// Check every WIT package within the input directory instead of emitting
+    /// one.
+    ///
+    /// Each directory containing `*.wit` files is checked as a package, except
+    /// for dependencies within `deps` directories which are only checked as
+    /// dependencies of their packages. All syntax and resolution errors found
+    /// are printed along with their locations and the command fails if there
+    /// are any.
+    #[clap(
+        long,
+        conflicts_with = "wasm",
+        conflicts_with = "out_dir",
+        conflicts_with = "wat",
+        conflicts_with = "json",
+        conflicts_with = "docs_json",
+        conflicts_with = "lint",
+        conflicts_with = "importize",
+        conflicts_with = "importize_world",
+        conflicts_with = "merge_world_imports_based_on_semver"
+    )]
+    check_dir: bool,
+
     /// Generates WIT to import the component specified to this command.
     ///
     /// This flags requires that the input is a binary component, not a
@@ -595,6 +958,10 @@ impl WitOpts {
 
     /// Executes the application.
     fn run(self) -> Result<()> {
+        if self.check_dir {
+            return self.check_dir();
+        }
+
         let mut decoded = self.decode_input()?;
 
         if self.importize {
@@ -629,6 +996,10 @@ impl WitOpts {
         // This interprets all of the output options and performs such a task.
         if self.json {
             self.emit_json(&decoded)?;
+        } else if self.docs_json {
+            self.emit_docs_json(&decoded)?;
+        } else if self.lint {
+            self.lint(&decoded)?;
         } else if self.wasm || self.wat {
             self.emit_wasm(&decoded)?;
         } else {
@@ -774,6 +1145,7 @@ impl WitOpts {
                 }
 
                 let main = decoded.package();
+                let mut written = HashSet::new();
                 for (id, pkg) in resolve.packages.iter() {
                     let is_main = id == main;
                     let output = printer.print(resolve, id, &[])?;
@@ -795,16 +1167,27 @@ impl WitOpts {
                                 .unwrap_or_else(|| pkg.name.name.clone())
                         }
                     } else {
-                        if packages_with_same_namespace == 1 {
-                            format!("{}:{}", pkg.name.namespace, pkg.name.name)
-                        } else {
-                            pkg.name.to_string()
+                        // Use `-` rather than `:` to separate the namespace as
+                        // `:` isn't valid in file names on all platforms.
+                        let stem = format!("{}-{}", pkg.name.namespace, pkg.name.name);
+                        match &pkg.name.version {
+                            Some(ver) if packages_with_same_namespace > 1 => {
+                                format!("{stem}@{ver}")
+                            }
+                            _ => stem,
                         }
                     };
                     std::fs::create_dir_all(&out_dir)
                         .with_context(|| format!("failed to create directory: {out_dir:?}"))?;
                     let filename = format!("{stem}.wit");
                     let path = out_dir.join(&filename);
+                    if !written.insert(path.clone()) {
+                        bail!(
+                            "cannot write package `{}` to {path:?} as another package \
+                             was already written there",
+                            pkg.name
+                        );
+                    }
                     std::fs::write(&path, &output)
                         .with_context(|| format!("failed to write file: {path:?}"))?;
                     println!("Writing: {}", path.display());
@@ -833,6 +1216,52 @@ impl WitOpts {
 
         Ok(())
     }
+
+    fn emit_docs_json(&self, decoded: &DecodedWasm) -> Result<()> {
+        assert!(!self.wasm && !self.wat);
+
+        let docs = decoded.resolve().package_docs(decoded.package());
+        let output = serde_json::to_string_pretty(&docs)?;
+        self.output.output(&self.general, Output::Json(&output))?;
+
+        Ok(())
+    }
+
+    fn lint(&self, decoded: &DecodedWasm) -> Result<()> {
+        let resolve = decoded.resolve();
+        let lints = resolve.lint_package(decoded.package());
+        for lint in lints.iter() {
+            eprintln!("warning: {}", lint.display(resolve));
+        }
+        if !lints.is_empty() {
+            bail!(
+                "found {} dead declaration(s) in the WIT package",
+                lints.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn check_dir(&self) -> Result<()> {
+        let dir = match &self.input {
+            Some(dir) if dir.is_dir() => dir,
+            _ => bail!("the `--check-dir` flag requires a directory as input"),
+        };
+        let resolve = WitResolve::resolve_with_features(&self.features, self.all_features);
+        let check = resolve.check_workspace(dir)?;
+        if check.is_ok() {
+            return Ok(());
+        }
+        let count = check.errors.len();
+        for error in check.errors {
+            crate::print_error(self.general.color, error)?;
+            eprintln!();
+        }
+        bail!(
+            "found {count} error(s) in the WIT packages of {}",
+            dir.display()
+        )
+    }
 }
 
 /// Tool for verifying whether a component conforms to a world.
@@ -873,6 +1302,17 @@ impl TargetsOpts {
 
 /// Tool for verifying whether one world is a semver compatible evolution of
 /// another.
+///
+/// This subcommand operates in one of two modes. With a single WIT input the
+/// `--prev` and `--new` worlds are both looked up in that package and the
+/// command fails if `--new` is not a compatible evolution of `--prev`.
+///
+/// With two WIT inputs, `OLD` and `NEW`, the two versions of the package are
+/// compared structurally and every difference is printed along with the
+/// minimum version bump it requires. If `--prev` and `--new` are specified
+/// then only those worlds are compared, otherwise the entire packages are. If
+/// the new package has a version which is too small for the changes made then
+/// this command fails.
 #[derive(Parser)]
 pub struct SemverCheckOpts {
     #[clap(flatten)]
@@ -881,18 +1321,25 @@ pub struct SemverCheckOpts {
     #[clap(flatten)]
     resolve: WitResolve,
 
+    /// Path to the new version of the WIT package to compare against.
+    ///
+    /// When specified the first WIT input is considered the old version of
+    /// the package and this is the new version.
+    #[clap(value_name = "NEW")]
+    new_wit: Option<PathBuf>,
+
     /// The "previous" world, or older version, of what's being tested.
     ///
     /// This is considered the baseline for the semver compatibility check.
     #[clap(long)]
-    prev: String,
+    prev: Option<String>,
 
     /// The "new" world which is the "prev" world but modified.
     ///
     /// This is what's being tested to see whether it is a backwards-compatible
     /// evolution of the "prev" world specified.
     #[clap(long)]
-    new: String,
+    new: Option<String>,
 }
 
 impl SemverCheckOpts {
@@ -902,9 +1349,53 @@ impl SemverCheckOpts {
 
     fn run(self) -> Result<()> {
         let (resolve, pkg_id) = self.resolve.load()?;
-        let prev = resolve.select_world(pkg_id, Some(self.prev.as_str()))?;
-        let new = resolve.select_world(pkg_id, Some(self.new.as_str()))?;
-        wit_component::semver_check(resolve, prev, new)?;
+        let new_wit = match &self.new_wit {
+            Some(path) => path,
+            None => {
+                let (prev, new) = match (&self.prev, &self.new) {
+                    (Some(prev), Some(new)) => (prev, new),
+                    _ => bail!("both `--prev` and `--new` must be specified with one WIT input"),
+                };
+                let prev = resolve.select_world(pkg_id, Some(prev.as_str()))?;
+                let new = resolve.select_world(pkg_id, Some(new.as_str()))?;
+                wit_component::semver_check(resolve, prev, new)?;
+                return Ok(());
+            }
+        };
+
+        let (new_resolve, new_pkg_id) = self.resolve.load_path(new_wit)?;
+
+        let report = match (&self.prev, &self.new) {
+            (None, None) => {
+                wit_component::semver_diff_packages(&resolve, pkg_id, &new_resolve, new_pkg_id)
+            }
+            (prev, new) => {
+                let prev = resolve.select_world(pkg_id, prev.as_deref())?;
+                let new = new_resolve.select_world(new_pkg_id, new.as_deref())?;
+                wit_component::semver_diff(&resolve, prev, &new_resolve, new)
+            }
+        };
+
+        for change in report.changes.iter() {
+            println!("{change}");
+        }
+        let bump = report.required_bump();
+        println!("minimum required version bump: {bump}");
+
+        let prev_version = &resolve.packages[pkg_id].name.version;
+        let new_version = &new_resolve.packages[new_pkg_id].name.version;
+        if let Some(prev_version) = prev_version {
+            let min = bump.next_version(prev_version);
+            println!("minimum next version: {min}");
+            if let Some(new_version) = new_version {
+                if !report.changes.is_empty() && *new_version < min {
+                    bail!(
+                        "new package version {new_version} is too small, \
+                         the changes made require at least {min}"
+                    );
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -940,6 +1431,15 @@ pub struct UnbundleOpts {
     #[clap(long, value_name = "SIZE", default_value_t = 10 << 10)]
     threshold: usize,
 
+    /// Additionally write the WIT world of the input component to
+    /// `component.wit` within `--module-dir`.
+    ///
+    /// Combined with `--threshold 0` this can be used to fully decompose a
+    /// component into its core wasm modules, including any adapters, and the
+    /// WIT describing its imports and exports.
+    #[clap(long)]
+    wit: bool,
+
     /// Output the text format of WebAssembly instead of the binary format.
     #[clap(short = 't', long)]
     wat: bool,
@@ -956,6 +1456,17 @@ impl UnbundleOpts {
             return self.io.output_wasm(&input, self.wat);
         }
 
+        if self.wit {
+            let decoded = wit_component::decode(&input)
+                .context("failed to decode WIT from the input component")?;
+            let output = WitPrinter::default().print(decoded.resolve(), decoded.package(), &[])?;
+            let dst = self.module_dir.join("component.wit");
+            std::fs::create_dir_all(&self.module_dir)
+                .with_context(|| format!("failed to create directory {:?}", self.module_dir))?;
+            std::fs::write(&dst, output)
+                .with_context(|| format!("failed to write file {dst:?}"))?;
+        }
+
         // Generate a list of all modules in the component in the order they
         // were found in the component itself. Record for each one the bytes of
         // the module itself or `None` indicating it's not being extracted.
@@ -1046,6 +1557,56 @@ impl UnbundleOpts {
     }
 }
 
+/// Flatten a component into a core wasm module for engines without support
+/// for components.
+///
+/// This subcommand extracts the core wasm module implementing a component
+/// along with a JSON description of the canonical ABI glue a host needs to
+/// provide to run it. The JSON lists, for each import and export of the
+/// module, the function or resource intrinsic of the component's world it
+/// corresponds to and which canonical ABI options it uses. The WIT of the
+/// component, as printed by `wasm-tools component wit`, describes the types
+/// of these functions.
+///
+/// Only components created from a single core wasm module without any
+/// adapters can be flattened.
+#[derive(Parser)]
+pub struct FlattenOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Write the JSON description of the glue to this file.
+    #[clap(long, value_name = "PATH")]
+    glue: Option<PathBuf>,
+
+    /// Print the JSON description of the glue instead of the module.
+    #[clap(long, conflicts_with = "glue", conflicts_with = "wat")]
+    json: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl FlattenOpts {
+    fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    fn run(self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let flattened = wit_component::flatten::flatten(&input)?;
+        let glue = serde_json::to_string_pretty(&flattened.glue)?;
+        if self.json {
+            return self.io.output(Output::Json(&glue));
+        }
+        if let Some(path) = &self.glue {
+            std::fs::write(path, glue).with_context(|| format!("failed to write file {path:?}"))?;
+        }
+        self.io.output_wasm(&flattened.module, self.wat)
+    }
+}
+
 /// Top-level conversion of core wasm function types to a component core types
 /// section.
 ///
@@ -1234,3 +1795,368 @@ impl ReencodeComponent for ModuleExtractor<'_> {
         Ok(())
     }
 }
+
+/// Print the instance graph of a component.
+///
+/// This subcommand prints which imports, instances, and exports of a
+/// component are connected to each other. Each instantiation of a nested
+/// component or core module within the component is a node in the graph and
+/// edges connect the items passed as instantiation arguments to the instances
+/// that consume them. This can be used to debug composition failures without
+/// having to read the full text format of a component.
+///
+/// The graph is printed in the DOT format by default, which can be rendered
+/// with Graphviz, or can alternatively be printed as JSON.
+#[derive(Parser)]
+pub struct GraphOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Print the graph as JSON instead of in the DOT format.
+    #[clap(long)]
+    json: bool,
+}
+
+impl GraphOpts {
+    fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    fn run(self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        if !wasmparser::Parser::is_component(&input) {
+            bail!("input is not a component");
+        }
+        let graph = InstanceGraph::new(&input)?;
+        if self.json {
+            let output = serde_json::to_string_pretty(&graph.to_json())?;
+            self.io.output(Output::Json(&output))
+        } else {
+            let mut output = self.io.output_writer()?;
+            graph.write_dot(&mut output)?;
+            Ok(())
+        }
+    }
+}
+
+/// The instance graph of the top level of a component.
+#[derive(Default)]
+struct InstanceGraph {
+    nodes: Vec<(&'static str, String)>,
+    edges: Vec<(usize, usize, String)>,
+
+    // Index spaces of the component, where each entry is the node that an item
+    // originates from, if any.
+    funcs: Vec<Option<usize>>,
+    values: Vec<Option<usize>>,
+    types: Vec<Option<usize>>,
+    instances: Vec<Option<usize>>,
+    core_funcs: Vec<Option<usize>>,
+    core_tables: Vec<Option<usize>>,
+    core_memories: Vec<Option<usize>>,
+    core_globals: Vec<Option<usize>>,
+    core_tags: Vec<Option<usize>>,
+    core_instances: Vec<Option<usize>>,
+
+    // Descriptions of the components and core modules which can be
+    // instantiated.
+    components: Vec<String>,
+    modules: Vec<String>,
+}
+
+impl InstanceGraph {
+    fn new(wasm: &[u8]) -> Result<InstanceGraph> {
+        use wasmparser::{
+            CanonicalFunction, ComponentAlias, ComponentInstance, ComponentOuterAliasKind, Instance,
+        };
+
+        let mut graph = InstanceGraph::default();
+        let mut depth = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            let payload = payload?;
+
+            // Only the top level of the component is inspected, so skip over
+            // the contents of nested components and modules.
+            if depth > 0 {
+                match payload {
+                    Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => depth += 1,
+                    Payload::End(_) => depth -= 1,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match payload {
+                Payload::ModuleSection { .. } => {
+                    graph
+                        .modules
+                        .push(format!("module {}", graph.modules.len()));
+                    depth += 1;
+                }
+                Payload::ComponentSection { .. } => {
+                    graph
+                        .components
+                        .push(format!("component {}", graph.components.len()));
+                    depth += 1;
+                }
+                Payload::ComponentImportSection(s) => {
+                    for import in s {
+                        let import = import?;
+                        let kind = import.ty.kind();
+                        let node = graph.node("import", import.name.0.to_string());
+                        graph.push(kind, Some(node), import.name.0);
+                    }
+                }
+                Payload::ComponentExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        let origin = graph.origin(export.kind, export.index)?;
+                        let node = graph.node("export", export.name.0.to_string());
+                        graph.edge(origin, node, String::new());
+                        graph.push(export.kind, origin, export.name.0);
+                    }
+                }
+                Payload::ComponentInstanceSection(s) => {
+                    for instance in s {
+                        match instance? {
+                            ComponentInstance::Instantiate {
+                                component_index,
+                                args,
+                            } => {
+                                let component = graph
+                                    .components
+                                    .get(component_index as usize)
+                                    .context("component index out of bounds")?;
+                                let label =
+                                    format!("instance {}\n({component})", graph.instances.len());
+                                let node = graph.node("instance", label);
+                                for arg in args.iter() {
+                                    let origin = graph.origin(arg.kind, arg.index)?;
+                                    graph.edge(origin, node, arg.name.to_string());
+                                }
+                                graph.instances.push(Some(node));
+                            }
+                            ComponentInstance::FromExports(exports) => {
+                                let label = format!("instance {}", graph.instances.len());
+                                let node = graph.node("instance", label);
+                                for export in exports.iter() {
+                                    let origin = graph.origin(export.kind, export.index)?;
+                                    graph.edge(origin, node, export.name.0.to_string());
+                                }
+                                graph.instances.push(Some(node));
+                            }
+                        }
+                    }
+                }
+                Payload::InstanceSection(s) => {
+                    for instance in s {
+                        match instance? {
+                            Instance::Instantiate { module_index, args } => {
+                                let module = graph
+                                    .modules
+                                    .get(module_index as usize)
+                                    .context("module index out of bounds")?;
+                                let label = format!(
+                                    "core instance {}\n({module})",
+                                    graph.core_instances.len()
+                                );
+                                let node = graph.node("core-instance", label);
+                                for arg in args.iter() {
+                                    let origin = *graph
+                                        .core_instances
+                                        .get(arg.index as usize)
+                                        .context("core instance index out of bounds")?;
+                                    graph.edge(origin, node, arg.name.to_string());
+                                }
+                                graph.core_instances.push(Some(node));
+                            }
+                            Instance::FromExports(exports) => {
+                                let label = format!("core instance {}", graph.core_instances.len());
+                                let node = graph.node("core-instance", label);
+                                for export in exports.iter() {
+                                    let origin = *graph
+                                        .core_space(export.kind)
+                                        .get(export.index as usize)
+                                        .context("core index out of bounds")?;
+                                    graph.edge(origin, node, export.name.to_string());
+                                }
+                                graph.core_instances.push(Some(node));
+                            }
+                        }
+                    }
+                }
+                Payload::ComponentAliasSection(s) => {
+                    for alias in s {
+                        match alias? {
+                            ComponentAlias::InstanceExport {
+                                kind,
+                                instance_index,
+                                name,
+                            } => {
+                                let origin = graph.origin(
+                                    wasmparser::ComponentExternalKind::Instance,
+                                    instance_index,
+                                )?;
+                                graph.push(kind, origin, name);
+                            }
+                            ComponentAlias::CoreInstanceExport {
+                                kind,
+                                instance_index,
+                                ..
+                            } => {
+                                let origin = *graph
+                                    .core_instances
+                                    .get(instance_index as usize)
+                                    .context("core instance index out of bounds")?;
+                                graph.core_space(kind).push(origin);
+                            }
+                            ComponentAlias::Outer { kind, count, index } => {
+                                let desc = format!("outer {count} {index}");
+                                match kind {
+                                    ComponentOuterAliasKind::CoreModule => graph.modules.push(desc),
+                                    ComponentOuterAliasKind::Component => {
+                                        graph.components.push(desc)
+                                    }
+                                    ComponentOuterAliasKind::Type => graph.types.push(None),
+                                    ComponentOuterAliasKind::CoreType => {}
+                                }
+                            }
+                        }
+                    }
+                }
+                Payload::ComponentCanonicalSection(s) => {
+                    for func in s {
+                        match func? {
+                            CanonicalFunction::Lift {
+                                core_func_index, ..
+                            } => {
+                                let origin = *graph
+                                    .core_funcs
+                                    .get(core_func_index as usize)
+                                    .context("core func index out of bounds")?;
+                                graph.funcs.push(origin);
+                            }
+                            CanonicalFunction::Lower { func_index, .. } => {
+                                let origin = *graph
+                                    .funcs
+                                    .get(func_index as usize)
+                                    .context("func index out of bounds")?;
+                                graph.core_funcs.push(origin);
+                            }
+                            _ => graph.core_funcs.push(None),
+                        }
+                    }
+                }
+                Payload::ComponentTypeSection(s) => {
+                    for _ in 0..s.count() {
+                        graph.types.push(None);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(graph)
+    }
+
+    fn node(&mut self, kind: &'static str, label: String) -> usize {
+        self.nodes.push((kind, label));
+        self.nodes.len() - 1
+    }
+
+    fn edge(&mut self, from: Option<usize>, to: usize, label: String) {
+        if let Some(from) = from {
+            self.edges.push((from, to, label));
+        }
+    }
+
+    /// Pushes a new item onto the index space for `kind`, which originates
+    /// from the node `origin`.
+    fn push(&mut self, kind: wasmparser::ComponentExternalKind, origin: Option<usize>, name: &str) {
+        use wasmparser::ComponentExternalKind::*;
+        match kind {
+            Func => self.funcs.push(origin),
+            Value => self.values.push(origin),
+            Type => self.types.push(origin),
+            Instance => self.instances.push(origin),
+            Component => self.components.push(format!("`{name}`")),
+            Module => self.modules.push(format!("`{name}`")),
+        }
+    }
+
+    /// Returns the node that the item `index` of `kind` originates from.
+    fn origin(&self, kind: wasmparser::ComponentExternalKind, index: u32) -> Result<Option<usize>> {
+        use wasmparser::ComponentExternalKind::*;
+        let space = match kind {
+            Func => &self.funcs,
+            Value => &self.values,
+            Type => &self.types,
+            Instance => &self.instances,
+            // Components and modules aren't nodes within the graph.
+            Component | Module => return Ok(None),
+        };
+        match space.get(index as usize) {
+            Some(origin) => Ok(*origin),
+            None => bail!("{} index {index} out of bounds", kind.desc()),
+        }
+    }
+
+    fn core_space(&mut self, kind: wasmparser::ExternalKind) -> &mut Vec<Option<usize>> {
+        match kind {
+            wasmparser::ExternalKind::Func => &mut self.core_funcs,
+            wasmparser::ExternalKind::Table => &mut self.core_tables,
+            wasmparser::ExternalKind::Memory => &mut self.core_memories,
+            wasmparser::ExternalKind::Global => &mut self.core_globals,
+            wasmparser::ExternalKind::Tag => &mut self.core_tags,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(id, (kind, label))| {
+                serde_json::json!({
+                    "id": id,
+                    "kind": kind,
+                    "label": label,
+                })
+            })
+            .collect::<Vec<_>>();
+        let edges = self
+            .edges
+            .iter()
+            .map(|(from, to, label)| {
+                serde_json::json!({
+                    "from": from,
+                    "to": to,
+                    "label": label,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "nodes": nodes,
+            "edges": edges,
+        })
+    }
+
+    fn write_dot(&self, out: &mut dyn std::io::Write) -> Result<()> {
+        writeln!(out, "digraph component {{")?;
+        for (id, (kind, label)) in self.nodes.iter().enumerate() {
+            let shape = match *kind {
+                "import" | "export" => "box",
+                _ => "ellipse",
+            };
+            writeln!(out, "  n{id} [label={label:?}, shape={shape}];")?;
+        }
+        for (from, to, label) in self.edges.iter() {
+            if label.is_empty() {
+                writeln!(out, "  n{from} -> n{to};")?;
+            } else {
+                writeln!(out, "  n{from} -> n{to} [label={label:?}];")?;
+            }
+        }
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+}
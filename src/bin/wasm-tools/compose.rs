@@ -45,6 +45,18 @@ pub struct Opts {
     /// Output the text format of WebAssembly instead of the binary format.
     #[clap(short = 't', long)]
     wat: bool,
+
+    /// Output the plan of the composition as JSON instead of composing.
+    ///
+    /// The plan describes which instance satisfies each import and which
+    /// imports remain unsatisfied and are imported by the composed component.
+    #[clap(long, conflicts_with = "wat")]
+    plan: bool,
+
+    /// Watch the root component, the configuration file, definitions, and
+    /// search paths, and compose again whenever they change.
+    #[clap(long)]
+    watch: bool,
 }
 
 impl Opts {
@@ -56,10 +68,29 @@ impl Opts {
         eprintln!("WARNING: `wasm-tools compose` has been deprecated.");
         eprintln!("");
         eprintln!("Please use `wac` instead. You can find more information about `wac` at https://github.com/bytecodealliance/wac.");
+        if self.watch {
+            let mut paths = vec![self.component.clone()];
+            paths.extend(self.config.clone());
+            paths.extend(self.defs.iter().cloned());
+            paths.extend(self.paths.iter().cloned());
+            return wasm_tools::watch::watch(&paths, self.output.output_path(), || self.compose());
+        }
+        self.compose()
+    }
+
+    fn compose(&self) -> Result<()> {
         let config = self.create_config()?;
         log::debug!("configuration:\n{:#?}", config);
 
-        let bytes = ComponentComposer::new(&self.component, &config).compose()?;
+        let composer = ComponentComposer::new(&self.component, &config);
+        if self.plan {
+            let plan = serde_json::to_string_pretty(&composer.plan()?)?;
+            return self
+                .output
+                .output(&self.general, wasm_tools::Output::Json(&plan));
+        }
+
+        let bytes = composer.compose()?;
 
         self.output.output_wasm(&self.general, &bytes, self.wat)?;
 
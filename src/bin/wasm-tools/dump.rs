@@ -175,10 +175,7 @@ impl<'a> Dump<'a> {
                 }
                 Payload::ElementSection(s) => self.section(s, "element", |me, _end, i| {
                     write!(me.state, "element")?;
-                    let item_count = match &i.items {
-                        ElementItems::Functions(reader) => reader.count(),
-                        ElementItems::Expressions(_, reader) => reader.count(),
-                    };
+                    let item_count = i.items.count();
                     match i.kind {
                         ElementKind::Passive => {
                             write!(me.state, " passive, {} items", item_count)?;
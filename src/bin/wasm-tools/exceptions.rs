@@ -0,0 +1,44 @@
+use anyhow::Result;
+use wasm_tools::exceptions::{downgrade_exceptions, upgrade_exceptions};
+
+/// Convert between the legacy and standardized exception-handling encodings.
+///
+/// By default the legacy `try`, `catch`, `catch_all`, `delegate`, and
+/// `rethrow` instructions are rewritten to the standardized `try_table` and
+/// `throw_ref` instructions, so that modules produced by older toolchains can
+/// run on engines without support for the legacy encoding.
+///
+/// With `--legacy` the conversion is reversed for engines which only support
+/// the legacy encoding. This is best-effort as `exnref` values have no legacy
+/// equivalent, so an error is reported for modules using `catch_ref`,
+/// `catch_all_ref`, or `throw_ref`.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Convert `try_table` instructions to the legacy encoding instead.
+    #[clap(long)]
+    legacy: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let output = if self.legacy {
+            downgrade_exceptions(&input)?
+        } else {
+            upgrade_exceptions(&input)?
+        };
+        self.io.output_wasm(&output, self.wat)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use anyhow::{bail, Context, Result};
+use wasm_tools::instrument::coverage::Coverage;
+use wasm_tools::instrument::memory_trace::MemoryTrace;
+use wasm_tools::instrument::stack_depth::StackDepth;
+use wasm_tools::transform::Transform;
+
+/// Instrument a WebAssembly module to collect runtime information.
+#[derive(clap::Parser)]
+pub enum Opts {
+    Coverage(CoverageOpts),
+    StackDepth(StackDepthOpts),
+    MemoryTrace(MemoryTraceOpts),
+    CanonicalizeNans(CanonicalizeNansOpts),
+}
+
+impl Opts {
+    pub fn run(&self) -> Result<()> {
+        match self {
+            Opts::Coverage(opts) => opts.run(),
+            Opts::StackDepth(opts) => opts.run(),
+            Opts::MemoryTrace(opts) => opts.run(),
+            Opts::CanonicalizeNans(opts) => opts.run(),
+        }
+    }
+
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        match self {
+            Opts::Coverage(opts) => opts.general_opts(),
+            Opts::StackDepth(opts) => opts.general_opts(),
+            Opts::MemoryTrace(opts) => opts.general_opts(),
+            Opts::CanonicalizeNans(opts) => opts.general_opts(),
+        }
+    }
+}
+
+/// Instrument every basic block of a module with a coverage counter.
+///
+/// Each basic block of each function is assigned a counter which is
+/// incremented every time the block is entered. By default counters are
+/// 32-bit integers stored in a new memory, exported as `__coverage_counters`,
+/// where counter `N` lives at byte offset `N * 4`. With `--import` an imported
+/// function `coverage.hit` of type `(func (param i32))` is instead called
+/// with the index of the counter.
+///
+/// A `coverage-map` custom section is added to the module which contains a
+/// JSON array describing each counter, in order, as the index of its function
+/// and the offset of its first instruction relative to the start of the code
+/// section. Both refer to the original, uninstrumented module so they can be
+/// resolved with its `name` section or DWARF information.
+///
+/// Note that storing counters in a new memory requires the multi-memory
+/// proposal if the module already defines or imports a memory.
+#[derive(clap::Parser)]
+pub struct CoverageOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Call an imported `coverage.hit` function instead of incrementing
+    /// counters in memory.
+    #[clap(long)]
+    import: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl CoverageOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let output = Coverage::new().import(self.import).apply(&input)?;
+        self.io.output_wasm(&output.wasm, self.wat)?;
+        Ok(())
+    }
+}
+
+/// Limit the call depth of a module with a global counter.
+///
+/// A new mutable `i32` global, exported as `__call_depth`, is incremented on
+/// entry to every function and decremented when it returns, including before
+/// tail calls. If the depth is already at the limit when a function is
+/// entered then the function traps instead. With `--import` an imported
+/// function `stack-depth.exceeded` of type `(func)` is called first, which
+/// can be used by the host to report the error or to throw an exception.
+///
+/// This is intended to protect hosts, such as interpreters, which can't rely on
+/// native stack guards to catch unbounded recursion. Note that the counter is
+/// not decremented when a trap or exception unwinds through a function, so
+/// hosts should reset the exported global after either happens.
+#[derive(clap::Parser)]
+pub struct StackDepthOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// The maximum number of nested calls which are allowed.
+    #[clap(long, default_value_t = 1024)]
+    limit: u32,
+
+    /// Call an imported `stack-depth.exceeded` function before trapping when
+    /// the limit is exceeded.
+    #[clap(long)]
+    import: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl StackDepthOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let output = StackDepth::new()
+            .limit(self.limit)
+            .import(self.import)
+            .apply(&input)?;
+        self.io.output_wasm(&output.wasm, self.wat)?;
+        Ok(())
+    }
+}
+
+/// Trace loads from and stores to memory with an imported function.
+///
+/// Every load and store is preceded by a call to an imported function
+/// `memory-trace.access` of type `(func (param i64 i32 i32))` whose arguments
+/// are the effective address of the access, its size in bytes, and the opcode
+/// of the instruction. Opcodes of prefixed instructions, such as
+/// `v128.load`, include the prefix byte shifted left by 8 bits.
+///
+/// Core loads and stores of all memories are traced as well as `v128.load`
+/// and `v128.store`. Other instructions which access memory, such as atomics
+/// and bulk memory operations, are not traced.
+#[derive(clap::Parser)]
+pub struct MemoryTraceOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Only trace accesses whose effective address is within the range
+    /// `START..END`, where `END` is exclusive.
+    #[clap(long, value_name = "START..END", value_parser = parse_range)]
+    range: Option<(u64, u64)>,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+fn parse_range(s: &str) -> Result<(u64, u64)> {
+    let (start, end) = s
+        .split_once("..")
+        .context("expected a range of the form `START..END`")?;
+    let parse = |s: &str| match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    let start = parse(start).with_context(|| format!("invalid range start `{start}`"))?;
+    let end = parse(end).with_context(|| format!("invalid range end `{end}`"))?;
+    if start > end {
+        bail!("range start {start} is greater than its end {end}");
+    }
+    Ok((start, end))
+}
+
+impl MemoryTraceOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let output = MemoryTrace::new()
+            .range(self.range.map(|(start, end)| start..end))
+            .apply(&input)?;
+        self.io.output_wasm(&output.wasm, self.wat)?;
+        Ok(())
+    }
+}
+
+/// Canonicalize NaNs produced by floating-point instructions.
+///
+/// Every instruction which may produce a NaN with nondeterministic bits, such
+/// as `f32.add` or `f64x2.sqrt`, is followed by a sequence which replaces any
+/// NaN it produces with the canonical NaN. This makes execution of the module
+/// deterministic across engines at the cost of some performance.
+#[derive(clap::Parser)]
+pub struct CanonicalizeNansOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl CanonicalizeNansOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let output = wasm_tools::nan_canonicalization::canonicalize_nans(&input)?;
+        self.io.output_wasm(&output, self.wat)?;
+        Ok(())
+    }
+}
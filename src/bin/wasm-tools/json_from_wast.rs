@@ -1,7 +1,9 @@
 use anyhow::{bail, Context, Result};
 use clap::Parser;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use wast::core::{AbstractHeapType, HeapType, NanPattern, V128Const, V128Pattern, WastRetCore};
+use wast::harness::{sets_up_state, Filter};
 use wast::lexer::Lexer;
 use wast::parser::{self, ParseBuffer};
 use wast::token::{Span, F32, F64};
@@ -56,6 +58,53 @@ pub struct Opts {
     /// but can be disabled if desired too.
     #[clap(long)]
     allow_confusing_unicode: Option<bool>,
+
+    /// Also write the `spectest` module which spec tests import from as
+    /// `spectest.wat` and `spectest.wasm` next to the other files.
+    ///
+    /// This module provides the same exports as the `spectest` host module of
+    /// the spec's reference interpreter, with `print` functions which do
+    /// nothing, for engines to register before running the tests.
+    #[clap(long)]
+    spectest: bool,
+
+    /// Only convert directives which start within these 1-based lines, given
+    /// as `N` or `N-M`.
+    ///
+    /// Modules, module definitions and instances, and registrations are always
+    /// converted so that the selected directives refer to the same modules as
+    /// in the full script. The same applies to `--directives` and `--name`.
+    #[clap(long, value_name = "LINES", value_parser = parse_range)]
+    lines: Option<(usize, usize)>,
+
+    /// Only convert the directives at these 0-based indices within the
+    /// script, given as `N` or `N-M`.
+    #[clap(long, value_name = "INDICES", value_parser = parse_range)]
+    directives: Option<(usize, usize)>,
+
+    /// Only convert directives with a name matching this pattern, such as the
+    /// name of an invoked export.
+    ///
+    /// Within the pattern `*` matches any sequence of characters and `?` any
+    /// single character. This option may be given more than once to select
+    /// directives matching any of the patterns.
+    #[clap(long = "name", value_name = "PATTERN")]
+    names: Vec<String>,
+
+    /// Print how long each directive took to convert to stderr.
+    #[clap(long)]
+    timings: bool,
+}
+
+/// Parses an inclusive range written as `N` or `N-M`.
+fn parse_range(s: &str) -> Result<(usize, usize)> {
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let start = start.trim().parse()?;
+    let end = end.trim().parse()?;
+    if start > end {
+        bail!("the start of range `{s}` is after its end");
+    }
+    Ok((start, end))
 }
 
 impl Opts {
@@ -96,14 +145,39 @@ impl Opts {
         }
         builder.line_end_offsets.push(contents.len());
 
-        for directive in directives {
+        if self.spectest {
+            self.write_spectest()?;
+        }
+
+        let mut filter = Filter::new();
+        if let Some((start, end)) = self.lines {
+            filter.lines(start..=end);
+        }
+        if let Some((start, end)) = self.directives {
+            filter.directives(start..end + 1);
+        }
+        for name in self.names.iter() {
+            filter.name(name);
+        }
+
+        for (index, directive) in directives.into_iter().enumerate() {
             let span = directive.span();
+            let line = builder.lineno(span);
+            // Directives setting up state are always converted since the
+            // selected commands may depend on them.
+            if !filter.selects(index, line as usize, &directive) && !sets_up_state(&directive) {
+                continue;
+            }
+            let start = Instant::now();
             let command = builder.directive(directive).with_context(|| {
                 format!(
                     "failure processing directive on line {}",
                     builder.lineno(span)
                 )
             })?;
+            if self.timings {
+                eprintln!("{}:{line}: {:?}", self.wast, start.elapsed());
+            }
             builder.ret.commands.push(command);
         }
 
@@ -116,6 +190,22 @@ impl Opts {
             .output(&self.general, wasm_tools::Output::Json(&json))?;
         Ok(())
     }
+
+    fn write_spectest(&self) -> Result<()> {
+        let files = [
+            ("spectest.wat", wast::harness::SPECTEST.as_bytes().to_vec()),
+            ("spectest.wasm", wast::harness::spectest()),
+        ];
+        for (filename, contents) in files {
+            let dst = match &self.wasm_dir {
+                Some(dir) => dir.join(filename),
+                None => filename.into(),
+            };
+            std::fs::write(&dst, contents)
+                .with_context(|| format!("failed to write file {dst:?}"))?;
+        }
+        Ok(())
+    }
 }
 
 struct JsonBuilder<'a> {
@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use wasm_tools::Output;
+use wat::Detect;
+use wit_component::DecodedWasm;
+use wit_parser::{PackageId, Resolve, WorldId};
+
+/// Generate JSON Schema documents for the types and functions of WIT
+/// interfaces and worlds.
+///
+/// Each document describes the JSON representation of the named types of an
+/// interface or world in its `$defs`, along with an object of the parameters
+/// of each function as `NAME.params` and its result as `NAME.result`. Records
+/// are objects, variants and results are either the name of a case or an
+/// object with the case's name as its only property, options are `null` or
+/// their value, and flags are arrays of the names of the flags which are set.
+/// Types which contain resources, futures, or streams have no JSON
+/// representation and are left out, as are the functions which use them.
+///
+/// With `--interface` or `--world` a single document is printed. Otherwise
+/// `--out-dir` is required and a `NAME.json` document is written there for
+/// each interface and world of the package, or for the world of a component.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    general: wasm_tools::GeneralOpts,
+
+    #[clap(flatten)]
+    output: wasm_tools::OutputArg,
+
+    /// The WIT to read, which can be a directory of `*.wit` files, a `*.wit`
+    /// file, a WIT package encoded as WebAssembly, or a component.
+    input: PathBuf,
+
+    /// Print the document for the interface of the package with this name.
+    #[clap(long, value_name = "NAME", conflicts_with = "world")]
+    interface: Option<String>,
+
+    /// Print the document for the world of the package with this name.
+    #[clap(long, value_name = "NAME")]
+    world: Option<String>,
+
+    /// Write a document for each interface and world to this directory.
+    #[clap(short = 'd', long, value_name = "DIR", conflicts_with_all = ["interface", "world"])]
+    out_dir: Option<PathBuf>,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        &self.general
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let (resolve, pkg, component_world) = self.load()?;
+        let package = &resolve.packages[pkg];
+
+        if let Some(dir) = &self.out_dir {
+            let mut schemas = Vec::new();
+            match component_world {
+                Some(world) => {
+                    let name = &resolve.worlds[world].name;
+                    schemas.push((name, resolve.world_json_schema(world)));
+                }
+                None => {
+                    for (name, id) in package.interfaces.iter() {
+                        schemas.push((name, resolve.interface_json_schema(*id)));
+                    }
+                    for (name, id) in package.worlds.iter() {
+                        schemas.push((name, resolve.world_json_schema(*id)));
+                    }
+                }
+            }
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create `{}`", dir.display()))?;
+            for (name, schema) in schemas {
+                let path = dir.join(format!("{name}.json"));
+                let json = serde_json::to_string_pretty(&schema)?;
+                std::fs::write(&path, json)
+                    .with_context(|| format!("failed to write `{}`", path.display()))?;
+            }
+            return Ok(());
+        }
+
+        let schema = match (&self.interface, &self.world, component_world) {
+            (Some(name), _, _) => match package.interfaces.get(name) {
+                Some(id) => resolve.interface_json_schema(*id),
+                None => bail!("no interface named `{name}` in package `{}`", package.name),
+            },
+            (None, Some(name), _) => match package.worlds.get(name) {
+                Some(id) => resolve.world_json_schema(*id),
+                None => bail!("no world named `{name}` in package `{}`", package.name),
+            },
+            (None, None, Some(world)) => resolve.world_json_schema(world),
+            (None, None, None) => {
+                bail!("one of `--interface`, `--world`, or `--out-dir` must be given")
+            }
+        };
+        let json = serde_json::to_string_pretty(&schema)?;
+        self.output.output(&self.general, Output::Json(&json))?;
+        Ok(())
+    }
+
+    /// Loads the input, returning the package to describe along with the
+    /// world of the input if it's a component.
+    fn load(&self) -> Result<(Resolve, PackageId, Option<WorldId>)> {
+        if !self.input.is_dir() {
+            let contents = std::fs::read(&self.input)
+                .with_context(|| format!("failed to read `{}`", self.input.display()))?;
+            if let Detect::WasmBinary | Detect::WasmText = Detect::from_bytes(&contents) {
+                let wasm = wat::parse_bytes(&contents).map_err(|mut e| {
+                    e.set_path(&self.input);
+                    e
+                })?;
+                return Ok(match wit_component::decode(&wasm)? {
+                    DecodedWasm::WitPackage(resolve, pkg) => (resolve, pkg, None),
+                    DecodedWasm::Component(resolve, world) => {
+                        let pkg = resolve.worlds[world].package.unwrap();
+                        (resolve, pkg, Some(world))
+                    }
+                });
+            }
+        }
+        let mut resolve = Resolve::default();
+        let (pkg, _) = resolve.push_path(&self.input)?;
+        Ok((resolve, pkg, None))
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use wasm_tools::lowering::Lowering;
+
+/// Lower the use of newer proposals in a module for older engines.
+///
+/// This rewrites a module which uses multi-value function results, tail
+/// calls, 64-bit memories, or threads into an equivalent module which doesn't,
+/// so that a single artifact can be run on engines with differing support for
+/// these proposals. By
+/// default all lowerings are applied, and if any flags are passed only the
+/// selected ones are.
+///
+/// Extra results of functions with multiple results are passed through new
+/// mutable globals, which changes the signature of any such exported
+/// functions. Tail calls are replaced with a call followed by `return`, so
+/// deep tail recursion may exhaust the engine's stack. 64-bit memories are
+/// downgraded on a best-effort basis if they fit in 4 GiB, and an error
+/// describing the problem is reported when this isn't possible. Threads are
+/// lowered assuming a single thread: shared memories become unshared, atomic
+/// instructions become their non-atomic equivalents, and waits never block.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Lower functions with multiple results.
+    #[clap(long)]
+    multi_value: bool,
+
+    /// Lower `return_call`, `return_call_indirect`, and `return_call_ref`.
+    #[clap(long)]
+    return_calls: bool,
+
+    /// Downgrade 64-bit memories to 32-bit memories.
+    #[clap(long)]
+    memory64: bool,
+
+    /// Lower shared memories and atomic instructions to single-threaded
+    /// equivalents.
+    #[clap(long)]
+    threads: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let all = !self.multi_value && !self.return_calls && !self.memory64 && !self.threads;
+        let output = Lowering::new()
+            .multi_value(all || self.multi_value)
+            .return_calls(all || self.return_calls)
+            .memory64(all || self.memory64)
+            .threads(all || self.threads)
+            .lower(&input)?;
+        self.io.output_wasm(&output, self.wat)?;
+        Ok(())
+    }
+}
@@ -74,6 +74,20 @@ subcommands! {
     (addr2line, "addr2line")
     (completion, "completion")
     (json_from_wast, "json-from-wast")
+    #[command(subcommand)]
+    (instrument, "instrument")
+    (lower, "lower")
+    (stats, "stats")
+    (canonicalize, "canonicalize")
+    (optimize, "optimize")
+    (exceptions, "exceptions")
+    (analyze, "analyze")
+    #[command(subcommand)]
+    (branch_hints, "branch-hints")
+    (relocate, "relocate")
+    (split, "split")
+    (json_schema, "json-schema")
+    (wast, "wast")
 }
 
 // when all features are disabled then `WasmTools` is an empty enum so suppress
@@ -1,11 +1,15 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 
-/// Manipulate metadata (module name, producers) to a WebAssembly file.
+/// Manipulate metadata (module name, producers, signatures) of a WebAssembly
+/// file.
 #[derive(clap::Parser)]
 pub enum Opts {
     Show(ShowOpts),
     Add(AddOpts),
+    Sign(SignOpts),
+    Verify(VerifyOpts),
 }
 
 impl Opts {
@@ -13,6 +17,8 @@ impl Opts {
         match self {
             Opts::Show(opts) => opts.run(),
             Opts::Add(opts) => opts.run(),
+            Opts::Sign(opts) => opts.run(),
+            Opts::Verify(opts) => opts.run(),
         }
     }
 
@@ -20,6 +26,8 @@ impl Opts {
         match self {
             Opts::Show(opts) => opts.general_opts(),
             Opts::Add(opts) => opts.general_opts(),
+            Opts::Sign(opts) => opts.general_opts(),
+            Opts::Verify(opts) => opts.general_opts(),
         }
     }
 }
@@ -31,6 +39,9 @@ pub struct ShowOpts {
     io: wasm_tools::InputOutput,
 
     /// Output in JSON encoding
+    ///
+    /// The JSON output describes every nested module and component of the
+    /// input, including its name, producers, byte range, and SHA-256 digest.
     #[clap(long)]
     json: bool,
 }
@@ -82,3 +93,95 @@ impl AddOpts {
         Ok(())
     }
 }
+
+/// Sign a WebAssembly file with an Ed25519 key.
+///
+/// By default the signature is embedded in a `signature` custom section at the
+/// start of the output. Signing an already-signed file adds another signature.
+/// With `--detached` only the signature itself is written to the output and
+/// the input is left unmodified.
+#[derive(clap::Parser)]
+pub struct SignOpts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Path to a PEM-encoded Ed25519 secret key to sign with.
+    #[clap(short = 'k', long, value_name = "PATH")]
+    key: PathBuf,
+
+    /// Output a detached signature instead of the signed WebAssembly file.
+    #[clap(long)]
+    detached: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format
+    #[clap(short = 't', long, conflicts_with = "detached")]
+    wat: bool,
+}
+
+impl SignOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let key = read_key(&self.key, wasm_metadata::SigningKey::from_pem)?;
+
+        if self.detached {
+            let signature = wasm_metadata::sign_detached(&input, &key)?;
+            self.io.output_writer()?.write_all(&signature)?;
+            return Ok(());
+        }
+
+        let output = wasm_metadata::sign(&input, &key)?;
+        self.io.output_wasm(&output, self.wat)?;
+        Ok(())
+    }
+}
+
+/// Verify the signature of a WebAssembly file.
+///
+/// This command fails if the file wasn't signed with the given public key or
+/// if it has been modified since it was signed.
+#[derive(clap::Parser)]
+pub struct VerifyOpts {
+    #[clap(flatten)]
+    general: wasm_tools::GeneralOpts,
+
+    #[clap(flatten)]
+    input: wasm_tools::InputArg,
+
+    /// Path to a PEM-encoded Ed25519 public key to verify the signature with.
+    #[clap(short = 'K', long, value_name = "PATH")]
+    public_key: PathBuf,
+
+    /// Path to a detached signature to verify instead of the signature
+    /// embedded in the input.
+    #[clap(short, long, value_name = "PATH")]
+    signature: Option<PathBuf>,
+}
+
+impl VerifyOpts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        &self.general
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.input.parse_wasm()?;
+        let key = read_key(&self.public_key, wasm_metadata::VerifyingKey::from_pem)?;
+        let detached = match &self.signature {
+            Some(path) => Some(
+                std::fs::read(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+            ),
+            None => None,
+        };
+        wasm_metadata::verify(&input, &key, detached.as_deref())
+    }
+}
+
+fn read_key<T>(path: &Path, parse: impl FnOnce(&str) -> Result<T>) -> Result<T> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(&pem).with_context(|| format!("failed to parse key {}", path.display()))
+}
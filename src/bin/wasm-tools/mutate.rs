@@ -1,6 +1,8 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use wasm_mutate::ErrorKind;
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+use wasm_mutate::{ErrorKind, MutationTrace};
 
 /// A WebAssembly test case mutator.
 ///
@@ -17,6 +19,12 @@ use wasm_mutate::ErrorKind;
 ///
 /// $ wasm-mutate ./input.wasm --seed 1234 -o output.wasm
 ///
+/// Record the mutation in a trace and replay it later on the same input:
+///
+/// $ wasm-mutate ./input.wasm --seed 1234 --trace trace.json -o output.wasm
+///
+/// $ wasm-mutate ./input.wasm --replay trace.json -o output.wasm
+///
 /// ## Exit Codes
 ///
 /// * 0: Success
@@ -44,6 +52,22 @@ pub struct Opts {
     #[clap(short = 't', long)]
     wat: bool,
 
+    /// Write a JSON trace of the mutator which was applied and of its
+    /// parameters to this file.
+    ///
+    /// The trace can be passed to `--replay` to reproduce the same mutation
+    /// on the same input.
+    #[clap(long, value_name = "PATH")]
+    trace: Option<PathBuf>,
+
+    /// Replay the mutation recorded in a trace written by `--trace`.
+    ///
+    /// This overrides `--seed`, `--fuel`, `--preserve-semantics`, and
+    /// `--reduce` with the values in the trace, and fails if the mutation
+    /// diverges from the trace, such as when the input differs.
+    #[clap(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+
     #[clap(flatten)]
     wasm_mutate: wasm_mutate::WasmMutate<'static>,
 }
@@ -63,10 +87,23 @@ impl Opts {
         // anyway.
         let input_wasm = Box::leak(input_wasm.into_boxed_slice());
 
-        let mut output_wasms =
-            unwrap_wasm_mutate_result(self.wasm_mutate.run(input_wasm)).take(100);
-        let wasm = loop {
-            let res = match output_wasms.next() {
+        let replay = match &self.replay {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read trace {path:?}"))?;
+                let trace: Trace = serde_json::from_str(&contents)
+                    .with_context(|| format!("failed to parse trace {path:?}"))?;
+                self.wasm_mutate.replay(&trace.mutation());
+                Some(trace)
+            }
+            None => None,
+        };
+
+        let mut output_wasms = unwrap_wasm_mutate_result(self.wasm_mutate.run(input_wasm))
+            .take(100)
+            .enumerate();
+        let (output, wasm) = loop {
+            let (i, res) = match output_wasms.next() {
                 Some(res) => res,
                 None => {
                     eprintln!("no mutations found");
@@ -78,9 +115,25 @@ impl Opts {
                     // Try the next mutation.
                     continue;
                 }
-                _ => break unwrap_wasm_mutate_result(res),
+                _ => break (i, unwrap_wasm_mutate_result(res)),
             }
         };
+        drop(output_wasms);
+
+        if let Some(replay) = &replay {
+            if replay.output != output {
+                bail!(
+                    "replay diverged from the trace: expected mutation {} but got mutation {output}",
+                    replay.output
+                );
+            }
+        }
+        if let Some(path) = &self.trace {
+            let trace = Trace::new(self.wasm_mutate.trace().unwrap(), output);
+            let contents = serde_json::to_string_pretty(&trace)?;
+            std::fs::write(path, contents + "\n")
+                .with_context(|| format!("failed to write trace {path:?}"))?;
+        }
 
         self.io.output_wasm(&wasm, self.wat)?;
 
@@ -88,6 +141,42 @@ impl Opts {
     }
 }
 
+/// The JSON representation of a trace for `--trace` and `--replay`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Trace {
+    seed: u64,
+    preserve_semantics: bool,
+    reduce: bool,
+    fuel: u64,
+    mutator: Option<String>,
+    /// The index of the mutation used among those generated by the mutator.
+    output: usize,
+}
+
+impl Trace {
+    fn new(mutation: &MutationTrace, output: usize) -> Trace {
+        Trace {
+            seed: mutation.seed,
+            preserve_semantics: mutation.preserve_semantics,
+            reduce: mutation.reduce,
+            fuel: mutation.fuel,
+            mutator: mutation.mutator.clone(),
+            output,
+        }
+    }
+
+    fn mutation(&self) -> MutationTrace {
+        MutationTrace {
+            seed: self.seed,
+            preserve_semantics: self.preserve_semantics,
+            reduce: self.reduce,
+            fuel: self.fuel,
+            mutator: self.mutator.clone(),
+        }
+    }
+}
+
 fn unwrap_wasm_mutate_result<T>(result: wasm_mutate::Result<T>) -> T {
     match result {
         Ok(x) => x,
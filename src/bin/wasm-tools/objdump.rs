@@ -12,6 +12,11 @@ use wasmparser::{Encoding, Parser, Payload::*};
 pub struct Opts {
     #[clap(flatten)]
     io: wasm_tools::InputOutput,
+
+    /// Print which functions may be stored in each table of a module instead
+    /// of its sections.
+    #[clap(long)]
+    tables: bool,
 }
 
 impl Opts {
@@ -22,6 +27,10 @@ impl Opts {
     pub fn run(&self) -> Result<()> {
         let input = self.io.parse_input_wasm()?;
 
+        if self.tables {
+            return self.print_tables(&input);
+        }
+
         let mut printer = Printer {
             indices: Vec::new(),
             output: self.io.output_writer()?,
@@ -90,6 +99,28 @@ impl Opts {
 
         Ok(())
     }
+
+    fn print_tables(&self, wasm: &[u8]) -> Result<()> {
+        let mut output = self.io.output_writer()?;
+        for (i, table) in wasm_tools::tables::analyze_tables(wasm)?.iter().enumerate() {
+            let n = table.functions.len();
+            let plural = if n == 1 { "" } else { "s" };
+            write!(output, "table {i}: {n} function{plural}")?;
+            if table.unknown {
+                write!(output, " and unknown references")?;
+            }
+            if !table.functions.is_empty() {
+                let functions = table
+                    .functions
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>();
+                write!(output, ": {}", functions.join(" "))?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Default)]
@@ -0,0 +1,68 @@
+use anyhow::{bail, Result};
+use wasm_tools::data_segments::CompactDataSegments;
+use wasm_tools::optimize::OptimizeSize;
+use wasm_tools::transform::{Pipeline, Transform};
+
+/// Optimize a module.
+///
+/// Only conservative size optimizations are currently implemented. With
+/// `--size-only` peephole rewrites which are always safe to apply are made,
+/// such as combining `local.set` and `local.get` into `local.tee`, removing
+/// additions of zero and code which is unreachable, and encoding all integers
+/// with their minimal width. With `--data-segments` data segments are split
+/// around long runs of zeros and segments close to each other are merged. This
+/// is not a replacement for a full optimizer such as `wasm-opt`.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Only apply optimizations which reduce the size of the module.
+    #[clap(long)]
+    size_only: bool,
+
+    /// Split data segments around long runs of zeros and merge segments which
+    /// are close to each other, printing a report of the sizes before and
+    /// after to stderr.
+    #[clap(long)]
+    data_segments: bool,
+
+    /// The length of the longest run of zeros which is kept within a data
+    /// segment by `--data-segments`.
+    #[clap(long, value_name = "BYTES", requires = "data_segments")]
+    zero_run_threshold: Option<u32>,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        if !self.size_only && !self.data_segments {
+            bail!("no optimizations selected, pass `--size-only` or `--data-segments`");
+        }
+        let input = self.io.parse_input_wasm()?;
+        let mut pipeline = Pipeline::new();
+        if self.size_only {
+            pipeline.add(OptimizeSize);
+        }
+        if self.data_segments {
+            let mut compact = CompactDataSegments::new();
+            if let Some(threshold) = self.zero_run_threshold {
+                compact.zero_run_threshold(threshold);
+            }
+            pipeline.add(compact);
+        }
+        let output = pipeline.apply(&input)?;
+        for diagnostic in output.diagnostics.iter() {
+            eprintln!("{diagnostic}");
+        }
+        self.io.output_wasm(&output.wasm, self.wat)?;
+        Ok(())
+    }
+}
@@ -13,6 +13,10 @@ pub struct Opts {
     /// Output the text format of WebAssembly instead of the binary format.
     #[clap(short = 't', long)]
     wat: bool,
+
+    /// Watch the input file and parse it again whenever it changes.
+    #[clap(long)]
+    watch: bool,
 }
 
 impl Opts {
@@ -21,6 +25,14 @@ impl Opts {
     }
 
     pub fn run(&self) -> Result<()> {
+        if self.watch {
+            let input = self.io.watched_input_path()?;
+            return wasm_tools::watch::watch(&[input], self.io.output_path(), || self.parse());
+        }
+        self.parse()
+    }
+
+    fn parse(&self) -> Result<()> {
         let binary = self.io.parse_input_wasm()?;
         self.io.output_wasm(&binary, self.wat)?;
         Ok(())
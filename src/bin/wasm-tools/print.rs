@@ -12,13 +12,30 @@ pub struct Opts {
     #[clap(short, long)]
     print_offsets: bool,
 
+    /// Print the relocations of instructions in object files as comments
+    /// after them.
+    ///
+    /// Relocations are read from the `reloc.*` custom sections and are printed
+    /// with their type and the name of their symbol from the symbol table of
+    /// the `linking` custom section, similar to `objdump -dr`.
+    #[clap(long)]
+    relocations: bool,
+
     /// Indicates that the "skeleton" of a module should be printed.
     ///
-    /// Items such as function bodies, data segments, and element segments are
-    /// replaced with "..." instead of printing their actual contents.
+    /// Items such as data segments and element segments are replaced with
+    /// "..." instead of printing their actual contents. Function bodies are
+    /// replaced with a comment with their number of instructions and size.
     #[clap(long)]
     skeleton: bool,
 
+    /// Skips printing function bodies larger than this many bytes.
+    ///
+    /// Function bodies above this size are summarized as with `--skeleton`,
+    /// which can be used to make it feasible to explore huge modules.
+    #[clap(long, value_name = "BYTES")]
+    max_func_size: Option<usize>,
+
     /// Ensure all wasm items have `$`-based names, even if they don't have an
     /// entry in the `name` section.
     ///
@@ -26,6 +43,32 @@ pub struct Opts {
     /// doesn't previously have a name.
     #[clap(long)]
     name_unnamed: bool,
+
+    /// Name unnamed labels after the instruction introducing them, such as
+    /// `$#block0` or `$#loop1`, and print branches with the name of their
+    /// target instead of a relative depth.
+    #[clap(long)]
+    name_labels: bool,
+
+    /// Print the immediates of `f32.const` and `f64.const` as decimal numbers
+    /// instead of hexadecimal floats.
+    #[clap(long)]
+    decimal_floats: bool,
+
+    /// Print the bit pattern of the immediates of `f32.const` and
+    /// `f64.const` as a comment after them.
+    #[clap(long)]
+    float_bits: bool,
+
+    /// Print the immediates of `i32.const` and `i64.const` in hexadecimal.
+    #[clap(long)]
+    hex_ints: bool,
+
+    /// Number of threads to print function bodies with.
+    ///
+    /// The output is the same regardless of the number of threads.
+    #[clap(long, value_name = "N", default_value_t = 1)]
+    threads: usize,
 }
 
 impl Opts {
@@ -38,8 +81,15 @@ impl Opts {
 
         let mut config = wasmprinter::Config::new();
         config.print_offsets(self.print_offsets);
+        config.print_relocations(self.relocations);
         config.print_skeleton(self.skeleton);
+        config.max_func_size(self.max_func_size);
         config.name_unnamed(self.name_unnamed);
+        config.name_labels(self.name_labels);
+        config.print_decimal_floats(self.decimal_floats);
+        config.print_float_bits(self.float_bits);
+        config.print_hex_ints(self.hex_ints);
+        config.threads(self.threads);
         self.io.output(wasm_tools::Output::Wat {
             wasm: &wasm,
             config,
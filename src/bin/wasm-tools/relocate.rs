@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use wasm_tools::relocations::{verify_relocations, ApplyRelocations, SymbolValues};
+use wasm_tools::transform::Transform;
+
+/// Apply the relocations of a relocatable object file, or verify them.
+///
+/// Relocations are read from the `reloc.*` custom sections of the object
+/// file and those of its code and data sections are applied. Each relocation
+/// refers to a symbol of the `linking` custom section, whose value is taken
+/// from the `--symbols` file. Symbols which aren't in the file resolve to the
+/// function, global, table, or tag they refer to in the object file itself,
+/// and defined data symbols to their address according to the constant offset
+/// of their segment.
+///
+/// The symbols file is a text file where each line gives the name of a
+/// symbol followed by its value and, optionally, its table slot which is
+/// used by relocations taking the address of a function. Integers may be
+/// written in hexadecimal with a `0x` prefix, and `#` starts a comment.
+///
+/// Relocated LEB128 integers keep their width, such as the 5 bytes used by
+/// LLVM, and are widened when the new value doesn't fit in it, adjusting the
+/// sizes of function bodies and data segments along with the offsets of the
+/// relocations.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Path to the values of the symbols.
+    #[clap(short, long, value_name = "PATH")]
+    symbols: Option<PathBuf>,
+
+    /// Check that the input holds the values of the symbols at the places
+    /// described by its relocations instead of applying them.
+    ///
+    /// The number of relocations checked is printed.
+    #[clap(long, conflicts_with_all = ["strip", "wat"])]
+    verify: bool,
+
+    /// Remove the `linking` and `reloc.*` custom sections from the output.
+    #[clap(long)]
+    strip: bool,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let symbols = match &self.symbols {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                SymbolValues::parse(&text)
+                    .with_context(|| format!("failed to parse {}", path.display()))?
+            }
+            None => SymbolValues::new(),
+        };
+
+        if self.verify {
+            let checked = verify_relocations(&input, &symbols)?;
+            let mut output = self.io.output_writer()?;
+            writeln!(output, "{checked} relocations verified")?;
+            return Ok(());
+        }
+
+        let mut apply = ApplyRelocations::new(symbols);
+        apply.strip_relocations(self.strip);
+        let output = apply.apply(&input)?;
+        for diagnostic in output.diagnostics.iter() {
+            eprintln!("{diagnostic}");
+        }
+        self.io.output_wasm(&output.wasm, self.wat)?;
+        Ok(())
+    }
+}
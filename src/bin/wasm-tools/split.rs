@@ -0,0 +1,87 @@
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use wasm_tools::splitting::SplitModule;
+
+/// Split a module into a primary module and secondary modules which can be
+/// loaded lazily.
+///
+/// Each `--split` option names a secondary module and its root functions. The
+/// roots and the functions which are only reachable through them are moved
+/// to the secondary module, along with the passive data segments which only
+/// they use. In the primary module each moved function is replaced by a
+/// trampoline which calls it through the `__split_table` table. Secondary
+/// modules import what they use from the primary module, and fill in their
+/// slots of `__split_table` when they're instantiated.
+///
+/// The primary module is written to the output and each secondary module to
+/// `NAME.wasm` in the `--out-dir` directory. What was moved is printed to
+/// stderr.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// A secondary module to split off, given as `NAME=FUNC[,FUNC...]`.
+    ///
+    /// Functions are referred to by their name in the `name` section, the
+    /// name they're exported with, or their index.
+    #[clap(long = "split", value_name = "NAME=FUNCS", required = true)]
+    secondaries: Vec<String>,
+
+    /// The directory in which to write secondary modules, which defaults to
+    /// the current directory.
+    #[clap(short = 'd', long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// The module name of the imports of secondary modules.
+    #[clap(long, value_name = "NAME", default_value = "primary")]
+    import_module: String,
+
+    /// Make the primary module import a placeholder function for each moved
+    /// function, which trampolines call until its secondary module has been
+    /// instantiated.
+    ///
+    /// Placeholders are imported from the `placeholder` module with the slot
+    /// of their function in `__split_table` as their name.
+    #[clap(long)]
+    placeholders: bool,
+
+    /// Output the text format of WebAssembly for the primary module instead
+    /// of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let mut config = SplitModule::new();
+        for secondary in self.secondaries.iter() {
+            let (name, funcs) = match secondary.split_once('=') {
+                Some(pair) => pair,
+                None => bail!("expected `NAME=FUNC[,FUNC...]` for `--split`, found `{secondary}`"),
+            };
+            config.secondary(name, funcs.split(',').filter(|f| !f.is_empty()));
+        }
+        config
+            .import_module(&self.import_module)
+            .placeholders(self.placeholders);
+
+        let split = config.split(&input)?;
+        for diagnostic in split.diagnostics.iter() {
+            eprintln!("{diagnostic}");
+        }
+        let dir = self.out_dir.clone().unwrap_or_default();
+        for (name, wasm) in split.secondaries.iter() {
+            let path = dir.join(format!("{name}.wasm"));
+            std::fs::write(&path, wasm)
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+        }
+        self.io.output_wasm(&split.primary, self.wat)?;
+        Ok(())
+    }
+}
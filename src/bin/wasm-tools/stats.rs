@@ -0,0 +1,291 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Write;
+use wasmparser::{Encoding, Parser, Payload, VisitOperator};
+
+/// Print statistics about the sections and instructions of a WebAssembly file.
+///
+/// This reports the number and size of each kind of section, a histogram of
+/// the opcodes used, the distribution of the size in bytes of instructions'
+/// immediates, the number of locals declared by functions, and the number of
+/// instructions originating from each WebAssembly proposal. Statistics are
+/// aggregated over all core modules, including those nested in components.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Output in JSON encoding
+    #[clap(long)]
+    json: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.parse_input_wasm()?;
+        let stats = Stats::new(&input)?;
+        let mut output = self.io.output_writer()?;
+        if self.json {
+            writeln!(
+                output,
+                "{}",
+                serde_json::to_string_pretty(&stats.to_json())?
+            )?;
+        } else {
+            stats.print(&mut output)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    /// Number and total size of sections, by name, in order of appearance.
+    sections: Vec<(String, u64, u64)>,
+    /// Number of uses of each opcode, by the name of its `Operator` variant.
+    opcodes: HashMap<&'static str, u64>,
+    /// Number of instructions from each proposal.
+    proposals: HashMap<&'static str, u64>,
+    /// Number of instructions by the size in bytes of their immediates.
+    immediate_sizes: HashMap<usize, u64>,
+    /// Number of locals declared by each function, excluding parameters.
+    locals: Vec<u32>,
+}
+
+impl Stats {
+    fn new(wasm: &[u8]) -> Result<Stats> {
+        let mut ret = Stats::default();
+        let mut encodings = Vec::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload?;
+            match &payload {
+                Payload::Version { encoding, .. } => encodings.push(*encoding),
+                Payload::End(_) => {
+                    encodings.pop();
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut locals = 0;
+                    for pair in body.get_locals_reader()? {
+                        locals += pair?.0;
+                    }
+                    ret.locals.push(locals);
+
+                    let mut reader = body.get_operators_reader()?;
+                    while !reader.eof() {
+                        let start = reader.original_position();
+                        let (opcode, proposal) = reader.visit_operator(&mut OperatorInfo)?;
+                        let end = reader.original_position();
+                        *ret.opcodes.entry(opcode).or_insert(0) += 1;
+                        *ret.proposals.entry(proposal).or_insert(0) += 1;
+                        let immediates = end - start - opcode_len(&wasm[start..end]);
+                        *ret.immediate_sizes.entry(immediates).or_insert(0) += 1;
+                    }
+                }
+                _ => {}
+            }
+
+            let name = match &payload {
+                Payload::CustomSection(c) => format!("custom {:?}", c.name()),
+                _ => match (payload.as_section(), encodings.last()) {
+                    (Some((id, _)), Some(encoding)) => section_name(*encoding, id).to_string(),
+                    _ => continue,
+                },
+            };
+            let size = payload.as_section().unwrap().1.len() as u64;
+            match ret.sections.iter_mut().find(|(n, ..)| *n == name) {
+                Some((_, count, total)) => {
+                    *count += 1;
+                    *total += size;
+                }
+                None => ret.sections.push((name, 1, size)),
+            }
+        }
+        Ok(ret)
+    }
+
+    fn instructions(&self) -> u64 {
+        self.opcodes.values().sum()
+    }
+
+    fn print(&self, output: &mut dyn Write) -> Result<()> {
+        writeln!(output, "sections:")?;
+        writeln!(output, "  {:<30} {:>8} {:>10}", "name", "count", "bytes")?;
+        for (name, count, size) in &self.sections {
+            writeln!(output, "  {name:<30} {count:>8} {size:>10}")?;
+        }
+
+        writeln!(output)?;
+        writeln!(output, "opcodes ({} instructions):", self.instructions())?;
+        for (opcode, count) in sorted(&self.opcodes) {
+            writeln!(output, "  {opcode:<30} {count:>8}")?;
+        }
+
+        writeln!(output)?;
+        writeln!(output, "proposals:")?;
+        for (proposal, count) in sorted(&self.proposals) {
+            writeln!(output, "  {proposal:<30} {count:>8}")?;
+        }
+
+        writeln!(output)?;
+        writeln!(output, "immediate sizes:")?;
+        writeln!(output, "  {:<30} {:>8}", "bytes", "count")?;
+        let mut sizes = self.immediate_sizes.iter().collect::<Vec<_>>();
+        sizes.sort();
+        for (size, count) in sizes {
+            writeln!(output, "  {size:<30} {count:>8}")?;
+        }
+
+        writeln!(output)?;
+        writeln!(output, "locals:")?;
+        let locals = self.locals_summary();
+        writeln!(output, "  {:<30} {:>8}", "functions", locals.functions)?;
+        writeln!(output, "  {:<30} {:>8}", "total", locals.total)?;
+        writeln!(output, "  {:<30} {:>8}", "min", locals.min)?;
+        writeln!(output, "  {:<30} {:>8}", "max", locals.max)?;
+        writeln!(output, "  {:<30} {:>8.2}", "mean", locals.mean)?;
+        writeln!(output, "  {:<30} {:>8}", "median", locals.median)?;
+        Ok(())
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let sections = self
+            .sections
+            .iter()
+            .map(|(name, count, size)| {
+                serde_json::json!({
+                    "name": name,
+                    "count": count,
+                    "bytes": size,
+                })
+            })
+            .collect::<Vec<_>>();
+        let opcodes = sorted(&self.opcodes)
+            .into_iter()
+            .map(|(name, count)| serde_json::json!({ "name": name, "count": count }))
+            .collect::<Vec<_>>();
+        let proposals = sorted(&self.proposals)
+            .into_iter()
+            .map(|(name, count)| serde_json::json!({ "name": name, "count": count }))
+            .collect::<Vec<_>>();
+        let mut sizes = self.immediate_sizes.iter().collect::<Vec<_>>();
+        sizes.sort();
+        let immediate_sizes = sizes
+            .into_iter()
+            .map(|(bytes, count)| serde_json::json!({ "bytes": bytes, "count": count }))
+            .collect::<Vec<_>>();
+        let locals = self.locals_summary();
+        serde_json::json!({
+            "sections": sections,
+            "instructions": self.instructions(),
+            "opcodes": opcodes,
+            "proposals": proposals,
+            "immediate_sizes": immediate_sizes,
+            "locals": {
+                "functions": locals.functions,
+                "total": locals.total,
+                "min": locals.min,
+                "max": locals.max,
+                "mean": locals.mean,
+                "median": locals.median,
+            },
+        })
+    }
+
+    fn locals_summary(&self) -> LocalsSummary {
+        let mut locals = self.locals.clone();
+        locals.sort();
+        let total = locals.iter().map(|n| u64::from(*n)).sum::<u64>();
+        LocalsSummary {
+            functions: locals.len(),
+            total,
+            min: locals.first().copied().unwrap_or(0),
+            max: locals.last().copied().unwrap_or(0),
+            mean: if locals.is_empty() {
+                0.0
+            } else {
+                total as f64 / locals.len() as f64
+            },
+            median: locals.get(locals.len() / 2).copied().unwrap_or(0),
+        }
+    }
+}
+
+struct LocalsSummary {
+    functions: usize,
+    total: u64,
+    min: u32,
+    max: u32,
+    mean: f64,
+    median: u32,
+}
+
+/// Returns the entries of `counts` from most to least frequent, with ties
+/// sorted by name.
+fn sorted<'a>(counts: &HashMap<&'a str, u64>) -> Vec<(&'a str, u64)> {
+    let mut ret = counts.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    ret.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ret
+}
+
+/// Returns the length of the opcode at the start of the encoded instruction
+/// `bytes`, including the LEB-encoded opcode following any prefix byte.
+fn opcode_len(bytes: &[u8]) -> usize {
+    match bytes[0] {
+        0xfb..=0xfe => 1 + bytes[1..].iter().position(|b| b & 0x80 == 0).unwrap() + 1,
+        _ => 1,
+    }
+}
+
+fn section_name(encoding: Encoding, id: u8) -> &'static str {
+    match (encoding, id) {
+        (Encoding::Module, 1) => "type",
+        (Encoding::Module, 2) => "import",
+        (Encoding::Module, 3) => "function",
+        (Encoding::Module, 4) => "table",
+        (Encoding::Module, 5) => "memory",
+        (Encoding::Module, 6) => "global",
+        (Encoding::Module, 7) => "export",
+        (Encoding::Module, 8) => "start",
+        (Encoding::Module, 9) => "element",
+        (Encoding::Module, 10) => "code",
+        (Encoding::Module, 11) => "data",
+        (Encoding::Module, 12) => "data count",
+        (Encoding::Module, 13) => "tag",
+        (Encoding::Component, 1) => "core module",
+        (Encoding::Component, 2) => "core instance",
+        (Encoding::Component, 3) => "core type",
+        (Encoding::Component, 4) => "component",
+        (Encoding::Component, 5) => "component instance",
+        (Encoding::Component, 6) => "component alias",
+        (Encoding::Component, 7) => "component type",
+        (Encoding::Component, 8) => "component canonical",
+        (Encoding::Component, 9) => "component start",
+        (Encoding::Component, 10) => "component import",
+        (Encoding::Component, 11) => "component export",
+        _ => "unknown",
+    }
+}
+
+/// Visitor returning the name and proposal of each operator.
+struct OperatorInfo;
+
+macro_rules! define_visit_operator {
+    ($(@$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        $(
+            fn $visit(&mut self $($(,$arg: $argty)*)?) -> Self::Output {
+                $( $(let _ = $arg;)* )?
+                (stringify!($op), stringify!($proposal))
+            }
+        )*
+    }
+}
+
+impl<'a> VisitOperator<'a> for OperatorInfo {
+    type Output = (&'static str, &'static str);
+
+    wasmparser::for_each_operator!(define_visit_operator);
+}
@@ -1,7 +1,5 @@
 use anyhow::Result;
-use std::mem;
-use wasm_encoder::{ComponentSectionId, Encode, RawSection, Section};
-use wasmparser::{Parser, Payload::*};
+use wasm_tools::transform::{Strip, Transform};
 
 /// Removes custom sections from an input WebAssembly file.
 ///
@@ -21,6 +19,15 @@ pub struct Opts {
     #[clap(long, short, value_name = "REGEX")]
     delete: Vec<String>,
 
+    /// Keep the line tables of DWARF debugging information.
+    ///
+    /// The DWARF sections of each module are replaced with only the line
+    /// tables in `.debug_line` and the compilation units they belong to,
+    /// which is enough to map code offsets to source locations for stack
+    /// traces while removing most of the size of DWARF.
+    #[clap(long)]
+    debug_keep_lines: bool,
+
     /// Output the text format of WebAssembly instead of the binary format.
     #[clap(short = 't', long)]
     wat: bool,
@@ -33,80 +40,12 @@ impl Opts {
 
     pub fn run(&self) -> Result<()> {
         let input = self.io.parse_input_wasm()?;
-        let to_delete = regex::RegexSet::new(self.delete.iter())?;
-
-        let strip_custom_section = |name: &str| {
-            // If explicitly specified, strip everything.
-            if self.all {
-                return true;
-            }
-
-            // If any section was called out by name only delete those sections.
-            if !to_delete.is_empty() {
-                return to_delete.is_match(name);
-            }
-
-            // Finally default strip everything but:
-            // * the `name` section
-            // * any `component-type` sections
-            // * the `dylink.0` section
-            name != "name" && !name.starts_with("component-type:") && name != "dylink.0"
-        };
-
-        let mut output = Vec::new();
-        let mut stack = Vec::new();
-
-        for payload in Parser::new(0).parse_all(&input) {
-            let payload = payload?;
-
-            // Track nesting depth, so that we don't mess with inner producer sections:
-            match payload {
-                Version { encoding, .. } => {
-                    output.extend_from_slice(match encoding {
-                        wasmparser::Encoding::Component => &wasm_encoder::Component::HEADER,
-                        wasmparser::Encoding::Module => &wasm_encoder::Module::HEADER,
-                    });
-                }
-                ModuleSection { .. } | ComponentSection { .. } => {
-                    stack.push(mem::take(&mut output));
-                    continue;
-                }
-                End { .. } => {
-                    let mut parent = match stack.pop() {
-                        Some(c) => c,
-                        None => break,
-                    };
-                    if output.starts_with(&wasm_encoder::Component::HEADER) {
-                        parent.push(ComponentSectionId::Component as u8);
-                        output.encode(&mut parent);
-                    } else {
-                        parent.push(ComponentSectionId::CoreModule as u8);
-                        output.encode(&mut parent);
-                    }
-                    output = parent;
-                }
-                _ => {}
-            }
-
-            match &payload {
-                CustomSection(c) => {
-                    if strip_custom_section(c.name()) {
-                        continue;
-                    }
-                }
-
-                _ => {}
-            }
-            if let Some((id, range)) = payload.as_section() {
-                RawSection {
-                    id,
-                    data: &input[range],
-                }
-                .append_to(&mut output);
-            }
-        }
-
-        self.io.output_wasm(&output, self.wat)?;
+        let output = Strip::new()
+            .all(self.all)
+            .delete(regex::RegexSet::new(self.delete.iter())?)
+            .debug_keep_lines(self.debug_keep_lines)
+            .apply(&input)?;
+        self.io.output_wasm(&output.wasm, self.wat)?;
         Ok(())
     }
 }
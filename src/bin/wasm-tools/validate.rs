@@ -180,7 +180,7 @@ impl Opts {
     }
 }
 
-fn parse_features(arg: &str) -> Result<WasmFeatures> {
+pub(crate) fn parse_features(arg: &str) -> Result<WasmFeatures> {
     let mut ret = WasmFeatures::default();
 
     const GROUPS: &[(&str, WasmFeatures)] = &[
@@ -0,0 +1,149 @@
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use wasmparser::{Validator, WasmFeatures};
+use wast::harness::{Engine, Filter, Runner, Val};
+use wast::lexer::Lexer;
+use wast::parser::{self, ParseBuffer};
+use wast::Wast;
+
+/// Check the modules of a `*.wast` script with wasmparser's validator.
+///
+/// wasm-tools doesn't execute WebAssembly, so this only checks the
+/// directives of the script which define modules, along with
+/// `assert_malformed` and `assert_invalid`, where instantiating a module only
+/// validates it. All other directives are skipped, and are logged as such.
+/// Error messages of wasmparser differ from those of the spec's reference
+/// interpreter, so any error satisfies an assertion that a module is
+/// malformed or invalid.
+///
+/// Directives which don't behave as expected are printed to stderr, and the
+/// process exits with a nonzero status if there are any.
+#[derive(clap::Parser)]
+#[clap(after_help = "\
+Examples:
+
+    # Check the modules of `binary.wast` with all Wasm feature proposals.
+    $ wasm-tools wast --features all binary.wast
+
+    # Log each directive as a line of JSON to `log.jsonl`.
+    $ wasm-tools wast --log log.jsonl binary.wast
+")]
+pub struct Opts {
+    #[clap(flatten)]
+    general: wasm_tools::GeneralOpts,
+
+    /// Comma-separated list of WebAssembly features to enable during
+    /// validation, in the same format as for `wasm-tools validate`.
+    #[clap(long, short = 'f', value_parser = crate::validate::parse_features)]
+    features: Option<WasmFeatures>,
+
+    /// Write a log of every directive to this file, or to stdout if this is
+    /// `-`.
+    ///
+    /// Each directive is logged as a JSON object on its own line, with its
+    /// index, line and column within the script, its kind, its outcome, and
+    /// what it expected and what the validator produced. Directives which
+    /// aren't checked have the outcome `skipped`.
+    #[clap(long, value_name = "FILE")]
+    log: Option<PathBuf>,
+
+    /// Input `*.wast` script to run.
+    wast: PathBuf,
+}
+
+/// An engine which validates modules rather than instantiating them.
+struct ValidatingEngine {
+    features: WasmFeatures,
+}
+
+impl Engine for ValidatingEngine {
+    type Instance = ();
+    type Error = String;
+
+    fn instantiate(&mut self, wasm: &[u8]) -> Result<(), String> {
+        Validator::new_with_features(self.features)
+            .validate_all(wasm)
+            .map(drop)
+            .map_err(|e| e.to_string())
+    }
+
+    fn register(&mut self, _name: &str, _instance: &()) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn invoke(&mut self, _: &(), _name: &str, _args: &[Val]) -> Result<Vec<Val>, String> {
+        Err("executing functions is not supported".to_string())
+    }
+
+    fn get_global(&mut self, _: &(), _name: &str) -> Result<Val, String> {
+        Err("getting globals is not supported".to_string())
+    }
+
+    fn error_matches(&self, _error: &String, _expected: &str) -> bool {
+        true
+    }
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        &self.general
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let contents = std::fs::read_to_string(&self.wast)
+            .with_context(|| format!("failed to read input wast file: {:?}", self.wast))?;
+        let mut lexer = Lexer::new(&contents);
+        lexer.allow_confusing_unicode(true);
+        let adjust_error = |mut err: wast::Error| {
+            err.set_path(&self.wast);
+            err.set_text(&contents);
+            err
+        };
+        let buf = ParseBuffer::new_with_lexer(lexer).map_err(&adjust_error)?;
+        let wast = parser::parse::<Wast>(&buf).map_err(&adjust_error)?;
+
+        let mut runner = Runner::new(ValidatingEngine {
+            features: self.features.unwrap_or_default(),
+        });
+        let mut filter = Filter::new();
+        filter
+            .kind("module")
+            .kind("module definition")
+            .kind("assert_malformed")
+            .kind("assert_invalid");
+        runner.set_filter(filter);
+        runner.record_log(self.log.is_some());
+        let report = runner.run(&contents, wast);
+
+        if let Some(path) = &self.log {
+            let mut log = String::new();
+            for entry in report.log.iter() {
+                log.push_str(&entry.to_json());
+                log.push('\n');
+            }
+            if path.as_os_str() == "-" {
+                std::io::stdout().write_all(log.as_bytes())?;
+            } else {
+                std::fs::write(path, log)
+                    .with_context(|| format!("failed to write log to {path:?}"))?;
+            }
+        }
+
+        let mut stderr = std::io::stderr().lock();
+        for failure in report.failures.iter() {
+            writeln!(stderr, "{}:{failure}", self.wast.display())?;
+        }
+        writeln!(
+            stderr,
+            "{} passed, {} failed, {} skipped",
+            report.passed,
+            report.failures.len(),
+            report.skipped
+        )?;
+        if !report.failures.is_empty() {
+            bail!("{} directives failed", report.failures.len());
+        }
+        Ok(())
+    }
+}
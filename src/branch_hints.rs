@@ -0,0 +1,376 @@
+//! Profile-guided branch hints.
+//!
+//! The [branch hinting proposal] defines a `metadata.code.branch_hint` custom
+//! section which tells engines whether `if` and `br_if` instructions are
+//! likely to be taken, so that they can lay out the likely path first.
+//! [`InjectBranchHints`] derives these hints from a [`BranchProfile`], which
+//! records how often each branch of a module was taken while running it, and
+//! [`verify_branch_hints`] checks that the hints of a module refer to actual
+//! branch instructions.
+//!
+//! [branch hinting proposal]: https://github.com/WebAssembly/branch-hinting
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use wasm_encoder::{BranchHint, BranchHints, RawSection, Section};
+use wasmparser::{Encoding, KnownCustom, Operator, Parser, Payload};
+
+/// Name of the custom section holding branch hints.
+const BRANCH_HINT_SECTION: &str = "metadata.code.branch_hint";
+
+/// How often a branch was taken and not taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BranchCounts {
+    /// The number of times the branch was taken.
+    pub taken: u64,
+    /// The number of times the branch was not taken.
+    pub not_taken: u64,
+}
+
+/// The number of times each branch of a module was taken and not taken.
+///
+/// Branches are identified by the index of their function, in the function
+/// index space, and the byte offset of their `if` or `br_if` instruction from
+/// the start of the function body, which is also how the branch hints section
+/// identifies them.
+#[derive(Debug, Clone, Default)]
+pub struct BranchProfile {
+    branches: BTreeMap<(u32, u32), BranchCounts>,
+}
+
+impl BranchProfile {
+    /// Creates an empty profile.
+    pub fn new() -> BranchProfile {
+        BranchProfile::default()
+    }
+
+    /// Parses a profile in its text format.
+    ///
+    /// Each line of the text describes a branch with four integers separated
+    /// by whitespace: the function index, the offset of the branch
+    /// instruction, and the number of times it was taken and not taken.
+    /// Integers may be written in hexadecimal with a `0x` prefix. Empty lines
+    /// and comments, from `#` to the end of a line, are ignored. Counts of the
+    /// same branch on multiple lines are added together, so that the profiles
+    /// of multiple runs can be concatenated.
+    pub fn parse(text: &str) -> Result<BranchProfile> {
+        let mut profile = BranchProfile::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.is_empty() {
+                continue;
+            }
+            let parse = || -> Result<(u32, u32, BranchCounts)> {
+                let [func, offset, taken, not_taken] = fields[..] else {
+                    bail!("expected 4 fields, found {}", fields.len());
+                };
+                let counts = BranchCounts {
+                    taken: parse_int(taken)?,
+                    not_taken: parse_int(not_taken)?,
+                };
+                Ok((
+                    parse_int(func)?.try_into()?,
+                    parse_int(offset)?.try_into()?,
+                    counts,
+                ))
+            };
+            let (func, offset, counts) =
+                parse().with_context(|| format!("invalid profile line {}", i + 1))?;
+            profile.add(func, offset, counts);
+        }
+        Ok(profile)
+    }
+
+    /// Adds `counts` to those of the branch at `offset` in function `func`.
+    pub fn add(&mut self, func: u32, offset: u32, counts: BranchCounts) {
+        let entry = self.branches.entry((func, offset)).or_default();
+        entry.taken = entry.taken.saturating_add(counts.taken);
+        entry.not_taken = entry.not_taken.saturating_add(counts.not_taken);
+    }
+
+    /// Adds all the counts of `other` to this profile.
+    pub fn merge(&mut self, other: &BranchProfile) {
+        for (&(func, offset), counts) in other.branches.iter() {
+            self.add(func, offset, *counts);
+        }
+    }
+
+    /// Returns the counts of the branches of this profile, as
+    /// `(func, offset, counts)`, sorted by function and offset.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u32, BranchCounts)> + '_ {
+        self.branches
+            .iter()
+            .map(|(&(func, offset), counts)| (func, offset, *counts))
+    }
+}
+
+fn parse_int(s: &str) -> Result<u64> {
+    let ret = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    ret.with_context(|| format!("invalid integer `{s}`"))
+}
+
+/// A [`Transform`] which adds branch hints derived from a [`BranchProfile`]
+/// to a core module.
+///
+/// A branch is hinted as likely taken, or likely not taken, if it went that
+/// way in at least a fraction [`InjectBranchHints::min_bias`] of the times it
+/// was executed, and was executed at least [`InjectBranchHints::min_count`]
+/// times. Hints already present in the module are kept unless the profile
+/// has enough data about the branch to replace them.
+///
+/// An error is returned if the profile refers to something other than a
+/// branch instruction of the module, which usually means that it was
+/// collected with a different build of the module.
+pub struct InjectBranchHints {
+    profile: BranchProfile,
+    min_count: u64,
+    min_bias: f64,
+}
+
+impl InjectBranchHints {
+    /// Creates a transform adding the hints derived from `profile`.
+    pub fn new(profile: BranchProfile) -> InjectBranchHints {
+        InjectBranchHints {
+            profile,
+            min_count: 1,
+            min_bias: 0.5,
+        }
+    }
+
+    /// The minimum number of times a branch must have been executed to be
+    /// hinted, which defaults to 1.
+    pub fn min_count(&mut self, min_count: u64) -> &mut Self {
+        self.min_count = min_count;
+        self
+    }
+
+    /// The minimum fraction of executions a branch must have gone the same
+    /// way to be hinted, which defaults to 0.5.
+    ///
+    /// Branches which were taken exactly as often as not are never hinted.
+    pub fn min_bias(&mut self, min_bias: f64) -> &mut Self {
+        self.min_bias = min_bias;
+        self
+    }
+
+    /// Returns the hint for a branch with `counts`, if any.
+    fn hint(&self, counts: BranchCounts) -> Option<bool> {
+        let total = counts.taken.saturating_add(counts.not_taken);
+        let majority = counts.taken.max(counts.not_taken);
+        if total == 0
+            || total < self.min_count
+            || counts.taken == counts.not_taken
+            || (majority as f64) < self.min_bias * total as f64
+        {
+            return None;
+        }
+        Some(counts.taken > counts.not_taken)
+    }
+}
+
+impl Transform for InjectBranchHints {
+    fn name(&self) -> &str {
+        "inject-branch-hints"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let module = ModuleBranches::new(wasm)?;
+        module.check_hints()?;
+
+        let mut hints = module
+            .hints
+            .iter()
+            .flat_map(|(func, hints)| {
+                hints
+                    .iter()
+                    .map(|(offset, taken)| ((*func, *offset), *taken))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let mut hinted = 0;
+        let mut skipped = 0;
+        for (func, offset, counts) in self.profile.iter() {
+            if !module.is_branch(func, offset) {
+                bail!(
+                    "profile refers to function {func} at offset {offset:#x}, which is not an \
+                     `if` or `br_if` instruction"
+                );
+            }
+            match self.hint(counts) {
+                Some(taken) => {
+                    hints.insert((func, offset), taken);
+                    hinted += 1;
+                }
+                None => skipped += 1,
+            }
+        }
+
+        let mut section = BranchHints::new();
+        let mut hints = hints.into_iter().peekable();
+        while let Some(((func, _), _)) = hints.peek().copied() {
+            let mut function_hints = Vec::new();
+            while let Some(((_, offset), taken)) = hints.next_if(|((f, _), _)| *f == func) {
+                function_hints.push(BranchHint {
+                    branch_func_offset: offset,
+                    branch_hint_value: taken.into(),
+                });
+            }
+            section.function_hints(func, function_hints);
+        }
+
+        // The section is placed right before the code section, replacing any
+        // existing one, so that engines know about the hints when compiling.
+        let mut output = wasm_encoder::Module::HEADER.to_vec();
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload?;
+            match &payload {
+                Payload::CustomSection(c) if c.name() == BRANCH_HINT_SECTION => continue,
+                Payload::CodeSectionStart { .. } if !section.is_empty() => {
+                    section.append_to(&mut output);
+                }
+                _ => {}
+            }
+            if let Some((id, range)) = payload.as_section() {
+                RawSection {
+                    id,
+                    data: &wasm[range],
+                }
+                .append_to(&mut output);
+            }
+        }
+
+        Ok(Transformed {
+            wasm: output,
+            diagnostics: vec![format!(
+                "hinted {hinted} branches, skipped {skipped} branches without enough data"
+            )],
+        })
+    }
+}
+
+/// Checks that the branch hints of the core module `wasm` are well-formed,
+/// returning the number of hints.
+///
+/// Hints must be sorted by function index and, within a function, by offset,
+/// and must refer to `if` or `br_if` instructions of functions defined by the
+/// module. The branch hints section must also appear before the code section.
+pub fn verify_branch_hints(wasm: &[u8]) -> Result<usize> {
+    let module = ModuleBranches::new(wasm)?;
+    module.check_hints()?;
+    Ok(module.hints.iter().map(|(_, hints)| hints.len()).sum())
+}
+
+/// The branch instructions and the branch hints of a core module.
+struct ModuleBranches {
+    /// The offsets of the branch instructions of each defined function, keyed
+    /// by function index.
+    branches: HashMap<u32, HashSet<u32>>,
+    /// The hints of the branch hints section, in order, as the function index
+    /// and the `(offset, taken)` pair of each of its hints.
+    hints: Vec<(u32, Vec<(u32, bool)>)>,
+    /// Whether the branch hints section appears after the code section.
+    hints_after_code: bool,
+}
+
+impl ModuleBranches {
+    fn new(wasm: &[u8]) -> Result<ModuleBranches> {
+        let mut ret = ModuleBranches {
+            branches: HashMap::new(),
+            hints: Vec::new(),
+            hints_after_code: false,
+        };
+        let mut func = 0;
+        let mut code = false;
+        let mut hints_seen = false;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version { encoding, .. } if encoding != Encoding::Module => {
+                    bail!("branch hints are only supported for core modules")
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        if let wasmparser::TypeRef::Func(_) = import?.ty {
+                            func += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionStart { .. } => code = true,
+                Payload::CodeSectionEntry(body) => {
+                    let start = body.range().start;
+                    let mut reader = body.get_operators_reader()?;
+                    let mut branches = HashSet::new();
+                    while !reader.eof() {
+                        let (op, offset) = reader.read_with_offset()?;
+                        if let Operator::If { .. } | Operator::BrIf { .. } = op {
+                            branches.insert((offset - start) as u32);
+                        }
+                    }
+                    ret.branches.insert(func, branches);
+                    func += 1;
+                }
+                Payload::CustomSection(c) => {
+                    let KnownCustom::BranchHints(reader) = c.as_known() else {
+                        continue;
+                    };
+                    if hints_seen {
+                        bail!("module has more than one branch hints section");
+                    }
+                    hints_seen = true;
+                    ret.hints_after_code = code;
+                    for function in reader {
+                        let function = function?;
+                        let mut hints = Vec::new();
+                        for hint in function.hints {
+                            let hint = hint?;
+                            hints.push((hint.func_offset, hint.taken));
+                        }
+                        ret.hints.push((function.func, hints));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    fn is_branch(&self, func: u32, offset: u32) -> bool {
+        self.branches
+            .get(&func)
+            .is_some_and(|branches| branches.contains(&offset))
+    }
+
+    /// Checks that the existing hints of the module are well-formed.
+    fn check_hints(&self) -> Result<()> {
+        if self.hints_after_code {
+            bail!("branch hints section appears after the code section");
+        }
+        let mut prev_func = None;
+        for (func, hints) in self.hints.iter() {
+            if prev_func.is_some_and(|prev| prev >= *func) {
+                bail!("branch hints of function {func} are out of order");
+            }
+            prev_func = Some(*func);
+            if !self.branches.contains_key(func) {
+                bail!("branch hints refer to function {func}, which is not defined by the module");
+            }
+            let mut prev_offset = None;
+            for (offset, _) in hints {
+                if prev_offset.is_some_and(|prev| prev >= *offset) {
+                    bail!("branch hints of function {func} are out of order at offset {offset:#x}");
+                }
+                prev_offset = Some(*offset);
+                if !self.is_branch(*func, *offset) {
+                    bail!(
+                        "branch hint of function {func} at offset {offset:#x} does not refer to \
+                         an `if` or `br_if` instruction"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+//! Transform to canonicalize the encoding of a module.
+//!
+//! The same module can be encoded in many ways which differ only in choices
+//! that don't affect its meaning, such as how wide LEB128 integers are, where
+//! custom sections are placed, or whether identical types are defined more
+//! than once. Content-addressed stores of modules want semantically identical
+//! modules to be byte-identical, so this transform picks one canonical
+//! encoding for each of these choices.
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{CustomSection, Encode, Module, TypeSection};
+use wasmparser::Parser;
+
+/// Canonicalizes the encoding of the core wasm module `wasm`, returning the
+/// transformed module.
+///
+/// The canonical encoding:
+///
+/// * encodes all integers as LEB128 with the minimal width,
+/// * merges adjacent declarations of locals with the same type,
+/// * removes rec groups which are identical to an earlier rec group, and
+///   refers to the earlier one instead,
+/// * sorts the entries of the `name` section by index, and
+/// * moves all custom sections to the end of the module, sorted by name.
+///   Custom sections with the same name keep their relative order, and the
+///   `dylink.0` section is left at the start of the module as it's required
+///   to be the first section.
+pub fn canonicalize(wasm: &[u8]) -> Result<Vec<u8>> {
+    if Parser::is_component(wasm) {
+        bail!("canonicalizing components is not supported");
+    }
+
+    let mut canonicalizer = Canonicalizer::default();
+    let mut module = Module::new();
+    canonicalizer.parse_core_module(&mut module, Parser::new(0), wasm)?;
+    canonicalizer
+        .custom_sections
+        .sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, data) in canonicalizer.custom_sections {
+        module.section(&CustomSection {
+            name: name.into(),
+            data: data.into(),
+        });
+    }
+    Ok(module.finish())
+}
+
+/// A [`Transform`] applying [`canonicalize`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Canonicalize;
+
+impl Transform for Canonicalize {
+    fn name(&self) -> &str {
+        "canonicalize"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: canonicalize(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct Canonicalizer {
+    /// The new index of each type of the input, by its old index.
+    types: Vec<u32>,
+    /// The number of types in the output.
+    num_types: u32,
+    /// The rec groups in the output, keyed by their encoding, mapped to the
+    /// new index of their first type.
+    rec_groups: HashMap<Vec<u8>, u32>,
+    /// The old index of the first type of the rec group whose key is being
+    /// computed, if any.
+    key_group_start: Option<u32>,
+    /// The custom sections to append at the end of the module.
+    custom_sections: Vec<(String, Vec<u8>)>,
+}
+
+/// Added to indices of types within a rec group when computing the key of
+/// the group, which is larger than the maximum number of types in a module.
+const REC_GROUP_INDEX: u32 = 1 << 31;
+
+impl Reencode for Canonicalizer {
+    type Error = Infallible;
+
+    fn type_index(&mut self, ty: u32) -> u32 {
+        match self.key_group_start {
+            Some(start) if ty >= start => REC_GROUP_INDEX + (ty - start),
+            _ => self.types[ty as usize],
+        }
+    }
+
+    fn parse_type_section(
+        &mut self,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<(), Error> {
+        for rec_group in section {
+            let rec_group = rec_group?;
+            let start = self.types.len() as u32;
+            let len = rec_group.types().len() as u32;
+
+            // Compute the key of this rec group, where references to types
+            // within the group are encoded relative to the group, to find an
+            // earlier rec group identical to it.
+            self.key_group_start = Some(start);
+            let mut key_section = TypeSection::new();
+            let result = self.parse_recursive_type_group(key_section.ty(), rec_group.clone());
+            self.key_group_start = None;
+            result?;
+            let mut key = Vec::new();
+            key_section.encode(&mut key);
+
+            let new_start = match self.rec_groups.get(&key) {
+                Some(new_start) => *new_start,
+                None => {
+                    let new_start = self.num_types;
+                    self.rec_groups.insert(key, new_start);
+                    self.num_types += len;
+                    self.types.extend(new_start..new_start + len);
+                    self.parse_recursive_type_group(types.ty(), rec_group)?;
+                    continue;
+                }
+            };
+            self.types.extend(new_start..new_start + len);
+        }
+        Ok(())
+    }
+
+    fn new_function_with_parsed_locals(
+        &mut self,
+        func: &wasmparser::FunctionBody<'_>,
+    ) -> Result<wasm_encoder::Function, Error> {
+        let mut locals: Vec<(u32, wasm_encoder::ValType)> = Vec::new();
+        for pair in func.get_locals_reader()? {
+            let (count, ty) = pair?;
+            let ty = self.val_type(ty)?;
+            match locals.last_mut() {
+                _ if count == 0 => {}
+                Some((prev_count, prev_ty)) if *prev_ty == ty => *prev_count += count,
+                _ => locals.push((count, ty)),
+            }
+        }
+        Ok(wasm_encoder::Function::new(locals))
+    }
+
+    fn parse_custom_section(
+        &mut self,
+        module: &mut Module,
+        section: wasmparser::CustomSectionReader<'_>,
+    ) -> Result<(), Error> {
+        let data = match section.as_known() {
+            wasmparser::KnownCustom::Name(name) => self
+                .custom_name_section(name)?
+                .as_custom()
+                .data
+                .into_owned(),
+            _ if section.name() == "dylink.0" => {
+                utils::parse_custom_section(self, module, section)?;
+                return Ok(());
+            }
+            _ => section.data().to_vec(),
+        };
+        self.custom_sections
+            .push((section.name().to_string(), data));
+        Ok(())
+    }
+
+    fn parse_custom_name_subsection(
+        &mut self,
+        names: &mut wasm_encoder::NameSection,
+        section: wasmparser::Name<'_>,
+    ) -> Result<(), Error> {
+        match section {
+            wasmparser::Name::Function(map) => names.functions(&name_map(map, |i| i)?),
+            wasmparser::Name::Type(map) => names.types(&name_map(map, |i| self.type_index(i))?),
+            wasmparser::Name::Local(map) => names.locals(&indirect_name_map(map, |i| i)?),
+            wasmparser::Name::Label(map) => names.labels(&indirect_name_map(map, |i| i)?),
+            wasmparser::Name::Table(map) => names.tables(&name_map(map, |i| i)?),
+            wasmparser::Name::Memory(map) => names.memories(&name_map(map, |i| i)?),
+            wasmparser::Name::Global(map) => names.globals(&name_map(map, |i| i)?),
+            wasmparser::Name::Element(map) => names.elements(&name_map(map, |i| i)?),
+            wasmparser::Name::Data(map) => names.data(&name_map(map, |i| i)?),
+            wasmparser::Name::Tag(map) => names.tags(&name_map(map, |i| i)?),
+            wasmparser::Name::Field(map) => {
+                names.fields(&indirect_name_map(map, |i| self.type_index(i))?)
+            }
+            wasmparser::Name::Module { .. } | wasmparser::Name::Unknown { .. } => {
+                utils::parse_custom_name_subsection(self, names, section)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reencodes `map` with its entries sorted by their new index.
+///
+/// If multiple entries have the same new index, because they name types which
+/// were deduplicated, only the first one is kept.
+fn name_map(
+    map: wasmparser::NameMap<'_>,
+    mut map_index: impl FnMut(u32) -> u32,
+) -> wasmparser::Result<wasm_encoder::NameMap> {
+    let mut names = Vec::new();
+    for naming in map {
+        let naming = naming?;
+        names.push((map_index(naming.index), naming.name));
+    }
+    names.sort_by_key(|(index, _)| *index);
+    names.dedup_by_key(|(index, _)| *index);
+    let mut ret = wasm_encoder::NameMap::new();
+    for (index, name) in names {
+        ret.append(index, name);
+    }
+    Ok(ret)
+}
+
+/// Like [`name_map`] but for indirect name maps, where the inner maps are also
+/// sorted.
+fn indirect_name_map(
+    map: wasmparser::IndirectNameMap<'_>,
+    mut map_index: impl FnMut(u32) -> u32,
+) -> wasmparser::Result<wasm_encoder::IndirectNameMap> {
+    let mut names = Vec::new();
+    for naming in map {
+        let naming = naming?;
+        names.push((map_index(naming.index), name_map(naming.names, |i| i)?));
+    }
+    names.sort_by_key(|(index, _)| *index);
+    names.dedup_by_key(|(index, _)| *index);
+    let mut ret = wasm_encoder::IndirectNameMap::new();
+    for (index, names) in names {
+        ret.append(index, &names);
+    }
+    Ok(ret)
+}
@@ -0,0 +1,354 @@
+//! Transform to shrink the data segments of modules.
+//!
+//! Toolchains typically emit one active data segment per section of a linked
+//! binary, such as `.rodata` and `.data`, and these often contain long runs of
+//! zeros from zero-initialized statics and padding. Memories start out zeroed
+//! so these runs don't need to be written at all. This transform splits data
+//! segments around long runs of zeros and merges segments separated by only a
+//! few bytes, which shrinks both the module and the amount of memory written
+//! during instantiation.
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{ConstExpr, DataSection, Module};
+use wasmparser::{DataKind, Encoding, MemoryType, Operator, Parser, Payload, TypeRef};
+
+/// Configuration of how data segments are compacted.
+///
+/// Only the active data segments of memories defined by the module are
+/// compacted, and only if all of them have constant offsets, fit within the
+/// minimum size of the memory, and don't overlap each other. Under those
+/// conditions the order of the segments doesn't matter, so all of them are
+/// replaced with new segments at the position of the first one. Segments of
+/// other memories and passive segments are left as-is.
+#[derive(Debug, Clone)]
+pub struct CompactDataSegments {
+    zero_run_threshold: u32,
+}
+
+impl CompactDataSegments {
+    /// Creates a new configuration with the default threshold of 16 bytes.
+    pub fn new() -> CompactDataSegments {
+        CompactDataSegments::default()
+    }
+
+    /// Configures the length of the longest run of zeros which is kept within
+    /// a data segment.
+    ///
+    /// Longer runs of zeros split the segment containing them in two, and
+    /// segments which are separated by at most this many bytes are merged
+    /// into one. Each new segment costs a handful of bytes to encode, so
+    /// small thresholds may make the module larger.
+    pub fn zero_run_threshold(&mut self, bytes: u32) -> &mut Self {
+        self.zero_run_threshold = bytes;
+        self
+    }
+}
+
+impl Default for CompactDataSegments {
+    fn default() -> CompactDataSegments {
+        CompactDataSegments {
+            zero_run_threshold: 16,
+        }
+    }
+}
+
+impl Transform for CompactDataSegments {
+    fn name(&self) -> &str {
+        "compact-data-segments"
+    }
+
+    /// Compacts the data segments of the core wasm module `wasm`.
+    ///
+    /// The diagnostics report the number of segments and bytes they write
+    /// and the size of the module before and after, as well as memories
+    /// whose segments couldn't be compacted.
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let mut compactor = Compactor::new(self, wasm)?;
+        let mut module = Module::new();
+        compactor.parse_core_module(&mut module, Parser::new(0), wasm)?;
+        let wasm_out = module.finish();
+
+        let mut diagnostics = compactor.diagnostics;
+        diagnostics.push(format!(
+            "{} data segments writing {} bytes were replaced with {} segments writing {} \
+             bytes, and the module shrank from {} to {} bytes",
+            compactor.stats.segments_before,
+            compactor.stats.bytes_before,
+            compactor.stats.segments_after,
+            compactor.stats.bytes_after,
+            wasm.len(),
+            wasm_out.len(),
+        ));
+        Ok(Transformed {
+            wasm: wasm_out,
+            diagnostics,
+        })
+    }
+}
+
+/// A data segment of the output.
+enum Segment {
+    /// The segment at this index of the input, unchanged.
+    Original(u32),
+    /// A new active segment.
+    Active {
+        memory: u32,
+        memory64: bool,
+        offset: u64,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Default)]
+struct Stats {
+    segments_before: usize,
+    bytes_before: usize,
+    segments_after: usize,
+    bytes_after: usize,
+}
+
+struct Compactor {
+    /// The data segments of the output, in order.
+    segments: Vec<Segment>,
+    /// The new index of each data segment of the input.
+    data_indices: Vec<u32>,
+    diagnostics: Vec<String>,
+    stats: Stats,
+}
+
+impl Compactor {
+    fn new(config: &CompactDataSegments, wasm: &[u8]) -> Result<Compactor> {
+        // Defined memories are `Some` and imported ones are `None`.
+        let mut memories: Vec<Option<MemoryType>> = Vec::new();
+        let mut data = Vec::new();
+        let mut has_data_count = false;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("compacting data segments of components is not supported"),
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        if let TypeRef::Memory(_) = import?.ty {
+                            memories.push(None);
+                        }
+                    }
+                }
+                Payload::MemorySection(s) => {
+                    for ty in s {
+                        memories.push(Some(ty?));
+                    }
+                }
+                Payload::DataCountSection { .. } => has_data_count = true,
+                Payload::DataSection(s) => {
+                    for datum in s {
+                        data.push(datum?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut ret = Compactor {
+            segments: Vec::new(),
+            data_indices: vec![0; data.len()],
+            diagnostics: Vec::new(),
+            stats: Stats::default(),
+        };
+
+        // Find the memories whose segments can be compacted and gather their
+        // segments, sorted by offset.
+        let mut compacted = vec![None; memories.len()];
+        for (memory, ty) in memories.iter().enumerate() {
+            let mut segments = Vec::new();
+            let mut problem = None;
+            for datum in data.iter() {
+                let offset_expr = match &datum.kind {
+                    DataKind::Active {
+                        memory_index,
+                        offset_expr,
+                    } if *memory_index as usize == memory => offset_expr,
+                    _ => continue,
+                };
+                let ty = match ty {
+                    Some(ty) => ty,
+                    None => {
+                        problem = Some("is imported");
+                        break;
+                    }
+                };
+                let offset = match data_offset(offset_expr) {
+                    Some(offset) => offset,
+                    None => {
+                        problem = Some("has a data segment whose offset isn't a constant");
+                        break;
+                    }
+                };
+                let min_size = ty
+                    .initial
+                    .checked_mul(1 << ty.page_size_log2.unwrap_or(16))
+                    .unwrap_or(u64::MAX);
+                match offset.checked_add(datum.data.len() as u64) {
+                    Some(end) if end <= min_size => {}
+                    _ => {
+                        problem = Some("has a data segment which doesn't fit in its minimum size");
+                        break;
+                    }
+                }
+                segments.push((offset, datum.data));
+            }
+            if let Some(problem) = problem {
+                ret.diagnostics.push(format!(
+                    "data segments of memory {memory} were left as-is as the memory {problem}"
+                ));
+                continue;
+            }
+            if segments.is_empty() {
+                continue;
+            }
+            segments.sort_by_key(|(offset, _)| *offset);
+            let overlaps = segments
+                .windows(2)
+                .any(|w| w[0].0 + w[0].1.len() as u64 > w[1].0);
+            if overlaps {
+                ret.diagnostics.push(format!(
+                    "data segments of memory {memory} were left as-is as they overlap"
+                ));
+                continue;
+            }
+            compacted[memory] = Some(split(&segments, config.zero_run_threshold.into()));
+        }
+
+        // Emit the new segments of each compacted memory in place of its
+        // first segment. Instructions referring to any of its segments refer
+        // to the first new segment instead, which is equivalent as active
+        // segments are dropped after instantiation. If there's no new segment
+        // to refer to then an empty one is kept.
+        for (index, datum) in data.iter().enumerate() {
+            let memory = match &datum.kind {
+                DataKind::Active { memory_index, .. } => *memory_index,
+                DataKind::Passive => {
+                    ret.data_indices[index] = ret.segments.len() as u32;
+                    ret.segments.push(Segment::Original(index as u32));
+                    continue;
+                }
+            };
+            let pieces = match &mut compacted[memory as usize] {
+                Some(pieces) => pieces,
+                None => {
+                    ret.data_indices[index] = ret.segments.len() as u32;
+                    ret.segments.push(Segment::Original(index as u32));
+                    continue;
+                }
+            };
+            ret.stats.segments_before += 1;
+            ret.stats.bytes_before += datum.data.len();
+            let first = ret.segments.iter().position(|s| match s {
+                Segment::Active { memory: m, .. } => *m == memory,
+                Segment::Original(_) => false,
+            });
+            if let Some(first) = first {
+                ret.data_indices[index] = first as u32;
+                continue;
+            }
+            if pieces.is_empty() && has_data_count {
+                pieces.push((0, Vec::new()));
+            }
+            ret.data_indices[index] = ret.segments.len() as u32;
+            let memory64 = memories[memory as usize].as_ref().unwrap().memory64;
+            for (offset, data) in pieces.drain(..) {
+                ret.stats.segments_after += 1;
+                ret.stats.bytes_after += data.len();
+                ret.segments.push(Segment::Active {
+                    memory,
+                    memory64,
+                    offset,
+                    data,
+                });
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Reencode for Compactor {
+    type Error = Infallible;
+
+    fn data_index(&mut self, data: u32) -> u32 {
+        self.data_indices[data as usize]
+    }
+
+    fn data_count(&mut self, _count: u32) -> u32 {
+        self.segments.len() as u32
+    }
+
+    fn parse_data_section(
+        &mut self,
+        data: &mut DataSection,
+        section: wasmparser::DataSectionReader<'_>,
+    ) -> Result<(), Error> {
+        let original = section.into_iter().collect::<Result<Vec<_>, _>>()?;
+        for segment in std::mem::take(&mut self.segments) {
+            match segment {
+                Segment::Original(index) => {
+                    utils::parse_data(self, data, original[index as usize].clone())?
+                }
+                Segment::Active {
+                    memory,
+                    memory64,
+                    offset,
+                    data: bytes,
+                } => {
+                    let offset = if memory64 {
+                        ConstExpr::i64_const(offset as i64)
+                    } else {
+                        ConstExpr::i32_const(offset as i32)
+                    };
+                    data.active(memory, &offset, bytes);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the offset of a data segment if it's a constant.
+fn data_offset(expr: &wasmparser::ConstExpr<'_>) -> Option<u64> {
+    let mut reader = expr.get_operators_reader();
+    let value = match reader.read().ok()? {
+        Operator::I32Const { value } => u64::from(value as u32),
+        Operator::I64Const { value } => value as u64,
+        _ => return None,
+    };
+    match reader.read().ok()? {
+        Operator::End if reader.eof() => Some(value),
+        _ => None,
+    }
+}
+
+/// Splits the non-overlapping `segments`, sorted by offset, into new segments
+/// which don't contain runs of more than `threshold` zeros and don't start or
+/// end with a zero.
+fn split(segments: &[(u64, &[u8])], threshold: u64) -> Vec<(u64, Vec<u8>)> {
+    let mut pieces: Vec<(u64, Vec<u8>)> = Vec::new();
+    for (offset, data) in segments {
+        for (i, byte) in data.iter().enumerate() {
+            if *byte == 0 {
+                continue;
+            }
+            let addr = offset + i as u64;
+            match pieces.last_mut() {
+                Some((start, piece)) if addr - (*start + piece.len() as u64) <= threshold => {
+                    piece.resize((addr - *start) as usize, 0);
+                    piece.push(*byte);
+                }
+                _ => pieces.push((addr, vec![*byte])),
+            }
+        }
+    }
+    pieces
+}
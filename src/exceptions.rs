@@ -0,0 +1,958 @@
+//! Transforms between the legacy and standardized encodings of exception
+//! handling.
+//!
+//! The exception-handling proposal was first shipped with the `try`, `catch`,
+//! `catch_all`, `delegate`, and `rethrow` instructions, which were later
+//! replaced by `try_table` and `throw_ref`. Toolchains still emit the legacy
+//! encoding for compatibility, so [`upgrade_exceptions`] rewrites it into the
+//! standardized one, allowing engines to drop support for the former.
+//!
+//! The reverse, [`downgrade_exceptions`], is best-effort since `exnref`
+//! values have no legacy equivalent: only `try_table` instructions with
+//! `catch` and `catch_all` clauses are supported.
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode, RoundtripReencoder};
+use wasm_encoder::{
+    BlockType, Catch, CodeSection, Function, Handle, IndirectNameMap, Instruction, Module, NameMap,
+    NameSection, TypeSection, ValType,
+};
+use wasmparser::{Encoding, FunctionBody, Operator, Parser, Payload, TypeRef};
+
+/// Converts the legacy exception-handling instructions in the core wasm module
+/// `wasm` to `try_table` and `throw_ref`, returning the transformed module.
+///
+/// A `try` with `catch` clauses becomes a `try_table` nested in one `block`
+/// per clause, each of which is branched to with the caught values and is
+/// followed by the code of the clause. A `try` ending in `delegate` becomes a
+/// `try_table` which catches all exceptions with `catch_all_ref` and branches
+/// to a new `block` in the targeted construct, where the exception is thrown
+/// again with `throw_ref`. Clauses which are the target of a `rethrow` store
+/// the caught exception in a new `exnref` local for `throw_ref` to use.
+///
+/// New function types are appended to the type section for blocks with
+/// parameters or multiple results.
+pub fn upgrade_exceptions(wasm: &[u8]) -> Result<Vec<u8>> {
+    let mut upgrader = Upgrader::new(wasm)?;
+    let mut module = Module::new();
+    upgrader.parse_core_module(&mut module, Parser::new(0), wasm)?;
+    Ok(module.finish())
+}
+
+/// Converts the `try_table` instructions in the core wasm module `wasm` to
+/// the legacy exception-handling instructions, returning the transformed
+/// module.
+///
+/// Each `catch` clause of a `try_table` becomes a legacy `catch` clause which
+/// branches to the original label. This is best-effort: modules using
+/// `catch_ref`, `catch_all_ref`, or `throw_ref` can't be converted and an
+/// error is returned instead.
+pub fn downgrade_exceptions(wasm: &[u8]) -> Result<Vec<u8>> {
+    let mut downgrader = Downgrader::new(wasm)?;
+    let mut module = Module::new();
+    downgrader.parse_core_module(&mut module, Parser::new(0), wasm)?;
+    Ok(module.finish())
+}
+
+/// A [`Transform`] applying [`upgrade_exceptions`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UpgradeExceptions;
+
+impl Transform for UpgradeExceptions {
+    fn name(&self) -> &str {
+        "upgrade-exceptions"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: upgrade_exceptions(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+/// A [`Transform`] applying [`downgrade_exceptions`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DowngradeExceptions;
+
+impl Transform for DowngradeExceptions {
+    fn name(&self) -> &str {
+        "downgrade-exceptions"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: downgrade_exceptions(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+/// Returns whether `op` is a legacy exception-handling instruction.
+fn is_legacy(op: &Operator<'_>) -> bool {
+    matches!(
+        op,
+        Operator::Try { .. }
+            | Operator::Catch { .. }
+            | Operator::CatchAll
+            | Operator::Delegate { .. }
+            | Operator::Rethrow { .. }
+    )
+}
+
+fn val_types(tys: &[wasmparser::ValType]) -> Result<Vec<ValType>> {
+    tys.iter()
+        .map(|ty| Ok(RoundtripReencoder.val_type(*ty)?))
+        .collect()
+}
+
+fn read_locals(func: &FunctionBody<'_>) -> Result<(Vec<(u32, ValType)>, u32)> {
+    let mut locals = Vec::new();
+    let mut num_locals = 0;
+    for pair in func.get_locals_reader()? {
+        let (cnt, ty) = pair?;
+        locals.push((cnt, RoundtripReencoder.val_type(ty)?));
+        num_locals += cnt;
+    }
+    Ok((locals, num_locals))
+}
+
+struct Upgrader {
+    /// Each type in the original module, or `None` if it isn't a function
+    /// type.
+    types: Vec<Option<wasmparser::FuncType>>,
+    /// The type of each tag, including imported ones.
+    tag_types: Vec<u32>,
+    /// Function types appended to the type section for the types of new
+    /// blocks.
+    new_types: Vec<(Vec<ValType>, Vec<ValType>)>,
+    /// The upgraded body of each defined function, or `None` if it doesn't
+    /// use legacy exception handling.
+    funcs: Vec<Option<Function>>,
+    /// For each upgraded function, by index, the index of the label of each
+    /// original construction in the upgraded function, to fix up the names
+    /// of labels.
+    label_maps: HashMap<u32, Vec<u32>>,
+    num_imported_funcs: u32,
+    next_func: usize,
+}
+
+impl Upgrader {
+    fn new(wasm: &[u8]) -> Result<Upgrader> {
+        let mut ret = Upgrader {
+            types: Vec::new(),
+            tag_types: Vec::new(),
+            new_types: Vec::new(),
+            funcs: Vec::new(),
+            label_maps: HashMap::new(),
+            num_imported_funcs: 0,
+            next_func: 0,
+        };
+        // The types of new blocks need to be added to the type section, so
+        // all functions are upgraded ahead of time to find out what they are.
+        let mut func_types = Vec::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("upgrading exceptions in components is not supported"),
+                Payload::TypeSection(s) => {
+                    for group in s {
+                        for ty in group?.into_types() {
+                            let func = match ty.composite_type.inner {
+                                wasmparser::CompositeInnerType::Func(f) => Some(f),
+                                _ => None,
+                            };
+                            ret.types.push(func);
+                        }
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        match import?.ty {
+                            TypeRef::Func(_) => ret.num_imported_funcs += 1,
+                            TypeRef::Tag(ty) => ret.tag_types.push(ty.func_type_idx),
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        func_types.push(ty?);
+                    }
+                }
+                Payload::TagSection(s) => {
+                    for ty in s {
+                        ret.tag_types.push(ty?.func_type_idx);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let ty = func_types[ret.funcs.len()];
+                    let func = ret.upgrade_function(ty, &body)?;
+                    ret.funcs.push(func);
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    fn function_type(&self, ty: u32) -> Result<&wasmparser::FuncType> {
+        match self.types.get(ty as usize) {
+            Some(Some(func)) => Ok(func),
+            _ => bail!("type {ty} is not a function type"),
+        }
+    }
+
+    /// Returns the parameters and results of the block type `ty`.
+    fn block_signature(&self, ty: wasmparser::BlockType) -> Result<(Vec<ValType>, Vec<ValType>)> {
+        Ok(match ty {
+            wasmparser::BlockType::Empty => (Vec::new(), Vec::new()),
+            wasmparser::BlockType::Type(ty) => (Vec::new(), vec![RoundtripReencoder.val_type(ty)?]),
+            wasmparser::BlockType::FuncType(ty) => {
+                let func = self.function_type(ty)?;
+                (val_types(func.params())?, val_types(func.results())?)
+            }
+        })
+    }
+
+    /// Returns the types of the values carried by exceptions with `tag`.
+    fn tag_params(&self, tag: u32) -> Result<Vec<ValType>> {
+        match self.tag_types.get(tag as usize) {
+            Some(ty) => val_types(self.function_type(*ty)?.params()),
+            None => bail!("unknown tag {tag}"),
+        }
+    }
+
+    /// Returns the type of a new block with `params` and `results`, adding a
+    /// new function type for it if needed.
+    fn block_type(&mut self, params: &[ValType], results: &[ValType]) -> BlockType {
+        match (params, results) {
+            ([], []) => BlockType::Empty,
+            ([], [ty]) => BlockType::Result(*ty),
+            _ => {
+                let ty = (params.to_vec(), results.to_vec());
+                let index = match self.new_types.iter().position(|t| *t == ty) {
+                    Some(index) => index,
+                    None => {
+                        self.new_types.push(ty);
+                        self.new_types.len() - 1
+                    }
+                };
+                BlockType::FunctionType((self.types.len() + index) as u32)
+            }
+        }
+    }
+
+    fn upgrade_function(&mut self, ty: u32, body: &FunctionBody<'_>) -> Result<Option<Function>> {
+        let mut ops = Vec::new();
+        let mut reader = body.get_operators_reader()?;
+        while !reader.eof() {
+            ops.push(reader.read()?);
+        }
+        if !ops.iter().any(is_legacy) {
+            return Ok(None);
+        }
+
+        let (segments, tries) = plan(&ops)?;
+        let func = self.function_type(ty)?;
+        let num_params = func.params().len() as u32;
+        let results = val_types(func.results())?;
+        let (mut locals, num_locals) = read_locals(body)?;
+        let exns = tries
+            .iter()
+            .flat_map(|t| &t.catches)
+            .filter(|(_, rethrown)| *rethrown)
+            .count();
+        if exns > 0 {
+            locals.push((exns as u32, ValType::EXNREF));
+        }
+
+        let mut upgrader = FunctionUpgrader {
+            module: self,
+            f: Function::new(locals),
+            frames: Vec::new(),
+            depth: 1,
+            labels: 0,
+            label_map: Vec::new(),
+            segments,
+            next_segment: 0,
+            tries,
+            next_try: 0,
+            next_local: num_params + num_locals,
+        };
+        upgrader
+            .frames
+            .push(Frame::new(0, None, Vec::new(), results));
+        upgrader.start_segment(Vec::new());
+        for op in ops {
+            upgrader.op(op)?;
+        }
+        let FunctionUpgrader { f, label_map, .. } = upgrader;
+        let index = self.num_imported_funcs + self.funcs.len() as u32;
+        self.label_maps.insert(index, label_map);
+        Ok(Some(f))
+    }
+}
+
+impl Reencode for Upgrader {
+    type Error = Infallible;
+
+    fn parse_type_section(
+        &mut self,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_type_section(self, types, section)?;
+        for (params, results) in &self.new_types {
+            types
+                .ty()
+                .function(params.iter().copied(), results.iter().copied());
+        }
+        Ok(())
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let upgraded = self.funcs[self.next_func].take();
+        self.next_func += 1;
+        match upgraded {
+            Some(f) => {
+                code.function(&f);
+                Ok(())
+            }
+            None => utils::parse_function_body(self, code, func),
+        }
+    }
+
+    fn parse_custom_name_subsection(
+        &mut self,
+        names: &mut NameSection,
+        section: wasmparser::Name<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let map = match section {
+            wasmparser::Name::Label(map) => map,
+            section => return utils::parse_custom_name_subsection(self, names, section),
+        };
+        let mut labels = IndirectNameMap::new();
+        for naming in map {
+            let naming = naming?;
+            let label_map = self.label_maps.get(&naming.index);
+            let mut func = NameMap::new();
+            for name in naming.names {
+                let name = name?;
+                match label_map {
+                    Some(map) => {
+                        if let Some(label) = map.get(name.index as usize) {
+                            func.append(*label, name.name);
+                        }
+                    }
+                    None => func.append(name.index, name.name),
+                }
+            }
+            labels.append(naming.index, &func);
+        }
+        names.labels(&labels);
+        Ok(())
+    }
+}
+
+/// How a legacy `try` is upgraded, which depends on the instructions after
+/// it.
+#[derive(Default)]
+struct TryPlan {
+    /// The tag of each `catch` clause, or `None` for `catch_all`, and whether
+    /// the clause is the target of a `rethrow`.
+    catches: Vec<(Option<u32>, bool)>,
+    /// The relative depth of the `delegate` ending the `try`, if any.
+    delegate: Option<u32>,
+}
+
+/// Plans the upgrade of the instructions `ops` of a function.
+///
+/// The code of each construction is split into segments: the body of blocks,
+/// loops, and `try`s, each arm of an `if`, and each `catch` clause. This
+/// returns whether each segment is targeted by a `delegate`, in order, along
+/// with the plan of each `try`.
+fn plan(ops: &[Operator<'_>]) -> Result<(Vec<bool>, Vec<TryPlan>)> {
+    // The current segment of each open construction and the index of its
+    // plan if it's a `try`, starting with the body of the function.
+    let mut frames: Vec<(usize, Option<usize>)> = vec![(0, None)];
+    let mut segments = vec![false];
+    let mut tries: Vec<TryPlan> = Vec::new();
+    let frame = |frames: &[(usize, Option<usize>)], depth: u32| match frames
+        .len()
+        .checked_sub(depth as usize + 1)
+    {
+        Some(i) => Ok(frames[i]),
+        None => bail!("unknown label {depth}"),
+    };
+    for op in ops {
+        match *op {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::TryTable { .. } => {
+                frames.push((segments.len(), None));
+                segments.push(false);
+            }
+            Operator::Try { .. } => {
+                frames.push((segments.len(), Some(tries.len())));
+                segments.push(false);
+                tries.push(TryPlan::default());
+            }
+            Operator::Else => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.0 = segments.len();
+                }
+                segments.push(false);
+            }
+            Operator::Catch { .. } | Operator::CatchAll => {
+                let tag = match *op {
+                    Operator::Catch { tag_index } => Some(tag_index),
+                    _ => None,
+                };
+                match frames.last_mut() {
+                    Some((segment, Some(t))) => {
+                        tries[*t].catches.push((tag, false));
+                        *segment = segments.len();
+                        segments.push(false);
+                    }
+                    _ => bail!("`catch` found outside of a `try`"),
+                }
+            }
+            Operator::Rethrow { relative_depth } => match frame(&frames, relative_depth)? {
+                (_, Some(t)) if !tries[t].catches.is_empty() => {
+                    tries[t].catches.last_mut().unwrap().1 = true;
+                }
+                _ => bail!("`rethrow` doesn't target a `catch`"),
+            },
+            Operator::Delegate { relative_depth } => {
+                match frames.pop() {
+                    Some((_, Some(t))) if tries[t].catches.is_empty() => {
+                        tries[t].delegate = Some(relative_depth);
+                    }
+                    _ => bail!("`delegate` found outside of a `try`"),
+                }
+                let (segment, _) = frame(&frames, relative_depth)?;
+                segments[segment] = true;
+            }
+            Operator::End => {
+                frames.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok((segments, tries))
+}
+
+/// A construction in the original function.
+struct Frame {
+    /// Position in the labels of the upgraded function of the label which
+    /// branches to this construction go to.
+    label: usize,
+    /// The index of the plan of this construction if it's a legacy `try`.
+    try_index: Option<usize>,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// The number of `catch` clauses of a `try` found so far.
+    clauses: usize,
+    /// Position of the label of the block receiving exceptions delegated to
+    /// the current segment, if any.
+    delegate: Option<usize>,
+    /// The local holding the exception caught by the current clause of a
+    /// `try`, if it's rethrown.
+    exn: Option<u32>,
+}
+
+impl Frame {
+    fn new(
+        label: usize,
+        try_index: Option<usize>,
+        params: Vec<ValType>,
+        results: Vec<ValType>,
+    ) -> Frame {
+        Frame {
+            label,
+            try_index,
+            params,
+            results,
+            clauses: 0,
+            delegate: None,
+            exn: None,
+        }
+    }
+}
+
+struct FunctionUpgrader<'a> {
+    module: &'a mut Upgrader,
+    f: Function,
+    frames: Vec<Frame>,
+    /// The number of labels currently open in the upgraded function.
+    depth: usize,
+    /// The number of constructions in the upgraded function so far.
+    labels: u32,
+    /// The index of the label of each original construction in the upgraded
+    /// function.
+    label_map: Vec<u32>,
+    segments: Vec<bool>,
+    next_segment: usize,
+    tries: Vec<TryPlan>,
+    next_try: usize,
+    next_local: u32,
+}
+
+impl FunctionUpgrader<'_> {
+    fn frame(&self, relative_depth: u32) -> Result<&Frame> {
+        match self.frames.len().checked_sub(relative_depth as usize + 1) {
+            Some(i) => Ok(&self.frames[i]),
+            None => bail!("unknown label {relative_depth}"),
+        }
+    }
+
+    fn top(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    /// Returns the relative depth of the label at position `label`.
+    fn relative(&self, label: usize) -> u32 {
+        (self.depth - 1 - label) as u32
+    }
+
+    /// Returns the relative depth in the upgraded function of branches to the
+    /// original `relative_depth`.
+    fn branch(&self, relative_depth: u32) -> Result<u32> {
+        Ok(self.relative(self.frame(relative_depth)?.label))
+    }
+
+    /// Emits `inst` opening a new construction, returning the position of its
+    /// label.
+    fn enter(&mut self, inst: &Instruction<'_>) -> usize {
+        self.f.instruction(inst);
+        self.depth += 1;
+        self.labels += 1;
+        self.depth - 1
+    }
+
+    /// Starts the next segment of the current construction, whose code takes
+    /// `params`.
+    ///
+    /// Segments targeted by a `delegate` are wrapped in two blocks: the inner
+    /// one receives delegated exceptions, which are then thrown again from
+    /// the segment, and the outer one is branched to when the segment
+    /// completes normally.
+    fn start_segment(&mut self, params: Vec<ValType>) {
+        let wrapped = self.segments[self.next_segment];
+        self.next_segment += 1;
+        if !wrapped {
+            return;
+        }
+        let results = self.top().results.clone();
+        let outer = self.module.block_type(&params, &results);
+        let inner = self.module.block_type(&params, &[ValType::EXNREF]);
+        self.enter(&Instruction::Block(outer));
+        let label = self.enter(&Instruction::Block(inner));
+        self.top().delegate = Some(label);
+    }
+
+    /// Ends the current segment of the current construction.
+    fn end_segment(&mut self) {
+        if self.top().delegate.take().is_some() {
+            self.f.instruction(&Instruction::Br(1));
+            self.f.instruction(&Instruction::End);
+            self.f.instruction(&Instruction::ThrowRef);
+            self.f.instruction(&Instruction::End);
+            self.depth -= 2;
+        }
+    }
+
+    /// Emits `inst` opening a construction of type `ty`.
+    fn open(
+        &mut self,
+        inst: Instruction<'_>,
+        ty: wasmparser::BlockType,
+        try_index: Option<usize>,
+    ) -> Result<()> {
+        let (params, results) = self.module.block_signature(ty)?;
+        self.label_map.push(self.labels);
+        let label = self.enter(&inst);
+        self.frames
+            .push(Frame::new(label, try_index, params.clone(), results));
+        self.start_segment(params);
+        Ok(())
+    }
+
+    fn op(&mut self, op: Operator<'_>) -> Result<()> {
+        match op {
+            Operator::Block { blockty } => {
+                let ty = RoundtripReencoder.block_type(blockty)?;
+                self.open(Instruction::Block(ty), blockty, None)?;
+            }
+            Operator::Loop { blockty } => {
+                let ty = RoundtripReencoder.block_type(blockty)?;
+                self.open(Instruction::Loop(ty), blockty, None)?;
+            }
+            Operator::If { blockty } => {
+                let ty = RoundtripReencoder.block_type(blockty)?;
+                self.open(Instruction::If(ty), blockty, None)?;
+            }
+            Operator::TryTable { ref try_table } => {
+                let ty = try_table.ty;
+                let inst = self.remap(RoundtripReencoder.instruction(op.clone())?)?;
+                self.open(inst, ty, None)?;
+            }
+            Operator::Try { blockty } => self.try_(blockty)?,
+            Operator::Else => {
+                self.end_segment();
+                self.f.instruction(&Instruction::Else);
+                let params = self.top().params.clone();
+                self.start_segment(params);
+            }
+            Operator::Catch { tag_index } => self.catch(Some(tag_index))?,
+            Operator::CatchAll => self.catch(None)?,
+            Operator::End | Operator::Delegate { .. } => {
+                // The last clause of a `try` falls through to the end of the
+                // outer block, and a `try` without clauses was upgraded to a
+                // `block` or `try_table` which ends like any other.
+                self.end_segment();
+                self.frames.pop();
+                self.f.instruction(&Instruction::End);
+                self.depth -= 1;
+            }
+            Operator::Rethrow { relative_depth } => match self.frame(relative_depth)?.exn {
+                Some(exn) => {
+                    self.f.instruction(&Instruction::LocalGet(exn));
+                    self.f.instruction(&Instruction::ThrowRef);
+                }
+                None => bail!("`rethrow` doesn't target a `catch`"),
+            },
+            op => {
+                let inst = self.remap(RoundtripReencoder.instruction(op)?)?;
+                self.f.instruction(&inst);
+            }
+        }
+        Ok(())
+    }
+
+    fn try_(&mut self, blockty: wasmparser::BlockType) -> Result<()> {
+        let index = self.next_try;
+        self.next_try += 1;
+        let ty = RoundtripReencoder.block_type(blockty)?;
+        let plan = &self.tries[index];
+
+        if let Some(relative_depth) = plan.delegate {
+            let label = match self.frame(relative_depth)?.delegate {
+                Some(label) => self.relative(label),
+                None => unreachable!("targets of `delegate` are always wrapped"),
+            };
+            let catches = vec![Catch::AllRef { label }];
+            return self.open(
+                Instruction::TryTable(ty, catches.into()),
+                blockty,
+                Some(index),
+            );
+        }
+        if plan.catches.is_empty() {
+            return self.open(Instruction::Block(ty), blockty, Some(index));
+        }
+
+        // Clauses are upgraded to an outer block which the `try` results go
+        // to, in which there is one block per clause receiving the values of
+        // caught exceptions, innermost first.
+        let (params, results) = self.module.block_signature(blockty)?;
+        let clauses = plan.catches.clone();
+        self.label_map.push(self.labels);
+        let outer = self.enter(&Instruction::Block(ty));
+        for (tag, rethrown) in clauses.iter().rev() {
+            let mut values = match tag {
+                Some(tag) => self.module.tag_params(*tag)?,
+                None => Vec::new(),
+            };
+            if *rethrown {
+                values.push(ValType::EXNREF);
+            }
+            let ty = self.module.block_type(&params, &values);
+            self.enter(&Instruction::Block(ty));
+        }
+        let catches = clauses
+            .iter()
+            .enumerate()
+            .map(|(i, clause)| {
+                let label = self.relative(outer + clauses.len() - i);
+                match *clause {
+                    (Some(tag), false) => Catch::One { tag, label },
+                    (Some(tag), true) => Catch::OneRef { tag, label },
+                    (None, false) => Catch::All { label },
+                    (None, true) => Catch::AllRef { label },
+                }
+            })
+            .collect::<Vec<_>>();
+        self.enter(&Instruction::TryTable(ty, catches.into()));
+        self.frames
+            .push(Frame::new(outer, Some(index), params.clone(), results));
+        self.start_segment(params);
+        Ok(())
+    }
+
+    fn catch(&mut self, tag: Option<u32>) -> Result<()> {
+        self.end_segment();
+        let frame = self.frames.last().unwrap();
+        let (clauses, label) = (frame.clauses, frame.label);
+        let rethrown = match frame.try_index {
+            Some(index) => self.tries[index].catches[clauses].1,
+            None => bail!("`catch` found outside of a `try`"),
+        };
+        // The first clause ends the `try_table`, and every clause ends with a
+        // branch out of the `try` followed by the block of the next clause.
+        if clauses == 0 {
+            self.f.instruction(&Instruction::End);
+            self.depth -= 1;
+        }
+        self.f.instruction(&Instruction::Br(self.relative(label)));
+        self.f.instruction(&Instruction::End);
+        self.depth -= 1;
+
+        let exn = if rethrown {
+            let exn = self.next_local;
+            self.next_local += 1;
+            self.f.instruction(&Instruction::LocalSet(exn));
+            Some(exn)
+        } else {
+            None
+        };
+        let params = match tag {
+            Some(tag) => self.module.tag_params(tag)?,
+            None => Vec::new(),
+        };
+        let frame = self.top();
+        frame.clauses += 1;
+        frame.exn = exn;
+        self.start_segment(params);
+        Ok(())
+    }
+
+    /// Remaps the labels of `inst` to the labels of the upgraded function.
+    fn remap<'a>(&self, inst: Instruction<'a>) -> Result<Instruction<'a>> {
+        let handle = |handle: &Handle| -> Result<Handle> {
+            Ok(match *handle {
+                Handle::OnLabel { tag, label } => Handle::OnLabel {
+                    tag,
+                    label: self.branch(label)?,
+                },
+                Handle::OnSwitch { tag } => Handle::OnSwitch { tag },
+            })
+        };
+        Ok(match inst {
+            Instruction::Br(l) => Instruction::Br(self.branch(l)?),
+            Instruction::BrIf(l) => Instruction::BrIf(self.branch(l)?),
+            Instruction::BrOnNull(l) => Instruction::BrOnNull(self.branch(l)?),
+            Instruction::BrOnNonNull(l) => Instruction::BrOnNonNull(self.branch(l)?),
+            Instruction::BrTable(ls, l) => Instruction::BrTable(
+                ls.iter()
+                    .map(|l| self.branch(*l))
+                    .collect::<Result<Vec<_>>>()?
+                    .into(),
+                self.branch(l)?,
+            ),
+            Instruction::BrOnCast {
+                relative_depth,
+                from_ref_type,
+                to_ref_type,
+            } => Instruction::BrOnCast {
+                relative_depth: self.branch(relative_depth)?,
+                from_ref_type,
+                to_ref_type,
+            },
+            Instruction::BrOnCastFail {
+                relative_depth,
+                from_ref_type,
+                to_ref_type,
+            } => Instruction::BrOnCastFail {
+                relative_depth: self.branch(relative_depth)?,
+                from_ref_type,
+                to_ref_type,
+            },
+            Instruction::TryTable(ty, catches) => Instruction::TryTable(
+                ty,
+                catches
+                    .iter()
+                    .map(|catch| {
+                        Ok(match *catch {
+                            Catch::One { tag, label } => Catch::One {
+                                tag,
+                                label: self.branch(label)?,
+                            },
+                            Catch::OneRef { tag, label } => Catch::OneRef {
+                                tag,
+                                label: self.branch(label)?,
+                            },
+                            Catch::All { label } => Catch::All {
+                                label: self.branch(label)?,
+                            },
+                            Catch::AllRef { label } => Catch::AllRef {
+                                label: self.branch(label)?,
+                            },
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?
+                    .into(),
+            ),
+            Instruction::Resume {
+                cont_type_index,
+                resume_table,
+            } => Instruction::Resume {
+                cont_type_index,
+                resume_table: resume_table
+                    .iter()
+                    .map(handle)
+                    .collect::<Result<Vec<_>>>()?
+                    .into(),
+            },
+            Instruction::ResumeThrow {
+                cont_type_index,
+                tag_index,
+                resume_table,
+            } => Instruction::ResumeThrow {
+                cont_type_index,
+                tag_index,
+                resume_table: resume_table
+                    .iter()
+                    .map(handle)
+                    .collect::<Result<Vec<_>>>()?
+                    .into(),
+            },
+            inst => inst,
+        })
+    }
+}
+
+struct Downgrader {
+    /// The downgraded body of each defined function, or `None` if it doesn't
+    /// use `try_table`.
+    funcs: Vec<Option<Function>>,
+    next_func: usize,
+}
+
+impl Downgrader {
+    fn new(wasm: &[u8]) -> Result<Downgrader> {
+        let mut ret = Downgrader {
+            funcs: Vec::new(),
+            next_func: 0,
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("downgrading exceptions in components is not supported"),
+                Payload::CodeSectionEntry(body) => {
+                    let func = downgrade_function(&body)?;
+                    ret.funcs.push(func);
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+}
+
+fn downgrade_function(body: &FunctionBody<'_>) -> Result<Option<Function>> {
+    let mut reader = body.get_operators_reader()?;
+    let mut any = false;
+    while !reader.eof() {
+        if let Operator::TryTable { .. } | Operator::ThrowRef = reader.read()? {
+            any = true;
+            break;
+        }
+    }
+    if !any {
+        return Ok(None);
+    }
+
+    let (locals, _) = read_locals(body)?;
+    let mut f = Function::new(locals);
+    // The legacy `catch` clauses, as a tag and a label, to emit at the end
+    // of each open construction.
+    let mut frames: Vec<Vec<(Option<u32>, u32)>> = Vec::new();
+    let mut reader = body.get_operators_reader()?;
+    while !reader.eof() {
+        let (op, offset) = reader.read_with_offset()?;
+        match op {
+            Operator::TryTable { try_table } => {
+                let mut clauses = Vec::new();
+                for catch in try_table.catches {
+                    match catch {
+                        wasmparser::Catch::One { tag, label } => clauses.push((Some(tag), label)),
+                        // Any clause after `catch_all` can't be reached.
+                        wasmparser::Catch::All { label } => {
+                            clauses.push((None, label));
+                            break;
+                        }
+                        wasmparser::Catch::OneRef { .. } | wasmparser::Catch::AllRef { .. } => {
+                            bail!(
+                                "cannot downgrade `try_table` with `catch_ref` or \
+                                 `catch_all_ref` clauses (at offset {offset:#x})"
+                            )
+                        }
+                    }
+                }
+                f.instruction(&Instruction::Try(
+                    RoundtripReencoder.block_type(try_table.ty)?,
+                ));
+                frames.push(clauses);
+            }
+            Operator::ThrowRef => bail!("cannot downgrade `throw_ref` (at offset {offset:#x})"),
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. } => {
+                frames.push(Vec::new());
+                f.instruction(&RoundtripReencoder.instruction(op)?);
+            }
+            Operator::End | Operator::Delegate { .. } => {
+                // Clauses branch to the same labels as the original ones,
+                // which are one level further away since the `try` label is
+                // in scope.
+                for (tag, label) in frames.pop().unwrap_or_default() {
+                    f.instruction(&match tag {
+                        Some(tag) => Instruction::Catch(tag),
+                        None => Instruction::CatchAll,
+                    });
+                    f.instruction(&Instruction::Br(label + 1));
+                }
+                f.instruction(&RoundtripReencoder.instruction(op)?);
+            }
+            op => {
+                f.instruction(&RoundtripReencoder.instruction(op)?);
+            }
+        }
+    }
+    Ok(Some(f))
+}
+
+impl Reencode for Downgrader {
+    type Error = Infallible;
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let downgraded = self.funcs[self.next_func].take();
+        self.next_func += 1;
+        match downgraded {
+            Some(f) => {
+                code.function(&f);
+                Ok(())
+            }
+            None => utils::parse_function_body(self, code, func),
+        }
+    }
+}
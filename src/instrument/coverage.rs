@@ -0,0 +1,413 @@
+//! Instrumentation counting how often each basic block is executed.
+
+use super::section_passed;
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{
+    CodeSection, CustomSection, EntityType, ExportKind, ExportSection, ImportSection, Instruction,
+    MemArg, MemorySection, MemoryType, Module, SectionId, TypeSection, ValType,
+};
+use wasmparser::{Encoding, FunctionBody, Operator, Parser, Payload, TypeRef};
+
+/// Name of the custom section mapping counters to their location.
+const COVERAGE_MAP_SECTION: &str = "coverage-map";
+
+/// Name of the exported memory holding counters.
+const COVERAGE_MEMORY_EXPORT: &str = "__coverage_counters";
+
+/// Module and name of the imported function notified of counter hits.
+const COVERAGE_IMPORT: (&str, &str) = ("coverage", "hit");
+
+/// A [`Transform`] instrumenting every basic block of a module with a
+/// coverage counter.
+///
+/// Each basic block of each function is assigned a counter which is
+/// incremented every time the block is entered. By default counters are
+/// 32-bit integers stored in a new memory, exported as `__coverage_counters`,
+/// where counter `N` lives at byte offset `N * 4`. With [`Coverage::import`]
+/// an imported function `coverage.hit` of type `(func (param i32))` is
+/// instead called with the index of the counter.
+///
+/// A `coverage-map` custom section is added to the module which contains a
+/// JSON array describing each counter, in order, as the index of its function
+/// and the offset of its first instruction relative to the start of the code
+/// section. Both refer to the original, uninstrumented module so they can be
+/// resolved with its `name` section or DWARF information.
+///
+/// Note that storing counters in a new memory requires the multi-memory
+/// proposal if the module already defines or imports a memory.
+///
+/// ```
+/// use wasm_tools::instrument::coverage::Coverage;
+/// use wasm_tools::transform::Transform;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let wasm = wat::parse_str(
+///     r#"
+///         (module
+///             (func (param i32)
+///                 (if (local.get 0) (then nop))
+///             )
+///         )
+///     "#,
+/// )?;
+/// let instrumented = Coverage::new().import(true).apply(&wasm)?;
+/// wasmparser::validate(&instrumented.wasm)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    import: bool,
+}
+
+impl Coverage {
+    /// Creates a new configuration storing counters in memory by default.
+    pub fn new() -> Coverage {
+        Coverage::default()
+    }
+
+    /// Configures whether an imported `coverage.hit` function is called
+    /// instead of incrementing counters in memory.
+    pub fn import(&mut self, import: bool) -> &mut Self {
+        self.import = import;
+        self
+    }
+}
+
+impl Transform for Coverage {
+    fn name(&self) -> &str {
+        "coverage"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let mut instrumenter = Instrumenter::new(wasm, self.import)?;
+        let mut module = Module::new();
+        instrumenter.parse_core_module(&mut module, Parser::new(0), wasm)?;
+        Ok(Transformed {
+            wasm: module.finish(),
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+/// A counter inserted into the instrumented module.
+struct Counter {
+    /// Index of the function containing this counter in the original module.
+    func: u32,
+    /// Absolute offset, in the original module, of the first instruction of
+    /// the basic block that this counter is for.
+    offset: usize,
+}
+
+struct Instrumenter {
+    import: bool,
+    num_types: u32,
+    num_imported_funcs: u32,
+    num_memories: u32,
+    code_section_start: usize,
+    counters: Vec<Counter>,
+    next_counter: usize,
+    added_types: bool,
+    added_imports: bool,
+    added_memories: bool,
+    added_exports: bool,
+}
+
+impl Instrumenter {
+    /// Scans `wasm` to determine where counters need to be placed.
+    fn new(wasm: &[u8], import: bool) -> Result<Instrumenter> {
+        let mut ret = Instrumenter {
+            import,
+            num_types: 0,
+            num_imported_funcs: 0,
+            num_memories: 0,
+            code_section_start: 0,
+            counters: Vec::new(),
+            next_counter: 0,
+            added_types: false,
+            added_imports: false,
+            added_memories: false,
+            added_exports: false,
+        };
+        let mut num_defined_funcs = 0;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("instrumenting components is not supported"),
+                Payload::TypeSection(s) => {
+                    for group in s {
+                        ret.num_types += group?.types().len() as u32;
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        match import?.ty {
+                            TypeRef::Func(_) => ret.num_imported_funcs += 1,
+                            TypeRef::Memory(_) => ret.num_memories += 1,
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::MemorySection(s) => ret.num_memories += s.count(),
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        if !import && export.name == COVERAGE_MEMORY_EXPORT {
+                            bail!("module already has an export named `{COVERAGE_MEMORY_EXPORT}`");
+                        }
+                    }
+                }
+                Payload::CodeSectionStart { range, .. } => ret.code_section_start = range.start,
+                Payload::CodeSectionEntry(body) => {
+                    let func = ret.num_imported_funcs + num_defined_funcs;
+                    ret.add_counters(func, &body)?;
+                    num_defined_funcs += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Adds a counter for each basic block within `body`.
+    ///
+    /// A new basic block starts at the beginning of the function, at the
+    /// beginning of a `loop` since it's a branch target, within each arm of an
+    /// `if` or `catch`, and after every instruction which may fall through
+    /// after branching elsewhere or after which control may resume from a
+    /// branch.
+    fn add_counters(&mut self, func: u32, body: &FunctionBody<'_>) -> Result<()> {
+        let mut reader = body.get_operators_reader()?;
+        let mut starts_block = true;
+        while !reader.eof() {
+            let (op, offset) = reader.read_with_offset()?;
+            if starts_block {
+                self.counters.push(Counter { func, offset });
+            }
+            starts_block = matches!(
+                op,
+                Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Else
+                    | Operator::End
+                    | Operator::BrIf { .. }
+                    | Operator::BrOnNull { .. }
+                    | Operator::BrOnNonNull { .. }
+                    | Operator::BrOnCast { .. }
+                    | Operator::BrOnCastFail { .. }
+                    | Operator::Catch { .. }
+                    | Operator::CatchAll
+                    | Operator::Delegate { .. }
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the index of the memory that counters are stored in.
+    fn counter_memory(&self) -> u32 {
+        self.num_memories
+    }
+
+    /// Returns the index of the imported `coverage.hit` function.
+    fn hit_func(&self) -> u32 {
+        self.num_imported_funcs
+    }
+
+    fn add_types(&mut self, types: &mut TypeSection) {
+        if self.import {
+            types.ty().function([ValType::I32], []);
+        }
+        self.added_types = true;
+    }
+
+    fn add_imports(&mut self, imports: &mut ImportSection) {
+        if self.import {
+            let (module, name) = COVERAGE_IMPORT;
+            imports.import(module, name, EntityType::Function(self.num_types));
+        }
+        self.added_imports = true;
+    }
+
+    fn add_memories(&mut self, memories: &mut MemorySection) {
+        if !self.import {
+            let bytes = self.counters.len() as u64 * 4;
+            memories.memory(MemoryType {
+                minimum: bytes.div_ceil(1 << 16).max(1),
+                maximum: None,
+                memory64: false,
+                shared: false,
+                page_size_log2: None,
+            });
+        }
+        self.added_memories = true;
+    }
+
+    fn add_exports(&mut self, exports: &mut ExportSection) {
+        if !self.import {
+            exports.export(
+                COVERAGE_MEMORY_EXPORT,
+                ExportKind::Memory,
+                self.counter_memory(),
+            );
+        }
+        self.added_exports = true;
+    }
+
+    fn coverage_map(&self) -> String {
+        let counters = self
+            .counters
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "func": c.func,
+                    "offset": c.offset - self.code_section_start,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::Value::Array(counters).to_string()
+    }
+
+    fn increment(&self, f: &mut wasm_encoder::Function, counter: u32) {
+        if self.import {
+            f.instruction(&Instruction::I32Const(counter as i32));
+            f.instruction(&Instruction::Call(self.hit_func()));
+            return;
+        }
+        let memarg = MemArg {
+            offset: u64::from(counter) * 4,
+            align: 2,
+            memory_index: self.counter_memory(),
+        };
+        f.instruction(&Instruction::I32Const(0));
+        f.instruction(&Instruction::I32Const(0));
+        f.instruction(&Instruction::I32Load(memarg));
+        f.instruction(&Instruction::I32Const(1));
+        f.instruction(&Instruction::I32Add);
+        f.instruction(&Instruction::I32Store(memarg));
+    }
+}
+
+impl Reencode for Instrumenter {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        if self.import && func >= self.num_imported_funcs {
+            func + 1
+        } else {
+            func
+        }
+    }
+
+    fn parse_type_section(
+        &mut self,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_type_section(self, types, section)?;
+        self.add_types(types);
+        Ok(())
+    }
+
+    fn parse_import_section(
+        &mut self,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_import_section(self, imports, section)?;
+        self.add_imports(imports);
+        Ok(())
+    }
+
+    fn parse_memory_section(
+        &mut self,
+        memories: &mut MemorySection,
+        section: wasmparser::MemorySectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_memory_section(self, memories, section)?;
+        self.add_memories(memories);
+        Ok(())
+    }
+
+    fn parse_export_section(
+        &mut self,
+        exports: &mut ExportSection,
+        section: wasmparser::ExportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_export_section(self, exports, section)?;
+        self.add_exports(exports);
+        Ok(())
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let mut f = self.new_function_with_parsed_locals(&func)?;
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            let offset = reader.original_position();
+            if let Some(counter) = self.counters.get(self.next_counter) {
+                if counter.offset == offset {
+                    self.increment(&mut f, self.next_counter as u32);
+                    self.next_counter += 1;
+                }
+            }
+            f.instruction(&self.parse_instruction(&mut reader)?);
+        }
+        code.function(&f);
+        Ok(())
+    }
+
+    fn intersperse_section_hook(
+        &mut self,
+        module: &mut Module,
+        _after: Option<SectionId>,
+        before: Option<SectionId>,
+    ) -> Result<(), Error<Infallible>> {
+        // Insert any sections that the module doesn't have but which are
+        // needed for instrumentation once the point where they would have
+        // been has passed.
+        let passed = |id| section_passed(before, id);
+        if !self.added_types && passed(SectionId::Type) {
+            let mut types = TypeSection::new();
+            self.add_types(&mut types);
+            if !types.is_empty() {
+                module.section(&types);
+            }
+        }
+        if !self.added_imports && passed(SectionId::Import) {
+            let mut imports = ImportSection::new();
+            self.add_imports(&mut imports);
+            if !imports.is_empty() {
+                module.section(&imports);
+            }
+        }
+        if !self.added_memories && passed(SectionId::Memory) {
+            let mut memories = MemorySection::new();
+            self.add_memories(&mut memories);
+            if !memories.is_empty() {
+                module.section(&memories);
+            }
+        }
+        if !self.added_exports && passed(SectionId::Export) {
+            let mut exports = ExportSection::new();
+            self.add_exports(&mut exports);
+            if !exports.is_empty() {
+                module.section(&exports);
+            }
+        }
+        if before.is_none() {
+            module.section(&CustomSection {
+                name: COVERAGE_MAP_SECTION.into(),
+                data: self.coverage_map().into_bytes().into(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+//! Instrumentation reporting the loads and stores of a module to the host.
+
+use super::section_passed;
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::convert::Infallible;
+use std::ops::Range;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{
+    BlockType, CodeSection, EntityType, ImportSection, Instruction, Module, SectionId, TypeSection,
+    ValType,
+};
+use wasmparser::{Encoding, FunctionBody, Operator, Parser, Payload, TypeRef};
+
+/// Module and name of the imported function notified of memory accesses.
+const MEMORY_TRACE_IMPORT: (&str, &str) = ("memory-trace", "access");
+
+/// A [`Transform`] tracing loads from and stores to memory with an imported
+/// function.
+///
+/// Every load and store is preceded by a call to an imported function
+/// `memory-trace.access` of type `(func (param i64 i32 i32))` whose arguments
+/// are the effective address of the access, its size in bytes, and the opcode
+/// of the instruction. Opcodes of prefixed instructions, such as
+/// `v128.load`, include the prefix byte shifted left by 8 bits.
+///
+/// Core loads and stores of all memories are traced as well as `v128.load`
+/// and `v128.store`. Other instructions which access memory, such as atomics
+/// and bulk memory operations, are not traced.
+///
+/// ```
+/// use wasm_tools::instrument::memory_trace::MemoryTrace;
+/// use wasm_tools::transform::Transform;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let wasm = wat::parse_str(
+///     r#"
+///         (module
+///             (memory 1)
+///             (func (param i32) (result i32)
+///                 (i32.load (local.get 0))
+///             )
+///         )
+///     "#,
+/// )?;
+/// let instrumented = MemoryTrace::new().range(Some(0..1024)).apply(&wasm)?;
+/// wasmparser::validate(&instrumented.wasm)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTrace {
+    range: Option<Range<u64>>,
+}
+
+impl MemoryTrace {
+    /// Creates a new configuration tracing all accesses by default.
+    pub fn new() -> MemoryTrace {
+        MemoryTrace::default()
+    }
+
+    /// Configures that only accesses whose effective address is within
+    /// `range` are traced.
+    pub fn range(&mut self, range: Option<Range<u64>>) -> &mut Self {
+        self.range = range;
+        self
+    }
+}
+
+impl Transform for MemoryTrace {
+    fn name(&self) -> &str {
+        "memory-trace"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let range = self.range.as_ref().map(|r| (r.start, r.end));
+        let mut instrumenter = Instrumenter::new(wasm, range)?;
+        let mut module = Module::new();
+        instrumenter.parse_core_module(&mut module, Parser::new(0), wasm)?;
+        Ok(Transformed {
+            wasm: module.finish(),
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+struct Instrumenter {
+    range: Option<(u64, u64)>,
+    num_types: u32,
+    num_imported_funcs: u32,
+    /// The number of parameters of each type in the original module.
+    type_params: Vec<u32>,
+    /// The type of each function defined in the original module.
+    func_types: Vec<u32>,
+    /// Whether each memory of the original module is a 64-bit memory.
+    memory64: Vec<bool>,
+    next_func: usize,
+    added_types: bool,
+    added_imports: bool,
+}
+
+/// A memory access performed by an instruction.
+struct Access {
+    memarg: wasmparser::MemArg,
+    size: i32,
+    opcode: i32,
+    /// The type of the value being stored, if this is a store.
+    stored: Option<ValType>,
+}
+
+impl Access {
+    fn new(op: &Operator<'_>) -> Option<Access> {
+        use Operator::*;
+
+        let (memarg, size, opcode, stored) = match *op {
+            I32Load { memarg } => (memarg, 4, 0x28, None),
+            I64Load { memarg } => (memarg, 8, 0x29, None),
+            F32Load { memarg } => (memarg, 4, 0x2a, None),
+            F64Load { memarg } => (memarg, 8, 0x2b, None),
+            I32Load8S { memarg } => (memarg, 1, 0x2c, None),
+            I32Load8U { memarg } => (memarg, 1, 0x2d, None),
+            I32Load16S { memarg } => (memarg, 2, 0x2e, None),
+            I32Load16U { memarg } => (memarg, 2, 0x2f, None),
+            I64Load8S { memarg } => (memarg, 1, 0x30, None),
+            I64Load8U { memarg } => (memarg, 1, 0x31, None),
+            I64Load16S { memarg } => (memarg, 2, 0x32, None),
+            I64Load16U { memarg } => (memarg, 2, 0x33, None),
+            I64Load32S { memarg } => (memarg, 4, 0x34, None),
+            I64Load32U { memarg } => (memarg, 4, 0x35, None),
+            I32Store { memarg } => (memarg, 4, 0x36, Some(ValType::I32)),
+            I64Store { memarg } => (memarg, 8, 0x37, Some(ValType::I64)),
+            F32Store { memarg } => (memarg, 4, 0x38, Some(ValType::F32)),
+            F64Store { memarg } => (memarg, 8, 0x39, Some(ValType::F64)),
+            I32Store8 { memarg } => (memarg, 1, 0x3a, Some(ValType::I32)),
+            I32Store16 { memarg } => (memarg, 2, 0x3b, Some(ValType::I32)),
+            I64Store8 { memarg } => (memarg, 1, 0x3c, Some(ValType::I64)),
+            I64Store16 { memarg } => (memarg, 2, 0x3d, Some(ValType::I64)),
+            I64Store32 { memarg } => (memarg, 4, 0x3e, Some(ValType::I64)),
+            V128Load { memarg } => (memarg, 16, 0xfd00, None),
+            V128Store { memarg } => (memarg, 16, 0xfd0b, Some(ValType::V128)),
+            _ => return None,
+        };
+        Some(Access {
+            memarg,
+            size,
+            opcode,
+            stored,
+        })
+    }
+}
+
+/// A scratch local added to a function to hold operands of an access.
+#[derive(PartialEq, Clone, Copy)]
+enum Scratch {
+    Address(ValType),
+    EffectiveAddress,
+    Value(ValType),
+}
+
+impl Scratch {
+    fn ty(&self) -> ValType {
+        match self {
+            Scratch::Address(ty) | Scratch::Value(ty) => *ty,
+            Scratch::EffectiveAddress => ValType::I64,
+        }
+    }
+}
+
+/// Scratch locals allocated for a function, starting at local index `base`.
+struct ScratchLocals {
+    base: u32,
+    locals: Vec<Scratch>,
+}
+
+impl ScratchLocals {
+    fn local(&mut self, scratch: Scratch) -> u32 {
+        let i = match self.locals.iter().position(|s| *s == scratch) {
+            Some(i) => i,
+            None => {
+                self.locals.push(scratch);
+                self.locals.len() - 1
+            }
+        };
+        self.base + i as u32
+    }
+}
+
+impl Instrumenter {
+    fn new(wasm: &[u8], range: Option<(u64, u64)>) -> Result<Instrumenter> {
+        let mut ret = Instrumenter {
+            range,
+            num_types: 0,
+            num_imported_funcs: 0,
+            type_params: Vec::new(),
+            func_types: Vec::new(),
+            memory64: Vec::new(),
+            next_func: 0,
+            added_types: false,
+            added_imports: false,
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("instrumenting components is not supported"),
+                Payload::TypeSection(s) => {
+                    for group in s {
+                        for ty in group?.into_types() {
+                            let params = match &ty.composite_type.inner {
+                                wasmparser::CompositeInnerType::Func(f) => f.params().len(),
+                                _ => 0,
+                            };
+                            ret.type_params.push(params as u32);
+                        }
+                    }
+                    ret.num_types = ret.type_params.len() as u32;
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        match import?.ty {
+                            TypeRef::Func(_) => ret.num_imported_funcs += 1,
+                            TypeRef::Memory(ty) => ret.memory64.push(ty.memory64),
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        ret.func_types.push(ty?);
+                    }
+                }
+                Payload::MemorySection(s) => {
+                    for ty in s {
+                        ret.memory64.push(ty?.memory64);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the index of the imported `memory-trace.access` function.
+    fn access_func(&self) -> u32 {
+        self.num_imported_funcs
+    }
+
+    fn add_types(&mut self, types: &mut TypeSection) {
+        types
+            .ty()
+            .function([ValType::I64, ValType::I32, ValType::I32], []);
+        self.added_types = true;
+    }
+
+    fn add_imports(&mut self, imports: &mut ImportSection) {
+        let (module, name) = MEMORY_TRACE_IMPORT;
+        imports.import(module, name, EntityType::Function(self.num_types));
+        self.added_imports = true;
+    }
+
+    /// Emits a call to the tracing function for `access`, with its operands on
+    /// top of the stack, and leaves the operands on the stack afterwards.
+    fn trace(&self, f: &mut wasm_encoder::Function, locals: &mut ScratchLocals, access: &Access) {
+        let memory64 = self
+            .memory64
+            .get(access.memarg.memory as usize)
+            .copied()
+            .unwrap_or(false);
+        let address = locals.local(Scratch::Address(if memory64 {
+            ValType::I64
+        } else {
+            ValType::I32
+        }));
+        let value = access.stored.map(|ty| locals.local(Scratch::Value(ty)));
+
+        if let Some(value) = value {
+            f.instruction(&Instruction::LocalSet(value));
+        }
+        f.instruction(&Instruction::LocalTee(address));
+        if !memory64 {
+            f.instruction(&Instruction::I64ExtendI32U);
+        }
+        f.instruction(&Instruction::I64Const(access.memarg.offset as i64));
+        f.instruction(&Instruction::I64Add);
+
+        let call = |f: &mut wasm_encoder::Function| {
+            f.instruction(&Instruction::I32Const(access.size));
+            f.instruction(&Instruction::I32Const(access.opcode));
+            f.instruction(&Instruction::Call(self.access_func()));
+        };
+        match self.range {
+            Some((start, end)) => {
+                let ea = locals.local(Scratch::EffectiveAddress);
+                f.instruction(&Instruction::LocalTee(ea));
+                f.instruction(&Instruction::I64Const(start as i64));
+                f.instruction(&Instruction::I64GeU);
+                f.instruction(&Instruction::LocalGet(ea));
+                f.instruction(&Instruction::I64Const(end as i64));
+                f.instruction(&Instruction::I64LtU);
+                f.instruction(&Instruction::I32And);
+                f.instruction(&Instruction::If(BlockType::Empty));
+                f.instruction(&Instruction::LocalGet(ea));
+                call(f);
+                f.instruction(&Instruction::End);
+            }
+            None => call(f),
+        }
+
+        f.instruction(&Instruction::LocalGet(address));
+        if let Some(value) = value {
+            f.instruction(&Instruction::LocalGet(value));
+        }
+    }
+}
+
+impl Reencode for Instrumenter {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        if func >= self.num_imported_funcs {
+            func + 1
+        } else {
+            func
+        }
+    }
+
+    fn parse_type_section(
+        &mut self,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_type_section(self, types, section)?;
+        self.add_types(types);
+        Ok(())
+    }
+
+    fn parse_import_section(
+        &mut self,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_import_section(self, imports, section)?;
+        self.add_imports(imports);
+        Ok(())
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let ty = self.func_types[self.next_func];
+        self.next_func += 1;
+
+        let mut locals = Vec::new();
+        let mut base = self.type_params[ty as usize];
+        for pair in func.get_locals_reader()? {
+            let (cnt, ty) = pair?;
+            locals.push((cnt, self.val_type(ty)?));
+            base += cnt;
+        }
+
+        // Scratch locals need to be declared before the body, so first
+        // determine which are needed by tracing all accesses into a throwaway
+        // function.
+        let mut scratch = ScratchLocals {
+            base,
+            locals: Vec::new(),
+        };
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            if let Some(access) = Access::new(&reader.read()?) {
+                self.trace(&mut wasm_encoder::Function::new([]), &mut scratch, &access);
+            }
+        }
+        locals.extend(scratch.locals.iter().map(|s| (1, s.ty())));
+
+        let mut f = wasm_encoder::Function::new(locals);
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            let op = reader.read()?;
+            if let Some(access) = Access::new(&op) {
+                self.trace(&mut f, &mut scratch, &access);
+            }
+            f.instruction(&self.instruction(op)?);
+        }
+        code.function(&f);
+        Ok(())
+    }
+
+    fn intersperse_section_hook(
+        &mut self,
+        module: &mut Module,
+        _after: Option<SectionId>,
+        before: Option<SectionId>,
+    ) -> Result<(), Error<Infallible>> {
+        // Insert the type and import sections if the module doesn't have them
+        // once the point where they would have been has passed.
+        let passed = |id| section_passed(before, id);
+        if !self.added_types && passed(SectionId::Type) {
+            let mut types = TypeSection::new();
+            self.add_types(&mut types);
+            module.section(&types);
+        }
+        if !self.added_imports && passed(SectionId::Import) {
+            let mut imports = ImportSection::new();
+            self.add_imports(&mut imports);
+            module.section(&imports);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+//! Passes instrumenting modules to collect information at runtime.
+//!
+//! Each pass is a [`Transform`](crate::transform::Transform) of core wasm
+//! modules which adds the imports, exports, and code that it needs to
+//! collect its information, leaving the behavior of the module otherwise
+//! unchanged.
+
+use wasm_encoder::SectionId;
+
+pub mod coverage;
+pub mod memory_trace;
+pub mod stack_depth;
+
+/// Returns the position of `id` within the order that sections must appear
+/// in a module.
+fn section_order(id: SectionId) -> u32 {
+    match id {
+        SectionId::Custom => 0,
+        SectionId::Type => 1,
+        SectionId::Import => 2,
+        SectionId::Function => 3,
+        SectionId::Table => 4,
+        SectionId::Memory => 5,
+        SectionId::Tag => 6,
+        SectionId::Global => 7,
+        SectionId::Export => 8,
+        SectionId::Start => 9,
+        SectionId::Element => 10,
+        SectionId::DataCount => 11,
+        SectionId::Code => 12,
+        SectionId::Data => 13,
+    }
+}
+
+/// Returns whether the section `id` must have already appeared in a module if
+/// the next section is `before`, where `None` is the end of the module.
+fn section_passed(before: Option<SectionId>, id: SectionId) -> bool {
+    before.map_or(true, |b| section_order(b) > section_order(id))
+}
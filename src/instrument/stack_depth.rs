@@ -0,0 +1,426 @@
+//! Instrumentation limiting how deeply functions of a module can recurse.
+
+use super::section_passed;
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Context, Result};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{
+    BlockType, CodeSection, ConstExpr, EntityType, ExportKind, ExportSection, GlobalSection,
+    GlobalType, ImportSection, Instruction, Module, SectionId, TypeSection, ValType,
+};
+use wasmparser::{Encoding, FunctionBody, Operator, Parser, Payload, TypeRef};
+
+/// Name of the exported global holding the current call depth.
+const STACK_DEPTH_GLOBAL_EXPORT: &str = "__call_depth";
+
+/// Module and name of the imported function called when the limit is hit.
+const STACK_DEPTH_IMPORT: (&str, &str) = ("stack-depth", "exceeded");
+
+/// A [`Transform`] limiting the call depth of a module with a global counter.
+///
+/// A new mutable `i32` global, exported as `__call_depth`, is incremented on
+/// entry to every function and decremented when it returns, including before
+/// tail calls. If the depth is already at the limit when a function is
+/// entered then the function traps instead. With [`StackDepth::import`] an
+/// imported function `stack-depth.exceeded` of type `(func)` is called first,
+/// which can be used by the host to report the error or to throw an exception.
+///
+/// This is intended to protect hosts, such as interpreters, which can't rely on
+/// native stack guards to catch unbounded recursion. Note that the counter is
+/// not decremented when a trap or exception unwinds through a function, so
+/// hosts should reset the exported global after either happens.
+///
+/// ```
+/// use wasm_tools::instrument::stack_depth::StackDepth;
+/// use wasm_tools::transform::Transform;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let wasm = wat::parse_str(
+///     r#"
+///         (module
+///             (func $f (export "f") call $f)
+///         )
+///     "#,
+/// )?;
+/// let instrumented = StackDepth::new().limit(100).apply(&wasm)?;
+/// wasmparser::validate(&instrumented.wasm)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StackDepth {
+    limit: u32,
+    import: bool,
+}
+
+impl StackDepth {
+    /// Creates a new configuration allowing 1024 nested calls by default.
+    pub fn new() -> StackDepth {
+        StackDepth {
+            limit: 1024,
+            import: false,
+        }
+    }
+
+    /// Configures the maximum number of nested calls which are allowed.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Configures whether an imported `stack-depth.exceeded` function is
+    /// called before trapping when the limit is exceeded.
+    pub fn import(&mut self, import: bool) -> &mut Self {
+        self.import = import;
+        self
+    }
+}
+
+impl Default for StackDepth {
+    fn default() -> StackDepth {
+        StackDepth::new()
+    }
+}
+
+impl Transform for StackDepth {
+    fn name(&self) -> &str {
+        "stack-depth"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let mut instrumenter = Instrumenter::new(wasm, self.limit, self.import)?;
+        let mut module = Module::new();
+        instrumenter.parse_core_module(&mut module, Parser::new(0), wasm)?;
+        Ok(Transformed {
+            wasm: module.finish(),
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+struct Instrumenter {
+    limit: u32,
+    import: bool,
+    num_types: u32,
+    num_imported_funcs: u32,
+    num_globals: u32,
+    /// The results of each type in the original module, or `None` if it isn't
+    /// a function type.
+    type_results: Vec<Option<Vec<wasmparser::ValType>>>,
+    /// The type of each function defined in the original module.
+    func_types: Vec<u32>,
+    /// Result types of functions which need a new type to be used as the type
+    /// of a block.
+    block_types: Vec<Vec<wasmparser::ValType>>,
+    next_func: usize,
+    added_types: bool,
+    added_imports: bool,
+    added_globals: bool,
+    added_exports: bool,
+}
+
+impl Instrumenter {
+    fn new(wasm: &[u8], limit: u32, import: bool) -> Result<Instrumenter> {
+        let mut ret = Instrumenter {
+            limit,
+            import,
+            num_types: 0,
+            num_imported_funcs: 0,
+            num_globals: 0,
+            type_results: Vec::new(),
+            func_types: Vec::new(),
+            block_types: Vec::new(),
+            next_func: 0,
+            added_types: false,
+            added_imports: false,
+            added_globals: false,
+            added_exports: false,
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("instrumenting components is not supported"),
+                Payload::TypeSection(s) => {
+                    for group in s {
+                        for ty in group?.into_types() {
+                            let results = match &ty.composite_type.inner {
+                                wasmparser::CompositeInnerType::Func(f) => {
+                                    Some(f.results().to_vec())
+                                }
+                                _ => None,
+                            };
+                            ret.type_results.push(results);
+                        }
+                    }
+                    ret.num_types = ret.type_results.len() as u32;
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        match import?.ty {
+                            TypeRef::Func(_) => ret.num_imported_funcs += 1,
+                            TypeRef::Global(_) => ret.num_globals += 1,
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        ret.func_types.push(ty?);
+                    }
+                }
+                Payload::GlobalSection(s) => ret.num_globals += s.count(),
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        if export?.name == STACK_DEPTH_GLOBAL_EXPORT {
+                            bail!(
+                                "module already has an export named `{STACK_DEPTH_GLOBAL_EXPORT}`"
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for ty in ret.func_types.iter() {
+            let results = ret
+                .type_results
+                .get(*ty as usize)
+                .and_then(|r| r.as_ref())
+                .with_context(|| format!("type {ty} is not a function type"))?;
+            if results.len() > 1 && !ret.block_types.contains(results) {
+                ret.block_types.push(results.clone());
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the index of the global holding the call depth.
+    fn depth_global(&self) -> u32 {
+        self.num_globals
+    }
+
+    /// Returns the index of the imported `stack-depth.exceeded` function.
+    fn exceeded_func(&self) -> u32 {
+        self.num_imported_funcs
+    }
+
+    /// Returns the type of the block wrapping the body of a function of type
+    /// `ty`.
+    fn block_type(&mut self, ty: u32) -> Result<BlockType, Error<Infallible>> {
+        let results = self.type_results[ty as usize].clone().unwrap();
+        Ok(match results.as_slice() {
+            [] => BlockType::Empty,
+            [ty] => BlockType::Result(self.val_type(*ty)?),
+            _ => {
+                let i = self.block_types.iter().position(|r| *r == results).unwrap();
+                BlockType::FunctionType(self.num_types + u32::from(self.import) + i as u32)
+            }
+        })
+    }
+
+    fn add_types(&mut self, types: &mut TypeSection) -> Result<(), Error<Infallible>> {
+        if self.import {
+            types.ty().function([], []);
+        }
+        for results in self.block_types.clone() {
+            let results = results
+                .iter()
+                .map(|ty| self.val_type(*ty))
+                .collect::<Result<Vec<_>, _>>()?;
+            types.ty().function([], results);
+        }
+        self.added_types = true;
+        Ok(())
+    }
+
+    fn add_imports(&mut self, imports: &mut ImportSection) {
+        if self.import {
+            let (module, name) = STACK_DEPTH_IMPORT;
+            imports.import(module, name, EntityType::Function(self.num_types));
+        }
+        self.added_imports = true;
+    }
+
+    fn add_globals(&mut self, globals: &mut GlobalSection) {
+        globals.global(
+            GlobalType {
+                val_type: ValType::I32,
+                mutable: true,
+                shared: false,
+            },
+            &ConstExpr::i32_const(0),
+        );
+        self.added_globals = true;
+    }
+
+    fn add_exports(&mut self, exports: &mut ExportSection) {
+        exports.export(
+            STACK_DEPTH_GLOBAL_EXPORT,
+            ExportKind::Global,
+            self.depth_global(),
+        );
+        self.added_exports = true;
+    }
+
+    fn enter(&self, f: &mut wasm_encoder::Function) {
+        let depth = self.depth_global();
+        f.instruction(&Instruction::GlobalGet(depth));
+        f.instruction(&Instruction::I32Const(self.limit as i32));
+        f.instruction(&Instruction::I32GeU);
+        f.instruction(&Instruction::If(BlockType::Empty));
+        if self.import {
+            f.instruction(&Instruction::Call(self.exceeded_func()));
+        }
+        f.instruction(&Instruction::Unreachable);
+        f.instruction(&Instruction::End);
+        f.instruction(&Instruction::GlobalGet(depth));
+        f.instruction(&Instruction::I32Const(1));
+        f.instruction(&Instruction::I32Add);
+        f.instruction(&Instruction::GlobalSet(depth));
+    }
+
+    fn exit(&self, f: &mut wasm_encoder::Function) {
+        let depth = self.depth_global();
+        f.instruction(&Instruction::GlobalGet(depth));
+        f.instruction(&Instruction::I32Const(1));
+        f.instruction(&Instruction::I32Sub);
+        f.instruction(&Instruction::GlobalSet(depth));
+    }
+}
+
+impl Reencode for Instrumenter {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        if self.import && func >= self.num_imported_funcs {
+            func + 1
+        } else {
+            func
+        }
+    }
+
+    fn parse_type_section(
+        &mut self,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_type_section(self, types, section)?;
+        self.add_types(types)
+    }
+
+    fn parse_import_section(
+        &mut self,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_import_section(self, imports, section)?;
+        self.add_imports(imports);
+        Ok(())
+    }
+
+    fn parse_global_section(
+        &mut self,
+        globals: &mut GlobalSection,
+        section: wasmparser::GlobalSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_global_section(self, globals, section)?;
+        self.add_globals(globals);
+        Ok(())
+    }
+
+    fn parse_export_section(
+        &mut self,
+        exports: &mut ExportSection,
+        section: wasmparser::ExportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_export_section(self, exports, section)?;
+        self.add_exports(exports);
+        Ok(())
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let ty = self.func_types[self.next_func];
+        self.next_func += 1;
+
+        // The body of the function is wrapped in a block so that the depth
+        // can be decremented in one place. Branches to the function's label
+        // now target the block instead, and `return` is replaced with a branch
+        // to it.
+        let mut f = self.new_function_with_parsed_locals(&func)?;
+        self.enter(&mut f);
+        f.instruction(&Instruction::Block(self.block_type(ty)?));
+        let mut reader = func.get_operators_reader()?;
+        let mut depth = 0;
+        while !reader.eof() {
+            let op = reader.read()?;
+            match &op {
+                Operator::Block { .. }
+                | Operator::Loop { .. }
+                | Operator::If { .. }
+                | Operator::Try { .. }
+                | Operator::TryTable { .. } => depth += 1,
+                Operator::End | Operator::Delegate { .. } if depth > 0 => depth -= 1,
+                Operator::End => {
+                    f.instruction(&Instruction::End);
+                    self.exit(&mut f);
+                }
+                Operator::Return => {
+                    f.instruction(&Instruction::Br(depth));
+                    continue;
+                }
+                Operator::ReturnCall { .. }
+                | Operator::ReturnCallIndirect { .. }
+                | Operator::ReturnCallRef { .. } => self.exit(&mut f),
+                _ => {}
+            }
+            f.instruction(&self.instruction(op)?);
+        }
+        code.function(&f);
+        Ok(())
+    }
+
+    fn intersperse_section_hook(
+        &mut self,
+        module: &mut Module,
+        _after: Option<SectionId>,
+        before: Option<SectionId>,
+    ) -> Result<(), Error<Infallible>> {
+        // Insert any sections that the module doesn't have but which are
+        // needed for instrumentation once the point where they would have
+        // been has passed.
+        let passed = |id| section_passed(before, id);
+        if !self.added_types && passed(SectionId::Type) {
+            let mut types = TypeSection::new();
+            self.add_types(&mut types)?;
+            if !types.is_empty() {
+                module.section(&types);
+            }
+        }
+        if !self.added_imports && passed(SectionId::Import) {
+            let mut imports = ImportSection::new();
+            self.add_imports(&mut imports);
+            if !imports.is_empty() {
+                module.section(&imports);
+            }
+        }
+        if !self.added_globals && passed(SectionId::Global) {
+            let mut globals = GlobalSection::new();
+            self.add_globals(&mut globals);
+            module.section(&globals);
+        }
+        if !self.added_exports && passed(SectionId::Export) {
+            let mut exports = ExportSection::new();
+            self.add_exports(&mut exports);
+            module.section(&exports);
+        }
+        Ok(())
+    }
+}
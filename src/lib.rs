@@ -10,6 +10,33 @@ use termcolor::{Ansi, ColorChoice, NoColor, StandardStream, WriteColor};
 
 #[cfg(any(feature = "addr2line", feature = "validate"))]
 pub mod addr2line;
+#[cfg(feature = "analyze")]
+pub mod analysis;
+#[cfg(feature = "branch-hints")]
+pub mod branch_hints;
+#[cfg(feature = "canonicalize")]
+pub mod canonicalize;
+#[cfg(feature = "optimize")]
+pub mod data_segments;
+#[cfg(feature = "exceptions")]
+pub mod exceptions;
+#[cfg(feature = "instrument")]
+pub mod instrument;
+#[cfg(feature = "lower")]
+pub mod lowering;
+#[cfg(feature = "instrument")]
+pub mod nan_canonicalization;
+#[cfg(feature = "optimize")]
+pub mod optimize;
+#[cfg(feature = "relocate")]
+pub mod relocations;
+#[cfg(feature = "split")]
+pub mod splitting;
+#[cfg(feature = "objdump")]
+pub mod tables;
+#[cfg(feature = "transform")]
+pub mod transform;
+pub mod watch;
 
 #[derive(clap::Parser)]
 pub struct GeneralOpts {
@@ -88,6 +115,23 @@ pub struct InputArg {
     /// Shorthand for `--generate-dwarf full`
     #[clap(short, conflicts_with = "generate_dwarf")]
     generate_full_dwarf: bool,
+
+    /// Pad the immediates at these sites in function bodies of WebAssembly
+    /// text files to a fixed width so they can be patched in place later.
+    ///
+    /// By default all integers are encoded with the minimal number of bytes.
+    /// With this option the function indices of calls (`func`), the type and
+    /// table indices of indirect calls (`type` and `table`), the indices of
+    /// globals (`global`), the offsets of loads and stores (`offset`), and the
+    /// values of integer constants (`const`) instead take 5 bytes, or 10 bytes
+    /// for 64-bit values. Like `--generate-dwarf` this has no effect on
+    /// WebAssembly binaries or the `(module binary ...)` form.
+    #[clap(
+        long,
+        value_name = "func|type|table|global|offset|const|all",
+        value_delimiter = ','
+    )]
+    pad_lebs: Vec<PadLebs>,
 }
 
 #[derive(Copy, Clone)]
@@ -108,6 +152,29 @@ impl FromStr for GenerateDwarf {
     }
 }
 
+#[derive(Copy, Clone)]
+enum PadLebs {
+    Site(wat::LebSite),
+    All,
+}
+
+impl FromStr for PadLebs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<PadLebs> {
+        match s {
+            "func" => Ok(PadLebs::Site(wat::LebSite::FuncIndex)),
+            "type" => Ok(PadLebs::Site(wat::LebSite::TypeIndex)),
+            "table" => Ok(PadLebs::Site(wat::LebSite::TableIndex)),
+            "global" => Ok(PadLebs::Site(wat::LebSite::GlobalIndex)),
+            "offset" => Ok(PadLebs::Site(wat::LebSite::MemoryOffset)),
+            "const" => Ok(PadLebs::Site(wat::LebSite::Const)),
+            "all" => Ok(PadLebs::All),
+            other => bail!("unknown `--pad-lebs` site: {other}"),
+        }
+    }
+}
+
 impl InputArg {
     pub fn parse_wasm(&self) -> Result<Vec<u8>> {
         let mut parser = wat::Parser::new();
@@ -120,6 +187,18 @@ impl InputArg {
             }
             (false, None) => {}
         }
+        for pad in self.pad_lebs.iter() {
+            match pad {
+                PadLebs::Site(site) => {
+                    parser.pad_lebs(*site);
+                }
+                PadLebs::All => {
+                    for site in wat::LebSite::ALL {
+                        parser.pad_lebs(site);
+                    }
+                }
+            }
+        }
         if let Some(path) = &self.input {
             if path != Path::new("-") {
                 let bytes = parser.parse_file(path)?;
@@ -190,6 +269,15 @@ impl InputOutput {
         self.input.input.as_deref()
     }
 
+    /// Returns the path of the input file for use with `--watch`, failing if
+    /// the input is read from stdin.
+    pub fn watched_input_path(&self) -> Result<PathBuf> {
+        match self.input_path() {
+            Some(path) if path != Path::new("-") => Ok(path.to_path_buf()),
+            _ => bail!("`--watch` requires an input file instead of stdin"),
+        }
+    }
+
     pub fn general_opts(&self) -> &GeneralOpts {
         &self.general
     }
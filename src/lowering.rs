@@ -0,0 +1,1326 @@
+//! Transforms lowering the use of newer proposals to older equivalents.
+//!
+//! These make it possible to build a single artifact and run it on engines
+//! which don't support all of the proposals that the producing toolchain
+//! targets:
+//!
+//! * Multi-value function results are lowered to returning only the first
+//!   result, with the rest being passed back through scratch globals which
+//!   callers read immediately after the call returns.
+//!
+//! * Tail calls with `return_call`, `return_call_indirect`, and
+//!   `return_call_ref` are lowered to a regular call followed by `return`.
+//!   Note that this means that deep tail recursion may exhaust the stack of
+//!   the engine whereas it wouldn't have originally.
+//!
+//! * 64-bit memories are downgraded to 32-bit memories if they fit in 4 GiB,
+//!   with addresses being wrapped to 32 bits before they're used.
+//!
+//! * Shared memories are made unshared and atomic instructions are replaced
+//!   with their non-atomic equivalents, so that multithreaded builds can be
+//!   tested on engines without support for threads.
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{
+    CodeSection, ConstExpr, DataSection, Function, GlobalSection, GlobalType, Instruction,
+    MemoryType, Module, SectionId,
+};
+use wasmparser::{
+    BlockType, DataKind, Encoding, FuncToValidate, FunctionBody, MemArg, Operator, Parser, Payload,
+    TypeRef, ValType, ValidPayload, Validator, ValidatorResources, WasmFeatures,
+};
+
+/// Configuration of which lowerings to apply to a module.
+#[derive(Default, Debug, Clone)]
+pub struct Lowering {
+    multi_value: bool,
+    return_calls: bool,
+    memory64: bool,
+    threads: bool,
+}
+
+impl Lowering {
+    /// Creates a new configuration with all lowerings disabled.
+    pub fn new() -> Lowering {
+        Lowering::default()
+    }
+
+    /// Configures whether functions with multiple results are lowered.
+    ///
+    /// Functions with multiple results are changed to only return their first
+    /// result, with the remaining results being stored in new mutable globals
+    /// before returning and read back by callers after the call. This applies
+    /// to exported functions as well.
+    ///
+    /// Modules which import functions with multiple results, have branches to
+    /// the outermost label of functions with multiple results, or have blocks
+    /// with parameters or multiple results can't be lowered and produce an
+    /// error.
+    pub fn multi_value(&mut self, enable: bool) -> &mut Self {
+        self.multi_value = enable;
+        self
+    }
+
+    /// Configures whether tail calls are lowered to a call followed by a
+    /// `return`.
+    pub fn return_calls(&mut self, enable: bool) -> &mut Self {
+        self.return_calls = enable;
+        self
+    }
+
+    /// Configures whether 64-bit memories are downgraded to 32-bit memories.
+    ///
+    /// This is a best-effort transform for modules whose memories never
+    /// actually grow beyond 4 GiB. Addresses and lengths are wrapped to 32 bits
+    /// before use and the results of `memory.size` and `memory.grow` are
+    /// extended back to 64 bits, so accesses to addresses beyond 4 GiB wrap
+    /// around instead of trapping. The maximum size of memories is clamped to
+    /// 4 GiB.
+    ///
+    /// Modules which import 64-bit memories, have a 64-bit memory with a
+    /// minimum size larger than 4 GiB, have memory accesses with a static
+    /// offset larger than 4 GiB, or have data segments whose offset isn't a
+    /// constant below 4 GiB can't be downgraded and produce an error
+    /// describing where the problem is.
+    pub fn memory64(&mut self, enable: bool) -> &mut Self {
+        self.memory64 = enable;
+        self
+    }
+
+    /// Configures whether the use of threads is lowered to single-threaded
+    /// equivalents.
+    ///
+    /// Shared memories are made unshared, atomic loads, stores, and
+    /// read-modify-write instructions are replaced by non-atomic sequences
+    /// with the same single-threaded behavior, and `atomic.fence` is removed.
+    /// As no other thread can notify a waiter, `memory.atomic.wait32` and
+    /// `memory.atomic.wait64` return 1 ("not-equal") if the value in memory
+    /// isn't the expected one and 2 ("timed-out") otherwise, without waiting.
+    /// `memory.atomic.notify` always returns 0 waiters woken up.
+    ///
+    /// Note that the non-atomic replacements don't trap on unaligned
+    /// addresses.
+    pub fn threads(&mut self, enable: bool) -> &mut Self {
+        self.threads = enable;
+        self
+    }
+
+    /// Applies the configured lowerings to the core wasm module `wasm`,
+    /// returning the lowered module.
+    pub fn lower(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let mut lowerer = Lowerer::new(self, wasm)?;
+        let mut module = Module::new();
+        lowerer.parse_core_module(&mut module, Parser::new(0), wasm)?;
+        Ok(module.finish())
+    }
+}
+
+impl Transform for Lowering {
+    fn name(&self) -> &str {
+        "lower"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: self.lower(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+struct Lowerer {
+    multi_value: bool,
+    return_calls: bool,
+    memory64: bool,
+    threads: bool,
+    /// Whether each memory in the original module is a 64-bit memory.
+    memories64: Vec<bool>,
+    /// How to downgrade memory accesses in each defined function.
+    memory64_funcs: Vec<Memory64Func>,
+    num_globals: u32,
+    /// Each type in the original module, or `None` if it isn't a function
+    /// type.
+    types: Vec<Option<wasmparser::FuncType>>,
+    /// The type of each function, including imported ones.
+    func_types: Vec<u32>,
+    num_imported_funcs: u32,
+    /// Scratch globals for the results of functions beyond the first. A global
+    /// is identified by its type and how many results of that type precede it
+    /// in a function's results, so that functions with the same kind of
+    /// results share globals.
+    scratch_globals: Vec<(ValType, usize)>,
+    next_func: usize,
+    added_globals: bool,
+}
+
+impl Lowerer {
+    fn new(config: &Lowering, wasm: &[u8]) -> Result<Lowerer> {
+        let mut ret = Lowerer {
+            multi_value: config.multi_value,
+            return_calls: config.return_calls,
+            memory64: config.memory64,
+            threads: config.threads,
+            memories64: Vec::new(),
+            memory64_funcs: Vec::new(),
+            num_globals: 0,
+            types: Vec::new(),
+            func_types: Vec::new(),
+            num_imported_funcs: 0,
+            scratch_globals: Vec::new(),
+            next_func: 0,
+            // Don't add the global section if there are no globals to add.
+            added_globals: !config.multi_value,
+        };
+        // Downgrading memories needs the types of operands, so the module is
+        // validated to figure them out.
+        let mut validator = config
+            .memory64
+            .then(|| Validator::new_with_features(WasmFeatures::all()));
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload?;
+            if let Some(validator) = &mut validator {
+                if let ValidPayload::Func(func, body) = validator.payload(&payload)? {
+                    let func = ret.plan_memory64(func, &body)?;
+                    ret.memory64_funcs.push(func);
+                }
+            }
+            match payload {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("lowering components is not supported"),
+                Payload::TypeSection(s) => {
+                    for group in s {
+                        for ty in group?.into_types() {
+                            let func = match ty.composite_type.inner {
+                                wasmparser::CompositeInnerType::Func(f) => Some(f),
+                                _ => None,
+                            };
+                            ret.types.push(func);
+                        }
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        let import = import?;
+                        match import.ty {
+                            TypeRef::Func(ty) => {
+                                if !ret.extra_results(ty).is_empty() {
+                                    bail!(
+                                        "cannot lower imported function `{}::{}` with multiple \
+                                         results",
+                                        import.module,
+                                        import.name
+                                    );
+                                }
+                                ret.func_types.push(ty);
+                                ret.num_imported_funcs += 1;
+                            }
+                            TypeRef::Global(_) => ret.num_globals += 1,
+                            TypeRef::Memory(ty) => {
+                                if config.memory64 && ty.memory64 {
+                                    bail!(
+                                        "cannot downgrade imported 64-bit memory `{}::{}`",
+                                        import.module,
+                                        import.name
+                                    );
+                                }
+                                ret.memories64.push(ty.memory64);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        ret.func_types.push(ty?);
+                    }
+                }
+                Payload::GlobalSection(s) => ret.num_globals += s.count(),
+                Payload::MemorySection(s) => {
+                    for ty in s {
+                        let ty = ty?;
+                        let index = ret.memories64.len();
+                        if config.memory64 && ty.memory64 && ty.initial > max_memory32_pages(&ty) {
+                            bail!(
+                                "cannot downgrade memory {index} with a minimum size of {} \
+                                 pages which is larger than 4 GiB",
+                                ty.initial
+                            );
+                        }
+                        ret.memories64.push(ty.memory64);
+                    }
+                }
+                Payload::DataSection(s) if config.memory64 => {
+                    for (index, data) in s.into_iter().enumerate() {
+                        let data = data?;
+                        if let DataKind::Active {
+                            memory_index,
+                            offset_expr,
+                        } = &data.kind
+                        {
+                            if ret.is_memory64(*memory_index)
+                                && data_offset32(offset_expr).is_none()
+                            {
+                                bail!(
+                                    "cannot downgrade data segment {index} whose offset isn't \
+                                     a constant below 4 GiB (at offset {:#x})",
+                                    data.range.start
+                                );
+                            }
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) if config.multi_value => {
+                    let func = ret.num_imported_funcs as usize + ret.next_func;
+                    ret.next_func += 1;
+                    ret.check_multi_value(func, &body)?;
+                }
+                _ => {}
+            }
+        }
+        ret.next_func = 0;
+
+        if ret.multi_value {
+            for func in ret.types.iter().flatten() {
+                for global in scratch_globals(func.results()) {
+                    if !ret.scratch_globals.contains(&global) {
+                        if !global.0.is_defaultable() {
+                            bail!("cannot lower multiple results of non-nullable type");
+                        }
+                        ret.scratch_globals.push(global);
+                    }
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the types of results of the function type `ty` beyond the
+    /// first one, which are lowered to globals.
+    fn extra_results(&self, ty: u32) -> &[ValType] {
+        match self.types.get(ty as usize) {
+            Some(Some(func)) if self.multi_value && func.results().len() > 1 => {
+                &func.results()[1..]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Returns the indices of the globals that the results of the function
+    /// type `ty` beyond the first one are stored in.
+    fn result_globals(&self, ty: u32) -> Vec<u32> {
+        let func = match self.types.get(ty as usize) {
+            Some(Some(func)) if !self.extra_results(ty).is_empty() => func,
+            _ => return Vec::new(),
+        };
+        scratch_globals(func.results())
+            .map(|g| {
+                let i = self.scratch_globals.iter().position(|s| *s == g).unwrap();
+                self.num_globals + i as u32
+            })
+            .collect()
+    }
+
+    /// Checks that the body of the function `func` uses multi-value only in
+    /// ways which can be lowered.
+    fn check_multi_value(&self, func: usize, body: &FunctionBody<'_>) -> Result<()> {
+        let has_extra_results = !self.extra_results(self.func_types[func]).is_empty();
+        let mut reader = body.get_operators_reader()?;
+        let mut depth = 0;
+        while !reader.eof() {
+            let (op, offset) = reader.read_with_offset()?;
+            let mut targets = Vec::new();
+            match &op {
+                Operator::Block { blockty }
+                | Operator::Loop { blockty }
+                | Operator::If { blockty }
+                | Operator::Try { blockty } => {
+                    self.check_block_type(*blockty, offset)?;
+                    depth += 1;
+                }
+                Operator::TryTable { try_table } => {
+                    self.check_block_type(try_table.ty, offset)?;
+                    for catch in try_table.catches.iter() {
+                        targets.push(match *catch {
+                            wasmparser::Catch::One { label, .. }
+                            | wasmparser::Catch::OneRef { label, .. }
+                            | wasmparser::Catch::All { label }
+                            | wasmparser::Catch::AllRef { label } => label,
+                        });
+                    }
+                    // Labels of catch clauses are relative to the outside of
+                    // the `try_table` block.
+                    targets.iter_mut().for_each(|t| *t += 1);
+                    depth += 1;
+                }
+                Operator::End | Operator::Delegate { .. } if depth > 0 => depth -= 1,
+                Operator::Br { relative_depth }
+                | Operator::BrIf { relative_depth }
+                | Operator::BrOnNull { relative_depth }
+                | Operator::BrOnNonNull { relative_depth }
+                | Operator::BrOnCast { relative_depth, .. }
+                | Operator::BrOnCastFail { relative_depth, .. } => targets.push(*relative_depth),
+                Operator::BrTable { targets: table } => {
+                    for target in table.targets() {
+                        targets.push(target?);
+                    }
+                    targets.push(table.default());
+                }
+                _ => {}
+            }
+            if has_extra_results && targets.contains(&depth) {
+                bail!(
+                    "cannot lower branch to the outermost label of a function with \
+                     multiple results (at offset {offset:#x})"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn check_block_type(&self, ty: BlockType, offset: usize) -> Result<()> {
+        if let BlockType::FuncType(idx) = ty {
+            let has_params = match self.types.get(idx as usize) {
+                Some(Some(func)) => !func.params().is_empty(),
+                _ => false,
+            };
+            if has_params || !self.extra_results(idx).is_empty() {
+                bail!(
+                    "cannot lower block with parameters or multiple results \
+                     (at offset {offset:#x})"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether `memory` is a 64-bit memory which is being downgraded.
+    fn is_memory64(&self, memory: u32) -> bool {
+        self.memory64
+            && self
+                .memories64
+                .get(memory as usize)
+                .copied()
+                .unwrap_or(false)
+    }
+
+    /// Returns, for each operand of `op` from the bottom of the stack, whether
+    /// it's an address or length in a 64-bit memory, along with an
+    /// instruction converting the result of the downgraded `op` back to 64
+    /// bits if needed. Returns `None` if `op` doesn't use 64-bit memories.
+    fn memory64_operands(
+        &self,
+        op: &Operator<'_>,
+    ) -> Option<(Vec<bool>, Option<Instruction<'static>>)> {
+        let (operands, result) = match *op {
+            Operator::MemorySize { mem } => (
+                vec![],
+                self.is_memory64(mem).then_some(Instruction::I64ExtendI32U),
+            ),
+            Operator::MemoryGrow { mem } => {
+                let is64 = self.is_memory64(mem);
+                (vec![is64], is64.then_some(Instruction::I64ExtendI32S))
+            }
+            Operator::MemoryFill { mem } => {
+                let is64 = self.is_memory64(mem);
+                (vec![is64, false, is64], None)
+            }
+            Operator::MemoryCopy { dst_mem, src_mem } => {
+                let dst = self.is_memory64(dst_mem);
+                let src = self.is_memory64(src_mem);
+                (vec![dst, src, dst && src], None)
+            }
+            Operator::MemoryInit { mem, .. } => (vec![self.is_memory64(mem), false, false], None),
+            Operator::MemoryDiscard { mem } => {
+                let is64 = self.is_memory64(mem);
+                (vec![is64, is64], None)
+            }
+            _ => {
+                let (memarg, values) = memarg_operands(op)?;
+                let mut operands = vec![self.is_memory64(memarg.memory)];
+                operands.extend((0..values).map(|_| false));
+                (operands, None)
+            }
+        };
+        if operands.iter().any(|o| *o) || result.is_some() {
+            Some((operands, result))
+        } else {
+            None
+        }
+    }
+
+    /// Validates the body of a function to determine the types of the operands
+    /// of instructions using 64-bit memories, and checks that they can be
+    /// downgraded.
+    fn plan_memory64(
+        &self,
+        func: FuncToValidate<ValidatorResources>,
+        body: &FunctionBody<'_>,
+    ) -> Result<Memory64Func> {
+        let mut validator = func.into_validator(Default::default());
+        let mut locals = body.get_locals_reader()?;
+        for _ in 0..locals.get_count() {
+            let offset = locals.original_position();
+            let (count, ty) = locals.read()?;
+            validator.define_locals(offset, count, ty)?;
+        }
+        let mut ret = Memory64Func {
+            operands: HashMap::new(),
+        };
+        let mut reader = body.get_operators_reader()?;
+        while !reader.eof() {
+            let (op, offset) = reader.read_with_offset()?;
+            if let Some((memarg, _)) = memarg_operands(&op) {
+                if self.is_memory64(memarg.memory) && memarg.offset > u64::from(u32::MAX) {
+                    bail!(
+                        "cannot downgrade memory access with a static offset larger than 4 GiB \
+                         (at offset {offset:#x})"
+                    );
+                }
+            }
+            if let Some((operands, _)) = self.memory64_operands(&op) {
+                let n = operands.len();
+                let types = (0..n)
+                    .map(|i| validator.get_operand_type(n - 1 - i).flatten())
+                    .collect::<Vec<_>>();
+                ret.operands.insert(offset, types);
+            }
+            validator.op(offset, &op)?;
+        }
+        Ok(ret)
+    }
+
+    /// Returns the types of the operands of `op` which need to be spilled to
+    /// wrap its addresses when downgrading 64-bit memories, or `None` if the
+    /// types aren't known because the code is unreachable.
+    fn memory64_spills(
+        &self,
+        func: usize,
+        op: &Operator<'_>,
+        offset: usize,
+    ) -> Option<Vec<ValType>> {
+        let (operands, _) = self.memory64_operands(op)?;
+        let lowest = operands.iter().position(|o| *o)?;
+        let types = &self.memory64_funcs[func].operands[&offset];
+        types[lowest + 1..].iter().copied().collect()
+    }
+
+    /// Emits the downgraded version of `op`, which uses a 64-bit memory, for
+    /// the function `func`.
+    fn downgrade_memory64(
+        &mut self,
+        f: &mut Function,
+        scratch: &ScratchLocals,
+        func: usize,
+        op: Operator<'_>,
+        offset: usize,
+    ) -> Result<(), Error<Infallible>> {
+        let (operands, result) = self.memory64_operands(&op).unwrap();
+        if let Some(lowest) = operands.iter().position(|o| *o) {
+            match self.memory64_spills(func, &op, offset) {
+                // Spill all operands above the lowest address to wrap it, and
+                // then wrap any other addresses while restoring them.
+                Some(spilled) => {
+                    let locals = scratch.get(spilled);
+                    for local in locals.iter().rev() {
+                        f.instruction(&Instruction::LocalSet(*local));
+                    }
+                    f.instruction(&Instruction::I32WrapI64);
+                    for (local, is64) in locals.iter().zip(&operands[lowest + 1..]) {
+                        f.instruction(&Instruction::LocalGet(*local));
+                        if *is64 {
+                            f.instruction(&Instruction::I32WrapI64);
+                        }
+                    }
+                }
+                // If the types of operands aren't known then this is
+                // unreachable code, so make sure that validation doesn't
+                // depend on them.
+                None => {
+                    f.instruction(&Instruction::Unreachable);
+                }
+            }
+        }
+        self.lower_memory_op(f, scratch, op)?;
+        if let Some(result) = result {
+            f.instruction(&result);
+        }
+        Ok(())
+    }
+
+    /// Returns the type of addresses in `memory` after lowering.
+    fn address_type(&self, memory: u32) -> ValType {
+        let memory64 = self
+            .memories64
+            .get(memory as usize)
+            .copied()
+            .unwrap_or(false);
+        if memory64 && !self.memory64 {
+            ValType::I64
+        } else {
+            ValType::I32
+        }
+    }
+
+    /// Returns how `op` is lowered if it's an atomic instruction and threads
+    /// are being lowered.
+    fn atomic(&mut self, op: &Operator<'_>) -> Option<Atomic> {
+        if !self.threads {
+            return None;
+        }
+        use Operator::*;
+        use RmwOp::*;
+        use ValType::{I32, I64};
+
+        let (memarg, ty, bits, kind) = match *op {
+            AtomicFence => return Some(Atomic::Fence),
+            MemoryAtomicNotify { .. } => return Some(Atomic::Notify),
+            MemoryAtomicWait32 { memarg } => (memarg, I32, 32, AtomicKind::Wait),
+            MemoryAtomicWait64 { memarg } => (memarg, I64, 64, AtomicKind::Wait),
+            I32AtomicLoad { memarg } => (memarg, I32, 32, AtomicKind::Load),
+            I64AtomicLoad { memarg } => (memarg, I64, 64, AtomicKind::Load),
+            I32AtomicLoad8U { memarg } => (memarg, I32, 8, AtomicKind::Load),
+            I32AtomicLoad16U { memarg } => (memarg, I32, 16, AtomicKind::Load),
+            I64AtomicLoad8U { memarg } => (memarg, I64, 8, AtomicKind::Load),
+            I64AtomicLoad16U { memarg } => (memarg, I64, 16, AtomicKind::Load),
+            I64AtomicLoad32U { memarg } => (memarg, I64, 32, AtomicKind::Load),
+            I32AtomicStore { memarg } => (memarg, I32, 32, AtomicKind::Store),
+            I64AtomicStore { memarg } => (memarg, I64, 64, AtomicKind::Store),
+            I32AtomicStore8 { memarg } => (memarg, I32, 8, AtomicKind::Store),
+            I32AtomicStore16 { memarg } => (memarg, I32, 16, AtomicKind::Store),
+            I64AtomicStore8 { memarg } => (memarg, I64, 8, AtomicKind::Store),
+            I64AtomicStore16 { memarg } => (memarg, I64, 16, AtomicKind::Store),
+            I64AtomicStore32 { memarg } => (memarg, I64, 32, AtomicKind::Store),
+            I32AtomicRmwAdd { memarg } => (memarg, I32, 32, AtomicKind::Rmw(Add)),
+            I64AtomicRmwAdd { memarg } => (memarg, I64, 64, AtomicKind::Rmw(Add)),
+            I32AtomicRmw8AddU { memarg } => (memarg, I32, 8, AtomicKind::Rmw(Add)),
+            I32AtomicRmw16AddU { memarg } => (memarg, I32, 16, AtomicKind::Rmw(Add)),
+            I64AtomicRmw8AddU { memarg } => (memarg, I64, 8, AtomicKind::Rmw(Add)),
+            I64AtomicRmw16AddU { memarg } => (memarg, I64, 16, AtomicKind::Rmw(Add)),
+            I64AtomicRmw32AddU { memarg } => (memarg, I64, 32, AtomicKind::Rmw(Add)),
+            I32AtomicRmwSub { memarg } => (memarg, I32, 32, AtomicKind::Rmw(Sub)),
+            I64AtomicRmwSub { memarg } => (memarg, I64, 64, AtomicKind::Rmw(Sub)),
+            I32AtomicRmw8SubU { memarg } => (memarg, I32, 8, AtomicKind::Rmw(Sub)),
+            I32AtomicRmw16SubU { memarg } => (memarg, I32, 16, AtomicKind::Rmw(Sub)),
+            I64AtomicRmw8SubU { memarg } => (memarg, I64, 8, AtomicKind::Rmw(Sub)),
+            I64AtomicRmw16SubU { memarg } => (memarg, I64, 16, AtomicKind::Rmw(Sub)),
+            I64AtomicRmw32SubU { memarg } => (memarg, I64, 32, AtomicKind::Rmw(Sub)),
+            I32AtomicRmwAnd { memarg } => (memarg, I32, 32, AtomicKind::Rmw(And)),
+            I64AtomicRmwAnd { memarg } => (memarg, I64, 64, AtomicKind::Rmw(And)),
+            I32AtomicRmw8AndU { memarg } => (memarg, I32, 8, AtomicKind::Rmw(And)),
+            I32AtomicRmw16AndU { memarg } => (memarg, I32, 16, AtomicKind::Rmw(And)),
+            I64AtomicRmw8AndU { memarg } => (memarg, I64, 8, AtomicKind::Rmw(And)),
+            I64AtomicRmw16AndU { memarg } => (memarg, I64, 16, AtomicKind::Rmw(And)),
+            I64AtomicRmw32AndU { memarg } => (memarg, I64, 32, AtomicKind::Rmw(And)),
+            I32AtomicRmwOr { memarg } => (memarg, I32, 32, AtomicKind::Rmw(Or)),
+            I64AtomicRmwOr { memarg } => (memarg, I64, 64, AtomicKind::Rmw(Or)),
+            I32AtomicRmw8OrU { memarg } => (memarg, I32, 8, AtomicKind::Rmw(Or)),
+            I32AtomicRmw16OrU { memarg } => (memarg, I32, 16, AtomicKind::Rmw(Or)),
+            I64AtomicRmw8OrU { memarg } => (memarg, I64, 8, AtomicKind::Rmw(Or)),
+            I64AtomicRmw16OrU { memarg } => (memarg, I64, 16, AtomicKind::Rmw(Or)),
+            I64AtomicRmw32OrU { memarg } => (memarg, I64, 32, AtomicKind::Rmw(Or)),
+            I32AtomicRmwXor { memarg } => (memarg, I32, 32, AtomicKind::Rmw(Xor)),
+            I64AtomicRmwXor { memarg } => (memarg, I64, 64, AtomicKind::Rmw(Xor)),
+            I32AtomicRmw8XorU { memarg } => (memarg, I32, 8, AtomicKind::Rmw(Xor)),
+            I32AtomicRmw16XorU { memarg } => (memarg, I32, 16, AtomicKind::Rmw(Xor)),
+            I64AtomicRmw8XorU { memarg } => (memarg, I64, 8, AtomicKind::Rmw(Xor)),
+            I64AtomicRmw16XorU { memarg } => (memarg, I64, 16, AtomicKind::Rmw(Xor)),
+            I64AtomicRmw32XorU { memarg } => (memarg, I64, 32, AtomicKind::Rmw(Xor)),
+            I32AtomicRmwXchg { memarg } => (memarg, I32, 32, AtomicKind::Rmw(Xchg)),
+            I64AtomicRmwXchg { memarg } => (memarg, I64, 64, AtomicKind::Rmw(Xchg)),
+            I32AtomicRmw8XchgU { memarg } => (memarg, I32, 8, AtomicKind::Rmw(Xchg)),
+            I32AtomicRmw16XchgU { memarg } => (memarg, I32, 16, AtomicKind::Rmw(Xchg)),
+            I64AtomicRmw8XchgU { memarg } => (memarg, I64, 8, AtomicKind::Rmw(Xchg)),
+            I64AtomicRmw16XchgU { memarg } => (memarg, I64, 16, AtomicKind::Rmw(Xchg)),
+            I64AtomicRmw32XchgU { memarg } => (memarg, I64, 32, AtomicKind::Rmw(Xchg)),
+            I32AtomicRmwCmpxchg { memarg } => (memarg, I32, 32, AtomicKind::Cmpxchg),
+            I64AtomicRmwCmpxchg { memarg } => (memarg, I64, 64, AtomicKind::Cmpxchg),
+            I32AtomicRmw8CmpxchgU { memarg } => (memarg, I32, 8, AtomicKind::Cmpxchg),
+            I32AtomicRmw16CmpxchgU { memarg } => (memarg, I32, 16, AtomicKind::Cmpxchg),
+            I64AtomicRmw8CmpxchgU { memarg } => (memarg, I64, 8, AtomicKind::Cmpxchg),
+            I64AtomicRmw16CmpxchgU { memarg } => (memarg, I64, 16, AtomicKind::Cmpxchg),
+            I64AtomicRmw32CmpxchgU { memarg } => (memarg, I64, 32, AtomicKind::Cmpxchg),
+            _ => return None,
+        };
+        let address = self.address_type(memarg.memory);
+        let memarg = self.mem_arg(memarg);
+        let (load, store) = match (ty, bits) {
+            (I32, 8) => (
+                Instruction::I32Load8U(memarg),
+                Instruction::I32Store8(memarg),
+            ),
+            (I32, 16) => (
+                Instruction::I32Load16U(memarg),
+                Instruction::I32Store16(memarg),
+            ),
+            (I32, _) => (Instruction::I32Load(memarg), Instruction::I32Store(memarg)),
+            (_, 8) => (
+                Instruction::I64Load8U(memarg),
+                Instruction::I64Store8(memarg),
+            ),
+            (_, 16) => (
+                Instruction::I64Load16U(memarg),
+                Instruction::I64Store16(memarg),
+            ),
+            (_, 32) => (
+                Instruction::I64Load32U(memarg),
+                Instruction::I64Store32(memarg),
+            ),
+            (_, _) => (Instruction::I64Load(memarg), Instruction::I64Store(memarg)),
+        };
+        Some(Atomic::Memory {
+            kind,
+            ty,
+            bits,
+            address,
+            load,
+            store,
+        })
+    }
+
+    /// Emits `op`, which accesses memory, lowering it if it's atomic.
+    fn lower_memory_op(
+        &mut self,
+        f: &mut Function,
+        scratch: &ScratchLocals,
+        op: Operator<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        match self.atomic(&op) {
+            Some(atomic) => atomic.lower(f, scratch),
+            None => {
+                f.instruction(&self.instruction(op)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_globals(&mut self, globals: &mut GlobalSection) -> Result<(), Error<Infallible>> {
+        for (ty, _) in self.scratch_globals.clone() {
+            let init = match ty {
+                ValType::I32 => ConstExpr::i32_const(0),
+                ValType::I64 => ConstExpr::i64_const(0),
+                ValType::F32 => ConstExpr::f32_const(0.0),
+                ValType::F64 => ConstExpr::f64_const(0.0),
+                ValType::V128 => ConstExpr::v128_const(0),
+                ValType::Ref(r) => ConstExpr::ref_null(self.heap_type(r.heap_type())?),
+            };
+            globals.global(
+                GlobalType {
+                    val_type: self.val_type(ty)?,
+                    mutable: true,
+                    shared: false,
+                },
+                &init,
+            );
+        }
+        self.added_globals = true;
+        Ok(())
+    }
+
+    /// Emits instructions reading the extra results of a call to a function
+    /// of type `ty` from their globals.
+    fn after_call(&self, f: &mut Function, ty: u32) {
+        for global in self.result_globals(ty) {
+            f.instruction(&Instruction::GlobalGet(global));
+        }
+    }
+
+    /// Emits instructions storing the extra results of the current function
+    /// of type `ty` into their globals before returning.
+    fn before_return(&self, f: &mut Function, ty: u32) {
+        for global in self.result_globals(ty).into_iter().rev() {
+            f.instruction(&Instruction::GlobalSet(global));
+        }
+    }
+
+    /// Emits a lowered tail call from the current function of type `ty` to a
+    /// function of type `callee`, where `call` is the equivalent non-tail call.
+    fn return_call(&self, f: &mut Function, call: &Instruction<'_>, callee: u32, ty: u32) {
+        f.instruction(call);
+        // Callers' results are usually the same as the callee's, in which case
+        // the extra results are already in the right globals.
+        if self.result_globals(callee) != self.result_globals(ty) {
+            self.after_call(f, callee);
+            self.before_return(f, ty);
+        }
+        f.instruction(&Instruction::Return);
+    }
+}
+
+/// How to downgrade the memory accesses of a function using 64-bit memories.
+struct Memory64Func {
+    /// The types of the operands, from the bottom of the stack, of each
+    /// instruction which uses a 64-bit memory, keyed by its offset. Types are
+    /// `None` in unreachable code.
+    operands: HashMap<usize, Vec<Option<ValType>>>,
+}
+
+/// Scratch locals added to a function to temporarily hold values.
+///
+/// Each local is identified by its type and how many other locals of that
+/// type are needed at the same time, so that locals are shared between all
+/// of the instructions needing them.
+struct ScratchLocals {
+    /// The index of the first scratch local.
+    base: u32,
+    slots: Vec<(ValType, usize)>,
+}
+
+impl ScratchLocals {
+    /// Reserves locals to simultaneously hold values of `types`.
+    fn reserve(&mut self, types: Vec<ValType>) {
+        for slot in scratch_slots(types) {
+            if !self.slots.contains(&slot) {
+                self.slots.push(slot);
+            }
+        }
+    }
+
+    /// Returns the locals reserved for values of `types`.
+    fn get(&self, types: Vec<ValType>) -> Vec<u32> {
+        scratch_slots(types)
+            .map(|slot| {
+                let i = self.slots.iter().position(|s| *s == slot).unwrap();
+                self.base + i as u32
+            })
+            .collect()
+    }
+}
+
+fn scratch_slots(types: Vec<ValType>) -> impl Iterator<Item = (ValType, usize)> {
+    let mut counts = HashMap::new();
+    types.into_iter().map(move |ty| {
+        let count = counts.entry(ty).or_insert(0);
+        *count += 1;
+        (ty, *count - 1)
+    })
+}
+
+/// The lowering of an atomic instruction.
+enum Atomic {
+    Fence,
+    Notify,
+    Memory {
+        kind: AtomicKind,
+        /// The type of values operated on.
+        ty: ValType,
+        /// The width in bits of the memory accessed.
+        bits: u32,
+        /// The type of the address operand.
+        address: ValType,
+        /// The non-atomic load of the memory accessed.
+        load: Instruction<'static>,
+        /// The non-atomic store of the memory accessed.
+        store: Instruction<'static>,
+    },
+}
+
+enum AtomicKind {
+    Load,
+    Store,
+    Rmw(RmwOp),
+    Cmpxchg,
+    Wait,
+}
+
+#[derive(Clone, Copy)]
+enum RmwOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Xchg,
+}
+
+impl Atomic {
+    /// Returns the types of the scratch locals needed to lower this
+    /// instruction.
+    fn scratch(&self) -> Vec<ValType> {
+        match self {
+            Atomic::Fence | Atomic::Notify => vec![],
+            Atomic::Memory {
+                kind, ty, address, ..
+            } => match kind {
+                AtomicKind::Load | AtomicKind::Store => vec![],
+                AtomicKind::Rmw(_) => vec![*address, *ty, *ty],
+                AtomicKind::Cmpxchg => vec![*address, *ty, *ty, *ty],
+                AtomicKind::Wait => vec![*ty],
+            },
+        }
+    }
+
+    fn lower(self, f: &mut Function, scratch: &ScratchLocals) {
+        let locals = scratch.get(self.scratch());
+        let (kind, ty, bits, load, store) = match self {
+            Atomic::Fence => return,
+            Atomic::Notify => {
+                f.instruction(&Instruction::Drop);
+                f.instruction(&Instruction::Drop);
+                f.instruction(&Instruction::I32Const(0));
+                return;
+            }
+            Atomic::Memory {
+                kind,
+                ty,
+                bits,
+                load,
+                store,
+                ..
+            } => (kind, ty, bits, load, store),
+        };
+        let is32 = ty == ValType::I32;
+        match kind {
+            AtomicKind::Load => {
+                f.instruction(&load);
+            }
+            AtomicKind::Store => {
+                f.instruction(&store);
+            }
+            AtomicKind::Rmw(op) => {
+                let [address, value, old] = locals[..] else {
+                    unreachable!()
+                };
+                f.instruction(&Instruction::LocalSet(value));
+                f.instruction(&Instruction::LocalTee(address));
+                f.instruction(&Instruction::LocalGet(address));
+                f.instruction(&load);
+                f.instruction(&Instruction::LocalTee(old));
+                let op = match (op, is32) {
+                    (RmwOp::Add, true) => Some(Instruction::I32Add),
+                    (RmwOp::Sub, true) => Some(Instruction::I32Sub),
+                    (RmwOp::And, true) => Some(Instruction::I32And),
+                    (RmwOp::Or, true) => Some(Instruction::I32Or),
+                    (RmwOp::Xor, true) => Some(Instruction::I32Xor),
+                    (RmwOp::Add, false) => Some(Instruction::I64Add),
+                    (RmwOp::Sub, false) => Some(Instruction::I64Sub),
+                    (RmwOp::And, false) => Some(Instruction::I64And),
+                    (RmwOp::Or, false) => Some(Instruction::I64Or),
+                    (RmwOp::Xor, false) => Some(Instruction::I64Xor),
+                    (RmwOp::Xchg, _) => None,
+                };
+                match op {
+                    Some(op) => {
+                        f.instruction(&Instruction::LocalGet(value));
+                        f.instruction(&op);
+                    }
+                    None => {
+                        f.instruction(&Instruction::Drop);
+                        f.instruction(&Instruction::LocalGet(value));
+                    }
+                }
+                f.instruction(&store);
+                f.instruction(&Instruction::LocalGet(old));
+            }
+            AtomicKind::Cmpxchg => {
+                let [address, expected, replacement, old] = locals[..] else {
+                    unreachable!()
+                };
+                f.instruction(&Instruction::LocalSet(replacement));
+                f.instruction(&Instruction::LocalSet(expected));
+                f.instruction(&Instruction::LocalTee(address));
+                f.instruction(&load);
+                f.instruction(&Instruction::LocalTee(old));
+                f.instruction(&Instruction::LocalGet(expected));
+                // The expected value is wrapped to the width of the memory
+                // accessed before it's compared.
+                let mask = (1u64 << bits).wrapping_sub(1);
+                match (is32, bits) {
+                    (true, 32) | (false, 64) => {}
+                    (true, _) => {
+                        f.instruction(&Instruction::I32Const(mask as i32));
+                        f.instruction(&Instruction::I32And);
+                    }
+                    (false, _) => {
+                        f.instruction(&Instruction::I64Const(mask as i64));
+                        f.instruction(&Instruction::I64And);
+                    }
+                }
+                f.instruction(if is32 {
+                    &Instruction::I32Eq
+                } else {
+                    &Instruction::I64Eq
+                });
+                f.instruction(&Instruction::If(wasm_encoder::BlockType::Empty));
+                f.instruction(&Instruction::LocalGet(address));
+                f.instruction(&Instruction::LocalGet(replacement));
+                f.instruction(&store);
+                f.instruction(&Instruction::End);
+                f.instruction(&Instruction::LocalGet(old));
+            }
+            AtomicKind::Wait => {
+                let [expected] = locals[..] else {
+                    unreachable!()
+                };
+                // Nothing can notify a waiter, so the result is "not-equal"
+                // (1) or "timed-out" (2) depending on the value in memory.
+                f.instruction(&Instruction::Drop);
+                f.instruction(&Instruction::LocalSet(expected));
+                f.instruction(&load);
+                f.instruction(&Instruction::LocalGet(expected));
+                f.instruction(if is32 {
+                    &Instruction::I32Eq
+                } else {
+                    &Instruction::I64Eq
+                });
+                f.instruction(&Instruction::I32Const(1));
+                f.instruction(&Instruction::I32Add);
+            }
+        }
+    }
+}
+
+/// Returns the number of 32-bit memory pages of `ty` which fit in 4 GiB.
+fn max_memory32_pages(ty: &wasmparser::MemoryType) -> u64 {
+    1 << (32 - ty.page_size_log2.unwrap_or(16))
+}
+
+/// Returns the offset of an active data segment in a 64-bit memory as a
+/// 32-bit offset, if it's a constant which fits.
+fn data_offset32(expr: &wasmparser::ConstExpr<'_>) -> Option<u32> {
+    let mut reader = expr.get_operators_reader();
+    let value = match reader.read().ok()? {
+        Operator::I64Const { value } => u32::try_from(value).ok()?,
+        _ => return None,
+    };
+    match reader.read().ok()? {
+        Operator::End if reader.eof() => Some(value),
+        _ => None,
+    }
+}
+
+/// Returns the memory argument of `op` if it accesses memory through one,
+/// along with the number of operands it takes above the address.
+fn memarg_operands(op: &Operator<'_>) -> Option<(MemArg, usize)> {
+    use Operator::*;
+
+    Some(match *op {
+        I32Load { memarg }
+        | I64Load { memarg }
+        | F32Load { memarg }
+        | F64Load { memarg }
+        | I32Load8S { memarg }
+        | I32Load8U { memarg }
+        | I32Load16S { memarg }
+        | I32Load16U { memarg }
+        | I64Load8S { memarg }
+        | I64Load8U { memarg }
+        | I64Load16S { memarg }
+        | I64Load16U { memarg }
+        | I64Load32S { memarg }
+        | I64Load32U { memarg }
+        | I32AtomicLoad { memarg }
+        | I64AtomicLoad { memarg }
+        | I32AtomicLoad8U { memarg }
+        | I32AtomicLoad16U { memarg }
+        | I64AtomicLoad8U { memarg }
+        | I64AtomicLoad16U { memarg }
+        | I64AtomicLoad32U { memarg }
+        | V128Load { memarg }
+        | V128Load8x8S { memarg }
+        | V128Load8x8U { memarg }
+        | V128Load16x4S { memarg }
+        | V128Load16x4U { memarg }
+        | V128Load32x2S { memarg }
+        | V128Load32x2U { memarg }
+        | V128Load8Splat { memarg }
+        | V128Load16Splat { memarg }
+        | V128Load32Splat { memarg }
+        | V128Load64Splat { memarg }
+        | V128Load32Zero { memarg }
+        | V128Load64Zero { memarg } => (memarg, 0),
+
+        I32Store { memarg }
+        | I64Store { memarg }
+        | F32Store { memarg }
+        | F64Store { memarg }
+        | I32Store8 { memarg }
+        | I32Store16 { memarg }
+        | I64Store8 { memarg }
+        | I64Store16 { memarg }
+        | I64Store32 { memarg }
+        | I32AtomicStore { memarg }
+        | I64AtomicStore { memarg }
+        | I32AtomicStore8 { memarg }
+        | I32AtomicStore16 { memarg }
+        | I64AtomicStore8 { memarg }
+        | I64AtomicStore16 { memarg }
+        | I64AtomicStore32 { memarg }
+        | I32AtomicRmwAdd { memarg }
+        | I64AtomicRmwAdd { memarg }
+        | I32AtomicRmw8AddU { memarg }
+        | I32AtomicRmw16AddU { memarg }
+        | I64AtomicRmw8AddU { memarg }
+        | I64AtomicRmw16AddU { memarg }
+        | I64AtomicRmw32AddU { memarg }
+        | I32AtomicRmwSub { memarg }
+        | I64AtomicRmwSub { memarg }
+        | I32AtomicRmw8SubU { memarg }
+        | I32AtomicRmw16SubU { memarg }
+        | I64AtomicRmw8SubU { memarg }
+        | I64AtomicRmw16SubU { memarg }
+        | I64AtomicRmw32SubU { memarg }
+        | I32AtomicRmwAnd { memarg }
+        | I64AtomicRmwAnd { memarg }
+        | I32AtomicRmw8AndU { memarg }
+        | I32AtomicRmw16AndU { memarg }
+        | I64AtomicRmw8AndU { memarg }
+        | I64AtomicRmw16AndU { memarg }
+        | I64AtomicRmw32AndU { memarg }
+        | I32AtomicRmwOr { memarg }
+        | I64AtomicRmwOr { memarg }
+        | I32AtomicRmw8OrU { memarg }
+        | I32AtomicRmw16OrU { memarg }
+        | I64AtomicRmw8OrU { memarg }
+        | I64AtomicRmw16OrU { memarg }
+        | I64AtomicRmw32OrU { memarg }
+        | I32AtomicRmwXor { memarg }
+        | I64AtomicRmwXor { memarg }
+        | I32AtomicRmw8XorU { memarg }
+        | I32AtomicRmw16XorU { memarg }
+        | I64AtomicRmw8XorU { memarg }
+        | I64AtomicRmw16XorU { memarg }
+        | I64AtomicRmw32XorU { memarg }
+        | I32AtomicRmwXchg { memarg }
+        | I64AtomicRmwXchg { memarg }
+        | I32AtomicRmw8XchgU { memarg }
+        | I32AtomicRmw16XchgU { memarg }
+        | I64AtomicRmw8XchgU { memarg }
+        | I64AtomicRmw16XchgU { memarg }
+        | I64AtomicRmw32XchgU { memarg }
+        | MemoryAtomicNotify { memarg }
+        | V128Store { memarg }
+        | V128Load8Lane { memarg, .. }
+        | V128Load16Lane { memarg, .. }
+        | V128Load32Lane { memarg, .. }
+        | V128Load64Lane { memarg, .. }
+        | V128Store8Lane { memarg, .. }
+        | V128Store16Lane { memarg, .. }
+        | V128Store32Lane { memarg, .. }
+        | V128Store64Lane { memarg, .. } => (memarg, 1),
+
+        I32AtomicRmwCmpxchg { memarg }
+        | I64AtomicRmwCmpxchg { memarg }
+        | I32AtomicRmw8CmpxchgU { memarg }
+        | I32AtomicRmw16CmpxchgU { memarg }
+        | I64AtomicRmw8CmpxchgU { memarg }
+        | I64AtomicRmw16CmpxchgU { memarg }
+        | I64AtomicRmw32CmpxchgU { memarg }
+        | MemoryAtomicWait32 { memarg }
+        | MemoryAtomicWait64 { memarg } => (memarg, 2),
+
+        _ => return None,
+    })
+}
+
+/// Returns the scratch globals used for `results` beyond the first.
+fn scratch_globals(results: &[ValType]) -> impl Iterator<Item = (ValType, usize)> + '_ {
+    results
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, ty)| (*ty, results[1..i].iter().filter(|t| *t == ty).count()))
+}
+
+impl Reencode for Lowerer {
+    type Error = Infallible;
+
+    fn func_type(
+        &mut self,
+        func_ty: wasmparser::FuncType,
+    ) -> Result<wasm_encoder::FuncType, Error<Infallible>> {
+        if !self.multi_value || func_ty.results().len() <= 1 {
+            return utils::func_type(self, func_ty);
+        }
+        let params = func_ty
+            .params()
+            .iter()
+            .map(|ty| self.val_type(*ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.val_type(func_ty.results()[0])?;
+        Ok(wasm_encoder::FuncType::new(params, [result]))
+    }
+
+    fn memory_type(&mut self, memory_ty: wasmparser::MemoryType) -> MemoryType {
+        let mut ret = utils::memory_type(self, memory_ty);
+        if self.threads {
+            ret.shared = false;
+        }
+        if self.memory64 && memory_ty.memory64 {
+            let max = max_memory32_pages(&memory_ty);
+            ret.memory64 = false;
+            ret.maximum = ret.maximum.map(|m| m.min(max));
+        }
+        ret
+    }
+
+    fn parse_data(
+        &mut self,
+        data: &mut DataSection,
+        datum: wasmparser::Data<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        match &datum.kind {
+            DataKind::Active {
+                memory_index,
+                offset_expr,
+            } if self.is_memory64(*memory_index) => {
+                let offset = data_offset32(offset_expr).unwrap();
+                data.active(
+                    self.memory_index(*memory_index),
+                    &ConstExpr::i32_const(offset as i32),
+                    datum.data.iter().copied(),
+                );
+                Ok(())
+            }
+            _ => utils::parse_data(self, data, datum),
+        }
+    }
+
+    fn parse_global_section(
+        &mut self,
+        globals: &mut GlobalSection,
+        section: wasmparser::GlobalSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_global_section(self, globals, section)?;
+        self.add_globals(globals)
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let index = self.next_func;
+        let ty = self.func_types[self.num_imported_funcs as usize + index];
+        self.next_func += 1;
+
+        let mut locals = Vec::new();
+        let mut scratch = ScratchLocals {
+            base: self.types[ty as usize].as_ref().unwrap().params().len() as u32,
+            slots: Vec::new(),
+        };
+        for pair in func.get_locals_reader()? {
+            let (cnt, ty) = pair?;
+            locals.push((cnt, self.val_type(ty)?));
+            scratch.base += cnt;
+        }
+
+        // Scratch locals need to be declared before the body, so first
+        // determine which are needed.
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            let (op, offset) = reader.read_with_offset()?;
+            if self.memory64 {
+                if let Some(spilled) = self.memory64_spills(index, &op, offset) {
+                    scratch.reserve(spilled);
+                }
+            }
+            if let Some(atomic) = self.atomic(&op) {
+                scratch.reserve(atomic.scratch());
+            }
+        }
+        for (ty, _) in scratch.slots.clone() {
+            locals.push((1, self.val_type(ty)?));
+        }
+
+        let mut f = Function::new(locals);
+        let mut reader = func.get_operators_reader()?;
+        let mut depth = 0;
+        while !reader.eof() {
+            let (op, offset) = reader.read_with_offset()?;
+            if self.memory64 && self.memory64_operands(&op).is_some() {
+                self.downgrade_memory64(&mut f, &scratch, index, op, offset)?;
+                continue;
+            }
+            if self.atomic(&op).is_some() {
+                self.lower_memory_op(&mut f, &scratch, op)?;
+                continue;
+            }
+            match op {
+                Operator::Call { function_index } => {
+                    f.instruction(&self.instruction(op)?);
+                    self.after_call(&mut f, self.func_types[function_index as usize]);
+                }
+                Operator::CallIndirect { type_index, .. } | Operator::CallRef { type_index } => {
+                    f.instruction(&self.instruction(op)?);
+                    self.after_call(&mut f, type_index);
+                }
+                Operator::ReturnCall { function_index } if self.return_calls => {
+                    let call = self.instruction(Operator::Call { function_index })?;
+                    let callee = self.func_types[function_index as usize];
+                    self.return_call(&mut f, &call, callee, ty);
+                }
+                Operator::ReturnCallIndirect {
+                    type_index,
+                    table_index,
+                } if self.return_calls => {
+                    let call = self.instruction(Operator::CallIndirect {
+                        type_index,
+                        table_index,
+                    })?;
+                    self.return_call(&mut f, &call, type_index, ty);
+                }
+                Operator::ReturnCallRef { type_index } if self.return_calls => {
+                    let call = self.instruction(Operator::CallRef { type_index })?;
+                    self.return_call(&mut f, &call, type_index, ty);
+                }
+                Operator::Block { .. }
+                | Operator::Loop { .. }
+                | Operator::If { .. }
+                | Operator::Try { .. }
+                | Operator::TryTable { .. } => {
+                    depth += 1;
+                    f.instruction(&self.instruction(op)?);
+                }
+                Operator::End | Operator::Delegate { .. } if depth > 0 => {
+                    depth -= 1;
+                    f.instruction(&self.instruction(op)?);
+                }
+                Operator::End | Operator::Return => {
+                    self.before_return(&mut f, ty);
+                    f.instruction(&self.instruction(op)?);
+                }
+                _ => {
+                    f.instruction(&self.instruction(op)?);
+                }
+            }
+        }
+        code.function(&f);
+        Ok(())
+    }
+
+    fn intersperse_section_hook(
+        &mut self,
+        module: &mut Module,
+        _after: Option<SectionId>,
+        before: Option<SectionId>,
+    ) -> Result<(), Error<Infallible>> {
+        // Add a global section for the scratch globals if the module doesn't
+        // have one once the point where it would have been has passed.
+        let passed = match before {
+            Some(id) => matches!(
+                id,
+                SectionId::Export
+                    | SectionId::Start
+                    | SectionId::Element
+                    | SectionId::DataCount
+                    | SectionId::Code
+                    | SectionId::Data
+            ),
+            None => true,
+        };
+        if !self.added_globals && passed {
+            let mut globals = GlobalSection::new();
+            self.add_globals(&mut globals)?;
+            if !globals.is_empty() {
+                module.section(&globals);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Transform to make floating-point results deterministic across engines.
+//!
+//! WebAssembly leaves the sign and payload bits of NaNs produced by arithmetic
+//! instructions nondeterministic, so modules which inspect the bits of floats,
+//! for example through `reinterpret` or stores to memory, may behave
+//! differently on different engines. This transform inserts a sequence after
+//! every instruction which may produce a nondeterministic NaN which replaces
+//! any NaN with the canonical NaN of its type, making execution deterministic.
+//! This is commonly required by blockchain and replay systems.
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{Error, Reencode};
+use wasm_encoder::{CodeSection, Function, Instruction, Module, ValType};
+use wasmparser::{Encoding, FunctionBody, Operator, Parser, Payload};
+
+/// Bits of the canonical NaN for `f32`.
+const CANONICAL_NAN_F32: u32 = 0x7fc0_0000;
+
+/// Bits of the canonical NaN for `f64`.
+const CANONICAL_NAN_F64: u64 = 0x7ff8_0000_0000_0000;
+
+/// Canonicalizes NaNs produced by all floating-point instructions in the core
+/// wasm module `wasm`, returning the transformed module.
+///
+/// Scalar instructions are followed by a `select` of the canonical NaN when
+/// their result isn't equal to itself, and SIMD instructions by a
+/// `v128.bitselect` doing the same per lane. Instructions which only move or
+/// manipulate the sign of floats, such as `f32.neg` or `f64.reinterpret_i64`,
+/// are deterministic and are left as-is.
+pub fn canonicalize_nans(wasm: &[u8]) -> Result<Vec<u8>> {
+    let mut canonicalizer = NanCanonicalizer::new(wasm)?;
+    let mut module = Module::new();
+    canonicalizer.parse_core_module(&mut module, Parser::new(0), wasm)?;
+    Ok(module.finish())
+}
+
+/// A [`Transform`] applying [`canonicalize_nans`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanonicalizeNans;
+
+impl Transform for CanonicalizeNans {
+    fn name(&self) -> &str {
+        "canonicalize-nans"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: canonicalize_nans(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+/// The shape of the float values produced by an instruction.
+#[derive(Clone, Copy)]
+enum Shape {
+    F32,
+    F64,
+    F32x4,
+    F64x2,
+}
+
+impl Shape {
+    /// Returns the shape of the result of `op` if it may produce a
+    /// nondeterministic NaN.
+    fn of(op: &Operator<'_>) -> Option<Shape> {
+        use Operator::*;
+
+        Some(match op {
+            F32Add | F32Sub | F32Mul | F32Div | F32Sqrt | F32Min | F32Max | F32Ceil | F32Floor
+            | F32Trunc | F32Nearest | F32DemoteF64 => Shape::F32,
+            F64Add | F64Sub | F64Mul | F64Div | F64Sqrt | F64Min | F64Max | F64Ceil | F64Floor
+            | F64Trunc | F64Nearest | F64PromoteF32 => Shape::F64,
+            F32x4Add | F32x4Sub | F32x4Mul | F32x4Div | F32x4Sqrt | F32x4Min | F32x4Max
+            | F32x4Ceil | F32x4Floor | F32x4Trunc | F32x4Nearest | F32x4DemoteF64x2Zero
+            | F32x4RelaxedMadd | F32x4RelaxedNmadd | F32x4RelaxedMin | F32x4RelaxedMax => {
+                Shape::F32x4
+            }
+            F64x2Add | F64x2Sub | F64x2Mul | F64x2Div | F64x2Sqrt | F64x2Min | F64x2Max
+            | F64x2Ceil | F64x2Floor | F64x2Trunc | F64x2Nearest | F64x2PromoteLowF32x4
+            | F64x2RelaxedMadd | F64x2RelaxedNmadd | F64x2RelaxedMin | F64x2RelaxedMax => {
+                Shape::F64x2
+            }
+            _ => return None,
+        })
+    }
+
+    fn val_type(&self) -> ValType {
+        match self {
+            Shape::F32 => ValType::F32,
+            Shape::F64 => ValType::F64,
+            Shape::F32x4 | Shape::F64x2 => ValType::V128,
+        }
+    }
+
+    /// Emits the sequence replacing a NaN on top of the stack with the
+    /// canonical NaN, using `local` as a temporary.
+    fn canonicalize(&self, f: &mut Function, local: u32) {
+        f.instruction(&Instruction::LocalTee(local));
+        f.instruction(&match self {
+            Shape::F32 => Instruction::F32Const(f32::from_bits(CANONICAL_NAN_F32)),
+            Shape::F64 => Instruction::F64Const(f64::from_bits(CANONICAL_NAN_F64)),
+            Shape::F32x4 => {
+                let lane = u128::from(CANONICAL_NAN_F32);
+                Instruction::V128Const((lane | lane << 32 | lane << 64 | lane << 96) as i128)
+            }
+            Shape::F64x2 => {
+                let lane = u128::from(CANONICAL_NAN_F64);
+                Instruction::V128Const((lane | lane << 64) as i128)
+            }
+        });
+        f.instruction(&Instruction::LocalGet(local));
+        f.instruction(&Instruction::LocalGet(local));
+        match self {
+            Shape::F32 => {
+                f.instruction(&Instruction::F32Eq);
+                f.instruction(&Instruction::Select);
+            }
+            Shape::F64 => {
+                f.instruction(&Instruction::F64Eq);
+                f.instruction(&Instruction::Select);
+            }
+            Shape::F32x4 => {
+                f.instruction(&Instruction::F32x4Eq);
+                f.instruction(&Instruction::V128Bitselect);
+            }
+            Shape::F64x2 => {
+                f.instruction(&Instruction::F64x2Eq);
+                f.instruction(&Instruction::V128Bitselect);
+            }
+        }
+    }
+}
+
+struct NanCanonicalizer {
+    /// The number of parameters of each type in the original module.
+    type_params: Vec<u32>,
+    /// The type of each function defined in the original module.
+    func_types: Vec<u32>,
+    next_func: usize,
+}
+
+impl NanCanonicalizer {
+    fn new(wasm: &[u8]) -> Result<NanCanonicalizer> {
+        let mut ret = NanCanonicalizer {
+            type_params: Vec::new(),
+            func_types: Vec::new(),
+            next_func: 0,
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("canonicalizing NaNs in components is not supported"),
+                Payload::TypeSection(s) => {
+                    for group in s {
+                        for ty in group?.into_types() {
+                            let params = match &ty.composite_type.inner {
+                                wasmparser::CompositeInnerType::Func(f) => f.params().len(),
+                                _ => 0,
+                            };
+                            ret.type_params.push(params as u32);
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        ret.func_types.push(ty?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Reencode for NanCanonicalizer {
+    type Error = Infallible;
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let ty = self.func_types[self.next_func];
+        self.next_func += 1;
+
+        let mut locals = Vec::new();
+        let mut num_locals = self.type_params[ty as usize];
+        for pair in func.get_locals_reader()? {
+            let (cnt, ty) = pair?;
+            locals.push((cnt, self.val_type(ty)?));
+            num_locals += cnt;
+        }
+
+        // Temporaries are only declared for the types which are needed, so
+        // scan the body first to find out which those are.
+        let mut temps: Vec<ValType> = Vec::new();
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            if let Some(shape) = Shape::of(&reader.read()?) {
+                if !temps.contains(&shape.val_type()) {
+                    temps.push(shape.val_type());
+                }
+            }
+        }
+        locals.extend(temps.iter().map(|ty| (1, *ty)));
+
+        let mut f = Function::new(locals);
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            let op = reader.read()?;
+            let shape = Shape::of(&op);
+            f.instruction(&self.instruction(op)?);
+            if let Some(shape) = shape {
+                let temp = temps.iter().position(|ty| *ty == shape.val_type()).unwrap();
+                shape.canonicalize(&mut f, num_locals + temp as u32);
+            }
+        }
+        code.function(&f);
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Conservative size optimizations of modules.
+//!
+//! This isn't a replacement for a full optimizer such as `wasm-opt`, but
+//! rather a baseline of peephole rewrites which are always safe to apply and
+//! never make a module larger. Each function body is rewritten in a single
+//! pass over its instructions, and all integers of the module are reencoded as
+//! LEB128 with their minimal width along the way.
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode};
+use wasm_encoder::{CodeSection, Instruction, Module};
+use wasmparser::{Encoding, FunctionBody, Operator, Parser, Payload, TypeRef};
+
+/// Applies size optimizations to the core wasm module `wasm`, returning the
+/// transformed module.
+///
+/// The optimizations applied are:
+///
+/// * `local.set $x` followed by `local.get $x` becomes `local.tee $x`, and
+///   `local.tee $x` followed by `drop` becomes `local.set $x`,
+/// * `local.tee $x` followed by `local.set $x` or `local.tee $x` becomes just
+///   the latter, and `local.get $x` followed by `local.set $x` is removed,
+/// * `local.get` or a numeric constant followed by `drop` is removed,
+/// * integer constants of zero followed by an instruction for which zero is
+///   the identity on the right, such as `i32.add` or `i64.shl`, are removed,
+/// * code following an instruction which never falls through, such as
+///   `unreachable`, `br`, or `return`, is removed up to the end of its
+///   enclosing block, and
+/// * all integers are encoded as LEB128 with their minimal width.
+///
+/// Entries of the `name` section for labels of removed blocks are removed as
+/// well, and the remaining labels are renumbered.
+pub fn optimize_size(wasm: &[u8]) -> Result<Vec<u8>> {
+    let mut optimizer = SizeOptimizer::new(wasm)?;
+    let mut module = Module::new();
+    optimizer.parse_core_module(&mut module, Parser::new(0), wasm)?;
+    Ok(module.finish())
+}
+
+/// A [`Transform`] applying [`optimize_size`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OptimizeSize;
+
+impl Transform for OptimizeSize {
+    fn name(&self) -> &str {
+        "optimize-size"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: optimize_size(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
+
+struct SizeOptimizer {
+    /// The index of the next function whose body is parsed.
+    next_func: u32,
+    /// The labels of each function which were removed as dead code, in
+    /// increasing order.
+    removed_labels: HashMap<u32, Vec<u32>>,
+}
+
+impl SizeOptimizer {
+    fn new(wasm: &[u8]) -> Result<SizeOptimizer> {
+        let mut ret = SizeOptimizer {
+            next_func: 0,
+            removed_labels: HashMap::new(),
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("optimizing components is not supported"),
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        if let TypeRef::Func(_) = import?.ty {
+                            ret.next_func += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Reencode for SizeOptimizer {
+    type Error = Infallible;
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let index = self.next_func;
+        self.next_func += 1;
+
+        let mut body = Vec::new();
+        let mut removed_labels = Vec::new();
+        let mut next_label = 0;
+        // The number of blocks entered within dead code, if the current
+        // instruction is dead.
+        let mut dead: Option<u32> = None;
+        let mut reader = func.get_operators_reader()?;
+        while !reader.eof() {
+            let op = reader.read()?;
+            let starts_block = matches!(
+                op,
+                Operator::Block { .. }
+                    | Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Try { .. }
+                    | Operator::TryTable { .. }
+            );
+            if let Some(depth) = &mut dead {
+                match op {
+                    _ if starts_block => {
+                        removed_labels.push(next_label);
+                        next_label += 1;
+                        *depth += 1;
+                        continue;
+                    }
+                    Operator::End | Operator::Delegate { .. } if *depth > 0 => {
+                        *depth -= 1;
+                        continue;
+                    }
+                    Operator::End
+                    | Operator::Delegate { .. }
+                    | Operator::Else
+                    | Operator::Catch { .. }
+                    | Operator::CatchAll
+                        if *depth == 0 =>
+                    {
+                        dead = None;
+                    }
+                    _ => continue,
+                }
+            }
+            if starts_block {
+                next_label += 1;
+            }
+            let diverges = matches!(
+                op,
+                Operator::Unreachable
+                    | Operator::Br { .. }
+                    | Operator::BrTable { .. }
+                    | Operator::Return
+                    | Operator::ReturnCall { .. }
+                    | Operator::ReturnCallIndirect { .. }
+                    | Operator::ReturnCallRef { .. }
+                    | Operator::Throw { .. }
+                    | Operator::ThrowRef
+                    | Operator::Rethrow { .. }
+            );
+            push(&mut body, self.instruction(op)?);
+            if diverges {
+                dead = Some(0);
+            }
+        }
+
+        let mut f = self.new_function_with_parsed_locals(&func)?;
+        for inst in body {
+            f.instruction(&inst);
+        }
+        code.function(&f);
+        if !removed_labels.is_empty() {
+            self.removed_labels.insert(index, removed_labels);
+        }
+        Ok(())
+    }
+
+    fn parse_custom_name_subsection(
+        &mut self,
+        names: &mut wasm_encoder::NameSection,
+        section: wasmparser::Name<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let map = match section {
+            wasmparser::Name::Label(map) => map,
+            _ => return utils::parse_custom_name_subsection(self, names, section),
+        };
+        let mut labels = wasm_encoder::IndirectNameMap::new();
+        for naming in map {
+            let naming = naming?;
+            let removed = self
+                .removed_labels
+                .get(&naming.index)
+                .map(|r| &r[..])
+                .unwrap_or(&[]);
+            let mut func_labels = wasm_encoder::NameMap::new();
+            for label in naming.names {
+                let label = label?;
+                match removed.binary_search(&label.index) {
+                    Ok(_) => {}
+                    Err(i) => func_labels.append(label.index - i as u32, label.name),
+                }
+            }
+            labels.append(naming.index, &func_labels);
+        }
+        names.labels(&labels);
+        Ok(())
+    }
+}
+
+/// Pushes `inst` onto the end of `body`, combining it with the preceding
+/// instructions where possible.
+fn push<'a>(body: &mut Vec<Instruction<'a>>, inst: Instruction<'a>) {
+    use Instruction::*;
+
+    match (body.last(), &inst) {
+        (Some(&LocalSet(a)), &LocalGet(b)) if a == b => {
+            body.pop();
+            push(body, LocalTee(a));
+        }
+        (Some(&LocalTee(a)), Drop) => {
+            body.pop();
+            push(body, LocalSet(a));
+        }
+        (Some(&LocalTee(a)), &LocalSet(b)) if a == b => {
+            body.pop();
+            push(body, LocalSet(a));
+        }
+        (Some(&LocalTee(a)), &LocalTee(b)) if a == b => {}
+        (Some(&LocalGet(a)), &LocalSet(b)) if a == b => {
+            body.pop();
+        }
+        (Some(LocalGet(_) | I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_)), Drop) => {
+            body.pop();
+        }
+        (
+            Some(I32Const(0)),
+            I32Add | I32Sub | I32Or | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr,
+        )
+        | (
+            Some(I64Const(0)),
+            I64Add | I64Sub | I64Or | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr,
+        ) => {
+            body.pop();
+        }
+        _ => body.push(inst),
+    }
+}
@@ -0,0 +1,798 @@
+//! Applying and verifying the relocations of relocatable object files.
+//!
+//! Object files following the [linking convention] record in `reloc.*` custom
+//! sections the places where their code and data refer to symbols, such as
+//! the index of a called function or the address of a global variable.
+//! [`ApplyRelocations`] rewrites these places with the values of the symbols
+//! given by a [`SymbolValues`] map, and [`verify_relocations`] checks that a
+//! module holds the values of the symbols at all of these places.
+//!
+//! [linking convention]: https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md
+
+use crate::transform::{Transform, Transformed};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::ops::Range;
+use wasm_encoder::{CustomSection, Encode, RawSection, Section};
+use wasmparser::{
+    BinaryReader, ConstExpr, Encoding, KnownCustom, Linking, Operator, Parser, Payload,
+    RelocAddendKind, RelocationEntry, RelocationType, SymbolInfo, TypeRef,
+};
+
+/// Section id of the code section.
+const CODE_SECTION: u8 = 10;
+/// Section id of the data section.
+const DATA_SECTION: u8 = 11;
+
+/// The values which symbols of an object file resolve to.
+///
+/// A symbol is identified by its name. Its value is the index of the
+/// function, global, table, or tag that it refers to, or the address of a data
+/// symbol. Function symbols may additionally have a table slot, which is the
+/// value of relocations taking the address of the function.
+///
+/// Symbols without a value resolve to what they refer to in the object file
+/// itself: their own index, or for a defined data symbol its offset within
+/// its segment plus the constant offset of that segment.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolValues {
+    values: HashMap<String, u64>,
+    table_slots: HashMap<String, u64>,
+}
+
+impl SymbolValues {
+    /// Creates an empty map.
+    pub fn new() -> SymbolValues {
+        SymbolValues::default()
+    }
+
+    /// Parses a map in its text format.
+    ///
+    /// Each line of the text gives the name of a symbol followed by its value
+    /// and, optionally, its table slot, separated by whitespace. Integers may
+    /// be written in hexadecimal with a `0x` prefix. Empty lines and comments,
+    /// from `#` to the end of a line, are ignored.
+    pub fn parse(text: &str) -> Result<SymbolValues> {
+        let mut values = SymbolValues::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.is_empty() {
+                continue;
+            }
+            let mut parse = || -> Result<()> {
+                match fields[..] {
+                    [name, value] => {
+                        values.set(name, parse_int(value)?);
+                    }
+                    [name, value, slot] => {
+                        values.set(name, parse_int(value)?);
+                        values.set_table_slot(name, parse_int(slot)?);
+                    }
+                    _ => bail!("expected 2 or 3 fields, found {}", fields.len()),
+                }
+                Ok(())
+            };
+            parse().with_context(|| format!("invalid symbol line {}", i + 1))?;
+        }
+        Ok(values)
+    }
+
+    /// Sets the value of the symbol `name`.
+    pub fn set(&mut self, name: &str, value: u64) -> &mut Self {
+        self.values.insert(name.to_string(), value);
+        self
+    }
+
+    /// Sets the table slot of the function symbol `name`.
+    pub fn set_table_slot(&mut self, name: &str, slot: u64) -> &mut Self {
+        self.table_slots.insert(name.to_string(), slot);
+        self
+    }
+
+    /// Returns the value of the symbol `name`, if any.
+    pub fn value(&self, name: &str) -> Option<u64> {
+        self.values.get(name).copied()
+    }
+
+    /// Returns the table slot of the function symbol `name`, if any.
+    pub fn table_slot(&self, name: &str) -> Option<u64> {
+        self.table_slots.get(name).copied()
+    }
+}
+
+fn parse_int(s: &str) -> Result<u64> {
+    let ret = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    ret.with_context(|| format!("invalid integer `{s}`"))
+}
+
+/// A [`Transform`] which applies the relocations of the code and data
+/// sections of an object file using the values of a [`SymbolValues`] map.
+///
+/// Relocated LEB128 values keep the width they had in the input, which is 5
+/// or 10 bytes in object files produced by LLVM, and are widened if the new
+/// value doesn't fit in it. Widening a value moves the code and data after it,
+/// so the sizes of function bodies and data segments, and the offsets of the
+/// relocations themselves, are updated accordingly. Offsets into the code or
+/// data sections held by other sections aren't updated, which is reported as
+/// a diagnostic.
+///
+/// By default the `linking` and `reloc.*` sections are kept, with their
+/// offsets updated, so that the output can still be checked with
+/// [`verify_relocations`] or relocated again.
+pub struct ApplyRelocations {
+    symbols: SymbolValues,
+    strip: bool,
+}
+
+impl ApplyRelocations {
+    /// Creates a transform applying relocations with the values of `symbols`.
+    pub fn new(symbols: SymbolValues) -> ApplyRelocations {
+        ApplyRelocations {
+            symbols,
+            strip: false,
+        }
+    }
+
+    /// Whether to remove the `linking` and `reloc.*` sections once the
+    /// relocations are applied, which defaults to `false`.
+    pub fn strip_relocations(&mut self, strip: bool) -> &mut Self {
+        self.strip = strip;
+        self
+    }
+}
+
+impl Transform for ApplyRelocations {
+    fn name(&self) -> &str {
+        "relocate"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let object = Object::new(wasm)?;
+        let mut diagnostics = Vec::new();
+
+        // Relocate the code and data sections, remembering the new offsets of
+        // their relocations.
+        let mut relocated = HashMap::new();
+        let mut new_offsets = HashMap::new();
+        let mut applied = 0;
+        let mut widened = 0;
+        for (reloc_section, relocs) in object.relocs.iter().enumerate() {
+            let target = reloc_section_target(&object, relocs)?;
+            let section = &object.sections[target];
+            if !matches!(section.id, CODE_SECTION | DATA_SECTION) {
+                diagnostics.push(format!(
+                    "relocations of section {} were not applied",
+                    relocs.target
+                ));
+                continue;
+            }
+            let input = &wasm[section.range.clone()];
+            let mut output = Relocated::new(input);
+            let mut entries = relocs.entries.iter().enumerate().collect::<Vec<_>>();
+            entries.sort_by_key(|(_, entry)| entry.offset);
+            let pieces = match section.id {
+                CODE_SECTION => code_pieces(input)?,
+                _ => data_pieces(input)?,
+            };
+            let mut entries = entries.into_iter().peekable();
+            for piece in pieces {
+                let in_piece = |(_, entry): &(usize, &RelocationEntry)| {
+                    piece.contents.contains(&(entry.offset as usize))
+                };
+                let Some(size) = piece.size else {
+                    while let Some((i, entry)) = entries.next_if(in_piece) {
+                        let value = object.value(&self.symbols, entry)?;
+                        let (offset, wide) =
+                            output.patch(entry.offset as usize, entry.ty, value)?;
+                        new_offsets.insert((reloc_section, i), offset);
+                        widened += usize::from(wide);
+                        applied += 1;
+                    }
+                    continue;
+                };
+
+                // Relocate the contents on their own first since the size
+                // prefix depends on them.
+                let base = piece.contents.start;
+                let mut contents = Relocated::new(&input[piece.contents.clone()]);
+                let mut moved = Vec::new();
+                while let Some((i, entry)) = entries.next_if(in_piece) {
+                    let value = object.value(&self.symbols, entry)?;
+                    let offset = entry.offset as usize - base;
+                    let (offset, wide) = contents.patch(offset, entry.ty, value)?;
+                    moved.push((i, offset));
+                    widened += usize::from(wide);
+                    applied += 1;
+                }
+                let contents = contents.finish();
+                output.copy_to(size.start)?;
+                let len = u64::try_from(contents.len()).unwrap();
+                let (_, wide) = write_leb(len, false, &input[size.clone()], &mut output.output)?;
+                widened += usize::from(wide);
+                let start = output.output.len();
+                for (i, offset) in moved {
+                    new_offsets.insert((reloc_section, i), start + offset);
+                }
+                output.output.extend_from_slice(&contents);
+                output.pos = piece.contents.end;
+            }
+            if let Some((_, entry)) = entries.next() {
+                bail!(
+                    "relocation at offset {:#x} of section {} is out of bounds",
+                    entry.offset,
+                    relocs.target
+                );
+            }
+            relocated.insert(target, output.finish());
+        }
+
+        let mut output = wasm_encoder::Module::HEADER.to_vec();
+        for (index, section) in object.sections.iter().enumerate() {
+            if let Some(data) = relocated.get(&index) {
+                RawSection {
+                    id: section.id,
+                    data,
+                }
+                .append_to(&mut output);
+                continue;
+            }
+            if section.id == 0 {
+                let is_reloc = section.reloc.is_some();
+                if self.strip && (is_reloc || section.name == "linking") {
+                    continue;
+                }
+                if let Some(reloc_section) = section.reloc {
+                    let relocs = &object.relocs[reloc_section];
+                    if relocated.contains_key(&reloc_section_target(&object, relocs)?) {
+                        let mut data = Vec::new();
+                        relocs.target.encode(&mut data);
+                        relocs.entries.len().encode(&mut data);
+                        for (i, entry) in relocs.entries.iter().enumerate() {
+                            let offset = new_offsets[&(reloc_section, i)];
+                            encode_entry(entry, u32::try_from(offset)?, &mut data);
+                        }
+                        CustomSection {
+                            name: section.name.into(),
+                            data: data.into(),
+                        }
+                        .append_to(&mut output);
+                        continue;
+                    }
+                }
+            }
+            RawSection {
+                id: section.id,
+                data: &wasm[section.range.clone()],
+            }
+            .append_to(&mut output);
+        }
+
+        diagnostics.insert(0, format!("applied {applied} relocations"));
+        if widened > 0 {
+            diagnostics.push(format!(
+                "widened {widened} relocated values, so offsets into the code and data sections held by \
+                 other sections may be stale"
+            ));
+        }
+        Ok(Transformed {
+            wasm: output,
+            diagnostics,
+        })
+    }
+}
+
+/// Checks that the code and data of the object file `wasm` hold the values of
+/// `symbols` at the places described by its relocations, returning the number
+/// of relocations checked.
+///
+/// Relocations of sections other than the code and data sections aren't
+/// checked.
+pub fn verify_relocations(wasm: &[u8], symbols: &SymbolValues) -> Result<usize> {
+    let object = Object::new(wasm)?;
+    let mut checked = 0;
+    for relocs in object.relocs.iter() {
+        let section = &object.sections[reloc_section_target(&object, relocs)?];
+        if !matches!(section.id, CODE_SECTION | DATA_SECTION) {
+            continue;
+        }
+        let contents = &wasm[section.range.clone()];
+        for entry in relocs.entries.iter() {
+            let expected = object.value(symbols, entry)?;
+            let site = contents
+                .get(entry.offset as usize..)
+                .filter(|site| !site.is_empty())
+                .with_context(|| {
+                    format!(
+                        "relocation at offset {:#x} of section {} is out of bounds",
+                        entry.offset, relocs.target
+                    )
+                })?;
+            let found = read_value(entry.ty, site).with_context(|| {
+                format!(
+                    "failed to read relocated value at offset {:#x} of section {}",
+                    entry.offset, relocs.target
+                )
+            })?;
+            if found != expected {
+                bail!(
+                    "relocation {:?} at offset {:#x} of section {} holds {found:#x} instead of \
+                     {expected:#x}",
+                    entry.ty,
+                    entry.offset,
+                    relocs.target
+                );
+            }
+            checked += 1;
+        }
+    }
+    Ok(checked)
+}
+
+/// A section of an object file.
+struct ObjectSection<'a> {
+    id: u8,
+    /// The range of the contents of the section within the file.
+    range: Range<usize>,
+    /// The name of a custom section.
+    name: &'a str,
+    /// The index within [`Object::relocs`] of a `reloc.*` section.
+    reloc: Option<usize>,
+}
+
+/// The relocations of a `reloc.*` section.
+struct Relocs {
+    /// The index of the section which these relocations apply to.
+    target: u32,
+    entries: Vec<RelocationEntry>,
+}
+
+/// The parts of an object file which relocations depend on.
+struct Object<'a> {
+    sections: Vec<ObjectSection<'a>>,
+    relocs: Vec<Relocs>,
+    symbols: Vec<SymbolInfo<'a>>,
+    /// The names of the imported functions, globals, tables, and tags, in
+    /// that order, which are the names of undefined symbols without an
+    /// explicit name.
+    func_imports: Vec<&'a str>,
+    global_imports: Vec<&'a str>,
+    table_imports: Vec<&'a str>,
+    tag_imports: Vec<&'a str>,
+    /// The constant offset of each data segment, if any.
+    segment_offsets: Vec<Option<u64>>,
+}
+
+impl<'a> Object<'a> {
+    fn new(wasm: &'a [u8]) -> Result<Object<'a>> {
+        let mut ret = Object {
+            sections: Vec::new(),
+            relocs: Vec::new(),
+            symbols: Vec::new(),
+            func_imports: Vec::new(),
+            global_imports: Vec::new(),
+            table_imports: Vec::new(),
+            tag_imports: Vec::new(),
+            segment_offsets: Vec::new(),
+        };
+        let mut linking = false;
+        for payload in Parser::new(0).parse_all(wasm) {
+            let payload = payload?;
+            let mut name = "";
+            let mut reloc = None;
+            match &payload {
+                Payload::Version { encoding, .. } if *encoding != Encoding::Module => {
+                    bail!("relocations are only supported for core modules")
+                }
+                Payload::ImportSection(s) => {
+                    for import in s.clone() {
+                        let import = import?;
+                        match import.ty {
+                            TypeRef::Func(_) => ret.func_imports.push(import.name),
+                            TypeRef::Global(_) => ret.global_imports.push(import.name),
+                            TypeRef::Table(_) => ret.table_imports.push(import.name),
+                            TypeRef::Tag(_) => ret.tag_imports.push(import.name),
+                            TypeRef::Memory(_) => {}
+                        }
+                    }
+                }
+                Payload::DataSection(s) => {
+                    for segment in s.clone() {
+                        let offset = match segment?.kind {
+                            wasmparser::DataKind::Active { offset_expr, .. } => {
+                                constant(&offset_expr)?
+                            }
+                            wasmparser::DataKind::Passive => None,
+                        };
+                        ret.segment_offsets.push(offset);
+                    }
+                }
+                Payload::CustomSection(c) => {
+                    name = c.name();
+                    match c.as_known() {
+                        KnownCustom::Linking(reader) => {
+                            linking = true;
+                            for subsection in reader.subsections() {
+                                if let Linking::SymbolTable(map) = subsection? {
+                                    for symbol in map {
+                                        ret.symbols.push(symbol?);
+                                    }
+                                }
+                            }
+                        }
+                        KnownCustom::Reloc(reader) => {
+                            reloc = Some(ret.relocs.len());
+                            ret.relocs.push(Relocs {
+                                target: reader.section_index(),
+                                entries: reader.entries().into_iter().collect::<Result<_, _>>()?,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+            if let Some((id, range)) = payload.as_section() {
+                ret.sections.push(ObjectSection {
+                    id,
+                    range,
+                    name,
+                    reloc,
+                });
+            }
+        }
+        if !linking {
+            bail!("not a relocatable object file: missing `linking` custom section");
+        }
+        Ok(ret)
+    }
+
+    /// Returns the name of `symbol`, if any.
+    fn symbol_name(&self, symbol: &SymbolInfo<'a>) -> Option<&'a str> {
+        let (name, index, imports) = match *symbol {
+            SymbolInfo::Func { name, index, .. } => (name, index, &self.func_imports),
+            SymbolInfo::Global { name, index, .. } => (name, index, &self.global_imports),
+            SymbolInfo::Table { name, index, .. } => (name, index, &self.table_imports),
+            SymbolInfo::Event { name, index, .. } => (name, index, &self.tag_imports),
+            SymbolInfo::Data { name, .. } => return Some(name),
+            SymbolInfo::Section { .. } => return None,
+        };
+        name.or_else(|| imports.get(index as usize).copied())
+    }
+
+    /// Returns the value that `entry` relocates its place to.
+    ///
+    /// Values of relocations which are encoded as signed integers are
+    /// returned as the bits of their two's complement representation, in the
+    /// width of the relocation.
+    fn value(&self, symbols: &SymbolValues, entry: &RelocationEntry) -> Result<u64> {
+        use RelocationType::*;
+
+        // Type relocations refer to a type rather than to a symbol.
+        if entry.ty == TypeIndexLeb {
+            return Ok(entry.index.into());
+        }
+        let Some(symbol) = self.symbols.get(entry.index as usize) else {
+            bail!(
+                "relocation refers to symbol {}, which doesn't exist",
+                entry.index
+            );
+        };
+        let name = self.symbol_name(symbol);
+        let display = name.unwrap_or("<unnamed>");
+        let value = match (entry.ty, *symbol) {
+            (FunctionIndexLeb | FunctionIndexI32, SymbolInfo::Func { index, .. })
+            | (GlobalIndexLeb | GlobalIndexI32, SymbolInfo::Global { index, .. })
+            | (TableNumberLeb, SymbolInfo::Table { index, .. })
+            | (EventIndexLeb, SymbolInfo::Event { index, .. }) => {
+                name.and_then(|n| symbols.value(n)).unwrap_or(index.into())
+            }
+            (
+                TableIndexSleb | TableIndexI32 | TableIndexSleb64 | TableIndexI64
+                | TableIndexRelSleb | TableIndexRelSleb64,
+                SymbolInfo::Func { .. },
+            ) => match name.and_then(|n| symbols.table_slot(n)) {
+                Some(slot) => slot,
+                None => bail!("no table slot given for function `{display}`"),
+            },
+            (
+                MemoryAddrLeb | MemoryAddrSleb | MemoryAddrI32 | MemoryAddrRelSleb
+                | MemoryAddrTlsSleb | MemoryAddrLeb64 | MemoryAddrSleb64 | MemoryAddrI64
+                | MemoryAddrRelSleb64 | MemoryAddrTlsSleb64,
+                SymbolInfo::Data {
+                    symbol: defined, ..
+                },
+            ) => {
+                let address = match (name.and_then(|n| symbols.value(n)), defined) {
+                    (Some(address), _) => address,
+                    (None, Some(defined)) => {
+                        match self.segment_offsets.get(defined.index as usize) {
+                            Some(Some(offset)) => offset + u64::from(defined.offset),
+                            _ => bail!(
+                                "data symbol `{display}` is not in a segment with a constant \
+                                 offset and has no value"
+                            ),
+                        }
+                    }
+                    (None, None) => bail!("undefined data symbol `{display}` has no value"),
+                };
+                address.wrapping_add(entry.addend as u64)
+            }
+            (FunctionOffsetI32 | FunctionOffsetI64 | SectionOffsetI32 | MemoryAddrLocrelI32, _) => {
+                bail!("unsupported relocation type {:?}", entry.ty)
+            }
+            (ty, _) => bail!("relocation {ty:?} refers to `{display}`, a symbol of another kind"),
+        };
+        Ok(match ValueEncoding::of(entry.ty) {
+            ValueEncoding::Uleb32 | ValueEncoding::Sleb32 | ValueEncoding::I32 => {
+                // Addresses wrap around like they do in the linker, but
+                // indices and table slots must fit.
+                if !matches!(symbol, SymbolInfo::Data { .. }) && value > u64::from(u32::MAX) {
+                    bail!("value {value:#x} of `{display}` doesn't fit in 32 bits");
+                }
+                value & u64::from(u32::MAX)
+            }
+            _ => value,
+        })
+    }
+}
+
+/// Returns the index of the section which `relocs` apply to, within
+/// [`Object::sections`].
+fn reloc_section_target(object: &Object<'_>, relocs: &Relocs) -> Result<usize> {
+    let target = relocs.target as usize;
+    if target >= object.sections.len() {
+        bail!("relocations apply to section {target}, which doesn't exist");
+    }
+    Ok(target)
+}
+
+/// Returns the value of a constant `i32.const` or `i64.const` expression.
+fn constant(expr: &ConstExpr<'_>) -> Result<Option<u64>> {
+    let mut ops = expr.get_operators_reader();
+    let value = match ops.read()? {
+        Operator::I32Const { value } => u64::from(value as u32),
+        Operator::I64Const { value } => value as u64,
+        _ => return Ok(None),
+    };
+    Ok(match ops.read()? {
+        Operator::End => Some(value),
+        _ => None,
+    })
+}
+
+/// A part of the contents of the code or data section, as a range within the
+/// contents.
+struct Piece {
+    /// The LEB128 size of `contents`, if they are prefixed by it.
+    size: Option<Range<usize>>,
+    contents: Range<usize>,
+}
+
+/// Splits the contents of the code section into the function bodies, which
+/// are prefixed by their size, and the function count.
+fn code_pieces(contents: &[u8]) -> Result<Vec<Piece>> {
+    let mut reader = BinaryReader::new(contents, 0);
+    let count = reader.read_var_u32()?;
+    let mut pieces = vec![Piece {
+        size: None,
+        contents: 0..reader.current_position(),
+    }];
+    for _ in 0..count {
+        let size_start = reader.current_position();
+        let len = reader.read_var_u32()? as usize;
+        let size_end = reader.current_position();
+        reader.read_bytes(len)?;
+        pieces.push(Piece {
+            size: Some(size_start..size_end),
+            contents: size_end..size_end + len,
+        });
+    }
+    Ok(pieces)
+}
+
+/// Splits the contents of the data section into the segment headers, the
+/// segment data, which is prefixed by its size, and the segment count.
+fn data_pieces(contents: &[u8]) -> Result<Vec<Piece>> {
+    let mut reader = BinaryReader::new(contents, 0);
+    let count = reader.read_var_u32()?;
+    let mut header_start = 0;
+    let mut pieces = Vec::new();
+    for _ in 0..count {
+        let flags = reader.read_var_u32()?;
+        if flags & 0b10 != 0 {
+            reader.read_var_u32()?;
+        }
+        if flags & 0b01 == 0 {
+            reader.read::<ConstExpr<'_>>()?;
+        }
+        let size_start = reader.current_position();
+        pieces.push(Piece {
+            size: None,
+            contents: header_start..size_start,
+        });
+        let len = reader.read_var_u32()? as usize;
+        let size_end = reader.current_position();
+        reader.read_bytes(len)?;
+        pieces.push(Piece {
+            size: Some(size_start..size_end),
+            contents: size_end..size_end + len,
+        });
+        header_start = reader.current_position();
+    }
+    Ok(pieces)
+}
+
+/// A copy of `input` with some of its values patched.
+struct Relocated<'a> {
+    input: &'a [u8],
+    output: Vec<u8>,
+    /// The position in `input` up to which it was copied to `output`.
+    pos: usize,
+}
+
+impl<'a> Relocated<'a> {
+    fn new(input: &'a [u8]) -> Relocated<'a> {
+        Relocated {
+            input,
+            output: Vec::with_capacity(input.len()),
+            pos: 0,
+        }
+    }
+
+    /// Copies the input up to `offset`.
+    fn copy_to(&mut self, offset: usize) -> Result<()> {
+        if offset < self.pos {
+            bail!("overlapping relocations at offset {offset:#x}");
+        }
+        self.output.extend_from_slice(&self.input[self.pos..offset]);
+        self.pos = offset;
+        Ok(())
+    }
+
+    /// Replaces the value of type `ty` at `offset` with `value`, returning
+    /// the offset of the new value in the output and whether it had to be
+    /// widened.
+    fn patch(&mut self, offset: usize, ty: RelocationType, value: u64) -> Result<(usize, bool)> {
+        self.copy_to(offset)?;
+        let new_offset = self.output.len();
+        let original = &self.input[offset..];
+        let (len, widened) = match ValueEncoding::of(ty) {
+            ValueEncoding::Uleb32 | ValueEncoding::Uleb64 => {
+                write_leb(value, false, original, &mut self.output)?
+            }
+            ValueEncoding::Sleb32 => {
+                let value = i64::from(value as u32 as i32);
+                write_leb(value as u64, true, original, &mut self.output)?
+            }
+            ValueEncoding::Sleb64 => write_leb(value, true, original, &mut self.output)?,
+            ValueEncoding::I32 => {
+                if original.len() < 4 {
+                    bail!("relocation at offset {offset:#x} is out of bounds");
+                }
+                self.output.extend_from_slice(&(value as u32).to_le_bytes());
+                (4, false)
+            }
+            ValueEncoding::I64 => {
+                if original.len() < 8 {
+                    bail!("relocation at offset {offset:#x} is out of bounds");
+                }
+                self.output.extend_from_slice(&value.to_le_bytes());
+                (8, false)
+            }
+        };
+        self.pos = offset + len;
+        Ok((new_offset, widened))
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.output.extend_from_slice(&self.input[self.pos..]);
+        self.output
+    }
+}
+
+/// How the value of a relocation is encoded.
+#[derive(Clone, Copy)]
+enum ValueEncoding {
+    Uleb32,
+    Sleb32,
+    Uleb64,
+    Sleb64,
+    I32,
+    I64,
+}
+
+impl ValueEncoding {
+    fn of(ty: RelocationType) -> ValueEncoding {
+        use RelocationType::*;
+        match ty {
+            FunctionIndexLeb | MemoryAddrLeb | TypeIndexLeb | GlobalIndexLeb | EventIndexLeb
+            | TableNumberLeb => ValueEncoding::Uleb32,
+            TableIndexSleb | MemoryAddrSleb | MemoryAddrRelSleb | TableIndexRelSleb
+            | MemoryAddrTlsSleb => ValueEncoding::Sleb32,
+            MemoryAddrLeb64 => ValueEncoding::Uleb64,
+            MemoryAddrSleb64 | MemoryAddrRelSleb64 | TableIndexSleb64 | TableIndexRelSleb64
+            | MemoryAddrTlsSleb64 => ValueEncoding::Sleb64,
+            TableIndexI32 | MemoryAddrI32 | FunctionOffsetI32 | SectionOffsetI32
+            | GlobalIndexI32 | MemoryAddrLocrelI32 | FunctionIndexI32 => ValueEncoding::I32,
+            MemoryAddrI64 | TableIndexI64 | FunctionOffsetI64 => ValueEncoding::I64,
+        }
+    }
+}
+
+/// Writes `value` as a LEB128 integer in place of the one at the start of
+/// `original`, returning the length of the latter and whether the new value
+/// had to be widened.
+///
+/// The new value is padded to the length of the original one if it fits in
+/// it. `value` holds the bits of a two's complement `i64` if `signed`.
+fn write_leb(
+    value: u64,
+    signed: bool,
+    original: &[u8],
+    bytes: &mut Vec<u8>,
+) -> Result<(usize, bool)> {
+    let Some(len) = original.iter().take(10).position(|b| b & 0x80 == 0) else {
+        bail!("invalid LEB128 value to relocate");
+    };
+    let len = len + 1;
+    let value = value as i64;
+    let fits = |n: usize| {
+        let bits = 7 * n as u32;
+        if bits >= 64 {
+            true
+        } else if signed {
+            matches!(value >> (bits - 1), 0 | -1)
+        } else {
+            (value as u64) >> bits == 0
+        }
+    };
+    let padded = if fits(len) {
+        len
+    } else {
+        (1..).find(|n| fits(*n)).unwrap()
+    };
+    let mut rest = value;
+    for i in 0..padded {
+        let mut byte = (rest & 0x7f) as u8;
+        rest = if signed {
+            rest >> 7
+        } else {
+            ((rest as u64) >> 7) as i64
+        };
+        if i + 1 < padded {
+            byte |= 0x80;
+        }
+        bytes.push(byte);
+    }
+    Ok((len, padded > len))
+}
+
+/// Reads the value of a relocation of type `ty` at the start of `site`, in
+/// the same representation as [`Object::value`].
+fn read_value(ty: RelocationType, site: &[u8]) -> Result<u64> {
+    let mut reader = BinaryReader::new(site, 0);
+    Ok(match ValueEncoding::of(ty) {
+        ValueEncoding::Uleb32 => reader.read_var_u32()?.into(),
+        ValueEncoding::Sleb32 => u64::from(reader.read_var_i32()? as u32),
+        ValueEncoding::Uleb64 => reader.read_var_u64()?,
+        ValueEncoding::Sleb64 => reader.read_var_i64()? as u64,
+        ValueEncoding::I32 => reader.read_u32()?.into(),
+        ValueEncoding::I64 => reader.read_u64()?,
+    })
+}
+
+/// Encodes `entry` of a `reloc.*` section, at `offset`.
+fn encode_entry(entry: &RelocationEntry, offset: u32, bytes: &mut Vec<u8>) {
+    bytes.push(entry.ty as u8);
+    offset.encode(bytes);
+    entry.index.encode(bytes);
+    match entry.ty.addend_kind() {
+        RelocAddendKind::None => {}
+        RelocAddendKind::Addend32 => (entry.addend as i32).encode(bytes),
+        RelocAddendKind::Addend64 => entry.addend.encode(bytes),
+    }
+}
@@ -0,0 +1,1059 @@
+//! Splitting of a module into a primary module and secondary modules which
+//! can be loaded lazily.
+//!
+//! Large applications often contain code which is rarely run, such as error
+//! reporting or features which few users use. [`SplitModule`] partitions the
+//! functions of a module along its call graph: each secondary module is given
+//! a set of root functions, and the roots along with the functions which are
+//! only reachable through them are moved to it, together with the passive
+//! data segments which only they use. The primary module which remains can be
+//! downloaded and instantiated first, and each secondary module is
+//! instantiated with the exports of the primary module once it's needed.
+//!
+//! Every moved function is replaced in the primary module by a trampoline
+//! with the same index and type, so calls, exports, and tables of the primary
+//! module are unaffected. Trampolines call through a new table exported by
+//! the primary module as `__split_table`, whose slots are filled in by the
+//! element segment of a secondary module when it's instantiated. Until then
+//! calling a trampoline traps, or, with [`SplitModule::placeholders`], calls
+//! a placeholder function imported by the primary module which can load the
+//! secondary module and retry the call.
+
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{utils, Error, Reencode, RoundtripReencoder};
+use wasm_encoder::{
+    CodeSection, ConstExpr, DataCountSection, DataSection, ElementSection, Elements, EntityType,
+    ExportKind, ExportSection, Function, FunctionSection, ImportSection, Instruction, Module,
+    NameMap, NameSection, RawSection, RefType, SectionId, TableSection, TableType,
+};
+use wasmparser::{
+    CompositeInnerType, Encoding, ExternalKind, FunctionBody, KnownCustom, Name, Operator, Parser,
+    Payload, TableInit, TypeRef,
+};
+
+/// The name of the table through which trampolines call moved functions.
+const SPLIT_TABLE: &str = "__split_table";
+
+/// The module name of the placeholder functions imported by the primary
+/// module, see [`SplitModule::placeholders`].
+const PLACEHOLDER_MODULE: &str = "placeholder";
+
+/// Configuration of how a module is split.
+#[derive(Debug, Clone)]
+pub struct SplitModule {
+    secondaries: Vec<(String, Vec<String>)>,
+    import_module: String,
+    placeholders: bool,
+}
+
+/// The modules produced by [`SplitModule::split`].
+#[derive(Debug, Clone, Default)]
+pub struct Split {
+    /// The primary module.
+    pub primary: Vec<u8>,
+    /// The secondary modules along with their names, in the order they were
+    /// configured.
+    pub secondaries: Vec<(String, Vec<u8>)>,
+    /// Human-readable notes about what was moved, and about functions which
+    /// couldn't be moved.
+    pub diagnostics: Vec<String>,
+}
+
+impl SplitModule {
+    /// Creates a new configuration without any secondary modules.
+    pub fn new() -> SplitModule {
+        SplitModule::default()
+    }
+
+    /// Adds a secondary module named `name`, to which the functions `roots`
+    /// and those only reachable through them are moved.
+    ///
+    /// Functions are referred to by their name in the `name` section, the
+    /// name they're exported with, or their index.
+    pub fn secondary(
+        &mut self,
+        name: impl Into<String>,
+        roots: impl IntoIterator<Item = impl Into<String>>,
+    ) -> &mut Self {
+        self.secondaries
+            .push((name.into(), roots.into_iter().map(|r| r.into()).collect()));
+        self
+    }
+
+    /// Configures the module name of the imports of secondary modules, which
+    /// is `primary` by default.
+    ///
+    /// Each secondary module imports the functions, tables, memories, globals,
+    /// and tags of the primary module which it uses, and the primary module
+    /// exports them with matching names.
+    pub fn import_module(&mut self, name: impl Into<String>) -> &mut Self {
+        self.import_module = name.into();
+        self
+    }
+
+    /// Configures whether the primary module imports a placeholder function
+    /// for each moved function, which is disabled by default.
+    ///
+    /// Placeholders are imported from the `placeholder` module with the slot
+    /// of their function in `__split_table` as their name, such as `"0"`, and
+    /// have the type of that function. They're called by the trampolines of
+    /// the primary module until the secondary module defining the function
+    /// has been instantiated, so they can load the secondary module and then
+    /// call the function through the table. Note that the placeholders are
+    /// imported after the other functions imported by the primary module and
+    /// so shift the indices of its defined functions.
+    pub fn placeholders(&mut self, enable: bool) -> &mut Self {
+        self.placeholders = enable;
+        self
+    }
+
+    /// Splits the core wasm module `wasm`.
+    pub fn split(&self, wasm: &[u8]) -> Result<Split> {
+        if self.secondaries.is_empty() {
+            bail!("no secondary modules to split off were configured");
+        }
+        let info = Info::parse(wasm)?;
+
+        let mut roots = Vec::new();
+        let mut names = HashSet::new();
+        for (name, funcs) in self.secondaries.iter() {
+            if !names.insert(name.as_str()) {
+                bail!("secondary module `{name}` is configured more than once");
+            }
+            if funcs.is_empty() {
+                bail!("secondary module `{name}` has no root functions");
+            }
+            let funcs = funcs
+                .iter()
+                .map(|f| info.resolve_func(f))
+                .collect::<Result<Vec<_>>>()?;
+            roots.push(funcs);
+        }
+
+        let mut split = Split::default();
+        let mut owners = info.partition(&roots)?;
+        let data_owners = info.place_data(&mut owners, &mut split.diagnostics);
+
+        // Assign the slots of `__split_table` in order of secondary modules
+        // and then function indices.
+        let mut moved = vec![Vec::new(); roots.len()];
+        for (func, owner) in owners.iter().enumerate() {
+            if let Some(owner) = owner {
+                moved[*owner].push(func as u32);
+            }
+        }
+        let mut slots = HashMap::new();
+        let mut slot_types = Vec::new();
+        let mut first_slots = Vec::new();
+        for funcs in moved.iter() {
+            first_slots.push(slot_types.len() as u32);
+            for func in funcs {
+                slots.insert(*func, slot_types.len() as u32);
+                slot_types.push(info.func_types[*func as usize]);
+            }
+        }
+
+        let mut exports = Exports::default();
+        for (i, funcs) in moved.into_iter().enumerate() {
+            let data = (0..data_owners.len() as u32)
+                .filter(|d| data_owners[*d as usize] == Some(i))
+                .collect::<Vec<_>>();
+            split.diagnostics.push(format!(
+                "moved {} functions and {} data segments to `{}`",
+                funcs.len(),
+                data.len(),
+                self.secondaries[i].0,
+            ));
+            let mut secondary = Secondary::new(&info, funcs, data);
+            let wasm = secondary.encode(self, first_slots[i], slot_types.len() as u32)?;
+            exports.add(&secondary);
+            split
+                .secondaries
+                .push((self.secondaries[i].0.clone(), wasm));
+        }
+
+        let mut primary = Primary {
+            info: &info,
+            config: self,
+            slots,
+            slot_types,
+            data_owners,
+            exports,
+            next_func: 0,
+            next_data: 0,
+            added_imports: !self.placeholders,
+            added_table: false,
+            added_elements: !self.placeholders,
+            added_exports: false,
+        };
+        let mut module = Module::new();
+        primary.parse_core_module(&mut module, Parser::new(0), wasm)?;
+        split.primary = module.finish();
+        Ok(split)
+    }
+}
+
+impl Default for SplitModule {
+    fn default() -> SplitModule {
+        SplitModule {
+            secondaries: Vec::new(),
+            import_module: "primary".to_string(),
+            placeholders: false,
+        }
+    }
+}
+
+/// The name with which the primary module exports an entity to secondary
+/// modules.
+fn export_name(kind: &str, index: u32) -> String {
+    format!("__split_{kind}_{index}")
+}
+
+/// What a function body refers to, as far as splitting is concerned.
+#[derive(Default)]
+struct Uses {
+    /// Functions called or referenced with `ref.func`.
+    funcs: Vec<u32>,
+    /// Functions referenced with `ref.func`.
+    ref_funcs: Vec<u32>,
+    /// Data segments used with `memory.init`, `data.drop`, and the like.
+    data: Vec<u32>,
+    /// Whether element segments are used, which always stay in the primary
+    /// module.
+    elements: bool,
+}
+
+impl Uses {
+    fn new(body: &FunctionBody<'_>) -> Result<Uses> {
+        let mut uses = Uses::default();
+        let mut reader = body.get_operators_reader()?;
+        while !reader.eof() {
+            match reader.read()? {
+                Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
+                    uses.funcs.push(function_index);
+                }
+                Operator::RefFunc { function_index } => {
+                    uses.funcs.push(function_index);
+                    uses.ref_funcs.push(function_index);
+                }
+                Operator::MemoryInit { data_index, .. }
+                | Operator::DataDrop { data_index }
+                | Operator::ArrayNewData {
+                    array_data_index: data_index,
+                    ..
+                }
+                | Operator::ArrayInitData {
+                    array_data_index: data_index,
+                    ..
+                } => uses.data.push(data_index),
+                Operator::TableInit { .. }
+                | Operator::ElemDrop { .. }
+                | Operator::ArrayNewElem { .. }
+                | Operator::ArrayInitElem { .. } => uses.elements = true,
+                _ => {}
+            }
+        }
+        Ok(uses)
+    }
+}
+
+/// Everything about the input module which splitting it needs.
+#[derive(Default)]
+struct Info<'a> {
+    /// The contents of the type section, which is copied to secondary modules
+    /// so type indices are the same in all modules.
+    type_section: Option<&'a [u8]>,
+    /// The number of parameters of each function type, and `None` for other
+    /// types.
+    type_params: Vec<Option<u32>>,
+    /// The type of each function, including imported ones.
+    func_types: Vec<u32>,
+    num_imported_funcs: u32,
+    tables: Vec<wasmparser::TableType>,
+    memories: Vec<wasmparser::MemoryType>,
+    globals: Vec<wasmparser::GlobalType>,
+    tags: Vec<wasmparser::TagType>,
+    /// The bodies of the defined functions.
+    bodies: Vec<FunctionBody<'a>>,
+    /// What each defined function refers to.
+    uses: Vec<Uses>,
+    data: Vec<wasmparser::Data<'a>>,
+    /// The names of functions according to the `name` section.
+    func_names: HashMap<u32, &'a str>,
+    /// The functions exported by the module, by name.
+    exported_funcs: HashMap<&'a str, u32>,
+    /// Functions which may be called from outside of the module or through
+    /// references, which are kept in the primary module unless they're roots
+    /// of a secondary module.
+    entry_points: Vec<u32>,
+}
+
+impl<'a> Info<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Info<'a>> {
+        let mut info = Info::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => bail!("splitting components is not supported"),
+                Payload::TypeSection(s) => {
+                    info.type_section = Some(&wasm[s.range()]);
+                    for group in s {
+                        for ty in group?.types() {
+                            info.type_params.push(match &ty.composite_type.inner {
+                                CompositeInnerType::Func(f) => Some(f.params().len() as u32),
+                                _ => None,
+                            });
+                        }
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        match import?.ty {
+                            TypeRef::Func(ty) => {
+                                info.func_types.push(ty);
+                                info.num_imported_funcs += 1;
+                            }
+                            TypeRef::Table(ty) => info.tables.push(ty),
+                            TypeRef::Memory(ty) => info.memories.push(ty),
+                            TypeRef::Global(ty) => info.globals.push(ty),
+                            TypeRef::Tag(ty) => info.tags.push(ty),
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        info.func_types.push(ty?);
+                    }
+                }
+                Payload::TableSection(s) => {
+                    for table in s {
+                        let table = table?;
+                        if let TableInit::Expr(expr) = &table.init {
+                            info.entry_points.extend(const_expr_funcs(expr)?);
+                        }
+                        info.tables.push(table.ty);
+                    }
+                }
+                Payload::MemorySection(s) => {
+                    for ty in s {
+                        info.memories.push(ty?);
+                    }
+                }
+                Payload::TagSection(s) => {
+                    for ty in s {
+                        info.tags.push(ty?);
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        let global = global?;
+                        info.entry_points
+                            .extend(const_expr_funcs(&global.init_expr)?);
+                        info.globals.push(global.ty);
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            info.entry_points.push(export.index);
+                            info.exported_funcs.insert(export.name, export.index);
+                        }
+                    }
+                }
+                Payload::StartSection { func, .. } => info.entry_points.push(func),
+                Payload::ElementSection(s) => {
+                    for element in s {
+                        match element?.items {
+                            wasmparser::ElementItems::Functions(funcs) => {
+                                for func in funcs {
+                                    info.entry_points.push(func?);
+                                }
+                            }
+                            wasmparser::ElementItems::Expressions(_, exprs) => {
+                                for expr in exprs {
+                                    info.entry_points.extend(const_expr_funcs(&expr?)?);
+                                }
+                            }
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    info.uses.push(Uses::new(&body)?);
+                    info.bodies.push(body);
+                }
+                Payload::DataSection(s) => {
+                    for datum in s {
+                        info.data.push(datum?);
+                    }
+                }
+                Payload::CustomSection(c) => {
+                    if let KnownCustom::Name(reader) = c.as_known() {
+                        for name in reader {
+                            if let Name::Function(map) = name? {
+                                for naming in map {
+                                    let naming = naming?;
+                                    info.func_names.insert(naming.index, naming.name);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// Returns the index of the defined function referred to by `name`.
+    fn resolve_func(&self, name: &str) -> Result<u32> {
+        let named = self
+            .func_names
+            .iter()
+            .filter(|(_, n)| **n == name)
+            .map(|(i, _)| *i)
+            .min();
+        let index = match named.or(self.exported_funcs.get(name).copied()) {
+            Some(index) => index,
+            None => match name.parse::<u32>() {
+                Ok(index) if (index as usize) < self.func_types.len() => index,
+                _ => bail!("no function named `{name}` was found"),
+            },
+        };
+        if index < self.num_imported_funcs {
+            bail!("function `{name}` is imported and can't be moved to a secondary module");
+        }
+        Ok(index)
+    }
+
+    /// Returns the name of `func` for diagnostics.
+    fn func_name(&self, func: u32) -> String {
+        match self.func_names.get(&func) {
+            Some(name) => format!("`{name}`"),
+            None => format!("{func}"),
+        }
+    }
+
+    fn uses(&self, func: u32) -> &Uses {
+        &self.uses[(func - self.num_imported_funcs) as usize]
+    }
+
+    /// Returns the secondary module, if any, to which each function is moved.
+    ///
+    /// A function is moved to a secondary module if it's reachable from the
+    /// module's roots but neither from the entry points of the primary module
+    /// nor from the roots of other secondary modules, where reaching a root
+    /// of a secondary module stops the search.
+    fn partition(&self, roots: &[Vec<u32>]) -> Result<Vec<Option<usize>>> {
+        let mut root_of = HashMap::new();
+        for (i, funcs) in roots.iter().enumerate() {
+            for func in funcs {
+                if let Some(prev) = root_of.insert(*func, i) {
+                    if prev != i {
+                        bail!(
+                            "function {} is a root of more than one secondary module",
+                            self.func_name(*func)
+                        );
+                    }
+                }
+            }
+        }
+
+        let primary = self.reachable(
+            self.entry_points
+                .iter()
+                .copied()
+                .filter(|f| !root_of.contains_key(f)),
+            |f| !root_of.contains_key(&f),
+        );
+        let mut owners = vec![None; self.func_types.len()];
+        let mut shared = HashSet::new();
+        for (i, funcs) in roots.iter().enumerate() {
+            let reached = self.reachable(funcs.iter().copied(), |f| {
+                !primary.contains(&f) && root_of.get(&f).map_or(true, |r| *r == i)
+            });
+            for func in reached {
+                match owners[func as usize] {
+                    Some(j) if j != i => {
+                        shared.insert(func);
+                    }
+                    _ => owners[func as usize] = Some(i),
+                }
+            }
+        }
+        for func in shared {
+            owners[func as usize] = None;
+        }
+        Ok(owners)
+    }
+
+    /// Returns the defined functions reachable from `start` through calls
+    /// and `ref.func`, only continuing through functions where `enter`
+    /// returns true.
+    fn reachable(
+        &self,
+        start: impl IntoIterator<Item = u32>,
+        enter: impl Fn(u32) -> bool,
+    ) -> HashSet<u32> {
+        let mut reached = HashSet::new();
+        let mut worklist = start.into_iter().collect::<Vec<_>>();
+        while let Some(func) = worklist.pop() {
+            if func < self.num_imported_funcs || !reached.insert(func) {
+                continue;
+            }
+            worklist.extend(self.uses(func).funcs.iter().copied().filter(|f| enter(*f)));
+        }
+        reached
+    }
+
+    /// Returns the secondary module, if any, to which each data segment is
+    /// moved, keeping functions which use segments that can't be moved in the
+    /// primary module.
+    ///
+    /// Passive data segments are moved if they're only used by functions
+    /// moved to the same secondary module. Functions using other data
+    /// segments, or any element segment, are kept in the primary module.
+    fn place_data(
+        &self,
+        owners: &mut [Option<usize>],
+        diagnostics: &mut Vec<String>,
+    ) -> Vec<Option<usize>> {
+        let mut users = vec![Vec::new(); self.data.len()];
+        for func in self.num_imported_funcs..self.func_types.len() as u32 {
+            for data in self.uses(func).data.iter() {
+                users[*data as usize].push(func);
+            }
+        }
+
+        loop {
+            let mut changed = false;
+            for func in self.num_imported_funcs..self.func_types.len() as u32 {
+                let owner = match owners[func as usize] {
+                    Some(owner) => owner,
+                    None => continue,
+                };
+                let uses = self.uses(func);
+                let reason = if uses.elements {
+                    Some("uses element segments".to_string())
+                } else {
+                    uses.data
+                        .iter()
+                        .find(|d| {
+                            let passive = matches!(
+                                self.data[**d as usize].kind,
+                                wasmparser::DataKind::Passive
+                            );
+                            !passive
+                                || users[**d as usize]
+                                    .iter()
+                                    .any(|u| owners[*u as usize] != Some(owner))
+                        })
+                        .map(|d| format!("uses data segment {d} which can't be moved"))
+                };
+                if let Some(reason) = reason {
+                    diagnostics.push(format!(
+                        "function {} was kept in the primary module as it {reason}",
+                        self.func_name(func)
+                    ));
+                    owners[func as usize] = None;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        users
+            .iter()
+            .map(|users| match users.first() {
+                Some(first) => owners[*first as usize],
+                None => None,
+            })
+            .collect()
+    }
+}
+
+/// Returns the functions referenced by the constant expression `expr`.
+fn const_expr_funcs(expr: &wasmparser::ConstExpr<'_>) -> Result<Vec<u32>> {
+    let mut funcs = Vec::new();
+    let mut reader = expr.get_operators_reader();
+    while !reader.eof() {
+        if let Operator::RefFunc { function_index } = reader.read()? {
+            funcs.push(function_index);
+        }
+    }
+    Ok(funcs)
+}
+
+/// The entities of the primary module which are imported by secondary
+/// modules, by their index in the input.
+#[derive(Default)]
+struct Exports {
+    funcs: BTreeSet<u32>,
+    tables: BTreeSet<u32>,
+    memories: BTreeSet<u32>,
+    globals: BTreeSet<u32>,
+    tags: BTreeSet<u32>,
+}
+
+impl Exports {
+    fn add(&mut self, secondary: &Secondary<'_, '_>) {
+        self.funcs.extend(secondary.funcs.keys());
+        // `__split_table` is always exported by the primary module.
+        let split_table = secondary.info.tables.len() as u32;
+        self.tables
+            .extend(secondary.tables.keys().filter(|t| **t != split_table));
+        self.memories.extend(secondary.memories.keys());
+        self.globals.extend(secondary.globals.keys());
+        self.tags.extend(secondary.tags.keys());
+    }
+}
+
+/// Encodes a secondary module.
+///
+/// Function bodies are reencoded twice: first to find which entities of the
+/// primary module they use, which are then imported in order of their index,
+/// and then to refer to the imports.
+struct Secondary<'a, 'b> {
+    info: &'b Info<'a>,
+    /// The functions defined by this module, by their index in the input.
+    defined: Vec<u32>,
+    /// The data segments of this module, by their index in the input.
+    data: Vec<u32>,
+    /// The imported entities of the primary module, mapped from their index
+    /// in the input to their index in this module.
+    funcs: BTreeMap<u32, u32>,
+    tables: BTreeMap<u32, u32>,
+    memories: BTreeMap<u32, u32>,
+    globals: BTreeMap<u32, u32>,
+    tags: BTreeMap<u32, u32>,
+}
+
+impl<'a, 'b> Secondary<'a, 'b> {
+    fn new(info: &'b Info<'a>, defined: Vec<u32>, data: Vec<u32>) -> Secondary<'a, 'b> {
+        Secondary {
+            info,
+            defined,
+            data,
+            funcs: BTreeMap::new(),
+            tables: BTreeMap::new(),
+            memories: BTreeMap::new(),
+            globals: BTreeMap::new(),
+            tags: BTreeMap::new(),
+        }
+    }
+
+    fn encode(&mut self, config: &SplitModule, first_slot: u32, num_slots: u32) -> Result<Vec<u8>> {
+        let info = self.info;
+        let split_table = info.tables.len() as u32;
+
+        self.tables.insert(split_table, 0);
+        for func in self.defined.clone() {
+            let body = info.bodies[(func - info.num_imported_funcs) as usize].clone();
+            self.parse_function_body(&mut CodeSection::new(), body)?;
+        }
+        for map in [
+            &mut self.funcs,
+            &mut self.tables,
+            &mut self.memories,
+            &mut self.globals,
+            &mut self.tags,
+        ] {
+            for (i, index) in map.values_mut().enumerate() {
+                *index = i as u32;
+            }
+        }
+
+        let mut module = Module::new();
+        if let Some(types) = info.type_section {
+            module.section(&RawSection {
+                id: SectionId::Type.into(),
+                data: types,
+            });
+        }
+
+        let mut imports = ImportSection::new();
+        let module_name = config.import_module.as_str();
+        for func in self.funcs.keys() {
+            let ty = EntityType::Function(info.func_types[*func as usize]);
+            imports.import(module_name, &export_name("func", *func), ty);
+        }
+        for table in self.tables.keys() {
+            if *table == split_table {
+                imports.import(module_name, SPLIT_TABLE, split_table_type(num_slots));
+            } else {
+                let ty = RoundtripReencoder.table_type(info.tables[*table as usize])?;
+                imports.import(module_name, &export_name("table", *table), ty);
+            }
+        }
+        for memory in self.memories.keys() {
+            let ty = RoundtripReencoder.memory_type(info.memories[*memory as usize]);
+            imports.import(module_name, &export_name("memory", *memory), ty);
+        }
+        for global in self.globals.keys() {
+            let ty = RoundtripReencoder.global_type(info.globals[*global as usize])?;
+            imports.import(module_name, &export_name("global", *global), ty);
+        }
+        for tag in self.tags.keys() {
+            let ty = RoundtripReencoder.tag_type(info.tags[*tag as usize]);
+            imports.import(module_name, &export_name("tag", *tag), ty);
+        }
+        module.section(&imports);
+
+        let mut functions = FunctionSection::new();
+        for func in self.defined.iter() {
+            functions.function(info.func_types[*func as usize]);
+        }
+        module.section(&functions);
+
+        let mut elements = ElementSection::new();
+        let defined = self
+            .defined
+            .clone()
+            .into_iter()
+            .map(|f| self.function_index(f))
+            .collect::<Vec<_>>();
+        elements.active(
+            Some(self.tables[&split_table]),
+            &ConstExpr::i32_const(first_slot as i32),
+            Elements::Functions(defined.into()),
+        );
+        let mut referenced = BTreeSet::new();
+        for func in self.defined.clone() {
+            for r in info.uses(func).ref_funcs.iter() {
+                referenced.insert(self.function_index(*r));
+            }
+        }
+        if !referenced.is_empty() {
+            let referenced = referenced.into_iter().collect::<Vec<_>>();
+            elements.declared(Elements::Functions(referenced.into()));
+        }
+        module.section(&elements);
+
+        if !self.data.is_empty() {
+            module.section(&DataCountSection {
+                count: self.data.len() as u32,
+            });
+        }
+
+        let mut code = CodeSection::new();
+        for func in self.defined.clone() {
+            let body = info.bodies[(func - info.num_imported_funcs) as usize].clone();
+            self.parse_function_body(&mut code, body)?;
+        }
+        module.section(&code);
+
+        if !self.data.is_empty() {
+            let mut data = DataSection::new();
+            for datum in self.data.iter() {
+                data.passive(info.data[*datum as usize].data.iter().copied());
+            }
+            module.section(&data);
+        }
+
+        let mut func_names = NameMap::new();
+        for (i, func) in self.defined.iter().enumerate() {
+            if let Some(name) = info.func_names.get(func) {
+                func_names.append(self.funcs.len() as u32 + i as u32, name);
+            }
+        }
+        if !func_names.is_empty() {
+            let mut names = NameSection::new();
+            names.functions(&func_names);
+            module.section(&names);
+        }
+
+        Ok(module.finish())
+    }
+}
+
+/// Returns the index of `index` in `map`, adding it if it's not there yet.
+fn import_index(map: &mut BTreeMap<u32, u32>, index: u32) -> u32 {
+    let next = map.len() as u32;
+    *map.entry(index).or_insert(next)
+}
+
+impl Reencode for Secondary<'_, '_> {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        match self.defined.binary_search(&func) {
+            Ok(i) => self.funcs.len() as u32 + i as u32,
+            Err(_) => import_index(&mut self.funcs, func),
+        }
+    }
+
+    fn table_index(&mut self, table: u32) -> u32 {
+        import_index(&mut self.tables, table)
+    }
+
+    fn memory_index(&mut self, memory: u32) -> u32 {
+        import_index(&mut self.memories, memory)
+    }
+
+    fn global_index(&mut self, global: u32) -> u32 {
+        import_index(&mut self.globals, global)
+    }
+
+    fn tag_index(&mut self, tag: u32) -> u32 {
+        import_index(&mut self.tags, tag)
+    }
+
+    fn data_index(&mut self, data: u32) -> u32 {
+        self.data.binary_search(&data).unwrap() as u32
+    }
+}
+
+fn split_table_type(num_slots: u32) -> TableType {
+    TableType {
+        element_type: RefType::FUNCREF,
+        table64: false,
+        minimum: num_slots.into(),
+        maximum: Some(num_slots.into()),
+        shared: false,
+    }
+}
+
+/// Reencodes the input into the primary module.
+struct Primary<'a, 'b> {
+    info: &'b Info<'a>,
+    config: &'b SplitModule,
+    /// The slot in `__split_table` of each moved function.
+    slots: HashMap<u32, u32>,
+    /// The type of the function in each slot of `__split_table`.
+    slot_types: Vec<u32>,
+    /// The secondary module, if any, to which each data segment was moved.
+    data_owners: Vec<Option<usize>>,
+    exports: Exports,
+    next_func: u32,
+    next_data: u32,
+    added_imports: bool,
+    added_table: bool,
+    added_elements: bool,
+    added_exports: bool,
+}
+
+impl Primary<'_, '_> {
+    fn num_placeholders(&self) -> u32 {
+        if self.config.placeholders {
+            self.slot_types.len() as u32
+        } else {
+            0
+        }
+    }
+
+    fn split_table(&self) -> u32 {
+        self.info.tables.len() as u32
+    }
+
+    fn add_imports(&mut self, imports: &mut ImportSection) {
+        self.added_imports = true;
+        for (slot, ty) in self.slot_types.iter().enumerate() {
+            imports.import(
+                PLACEHOLDER_MODULE,
+                &slot.to_string(),
+                EntityType::Function(*ty),
+            );
+        }
+    }
+
+    fn add_table(&mut self, tables: &mut TableSection) {
+        self.added_table = true;
+        tables.table(split_table_type(self.slot_types.len() as u32));
+    }
+
+    fn add_elements(&mut self, elements: &mut ElementSection) {
+        self.added_elements = true;
+        let placeholders = (0..self.num_placeholders())
+            .map(|i| self.info.num_imported_funcs + i)
+            .collect::<Vec<_>>();
+        elements.active(
+            Some(self.split_table()),
+            &ConstExpr::i32_const(0),
+            Elements::Functions(placeholders.into()),
+        );
+    }
+
+    fn add_exports(&mut self, exports: &mut ExportSection) {
+        self.added_exports = true;
+        exports.export(SPLIT_TABLE, ExportKind::Table, self.split_table());
+        let funcs = std::mem::take(&mut self.exports.funcs);
+        for func in funcs {
+            exports.export(
+                &export_name("func", func),
+                ExportKind::Func,
+                self.function_index(func),
+            );
+        }
+        let entities = [
+            ("table", ExportKind::Table, &self.exports.tables),
+            ("memory", ExportKind::Memory, &self.exports.memories),
+            ("global", ExportKind::Global, &self.exports.globals),
+            ("tag", ExportKind::Tag, &self.exports.tags),
+        ];
+        for (kind, export_kind, indices) in entities {
+            for index in indices {
+                exports.export(&export_name(kind, *index), export_kind, *index);
+            }
+        }
+    }
+}
+
+/// Returns the position of `id` in the order of sections within a module.
+fn section_position(id: SectionId) -> u8 {
+    match id {
+        SectionId::Custom => 0,
+        SectionId::Type => 1,
+        SectionId::Import => 2,
+        SectionId::Function => 3,
+        SectionId::Table => 4,
+        SectionId::Memory => 5,
+        SectionId::Tag => 6,
+        SectionId::Global => 7,
+        SectionId::Export => 8,
+        SectionId::Start => 9,
+        SectionId::Element => 10,
+        SectionId::DataCount => 11,
+        SectionId::Code => 12,
+        SectionId::Data => 13,
+    }
+}
+
+impl Reencode for Primary<'_, '_> {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        if func < self.info.num_imported_funcs {
+            func
+        } else {
+            func + self.num_placeholders()
+        }
+    }
+
+    fn parse_import_section(
+        &mut self,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_import_section(self, imports, section)?;
+        if self.config.placeholders {
+            self.add_imports(imports);
+        }
+        Ok(())
+    }
+
+    fn parse_table_section(
+        &mut self,
+        tables: &mut TableSection,
+        section: wasmparser::TableSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_table_section(self, tables, section)?;
+        self.add_table(tables);
+        Ok(())
+    }
+
+    fn parse_export_section(
+        &mut self,
+        exports: &mut ExportSection,
+        section: wasmparser::ExportSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_export_section(self, exports, section)?;
+        self.add_exports(exports);
+        Ok(())
+    }
+
+    fn parse_element_section(
+        &mut self,
+        elements: &mut ElementSection,
+        section: wasmparser::ElementSectionReader<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        utils::parse_element_section(self, elements, section)?;
+        if self.config.placeholders {
+            self.add_elements(elements);
+        }
+        Ok(())
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: FunctionBody<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let index = self.info.num_imported_funcs + self.next_func;
+        self.next_func += 1;
+        let slot = match self.slots.get(&index) {
+            Some(slot) => *slot,
+            None => return utils::parse_function_body(self, code, func),
+        };
+
+        // The trampoline forwards its parameters to the function in its slot
+        // of `__split_table`.
+        let ty = self.info.func_types[index as usize];
+        let mut f = Function::new([]);
+        for local in 0..self.info.type_params[ty as usize].unwrap_or(0) {
+            f.instruction(&Instruction::LocalGet(local));
+        }
+        f.instruction(&Instruction::I32Const(slot as i32));
+        f.instruction(&Instruction::CallIndirect {
+            type_index: ty,
+            table_index: self.split_table(),
+        });
+        f.instruction(&Instruction::End);
+        code.function(&f);
+        Ok(())
+    }
+
+    fn parse_data(
+        &mut self,
+        data: &mut DataSection,
+        datum: wasmparser::Data<'_>,
+    ) -> Result<(), Error<Infallible>> {
+        let index = self.next_data;
+        self.next_data += 1;
+        if self.data_owners[index as usize].is_some() {
+            // Moved segments are left empty so the indices of the others
+            // don't change.
+            data.passive([]);
+            return Ok(());
+        }
+        utils::parse_data(self, data, datum)
+    }
+
+    fn intersperse_section_hook(
+        &mut self,
+        module: &mut Module,
+        _after: Option<SectionId>,
+        before: Option<SectionId>,
+    ) -> Result<(), Error<Infallible>> {
+        // Add the sections which the input doesn't have once the point where
+        // they would have been has passed.
+        let passed =
+            |id: SectionId| before.map_or(true, |b| section_position(b) > section_position(id));
+        if !self.added_imports && passed(SectionId::Import) {
+            let mut imports = ImportSection::new();
+            self.add_imports(&mut imports);
+            module.section(&imports);
+        }
+        if !self.added_table && passed(SectionId::Table) {
+            let mut tables = TableSection::new();
+            self.add_table(&mut tables);
+            module.section(&tables);
+        }
+        if !self.added_exports && passed(SectionId::Export) {
+            let mut exports = ExportSection::new();
+            self.add_exports(&mut exports);
+            module.section(&exports);
+        }
+        if !self.added_elements && passed(SectionId::Element) {
+            let mut elements = ElementSection::new();
+            self.add_elements(&mut elements);
+            module.section(&elements);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! Analysis of which functions may be stored in the tables of a module.
+//!
+//! Indirect calls go through tables, so knowing which functions can end up in
+//! a table bounds the possible targets of `call_indirect`. This is what
+//! devirtualization needs to replace indirect calls with direct ones, and what
+//! security audits need to find out which functions are reachable from a
+//! function pointer.
+
+use anyhow::{bail, Result};
+use std::collections::BTreeSet;
+use wasmparser::{
+    ElementItem, ElementKind, Encoding, ExternalKind, Operator, Parser, Payload, TableInit, TypeRef,
+};
+
+/// The functions which may be stored in a table, as found by
+/// [`analyze_tables`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableContents {
+    /// The functions of the module which may be stored in the table.
+    pub functions: BTreeSet<u32>,
+    /// Whether the table may also contain references which aren't known from
+    /// the module alone.
+    ///
+    /// This is the case if the table is imported or exported, as other
+    /// modules can then write to it, or if references are written to it with
+    /// `table.set`, `table.fill`, or `table.grow`. In the latter case
+    /// `functions` includes all functions whose reference is taken anywhere
+    /// in the module, but references from outside the module, such as
+    /// results of imported functions, may be written to the table as well.
+    /// Tables which are initialized with a `global.get` or which are the
+    /// destination of a `table.copy` from such a table are considered
+    /// unknown as well.
+    pub unknown: bool,
+}
+
+/// Returns which functions may be stored in each table of the core wasm
+/// module `wasm`, in the order of the tables' indices.
+///
+/// Functions are added to a table by the initializer of the table, by active
+/// element segments, by `table.init` of passive element segments anywhere in
+/// the module's code, and by `table.copy` from other tables. This doesn't
+/// consider whether the code writing to a table is reachable, so it's an
+/// overapproximation of the functions which are actually stored.
+pub fn analyze_tables(wasm: &[u8]) -> Result<Vec<TableContents>> {
+    let mut tables = Vec::new();
+    // The functions and whether unknown references may be included for each
+    // element segment.
+    let mut segments: Vec<(Vec<u32>, bool)> = Vec::new();
+    // The functions whose reference is taken anywhere in the module.
+    let mut referenced = BTreeSet::new();
+    // The segment and table of each `table.init`.
+    let mut inits = Vec::new();
+    // The tables written with `table.set`, `table.fill`, and `table.grow`.
+    let mut writes = Vec::new();
+    // The destination and source tables of each `table.copy`.
+    let mut copies = Vec::new();
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::Version {
+                encoding: Encoding::Component,
+                ..
+            } => bail!("analyzing the tables of components is not supported"),
+            Payload::ImportSection(s) => {
+                for import in s {
+                    if let TypeRef::Table(_) = import?.ty {
+                        tables.push(TableContents {
+                            functions: BTreeSet::new(),
+                            unknown: true,
+                        });
+                    }
+                }
+            }
+            Payload::TableSection(s) => {
+                for table in s {
+                    let mut contents = TableContents::default();
+                    if let TableInit::Expr(expr) = table?.init {
+                        contents.unknown =
+                            const_expr_functions(&expr, &mut contents.functions, &mut referenced)?;
+                    }
+                    tables.push(contents);
+                }
+            }
+            Payload::GlobalSection(s) => {
+                for global in s {
+                    const_expr_functions(
+                        &global?.init_expr,
+                        &mut BTreeSet::new(),
+                        &mut referenced,
+                    )?;
+                }
+            }
+            Payload::ExportSection(s) => {
+                for export in s {
+                    let export = export?;
+                    if let ExternalKind::Table = export.kind {
+                        tables[export.index as usize].unknown = true;
+                    }
+                }
+            }
+            Payload::ElementSection(s) => {
+                for element in s {
+                    let element = element?;
+                    let mut functions = BTreeSet::new();
+                    let mut unknown = false;
+                    for item in element.items.items() {
+                        match item? {
+                            ElementItem::Func(f) => {
+                                functions.insert(f);
+                            }
+                            ElementItem::Null(_) => {}
+                            ElementItem::Expr(expr) => {
+                                unknown |=
+                                    const_expr_functions(&expr, &mut functions, &mut referenced)?;
+                            }
+                        }
+                    }
+                    referenced.extend(functions.iter().copied());
+                    if let ElementKind::Active { table_index, .. } = element.kind {
+                        let table = &mut tables[table_index.unwrap_or(0) as usize];
+                        table.functions.extend(functions.iter().copied());
+                        table.unknown |= unknown;
+                    }
+                    segments.push((functions.into_iter().collect(), unknown));
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut reader = body.get_operators_reader()?;
+                while !reader.eof() {
+                    match reader.read()? {
+                        Operator::RefFunc { function_index } => {
+                            referenced.insert(function_index);
+                        }
+                        Operator::TableInit { elem_index, table } => {
+                            inits.push((elem_index, table));
+                        }
+                        Operator::TableSet { table }
+                        | Operator::TableFill { table }
+                        | Operator::TableGrow { table } => writes.push(table),
+                        Operator::TableCopy {
+                            dst_table,
+                            src_table,
+                        } => copies.push((dst_table, src_table)),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (segment, table) in inits {
+        let (functions, unknown) = &segments[segment as usize];
+        let table = &mut tables[table as usize];
+        table.functions.extend(functions.iter().copied());
+        table.unknown |= unknown;
+    }
+    for table in writes {
+        let table = &mut tables[table as usize];
+        table.functions.extend(referenced.iter().copied());
+        table.unknown = true;
+    }
+
+    // Propagate the contents of tables through copies until nothing changes
+    // anymore, as copies may be chained.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (dst, src) in copies.iter() {
+            let src = tables[*src as usize].clone();
+            let dst = &mut tables[*dst as usize];
+            let len = dst.functions.len();
+            dst.functions.extend(src.functions);
+            changed |= dst.functions.len() != len || (src.unknown && !dst.unknown);
+            dst.unknown |= src.unknown;
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Adds the functions referenced by `ref.func` in `expr` to both `functions`
+/// and `referenced`, returning whether `expr` may produce another reference
+/// through a `global.get`.
+fn const_expr_functions(
+    expr: &wasmparser::ConstExpr<'_>,
+    functions: &mut BTreeSet<u32>,
+    referenced: &mut BTreeSet<u32>,
+) -> Result<bool> {
+    let mut unknown = false;
+    let mut reader = expr.get_operators_reader();
+    while !reader.eof() {
+        match reader.read()? {
+            Operator::RefFunc { function_index } => {
+                functions.insert(function_index);
+                referenced.insert(function_index);
+            }
+            Operator::GlobalGet { .. } => unknown = true,
+            _ => {}
+        }
+    }
+    Ok(unknown)
+}
@@ -0,0 +1,489 @@
+//! A common interface to transforms of WebAssembly binaries.
+//!
+//! Each transform, such as stripping custom sections, adding metadata, or the
+//! instrumentation and lowering passes, takes the bytes of a module or
+//! component and produces new bytes along with any diagnostics describing what
+//! was done. A [`Pipeline`] chains multiple transforms together, and runs of
+//! transforms which only operate on whole custom sections are fused into a
+//! single parse of their input instead of each parsing and reencoding it.
+//!
+//! ```
+//! # #[cfg(all(feature = "strip", feature = "instrument"))]
+//! # fn main() -> anyhow::Result<()> {
+//! use wasm_tools::nan_canonicalization::CanonicalizeNans;
+//! use wasm_tools::transform::{Pipeline, Strip, Transform};
+//!
+//! let wasm = wat::parse_str(
+//!     r#"
+//!         (module
+//!             (func (param f32) (result f32) local.get 0 f32.sqrt)
+//!             (@custom "debug" "...")
+//!         )
+//!     "#,
+//! )?;
+//!
+//! let mut pipeline = Pipeline::new();
+//! pipeline.add(Strip::new()).add(CanonicalizeNans);
+//! let transformed = pipeline.apply(&wasm)?;
+//! assert_eq!(
+//!     transformed.diagnostics,
+//!     ["strip: removed custom section `debug`"]
+//! );
+//! # Ok(())
+//! # }
+//! # #[cfg(not(all(feature = "strip", feature = "instrument")))]
+//! # fn main() {}
+//! ```
+
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::mem;
+use wasm_encoder::{ComponentSectionId, CustomSection, Encode, RawSection, Section};
+use wasmparser::{CustomSectionReader, Encoding, Parser, Payload};
+
+/// A transform of a WebAssembly module or component.
+pub trait Transform {
+    /// Returns a short name for this transform, used to attribute errors and
+    /// diagnostics when it's part of a [`Pipeline`].
+    fn name(&self) -> &str;
+
+    /// Applies this transform to the module or component `wasm`.
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed>;
+
+    /// Returns this transform as a [`SectionTransform`] if it only operates on
+    /// custom sections, which allows a [`Pipeline`] to fuse it with adjacent
+    /// section transforms.
+    fn as_section_transform(&self) -> Option<&dyn SectionTransform> {
+        None
+    }
+}
+
+/// The result of applying a [`Transform`].
+#[derive(Debug, Clone, Default)]
+pub struct Transformed {
+    /// The transformed module or component.
+    pub wasm: Vec<u8>,
+    /// Human-readable notes about what the transform did, such as sections
+    /// which were removed or behavior which may differ from the input.
+    pub diagnostics: Vec<String>,
+}
+
+/// A transform which rewrites or removes custom sections and leaves all other
+/// sections untouched.
+pub trait SectionTransform {
+    /// Returns the new contents of the custom section `name` with contents
+    /// `data`, or `None` to remove the section.
+    ///
+    /// The `depth` is 0 for sections of the outermost module or component and
+    /// increases for each module or component it's nested in.
+    fn custom_section<'a>(
+        &self,
+        depth: usize,
+        name: &str,
+        data: Cow<'a, [u8]>,
+        diagnostics: &mut Vec<String>,
+    ) -> Result<Option<Cow<'a, [u8]>>>;
+}
+
+/// Applies all of `transforms`, in order, in a single pass over `wasm`.
+///
+/// Diagnostics are prefixed with the name of the transform producing them.
+pub fn apply_section_transforms(
+    transforms: &[(&str, &dyn SectionTransform)],
+    wasm: &[u8],
+) -> Result<Transformed> {
+    let mut ret = Transformed::default();
+    ret.wasm = rewrite_custom_sections(wasm, |event, output| {
+        let (depth, c) = match event {
+            CustomSectionEvent::Section { depth, section } => (depth, section),
+            CustomSectionEvent::End { .. } => return Ok(()),
+        };
+        let mut data = Some(Cow::Borrowed(c.data()));
+        for (name, transform) in transforms {
+            let mut diagnostics = Vec::new();
+            data = transform
+                .custom_section(depth, c.name(), data.unwrap(), &mut diagnostics)
+                .with_context(|| format!("{name} failed"))?;
+            ret.diagnostics
+                .extend(diagnostics.into_iter().map(|d| format!("{name}: {d}")));
+            if data.is_none() {
+                break;
+            }
+        }
+        if let Some(data) = data {
+            CustomSection {
+                name: c.name().into(),
+                data,
+            }
+            .append_to(output);
+        }
+        Ok(())
+    })?;
+    Ok(ret)
+}
+
+/// An event of [`rewrite_custom_sections`].
+enum CustomSectionEvent<'a> {
+    /// A custom section at `depth` which is to be appended to the output,
+    /// if at all, by the callback.
+    Section {
+        depth: usize,
+        section: CustomSectionReader<'a>,
+    },
+    /// The end of the module or component at `depth`, where the callback may
+    /// append more custom sections to it.
+    End { depth: usize },
+}
+
+/// Copies `wasm`, including nested modules and components, except that the
+/// custom sections are passed to `f` to be written to the output instead.
+fn rewrite_custom_sections<'a>(
+    wasm: &'a [u8],
+    mut f: impl FnMut(CustomSectionEvent<'a>, &mut Vec<u8>) -> Result<()>,
+) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut stack = Vec::new();
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        match &payload {
+            Payload::Version { encoding, .. } => {
+                output.extend_from_slice(match encoding {
+                    Encoding::Component => &wasm_encoder::Component::HEADER,
+                    Encoding::Module => &wasm_encoder::Module::HEADER,
+                });
+            }
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => {
+                stack.push(mem::take(&mut output));
+                continue;
+            }
+            Payload::End { .. } => {
+                f(CustomSectionEvent::End { depth: stack.len() }, &mut output)?;
+                let mut parent = match stack.pop() {
+                    Some(c) => c,
+                    None => break,
+                };
+                if output.starts_with(&wasm_encoder::Component::HEADER) {
+                    parent.push(ComponentSectionId::Component as u8);
+                } else {
+                    parent.push(ComponentSectionId::CoreModule as u8);
+                }
+                output.encode(&mut parent);
+                output = parent;
+            }
+            Payload::CustomSection(c) => {
+                f(
+                    CustomSectionEvent::Section {
+                        depth: stack.len(),
+                        section: c.clone(),
+                    },
+                    &mut output,
+                )?;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some((id, range)) = payload.as_section() {
+            RawSection {
+                id,
+                data: &wasm[range],
+            }
+            .append_to(&mut output);
+        }
+    }
+
+    Ok(output)
+}
+
+/// A sequence of transforms applied one after another.
+#[derive(Default)]
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    /// Creates a new, empty, pipeline.
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Appends `transform` to the end of this pipeline.
+    pub fn add(&mut self, transform: impl Transform + 'static) -> &mut Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+}
+
+impl Transform for Pipeline {
+    fn name(&self) -> &str {
+        "pipeline"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        let mut ret = Transformed {
+            wasm: wasm.to_vec(),
+            diagnostics: Vec::new(),
+        };
+        let mut transforms = self.transforms.iter().peekable();
+        while let Some(transform) = transforms.next() {
+            let transformed = match transform.as_section_transform() {
+                Some(section_transform) => {
+                    let mut fused = vec![(transform.name(), section_transform)];
+                    while let Some(next) = transforms.peek() {
+                        match next.as_section_transform() {
+                            Some(t) => fused.push((next.name(), t)),
+                            None => break,
+                        }
+                        transforms.next();
+                    }
+                    apply_section_transforms(&fused, &ret.wasm)?
+                }
+                None => {
+                    let mut transformed = transform
+                        .apply(&ret.wasm)
+                        .with_context(|| format!("{} failed", transform.name()))?;
+                    for d in transformed.diagnostics.iter_mut() {
+                        *d = format!("{}: {d}", transform.name());
+                    }
+                    transformed
+                }
+            };
+            ret.wasm = transformed.wasm;
+            ret.diagnostics.extend(transformed.diagnostics);
+        }
+        Ok(ret)
+    }
+}
+
+/// Removes custom sections from a module or component.
+///
+/// By default all custom sections are removed except for the `name` section,
+/// `component-type` sections, and the `dylink.0` section. If the outermost
+/// `producers` section is kept, and parses, then `wasm-tools` is added to its
+/// `processed-by` field.
+///
+/// With [`Strip::debug_keep_lines`] the DWARF sections of each module are
+/// instead reduced to what's needed to map code offsets to source lines.
+#[cfg(feature = "strip")]
+#[derive(Debug, Clone)]
+pub struct Strip {
+    all: bool,
+    delete: regex::RegexSet,
+    debug_keep_lines: bool,
+}
+
+#[cfg(feature = "strip")]
+impl Strip {
+    /// Creates a new configuration stripping custom sections by default.
+    pub fn new() -> Strip {
+        Strip {
+            all: false,
+            delete: regex::RegexSet::empty(),
+            debug_keep_lines: false,
+        }
+    }
+
+    /// Configures whether all custom sections are removed, regardless of
+    /// their name.
+    pub fn all(&mut self, all: bool) -> &mut Self {
+        self.all = all;
+        self
+    }
+
+    /// Configures that only custom sections whose name matches any of
+    /// `delete` are removed.
+    pub fn delete(&mut self, delete: regex::RegexSet) -> &mut Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Configures whether the line tables of DWARF debugging information are
+    /// kept when stripping.
+    ///
+    /// If enabled then the DWARF custom sections (`.debug_*`) of each module
+    /// are replaced with a DWARF compilation unit for each original one that
+    /// has no entries other than its root, along with its line table. This
+    /// keeps the `.debug_line` section, so that code offsets can still be
+    /// mapped to source locations for stack traces, while removing the
+    /// descriptions of functions, types, and variables which make up most
+    /// of the size of DWARF. The DWARF sections are replaced regardless of
+    /// [`Strip::all`] and [`Strip::delete`].
+    pub fn debug_keep_lines(&mut self, keep: bool) -> &mut Self {
+        self.debug_keep_lines = keep;
+        self
+    }
+
+    fn strips(&self, name: &str) -> bool {
+        // If explicitly specified, strip everything.
+        if self.all {
+            return true;
+        }
+
+        // If any section was called out by name only delete those sections.
+        if !self.delete.is_empty() {
+            return self.delete.is_match(name);
+        }
+
+        // Finally default strip everything but:
+        // * the `name` section
+        // * any `component-type` sections
+        // * the `dylink.0` section
+        name != "name" && !name.starts_with("component-type:") && name != "dylink.0"
+    }
+}
+
+#[cfg(feature = "strip")]
+impl Default for Strip {
+    fn default() -> Strip {
+        Strip::new()
+    }
+}
+
+#[cfg(feature = "strip")]
+impl Transform for Strip {
+    fn name(&self) -> &str {
+        "strip"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        if !self.debug_keep_lines {
+            return apply_section_transforms(&[(self.name(), self)], wasm);
+        }
+
+        // DWARF sections are collected for each module, and replaced at its
+        // end, since they can only be rewritten together.
+        let mut ret = Transformed::default();
+        let mut dwarf: Vec<Vec<(&str, &[u8])>> = Vec::new();
+        ret.wasm = rewrite_custom_sections(wasm, |event, output| {
+            match event {
+                CustomSectionEvent::Section { depth, section } => {
+                    let (name, data) = (section.name(), section.data());
+                    if name.starts_with(".debug_") {
+                        dwarf.resize_with(dwarf.len().max(depth + 1), Vec::new);
+                        dwarf[depth].push((name, data));
+                        return Ok(());
+                    }
+                    let data =
+                        self.custom_section(depth, name, data.into(), &mut ret.diagnostics)?;
+                    if let Some(data) = data {
+                        CustomSection {
+                            name: name.into(),
+                            data,
+                        }
+                        .append_to(output);
+                    }
+                }
+                CustomSectionEvent::End { depth } => {
+                    let sections = match dwarf.get_mut(depth) {
+                        Some(sections) if !sections.is_empty() => mem::take(sections),
+                        _ => return Ok(()),
+                    };
+                    for (name, data) in dwarf_line_tables(&sections)? {
+                        CustomSection {
+                            name: name.into(),
+                            data: data.into(),
+                        }
+                        .append_to(output);
+                    }
+                    ret.diagnostics
+                        .push("reduced DWARF sections to line tables".to_string());
+                }
+            }
+            Ok(())
+        })?;
+        Ok(ret)
+    }
+
+    fn as_section_transform(&self) -> Option<&dyn SectionTransform> {
+        if self.debug_keep_lines {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+/// Rewrites the DWARF `sections` of a module, keyed by name, into new DWARF
+/// sections which only contain the root entry of each compilation unit and
+/// its line table.
+#[cfg(feature = "strip")]
+fn dwarf_line_tables(sections: &[(&str, &[u8])]) -> Result<Vec<(&'static str, Vec<u8>)>> {
+    use gimli::write::{Address, EndianVec, Sections};
+    use gimli::{EndianSlice, LittleEndian};
+
+    let dwarf = gimli::read::Dwarf::load(|id| -> Result<_> {
+        let data = sections
+            .iter()
+            .find(|(name, _)| *name == id.name())
+            .map(|(_, data)| *data)
+            .unwrap_or(&[]);
+        Ok(EndianSlice::new(data, LittleEndian))
+    })?;
+    let mut dwarf = gimli::write::Dwarf::from(&dwarf, &|addr| Some(Address::Constant(addr)))
+        .context("failed to read DWARF")?;
+
+    for i in 0..dwarf.units.count() {
+        let unit = dwarf.units.get_mut(dwarf.units.id(i));
+        let root = unit.root();
+        let children = unit.get(root).children().copied().collect::<Vec<_>>();
+        for child in children {
+            unit.get_mut(root).delete_child(child);
+        }
+    }
+
+    let mut sections = Sections::new(EndianVec::new(LittleEndian));
+    dwarf
+        .write(&mut sections)
+        .context("failed to write DWARF")?;
+    let mut ret = Vec::new();
+    sections.for_each(|id, data| -> Result<()> {
+        if !data.slice().is_empty() {
+            ret.push((id.name(), data.slice().to_vec()));
+        }
+        Ok(())
+    })?;
+    Ok(ret)
+}
+
+#[cfg(feature = "strip")]
+impl SectionTransform for Strip {
+    fn custom_section<'a>(
+        &self,
+        depth: usize,
+        name: &str,
+        data: Cow<'a, [u8]>,
+        diagnostics: &mut Vec<String>,
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        if self.strips(name) {
+            diagnostics.push(format!("removed custom section `{name}`"));
+            Ok(None)
+        } else if depth == 0 && name == "producers" {
+            // Malformed producers sections are passed through as-is since
+            // stripping doesn't otherwise look at their contents.
+            match wasm_metadata::Producers::from_bytes(&data, 0) {
+                Ok(mut producers) => {
+                    producers.add("processed-by", "wasm-tools", env!("CARGO_PKG_VERSION"));
+                    Ok(Some(producers.to_bytes().into()))
+                }
+                Err(_) => Ok(Some(data)),
+            }
+        } else {
+            Ok(Some(data))
+        }
+    }
+}
+
+#[cfg(feature = "metadata")]
+impl Transform for wasm_metadata::AddMetadata {
+    fn name(&self) -> &str {
+        "metadata"
+    }
+
+    fn apply(&self, wasm: &[u8]) -> Result<Transformed> {
+        Ok(Transformed {
+            wasm: self.to_wasm(wasm)?,
+            diagnostics: Vec::new(),
+        })
+    }
+}
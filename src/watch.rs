@@ -0,0 +1,130 @@
+//! Support for the `--watch` flag of subcommands which rebuild their output
+//! whenever their inputs change.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watched paths are checked for modifications.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runs `build`, and then runs it again whenever a file at one of `paths`, or
+/// within one of the directories at `paths`, is created, modified, or
+/// removed.
+///
+/// The file at `output`, which `build` writes, is never watched, so that
+/// writing it into one of the watched directories doesn't trigger another
+/// build.
+///
+/// The time taken by each build is reported on stderr. Errors of `build` are
+/// reported on stderr as well instead of being returned, so that editing the
+/// inputs can fix them. This function only returns if watching the `paths`
+/// fails.
+pub fn watch(
+    paths: &[PathBuf],
+    output: Option<&Path>,
+    mut build: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    let output = output.map(normalize);
+    let mut last = snapshot(paths, output.as_deref())?;
+    loop {
+        let start = Instant::now();
+        match build() {
+            Ok(()) => eprintln!("built in {:.2?}", start.elapsed()),
+            Err(e) => eprintln!("error: {e:#}"),
+        }
+        eprintln!("watching for changes...");
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = snapshot(paths, output.as_deref())?;
+            if current != last {
+                last = current;
+                break;
+            }
+        }
+    }
+}
+
+/// The modification time of each file found at `paths`, keyed by path.
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// Takes a snapshot of the files at `paths`, leaving out the file at the
+/// normalized path `output`.
+fn snapshot(paths: &[PathBuf], output: Option<&Path>) -> Result<Snapshot> {
+    let mut files = Snapshot::new();
+    for path in paths {
+        add(&mut files, path, output)
+            .with_context(|| format!("failed to watch `{}`", path.display()))?;
+    }
+    Ok(files)
+}
+
+fn add(files: &mut Snapshot, path: &Path, output: Option<&Path>) -> Result<()> {
+    // Only paths with the same file name as the output need to be normalized
+    // to tell whether they're the output.
+    if let Some(output) = output {
+        if path.file_name() == output.file_name() && normalize(path) == output {
+            return Ok(());
+        }
+    }
+
+    // A missing file is recorded as such so that its creation is noticed.
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            files.insert(path.to_path_buf(), None);
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            add(files, &entry?.path(), output)?;
+        }
+    } else {
+        files.insert(path.to_path_buf(), metadata.modified().ok());
+    }
+    Ok(())
+}
+
+/// Returns `path` with its parent directory canonicalized, so that different
+/// spellings of the path of a file compare equal even if the file doesn't
+/// exist yet.
+fn normalize(path: &Path) -> PathBuf {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(parent), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_inside_watched_directory() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("input.wasm"), "a")?;
+        let paths = [dir.path().to_path_buf()];
+
+        // The output is spelled differently from the watched directory, as
+        // with `-p deps -o ./deps/out.wasm`.
+        let output = normalize(&dir.path().join(".").join("out.wasm"));
+        let before = snapshot(&paths, Some(&output))?;
+        fs::write(dir.path().join("out.wasm"), "b")?;
+        assert_eq!(snapshot(&paths, Some(&output))?, before);
+
+        // Other files in the directory are still watched.
+        fs::write(dir.path().join("other.wasm"), "c")?;
+        assert_ne!(snapshot(&paths, Some(&output))?, before);
+        Ok(())
+    }
+}
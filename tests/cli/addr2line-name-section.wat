@@ -0,0 +1,15 @@
+;; RUN: addr2line % 0x23 0x2b 0x40
+
+(module
+  (import "env" "f" (func))
+
+  (func $_ZN4core9panicking5panic17h0123456789abcdefE
+    i32.const 0
+    drop
+  )
+
+  (func
+    i32.const 0
+    drop
+  )
+)
@@ -0,0 +1,49 @@
+;; RUN: analyze %
+
+(module
+  (import "host" "log" (func $log (param i32)))
+  (memory $m 1)
+  (memory $n 1)
+  (global $counter (mut i32) (i32.const 0))
+  (global $limit i32 (i32.const 10))
+  (table 1 funcref)
+  (elem (i32.const 0) func $pure)
+
+  (func $pure (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add
+  )
+
+  (func $increment
+    global.get $counter
+    call $pure
+    global.set $counter
+  )
+
+  (func $load (param i32) (result i32)
+    local.get 0
+    i32.load $m
+    global.get $limit
+    i32.div_u
+  )
+
+  (func $copy
+    i32.const 0
+    i32.const 0
+    i32.const 1
+    memory.copy $n $m
+    call $increment
+  )
+
+  (func $indirect (param i32) (result i32)
+    local.get 0
+    i32.const 0
+    call_indirect (param i32) (result i32)
+  )
+
+  (func $log-counter
+    global.get $counter
+    call $log
+  )
+)
@@ -0,0 +1,8 @@
+;; FAIL: branch-hints verify %
+
+(module
+  (func (param i32) (result i32)
+    local.get 0
+    (@metadata.code.branch_hint "\01")
+    i32.eqz)
+)
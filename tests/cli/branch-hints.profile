@@ -0,0 +1,3 @@
+# function offset taken not-taken
+0 0x3 10 990
+0 0xf 400 100
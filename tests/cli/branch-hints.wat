@@ -0,0 +1,21 @@
+;; RUN: branch-hints inject % --profile tests/cli/branch-hints.profile -t
+;; RUN[verify]: branch-hints inject % --profile tests/cli/branch-hints.profile | \
+;;   branch-hints verify
+;; RUN[min-count]: branch-hints inject % --profile tests/cli/branch-hints.profile \
+;;   --min-count 600 -t
+
+(module
+  (func (export "f") (param i32) (result i32)
+    local.get 0
+    if (result i32)
+      i32.const 1
+    else
+      block
+        local.get 0
+        i32.const 10
+        i32.gt_u
+        br_if 0
+      end
+      i32.const 2
+    end)
+)
@@ -0,0 +1,23 @@
+;; RUN: canonicalize % | print
+
+(module
+  (@custom "z" "last")
+  (type $a (func (param i32)))
+  (type $b (func (param i32)))
+  (type $list (struct (field (ref null $list))))
+  (type $list2 (struct (field (ref null $list2))))
+  (rec
+    (type $x (struct (field (ref null $y))))
+    (type $y (struct (field (ref null $x)))))
+  (rec
+    (type $x2 (struct (field (ref null $y2))))
+    (type $y2 (struct (field (ref null $x2)))))
+  (type $uses (struct (field (ref null $y2))))
+  (@custom "a" (after func) "first")
+  (import "m" "f" (func $f (type $b)))
+  (func $g (type $a) (param $p i32)
+    (local $l1 i32) (local $l2 i32) (local i64)
+    (call $f (local.get 0))
+  )
+  (func (param (ref null $list2) (ref null $x2)))
+)
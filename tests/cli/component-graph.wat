@@ -0,0 +1,26 @@
+;; RUN[dot]: component graph %
+;; RUN[json]: component graph --json %
+
+(component
+  (import "host" (instance $host
+    (export "log" (func (param "msg" string)))
+  ))
+
+  (component $logger
+    (import "host" (instance
+      (export "log" (func (param "msg" string)))
+    ))
+    (alias export 0 "log" (func $log))
+    (export "log" (func $log))
+  )
+
+  (core module $m
+    (func (export "f"))
+  )
+
+  (core instance (instantiate $m))
+  (instance $a (instantiate $logger (with "host" (instance $host))))
+  (instance $b (instantiate $logger (with "host" (instance $a))))
+  (export "first" (instance $a))
+  (export "second" (instance $b))
+)
@@ -0,0 +1,14 @@
+;; RUN: print --relocations %
+;;
+;; The library object file linked by `component-link-static.wat`. Only `answer`
+;; is needed, so `unused` isn't linked.
+
+(module
+  (func $answer (result i32)
+    i32.const 42)
+  (func $unused (result i32)
+    i32.const 0)
+
+  ;; Symbols `answer` and `unused`.
+  (@custom "linking" (after code) "\02\08\15\02\00\00\00\06answer\00\00\01\06unused")
+)
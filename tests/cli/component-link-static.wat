@@ -0,0 +1,13 @@
+;; RUN: component link --static % tests/cli/component-link-static-library.wat -t
+
+(module
+  (import "env" "answer" (func $answer (result i32)))
+  (func $_initialize
+    call $answer
+    drop)
+
+  ;; Symbols `answer` (undefined) and `_initialize`.
+  (@custom "linking" (after code) "\02\08\13\02\00\10\00\00\00\01\0b_initialize")
+  ;; Relocation of the call to `answer`.
+  (@custom "reloc.CODE" (after code) "\03\01\00\04\00")
+)
@@ -0,0 +1,10 @@
+;; RUN: validate %
+;;
+;; This is the adapter used by `component-new-adapt-wit.wat`, note that it has
+;; no WIT metadata embedded within it.
+
+(module
+  (import "a:shim/host" "value" (func $value (result i32)))
+  (func (export "get") (result i32)
+    call $value)
+)
@@ -0,0 +1,11 @@
+;; RUN: component new % -t \
+;;   --adapt shim=tests/cli/component-new-adapt-wit-shim.wat \
+;;   --adapt-wit shim=tests/cli/component-new-adapt-wit-shim.wit
+;; FAIL[no-wit]: component new % \
+;;   --adapt shim=tests/cli/component-new-adapt-wit-shim.wat
+;; FAIL[unknown]: component new % \
+;;   --adapt-wit shim=tests/cli/component-new-adapt-wit-shim.wit
+
+(module
+  (import "shim" "get" (func (result i32)))
+)
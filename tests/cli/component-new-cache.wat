@@ -0,0 +1,8 @@
+;; RUN: component embed tests/cli/component-new-cache.wit % \
+;;   | component new -t --cache-dir %tmpdir/cache
+;; RUN[cached]: component embed tests/cli/component-new-cache.wit % \
+;;   | component new -t --cache-dir %tmpdir/cache
+
+(module
+  (func (export "f") (result i32) i32.const 42)
+)
@@ -0,0 +1,16 @@
+;; RUN: component embed tests/cli/component-new-canonical-options.wit % \
+;;   | component new -t --string-encoding compact-utf16 --realloc alloc \
+;;     --post-return-prefix free_ --memory heap
+;; FAIL[missing-realloc]: component embed tests/cli/component-new-canonical-options.wit % \
+;;   | component new --realloc missing --memory heap
+;; FAIL[multiple-memories]: component embed tests/cli/component-new-canonical-options.wit % \
+;;   | component new --realloc alloc
+
+(module
+  (import "$root" "log" (func (param i32 i32)))
+  (memory (export "scratch") 0)
+  (memory (export "heap") 1)
+  (func (export "alloc") (param i32 i32 i32 i32) (result i32) unreachable)
+  (func (export "greet") (param i32 i32) (result i32) unreachable)
+  (func (export "free_greet") (param i32))
+)
@@ -0,0 +1,8 @@
+;; FAIL: exceptions --legacy %
+
+(module
+  (func (param exnref)
+    local.get 0
+    throw_ref
+  )
+)
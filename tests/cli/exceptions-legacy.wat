@@ -0,0 +1,30 @@
+;; RUN[downgrade]: exceptions --legacy % -t
+;; RUN[validate]: exceptions --legacy % | validate --features all
+
+(module
+  (tag $e (param i32))
+
+  (func (export "catch") (param i32) (result i32)
+    block $outer (result i32)
+      block $all
+        block $handler (result i32)
+          try_table (result i32) (catch $e $handler) (catch_all $all)
+            local.get 0
+            throw $e
+          end
+          br $outer
+        end
+        i32.const 1
+        i32.add
+        br $outer
+      end
+      i32.const -1
+    end
+  )
+
+  (func (export "empty")
+    try_table
+      nop
+    end
+  )
+)
@@ -0,0 +1,70 @@
+;; RUN[upgrade]: exceptions % -t
+;; RUN[validate]: exceptions % | validate
+
+(module
+  (tag $e (param i32))
+  (tag $f)
+
+  (func $throw (param i32)
+    local.get 0
+    throw $e
+  )
+
+  (func (export "catch") (param i32) (result i32)
+    try (result i32)
+      local.get 0
+      call $throw
+      i32.const 0
+    catch $e
+      i32.const 1
+      i32.add
+    catch $f
+      i32.const 2
+    catch_all
+      i32.const -1
+    end
+  )
+
+  (func (export "params") (param i32) (result i32)
+    local.get 0
+    try $l (param i32) (result i32)
+      call $throw
+      i32.const 0
+      br $l
+    catch_all
+      rethrow $l
+    end
+  )
+
+  (func (export "rethrow") (param i32)
+    try
+      local.get 0
+      call $throw
+    catch_all
+      local.get 0
+      br_if 0
+      rethrow 0
+    end
+  )
+
+  (func (export "delegate") (param i32) (result i32)
+    block $out (result i32)
+      try $t (result i32)
+        try
+          local.get 0
+          call $throw
+        delegate $t
+        i32.const 0
+      catch $e
+        br $out
+      end
+    end
+  )
+
+  (func (export "delegate-to-caller") (param i32)
+    try
+      local.get 0
+      call $throw
+    delegate 0
+  )
+)
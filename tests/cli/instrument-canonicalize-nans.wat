@@ -0,0 +1,20 @@
+;; RUN: instrument canonicalize-nans % -t
+
+(module
+  (func (export "f") (param f32 f64 v128) (result f32 f64 v128)
+    (local i32)
+    local.get 0
+    local.get 0
+    f32.add
+    f32.neg
+    local.get 1
+    f64.sqrt
+    local.get 2
+    local.get 2
+    f32x4.mul
+    f64x2.floor)
+  (func (export "g") (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add)
+)
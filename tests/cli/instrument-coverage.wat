@@ -0,0 +1,29 @@
+;; RUN[memory]: instrument coverage % -t
+;; RUN[import]: instrument coverage --import % -t
+
+(module
+  (import "env" "log" (func $log (param i32)))
+  (func $f (export "f") (param i32) (result i32)
+    local.get 0
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 2
+    end
+    call $g)
+  (func $g (param i32) (result i32)
+    (local i32)
+    loop $l
+      local.get 0
+      call $log
+      local.get 0
+      i32.const 1
+      i32.sub
+      local.tee 0
+      br_if $l
+    end
+    local.get 0)
+  (func (export "ptr") (result funcref)
+    ref.func $g)
+  (elem declare func $g)
+)
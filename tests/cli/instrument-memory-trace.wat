@@ -0,0 +1,20 @@
+;; RUN[all]: instrument memory-trace % -t
+;; RUN[range]: instrument memory-trace --range 0x100..0x200 % -t
+
+(module
+  (memory 1)
+  (memory $m64 i64 1)
+  (func (export "copy") (param i32 i32)
+    local.get 1
+    local.get 0
+    i32.load offset=4
+    i32.store8)
+  (func (export "wide") (param i64)
+    (local f64)
+    local.get 0
+    local.get 0
+    i64.load $m64
+    i64.store $m64 offset=8)
+  (func (export "none") (result i32)
+    i32.const 1)
+)
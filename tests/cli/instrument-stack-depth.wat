@@ -0,0 +1,36 @@
+;; RUN[trap]: instrument stack-depth --limit 100 % -t
+;; RUN[import]: instrument stack-depth --import % -t
+
+(module
+  (global $g (mut i32) (i32.const 0))
+  (func $fib (export "fib") (param i32) (result i32)
+    local.get 0
+    i32.const 2
+    i32.lt_u
+    if
+      local.get 0
+      return
+    end
+    local.get 0
+    i32.const 1
+    i32.sub
+    call $fib
+    local.get 0
+    i32.const 2
+    i32.sub
+    call $fib
+    i32.add)
+  (func $pair (param i32) (result i32 i32)
+    block
+      local.get 0
+      br_if 0
+      local.get 0
+      local.get 0
+      br 1
+    end
+    i32.const 0
+    i32.const 0)
+  (func $tail (param i32) (result i32)
+    local.get 0
+    return_call $fib)
+)
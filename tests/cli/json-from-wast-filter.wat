@@ -0,0 +1,14 @@
+;; RUN[name]: json-from-wast --wasm-dir %tmpdir --name ad? %
+;; RUN[lines]: json-from-wast --wasm-dir %tmpdir --lines 12-13 %
+;; RUN[directives]: json-from-wast --wasm-dir %tmpdir --directives 3 %
+
+(module
+  (func (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add)
+  (func (export "zero") (result i32) i32.const 0))
+
+(assert_return (invoke "add" (i32.const 1) (i32.const 2)) (i32.const 3))
+(assert_return (invoke "zero") (i32.const 0))
+(assert_return (invoke "add" (i32.const 2) (i32.const 2)) (i32.const 4))
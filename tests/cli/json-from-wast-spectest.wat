@@ -0,0 +1,8 @@
+;; RUN[json]: json-from-wast --spectest --wasm-dir %tmpdir %
+;; RUN[validate]: validate %tmpdir/spectest.wasm
+;; RUN[print]: print %tmpdir/spectest.wasm
+
+(module
+  (import "spectest" "print_i32" (func (param i32)))
+  (import "spectest" "global_i32" (global i32))
+)
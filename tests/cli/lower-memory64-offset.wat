@@ -0,0 +1,8 @@
+;; FAIL: lower --memory64 %
+
+(module
+  (memory i64 1)
+  (func (param i64) (result i32)
+    local.get 0
+    i32.load offset=0x100000000)
+)
@@ -0,0 +1,35 @@
+;; RUN: lower --memory64 % -t
+
+(module
+  (memory $m i64 1 100000)
+  (memory $m32 1)
+  (data (memory $m) (i64.const 16) "hello")
+  (func (export "copy") (param $dst i64) (param $src i64) (param $len i64)
+    local.get $dst
+    local.get $src
+    local.get $len
+    memory.copy $m $m)
+  (func (export "store") (param i64 f64)
+    local.get 0
+    local.get 1
+    f64.store offset=8)
+  (func (export "load") (param i64) (result i32)
+    local.get 0
+    i32.load $m
+    i32.const 0
+    i32.load $m32
+    i32.add)
+  (func (export "grow") (param i64) (result i64)
+    local.get 0
+    memory.grow $m
+    drop
+    memory.size $m)
+  (func (export "to32") (param i64 i32)
+    i32.const 0
+    local.get 0
+    local.get 1
+    memory.copy $m32 $m)
+  (func (param i64 i32)
+    unreachable
+    i32.store $m)
+)
@@ -0,0 +1,13 @@
+;; FAIL: lower --multi-value %
+
+(module
+  (func (result i32 i32)
+    i32.const 0
+    i32.const 1
+    i32.const 1
+    br_if 0
+    drop
+    drop
+    i32.const 2
+    i32.const 3)
+)
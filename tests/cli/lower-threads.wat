@@ -0,0 +1,39 @@
+;; RUN[threads]: lower --threads % -t
+;; RUN[all]: lower % -t
+
+(module
+  (memory $m 1 1 shared)
+  (memory $m64 i64 1 1 shared)
+  (func (export "counter") (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.atomic.rmw.add $m)
+  (func (export "xchg8") (param i32 i64) (result i64)
+    local.get 0
+    local.get 1
+    i64.atomic.rmw8.xchg_u $m)
+  (func (export "cmpxchg16") (param i32 i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    local.get 2
+    i32.atomic.rmw16.cmpxchg_u $m)
+  (func (export "load-store") (param i64)
+    atomic.fence
+    local.get 0
+    local.get 0
+    i64.atomic.load $m64
+    i64.atomic.store $m64)
+  (func (export "wait") (param i32 i64) (result i32)
+    local.get 0
+    local.get 1
+    i64.const -1
+    memory.atomic.wait64 $m)
+  (func (export "notify") (param i64) (result i32)
+    local.get 0
+    i32.const 1
+    memory.atomic.notify $m64)
+  (func (export "sub64") (param i64 i64) (result i64)
+    local.get 0
+    local.get 1
+    i64.atomic.rmw.sub $m64)
+)
@@ -0,0 +1,38 @@
+;; RUN[all]: lower % -t
+;; RUN[return-calls]: lower --return-calls % -t
+
+(module
+  (type $pair (func (param i32) (result i32 i64)))
+  (table 1 funcref)
+  (func $swap (type $pair)
+    local.get 0
+    i32.eqz
+    if
+      i32.const 0
+      i64.const 0
+      return
+    end
+    local.get 0
+    i64.const 1)
+  (func $tail (type $pair)
+    local.get 0
+    return_call $swap)
+  (func $indirect (type $pair)
+    local.get 0
+    i32.const 0
+    return_call_indirect (type $pair))
+  (func (export "sum") (param i32) (result i64)
+    (local i64)
+    local.get 0
+    call $swap
+    local.set 1
+    drop
+    local.get 0
+    i32.const 0
+    call_indirect (type $pair)
+    local.get 1
+    i64.add
+    local.set 1
+    drop
+    local.get 1)
+)
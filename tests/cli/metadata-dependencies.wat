@@ -0,0 +1,2 @@
+;; RUN: metadata add % --dependency foo@1.0.0=sha256:abcd --dependency bar | metadata add --dependency foo@1.0.0=sha256:ef01 --dependency baz@0.1.0 | metadata show
+(module)
@@ -0,0 +1,9 @@
+;; RUN: metadata show --json %
+(component $outer
+  (core module $inner
+    (@producers (language "Rust" "1.0")))
+  (component $nested
+    (core module (@name "deep")
+      (@producers (processed-by "wasm-tools" "1.0")))
+  )
+)
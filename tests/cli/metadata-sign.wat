@@ -0,0 +1,13 @@
+;; RUN[sign]: metadata sign % -k tests/cli/signing/secret.pem -o %tmpdir/signed.wasm
+;; RUN[verify]: metadata verify %tmpdir/signed.wasm -K tests/cli/signing/public.pem
+;; FAIL[wrong-key]: metadata verify %tmpdir/signed.wasm -K tests/cli/signing/other-public.pem
+;; FAIL[modified]: metadata add %tmpdir/signed.wasm --name foo | metadata verify -K tests/cli/signing/public.pem
+;; FAIL[unsigned]: metadata verify % -K tests/cli/signing/public.pem
+;; RUN[detached]: metadata sign % -k tests/cli/signing/secret.pem --detached -o %tmpdir/module.sig
+;; RUN[verify-detached]: metadata verify % -K tests/cli/signing/public.pem -s %tmpdir/module.sig
+;; RUN[verify-detached-signed]: metadata verify %tmpdir/signed.wasm -K tests/cli/signing/public.pem -s %tmpdir/module.sig
+
+(module
+  (func (export "f") (result i32)
+    i32.const 42)
+)
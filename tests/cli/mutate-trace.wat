@@ -0,0 +1,13 @@
+;; RUN[trace]: mutate % --seed 1 --preserve-semantics --trace %tmpdir/trace.json -t
+;; RUN[replay]: mutate % --replay %tmpdir/trace.json -t
+
+(module
+  (func (export "f") (param i32) (result i32)
+    local.get 0
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 2
+    end
+  )
+)
@@ -0,0 +1,29 @@
+;; RUN: objdump --tables %
+
+(module
+  (import "env" "imported" (table $imported 1 funcref))
+  (table $active 2 funcref)
+  (table $passive 2 funcref)
+  (table $written 2 funcref)
+  (table $copied 2 funcref)
+  (table $exported 2 funcref)
+  (table $initialized 2 funcref (ref.func $f3))
+  (export "exported" (table $exported))
+
+  (func $f0)
+  (func $f1)
+  (func $f2)
+  (func $f3)
+  (func $f4)
+  (func $f5)
+
+  (elem (table $active) (i32.const 0) func $f0 $f1)
+  (elem $p func $f2)
+  (elem declare func $f4)
+
+  (func
+    (table.init $passive $p (i32.const 0) (i32.const 0) (i32.const 1))
+    (table.set $written (i32.const 0) (ref.func $f5))
+    (table.copy $copied $active (i32.const 0) (i32.const 0) (i32.const 1))
+    (table.copy $copied $passive (i32.const 0) (i32.const 0) (i32.const 1)))
+)
@@ -0,0 +1,28 @@
+;; RUN[default]: optimize --data-segments -t %
+;; RUN[threshold]: optimize --data-segments --zero-run-threshold 1 -t %
+
+(module
+  (memory 1)
+  (memory $overlapping 1)
+  (memory $non-const 1)
+  (global $g i32 (i32.const 0))
+
+  ;; leading and trailing zeros are removed, and the long run in the middle
+  ;; splits the segment in two
+  (data (i32.const 100)
+    "\00\00\00a\00\00b\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00c\00")
+  ;; this is merged with the segment before
+  (data (i32.const 130) "d")
+  ;; passive segments are kept in their place
+  (data $passive "passive")
+  ;; segments which are all zeros are removed
+  (data (i32.const 200) "\00\00\00\00")
+  (data (memory $overlapping) (i32.const 0) "\00\00x\00\00")
+  (data (memory $overlapping) (i32.const 2) "overlap")
+  (data (memory $non-const) (global.get $g) "\00\00y")
+
+  (func
+    i32.const 0 i32.const 0 i32.const 1 memory.init $passive
+    data.drop $passive
+    data.drop 0)
+)
@@ -0,0 +1,48 @@
+;; RUN[size]: optimize --size-only % | print
+;; FAIL[no-flags]: optimize %
+
+(module
+  (func $f (param $p i32) (result i32) (local $x i32)
+    ;; `local.set` and `local.get` become `local.tee`
+    local.get $p
+    local.set $x
+    local.get $x
+    ;; additions of zero are removed
+    i32.const 0
+    i32.add
+    ;; `local.tee` and `drop` become `local.set`
+    local.tee $x
+    drop
+    ;; loads and stores of the same local are removed
+    local.get $x
+    local.set $x
+    ;; dropped constants are removed
+    i64.const 1
+    drop
+    local.get $x)
+
+  (func $dead (param i32) (result i32)
+    block $a
+      local.get 0
+      br_if $a
+      i32.const 1
+      return
+      ;; everything up to the end of `$a` is removed, including blocks
+      block $removed
+        br $removed
+      end
+      i32.const 2
+      drop
+    end
+    block $b
+      unreachable
+      i32.const 3
+      drop
+    end
+    i32.const 4)
+
+  (func (param i64) (result i64)
+    local.get 0
+    i64.const 0
+    i64.shl)
+)
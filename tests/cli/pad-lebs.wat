@@ -0,0 +1,24 @@
+;; RUN[minimal]: parse % | dump
+;; RUN[func-const]: parse --pad-lebs func,const % | dump
+;; RUN[all]: parse --pad-lebs all % | dump
+;; RUN[valid]: parse --pad-lebs all % | validate
+
+(module
+  (type $t (func (param i32) (result i32)))
+  (import "" "f" (func $f (type $t)))
+  (table 1 funcref)
+  (memory 1)
+  (global $g (mut i32) (i32.const 0))
+  (func (type $t)
+    local.get 0
+    call $f
+    i32.const 1
+    call_indirect (type $t)
+    global.set $g
+    i32.const -1
+    i32.load offset=8
+    i64.const -2
+    drop
+    drop
+    global.get $g)
+)
@@ -0,0 +1,3 @@
+;; FAIL: parse --watch -
+
+(module)
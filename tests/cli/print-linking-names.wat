@@ -0,0 +1,26 @@
+;; RUN[print]: print %
+;; RUN[roundtrip]: print % | parse | print
+
+;; Only the first function is named by the `name` section, everything else is
+;; named by the `linking` section.
+(module
+  (import "env" "__memory_base" (global i32))
+  (global (mut i32) (i32.const 0))
+  (table 1 funcref)
+  (memory 1)
+  (func $named)
+  (func)
+  (func
+    data.drop 1
+    call 1
+    global.get 1
+    drop)
+  (data (global.get 0) "abc")
+  (data "passive")
+  (elem (global.get 0) func 1)
+
+  ;; Segments `.data.a` and `.rodata.str`, and symbols `foo` and `bar` for the
+  ;; first two functions, `__memory_base` with an explicit name for the
+  ;; imported global, and `__stack_pointer`.
+  (@custom "linking" "\02\05\19\02\07.data.a\00\00\0b.rodata.str\00\01\08\33\04\00\00\00\03foo\00\00\01\03bar\02\50\00\0d__memory_base\02\00\01\0f__stack_pointer")
+)
@@ -0,0 +1,12 @@
+;; RUN: print --max-func-size 8 %
+
+(module
+  (func $small (result i32)
+    i32.const 1)
+  (func $large (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add
+    i32.const 2
+    i32.mul)
+)
@@ -0,0 +1,24 @@
+;; RUN: print --name-labels %
+
+(module
+  (func (param i32)
+    (block
+      (loop
+        (block $named
+          (if (local.get 0)
+            (then
+              br 3
+              br 2
+              br 1
+              br 0))
+          local.get 0
+          br_table 0 1 2 3)
+        br 0))
+    (block $a
+      (block
+        (block $a
+          br 0
+          br 1
+          br 2
+          br 3))))
+)
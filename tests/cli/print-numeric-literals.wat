@@ -0,0 +1,19 @@
+;; RUN[default]: print --float-bits %
+;; RUN[decimal]: print --decimal-floats --hex-ints %
+;; RUN[all]: print --decimal-floats --float-bits --hex-ints %
+
+(module
+  (func
+    i32.const -1
+    i32.const 42
+    i64.const -2
+    i64.const 0x7fffffffffffffff
+    f32.const 1.5
+    f32.const -0.1
+    f32.const 1e-45
+    f32.const inf
+    f64.const 0.1
+    f64.const -nan
+    f64.const 1e300
+    drop drop drop drop drop drop drop drop drop drop drop)
+)
@@ -0,0 +1,19 @@
+;; RUN: print --relocations %
+
+(module
+  (import "env" "ext" (func $ext))
+  (global $g (mut i32) (i32.const 0))
+  (func $f
+    call $ext
+    call $f
+    global.get $g
+    drop
+    i32.const 0
+    drop)
+
+  ;; Symbols `ext` (undefined, named by the `name` section), `f`, `g`, and
+  ;; `buf`.
+  (@custom "linking" (after code) "\02\08\17\04\00\10\00\00\00\01\01\66\02\00\00\01\67\01\00\03\62\75\66\00\00\04")
+  ;; Relocations of both calls, the `global.get`, and the `i32.const`.
+  (@custom "reloc.CODE" (after code) "\04\04\00\04\00\00\06\01\07\08\02\04\0b\03\08")
+)
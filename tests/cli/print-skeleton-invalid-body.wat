@@ -0,0 +1,12 @@
+;; RUN: print --skeleton %
+
+;; The first body has an invalid opcode and the second a truncated local
+;; declaration, so both are summarized with their size alone.
+(module binary
+  "\00asm\01\00\00\00"
+  "\01\04\01\60\00\00"          ;; type section: (func)
+  "\03\03\02\00\00"             ;; function section: two functions of type 0
+  "\0a\09\02"                   ;; code section with two bodies
+  "\04\01\01\7f\ff"             ;; (local i32) followed by an invalid opcode
+  "\02\01\01"                 ;; a truncated local declaration
+)
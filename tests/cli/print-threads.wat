@@ -0,0 +1,21 @@
+;; RUN: print --threads 4 %
+
+(module
+  (global $g (mut i32) (i32.const 0))
+  (func $a (param $x i32) (result i32)
+    block $exit
+      local.get $x
+      br_if $exit
+      global.get $g
+      drop
+    end
+    local.get $x)
+  (func $b (local $y i64)
+    loop $l
+      call $c
+    end)
+  (func $c)
+  (func (export "d") (result i32)
+    i32.const 1
+    call $a)
+)
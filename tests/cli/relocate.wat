@@ -0,0 +1,20 @@
+;; RUN: relocate % --symbols tests/cli/relocate.symbols -t
+;; RUN[verify]: relocate % --symbols tests/cli/relocate.symbols | \
+;;   relocate --verify --symbols tests/cli/relocate.symbols
+;; FAIL[mismatch]: relocate --verify %
+
+(module
+  (import "env" "memory" (memory 1))
+  (import "env" "log" (func $log (param i32)))
+  (func $greet
+    i32.const 0
+    call $log)
+  (data (i32.const 16) "\00\00\00\00hello")
+
+  ;; Symbols `log` (undefined) and `msg`, at offset 4 of the data segment.
+  (@custom "linking" (after data) "\02\08\0d\02\00\10\00\01\00\03msg\00\04\05")
+  ;; Relocations of the address of `msg` and of the call to `log`.
+  (@custom "reloc.CODE" (after data) "\03\02\04\04\01\00\00\06\00")
+  ;; Relocation of a pointer to `msg` at the start of the data segment.
+  (@custom "reloc.DATA" (after data) "\04\01\05\06\01\00")
+)
@@ -0,0 +1,48 @@
+;; RUN: split % --split cold=report --split fmt=4 -d %tmpdir -t
+;; RUN[cold]: print %tmpdir/cold.wasm
+;; RUN[fmt]: print %tmpdir/fmt.wasm
+;; RUN[validate]: validate %tmpdir/cold.wasm
+;; RUN[placeholders]: split % --split cold=report --placeholders -d %tmpdir -t
+;; RUN[validate-placeholders]: split % --split cold=report --placeholders -d %tmpdir | validate
+;; FAIL[unknown]: split % --split cold=missing -d %tmpdir
+
+(module
+  (import "env" "log" (func $log (param i32 i32)))
+  (memory 1)
+  (global $errors (mut i32) (i32.const 0))
+
+  (func $main (export "main") (param i32) (result i32)
+    local.get 0
+    i32.eqz
+    if
+      i32.const 1
+      call $report
+    end
+    local.get 0
+    call $helper)
+
+  (func $helper (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add)
+
+  (func $report (param i32)
+    global.get $errors
+    local.get 0
+    i32.add
+    global.set $errors
+    i32.const 100
+    i32.const 0
+    i32.const 11
+    memory.init $message
+    i32.const 100
+    call $format
+    call $log)
+
+  (func $format (param i32) (result i32 i32)
+    local.get 0
+    local.get 0
+    call $helper)
+
+  (data $message "error: oops")
+)
@@ -0,0 +1,28 @@
+;; RUN[table]: stats %
+;; RUN[json]: stats % --json
+
+(module
+  (memory 1)
+  (func (export "add") (param i32 i32) (result i32)
+    (local i64 f32)
+    local.get 0
+    local.get 1
+    i32.add
+    i32.const 100000
+    i32.add)
+  (func (param i32)
+    local.get 0
+    i32.const 0
+    i32.atomic.store offset=8
+    v128.const i64x2 0 0
+    drop
+    local.get 0
+    i32.extend8_s
+    drop)
+  (func (export "f") (param i32) (result i32)
+    (local i32)
+    local.get 0
+    i32.const 3
+    return_call 0)
+  (@custom "hello" "world")
+)
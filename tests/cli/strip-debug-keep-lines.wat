@@ -0,0 +1,14 @@
+;; RUN[objdump]: strip --debug-keep-lines -g % | objdump
+;; RUN[addr2line]: strip --debug-keep-lines -g % | addr2line - 0x18 0x1a 0x1e 0x20
+
+(module
+  (func $"dwarf(name)"
+(;@18;)  i32.const 0
+(;@1a;)  drop
+  )
+
+  (func $another-function
+(;@1e;)  i32.const 0
+(;@20;)  drop
+  )
+)
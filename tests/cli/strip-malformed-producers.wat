@@ -0,0 +1,7 @@
+;; RUN: strip --delete foo % -t
+
+;; A producers section which doesn't parse is kept as-is.
+(module
+  (@custom "producers" "\01\0cprocessed-by")
+  (@custom "foo" "...")
+)
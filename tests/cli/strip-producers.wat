@@ -0,0 +1,8 @@
+;; RUN: strip --delete foo % -t
+
+(module
+  (@producers
+    (processed-by "clang" "18.1")
+  )
+  (@custom "foo" "...")
+)
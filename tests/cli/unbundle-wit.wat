@@ -0,0 +1,15 @@
+;; RUN[gen]: component unbundle --threshold 0 --wit % -t --module-dir %tmpdir
+;; RUN[wit]: component wit %tmpdir/component.wit
+;; RUN[module]: print %tmpdir/unbundled-module0.wasm
+
+(component
+  (type (func (param "x" u32)))
+  (import "f" (func $f (type 0)))
+  (core func $f (canon lower (func $f)))
+  (core module $m
+    (import "" "f" (func (param i32)))
+  )
+  (core instance (instantiate $m
+    (with "" (instance (export "f" (func $f))))
+  ))
+)
@@ -0,0 +1,7 @@
+;; FAIL: wast --log - %
+
+(assert_invalid
+  (module (func (result i32) i32.const 0))
+  "type mismatch")
+
+(module (func (result i32)))
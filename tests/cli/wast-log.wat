@@ -0,0 +1,18 @@
+;; RUN: wast --log - %
+
+(module
+  (func (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add))
+(register "m")
+
+(assert_return (invoke "add" (i32.const 1) (i32.const 2)) (i32.const 3))
+
+(assert_invalid
+  (module (func (result i32)))
+  "type mismatch")
+
+(assert_malformed
+  (module quote "(func (local $x i32) (local $x i32))")
+  "duplicate local")
@@ -34,6 +34,7 @@ use wasm_encoder::reencode::{Reencode, ReencodeComponent, RoundtripReencoder};
 use wasmparser::*;
 use wast::component::{Component, ComponentKind};
 use wast::core::{Module, ModuleKind};
+use wast::harness::Shard;
 use wast::lexer::Lexer;
 use wast::parser::ParseBuffer;
 use wast::{parser, QuoteWat, Wast, WastDirective, Wat};
@@ -41,9 +42,17 @@ use wast::{parser, QuoteWat, Wast, WastDirective, Wat};
 fn main() {
     env_logger::init();
 
-    let tests = find_tests();
+    let mut tests = find_tests();
     let bless = std::env::var_os("BLESS").is_some();
 
+    // Tests can be split between parallel CI jobs with `WAST_SHARD=I/N`.
+    let shard = std::env::var("WAST_SHARD")
+        .ok()
+        .map(|s| s.parse::<Shard>().unwrap());
+    if let Some(shard) = shard {
+        tests = shard.select(tests);
+    }
+
     let state = Arc::new(TestState::default());
     let mut trials = Vec::new();
     for test in tests {
@@ -68,7 +77,7 @@ fn main() {
     if cfg!(target_family = "wasm") && !cfg!(target_feature = "atomics") {
         args.test_threads = Some(1);
     }
-    if bless && !args.list {
+    if bless && !args.list && shard.is_none() {
         drop(std::fs::remove_dir_all("tests/snapshots"));
     }
     libtest_mimic::run(&args, trials).exit();